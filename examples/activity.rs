@@ -0,0 +1,52 @@
+//! An input activity indicator, like a tray icon blinking on input.
+//!
+//! Run with: cargo run --example activity
+//!
+//! Prints a line for each blink, at most ten a second, naming the kinds of
+//! input since the last one. Every ten seconds it prints how much time the
+//! hook thread spent per event: an atomic load for most events while input
+//! keeps coming, a few microseconds for the first one after a quiet spell,
+//! which wakes the callback thread. Press Ctrl+C to quit.
+
+use monio::{ActivityKind, on_activity};
+use std::io::Write;
+use std::time::Duration;
+
+fn main() {
+    println!("monio activity example");
+    println!("======================\n");
+
+    let handle = match on_activity(Duration::from_millis(100), |kinds| {
+        let names = [
+            (ActivityKind::KEY, "key"),
+            (ActivityKind::BUTTON, "button"),
+            (ActivityKind::MOTION, "motion"),
+            (ActivityKind::WHEEL, "wheel"),
+        ];
+        let seen: Vec<_> = names
+            .iter()
+            .filter(|(kind, _)| kinds.contains(*kind))
+            .map(|(_, name)| *name)
+            .collect();
+        println!("● {}", seen.join(" "));
+        let _ = std::io::stdout().flush();
+    }) {
+        Ok(handle) => handle,
+        Err(e) => {
+            eprintln!("Failed to watch activity: {e}");
+            return;
+        }
+    };
+
+    loop {
+        std::thread::sleep(Duration::from_secs(10));
+        let stats = handle.stats();
+        match stats.per_event() {
+            Some(per_event) => println!(
+                "-- {} events, {} blinks, {:?} per event on the hook thread",
+                stats.events, stats.callbacks, per_event
+            ),
+            None => println!("-- no activity yet"),
+        }
+    }
+}
@@ -1,12 +1,19 @@
 //! Basic example demonstrating event listening.
 //!
 //! Run with: cargo run --example basic
+//! Without input permissions: cargo run --example basic -- --demo
 //!
 //! Note: On macOS, you need to grant Accessibility permissions to the terminal.
 
+use monio::demo::{self, DemoScript};
 use monio::{Event, EventType, listen};
 
 fn main() {
+    // Play a scripted session instead of reading the real input
+    let _demo = std::env::args()
+        .any(|arg| arg == "--demo")
+        .then(|| demo::synthetic_session(DemoScript::sample()));
+
     println!("monio basic example");
     println!("Press Ctrl+C to exit\n");
 
@@ -0,0 +1,50 @@
+//! Writing a bug report bundle for an input or key-mapping issue.
+//!
+//! Run with: cargo run --example bug_report --features recorder,serde [dir]
+//!
+//! Reproduce the problem, then press Escape. The bundle is written to `dir`
+//! (`monio-bug-report` by default); zip it and attach it to the issue. Keys
+//! in the flight recording are redacted unless `MONIO_REPORT_KEYS` is set.
+
+use monio::debug::BugReport;
+use monio::recorder::FlightRecorder;
+use monio::{Event, EventType, Hook, Key, ShutdownToken};
+use std::time::Duration;
+
+fn main() {
+    let dir = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "monio-bug-report".into());
+
+    println!("monio bug report");
+    println!("================\n");
+    println!("Reproduce the problem, then press Escape.\n");
+
+    let hook = Hook::new();
+    let recorder = FlightRecorder::attach(&hook, Duration::from_secs(30));
+
+    let token = ShutdownToken::new();
+    let handler_token = token.clone();
+    let result = hook.run_with_token(
+        move |event: &Event| {
+            if event.event_type == EventType::KeyPressed
+                && event.keyboard.as_ref().map(|kb| kb.key) == Some(Key::Escape)
+            {
+                handler_token.cancel();
+            }
+        },
+        &token,
+    );
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+    }
+
+    let report = BugReport::new()
+        .hook(&hook)
+        .flight_recorder(&recorder)
+        .keyboard_identities(std::env::var_os("MONIO_REPORT_KEYS").is_some());
+    match report.write(&dir) {
+        Ok(()) => println!("Bug report written to {dir}/"),
+        Err(e) => eprintln!("Failed to write bug report: {}", e),
+    }
+}
@@ -0,0 +1,26 @@
+//! Keep the cursor on the primary display for ten seconds.
+//!
+//! Run with: cargo run --example confine
+
+use monio::{confine_cursor, primary_display};
+use std::thread;
+use std::time::Duration;
+
+fn main() -> monio::Result<()> {
+    let display = primary_display()?;
+    let bounds = display.bounds;
+    println!(
+        "Confining the cursor to the primary display: {:.0}x{:.0} @ ({:.0}, {:.0})",
+        bounds.width, bounds.height, bounds.x, bounds.y
+    );
+
+    let guard = confine_cursor(bounds)?;
+    for remaining in (1..=10).rev() {
+        println!("  released in {remaining}s");
+        thread::sleep(Duration::from_secs(1));
+    }
+    drop(guard);
+
+    println!("Cursor released.");
+    Ok(())
+}
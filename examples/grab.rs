@@ -12,13 +12,11 @@
 //! In this example, the Q, W, and E keys are blocked. Try typing them -
 //! they won't work in other applications while this example is running!
 //!
-//! Platform support:
-//! - macOS: Full support (CGEventTap)
-//! - Windows: Full support (low-level hooks)
-//! - Linux/X11: Falls back to listen mode (XRecord cannot grab)
-//! - Linux/Wayland: Requires evdev feature + input group permissions
+//! Backends that can't grab (X11, whose XRecord only listens) fall back to
+//! listen mode; see `monio::capabilities()`. The evdev backend needs input
+//! group permissions.
 
-use monio::{Event, EventType, Key, grab};
+use monio::{Event, EventType, Key, capabilities, grab};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::thread;
 use std::time::Duration;
@@ -58,7 +56,7 @@ fn check_linux_permissions() {
             .ok()
             .and_then(|o| String::from_utf8(o.stdout).ok());
 
-        let has_input_group = output.as_ref().map_or(false, |g| g.contains("input"));
+        let has_input_group = output.as_ref().is_some_and(|g| g.contains("input"));
 
         // Check current process groups (active now)
         let current_groups = Command::new("groups")
@@ -66,9 +64,7 @@ fn check_linux_permissions() {
             .ok()
             .and_then(|o| String::from_utf8(o.stdout).ok());
 
-        let currently_has_input = current_groups
-            .as_ref()
-            .map_or(false, |g| g.contains("input"));
+        let currently_has_input = current_groups.as_ref().is_some_and(|g| g.contains("input"));
 
         if has_input_group && !currently_has_input {
             eprintln!("⚠️  You are in the 'input' group, but the change hasn't taken effect yet.");
@@ -140,7 +136,16 @@ fn main() {
     println!("===================\n");
     println!("Display server: {}\n", get_display_server());
 
-    check_linux_permissions();
+    let caps = capabilities();
+    if caps.backend == "evdev" {
+        check_linux_permissions();
+    }
+    if !caps.can_grab {
+        println!(
+            "The {} backend can't grab: keys will be reported but not blocked.\n",
+            caps.backend
+        );
+    }
 
     // Safety timeout: auto-exit after 10 seconds
     thread::spawn(move || {
@@ -0,0 +1,33 @@
+//! A small hotkey script built from subscription helpers.
+//!
+//! Run with: cargo run --example hotkeys
+//!
+//! Note: On macOS, you need to grant Accessibility permissions to the terminal.
+
+use monio::format::{ChordStyle, format_chord};
+use monio::{Button, Hook, Key};
+use std::sync::mpsc;
+
+fn main() -> monio::Result<()> {
+    let hook = Hook::new();
+    let (quit, quit_requested) = mpsc::channel();
+
+    let _f1 = hook.on_key_pressed(Key::F1, || println!("F1: hello!"));
+    let _ctrl_keys = hook.on_any_key(|key, mods| {
+        if mods.ctrl {
+            println!("{}", format_chord(&mods, key, ChordStyle::native()));
+        }
+    });
+    let _click = hook.on_click(Button::Middle, |x, y| {
+        println!("Middle click at ({x:.0}, {y:.0})")
+    });
+    let _wheel = hook.on_wheel(|direction, delta| println!("Scrolled {direction:?} by {delta}"));
+    let _quit = hook.on_key_pressed(Key::Escape, move || {
+        let _ = quit.send(());
+    });
+
+    println!("F1, Ctrl+key, middle click and wheel are watched. Press Escape to exit.");
+    hook.run_async(|_: &monio::Event| {})?;
+    let _ = quit_requested.recv();
+    hook.stop()
+}
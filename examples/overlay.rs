@@ -0,0 +1,25 @@
+//! Input overlay for OBS - serves a browser source showing keys and mouse.
+//!
+//! Run with: cargo run --example overlay --features overlay
+//!
+//! Then add `http://127.0.0.1:9000/` as a browser source in OBS. Options go
+//! in the query string, e.g. `?keys=5&fade=1500&theme=transparent&mouse=0`.
+//! Press Ctrl+C to stop.
+
+use monio::overlay::overlay_server;
+use std::sync::mpsc;
+
+fn main() -> monio::Result<()> {
+    let server = overlay_server(9000)?;
+    println!("Overlay running at http://{}/", server.local_addr());
+    println!("Press Ctrl+C to stop.");
+
+    let (tx, rx) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = tx.send(());
+    })
+    .expect("Error setting Ctrl-C handler");
+    let _ = rx.recv();
+
+    server.stop()
+}
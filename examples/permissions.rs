@@ -0,0 +1,58 @@
+//! A permission onboarding flow for macOS apps.
+//!
+//! Run with: cargo run --example permissions
+//!
+//! Checks the Accessibility permission, opens System Settings if it's
+//! missing, and waits for the user to grant it before starting a hook.
+//! Elsewhere, there is nothing to grant and the hook starts right away.
+
+use monio::permissions::{self, AccessibilityStatus};
+use monio::{Event, EventType, Hook, Key, ShutdownToken};
+use std::time::Duration;
+
+fn main() {
+    match permissions::accessibility_status() {
+        AccessibilityStatus::Granted => {}
+        status => {
+            if status == AccessibilityStatus::StaleGrant {
+                println!("The Accessibility permission belongs to an older build of this app.");
+                println!("Remove it from the list with the - button, then add it again.");
+            } else {
+                println!("This app needs the Accessibility permission to see input.");
+                permissions::request_accessibility();
+            }
+            if let Err(e) = permissions::open_accessibility_settings() {
+                eprintln!("Couldn't open System Settings: {e}");
+            }
+            println!("Waiting for the permission...");
+            if let Err(e) = permissions::wait_for_accessibility(
+                Duration::from_secs(120),
+                Duration::from_millis(500),
+            ) {
+                eprintln!("{e}");
+                return;
+            }
+            println!("Thanks!");
+        }
+    }
+
+    println!("Listening; press Escape to quit.");
+    let token = ShutdownToken::new();
+    let handler_token = token.clone();
+    let result = Hook::new().run_with_token(
+        move |event: &Event| {
+            if event.event_type == EventType::KeyPressed
+                && event
+                    .keyboard
+                    .as_ref()
+                    .is_some_and(|kb| kb.key == Key::Escape)
+            {
+                handler_token.cancel();
+            }
+        },
+        &token,
+    );
+    if let Err(e) = result {
+        eprintln!("Hook failed: {e}");
+    }
+}
@@ -0,0 +1,64 @@
+//! Polling input once per frame, as a game loop would.
+//!
+//! Run with: cargo run --example poll_loop
+//!
+//! Runs a 60 FPS loop for ten seconds (or until Escape is pressed). Each
+//! frame drains the events queued since the previous one and reads the
+//! current input state.
+
+use monio::poll::PolledHook;
+use monio::{Button, EventType, Key};
+use std::time::{Duration, Instant};
+
+const FRAME: Duration = Duration::from_micros(16_667);
+
+fn main() -> monio::Result<()> {
+    println!("Polling input at 60 FPS for 10 seconds. Press Escape to quit early.");
+
+    let mut input = PolledHook::start()?;
+    let mut events = Vec::new();
+    let end = Instant::now() + Duration::from_secs(10);
+    let mut next_frame = Instant::now();
+
+    for frame in 0u64.. {
+        if Instant::now() >= end {
+            break;
+        }
+
+        events.clear();
+        input.poll_events_into(&mut events);
+        let state = input.poll_state();
+        if state.is_key_down(Key::Escape) {
+            break;
+        }
+
+        let presses = events
+            .iter()
+            .filter(|e| e.event_type == EventType::KeyPressed)
+            .count();
+        if !events.is_empty() {
+            println!(
+                "frame {:4}: {:3} events ({} key presses), mouse at ({:.0}, {:.0}){}, {} keys held",
+                frame,
+                events.len(),
+                presses,
+                state.mouse_position.0,
+                state.mouse_position.1,
+                if state.is_button_down(Button::Left) {
+                    ", left button down"
+                } else {
+                    ""
+                },
+                state.keys.len()
+            );
+        }
+
+        next_frame += FRAME;
+        if let Some(wait) = next_frame.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+    }
+
+    println!("Dropped events: {}", input.dropped_count());
+    input.stop()
+}
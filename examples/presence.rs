@@ -0,0 +1,35 @@
+//! A chat-style status that follows whether you're at the keyboard.
+//!
+//! Run with: cargo run --example presence
+//!
+//! The status turns to "Away" after 30 seconds without input, and back to
+//! "Available" when you return. Press Ctrl+C to quit.
+
+use monio::presence::{self, Presence};
+use std::time::Duration;
+
+fn main() {
+    println!("monio presence example");
+    println!("======================\n");
+    println!("Status: Available");
+
+    let (_watcher, rx) = match presence::watch_channel(Duration::from_secs(30)) {
+        Ok(watch) => watch,
+        Err(e) => {
+            eprintln!("Failed to watch presence: {e}");
+            return;
+        }
+    };
+
+    for presence in rx {
+        match presence {
+            Presence::Away { since } => {
+                let idle = since.elapsed().unwrap_or_default();
+                println!("Status: Away (idle for {}s)", idle.as_secs());
+            }
+            Presence::Returned { away_for } => {
+                println!("Status: Available (back after {}s)", away_for.as_secs());
+            }
+        }
+    }
+}
@@ -1,27 +1,30 @@
 //! Event recorder example - record and playback macros.
 //!
 //! Usage:
-//!   cargo run --example recorder --features recorder -- record macro.json
-//!   cargo run --example recorder --features recorder -- playback macro.json
-//!   cargo run --example recorder --features recorder -- playback-fast macro.json
+//!   cargo run --example recorder --features recorder,serde -- record macro.json [--demo]
+//!   cargo run --example recorder --features recorder,serde -- playback macro.json
+//!   cargo run --example recorder --features recorder,serde -- playback-fast macro.json
 
 use std::env;
 use std::time::Duration;
 
-#[cfg(feature = "recorder")]
+#[cfg(all(feature = "recorder", feature = "serde"))]
 use monio::{EventRecorder, Recording};
 
 fn main() -> monio::Result<()> {
-    #[cfg(not(feature = "recorder"))]
+    #[cfg(not(all(feature = "recorder", feature = "serde")))]
     {
-        eprintln!("This example requires the 'recorder' feature.");
-        eprintln!("Run with: cargo run --example recorder --features recorder -- ...");
+        eprintln!("This example requires the 'recorder' and 'serde' features.");
+        eprintln!("Run with: cargo run --example recorder --features recorder,serde -- ...");
         std::process::exit(1);
     }
 
-    #[cfg(feature = "recorder")]
+    #[cfg(all(feature = "recorder", feature = "serde"))]
     {
-        let args: Vec<String> = env::args().collect();
+        let mut args: Vec<String> = env::args().collect();
+        // Record a scripted session instead of the real input
+        let demo = args.iter().any(|arg| arg == "--demo");
+        args.retain(|arg| arg != "--demo");
 
         if args.len() < 3 {
             println!("Usage:");
@@ -42,6 +45,8 @@ fn main() -> monio::Result<()> {
 
         match command.as_str() {
             "record" => {
+                let _demo =
+                    demo.then(|| monio::demo::synthetic_session(monio::demo::DemoScript::sample()));
                 println!("Recording for 5 seconds...");
                 println!("Perform some keyboard and mouse actions!");
 
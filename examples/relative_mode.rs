@@ -0,0 +1,35 @@
+//! Relative pointer mode, as used by games and 3D viewports.
+//!
+//! Run with: cargo run --example relative_mode
+//!
+//! For five seconds the cursor is hidden and pinned to the centre of its
+//! display, and mouse movement is printed as deltas. Everything is restored
+//! afterwards.
+
+use monio::relative_pointer_channel;
+use std::time::{Duration, Instant};
+
+fn main() -> monio::Result<()> {
+    println!("Move the mouse; relative mode ends in 5 seconds.");
+
+    let (guard, rx) = relative_pointer_channel()?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let (mut yaw, mut pitch) = (0.0f64, 0.0f64);
+
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let Ok(delta) = rx.recv_timeout(remaining) else {
+            break;
+        };
+        // A typical camera mapping: 0.1 degrees per point
+        yaw += delta.dx * 0.1;
+        pitch = (pitch - delta.dy * 0.1).clamp(-89.0, 89.0);
+        println!(
+            "dx={:+5.0} dy={:+5.0}  yaw={:7.1}° pitch={:5.1}°",
+            delta.dx, delta.dy, yaw, pitch
+        );
+    }
+
+    drop(guard);
+    println!("Cursor restored.");
+    Ok(())
+}
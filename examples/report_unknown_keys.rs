@@ -0,0 +1,49 @@
+//! Collecting details about keys monio can't map, for bug reports.
+//!
+//! Run with: cargo run --example report_unknown_keys
+//!
+//! Press the keys that show up as `Unknown`, then Escape. The report printed
+//! at the end lists what the platform knows about each of them; paste it
+//! into the issue.
+
+use monio::debug::unknown_keys_seen;
+use monio::{Event, EventType, Hook, Key, ShutdownToken};
+
+fn main() {
+    println!("monio unknown key report");
+    println!("========================\n");
+    println!("Press the keys that show up as Unknown, then Escape.\n");
+
+    let token = ShutdownToken::new();
+    let handler_token = token.clone();
+    let result = Hook::new().run_with_token(
+        move |event: &Event| {
+            if event.event_type != EventType::KeyPressed {
+                return;
+            }
+            let Some(kb) = &event.keyboard else {
+                return;
+            };
+            match kb.key {
+                Key::Escape => handler_token.cancel(),
+                Key::Unknown(code) => println!("Unknown key: {code}"),
+                key => println!("Known key: {key:?}"),
+            }
+        },
+        &token,
+    );
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        return;
+    }
+
+    let reports = unknown_keys_seen();
+    println!("\n--- monio {} ---", env!("CARGO_PKG_VERSION"));
+    println!("os: {} ({})", std::env::consts::OS, std::env::consts::ARCH);
+    if reports.is_empty() {
+        println!("No unknown keys seen.");
+    }
+    for report in reports {
+        println!("{report}");
+    }
+}
@@ -0,0 +1,82 @@
+//! Key rollover / ghosting tester.
+//!
+//! Run with: cargo run --example rollover
+//!
+//! Walks you through holding several key combinations and reports the
+//! maximum number of simultaneous keys your keyboard registered, plus any
+//! combinations where some keys never showed up (ghosting).
+
+use monio::channel::listen_channel;
+use monio::rollover::RolloverTester;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long to wait for each combination before giving up on it.
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(8);
+
+fn main() {
+    println!("monio rollover tester");
+    println!("=====================\n");
+    println!("Hold each combination until it is accepted.");
+    println!(
+        "Combinations that don't register within {}s are marked as ghosted.\n",
+        PROMPT_TIMEOUT.as_secs()
+    );
+
+    let (handle, rx) = listen_channel(256).expect("Failed to start hook");
+    let mut tester = RolloverTester::with_script(RolloverTester::default_script());
+    let mut step = 0;
+
+    while let Some(prompt) = tester.current_prompt().map(<[_]>::to_vec) {
+        step += 1;
+        println!("[{}] Hold: {:?}", step, prompt);
+        let deadline = Instant::now() + PROMPT_TIMEOUT;
+
+        loop {
+            if tester.current_prompt() != Some(&prompt[..]) {
+                println!("    ok");
+                break;
+            }
+            if Instant::now() >= deadline {
+                tester.skip_prompt();
+                println!("    timed out");
+                break;
+            }
+            if let Ok(event) = rx.recv_timeout(Duration::from_millis(50)) {
+                tester.process_event(&event);
+            }
+        }
+
+        // Let the user release everything before the next prompt
+        std::thread::sleep(Duration::from_millis(500));
+        while let Ok(event) = rx.try_recv() {
+            tester.process_event(&event);
+        }
+    }
+
+    println!("\nResults");
+    println!("-------");
+    println!(
+        "Max simultaneous keys: {} {:?}",
+        tester.max_simultaneous_keys(),
+        tester.max_combination()
+    );
+
+    let ghosted = tester.suspected_ghosted_combinations();
+    if ghosted.is_empty() {
+        println!("No ghosting detected.");
+    } else {
+        for combo in ghosted {
+            println!(
+                "Ghosted: {:?} (missing {:?})",
+                combo.expected, combo.missing
+            );
+        }
+    }
+
+    let stuck = tester.suspected_stuck_keys(SystemTime::now());
+    if !stuck.is_empty() {
+        println!("Possibly stuck (missed release): {:?}", stuck);
+    }
+
+    handle.stop().expect("Failed to stop hook");
+}
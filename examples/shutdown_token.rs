@@ -0,0 +1,84 @@
+//! Stopping several hooks from one shutdown token.
+//!
+//! Run with: cargo run --example shutdown_token --features tokio
+//!
+//! A blocking hook runs on a blocking task and counts key presses, while an
+//! async channel prints mouse clicks. Pressing Escape, or 30 seconds
+//! passing, cancels the token and both stop.
+
+use monio::channel::listen_async_channel;
+use monio::{Event, EventType, Hook, Key, ShutdownToken};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() {
+    println!("monio shutdown token example");
+    println!("============================\n");
+    println!("Press Escape to quit (or wait 30 seconds).\n");
+
+    let token = ShutdownToken::new();
+    let key_presses = Arc::new(AtomicU64::new(0));
+
+    let counter = {
+        let token = token.clone();
+        let key_presses = key_presses.clone();
+        tokio::task::spawn_blocking(move || {
+            let handler_token = token.clone();
+            Hook::new().run_with_token(
+                move |event: &Event| {
+                    if event.event_type != EventType::KeyPressed {
+                        return;
+                    }
+                    key_presses.fetch_add(1, Ordering::SeqCst);
+                    if event
+                        .keyboard
+                        .as_ref()
+                        .is_some_and(|kb| kb.key == Key::Escape)
+                    {
+                        handler_token.cancel();
+                    }
+                },
+                &token,
+            )
+        })
+    };
+
+    let (handle, mut rx) = listen_async_channel(100).expect("Failed to start hook");
+    let handle = handle.stop_on(&token);
+
+    let timeout = tokio::time::sleep(Duration::from_secs(30));
+    tokio::pin!(timeout);
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) if event.event_type == EventType::MousePressed => {
+                    if let Some(mouse) = &event.mouse {
+                        println!("Click at ({:.0}, {:.0})", mouse.x, mouse.y);
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    println!("Channel closed.");
+                    break;
+                }
+            },
+            _ = &mut timeout, if !token.is_cancelled() => {
+                println!("Time's up.");
+                token.cancel();
+            }
+        }
+    }
+
+    match counter.await {
+        Ok(Ok(())) => println!(
+            "Counted {} key presses.",
+            key_presses.load(Ordering::SeqCst)
+        ),
+        Ok(Err(e)) => eprintln!("Hook failed: {e}"),
+        Err(e) => eprintln!("Hook task panicked: {e}"),
+    }
+    let _ = handle.stop();
+}
@@ -1,7 +1,7 @@
 //! Event statistics example - collect and display input statistics.
 //!
 //! Usage:
-//!   cargo run --example statistics --features statistics --
+//!   cargo run --example statistics --features statistics -- [--demo]
 //!
 //! Press Ctrl+C to stop and see the final statistics.
 
@@ -36,6 +36,11 @@ fn main() -> monio::Result<()> {
         println!("Type, click, and move your mouse!");
         println!("Press Ctrl+C to stop and see results.\n");
 
+        // Play a scripted session instead of reading the real input
+        let _demo = std::env::args()
+            .any(|arg| arg == "--demo")
+            .then(|| monio::demo::synthetic_session(monio::demo::DemoScript::sample()));
+
         let mut collector = StatisticsCollector::new();
         collector.start()?;
 
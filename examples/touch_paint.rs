@@ -0,0 +1,75 @@
+//! Touch paint example - draw on the terminal with a touchscreen.
+//!
+//! Each finger paints with its own character, so multitouch strokes can be
+//! told apart. Touches are only reported by the evdev backend:
+//!
+//! Run with: cargo run --example touch_paint --features evdev --no-default-features
+
+use monio::{Event, EventType, Hook, displays};
+use std::sync::Mutex;
+
+const COLUMNS: usize = 80;
+const ROWS: usize = 24;
+
+static CANVAS: Mutex<[[char; COLUMNS]; ROWS]> = Mutex::new([[' '; COLUMNS]; ROWS]);
+
+fn main() -> monio::Result<()> {
+    // Touches are mapped to the bounding box of all displays
+    let screen = displays()
+        .ok()
+        .and_then(|displays| {
+            displays
+                .iter()
+                .map(|display| display.bounds)
+                .reduce(|a, b| {
+                    let x = a.x.min(b.x);
+                    let y = a.y.min(b.y);
+                    monio::Rect {
+                        x,
+                        y,
+                        width: (a.x + a.width).max(b.x + b.width) - x,
+                        height: (a.y + a.height).max(b.y + b.height) - y,
+                    }
+                })
+        })
+        .unwrap_or(monio::Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 1920.0,
+            height: 1080.0,
+        });
+
+    println!("monio touch paint example");
+    println!("Touch the screen to draw. Press Ctrl+C to exit.");
+
+    let hook = Hook::new().touch_as_mouse(false);
+    hook.run(move |event: &Event| {
+        let Some(touch) = &event.touch else {
+            return;
+        };
+
+        let column = ((touch.x - screen.x) / screen.width * COLUMNS as f64) as usize;
+        let row = ((touch.y - screen.y) / screen.height * ROWS as f64) as usize;
+        let brush = match event.event_type {
+            EventType::TouchEnded => '.',
+            _ => char::from_digit(touch.id % 10, 10).unwrap_or('#'),
+        };
+
+        let Ok(mut canvas) = CANVAS.lock() else {
+            return;
+        };
+        canvas[row.min(ROWS - 1)][column.min(COLUMNS - 1)] = brush;
+
+        // Clear the terminal and redraw
+        let mut frame = String::from("\x1b[2J\x1b[H");
+        for line in canvas.iter() {
+            frame.extend(line.iter());
+            frame.push('\n');
+        }
+        frame.push_str(&format!(
+            "{:?} finger {} at ({:.0}, {:.0}), pressure {:?}\n",
+            event.event_type, touch.id, touch.x, touch.y, touch.pressure
+        ));
+        print!("{}", frame);
+    })
+}
@@ -1,6 +1,7 @@
 //! TUI key displayer example - like keycastr but for the terminal.
 //!
 //! Run with: cargo run --example tui_key_displayer
+//! Without input permissions: cargo run --example tui_key_displayer -- --demo
 //!
 //! Note: On macOS, you need to grant Accessibility permissions to the terminal.
 //! Press 'q' or Ctrl+C to exit.
@@ -10,6 +11,8 @@ use crossterm::{
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use monio::demo::{self, DemoScript};
+use monio::format::{ChordStyle, format_event, key_label};
 use monio::{Button, Event, EventType, Key as HookKey, ScrollDirection, listen};
 use ratatui::{
     Frame, Terminal,
@@ -108,132 +111,7 @@ impl App {
     }
 
     fn format_key(key: &HookKey) -> String {
-        match key {
-            HookKey::KeyA => "A".to_string(),
-            HookKey::KeyB => "B".to_string(),
-            HookKey::KeyC => "C".to_string(),
-            HookKey::KeyD => "D".to_string(),
-            HookKey::KeyE => "E".to_string(),
-            HookKey::KeyF => "F".to_string(),
-            HookKey::KeyG => "G".to_string(),
-            HookKey::KeyH => "H".to_string(),
-            HookKey::KeyI => "I".to_string(),
-            HookKey::KeyJ => "J".to_string(),
-            HookKey::KeyK => "K".to_string(),
-            HookKey::KeyL => "L".to_string(),
-            HookKey::KeyM => "M".to_string(),
-            HookKey::KeyN => "N".to_string(),
-            HookKey::KeyO => "O".to_string(),
-            HookKey::KeyP => "P".to_string(),
-            HookKey::KeyQ => "Q".to_string(),
-            HookKey::KeyR => "R".to_string(),
-            HookKey::KeyS => "S".to_string(),
-            HookKey::KeyT => "T".to_string(),
-            HookKey::KeyU => "U".to_string(),
-            HookKey::KeyV => "V".to_string(),
-            HookKey::KeyW => "W".to_string(),
-            HookKey::KeyX => "X".to_string(),
-            HookKey::KeyY => "Y".to_string(),
-            HookKey::KeyZ => "Z".to_string(),
-            HookKey::Num0 => "0".to_string(),
-            HookKey::Num1 => "1".to_string(),
-            HookKey::Num2 => "2".to_string(),
-            HookKey::Num3 => "3".to_string(),
-            HookKey::Num4 => "4".to_string(),
-            HookKey::Num5 => "5".to_string(),
-            HookKey::Num6 => "6".to_string(),
-            HookKey::Num7 => "7".to_string(),
-            HookKey::Num8 => "8".to_string(),
-            HookKey::Num9 => "9".to_string(),
-            HookKey::F1 => "F1".to_string(),
-            HookKey::F2 => "F2".to_string(),
-            HookKey::F3 => "F3".to_string(),
-            HookKey::F4 => "F4".to_string(),
-            HookKey::F5 => "F5".to_string(),
-            HookKey::F6 => "F6".to_string(),
-            HookKey::F7 => "F7".to_string(),
-            HookKey::F8 => "F8".to_string(),
-            HookKey::F9 => "F9".to_string(),
-            HookKey::F10 => "F10".to_string(),
-            HookKey::F11 => "F11".to_string(),
-            HookKey::F12 => "F12".to_string(),
-            HookKey::ShiftLeft => "Shift".to_string(),
-            HookKey::ShiftRight => "Shift".to_string(),
-            HookKey::ControlLeft => "Ctrl".to_string(),
-            HookKey::ControlRight => "Ctrl".to_string(),
-            HookKey::AltLeft => "Alt".to_string(),
-            HookKey::AltRight => "Alt".to_string(),
-            HookKey::MetaLeft => "Cmd".to_string(),
-            HookKey::MetaRight => "Cmd".to_string(),
-            HookKey::Escape => "Esc".to_string(),
-            HookKey::Tab => "Tab".to_string(),
-            HookKey::Space => "Space".to_string(),
-            HookKey::Enter => "Enter".to_string(),
-            HookKey::Backspace => "Backspace".to_string(),
-            HookKey::Delete => "Delete".to_string(),
-            HookKey::Home => "Home".to_string(),
-            HookKey::End => "End".to_string(),
-            HookKey::PageUp => "PgUp".to_string(),
-            HookKey::PageDown => "PgDn".to_string(),
-            HookKey::ArrowUp => "Up".to_string(),
-            HookKey::ArrowDown => "Down".to_string(),
-            HookKey::ArrowLeft => "Left".to_string(),
-            HookKey::ArrowRight => "Right".to_string(),
-            HookKey::Grave => "`".to_string(),
-            HookKey::Minus => "-".to_string(),
-            HookKey::Equal => "=".to_string(),
-            HookKey::BracketLeft => "[".to_string(),
-            HookKey::BracketRight => "]".to_string(),
-            HookKey::Backslash => "\\".to_string(),
-            HookKey::Semicolon => ";".to_string(),
-            HookKey::Quote => "'".to_string(),
-            HookKey::Comma => ",".to_string(),
-            HookKey::Period => ".".to_string(),
-            HookKey::Slash => "/".to_string(),
-            HookKey::CapsLock => "CapsLock".to_string(),
-            HookKey::Insert => "Insert".to_string(),
-            HookKey::NumLock => "NumLock".to_string(),
-            HookKey::ScrollLock => "ScrollLock".to_string(),
-            HookKey::PrintScreen => "PrtScn".to_string(),
-            HookKey::Pause => "Pause".to_string(),
-            HookKey::Numpad0 => "Numpad0".to_string(),
-            HookKey::Numpad1 => "Numpad1".to_string(),
-            HookKey::Numpad2 => "Numpad2".to_string(),
-            HookKey::Numpad3 => "Numpad3".to_string(),
-            HookKey::Numpad4 => "Numpad4".to_string(),
-            HookKey::Numpad5 => "Numpad5".to_string(),
-            HookKey::Numpad6 => "Numpad6".to_string(),
-            HookKey::Numpad7 => "Numpad7".to_string(),
-            HookKey::Numpad8 => "Numpad8".to_string(),
-            HookKey::Numpad9 => "Numpad9".to_string(),
-            HookKey::NumpadAdd => "Numpad+".to_string(),
-            HookKey::NumpadSubtract => "Numpad-".to_string(),
-            HookKey::NumpadMultiply => "Numpad*".to_string(),
-            HookKey::NumpadDivide => "Numpad/".to_string(),
-            HookKey::NumpadDecimal => "Numpad.".to_string(),
-            HookKey::NumpadEnter => "NumpadEnter".to_string(),
-            HookKey::NumpadEqual => "Numpad=".to_string(),
-            HookKey::VolumeUp => "VolUp".to_string(),
-            HookKey::VolumeDown => "VolDown".to_string(),
-            HookKey::VolumeMute => "Mute".to_string(),
-            HookKey::MediaPlayPause => "Play/Pause".to_string(),
-            HookKey::MediaStop => "Stop".to_string(),
-            HookKey::MediaNext => "Next".to_string(),
-            HookKey::MediaPrevious => "Prev".to_string(),
-            HookKey::BrowserBack => "BrowserBack".to_string(),
-            HookKey::BrowserForward => "BrowserForward".to_string(),
-            HookKey::BrowserRefresh => "BrowserRefresh".to_string(),
-            HookKey::BrowserStop => "BrowserStop".to_string(),
-            HookKey::BrowserSearch => "BrowserSearch".to_string(),
-            HookKey::BrowserFavorites => "BrowserFav".to_string(),
-            HookKey::BrowserHome => "BrowserHome".to_string(),
-            HookKey::LaunchMail => "LaunchMail".to_string(),
-            HookKey::LaunchApp1 => "LaunchApp1".to_string(),
-            HookKey::LaunchApp2 => "LaunchApp2".to_string(),
-            HookKey::ContextMenu => "Menu".to_string(),
-            HookKey::Unknown(code) => format!("Unknown({})", code),
-            _ => format!("{:?}", key),
-        }
+        key_label(*key, ChordStyle::native()).into_owned()
     }
 
     fn format_button(button: &Button) -> String {
@@ -272,7 +150,8 @@ impl App {
                 if let Some(kb) = &event.keyboard {
                     let key_str = Self::format_key(&kb.key);
                     self.add_key(&key_str, true);
-                    self.add_event("KeyPress", format!("{} (raw: {})", key_str, kb.raw_code));
+                    let chord = format_event(event, ChordStyle::native()).unwrap_or(key_str);
+                    self.add_event("KeyPress", format!("{} (raw: {})", chord, kb.raw_code));
                 }
             }
             EventType::KeyReleased => {
@@ -337,18 +216,13 @@ impl App {
             EventType::MouseWheel => {
                 if let Some(wheel) = &event.wheel {
                     self.mouse_position = (wheel.x, wheel.y);
-                    let dir_str = match wheel.direction {
-                        ScrollDirection::Up => "Up",
-                        ScrollDirection::Down => "Down",
-                        ScrollDirection::Left => "Left",
-                        ScrollDirection::Right => "Right",
-                    };
+                    let chord = format_event(event, ChordStyle::native()).unwrap_or_default();
                     self.last_scroll = Some((wheel.direction, Instant::now()));
                     self.add_event(
                         "Scroll",
                         format!(
                             "{} delta={:.1} at ({:.0}, {:.0})",
-                            dir_str, wheel.delta, wheel.x, wheel.y
+                            chord, wheel.delta, wheel.x, wheel.y
                         ),
                     );
                 }
@@ -367,6 +241,11 @@ impl App {
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Show a scripted session instead of the real input, e.g. for screenshots
+    let _demo = std::env::args()
+        .any(|arg| arg == "--demo")
+        .then(|| demo::synthetic_session(DemoScript::sample()));
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
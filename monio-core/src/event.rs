@@ -0,0 +1,467 @@
+//! Event types and enums for the input hook library.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The type of input event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EventType {
+    /// Hook has been enabled and is now listening.
+    HookEnabled,
+    /// Hook has been disabled and is no longer listening.
+    HookDisabled,
+    /// A grab hook released its devices and now only listens; events reach
+    /// other applications whatever the handler returns.
+    ///
+    /// Only reported by the evdev backend, after `force_ungrab_all`.
+    GrabSuspended,
+    /// A grab hook started as a listener, because the backend can't consume
+    /// events (X11) or grabbing is denied by policy. Events reach other
+    /// applications whatever the handler returns.
+    ///
+    /// Reported once, right after `HookEnabled`. See
+    /// `monio::GrabCapabilities`.
+    GrabDegraded,
+    /// Hooks were suspended or resumed with
+    /// `monio::suspend_all` / `monio::resume_all`, or a suspension ran out.
+    /// Check `monio::is_suspended`.
+    ///
+    /// Reported by each hook just before the next event it sees.
+    GlobalSuspendChanged,
+
+    /// A key was pressed down.
+    KeyPressed,
+    /// A key was released.
+    KeyReleased,
+    /// A character was typed (after dead key processing).
+    KeyTyped,
+    /// An input method started composing: keys now go to the composer
+    /// rather than the app.
+    ///
+    /// Reported just before the first key event after the change, where
+    /// the platform lets it be detected. See `monio::ime`.
+    ImeCompositionStarted,
+    /// The input method stopped composing.
+    ImeCompositionEnded,
+
+    /// A mouse button was pressed.
+    MousePressed,
+    /// A mouse button was released.
+    MouseReleased,
+    /// A mouse button was clicked (press + release without movement).
+    MouseClicked,
+    /// The mouse was moved (no buttons held).
+    MouseMoved,
+    /// The mouse was moved while a button was held (drag).
+    MouseDragged,
+    /// A drag began: the cursor moved past the drag threshold with a button
+    /// held. Carries the button and the position it was pressed at.
+    ///
+    /// Reported once per drag, just before the first `MouseDragged`. See
+    /// `monio::Hook::drag_threshold`.
+    DragStarted,
+
+    /// The mouse wheel was scrolled.
+    MouseWheel,
+
+    /// A finger touched a touchscreen.
+    ///
+    /// Only reported by the evdev backend.
+    TouchBegan,
+    /// A touching finger moved.
+    TouchMoved,
+    /// A finger was lifted.
+    TouchEnded,
+
+    /// The cursor moved onto another display.
+    ///
+    /// Only reported by hooks with
+    /// `monio::Hook::track_displays` enabled.
+    DisplayChanged,
+}
+
+/// Mouse button identifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Button {
+    /// Left mouse button (Button 1).
+    Left,
+    /// Right mouse button (Button 2).
+    Right,
+    /// Middle mouse button (Button 3).
+    Middle,
+    /// Extra button 1 (typically back).
+    Button4,
+    /// Extra button 2 (typically forward).
+    Button5,
+    /// Any further button, numbered from 6 in the order the platform
+    /// numbers them. See [`Button::from_platform_code`] for how each
+    /// platform's codes map onto this numbering.
+    Unknown(u8),
+}
+
+/// An input backend, for reading and writing its native codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Backend {
+    /// macOS Quartz event taps: the 0-based `kCGMouseEventButtonNumber`.
+    MacOS,
+    /// Windows: the virtual-key code (`VK_LBUTTON` .. `VK_XBUTTON2`).
+    Windows,
+    /// X11 core button numbers. Buttons 4-7 are the scroll wheel.
+    X11,
+    /// Linux evdev `BTN_*` codes, `BTN_LEFT` (0x110) to `BTN_TASK` (0x117).
+    Evdev,
+}
+
+impl Button {
+    /// Get the button number (1-indexed).
+    pub fn number(&self) -> u8 {
+        match self {
+            Button::Left => 1,
+            Button::Right => 2,
+            Button::Middle => 3,
+            Button::Button4 => 4,
+            Button::Button5 => 5,
+            Button::Unknown(n) => *n,
+        }
+    }
+
+    /// Create a Button from a number (1-indexed).
+    pub fn from_number(n: u8) -> Self {
+        match n {
+            1 => Button::Left,
+            2 => Button::Right,
+            3 => Button::Middle,
+            4 => Button::Button4,
+            5 => Button::Button5,
+            _ => Button::Unknown(n),
+        }
+    }
+
+    /// Map a backend's native button code to a button.
+    ///
+    /// Returns `None` for codes that aren't buttons there, such as the X11
+    /// wheel buttons 4-7. Extra buttons count on from `Button5` in the
+    /// backend's own order:
+    ///
+    /// | Button     | macOS | Windows       | X11   | evdev                  |
+    /// |------------|-------|---------------|-------|------------------------|
+    /// | Left       | 0     | `VK_LBUTTON`  | 1     | `BTN_LEFT`             |
+    /// | Right      | 1     | `VK_RBUTTON`  | 3     | `BTN_RIGHT`            |
+    /// | Middle     | 2     | `VK_MBUTTON`  | 2     | `BTN_MIDDLE`           |
+    /// | Button4    | 3     | `VK_XBUTTON1` | 8     | `BTN_SIDE`             |
+    /// | Button5    | 4     | `VK_XBUTTON2` | 9     | `BTN_EXTRA`            |
+    /// | Unknown(n) | n - 1 | -             | n + 4 | `BTN_FORWARD` + n - 6, up to `BTN_TASK` |
+    ///
+    /// ```
+    /// use monio_core::{Backend, Button};
+    ///
+    /// assert_eq!(Button::from_platform_code(Backend::X11, 8), Some(Button::Button4));
+    /// assert_eq!(Button::from_platform_code(Backend::X11, 12), Some(Button::Unknown(8)));
+    /// assert_eq!(Button::from_platform_code(Backend::X11, 4), None);
+    /// ```
+    pub fn from_platform_code(backend: Backend, code: u32) -> Option<Self> {
+        let n = match backend {
+            Backend::MacOS => code.checked_add(1)?,
+            Backend::Windows => match code {
+                0x01 => 1,
+                0x02 => 2,
+                0x04 => 3,
+                0x05 => 4,
+                0x06 => 5,
+                _ => return None,
+            },
+            Backend::X11 => match code {
+                1 => 1,
+                2 => 3,
+                3 => 2,
+                8 | 9 => code - 4,
+                c if c >= 10 => c - 4,
+                _ => return None,
+            },
+            Backend::Evdev => match code {
+                0x110 => 1,
+                0x111 => 2,
+                0x112 => 3,
+                0x113..=0x117 => code - 0x113 + 4,
+                _ => return None,
+            },
+        };
+        u8::try_from(n).ok().map(Button::from_number)
+    }
+
+    /// The backend's native code for this button, the inverse of
+    /// [`Button::from_platform_code`].
+    ///
+    /// Returns `None` if the backend can't represent the button, such as
+    /// anything past `Button5` on Windows.
+    pub fn platform_code(&self, backend: Backend) -> Option<u32> {
+        let n = u32::from(self.number());
+        if n == 0 {
+            return None;
+        }
+        match backend {
+            Backend::MacOS => Some(n - 1),
+            Backend::Windows => match n {
+                1 => Some(0x01),
+                2 => Some(0x02),
+                3 => Some(0x04),
+                4 => Some(0x05),
+                5 => Some(0x06),
+                _ => None,
+            },
+            Backend::X11 => match n {
+                1 => Some(1),
+                2 => Some(3),
+                3 => Some(2),
+                _ => Some(n + 4),
+            },
+            Backend::Evdev => match n {
+                1 => Some(0x110),
+                2 => Some(0x111),
+                3 => Some(0x112),
+                4..=8 => Some(0x113 + n - 4),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Scroll direction for mouse wheel events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ScrollDirection {
+    /// Scrolling up (away from user).
+    Up,
+    /// Scrolling down (toward user).
+    Down,
+    /// Scrolling left.
+    Left,
+    /// Scrolling right.
+    Right,
+}
+
+/// Where a wheel event falls in a touchpad scroll gesture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ScrollPhase {
+    /// The fingers started moving.
+    Began,
+    /// The fingers moved.
+    Changed,
+    /// The fingers were lifted. The event carries no distance.
+    Ended,
+}
+
+/// Mouse event data.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MouseData {
+    /// The mouse button (for press/release/click events).
+    ///
+    /// For drag events this is the button being dragged with; if several are
+    /// held, the first of Left, Right, Middle, Button4, Button5. All held
+    /// buttons are available through `monio::Event::held_buttons`.
+    pub button: Option<Button>,
+    /// X coordinate (screen coordinates).
+    pub x: f64,
+    /// Y coordinate (screen coordinates).
+    pub y: f64,
+    /// Click count (for click events).
+    pub clicks: u8,
+}
+
+/// Mouse wheel event data.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WheelData {
+    /// X coordinate (screen coordinates).
+    pub x: f64,
+    /// Y coordinate (screen coordinates).
+    pub y: f64,
+    /// Scroll direction.
+    pub direction: ScrollDirection,
+    /// Amount of rotation (in platform-specific units).
+    pub delta: f64,
+    /// Distance scrolled in pixels (at 96 DPI), from sources finer than
+    /// wheel notches. Only Windows precision touchpads report it, with the
+    /// `pointer` feature.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pixel_delta: Option<f64>,
+    /// Phase of the touchpad gesture this event belongs to, where reported
+    /// along with [`pixel_delta`](Self::pixel_delta).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub phase: Option<ScrollPhase>,
+}
+
+/// Touch event data.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TouchData {
+    /// Identifies the finger from [`TouchBegan`](EventType::TouchBegan)
+    /// until [`TouchEnded`](EventType::TouchEnded); may be reused afterwards.
+    pub id: u32,
+    /// X coordinate (screen coordinates).
+    pub x: f64,
+    /// Y coordinate (screen coordinates).
+    pub y: f64,
+    /// Pressure from 0.0 to 1.0, if the device reports it.
+    pub pressure: Option<f32>,
+}
+
+/// Display change data, see [`EventType::DisplayChanged`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DisplayChange {
+    /// `monio::DisplayInfo::id` of the display the cursor left.
+    pub from: u32,
+    /// `monio::DisplayInfo::id` of the display the cursor entered.
+    pub to: u32,
+}
+
+impl WheelData {
+    /// Whole scroll steps as `(vertical, horizontal)`.
+    ///
+    /// Vertical is positive when scrolling up, horizontal when scrolling
+    /// right. Fractional deltas round up so any scroll moves at least a step.
+    pub fn steps(&self) -> (i32, i32) {
+        // `f64::ceil` needs std; truncating and rounding up is the same here
+        let delta = if self.delta < 0.0 {
+            -self.delta
+        } else {
+            self.delta
+        };
+        let whole = delta as i32;
+        let steps = if (whole as f64) < delta {
+            whole.saturating_add(1)
+        } else {
+            whole
+        };
+        match self.direction {
+            ScrollDirection::Up => (steps, 0),
+            ScrollDirection::Down => (-steps, 0),
+            ScrollDirection::Left => (0, -steps),
+            ScrollDirection::Right => (0, steps),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wheel(direction: ScrollDirection, delta: f64) -> WheelData {
+        WheelData {
+            x: 0.0,
+            y: 0.0,
+            direction,
+            delta,
+            pixel_delta: None,
+            phase: None,
+        }
+    }
+
+    #[test]
+    fn test_wheel_steps_round_up() {
+        assert_eq!(wheel(ScrollDirection::Up, 1.0).steps(), (1, 0));
+        assert_eq!(wheel(ScrollDirection::Down, 0.25).steps(), (-1, 0));
+        assert_eq!(wheel(ScrollDirection::Left, -2.5).steps(), (0, -3));
+        assert_eq!(wheel(ScrollDirection::Right, 0.0).steps(), (0, 0));
+        assert_eq!(wheel(ScrollDirection::Up, f64::MAX).steps(), (i32::MAX, 0));
+    }
+
+    #[test]
+    fn test_button_numbers_round_trip() {
+        for n in 0..=8 {
+            assert_eq!(Button::from_number(n).number(), n);
+        }
+    }
+
+    #[test]
+    fn test_button_platform_codes() {
+        use Button::*;
+        let table: &[(Backend, &[(u32, Button)])] = &[
+            (
+                Backend::MacOS,
+                &[
+                    (0, Left),
+                    (1, Right),
+                    (2, Middle),
+                    (3, Button4),
+                    (4, Button5),
+                    (5, Unknown(6)),
+                    (11, Unknown(12)),
+                ],
+            ),
+            (
+                Backend::Windows,
+                &[
+                    (0x01, Left),
+                    (0x02, Right),
+                    (0x04, Middle),
+                    (0x05, Button4),
+                    (0x06, Button5),
+                ],
+            ),
+            (
+                Backend::X11,
+                &[
+                    (1, Left),
+                    (2, Middle),
+                    (3, Right),
+                    (8, Button4),
+                    (9, Button5),
+                    (10, Unknown(6)),
+                    (16, Unknown(12)),
+                ],
+            ),
+            (
+                Backend::Evdev,
+                &[
+                    (0x110, Left),
+                    (0x111, Right),
+                    (0x112, Middle),
+                    (0x113, Button4),
+                    (0x114, Button5),
+                    (0x115, Unknown(6)),
+                    (0x116, Unknown(7)),
+                    (0x117, Unknown(8)),
+                ],
+            ),
+        ];
+        for (backend, codes) in table {
+            for &(code, button) in *codes {
+                assert_eq!(
+                    Button::from_platform_code(*backend, code),
+                    Some(button),
+                    "{backend:?} {code:#x}"
+                );
+                assert_eq!(
+                    button.platform_code(*backend),
+                    Some(code),
+                    "{backend:?} {button:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_button_platform_codes_out_of_range() {
+        for code in 4..=7 {
+            assert_eq!(Button::from_platform_code(Backend::X11, code), None);
+        }
+        assert_eq!(Button::from_platform_code(Backend::X11, 0), None);
+        assert_eq!(Button::from_platform_code(Backend::X11, 260), None);
+        assert_eq!(Button::from_platform_code(Backend::Windows, 0x03), None);
+        assert_eq!(Button::from_platform_code(Backend::Evdev, 0x118), None);
+        assert_eq!(Button::from_platform_code(Backend::MacOS, 255), None);
+
+        assert_eq!(Button::Unknown(6).platform_code(Backend::Windows), None);
+        assert_eq!(Button::Unknown(9).platform_code(Backend::Evdev), None);
+        assert_eq!(Button::Unknown(0).platform_code(Backend::MacOS), None);
+        // Unknown(4) is the same button as Button4 everywhere
+        assert_eq!(Button::Unknown(4).platform_code(Backend::X11), Some(8));
+    }
+}
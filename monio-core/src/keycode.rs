@@ -0,0 +1,534 @@
+//! Virtual key code definitions.
+
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub mod names;
+
+/// Virtual key codes for keyboard keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Key {
+    // Letters
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyI,
+    KeyJ,
+    KeyK,
+    KeyL,
+    KeyM,
+    KeyN,
+    KeyO,
+    KeyP,
+    KeyQ,
+    KeyR,
+    KeyS,
+    KeyT,
+    KeyU,
+    KeyV,
+    KeyW,
+    KeyX,
+    KeyY,
+    KeyZ,
+
+    // Numbers (top row)
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+
+    // Function keys
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+
+    // Modifiers
+    ShiftLeft,
+    ShiftRight,
+    ControlLeft,
+    ControlRight,
+    AltLeft,
+    AltRight,
+    MetaLeft, // Windows/Command/Super
+    MetaRight,
+
+    // Navigation
+    Escape,
+    Tab,
+    CapsLock,
+    Space,
+    Enter,
+    Backspace,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+
+    // Lock keys
+    NumLock,
+    ScrollLock,
+    PrintScreen,
+    Pause,
+
+    // Punctuation and symbols
+    Grave,        // ` ~
+    Minus,        // - _
+    Equal,        // = +
+    BracketLeft,  // [ {
+    BracketRight, // ] }
+    Backslash,    // \ |
+    Semicolon,    // ; :
+    Quote,        // ' "
+    Comma,        // , <
+    Period,       // . >
+    Slash,        // / ?
+
+    // Numpad
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+    NumpadEqual,
+
+    // Media keys
+    VolumeUp,
+    VolumeDown,
+    VolumeMute,
+    MediaPlayPause,
+    MediaStop,
+    MediaNext,
+    MediaPrevious,
+
+    // Browser keys
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+
+    // Application keys
+    LaunchMail,
+    LaunchApp1,
+    LaunchApp2,
+
+    // International / special
+    IntlBackslash,
+    IntlYen,
+    IntlRo,
+
+    // Context menu
+    ContextMenu,
+
+    /// A printable key with no named variant, identified by the character it
+    /// produces (unshifted) on the active keyboard layout, e.g. `ö` or `§`.
+    Char(char),
+
+    // Unknown key with raw code
+    Unknown(u32),
+}
+
+impl Key {
+    /// Check if this is a modifier key.
+    pub fn is_modifier(&self) -> bool {
+        matches!(
+            self,
+            Key::ShiftLeft
+                | Key::ShiftRight
+                | Key::ControlLeft
+                | Key::ControlRight
+                | Key::AltLeft
+                | Key::AltRight
+                | Key::MetaLeft
+                | Key::MetaRight
+        )
+    }
+
+    /// Check if this is a letter key.
+    pub fn is_letter(&self) -> bool {
+        matches!(
+            self,
+            Key::KeyA
+                | Key::KeyB
+                | Key::KeyC
+                | Key::KeyD
+                | Key::KeyE
+                | Key::KeyF
+                | Key::KeyG
+                | Key::KeyH
+                | Key::KeyI
+                | Key::KeyJ
+                | Key::KeyK
+                | Key::KeyL
+                | Key::KeyM
+                | Key::KeyN
+                | Key::KeyO
+                | Key::KeyP
+                | Key::KeyQ
+                | Key::KeyR
+                | Key::KeyS
+                | Key::KeyT
+                | Key::KeyU
+                | Key::KeyV
+                | Key::KeyW
+                | Key::KeyX
+                | Key::KeyY
+                | Key::KeyZ
+        )
+    }
+
+    /// Check if this is a number key (top row).
+    pub fn is_number(&self) -> bool {
+        matches!(
+            self,
+            Key::Num0
+                | Key::Num1
+                | Key::Num2
+                | Key::Num3
+                | Key::Num4
+                | Key::Num5
+                | Key::Num6
+                | Key::Num7
+                | Key::Num8
+                | Key::Num9
+        )
+    }
+
+    /// Check if this is a function key.
+    pub fn is_function_key(&self) -> bool {
+        matches!(
+            self,
+            Key::F1
+                | Key::F2
+                | Key::F3
+                | Key::F4
+                | Key::F5
+                | Key::F6
+                | Key::F7
+                | Key::F8
+                | Key::F9
+                | Key::F10
+                | Key::F11
+                | Key::F12
+                | Key::F13
+                | Key::F14
+                | Key::F15
+                | Key::F16
+                | Key::F17
+                | Key::F18
+                | Key::F19
+                | Key::F20
+                | Key::F21
+                | Key::F22
+                | Key::F23
+                | Key::F24
+        )
+    }
+
+    /// Check if this is a numpad key.
+    pub fn is_numpad(&self) -> bool {
+        matches!(
+            self,
+            Key::Numpad0
+                | Key::Numpad1
+                | Key::Numpad2
+                | Key::Numpad3
+                | Key::Numpad4
+                | Key::Numpad5
+                | Key::Numpad6
+                | Key::Numpad7
+                | Key::Numpad8
+                | Key::Numpad9
+                | Key::NumpadAdd
+                | Key::NumpadSubtract
+                | Key::NumpadMultiply
+                | Key::NumpadDivide
+                | Key::NumpadDecimal
+                | Key::NumpadEnter
+                | Key::NumpadEqual
+        )
+    }
+
+    /// Check if this is a media key.
+    pub fn is_media(&self) -> bool {
+        matches!(
+            self,
+            Key::VolumeUp
+                | Key::VolumeDown
+                | Key::VolumeMute
+                | Key::MediaPlayPause
+                | Key::MediaStop
+                | Key::MediaNext
+                | Key::MediaPrevious
+        )
+    }
+
+    /// The key for a character as produced without modifiers.
+    ///
+    /// Letters, digits, space and US-layout punctuation map to their named
+    /// variants; any other character becomes [`Key::Char`] (lowercased).
+    pub fn from_char(ch: char) -> Key {
+        const LETTERS: [Key; 26] = [
+            Key::KeyA,
+            Key::KeyB,
+            Key::KeyC,
+            Key::KeyD,
+            Key::KeyE,
+            Key::KeyF,
+            Key::KeyG,
+            Key::KeyH,
+            Key::KeyI,
+            Key::KeyJ,
+            Key::KeyK,
+            Key::KeyL,
+            Key::KeyM,
+            Key::KeyN,
+            Key::KeyO,
+            Key::KeyP,
+            Key::KeyQ,
+            Key::KeyR,
+            Key::KeyS,
+            Key::KeyT,
+            Key::KeyU,
+            Key::KeyV,
+            Key::KeyW,
+            Key::KeyX,
+            Key::KeyY,
+            Key::KeyZ,
+        ];
+        const DIGITS: [Key; 10] = [
+            Key::Num0,
+            Key::Num1,
+            Key::Num2,
+            Key::Num3,
+            Key::Num4,
+            Key::Num5,
+            Key::Num6,
+            Key::Num7,
+            Key::Num8,
+            Key::Num9,
+        ];
+
+        match ch {
+            'a'..='z' => LETTERS[(ch as u8 - b'a') as usize],
+            'A'..='Z' => LETTERS[(ch as u8 - b'A') as usize],
+            '0'..='9' => DIGITS[(ch as u8 - b'0') as usize],
+            ' ' => Key::Space,
+            '`' => Key::Grave,
+            '-' => Key::Minus,
+            '=' => Key::Equal,
+            '[' => Key::BracketLeft,
+            ']' => Key::BracketRight,
+            '\\' => Key::Backslash,
+            ';' => Key::Semicolon,
+            '\'' => Key::Quote,
+            ',' => Key::Comma,
+            '.' => Key::Period,
+            '/' => Key::Slash,
+            _ => {
+                let mut lower = ch.to_lowercase();
+                match (lower.next(), lower.next()) {
+                    (Some(l), None) => Key::Char(l),
+                    _ => Key::Char(ch),
+                }
+            }
+        }
+    }
+
+    /// The character this key produces without modifiers on a US layout,
+    /// or the layout character carried by [`Key::Char`].
+    pub fn as_char(&self) -> Option<char> {
+        if let Key::Char(ch) = self {
+            return Some(*ch);
+        }
+        // Invert from_char over printable ASCII
+        (' '..='~').find(|&ch| !ch.is_ascii_uppercase() && Key::from_char(ch) == *self)
+    }
+
+    /// Check if this is a navigation key.
+    pub fn is_navigation(&self) -> bool {
+        matches!(
+            self,
+            Key::ArrowUp
+                | Key::ArrowDown
+                | Key::ArrowLeft
+                | Key::ArrowRight
+                | Key::Home
+                | Key::End
+                | Key::PageUp
+                | Key::PageDown
+        )
+    }
+}
+
+impl Default for Key {
+    fn default() -> Self {
+        Key::Unknown(0)
+    }
+}
+
+/// Error returned when a string names no [`Key`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyError {
+    name: String,
+}
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key `{}`", self.name)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseKeyError {}
+
+/// The character of `Char(..)` as `Debug` writes it, quoted or not.
+fn parse_char(text: &str) -> Option<char> {
+    let text = text
+        .strip_prefix('\'')
+        .and_then(|quoted| quoted.strip_suffix('\''))
+        .unwrap_or(text);
+    let mut chars = text.chars();
+    match (chars.next()?, chars.next()) {
+        (ch, None) => Some(ch),
+        ('\\', Some(escaped)) => match (escaped, chars.as_str()) {
+            ('n', "") => Some('\n'),
+            ('r', "") => Some('\r'),
+            ('t', "") => Some('\t'),
+            ('0', "") => Some('\0'),
+            ('u', code) => {
+                let hex = code.strip_prefix('{')?.strip_suffix('}')?;
+                char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+            }
+            (other, "") => Some(other),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl FromStr for Key {
+    type Err = ParseKeyError;
+
+    /// Parse a key by its variant name (`"ShiftLeft"`, any case), a single
+    /// character as for [`Key::from_char`] (`"a"`, `"ö"`), the `Debug`
+    /// form of the other variants (`"Char('ö')"`, `"Unknown(42)"`), or a
+    /// name xdotool or AutoHotkey use for it (`"Return"`, `"ctrl"`,
+    /// `"Esc"`), see [`names`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(key) = names::from_variant_name(s) {
+            return Ok(key);
+        }
+        let inner = |prefix: &str| s.strip_prefix(prefix)?.strip_suffix(')');
+        let key = if let Some(ch) = inner("Char(").and_then(parse_char) {
+            Some(Key::Char(ch))
+        } else if let Some(code) = inner("Unknown(") {
+            code.parse().ok().map(Key::Unknown)
+        } else {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Some(Key::from_char(ch)),
+                _ => names::from_tool_name(s),
+            }
+        };
+        key.ok_or_else(|| ParseKeyError {
+            name: s.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    #[test]
+    fn test_parse_round_trips_debug() {
+        for key in [Key::KeyA, Key::MetaLeft, Key::Grave, Key::ContextMenu] {
+            assert_eq!(format!("{:?}", key).parse::<Key>(), Ok(key));
+        }
+        for key in [
+            Key::Char('ö'),
+            Key::Char('\''),
+            Key::Char('\\'),
+            Key::Char('\u{301}'),
+            Key::Unknown(42),
+        ] {
+            assert_eq!(format!("{:?}", key).parse::<Key>(), Ok(key));
+        }
+    }
+
+    #[test]
+    fn test_parse_names_and_characters() {
+        assert_eq!("shiftleft".parse::<Key>(), Ok(Key::ShiftLeft));
+        assert_eq!("a".parse::<Key>(), Ok(Key::KeyA));
+        assert_eq!("ö".parse::<Key>(), Ok(Key::Char('ö')));
+        assert_eq!("Char(ö)".parse::<Key>(), Ok(Key::Char('ö')));
+        // Other tools' names and aliases
+        assert_eq!("ctrl".parse::<Key>(), Ok(Key::ControlLeft));
+        assert_eq!("Return".parse::<Key>(), Ok(Key::Enter));
+        assert_eq!("Esc".parse::<Key>(), Ok(Key::Escape));
+        assert_eq!("PgDn".parse::<Key>(), Ok(Key::PageDown));
+        assert_eq!("U00F6".parse::<Key>(), Ok(Key::Char('ö')));
+        let err = "Hyper".parse::<Key>().unwrap_err();
+        assert_eq!(err.to_string(), "unknown key `Hyper`");
+        assert!("".parse::<Key>().is_err());
+        assert!("Unknown(x)".parse::<Key>().is_err());
+    }
+}
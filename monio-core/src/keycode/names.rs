@@ -0,0 +1,499 @@
+//! Key names, in monio's vocabulary and in other tools'.
+//!
+//! One table lists every key with a variant of its own: its variant name
+//! (what `Debug` writes and [`Key`]'s `FromStr` reads), its X keysym name
+//! (as used by xdotool) and its AutoHotkey name, so parsers, exporters and
+//! importers agree. Letters and digits are named by their character in both
+//! tools and have no tool names here.
+
+use super::Key;
+use alloc::format;
+use alloc::string::{String, ToString};
+
+/// A named key: the key, its variant name, its X keysym name and its
+/// AutoHotkey name.
+type Names = (
+    Key,
+    &'static str,
+    Option<&'static str>,
+    Option<&'static str>,
+);
+
+const NAMES: &[Names] = &[
+    (Key::KeyA, "KeyA", None, None),
+    (Key::KeyB, "KeyB", None, None),
+    (Key::KeyC, "KeyC", None, None),
+    (Key::KeyD, "KeyD", None, None),
+    (Key::KeyE, "KeyE", None, None),
+    (Key::KeyF, "KeyF", None, None),
+    (Key::KeyG, "KeyG", None, None),
+    (Key::KeyH, "KeyH", None, None),
+    (Key::KeyI, "KeyI", None, None),
+    (Key::KeyJ, "KeyJ", None, None),
+    (Key::KeyK, "KeyK", None, None),
+    (Key::KeyL, "KeyL", None, None),
+    (Key::KeyM, "KeyM", None, None),
+    (Key::KeyN, "KeyN", None, None),
+    (Key::KeyO, "KeyO", None, None),
+    (Key::KeyP, "KeyP", None, None),
+    (Key::KeyQ, "KeyQ", None, None),
+    (Key::KeyR, "KeyR", None, None),
+    (Key::KeyS, "KeyS", None, None),
+    (Key::KeyT, "KeyT", None, None),
+    (Key::KeyU, "KeyU", None, None),
+    (Key::KeyV, "KeyV", None, None),
+    (Key::KeyW, "KeyW", None, None),
+    (Key::KeyX, "KeyX", None, None),
+    (Key::KeyY, "KeyY", None, None),
+    (Key::KeyZ, "KeyZ", None, None),
+    (Key::Num0, "Num0", None, None),
+    (Key::Num1, "Num1", None, None),
+    (Key::Num2, "Num2", None, None),
+    (Key::Num3, "Num3", None, None),
+    (Key::Num4, "Num4", None, None),
+    (Key::Num5, "Num5", None, None),
+    (Key::Num6, "Num6", None, None),
+    (Key::Num7, "Num7", None, None),
+    (Key::Num8, "Num8", None, None),
+    (Key::Num9, "Num9", None, None),
+    (Key::F1, "F1", Some("F1"), Some("F1")),
+    (Key::F2, "F2", Some("F2"), Some("F2")),
+    (Key::F3, "F3", Some("F3"), Some("F3")),
+    (Key::F4, "F4", Some("F4"), Some("F4")),
+    (Key::F5, "F5", Some("F5"), Some("F5")),
+    (Key::F6, "F6", Some("F6"), Some("F6")),
+    (Key::F7, "F7", Some("F7"), Some("F7")),
+    (Key::F8, "F8", Some("F8"), Some("F8")),
+    (Key::F9, "F9", Some("F9"), Some("F9")),
+    (Key::F10, "F10", Some("F10"), Some("F10")),
+    (Key::F11, "F11", Some("F11"), Some("F11")),
+    (Key::F12, "F12", Some("F12"), Some("F12")),
+    (Key::F13, "F13", Some("F13"), Some("F13")),
+    (Key::F14, "F14", Some("F14"), Some("F14")),
+    (Key::F15, "F15", Some("F15"), Some("F15")),
+    (Key::F16, "F16", Some("F16"), Some("F16")),
+    (Key::F17, "F17", Some("F17"), Some("F17")),
+    (Key::F18, "F18", Some("F18"), Some("F18")),
+    (Key::F19, "F19", Some("F19"), Some("F19")),
+    (Key::F20, "F20", Some("F20"), Some("F20")),
+    (Key::F21, "F21", Some("F21"), Some("F21")),
+    (Key::F22, "F22", Some("F22"), Some("F22")),
+    (Key::F23, "F23", Some("F23"), Some("F23")),
+    (Key::F24, "F24", Some("F24"), Some("F24")),
+    (Key::ShiftLeft, "ShiftLeft", Some("Shift_L"), Some("LShift")),
+    (
+        Key::ShiftRight,
+        "ShiftRight",
+        Some("Shift_R"),
+        Some("RShift"),
+    ),
+    (
+        Key::ControlLeft,
+        "ControlLeft",
+        Some("Control_L"),
+        Some("LCtrl"),
+    ),
+    (
+        Key::ControlRight,
+        "ControlRight",
+        Some("Control_R"),
+        Some("RCtrl"),
+    ),
+    (Key::AltLeft, "AltLeft", Some("Alt_L"), Some("LAlt")),
+    (Key::AltRight, "AltRight", Some("Alt_R"), Some("RAlt")),
+    (Key::MetaLeft, "MetaLeft", Some("Super_L"), Some("LWin")),
+    (Key::MetaRight, "MetaRight", Some("Super_R"), Some("RWin")),
+    (Key::Escape, "Escape", Some("Escape"), Some("Escape")),
+    (Key::Tab, "Tab", Some("Tab"), Some("Tab")),
+    (
+        Key::CapsLock,
+        "CapsLock",
+        Some("Caps_Lock"),
+        Some("CapsLock"),
+    ),
+    (Key::Space, "Space", Some("space"), Some("Space")),
+    (Key::Enter, "Enter", Some("Return"), Some("Enter")),
+    (
+        Key::Backspace,
+        "Backspace",
+        Some("BackSpace"),
+        Some("Backspace"),
+    ),
+    (Key::Insert, "Insert", Some("Insert"), Some("Insert")),
+    (Key::Delete, "Delete", Some("Delete"), Some("Delete")),
+    (Key::Home, "Home", Some("Home"), Some("Home")),
+    (Key::End, "End", Some("End"), Some("End")),
+    (Key::PageUp, "PageUp", Some("Prior"), Some("PgUp")),
+    (Key::PageDown, "PageDown", Some("Next"), Some("PgDn")),
+    (Key::ArrowUp, "ArrowUp", Some("Up"), Some("Up")),
+    (Key::ArrowDown, "ArrowDown", Some("Down"), Some("Down")),
+    (Key::ArrowLeft, "ArrowLeft", Some("Left"), Some("Left")),
+    (Key::ArrowRight, "ArrowRight", Some("Right"), Some("Right")),
+    (Key::NumLock, "NumLock", Some("Num_Lock"), Some("NumLock")),
+    (
+        Key::ScrollLock,
+        "ScrollLock",
+        Some("Scroll_Lock"),
+        Some("ScrollLock"),
+    ),
+    (
+        Key::PrintScreen,
+        "PrintScreen",
+        Some("Print"),
+        Some("PrintScreen"),
+    ),
+    (Key::Pause, "Pause", Some("Pause"), Some("Pause")),
+    (Key::Grave, "Grave", Some("grave"), Some("`")),
+    (Key::Minus, "Minus", Some("minus"), Some("-")),
+    (Key::Equal, "Equal", Some("equal"), Some("=")),
+    (
+        Key::BracketLeft,
+        "BracketLeft",
+        Some("bracketleft"),
+        Some("["),
+    ),
+    (
+        Key::BracketRight,
+        "BracketRight",
+        Some("bracketright"),
+        Some("]"),
+    ),
+    (Key::Backslash, "Backslash", Some("backslash"), Some("\\")),
+    (Key::Semicolon, "Semicolon", Some("semicolon"), Some(";")),
+    (Key::Quote, "Quote", Some("apostrophe"), Some("'")),
+    (Key::Comma, "Comma", Some("comma"), Some(",")),
+    (Key::Period, "Period", Some("period"), Some(".")),
+    (Key::Slash, "Slash", Some("slash"), Some("/")),
+    (Key::Numpad0, "Numpad0", Some("KP_0"), Some("Numpad0")),
+    (Key::Numpad1, "Numpad1", Some("KP_1"), Some("Numpad1")),
+    (Key::Numpad2, "Numpad2", Some("KP_2"), Some("Numpad2")),
+    (Key::Numpad3, "Numpad3", Some("KP_3"), Some("Numpad3")),
+    (Key::Numpad4, "Numpad4", Some("KP_4"), Some("Numpad4")),
+    (Key::Numpad5, "Numpad5", Some("KP_5"), Some("Numpad5")),
+    (Key::Numpad6, "Numpad6", Some("KP_6"), Some("Numpad6")),
+    (Key::Numpad7, "Numpad7", Some("KP_7"), Some("Numpad7")),
+    (Key::Numpad8, "Numpad8", Some("KP_8"), Some("Numpad8")),
+    (Key::Numpad9, "Numpad9", Some("KP_9"), Some("Numpad9")),
+    (
+        Key::NumpadAdd,
+        "NumpadAdd",
+        Some("KP_Add"),
+        Some("NumpadAdd"),
+    ),
+    (
+        Key::NumpadSubtract,
+        "NumpadSubtract",
+        Some("KP_Subtract"),
+        Some("NumpadSub"),
+    ),
+    (
+        Key::NumpadMultiply,
+        "NumpadMultiply",
+        Some("KP_Multiply"),
+        Some("NumpadMult"),
+    ),
+    (
+        Key::NumpadDivide,
+        "NumpadDivide",
+        Some("KP_Divide"),
+        Some("NumpadDiv"),
+    ),
+    (
+        Key::NumpadDecimal,
+        "NumpadDecimal",
+        Some("KP_Decimal"),
+        Some("NumpadDot"),
+    ),
+    (
+        Key::NumpadEnter,
+        "NumpadEnter",
+        Some("KP_Enter"),
+        Some("NumpadEnter"),
+    ),
+    (Key::NumpadEqual, "NumpadEqual", Some("KP_Equal"), None),
+    (
+        Key::VolumeUp,
+        "VolumeUp",
+        Some("XF86AudioRaiseVolume"),
+        Some("Volume_Up"),
+    ),
+    (
+        Key::VolumeDown,
+        "VolumeDown",
+        Some("XF86AudioLowerVolume"),
+        Some("Volume_Down"),
+    ),
+    (
+        Key::VolumeMute,
+        "VolumeMute",
+        Some("XF86AudioMute"),
+        Some("Volume_Mute"),
+    ),
+    (
+        Key::MediaPlayPause,
+        "MediaPlayPause",
+        Some("XF86AudioPlay"),
+        Some("Media_Play_Pause"),
+    ),
+    (
+        Key::MediaStop,
+        "MediaStop",
+        Some("XF86AudioStop"),
+        Some("Media_Stop"),
+    ),
+    (
+        Key::MediaNext,
+        "MediaNext",
+        Some("XF86AudioNext"),
+        Some("Media_Next"),
+    ),
+    (
+        Key::MediaPrevious,
+        "MediaPrevious",
+        Some("XF86AudioPrev"),
+        Some("Media_Prev"),
+    ),
+    (
+        Key::BrowserBack,
+        "BrowserBack",
+        Some("XF86Back"),
+        Some("Browser_Back"),
+    ),
+    (
+        Key::BrowserForward,
+        "BrowserForward",
+        Some("XF86Forward"),
+        Some("Browser_Forward"),
+    ),
+    (
+        Key::BrowserRefresh,
+        "BrowserRefresh",
+        Some("XF86Refresh"),
+        Some("Browser_Refresh"),
+    ),
+    (
+        Key::BrowserStop,
+        "BrowserStop",
+        Some("XF86Stop"),
+        Some("Browser_Stop"),
+    ),
+    (
+        Key::BrowserSearch,
+        "BrowserSearch",
+        Some("XF86Search"),
+        Some("Browser_Search"),
+    ),
+    (
+        Key::BrowserFavorites,
+        "BrowserFavorites",
+        Some("XF86Favorites"),
+        Some("Browser_Favorites"),
+    ),
+    (
+        Key::BrowserHome,
+        "BrowserHome",
+        Some("XF86HomePage"),
+        Some("Browser_Home"),
+    ),
+    (
+        Key::LaunchMail,
+        "LaunchMail",
+        Some("XF86Mail"),
+        Some("Launch_Mail"),
+    ),
+    (
+        Key::LaunchApp1,
+        "LaunchApp1",
+        Some("XF86MyComputer"),
+        Some("Launch_App1"),
+    ),
+    (
+        Key::LaunchApp2,
+        "LaunchApp2",
+        Some("XF86Calculator"),
+        Some("Launch_App2"),
+    ),
+    (Key::IntlBackslash, "IntlBackslash", None, None),
+    (Key::IntlYen, "IntlYen", None, None),
+    (Key::IntlRo, "IntlRo", None, None),
+    (
+        Key::ContextMenu,
+        "ContextMenu",
+        Some("Menu"),
+        Some("AppsKey"),
+    ),
+];
+
+/// Other names accepted when parsing X keysyms, as xdotool does.
+const XDOTOOL_ALIASES: &[(&str, Key)] = &[
+    ("shift", Key::ShiftLeft),
+    ("ctrl", Key::ControlLeft),
+    ("control", Key::ControlLeft),
+    ("alt", Key::AltLeft),
+    ("super", Key::MetaLeft),
+    ("meta", Key::MetaLeft),
+    ("Meta_L", Key::MetaLeft),
+    ("Meta_R", Key::MetaRight),
+    ("Page_Up", Key::PageUp),
+    ("Page_Down", Key::PageDown),
+    ("Enter", Key::Enter),
+    ("Esc", Key::Escape),
+    ("KP_Delete", Key::NumpadDecimal),
+];
+
+/// Other names AutoHotkey accepts for the same keys.
+const AHK_ALIASES: &[(&str, Key)] = &[
+    ("Shift", Key::ShiftLeft),
+    ("Ctrl", Key::ControlLeft),
+    ("Control", Key::ControlLeft),
+    ("LControl", Key::ControlLeft),
+    ("RControl", Key::ControlRight),
+    ("Alt", Key::AltLeft),
+    ("Esc", Key::Escape),
+    ("Return", Key::Enter),
+    ("BS", Key::Backspace),
+    ("Del", Key::Delete),
+    ("Ins", Key::Insert),
+    ("CtrlBreak", Key::Pause),
+    ("NumpadDel", Key::NumpadDecimal),
+];
+
+/// The X keysym name of `key`, as used by xdotool.
+///
+/// Keys without a named keysym but with a character use the `U<hex>` form.
+pub fn xdotool_name(key: Key) -> Option<String> {
+    if let Some(name) = NAMES.iter().find(|row| row.0 == key).and_then(|row| row.2) {
+        return Some(name.to_string());
+    }
+    match key.as_char()? {
+        ch if ch.is_ascii_alphanumeric() => Some(ch.to_string()),
+        ch => Some(format!("U{:04X}", ch as u32)),
+    }
+}
+
+/// The key named by an X keysym name or a common xdotool alias, ignoring
+/// case.
+pub fn from_xdotool_name(name: &str) -> Option<Key> {
+    if let Some(key) = single_char(name) {
+        return Some(key);
+    }
+    if let Some(hex) = name.strip_prefix(['U', 'u'])
+        && hex.len() >= 4
+        && let Some(ch) = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+    {
+        return Some(Key::from_char(ch));
+    }
+    NAMES
+        .iter()
+        .find(|row| row.2.is_some_and(|n| n.eq_ignore_ascii_case(name)))
+        .map(|row| row.0)
+        .or_else(|| lookup(XDOTOOL_ALIASES, name))
+}
+
+/// The AutoHotkey name of `key`, as written inside `{}` in `Send`.
+pub fn ahk_name(key: Key) -> Option<String> {
+    if let Some(name) = NAMES.iter().find(|row| row.0 == key).and_then(|row| row.3) {
+        return Some(name.to_string());
+    }
+    key.as_char().map(|ch| ch.to_string())
+}
+
+/// The key named by an AutoHotkey key name, ignoring case.
+pub fn from_ahk_name(name: &str) -> Option<Key> {
+    if let Some(key) = single_char(name) {
+        return Some(key);
+    }
+    NAMES
+        .iter()
+        .find(|row| row.3.is_some_and(|n| n.eq_ignore_ascii_case(name)))
+        .map(|row| row.0)
+        .or_else(|| lookup(AHK_ALIASES, name))
+}
+
+/// The key whose variant name is `name`, as `Debug` writes it, ignoring
+/// case.
+pub(crate) fn from_variant_name(name: &str) -> Option<Key> {
+    NAMES
+        .iter()
+        .find(|row| row.1.eq_ignore_ascii_case(name))
+        .map(|row| row.0)
+}
+
+/// The key an X keysym name, an AutoHotkey name, or an alias of either
+/// names, ignoring case.
+pub(crate) fn from_tool_name(name: &str) -> Option<Key> {
+    from_xdotool_name(name).or_else(|| from_ahk_name(name))
+}
+
+/// The key producing a one-character name, e.g. `a`, `7` or `ö`.
+fn single_char(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Some(Key::from_char(ch)),
+        _ => None,
+    }
+}
+
+fn lookup(aliases: &[(&str, Key)], name: &str) -> Option<Key> {
+    aliases
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_round_trip() {
+        for &(key, _, xdotool, ahk) in NAMES {
+            if let Some(name) = xdotool {
+                assert_eq!(from_xdotool_name(name), Some(key), "{name}");
+            }
+            if let Some(name) = ahk {
+                assert_eq!(from_ahk_name(name), Some(key), "{name}");
+            }
+        }
+        for key in [Key::KeyA, Key::Num7, Key::Char('ö')] {
+            assert_eq!(from_xdotool_name(&xdotool_name(key).unwrap()), Some(key));
+            assert_eq!(from_ahk_name(&ahk_name(key).unwrap()), Some(key));
+        }
+    }
+
+    #[test]
+    fn test_variant_names_are_debug() {
+        for &(key, name, _, _) in NAMES {
+            assert_eq!(format!("{:?}", key), name);
+            assert_eq!(name.parse::<Key>(), Ok(key));
+        }
+        // Every variant without data is listed, in declaration order
+        assert_eq!(NAMES.len(), 137);
+        assert_eq!(NAMES.first().map(|row| row.0), Some(Key::KeyA));
+        assert_eq!(NAMES.last().map(|row| row.0), Some(Key::ContextMenu));
+    }
+
+    #[test]
+    fn test_tools_agree_on_shared_names() {
+        for &(_, _, xdotool, ahk) in NAMES {
+            for name in [xdotool, ahk].into_iter().flatten() {
+                if let (Some(x), Some(a)) = (from_xdotool_name(name), from_ahk_name(name)) {
+                    assert_eq!(x, a, "{name}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(xdotool_name(Key::KeyQ).as_deref(), Some("q"));
+        assert_eq!(xdotool_name(Key::Char('ö')).as_deref(), Some("U00F6"));
+        assert_eq!(xdotool_name(Key::Unknown(300)), None);
+        assert_eq!(ahk_name(Key::PageDown).as_deref(), Some("PgDn"));
+        assert_eq!(ahk_name(Key::NumpadEqual), None);
+        assert_eq!(from_xdotool_name("ctrl"), Some(Key::ControlLeft));
+        assert_eq!(from_xdotool_name("return"), Some(Key::Enter));
+        assert_eq!(from_ahk_name("esc"), Some(Key::Escape));
+        assert_eq!(from_ahk_name("Bogus"), None);
+    }
+}
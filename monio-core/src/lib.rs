@@ -0,0 +1,41 @@
+//! The platform-independent data model of [monio](https://docs.rs/monio):
+//! keys, mouse buttons, event kinds and payloads, and the button/modifier
+//! mask.
+//!
+//! `monio` re-exports everything here unchanged, so applications using it
+//! don't need this crate. It is for code that handles monio's events without
+//! capturing input itself, such as a WASM dashboard reading recorded events
+//! or firmware bridging keys to another device.
+//!
+//! # Features
+//!
+//! - `std` (default): link the standard library. Without it the crate is
+//!   `no_std` and only needs `alloc`.
+//! - `serde`: serde derives, with the same representation `monio` saves.
+//!
+//! # Example
+//!
+//! ```
+//! use monio_core::{Button, Key, mask};
+//!
+//! assert_eq!(Key::from_char('a'), Key::KeyA);
+//!
+//! let held = mask::MASK_BUTTON1 | mask::MASK_SHIFT;
+//! assert_eq!(mask::buttons_in_mask(held), [Button::Left]);
+//! assert!(mask::Modifiers::from_mask(held).shift);
+//! ```
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod event;
+pub mod keycode;
+pub mod mask;
+
+pub use event::{
+    Backend, Button, DisplayChange, EventType, MouseData, ScrollDirection, ScrollPhase, TouchData,
+    WheelData,
+};
+pub use keycode::{Key, ParseKeyError};
+pub use mask::Modifiers;
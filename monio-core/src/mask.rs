@@ -0,0 +1,133 @@
+//! Button and modifier masks.
+//!
+//! Events carry the buttons and modifiers held when they occurred as a bit
+//! mask (`monio::Event::mask`); these are its bits and helpers to decode it.
+
+use crate::event::Button;
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Button masks (matches libumonio conventions)
+/// Left mouse button mask.
+pub const MASK_BUTTON1: u32 = 1 << 8;
+/// Right mouse button mask.
+pub const MASK_BUTTON2: u32 = 1 << 9;
+/// Middle mouse button mask.
+pub const MASK_BUTTON3: u32 = 1 << 10;
+/// Extra button 1 (X1) mask.
+pub const MASK_BUTTON4: u32 = 1 << 11;
+/// Extra button 2 (X2) mask.
+pub const MASK_BUTTON5: u32 = 1 << 12;
+
+// Keyboard modifier masks
+/// Shift key mask.
+pub const MASK_SHIFT: u32 = 1 << 0;
+/// Control key mask.
+pub const MASK_CTRL: u32 = 1 << 1;
+/// Alt/Option key mask.
+pub const MASK_ALT: u32 = 1 << 2;
+/// Meta/Command/Windows key mask.
+pub const MASK_META: u32 = 1 << 3;
+/// Caps Lock mask.
+pub const MASK_CAPS_LOCK: u32 = 1 << 4;
+/// Num Lock mask.
+pub const MASK_NUM_LOCK: u32 = 1 << 5;
+/// Scroll Lock mask.
+pub const MASK_SCROLL_LOCK: u32 = 1 << 6;
+
+/// All button masks combined.
+pub const MASK_ALL_BUTTONS: u32 =
+    MASK_BUTTON1 | MASK_BUTTON2 | MASK_BUTTON3 | MASK_BUTTON4 | MASK_BUTTON5;
+
+/// All modifier masks combined.
+pub const MASK_ALL_MODIFIERS: u32 = MASK_SHIFT
+    | MASK_CTRL
+    | MASK_ALT
+    | MASK_META
+    | MASK_CAPS_LOCK
+    | MASK_NUM_LOCK
+    | MASK_SCROLL_LOCK;
+
+/// Held keyboard modifiers, decoded from a mask.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Modifiers {
+    /// Shift is held.
+    pub shift: bool,
+    /// Control is held.
+    pub ctrl: bool,
+    /// Alt/Option is held.
+    pub alt: bool,
+    /// Meta/Command/Windows is held.
+    pub meta: bool,
+}
+
+impl Modifiers {
+    /// Decode the modifiers held in a mask (e.g. `Event::mask`).
+    pub fn from_mask(mask: u32) -> Self {
+        Self {
+            shift: mask & MASK_SHIFT != 0,
+            ctrl: mask & MASK_CTRL != 0,
+            alt: mask & MASK_ALT != 0,
+            meta: mask & MASK_META != 0,
+        }
+    }
+
+    /// Whether no modifier is held.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Get the button mask for a button number (1-indexed).
+pub fn button_to_mask(button_num: u8) -> u32 {
+    match button_num {
+        1 => MASK_BUTTON1,
+        2 => MASK_BUTTON2,
+        3 => MASK_BUTTON3,
+        4 => MASK_BUTTON4,
+        5 => MASK_BUTTON5,
+        _ => 0,
+    }
+}
+
+/// The mouse buttons held in a mask, ordered Left, Right, Middle, Button4, Button5.
+pub fn buttons_in_mask(mask: u32) -> Vec<Button> {
+    [
+        (MASK_BUTTON1, Button::Left),
+        (MASK_BUTTON2, Button::Right),
+        (MASK_BUTTON3, Button::Middle),
+        (MASK_BUTTON4, Button::Button4),
+        (MASK_BUTTON5, Button::Button5),
+    ]
+    .into_iter()
+    .filter(|(button_mask, _)| mask & button_mask != 0)
+    .map(|(_, button)| button)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_button_to_mask() {
+        assert_eq!(button_to_mask(1), MASK_BUTTON1);
+        assert_eq!(button_to_mask(2), MASK_BUTTON2);
+        assert_eq!(button_to_mask(3), MASK_BUTTON3);
+        assert_eq!(button_to_mask(4), MASK_BUTTON4);
+        assert_eq!(button_to_mask(5), MASK_BUTTON5);
+        assert_eq!(button_to_mask(6), 0);
+    }
+
+    #[test]
+    fn test_buttons_in_mask() {
+        assert!(buttons_in_mask(MASK_SHIFT).is_empty());
+        assert_eq!(
+            buttons_in_mask(MASK_BUTTON3 | MASK_BUTTON1 | MASK_CTRL),
+            vec![Button::Left, Button::Middle]
+        );
+    }
+}
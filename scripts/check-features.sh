@@ -0,0 +1,36 @@
+#!/usr/bin/env bash
+# Check that the crate builds with each feature combination that matters,
+# so cfg mistakes (serde derives, recorder-only code) don't slip through.
+#
+# Usage: scripts/check-features.sh [extra cargo args, e.g. --offline]
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+combos=(
+    ""
+    "serde"
+    "recorder"
+    "recorder,serde"
+    "statistics"
+    "statistics,serde"
+    "statistics,recorder"
+    "sqlite"
+    "overlay"
+    "tokio"
+    "ipc"
+    # x11 takes over as the backend, leaving evdev's surface unused
+    "x11"
+)
+
+# A Linux backend, so platform code is checked too
+backend=""
+if [[ "$(uname -s)" == "Linux" ]]; then
+    backend="evdev"
+fi
+
+for features in "${combos[@]}"; do
+    all="$(printf '%s\n' "$backend" "$features" | grep -v '^$' | paste -sd, -)"
+    echo "==> --no-default-features --features '$all'"
+    cargo check --lib --tests --no-default-features --features "$all" "$@"
+done
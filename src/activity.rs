@@ -0,0 +1,341 @@
+//! Input activity, for an indicator that blinks on input.
+//!
+//! [`on_activity`] calls back at most once per debounce window with the
+//! kinds of input seen, and does little else: its hook only registers for
+//! keys, buttons, motion and the wheel, and the hook thread just sets bits
+//! for what it sees. A tray app's "activity LED" needs nothing more.
+//!
+//! The first input after a quiet spell calls back right away. Input during
+//! the window that follows is gathered into one more callback when the
+//! window ends. Motion is sampled: once a window has seen motion, further
+//! moves in it cost an atomic load. The first input of a window also wakes
+//! the callback thread, a few microseconds.
+//!
+//! [`ActivityHandle::stats`] says how much time the hook thread spent on
+//! activity, per event; `examples/activity.rs` prints it.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::{ActivityKind, on_activity};
+//! use std::time::Duration;
+//!
+//! let handle = on_activity(Duration::from_millis(100), |kinds| {
+//!     if kinds.contains(ActivityKind::KEY) {
+//!         println!("typing");
+//!     }
+//! })?;
+//! # handle.stop()?;
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::event::{Event, EventType};
+use crate::hook::{EventMask, Hook};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Kinds of input, as a set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ActivityKind(u8);
+
+impl ActivityKind {
+    /// A key press.
+    pub const KEY: Self = Self(1);
+    /// A mouse button press.
+    pub const BUTTON: Self = Self(1 << 1);
+    /// Mouse motion, including drags.
+    pub const MOTION: Self = Self(1 << 2);
+    /// A wheel scroll.
+    pub const WHEEL: Self = Self(1 << 3);
+
+    /// The kind of `event`, if it counts as activity. Releases don't, as
+    /// they follow a press.
+    pub fn of(event: &Event) -> Option<Self> {
+        match event.event_type {
+            EventType::KeyPressed => Some(Self::KEY),
+            EventType::MousePressed => Some(Self::BUTTON),
+            EventType::MouseMoved | EventType::MouseDragged => Some(Self::MOTION),
+            EventType::MouseWheel => Some(Self::WHEEL),
+            _ => None,
+        }
+    }
+
+    /// Whether every kind in `other` is in the set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether the set is empty.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The set as bits, one per kind.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+impl std::ops::BitOr for ActivityKind {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for ActivityKind {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// What an [`ActivityHandle`] has done so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ActivityStats {
+    /// Events that counted as activity.
+    pub events: u64,
+    /// Times the callback was called.
+    pub callbacks: u64,
+    /// Time the hook thread spent noting activity, over all events.
+    pub hook_time: Duration,
+}
+
+impl ActivityStats {
+    /// Mean time the hook thread spent on an event, or `None` before any.
+    pub fn per_event(&self) -> Option<Duration> {
+        let nanos = self.hook_time.as_nanos().checked_div(self.events.into())?;
+        Some(Duration::from_nanos(nanos.try_into().unwrap_or(u64::MAX)))
+    }
+}
+
+/// Activity not yet reported, shared by the hook thread and the worker.
+#[derive(Default)]
+struct Pending {
+    kinds: AtomicU8,
+    events: AtomicU64,
+    callbacks: AtomicU64,
+    nanos: AtomicU64,
+}
+
+impl Pending {
+    /// Note `kind`, returning whether the worker must be woken: when it is
+    /// the first activity since the last callback.
+    fn note(&self, kind: ActivityKind) -> bool {
+        self.events.fetch_add(1, Ordering::Relaxed);
+        // Already noted in this window, as most motion is
+        if ActivityKind(self.kinds.load(Ordering::Relaxed)).contains(kind) {
+            return false;
+        }
+        self.kinds.fetch_or(kind.0, Ordering::AcqRel) == 0
+    }
+
+    fn take(&self) -> ActivityKind {
+        ActivityKind(self.kinds.swap(0, Ordering::AcqRel))
+    }
+}
+
+enum Message {
+    Wake,
+    Stop,
+}
+
+/// Call `callback` with the activity noted in `pending`: on the first
+/// activity, then at most once per `debounce`, until stopped.
+fn report<F>(pending: &Pending, debounce: Duration, messages: Receiver<Message>, mut callback: F)
+where
+    F: FnMut(ActivityKind),
+{
+    let mut window_end: Option<Instant> = None;
+    loop {
+        let message = match window_end {
+            Some(end) => messages.recv_timeout(end.saturating_duration_since(Instant::now())),
+            None => messages.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match message {
+            Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+            // Reported once the window ends
+            Ok(Message::Wake) if window_end.is_some() => continue,
+            Ok(Message::Wake) | Err(RecvTimeoutError::Timeout) => {}
+        }
+        let kinds = pending.take();
+        if kinds.is_empty() {
+            window_end = None;
+            continue;
+        }
+        pending.callbacks.fetch_add(1, Ordering::Relaxed);
+        callback(kinds);
+        window_end = Some(Instant::now() + debounce);
+    }
+}
+
+/// A running [`on_activity`] watch, stopped when dropped.
+pub struct ActivityHandle {
+    hook: Hook,
+    control: Sender<Message>,
+    worker: Option<JoinHandle<()>>,
+    pending: Arc<Pending>,
+}
+
+impl ActivityHandle {
+    /// Stop watching and wait for the last callback to return.
+    pub fn stop(mut self) -> Result<()> {
+        self.stop_inner()
+    }
+
+    /// What the watch has done so far.
+    pub fn stats(&self) -> ActivityStats {
+        ActivityStats {
+            events: self.pending.events.load(Ordering::Relaxed),
+            callbacks: self.pending.callbacks.load(Ordering::Relaxed),
+            hook_time: Duration::from_nanos(self.pending.nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn stop_inner(&mut self) -> Result<()> {
+        let stopped = match self.hook.stop() {
+            Ok(()) | Err(Error::NotRunning) => Ok(()),
+            Err(e) => Err(e),
+        };
+        let _ = self.control.send(Message::Stop);
+        if let Some(worker) = self.worker.take() {
+            worker
+                .join()
+                .map_err(|_| Error::ThreadError("activity callback panicked".into()))?;
+        }
+        stopped
+    }
+}
+
+impl Drop for ActivityHandle {
+    fn drop(&mut self) {
+        let _ = self.stop_inner();
+    }
+}
+
+impl std::fmt::Debug for ActivityHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActivityHandle")
+            .field("running", &self.hook.is_running())
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Call `callback` with the kinds of input seen, at most once per
+/// `debounce`.
+///
+/// The callback runs on a thread of its own, so it may block without
+/// holding up input. See the [module documentation](self) for when it is
+/// called.
+pub fn on_activity<F>(debounce: Duration, callback: F) -> Result<ActivityHandle>
+where
+    F: FnMut(ActivityKind) + Send + 'static,
+{
+    let (control, messages) = mpsc::channel();
+    let pending = Arc::new(Pending::default());
+    let worker = {
+        let pending = pending.clone();
+        thread::Builder::new()
+            .name("monio-activity".into())
+            .spawn(move || report(&pending, debounce, messages, callback))
+            .map_err(|e| Error::ThreadError(format!("failed to spawn activity thread: {}", e)))?
+    };
+
+    let hook = Hook::new().events(
+        EventMask::KEYBOARD
+            | EventMask::MOUSE_BUTTONS
+            | EventMask::MOUSE_MOTION
+            | EventMask::MOUSE_WHEEL,
+    );
+    let handle = ActivityHandle {
+        hook,
+        control: control.clone(),
+        worker: Some(worker),
+        pending: pending.clone(),
+    };
+    handle.hook.run_async(move |event: &Event| {
+        let Some(kind) = ActivityKind::of(event) else {
+            return;
+        };
+        let started = Instant::now();
+        if pending.note(kind) {
+            let _ = control.send(Message::Wake);
+        }
+        let nanos = started.elapsed().as_nanos().try_into().unwrap_or(u64::MAX);
+        pending.nanos.fetch_add(nanos, Ordering::Relaxed);
+    })?;
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Button;
+    use crate::keycode::Key;
+
+    #[test]
+    fn test_kinds_of_events() {
+        assert_eq!(
+            ActivityKind::of(&Event::key_pressed(Key::KeyA, 0)),
+            Some(ActivityKind::KEY)
+        );
+        assert_eq!(ActivityKind::of(&Event::key_released(Key::KeyA, 0)), None);
+        assert_eq!(
+            ActivityKind::of(&Event::mouse_pressed(Button::Left, 0.0, 0.0)),
+            Some(ActivityKind::BUTTON)
+        );
+        assert_eq!(ActivityKind::of(&Event::hook_enabled()), None);
+
+        let both = ActivityKind::KEY | ActivityKind::MOTION;
+        assert!(both.contains(ActivityKind::MOTION));
+        assert!(!both.contains(ActivityKind::KEY | ActivityKind::WHEEL));
+        assert!(ActivityKind::default().is_empty());
+    }
+
+    #[test]
+    fn test_reports_once_per_window() {
+        let debounce = Duration::from_millis(200);
+        let pending = Arc::new(Pending::default());
+        let (control, messages) = mpsc::channel();
+        let (reports, reported) = mpsc::channel();
+        let worker = {
+            let pending = pending.clone();
+            thread::spawn(move || {
+                report(&pending, debounce, messages, |kinds| {
+                    reports.send((kinds, Instant::now())).unwrap();
+                })
+            })
+        };
+        let note = |kind| {
+            if pending.note(kind) {
+                control.send(Message::Wake).unwrap();
+            }
+        };
+
+        // The first input is reported right away
+        note(ActivityKind::KEY);
+        let (kinds, first) = reported.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(kinds, ActivityKind::KEY);
+
+        // The rest of the window is gathered into one report at its end
+        for _ in 0..100 {
+            note(ActivityKind::MOTION);
+        }
+        note(ActivityKind::BUTTON);
+        let (kinds, second) = reported.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(kinds, ActivityKind::MOTION | ActivityKind::BUTTON);
+        assert!(second.duration_since(first) >= debounce);
+
+        // Then nothing until there is input again
+        assert!(reported.recv_timeout(debounce * 2).is_err());
+        control.send(Message::Stop).unwrap();
+        worker.join().unwrap();
+        assert_eq!(pending.events.load(Ordering::Relaxed), 102);
+        assert_eq!(pending.callbacks.load(Ordering::Relaxed), 2);
+    }
+}
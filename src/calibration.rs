@@ -0,0 +1,382 @@
+//! Calibration for absolute input devices (touchscreens, tablets).
+//!
+//! An [`InputTransform`] is a 2x3 affine matrix applied to a device's
+//! positions, like X11's "Coordinate Transformation Matrix" and libinput's
+//! calibration matrix. It works in normalized coordinates: the device's
+//! axis ranges map to `0.0..=1.0`, and so does the desktop (the bounding box
+//! of all displays). A position `(x, y)` becomes
+//!
+//! ```text
+//! x' = m[0] * x + m[1] * y + m[2]
+//! y' = m[3] * x + m[4] * y + m[5]
+//! ```
+//!
+//! Transforms are set per device with
+//! [`Hook::input_transform`](crate::Hook::input_transform) and are only
+//! applied by the evdev backend; the other platforms calibrate touch input
+//! themselves.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::Hook;
+//! use monio::calibration::InputTransform;
+//!
+//! // A touchscreen mounted upside down
+//! let hook = Hook::new().input_transform(
+//!     "ELAN Touchscreen",
+//!     InputTransform::rotate_90().then(&InputTransform::rotate_90()),
+//! );
+//! ```
+
+use crate::display::Rect;
+#[cfg(feature = "serde")]
+use crate::error::{Error, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+/// An `(expected, observed)` pair of normalized positions, for
+/// [`InputTransform::calibrate`].
+pub type CalibrationPair = ((f64, f64), (f64, f64));
+
+/// An affine transform of normalized device coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InputTransform {
+    /// Row-major `[a, b, c, d, e, f]`: `x' = ax + by + c`, `y' = dx + ey + f`.
+    pub matrix: [f64; 6],
+}
+
+impl InputTransform {
+    /// The transform that leaves positions unchanged.
+    pub const IDENTITY: Self = Self {
+        matrix: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    };
+
+    /// Create a transform from a row-major matrix.
+    pub fn new(matrix: [f64; 6]) -> Self {
+        Self { matrix }
+    }
+
+    /// Rotate a quarter turn clockwise, for a screen rotated the same way
+    /// relative to its touch panel.
+    pub fn rotate_90() -> Self {
+        Self::new([0.0, -1.0, 1.0, 1.0, 0.0, 0.0])
+    }
+
+    /// Mirror horizontally.
+    pub fn flip_x() -> Self {
+        Self::new([-1.0, 0.0, 1.0, 0.0, 1.0, 0.0])
+    }
+
+    /// Mirror vertically.
+    pub fn flip_y() -> Self {
+        Self::new([1.0, 0.0, 0.0, 0.0, -1.0, 1.0])
+    }
+
+    /// Swap the X and Y axes.
+    pub fn swap_axes() -> Self {
+        Self::new([0.0, 1.0, 0.0, 1.0, 0.0, 0.0])
+    }
+
+    /// Map the whole device onto `display`, one of the displays making up
+    /// `desktop` (their bounding box), instead of the entire desktop.
+    pub fn scale_to(display: Rect, desktop: Rect) -> Self {
+        let sx = if desktop.width > 0.0 {
+            display.width / desktop.width
+        } else {
+            1.0
+        };
+        let sy = if desktop.height > 0.0 {
+            display.height / desktop.height
+        } else {
+            1.0
+        };
+        let tx = if desktop.width > 0.0 {
+            (display.x - desktop.x) / desktop.width
+        } else {
+            0.0
+        };
+        let ty = if desktop.height > 0.0 {
+            (display.y - desktop.y) / desktop.height
+        } else {
+            0.0
+        };
+        Self::new([sx, 0.0, tx, 0.0, sy, ty])
+    }
+
+    /// This transform followed by `next`.
+    pub fn then(&self, next: &InputTransform) -> Self {
+        let [a, b, c, d, e, f] = self.matrix;
+        let [p, q, r, s, t, u] = next.matrix;
+        Self::new([
+            p * a + q * d,
+            p * b + q * e,
+            p * c + q * f + r,
+            s * a + t * d,
+            s * b + t * e,
+            s * c + t * f + u,
+        ])
+    }
+
+    /// Transform a normalized position.
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.matrix;
+        (a * x + b * y + c, d * x + e * y + f)
+    }
+
+    /// Compute the transform that best maps `observed` positions onto
+    /// `expected` ones (least squares), from `(expected, observed)` pairs in
+    /// normalized coordinates.
+    ///
+    /// Show targets at known positions (e.g. near the four corners), record
+    /// where the device reports each tap, and pass both here. Needs at least
+    /// three pairs that aren't on one line; returns `None` otherwise.
+    pub fn calibrate(pairs: &[CalibrationPair]) -> Option<Self> {
+        if pairs.len() < 3 {
+            return None;
+        }
+
+        // Normal equations: (AᵀA) m = Aᵀb, with rows [ox, oy, 1]
+        let mut ata = [[0.0; 3]; 3];
+        let mut atx = [0.0; 3];
+        let mut aty = [0.0; 3];
+        for &((ex, ey), (ox, oy)) in pairs {
+            let row = [ox, oy, 1.0];
+            for i in 0..3 {
+                for j in 0..3 {
+                    ata[i][j] += row[i] * row[j];
+                }
+                atx[i] += row[i] * ex;
+                aty[i] += row[i] * ey;
+            }
+        }
+
+        let [a, b, c] = solve3(ata, atx)?;
+        let [d, e, f] = solve3(ata, aty)?;
+        Some(Self::new([a, b, c, d, e, f]))
+    }
+}
+
+impl Default for InputTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Solve a 3x3 linear system by Cramer's rule, `None` if it is singular.
+fn solve3(m: [[f64; 3]; 3], rhs: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |m: &[[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(&m);
+    if d.abs() < 1e-12 {
+        return None;
+    }
+
+    let mut solution = [0.0; 3];
+    for (col, value) in solution.iter_mut().enumerate() {
+        let mut replaced = m;
+        for row in 0..3 {
+            replaced[row][col] = rhs[row];
+        }
+        *value = det(&replaced) / d;
+    }
+    Some(solution)
+}
+
+/// A transform for the devices matching `device`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceTransform {
+    /// The device name it reports, or its node (e.g. `/dev/input/event5`).
+    pub device: String,
+    /// The transform to apply.
+    pub transform: InputTransform,
+}
+
+impl DeviceTransform {
+    /// Whether this applies to the device with `name` at `path`.
+    pub fn matches(&self, name: &str, path: &str) -> bool {
+        self.device == name || self.device == path
+    }
+}
+
+/// Stored calibration for a set of devices.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CalibrationConfig {
+    /// Per-device transforms; the first match wins.
+    pub devices: Vec<DeviceTransform>,
+}
+
+impl CalibrationConfig {
+    /// Create an empty configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the transform for `device`, replacing any earlier one.
+    pub fn set(&mut self, device: impl Into<String>, transform: InputTransform) {
+        let device = device.into();
+        self.devices.retain(|d| d.device != device);
+        self.devices.push(DeviceTransform { device, transform });
+    }
+
+    /// The transform for the device with `name` at `path`, if any.
+    pub fn find(&self, name: &str, path: &str) -> Option<InputTransform> {
+        self.devices
+            .iter()
+            .find(|d| d.matches(name, path))
+            .map(|d| d.transform)
+    }
+
+    /// Save the configuration to a file (JSON format).
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Other(format!("Failed to serialize calibration: {}", e)))?;
+        std::fs::write(path, json)
+            .map_err(|e| Error::Other(format!("Failed to write calibration file: {}", e)))?;
+        Ok(())
+    }
+
+    /// Load a configuration from a file (JSON format).
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| Error::Other(format!("Failed to read calibration file: {}", e)))?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::Other(format!("Failed to deserialize calibration: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_helpers_map_corners() {
+        // Top-left of the panel goes to the top-right after a clockwise turn
+        assert_close(InputTransform::rotate_90().apply(0.0, 0.0), (1.0, 0.0));
+        assert_close(InputTransform::rotate_90().apply(1.0, 0.0), (1.0, 1.0));
+        assert_close(InputTransform::flip_x().apply(0.25, 0.5), (0.75, 0.5));
+        assert_close(InputTransform::flip_y().apply(0.25, 0.5), (0.25, 0.5));
+        assert_close(InputTransform::swap_axes().apply(0.25, 0.5), (0.5, 0.25));
+
+        let half_turn = InputTransform::rotate_90().then(&InputTransform::rotate_90());
+        assert_close(half_turn.apply(0.0, 0.0), (1.0, 1.0));
+        let full_turn = half_turn.then(&half_turn);
+        assert_close(full_turn.apply(0.3, 0.7), (0.3, 0.7));
+    }
+
+    #[test]
+    fn test_scale_to_second_display() {
+        let desktop = Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 3840.0,
+            height: 1080.0,
+        };
+        let right = Rect {
+            x: 1920.0,
+            y: 0.0,
+            width: 1920.0,
+            height: 1080.0,
+        };
+        let transform = InputTransform::scale_to(right, desktop);
+        assert_close(transform.apply(0.0, 0.0), (0.5, 0.0));
+        assert_close(transform.apply(1.0, 1.0), (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_calibrate_recovers_transform() {
+        // A panel that is rotated, slightly shrunk and offset
+        let actual = InputTransform::rotate_90()
+            .then(&InputTransform::new([0.9, 0.0, 0.05, 0.0, 0.95, 0.02]));
+        let targets = [(0.1, 0.1), (0.9, 0.1), (0.9, 0.9), (0.1, 0.9)];
+
+        // The device reports the position that transforms onto each target
+        let inverse = |(x, y): (f64, f64)| {
+            let (x, y) = ((x - 0.05) / 0.9, (y - 0.02) / 0.95);
+            (y, 1.0 - x)
+        };
+        let pairs: Vec<_> = targets.iter().map(|&t| (t, inverse(t))).collect();
+
+        let calibrated = InputTransform::calibrate(&pairs).unwrap();
+        for (got, want) in calibrated.matrix.iter().zip(actual.matrix) {
+            assert!((got - want).abs() < 1e-9, "{:?}", calibrated);
+        }
+        for &(expected, observed) in &pairs {
+            assert_close(calibrated.apply(observed.0, observed.1), expected);
+        }
+    }
+
+    #[test]
+    fn test_calibrate_least_squares_and_degenerate() {
+        // Noisy taps around an identity mapping average out
+        let pairs = [
+            ((0.1, 0.1), (0.11, 0.1)),
+            ((0.9, 0.1), (0.89, 0.1)),
+            ((0.9, 0.9), (0.91, 0.9)),
+            ((0.1, 0.9), (0.09, 0.9)),
+        ];
+        let calibrated = InputTransform::calibrate(&pairs).unwrap();
+        let (x, y) = calibrated.apply(0.5, 0.5);
+        assert!((x - 0.5).abs() < 0.01 && (y - 0.5).abs() < 1e-9);
+
+        // Collinear or too few points
+        let line = [
+            ((0.1, 0.1), (0.1, 0.1)),
+            ((0.5, 0.5), (0.5, 0.5)),
+            ((0.9, 0.9), (0.9, 0.9)),
+        ];
+        assert_eq!(InputTransform::calibrate(&line), None);
+        assert_eq!(InputTransform::calibrate(&pairs[..2]), None);
+    }
+
+    #[test]
+    fn test_config_lookup() {
+        let mut config = CalibrationConfig::new();
+        config.set("ELAN Touchscreen", InputTransform::flip_x());
+        config.set("/dev/input/event7", InputTransform::flip_y());
+        config.set("ELAN Touchscreen", InputTransform::rotate_90());
+
+        assert_eq!(config.devices.len(), 2);
+        assert_eq!(
+            config.find("ELAN Touchscreen", "/dev/input/event3"),
+            Some(InputTransform::rotate_90())
+        );
+        assert_eq!(
+            config.find("Pen", "/dev/input/event7"),
+            Some(InputTransform::flip_y())
+        );
+        assert_eq!(config.find("Keyboard", "/dev/input/event0"), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_save_load() {
+        let path = std::env::temp_dir().join("monio_test_calibration.json");
+        let mut config = CalibrationConfig::new();
+        config.set("ELAN Touchscreen", InputTransform::rotate_90());
+
+        config.save(&path).unwrap();
+        let loaded = CalibrationConfig::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded, config);
+    }
+}
@@ -0,0 +1,87 @@
+//! What the active input backend can do, see [`capabilities`].
+//!
+//! Backends differ in more than their OS: X11 and evdev both run on Linux
+//! but only evdev can grab. Branching on [`Capabilities`] instead of
+//! `cfg(target_os)` keeps apps right as backends are added or improve.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::{Event, capabilities, grab, listen};
+//!
+//! let caps = capabilities();
+//! if caps.can_grab {
+//!     grab(|event: &Event| Some(event.clone())).unwrap();
+//! } else {
+//!     println!("{} can't grab, listening instead", caps.backend);
+//!     listen(|_: &Event| {}).unwrap();
+//! }
+//! ```
+
+use crate::display::DisplaySource;
+use crate::platform;
+
+/// What the active input backend can do, from [`capabilities`].
+///
+/// For a grab that is already running, which may also have been
+/// [denied by policy](crate::policy), see
+/// [`Hook::grab_capabilities`](crate::Hook::grab_capabilities).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The input backend, such as `"evdev"` or `"x11"`; `"none"` on Linux
+    /// without a backend feature.
+    pub backend: &'static str,
+    /// monio version providing the backend.
+    pub version: &'static str,
+    /// Whether grab mode can keep events from reaching other applications.
+    /// If not, grabs run as listeners.
+    pub can_grab: bool,
+    /// Whether a grab handler's verdict applies to each event on its own,
+    /// rather than to the whole device.
+    pub can_consume_selectively: bool,
+    /// Whether event positions and
+    /// [`mouse_position`](crate::mouse_position) are absolute screen
+    /// coordinates. Without it, positions are built up from relative motion.
+    pub absolute_mouse_position: bool,
+    /// Whether the backend reads each input device on its own, so it can
+    /// list and grab them separately.
+    pub per_device_events: bool,
+    /// Whether [`Key::Char`](crate::Key::Char) keys that no key on the
+    /// active layout produces can be simulated.
+    pub can_simulate_unicode: bool,
+    /// Where [`displays`](crate::displays) come from, `None` if the backend
+    /// can't list them.
+    pub display_info_quality: Option<DisplaySource>,
+}
+
+/// What the active input backend can do.
+///
+/// Fixed for the life of the process: it depends on the OS and the backend
+/// features monio was built with, not on permissions or policy.
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        backend: platform::BACKEND,
+        version: env!("CARGO_PKG_VERSION"),
+        can_grab: platform::GRAB_SUPPRESSES,
+        // Every backend that can grab decides per event
+        can_consume_selectively: platform::GRAB_SUPPRESSES,
+        absolute_mouse_position: platform::ABSOLUTE_POSITION,
+        per_device_events: platform::PER_DEVICE,
+        can_simulate_unicode: platform::SIMULATES_UNICODE,
+        display_info_quality: platform::DISPLAY_SOURCE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_grab_capabilities() {
+        let caps = capabilities();
+        let grab = crate::Hook::new().grab_capabilities();
+        assert_eq!(caps.backend, grab.backend);
+        assert_eq!(caps.can_grab, grab.can_consume);
+        assert!(caps.can_grab || !caps.can_consume_selectively);
+    }
+}
@@ -4,6 +4,10 @@
 //! allowing you to receive events in the background and process them
 //! asynchronously in your main application.
 //!
+//! The last event on every channel is `HookDisabled`, after which the
+//! channel disconnects. A channel that disconnects without it means the hook
+//! thread died.
+//!
 //! # Example (Sync)
 //!
 //! ```no_run
@@ -42,13 +46,37 @@
 //! ```
 
 use crate::error::{Error, Result};
-use crate::event::Event;
-use crate::hook::{EventHandler, GrabHandler};
-use crate::platform;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use crate::event::{Event, EventType};
+use crate::fanout::{self, Config};
+use crate::hook::{
+    EventHandler, EventMask, GrabHandler, LifecycleTimes, LifecycleTracker, request_stop,
+    set_paused,
+};
+use crate::intercept::GrabCapabilities;
+use crate::policy;
+use crate::shutdown::{Registration, ShutdownToken};
+use crate::suspend::SuspendGate;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvError, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Receiving events along with how long they took to arrive.
+pub trait RecvWithLatency {
+    /// Like [`Receiver::recv`], but sets [`Event::queue_latency`] to the
+    /// time from the event happening until now, including the time it
+    /// waited in the channel. Left `None` where latency isn't known.
+    fn recv_with_latency(&self) -> std::result::Result<Event, RecvError>;
+}
+
+impl RecvWithLatency for Receiver<Event> {
+    fn recv_with_latency(&self) -> std::result::Result<Event, RecvError> {
+        let mut event = self.recv()?;
+        event.queue_latency = crate::latency::latency(event.time, SystemTime::now());
+        Ok(event)
+    }
+}
 
 /// Handle to control a channel-based hook.
 ///
@@ -57,6 +85,12 @@ use std::thread::{self, JoinHandle};
 pub struct ChannelHookHandle {
     running: Arc<AtomicBool>,
     thread_handle: Option<JoinHandle<()>>,
+    times: Arc<Mutex<LifecycleTimes>>,
+    paused: Arc<AtomicBool>,
+    grab_denied: bool,
+    grab_capabilities: Option<GrabCapabilities>,
+    registration: Option<Registration>,
+    dropped_events: Arc<AtomicU64>,
 }
 
 impl ChannelHookHandle {
@@ -70,13 +104,84 @@ impl ChannelHookHandle {
         self.running.load(Ordering::SeqCst)
     }
 
+    /// Stop sending events to the channel, keeping the hook installed.
+    ///
+    /// See [`Hook::pause`](crate::Hook::pause). Fails with
+    /// [`Error::NotRunning`] if the hook isn't running.
+    pub fn pause(&self) -> Result<()> {
+        set_paused(&self.running, &self.paused, true)
+    }
+
+    /// Send events to the channel again after [`pause`](Self::pause).
+    ///
+    /// Fails with [`Error::NotRunning`] if the hook isn't running.
+    pub fn resume(&self) -> Result<()> {
+        set_paused(&self.running, &self.paused, false)
+    }
+
+    /// Whether the hook is running but paused.
+    pub fn is_paused(&self) -> bool {
+        self.is_running() && self.paused.load(Ordering::SeqCst)
+    }
+
+    /// When the hook was enabled (the `HookEnabled` event was dispatched).
+    pub fn started_at(&self) -> Option<SystemTime> {
+        self.times.lock().ok()?.started_at
+    }
+
+    /// When the hook was disabled, if it has stopped.
+    pub fn stopped_at(&self) -> Option<SystemTime> {
+        self.times.lock().ok()?.stopped_at
+    }
+
+    /// How long the hook has been enabled. See [`Hook::uptime`](crate::Hook::uptime).
+    pub fn uptime(&self) -> Option<Duration> {
+        self.times.lock().ok()?.uptime()
+    }
+
+    /// Whether this grab runs as a listener because grabbing is denied by
+    /// [`policy`](crate::policy). Always `false` for listen hooks.
+    pub fn is_grab_denied(&self) -> bool {
+        self.grab_denied
+    }
+
+    /// What this grab can do, or `None` for listen hooks.
+    ///
+    /// If it can't consume, events the filter rejects still reach other
+    /// applications, and the channel gets an
+    /// [`EventType::GrabDegraded`] event after `HookEnabled`.
+    pub fn grab_capabilities(&self) -> Option<GrabCapabilities> {
+        self.grab_capabilities
+    }
+
+    /// How many events were dropped because the hook thread fell too far
+    /// behind, see [`Hook::dropped_events`](crate::Hook::dropped_events).
+    /// Events a full bounded channel turns away aren't counted. Always 0
+    /// for grab hooks.
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped_events.load(Ordering::Relaxed)
+    }
+
+    /// Stop the hook when `token` is cancelled, which closes the channel.
+    ///
+    /// If the token is already cancelled, the hook stops right away.
+    pub fn stop_on(mut self, token: &ShutdownToken) -> Self {
+        let running = self.running.clone();
+        let times = self.times.clone();
+        self.registration = Some(token.on_cancel(move || {
+            let _ = request_stop(&running, &times);
+        }));
+        self
+    }
+
     fn stop_inner(&mut self) -> Result<()> {
-        if !self.running.swap(false, Ordering::SeqCst) {
-            return Ok(()); // Already stopped
+        self.registration = None;
+        match request_stop(&self.running, &self.times) {
+            // Already stopped, e.g. by a shutdown token
+            Ok(()) | Err(Error::NotRunning) => {}
+            Err(e) => return Err(e),
         }
 
-        platform::stop_hook()?;
-
         if let Some(handle) = self.thread_handle.take() {
             handle
                 .join()
@@ -93,27 +198,84 @@ impl Drop for ChannelHookHandle {
     }
 }
 
-/// Handler that sends events to a bounded sync channel.
-struct ChannelHandler {
-    sender: SyncSender<Event>,
+/// How long the hook thread waits for room in a full channel to deliver a
+/// lifecycle event.
+const LIFECYCLE_SEND_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// The sending half of a channel fed by a hook.
+trait EventSender: Send {
+    /// Send `event` without blocking. Returns it back if the buffer is full.
+    fn try_send_event(&self, event: Event) -> Option<Event>;
 }
 
-impl EventHandler for ChannelHandler {
-    fn handle_event(&self, event: &Event) {
-        // Try to send, but don't block if the channel is full
-        // This prevents the hook from blocking input if the consumer is slow
-        let _ = self.sender.try_send(event.clone());
+impl EventSender for SyncSender<Event> {
+    fn try_send_event(&self, event: Event) -> Option<Event> {
+        match self.try_send(event) {
+            Err(mpsc::TrySendError::Full(event)) => Some(event),
+            _ => None,
+        }
     }
 }
 
-/// Handler that sends events to an unbounded sync channel.
-struct UnboundedChannelHandler {
-    sender: Sender<Event>,
+impl EventSender for Sender<Event> {
+    fn try_send_event(&self, event: Event) -> Option<Event> {
+        let _ = self.send(event);
+        None
+    }
+}
+
+/// Handler that sends events to a channel.
+///
+/// Input events are dropped when the buffer is full, so a slow consumer
+/// never blocks input. Lifecycle events wait a little for room instead, and
+/// the sender is dropped right after `HookDisabled`: consumers always see
+/// it before the channel disconnects, which tells a clean shutdown from a
+/// crashed hook thread.
+struct ChannelHandler<S> {
+    sender: Mutex<Option<S>>,
+}
+
+impl<S: EventSender> ChannelHandler<S> {
+    fn new(sender: S) -> Self {
+        Self {
+            sender: Mutex::new(Some(sender)),
+        }
+    }
+
+    fn send(&self, event: &Event) {
+        let mut guard = self.sender.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(sender) = guard.as_ref() else {
+            return;
+        };
+
+        let lifecycle = matches!(
+            event.event_type,
+            EventType::HookEnabled
+                | EventType::HookDisabled
+                | EventType::GrabSuspended
+                | EventType::GrabDegraded
+                | EventType::GlobalSuspendChanged
+        );
+        let mut pending = sender.try_send_event(event.clone());
+        if lifecycle {
+            let deadline = Instant::now() + LIFECYCLE_SEND_TIMEOUT;
+            while let Some(event) = pending
+                && Instant::now() < deadline
+            {
+                thread::sleep(Duration::from_millis(1));
+                pending = sender.try_send_event(event);
+            }
+        }
+
+        if event.event_type == EventType::HookDisabled {
+            *guard = None;
+        }
+    }
 }
 
-impl EventHandler for UnboundedChannelHandler {
+impl<S: EventSender> EventHandler for ChannelHandler<S> {
     fn handle_event(&self, event: &Event) {
-        let _ = self.sender.send(event.clone());
+        self.send(event);
     }
 }
 
@@ -125,7 +287,8 @@ impl EventHandler for UnboundedChannelHandler {
 /// # Arguments
 ///
 /// * `capacity` - Maximum number of events to buffer. If the buffer is full,
-///   new events are dropped to prevent blocking input.
+///   new events are dropped to prevent blocking input. Lifecycle events
+///   such as `HookDisabled` wait briefly for room instead.
 ///
 /// # Example
 ///
@@ -142,19 +305,39 @@ pub fn listen_channel(capacity: usize) -> Result<(ChannelHookHandle, Receiver<Ev
     let (sender, receiver) = mpsc::sync_channel(capacity);
     let running = Arc::new(AtomicBool::new(true));
     let running_clone = running.clone();
-
-    // Reset state before starting
-    crate::state::reset_mask();
+    let times = Arc::new(Mutex::new(LifecycleTimes::default()));
+    let times_clone = times.clone();
+    let paused = Arc::new(AtomicBool::new(false));
+    let paused_clone = paused.clone();
+    let dropped_events = Arc::new(AtomicU64::new(0));
+    let dropped_clone = dropped_events.clone();
 
     let thread_handle = thread::spawn(move || {
-        let handler = ChannelHandler { sender };
-        let _ = platform::run_hook(&running_clone, handler);
+        let handler = LifecycleTracker {
+            times: times_clone,
+            paused: paused_clone,
+            suspend: SuspendGate::new(false),
+            handler: ChannelHandler::new(sender),
+        };
+        let _ = fanout::listen(
+            &running_clone,
+            EventMask::ALL,
+            Config::default(),
+            dropped_clone,
+            handler,
+        );
         running_clone.store(false, Ordering::SeqCst);
     });
 
     let handle = ChannelHookHandle {
         running,
         thread_handle: Some(thread_handle),
+        times,
+        paused,
+        grab_denied: false,
+        grab_capabilities: None,
+        registration: None,
+        dropped_events,
     };
 
     Ok((handle, receiver))
@@ -181,40 +364,61 @@ pub fn listen_unbounded_channel() -> Result<(ChannelHookHandle, Receiver<Event>)
     let (sender, receiver) = mpsc::channel();
     let running = Arc::new(AtomicBool::new(true));
     let running_clone = running.clone();
-
-    // Reset state before starting
-    crate::state::reset_mask();
+    let times = Arc::new(Mutex::new(LifecycleTimes::default()));
+    let times_clone = times.clone();
+    let paused = Arc::new(AtomicBool::new(false));
+    let paused_clone = paused.clone();
+    let dropped_events = Arc::new(AtomicU64::new(0));
+    let dropped_clone = dropped_events.clone();
 
     let thread_handle = thread::spawn(move || {
-        let handler = UnboundedChannelHandler { sender };
-        let _ = platform::run_hook(&running_clone, handler);
+        let handler = LifecycleTracker {
+            times: times_clone,
+            paused: paused_clone,
+            suspend: SuspendGate::new(false),
+            handler: ChannelHandler::new(sender),
+        };
+        let _ = fanout::listen(
+            &running_clone,
+            EventMask::ALL,
+            Config::default(),
+            dropped_clone,
+            handler,
+        );
         running_clone.store(false, Ordering::SeqCst);
     });
 
     let handle = ChannelHookHandle {
         running,
         thread_handle: Some(thread_handle),
+        times,
+        paused,
+        grab_denied: false,
+        grab_capabilities: None,
+        registration: None,
+        dropped_events,
     };
 
     Ok((handle, receiver))
 }
 
 /// Handler for grab mode with a filter function and channel.
-struct GrabChannelHandler<F>
+struct GrabChannelHandler<S, F>
 where
     F: Fn(&Event) -> bool + Send + Sync,
 {
-    sender: SyncSender<Event>,
+    channel: ChannelHandler<S>,
     filter: F,
 }
 
-impl<F> GrabHandler for GrabChannelHandler<F>
+impl<S, F> GrabHandler for GrabChannelHandler<S, F>
 where
+    S: EventSender,
     F: Fn(&Event) -> bool + Send + Sync,
 {
     fn handle_event(&self, event: &Event) -> Option<Event> {
         // Send event to channel regardless of filter result
-        let _ = self.sender.try_send(event.clone());
+        self.channel.send(event);
 
         // Filter decides whether to pass through or consume
         if (self.filter)(event) {
@@ -265,19 +469,38 @@ where
     let (sender, receiver) = mpsc::sync_channel(capacity);
     let running = Arc::new(AtomicBool::new(true));
     let running_clone = running.clone();
+    let times = Arc::new(Mutex::new(LifecycleTimes::default()));
+    let times_clone = times.clone();
+    let paused = Arc::new(AtomicBool::new(false));
+    let paused_clone = paused.clone();
 
     // Reset state before starting
     crate::state::reset_mask();
+    let grab_denied = policy::grab_denied();
 
     let thread_handle = thread::spawn(move || {
-        let handler = GrabChannelHandler { sender, filter };
-        let _ = platform::run_grab_hook(&running_clone, handler);
+        let handler = LifecycleTracker {
+            times: times_clone,
+            paused: paused_clone,
+            suspend: SuspendGate::new(false),
+            handler: GrabChannelHandler {
+                channel: ChannelHandler::new(sender),
+                filter,
+            },
+        };
+        let _ = policy::run_grab_hook(&running_clone, handler, grab_denied);
         running_clone.store(false, Ordering::SeqCst);
     });
 
     let handle = ChannelHookHandle {
         running,
         thread_handle: Some(thread_handle),
+        times,
+        paused,
+        grab_denied,
+        grab_capabilities: Some(GrabCapabilities::new(grab_denied)),
+        registration: None,
+        dropped_events: Arc::default(),
     };
 
     Ok((handle, receiver))
@@ -295,15 +518,13 @@ mod tokio_channel {
     use super::*;
     use tokio::sync::mpsc as tokio_mpsc;
 
-    /// Handler that sends events to a tokio async channel.
-    struct TokioChannelHandler {
-        sender: tokio_mpsc::Sender<Event>,
-    }
-
-    impl EventHandler for TokioChannelHandler {
-        fn handle_event(&self, event: &Event) {
+    impl EventSender for tokio_mpsc::Sender<Event> {
+        fn try_send_event(&self, event: Event) -> Option<Event> {
             // Use try_send to avoid blocking the hook thread
-            let _ = self.sender.try_send(event.clone());
+            match self.try_send(event) {
+                Err(tokio_mpsc::error::TrySendError::Full(event)) => Some(event),
+                _ => None,
+            }
         }
     }
 
@@ -336,48 +557,44 @@ mod tokio_channel {
         let (sender, receiver) = tokio_mpsc::channel(capacity);
         let running = Arc::new(AtomicBool::new(true));
         let running_clone = running.clone();
-
-        // Reset state before starting
-        crate::state::reset_mask();
+        let times = Arc::new(Mutex::new(LifecycleTimes::default()));
+        let times_clone = times.clone();
+        let paused = Arc::new(AtomicBool::new(false));
+        let paused_clone = paused.clone();
+        let dropped_events = Arc::new(AtomicU64::new(0));
+        let dropped_clone = dropped_events.clone();
 
         let thread_handle = thread::spawn(move || {
-            let handler = TokioChannelHandler { sender };
-            let _ = platform::run_hook(&running_clone, handler);
+            let handler = LifecycleTracker {
+                times: times_clone,
+                paused: paused_clone,
+                suspend: SuspendGate::new(false),
+                handler: ChannelHandler::new(sender),
+            };
+            let _ = fanout::listen(
+                &running_clone,
+                EventMask::ALL,
+                Config::default(),
+                dropped_clone,
+                handler,
+            );
             running_clone.store(false, Ordering::SeqCst);
         });
 
         let handle = ChannelHookHandle {
             running,
             thread_handle: Some(thread_handle),
+            times,
+            paused,
+            grab_denied: false,
+            grab_capabilities: None,
+            registration: None,
+            dropped_events,
         };
 
         Ok((handle, receiver))
     }
 
-    /// Handler for grab mode with tokio channel.
-    struct TokioGrabChannelHandler<F>
-    where
-        F: Fn(&Event) -> bool + Send + Sync,
-    {
-        sender: tokio_mpsc::Sender<Event>,
-        filter: F,
-    }
-
-    impl<F> GrabHandler for TokioGrabChannelHandler<F>
-    where
-        F: Fn(&Event) -> bool + Send + Sync,
-    {
-        fn handle_event(&self, event: &Event) -> Option<Event> {
-            let _ = self.sender.try_send(event.clone());
-
-            if (self.filter)(event) {
-                Some(event.clone())
-            } else {
-                None
-            }
-        }
-    }
-
     /// Start a grab hook that sends events to a tokio async channel.
     ///
     /// # Arguments
@@ -420,21 +637,115 @@ mod tokio_channel {
         let (sender, receiver) = tokio_mpsc::channel(capacity);
         let running = Arc::new(AtomicBool::new(true));
         let running_clone = running.clone();
+        let times = Arc::new(Mutex::new(LifecycleTimes::default()));
+        let times_clone = times.clone();
+        let paused = Arc::new(AtomicBool::new(false));
+        let paused_clone = paused.clone();
 
         // Reset state before starting
         crate::state::reset_mask();
+        let grab_denied = policy::grab_denied();
 
         let thread_handle = thread::spawn(move || {
-            let handler = TokioGrabChannelHandler { sender, filter };
-            let _ = platform::run_grab_hook(&running_clone, handler);
+            let handler = LifecycleTracker {
+                times: times_clone,
+                paused: paused_clone,
+                suspend: SuspendGate::new(false),
+                handler: GrabChannelHandler {
+                    channel: ChannelHandler::new(sender),
+                    filter,
+                },
+            };
+            let _ = policy::run_grab_hook(&running_clone, handler, grab_denied);
             running_clone.store(false, Ordering::SeqCst);
         });
 
         let handle = ChannelHookHandle {
             running,
             thread_handle: Some(thread_handle),
+            times,
+            paused,
+            grab_denied,
+            grab_capabilities: Some(GrabCapabilities::new(grab_denied)),
+            registration: None,
+            dropped_events: Arc::default(),
         };
 
         Ok((handle, receiver))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycode::Key;
+    use std::sync::mpsc::RecvTimeoutError;
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_hook_disabled_waits_for_room_then_disconnects() {
+        let (sender, rx) = mpsc::sync_channel(1);
+        let handler = ChannelHandler::new(sender);
+        handler.handle_event(&Event::key_pressed(Key::KeyA, 0));
+        // Full: input events are dropped
+        handler.handle_event(&Event::key_pressed(Key::KeyB, 0));
+
+        let consumer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            let mut seen = Vec::new();
+            loop {
+                match rx.recv_timeout(WAIT) {
+                    Ok(event) => seen.push(event.event_type),
+                    Err(e) => return (seen, e),
+                }
+            }
+        });
+        handler.handle_event(&Event::hook_disabled());
+        // Nothing gets through after HookDisabled
+        handler.handle_event(&Event::key_pressed(Key::KeyC, 0));
+
+        let (seen, end) = consumer.join().unwrap();
+        assert_eq!(seen, [EventType::KeyPressed, EventType::HookDisabled]);
+        assert_eq!(end, RecvTimeoutError::Disconnected);
+    }
+
+    #[test]
+    fn test_hook_disabled_disconnects_without_consumer() {
+        let (sender, rx) = mpsc::sync_channel(1);
+        let handler = ChannelHandler::new(sender);
+        handler.handle_event(&Event::key_pressed(Key::KeyA, 0));
+        // Nobody reads: gives up after the timeout, but still disconnects
+        handler.handle_event(&Event::hook_disabled());
+
+        assert_eq!(rx.recv().unwrap().event_type, EventType::KeyPressed);
+        assert_eq!(rx.recv_timeout(WAIT), Err(RecvTimeoutError::Disconnected));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_async_channel_sees_hook_disabled_last() {
+        let (sender, mut rx) = tokio::sync::mpsc::channel(1);
+        let handler = GrabChannelHandler {
+            channel: ChannelHandler::new(sender),
+            filter: |_: &Event| true,
+        };
+        let _ = handler.handle_event(&Event::hook_enabled());
+        let _ = handler.handle_event(&Event::key_pressed(Key::KeyA, 0));
+
+        let consumer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            let mut seen = Vec::new();
+            while let Some(event) = rx.blocking_recv() {
+                seen.push(event.event_type);
+            }
+            seen
+        });
+        let _ = handler.handle_event(&Event::hook_disabled());
+
+        assert_eq!(
+            consumer.join().unwrap(),
+            [EventType::HookEnabled, EventType::HookDisabled]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Mouse button chords, like pressing Right while Left is held.
+//!
+//! A [`MouseChord`] matches the press that completes it: the trigger button
+//! pressed while the other buttons (and exactly the given keyboard
+//! modifiers) are held, read from the event's [`mask`](crate::Event::mask).
+//! Use [`Hook::on_mouse_chord`](crate::Hook::on_mouse_chord) to be called
+//! back, or [`MouseChord::matches`] in a grab handler to consume the
+//! trigger.
+//!
+//! Rocker gestures depend on the order of the presses:
+//! `MouseChord::new(Button::Right).while_held(Button::Left)` only matches
+//! Right pressed after Left, while `MouseChord::together` matches whichever
+//! of its buttons is pressed last.
+//!
+//! # Grab mode
+//!
+//! Only the trigger press can be consumed. The press of a held button came
+//! earlier, before anything knew a chord would follow, so it has already
+//! reached other applications. To keep it back too, hold it with
+//! [`Hook::grab_with_holds`](crate::Hook::grab_with_holds) and drop or
+//! replay it once the chord completes or doesn't.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::{Button, Event, Hook, Modifiers, MouseChord};
+//!
+//! let hook = Hook::new();
+//! let back = MouseChord::new(Button::Left).while_held(Button::Right);
+//! let _back = hook.on_mouse_chord(back, |_, _| println!("back"));
+//! let ctrl = Modifiers { ctrl: true, ..Default::default() };
+//! let _reset = hook.on_mouse_chord(
+//!     MouseChord::new(Button::Middle).with_modifiers(ctrl),
+//!     |x, y| println!("reset zoom at ({x}, {y})"),
+//! );
+//! hook.run(|_: &Event| {}).expect("Failed to start hook");
+//! ```
+
+use crate::event::{Button, Event, EventType};
+use crate::state::{Modifiers, button_to_mask};
+
+/// Mouse buttons, and keyboard modifiers, pressed together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MouseChord {
+    /// Buttons whose press completes the chord.
+    triggers: Vec<Button>,
+    /// Buttons that must all be held, triggers included.
+    buttons: Vec<Button>,
+    modifiers: Modifiers,
+}
+
+impl MouseChord {
+    /// `trigger` pressed without modifiers held.
+    pub fn new(trigger: Button) -> Self {
+        Self {
+            triggers: vec![trigger],
+            buttons: vec![trigger],
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    /// All of `buttons` held, in any order: the chord completes on the press
+    /// of whichever comes last.
+    pub fn together(buttons: &[Button]) -> Self {
+        Self {
+            triggers: buttons.to_vec(),
+            buttons: buttons.to_vec(),
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    /// Also require `button` to be held when the trigger is pressed.
+    pub fn while_held(mut self, button: Button) -> Self {
+        if !self.buttons.contains(&button) {
+            self.buttons.push(button);
+        }
+        self
+    }
+
+    /// Require exactly these keyboard modifiers to be held.
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    /// Whether `event` is the press completing this chord.
+    ///
+    /// Other buttons may be held too; modifiers must match exactly.
+    pub fn matches(&self, event: &Event) -> bool {
+        if event.event_type != EventType::MousePressed {
+            return false;
+        }
+        let Some(button) = event.mouse.as_ref().and_then(|m| m.button) else {
+            return false;
+        };
+        self.triggers.contains(&button)
+            && Modifiers::from_mask(event.mask) == self.modifiers
+            && self.buttons.iter().all(|&b| {
+                // The trigger itself counts as held, whatever the mask says
+                b == button || event.mask & button_to_mask(b.number()) != 0
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{MASK_BUTTON1, MASK_BUTTON2, MASK_BUTTON3, MASK_CTRL, MASK_SHIFT};
+
+    /// A press of `button` with `mask` held, the button itself included as
+    /// the backends report it.
+    fn press(button: Button, mask: u32) -> Event {
+        let mut event = Event::mouse_pressed(button, 0.0, 0.0);
+        event.mask = mask | button_to_mask(button.number());
+        event
+    }
+
+    #[test]
+    fn test_ordered_chord_needs_the_held_button_first() {
+        let chord = MouseChord::new(Button::Right).while_held(Button::Left);
+        // Left, then Right
+        assert!(!chord.matches(&press(Button::Left, 0)));
+        assert!(chord.matches(&press(Button::Right, MASK_BUTTON1)));
+        // Right, then Left
+        assert!(!chord.matches(&press(Button::Right, 0)));
+        assert!(!chord.matches(&press(Button::Left, MASK_BUTTON2)));
+    }
+
+    #[test]
+    fn test_together_matches_either_order() {
+        let chord = MouseChord::together(&[Button::Left, Button::Right]);
+        assert!(!chord.matches(&press(Button::Left, 0)));
+        assert!(chord.matches(&press(Button::Right, MASK_BUTTON1)));
+        assert!(!chord.matches(&press(Button::Right, 0)));
+        assert!(chord.matches(&press(Button::Left, MASK_BUTTON2)));
+        // Extra buttons don't matter, releases never match
+        assert!(chord.matches(&press(Button::Left, MASK_BUTTON2 | MASK_BUTTON3)));
+        let mut release = Event::mouse_released(Button::Left, 0.0, 0.0);
+        release.mask = MASK_BUTTON2;
+        assert!(!chord.matches(&release));
+    }
+
+    #[test]
+    fn test_modifiers_match_exactly() {
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Default::default()
+        };
+        let chord = MouseChord::new(Button::Middle).with_modifiers(ctrl);
+        assert!(chord.matches(&press(Button::Middle, MASK_CTRL)));
+        assert!(!chord.matches(&press(Button::Middle, 0)));
+        assert!(!chord.matches(&press(Button::Middle, MASK_CTRL | MASK_SHIFT)));
+        assert!(!MouseChord::new(Button::Middle).matches(&press(Button::Middle, MASK_CTRL)));
+    }
+}
@@ -0,0 +1,181 @@
+//! Cursor confinement.
+//!
+//! [`confine_cursor`] keeps the cursor inside a rectangle until the returned
+//! [`ConfineGuard`] is dropped. How it works depends on the platform:
+//!
+//! - **Windows**: `ClipCursor`.
+//! - **macOS**: a background thread warps the cursor back inside whenever it
+//!   leaves the rectangle. The cursor can overshoot for a few milliseconds.
+//! - **X11**: XFixes pointer barriers along the edges of the rectangle
+//!   (requires XFixes 5).
+//! - **evdev** (Wayland): not supported. Compositors own the cursor, so
+//!   confinement fails with [`Error::NotSupported`].
+//!
+//! Only one confinement is active at a time; confining again replaces the
+//! previous rectangle, and dropping the older guard then does nothing.
+//!
+//! Confinement is released when the process exits normally, and when a
+//! thread panics in builds with `panic = "abort"` (guards are dropped while
+//! unwinding otherwise). While active, the display layout is checked every
+//! second and the confinement re-applied when it changes, since display
+//! changes reset it on some platforms.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::confine::confine_cursor;
+//! use monio::primary_display;
+//!
+//! let display = primary_display()?;
+//! let guard = confine_cursor(display.bounds)?;
+//! std::thread::sleep(std::time::Duration::from_secs(10));
+//! drop(guard);
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::display::{Rect, displays};
+use crate::error::{Error, Result};
+use crate::platform;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How often the display layout is checked while confined.
+const LAYOUT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy)]
+struct Confinement {
+    generation: u64,
+    rect: Rect,
+}
+
+#[derive(Debug)]
+struct State {
+    active: Option<Confinement>,
+    /// Whether the layout watcher thread is running.
+    watching: bool,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    active: None,
+    watching: false,
+});
+
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// Keeps the cursor confined until dropped.
+#[derive(Debug)]
+#[must_use = "confinement is released as soon as the guard is dropped"]
+pub struct ConfineGuard {
+    generation: u64,
+    rect: Rect,
+}
+
+impl ConfineGuard {
+    /// The rectangle the cursor is confined to.
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Release the confinement, reporting platform errors.
+    pub fn release(self) -> Result<()> {
+        let result = release(self.generation);
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for ConfineGuard {
+    fn drop(&mut self) {
+        if let Err(e) = release(self.generation) {
+            log::warn!("Failed to release cursor confinement: {}", e);
+        }
+    }
+}
+
+/// Confine the cursor to `rect` (screen coordinates) until the guard is dropped.
+///
+/// If the cursor is outside `rect`, it is moved to the nearest point inside.
+pub fn confine_cursor(rect: Rect) -> Result<ConfineGuard> {
+    if !(rect.width >= 1.0 && rect.height >= 1.0 && rect.x.is_finite() && rect.y.is_finite()) {
+        return Err(Error::Other(format!(
+            "cannot confine the cursor to {:?}",
+            rect
+        )));
+    }
+    crate::cursor::restore_on_exit(release_now);
+
+    let mut state = STATE
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    platform::confine_cursor(rect)?;
+
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    state.active = Some(Confinement { generation, rect });
+    if !state.watching {
+        match std::thread::Builder::new()
+            .name("monio-confine".into())
+            .spawn(watch_layout)
+        {
+            Ok(_) => state.watching = true,
+            Err(e) => log::warn!("Failed to start display layout watcher: {}", e),
+        }
+    }
+    Ok(ConfineGuard { generation, rect })
+}
+
+fn release(generation: u64) -> Result<()> {
+    let mut state = STATE
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    if state
+        .active
+        .is_none_or(|active| active.generation != generation)
+    {
+        return Ok(());
+    }
+    state.active = None;
+    platform::release_cursor()
+}
+
+/// Release whatever confinement is active, without waiting on the lock.
+fn release_now() {
+    let mut state = match STATE.try_lock() {
+        Ok(state) => state,
+        Err(std::sync::TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return,
+    };
+    if state.active.take().is_some() {
+        let _ = platform::release_cursor();
+    }
+}
+
+/// Re-apply the confinement whenever the display layout changes.
+fn watch_layout() {
+    let layout = || -> Vec<Rect> {
+        displays()
+            .map(|displays| displays.iter().map(|d| d.bounds).collect())
+            .unwrap_or_default()
+    };
+    let mut last = layout();
+
+    loop {
+        std::thread::sleep(LAYOUT_POLL_INTERVAL);
+        let current = layout();
+
+        let Ok(mut state) = STATE.lock() else {
+            return;
+        };
+        let Some(active) = state.active else {
+            state.watching = false;
+            return;
+        };
+        if current != last && !current.is_empty() {
+            log::debug!("Display layout changed, re-applying cursor confinement");
+            if let Err(e) = platform::confine_cursor(active.rect) {
+                log::warn!("Failed to re-apply cursor confinement: {}", e);
+            }
+            last = current;
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Raw backend payloads to [`Event`]s, without OS calls.
+//!
+//! Each backend copies the fields it needs out of the OS's structure into a
+//! plain struct from here, and converts it against a button/modifier mask it
+//! passes in. Nothing here touches the OS or global state, so every
+//! backend's conversion can be tested, and fuzzed, on any platform.
+//! [`with_global_mask`] runs a conversion against the global mask in
+//! [`state`](crate::state).
+//!
+//! A conversion must not panic on any input, only ever set bits of
+//! [`MASK_ALL_BUTTONS`] and [`MASK_ALL_MODIFIERS`](crate::state::MASK_ALL_MODIFIERS),
+//! and return events carrying the payload their type calls for.
+
+#[cfg_attr(not(all(target_os = "linux", feature = "evdev")), allow(dead_code))]
+pub(crate) mod evdev;
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) mod macos;
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) mod windows;
+#[cfg_attr(not(all(target_os = "linux", feature = "x11")), allow(dead_code))]
+pub(crate) mod x11;
+
+use crate::event::{Button, Event};
+use crate::state::{self, MASK_ALL_BUTTONS, button_to_mask, buttons_in_mask};
+
+/// Run `convert` against the global mask and publish the bits it changed.
+///
+/// Only changed bits are written back, so bits other threads set meanwhile
+/// survive.
+#[cfg_attr(
+    all(target_os = "linux", not(any(feature = "x11", feature = "evdev"))),
+    allow(dead_code)
+)]
+pub(crate) fn with_global_mask<T>(convert: impl FnOnce(&mut u32) -> T) -> T {
+    let before = state::get_mask();
+    let mut mask = before;
+    let converted = convert(&mut mask);
+    state::set_mask(mask & !before);
+    state::unset_mask(before & !mask);
+    converted
+}
+
+/// Set or clear the bit of a modifier or button.
+fn update(mask: &mut u32, bit: u32, pressed: bool) {
+    if pressed {
+        *mask |= bit;
+    } else {
+        *mask &= !bit;
+    }
+}
+
+/// `event` with the mask it happened under.
+fn with_mask(mut event: Event, mask: u32) -> Event {
+    event.mask = mask;
+    event
+}
+
+/// A button press or release, updating the button's bit.
+fn button(button: Button, pressed: bool, x: f64, y: f64, mask: &mut u32) -> Event {
+    update(mask, button_to_mask(button.number()), pressed);
+    let event = if pressed {
+        Event::mouse_pressed(button, x, y)
+    } else {
+        Event::mouse_released(button, x, y)
+    };
+    with_mask(event, *mask)
+}
+
+/// A pointer move: dragged while a button is held, moved otherwise.
+fn motion(x: f64, y: f64, mask: u32) -> Event {
+    if mask & MASK_ALL_BUTTONS == 0 {
+        return with_mask(Event::mouse_moved(x, y), mask);
+    }
+    let mut event = with_mask(Event::mouse_dragged(x, y), mask);
+    if let Some(mouse) = &mut event.mouse {
+        mouse.button = buttons_in_mask(mask).first().copied();
+    }
+    event
+}
+
+/// Checks shared by every backend's fuzz tests.
+#[cfg(test)]
+pub(crate) mod invariants {
+    use crate::event::{Event, EventType};
+    use crate::state::{MASK_ALL_BUTTONS, MASK_ALL_MODIFIERS};
+
+    /// Assert that `mask` only has defined bits set.
+    pub(crate) fn assert_mask(mask: u32) {
+        assert_eq!(
+            mask & !(MASK_ALL_BUTTONS | MASK_ALL_MODIFIERS),
+            0,
+            "undefined mask bits in {:#x}",
+            mask
+        );
+    }
+
+    /// Assert that `event` carries what its type calls for, and the mask
+    /// after the conversion.
+    pub(crate) fn assert_consistent(event: &Event, mask: u32) {
+        assert_mask(event.mask);
+        assert_eq!(event.mask, mask);
+        match event.event_type {
+            EventType::KeyPressed | EventType::KeyReleased => {
+                assert!(event.keyboard.is_some(), "{:?}", event);
+                assert!(event.mouse.is_none() && event.wheel.is_none());
+            }
+            EventType::MousePressed | EventType::MouseReleased => {
+                let mouse = event.mouse.as_ref().expect("mouse data");
+                assert!(mouse.button.is_some());
+                assert!(mouse.x.is_finite() && mouse.y.is_finite());
+                assert!(event.keyboard.is_none());
+            }
+            EventType::MouseMoved => {
+                let mouse = event.mouse.as_ref().expect("mouse data");
+                assert!(mouse.x.is_finite() && mouse.y.is_finite());
+                assert_eq!(mask & MASK_ALL_BUTTONS, 0, "moved with a button held");
+            }
+            EventType::MouseDragged => {
+                let mouse = event.mouse.as_ref().expect("mouse data");
+                assert!(mouse.x.is_finite() && mouse.y.is_finite());
+                assert!(mouse.button.is_some());
+            }
+            EventType::MouseWheel => {
+                let wheel = event.wheel.as_ref().expect("wheel data");
+                assert!(wheel.delta.is_finite() && wheel.delta >= 0.0);
+                assert!(wheel.x.is_finite() && wheel.y.is_finite());
+            }
+            other => panic!("unexpected event type {:?}", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{MASK_BUTTON1, MASK_SHIFT};
+
+    #[test]
+    fn test_motion_reports_drag_with_held_button() {
+        let moved = motion(1.0, 2.0, MASK_SHIFT);
+        assert_eq!(moved.event_type, crate::EventType::MouseMoved);
+        assert_eq!(moved.mask, MASK_SHIFT);
+
+        let dragged = motion(1.0, 2.0, MASK_BUTTON1);
+        assert_eq!(dragged.event_type, crate::EventType::MouseDragged);
+        assert_eq!(dragged.mouse.unwrap().button, Some(Button::Left));
+    }
+}
@@ -0,0 +1,187 @@
+//! Linux evdev: `input_event` type, code and value.
+
+use super::{button, motion, update, with_mask};
+use crate::event::{Backend, Button, Event, ScrollDirection};
+use crate::keycode::Key;
+use crate::state::{MASK_ALT, MASK_CTRL, MASK_META, MASK_SHIFT};
+
+/// Event types (`input-event-codes.h`).
+pub(crate) const EV_KEY: u16 = 0x01;
+pub(crate) const EV_REL: u16 = 0x02;
+pub(crate) const EV_ABS: u16 = 0x03;
+
+/// Relative axes.
+pub(crate) const REL_X: u16 = 0x00;
+pub(crate) const REL_Y: u16 = 0x01;
+pub(crate) const REL_HWHEEL: u16 = 0x06;
+pub(crate) const REL_WHEEL: u16 = 0x08;
+
+/// Absolute axes.
+pub(crate) const ABS_X: u16 = 0x00;
+pub(crate) const ABS_Y: u16 = 0x01;
+
+/// An `input_event` without its timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RawEvent {
+    pub(crate) type_: u16,
+    pub(crate) code: u16,
+    pub(crate) value: i32,
+}
+
+/// The modifier bit of a key code.
+fn modifier_mask(code: u16) -> u32 {
+    match code {
+        42 | 54 => MASK_SHIFT,  // KEY_LEFTSHIFT, KEY_RIGHTSHIFT
+        29 | 97 => MASK_CTRL,   // KEY_LEFTCTRL, KEY_RIGHTCTRL
+        56 | 100 => MASK_ALT,   // KEY_LEFTALT, KEY_RIGHTALT
+        125 | 126 => MASK_META, // KEY_LEFTMETA, KEY_RIGHTMETA
+        _ => 0,
+    }
+}
+
+/// The position after an unmapped `ABS_X`/`ABS_Y` value: raw axis units.
+pub(crate) fn unmapped(position: (f64, f64), axis: u16, value: i32) -> Option<(f64, f64)> {
+    match axis {
+        ABS_X => Some((f64::from(value), position.1)),
+        ABS_Y => Some((position.0, f64::from(value))),
+        _ => None,
+    }
+}
+
+/// Convert an event.
+///
+/// Relative motion moves `position`. Absolute axes go through `absolute`,
+/// which gets the position, axis and value and returns the new position, or
+/// `None` to ignore the axis. Key codes are resolved with `resolve_key`.
+pub(crate) fn convert(
+    raw: &RawEvent,
+    mask: &mut u32,
+    position: &mut (f64, f64),
+    absolute: impl FnOnce((f64, f64), u16, i32) -> Option<(f64, f64)>,
+    resolve_key: impl FnOnce(u16) -> Key,
+) -> Option<Event> {
+    match raw.type_ {
+        EV_KEY => {
+            let code = raw.code;
+            let pressed = raw.value == 1;
+
+            // Mouse buttons
+            if (0x110..=0x117).contains(&code) {
+                let changed = Button::from_platform_code(Backend::Evdev, code.into())?;
+                let (x, y) = *position;
+                return Some(button(changed, pressed, x, y, mask));
+            }
+
+            update(mask, modifier_mask(code), pressed);
+            let key = resolve_key(code);
+            let event = if pressed {
+                Event::key_pressed(key, code.into())
+            } else {
+                Event::key_released(key, code.into())
+            };
+            Some(with_mask(event, *mask))
+        }
+
+        EV_REL => {
+            let value = f64::from(raw.value);
+            let wheel = |direction| {
+                let (x, y) = *position;
+                with_mask(Event::mouse_wheel(x, y, direction, value.abs()), *mask)
+            };
+            match raw.code {
+                REL_X => position.0 += value,
+                REL_Y => position.1 += value,
+                REL_WHEEL if value > 0.0 => return Some(wheel(ScrollDirection::Up)),
+                REL_WHEEL => return Some(wheel(ScrollDirection::Down)),
+                REL_HWHEEL if value > 0.0 => return Some(wheel(ScrollDirection::Right)),
+                REL_HWHEEL => return Some(wheel(ScrollDirection::Left)),
+                _ => return None,
+            }
+            Some(motion(position.0, position.1, *mask))
+        }
+
+        EV_ABS => {
+            *position = absolute(*position, raw.code, raw.value)?;
+            Some(motion(position.0, position.1, *mask))
+        }
+
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::invariants::{assert_consistent, assert_mask};
+    use crate::event::EventType;
+    use crate::state::{MASK_ALL_BUTTONS, MASK_ALL_MODIFIERS, MASK_BUTTON1};
+    use proptest::prelude::*;
+
+    fn convert_unmapped(raw: RawEvent, mask: &mut u32, position: &mut (f64, f64)) -> Option<Event> {
+        convert(&raw, mask, position, unmapped, |_| Key::Unknown(0))
+    }
+
+    #[test]
+    fn test_relative_motion_and_drag() {
+        let (mut mask, mut position) = (0, (0.0, 0.0));
+        let rel = |code, value| RawEvent {
+            type_: EV_REL,
+            code,
+            value,
+        };
+
+        let moved = convert_unmapped(rel(REL_X, 5), &mut mask, &mut position).unwrap();
+        assert_eq!(moved.event_type, EventType::MouseMoved);
+        let press = RawEvent {
+            type_: EV_KEY,
+            code: 0x110,
+            value: 1,
+        };
+        convert_unmapped(press, &mut mask, &mut position).unwrap();
+        assert_eq!(mask, MASK_BUTTON1);
+
+        let dragged = convert_unmapped(rel(REL_Y, -3), &mut mask, &mut position).unwrap();
+        assert_eq!(dragged.event_type, EventType::MouseDragged);
+        assert_eq!(position, (5.0, -3.0));
+
+        let wheel = convert_unmapped(rel(REL_WHEEL, -2), &mut mask, &mut position).unwrap();
+        let wheel = wheel.wheel.unwrap();
+        assert_eq!((wheel.direction, wheel.delta), (ScrollDirection::Down, 2.0));
+    }
+
+    #[test]
+    fn test_absolute_axes() {
+        let (mut mask, mut position) = (0, (1.0, 1.0));
+        let abs = |code, value| RawEvent {
+            type_: EV_ABS,
+            code,
+            value,
+        };
+        convert_unmapped(abs(ABS_X, 40), &mut mask, &mut position).unwrap();
+        assert_eq!(position, (40.0, 1.0));
+        // Other axes (pressure, tilt) aren't pointer motion
+        assert!(convert_unmapped(abs(0x18, 7), &mut mask, &mut position).is_none());
+        assert_eq!(position, (40.0, 1.0));
+    }
+
+    proptest! {
+        #[test]
+        fn test_fuzz_convert_keeps_mask_and_events_consistent(
+            start in any::<u32>(),
+            events in proptest::collection::vec(
+                (0u16..5, prop_oneof![0u16..0x20, 0x100u16..0x120, any::<u16>()], any::<i32>()),
+                1..32,
+            ),
+        ) {
+            let mut mask = start & (MASK_ALL_BUTTONS | MASK_ALL_MODIFIERS);
+            let mut position = (0.0, 0.0);
+            for (type_, code, value) in events {
+                let raw = RawEvent { type_, code, value };
+                if let Some(event) = convert_unmapped(raw, &mut mask, &mut position) {
+                    assert_consistent(&event, mask);
+                }
+                assert_mask(mask);
+            }
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! macOS: Quartz events from the event tap.
+
+use super::{button, motion, with_mask};
+use crate::event::{Backend, Button, Event, ScrollDirection};
+use crate::hook::EventMask;
+use crate::keycode::Key;
+use crate::state::{MASK_ALL_BUTTONS, MASK_ALT, MASK_CTRL, MASK_META, MASK_SHIFT};
+
+/// Event types (`CGEventType`).
+pub(crate) const LEFT_MOUSE_DOWN: u32 = 1;
+pub(crate) const LEFT_MOUSE_UP: u32 = 2;
+pub(crate) const RIGHT_MOUSE_DOWN: u32 = 3;
+pub(crate) const RIGHT_MOUSE_UP: u32 = 4;
+pub(crate) const MOUSE_MOVED: u32 = 5;
+pub(crate) const LEFT_MOUSE_DRAGGED: u32 = 6;
+pub(crate) const RIGHT_MOUSE_DRAGGED: u32 = 7;
+pub(crate) const KEY_DOWN: u32 = 10;
+pub(crate) const KEY_UP: u32 = 11;
+pub(crate) const FLAGS_CHANGED: u32 = 12;
+/// `NSEventTypeSystemDefined`, which carries the media keys.
+pub(crate) const SYSTEM_DEFINED: u32 = 14;
+pub(crate) const SCROLL_WHEEL: u32 = 22;
+pub(crate) const OTHER_MOUSE_DOWN: u32 = 25;
+pub(crate) const OTHER_MOUSE_UP: u32 = 26;
+pub(crate) const OTHER_MOUSE_DRAGGED: u32 = 27;
+
+/// Modifier flags (`CGEventFlags`), in the order press and release are
+/// looked for.
+const MODIFIER_FLAGS: [(u64, u32); 4] = [
+    (0x0002_0000, MASK_SHIFT), // kCGEventFlagMaskShift
+    (0x0004_0000, MASK_CTRL),  // kCGEventFlagMaskControl
+    (0x0008_0000, MASK_ALT),   // kCGEventFlagMaskAlternate
+    (0x0010_0000, MASK_META),  // kCGEventFlagMaskCommand
+];
+
+/// The fields of a `CGEvent` that are used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RawEvent {
+    /// The `CGEventType`, such as [`KEY_DOWN`].
+    pub(crate) event_type: u32,
+    /// `CGEventFlags`.
+    pub(crate) flags: u64,
+    /// `kCGKeyboardEventKeycode`.
+    pub(crate) keycode: i64,
+    /// `kCGMouseEventButtonNumber`, 0-based.
+    pub(crate) button_number: i64,
+    /// Location in global display coordinates.
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    /// `kCGScrollWheelEventDeltaAxis1` (vertical) and `Axis2` (horizontal).
+    pub(crate) scroll_delta: (i64, i64),
+}
+
+/// The `CGEventMask` of the event types behind the categories in `events`.
+///
+/// Motion registers for button events too: a drag is told from a move by
+/// the buttons held.
+pub(crate) fn tap_mask(events: EventMask) -> u64 {
+    let mut types = Vec::new();
+    if events.contains(EventMask::KEYBOARD) {
+        types.extend([KEY_DOWN, KEY_UP, FLAGS_CHANGED, SYSTEM_DEFINED]);
+    }
+    if events.intersects(EventMask::MOUSE_BUTTONS | EventMask::MOUSE_MOTION) {
+        types.extend([
+            LEFT_MOUSE_DOWN,
+            LEFT_MOUSE_UP,
+            RIGHT_MOUSE_DOWN,
+            RIGHT_MOUSE_UP,
+            OTHER_MOUSE_DOWN,
+            OTHER_MOUSE_UP,
+        ]);
+    }
+    if events.contains(EventMask::MOUSE_MOTION) {
+        types.extend([
+            MOUSE_MOVED,
+            LEFT_MOUSE_DRAGGED,
+            RIGHT_MOUSE_DRAGGED,
+            OTHER_MOUSE_DRAGGED,
+        ]);
+    }
+    if events.contains(EventMask::MOUSE_WHEEL) {
+        types.push(SCROLL_WHEEL);
+    }
+    types.iter().fold(0, |mask, type_| mask | 1 << type_)
+}
+
+/// The modifier bits held in `flags`.
+fn flags_to_mask(flags: u64) -> u32 {
+    MODIFIER_FLAGS
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .fold(0, |mask, (_, bit)| mask | bit)
+}
+
+/// The button of a 0-based button number.
+fn number_to_button(number: i64) -> Button {
+    u32::try_from(number)
+        .ok()
+        .and_then(|n| Button::from_platform_code(Backend::MacOS, n))
+        .unwrap_or(Button::Unknown(0))
+}
+
+/// Convert an event.
+///
+/// Every event's flags replace the modifier bits of `mask`. `last_flags`
+/// holds the flags of the last modifier change, to tell presses from
+/// releases. Keycodes are resolved with `resolve_key`, modifier keycodes
+/// with `modifier_key`.
+pub(crate) fn convert(
+    raw: &RawEvent,
+    mask: &mut u32,
+    last_flags: &mut u64,
+    resolve_key: impl FnOnce(u16) -> Key,
+    modifier_key: impl FnOnce(u16) -> Key,
+) -> Option<Event> {
+    // Update only modifier bits, preserve button bits
+    *mask = flags_to_mask(raw.flags) | (*mask & MASK_ALL_BUTTONS);
+
+    let code = raw.keycode as u16;
+    let (x, y) = (raw.x, raw.y);
+    match raw.event_type {
+        KEY_DOWN => Some(with_mask(
+            Event::key_pressed(resolve_key(code), raw.keycode as u32),
+            *mask,
+        )),
+
+        KEY_UP => Some(with_mask(
+            Event::key_released(resolve_key(code), raw.keycode as u32),
+            *mask,
+        )),
+
+        FLAGS_CHANGED => {
+            // Press or release, from the first modifier flag that changed
+            let (flag, _) = MODIFIER_FLAGS
+                .iter()
+                .find(|(flag, _)| (raw.flags ^ *last_flags) & flag != 0)?;
+            let pressed = raw.flags & flag != 0;
+            *last_flags = raw.flags;
+
+            let key = modifier_key(code);
+            let event = if pressed {
+                Event::key_pressed(key, raw.keycode as u32)
+            } else {
+                Event::key_released(key, raw.keycode as u32)
+            };
+            Some(with_mask(event, *mask))
+        }
+
+        LEFT_MOUSE_DOWN => Some(button(Button::Left, true, x, y, mask)),
+        LEFT_MOUSE_UP => Some(button(Button::Left, false, x, y, mask)),
+        RIGHT_MOUSE_DOWN => Some(button(Button::Right, true, x, y, mask)),
+        RIGHT_MOUSE_UP => Some(button(Button::Right, false, x, y, mask)),
+        OTHER_MOUSE_DOWN => Some(button(
+            number_to_button(raw.button_number),
+            true,
+            x,
+            y,
+            mask,
+        )),
+        OTHER_MOUSE_UP => Some(button(
+            number_to_button(raw.button_number),
+            false,
+            x,
+            y,
+            mask,
+        )),
+
+        // THE KEY FIX: Check button state for drag detection
+        MOUSE_MOVED => Some(motion(x, y, *mask)),
+
+        LEFT_MOUSE_DRAGGED | RIGHT_MOUSE_DRAGGED | OTHER_MOUSE_DRAGGED => {
+            let mut event = with_mask(Event::mouse_dragged(x, y), *mask);
+            if let Some(mouse) = &mut event.mouse {
+                // The event type names the button even if its press was missed
+                mouse.button = crate::state::buttons_in_mask(*mask)
+                    .first()
+                    .copied()
+                    .or(Some(number_to_button(raw.button_number)));
+            }
+            Some(event)
+        }
+
+        SCROLL_WHEEL => {
+            let (delta_y, delta_x) = raw.scroll_delta;
+            let (direction, delta) = if delta_y.unsigned_abs() > delta_x.unsigned_abs() {
+                if delta_y > 0 {
+                    (ScrollDirection::Up, delta_y.unsigned_abs())
+                } else {
+                    (ScrollDirection::Down, delta_y.unsigned_abs())
+                }
+            } else if delta_x > 0 {
+                (ScrollDirection::Left, delta_x.unsigned_abs())
+            } else {
+                (ScrollDirection::Right, delta_x.unsigned_abs())
+            };
+            Some(with_mask(
+                Event::mouse_wheel(x, y, direction, delta as f64),
+                *mask,
+            ))
+        }
+
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::invariants::{assert_consistent, assert_mask};
+    use crate::event::EventType;
+    use crate::state::{MASK_ALL_MODIFIERS, MASK_BUTTON3};
+    use proptest::prelude::*;
+
+    fn raw(event_type: u32) -> RawEvent {
+        RawEvent {
+            event_type,
+            flags: 0,
+            keycode: 0,
+            button_number: 0,
+            x: 1.0,
+            y: 2.0,
+            scroll_delta: (0, 0),
+        }
+    }
+
+    fn convert_raw(raw: &RawEvent, mask: &mut u32, last_flags: &mut u64) -> Option<Event> {
+        convert(raw, mask, last_flags, |_| Key::KeyA, |_| Key::ShiftLeft)
+    }
+
+    #[test]
+    fn test_flags_changed_press_and_release() {
+        let (mut mask, mut last_flags) = (0, 0);
+        let shift = RawEvent {
+            flags: 0x0002_0000,
+            keycode: 56,
+            ..raw(FLAGS_CHANGED)
+        };
+        let press = convert_raw(&shift, &mut mask, &mut last_flags).unwrap();
+        assert_eq!(press.event_type, EventType::KeyPressed);
+        assert_eq!(mask, MASK_SHIFT);
+
+        let release = convert_raw(&raw(FLAGS_CHANGED), &mut mask, &mut last_flags).unwrap();
+        assert_eq!(release.event_type, EventType::KeyReleased);
+        assert_eq!(mask, 0);
+
+        // Unchanged modifiers (e.g. Caps Lock) produce nothing
+        assert!(convert_raw(&raw(FLAGS_CHANGED), &mut mask, &mut last_flags).is_none());
+    }
+
+    #[test]
+    fn test_other_button_and_drag() {
+        let (mut mask, mut last_flags) = (0, 0);
+        let middle = RawEvent {
+            button_number: 2,
+            ..raw(OTHER_MOUSE_DOWN)
+        };
+        convert_raw(&middle, &mut mask, &mut last_flags).unwrap();
+        assert_eq!(mask, MASK_BUTTON3);
+
+        let dragged = convert_raw(&raw(OTHER_MOUSE_DRAGGED), &mut mask, &mut last_flags).unwrap();
+        assert_eq!(dragged.mouse.unwrap().button, Some(Button::Middle));
+
+        // A drag whose press was missed still names its button
+        let (mut mask, mut last_flags) = (0, 0);
+        let dragged = convert_raw(&raw(RIGHT_MOUSE_DRAGGED), &mut mask, &mut last_flags).unwrap();
+        assert_eq!(dragged.mouse.unwrap().button, Some(Button::Left));
+    }
+
+    #[test]
+    fn test_tap_mask() {
+        let keys = tap_mask(EventMask::KEYBOARD);
+        assert_eq!(keys, 1 << 10 | 1 << 11 | 1 << 12 | 1 << 14);
+        assert_eq!(keys & 1 << MOUSE_MOVED, 0);
+
+        let buttons = tap_mask(EventMask::MOUSE_BUTTONS);
+        assert_ne!(buttons & 1 << OTHER_MOUSE_UP, 0);
+        assert_eq!(buttons & (1 << MOUSE_MOVED | 1 << LEFT_MOUSE_DRAGGED), 0);
+        // Drags need the buttons
+        assert_eq!(tap_mask(EventMask::MOUSE_MOTION) & buttons, buttons);
+
+        assert_eq!(tap_mask(EventMask::TOUCH), 0);
+        assert_eq!(
+            tap_mask(EventMask::ALL),
+            keys | tap_mask(EventMask::MOUSE) | 1 << SCROLL_WHEEL
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_fuzz_convert_keeps_mask_and_events_consistent(
+            start in any::<u32>(),
+            events in proptest::collection::vec(
+                (0u32..30, any::<u64>(), any::<i64>(), any::<i64>(), any::<(i64, i64)>(),
+                 -1e6f64..1e6, -1e6f64..1e6),
+                1..32,
+            ),
+        ) {
+            let mut mask = start & (MASK_ALL_BUTTONS | MASK_ALL_MODIFIERS);
+            let mut last_flags = 0;
+            for (event_type, flags, keycode, button_number, scroll_delta, x, y) in events {
+                let raw = RawEvent { event_type, flags, keycode, button_number, x, y, scroll_delta };
+                if let Some(event) = convert_raw(&raw, &mut mask, &mut last_flags) {
+                    assert_consistent(&event, mask);
+                }
+                assert_mask(mask);
+            }
+        }
+    }
+}
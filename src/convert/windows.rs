@@ -0,0 +1,254 @@
+//! Windows: low-level keyboard and mouse hook messages.
+
+use super::{button, motion, update, with_mask};
+use crate::event::{Button, Event, ScrollDirection};
+use crate::keycode::Key;
+use crate::state::{MASK_ALT, MASK_BUTTON4, MASK_BUTTON5, MASK_CTRL, MASK_META, MASK_SHIFT};
+
+/// Window messages the hooks receive (`WinUser.h`).
+pub(crate) const WM_KEYDOWN: u32 = 0x0100;
+pub(crate) const WM_KEYUP: u32 = 0x0101;
+pub(crate) const WM_SYSKEYDOWN: u32 = 0x0104;
+pub(crate) const WM_SYSKEYUP: u32 = 0x0105;
+pub(crate) const WM_MOUSEMOVE: u32 = 0x0200;
+pub(crate) const WM_LBUTTONDOWN: u32 = 0x0201;
+pub(crate) const WM_LBUTTONUP: u32 = 0x0202;
+pub(crate) const WM_RBUTTONDOWN: u32 = 0x0204;
+pub(crate) const WM_RBUTTONUP: u32 = 0x0205;
+pub(crate) const WM_MBUTTONDOWN: u32 = 0x0207;
+pub(crate) const WM_MBUTTONUP: u32 = 0x0208;
+pub(crate) const WM_MOUSEWHEEL: u32 = 0x020A;
+pub(crate) const WM_XBUTTONDOWN: u32 = 0x020B;
+pub(crate) const WM_XBUTTONUP: u32 = 0x020C;
+pub(crate) const WM_MOUSEHWHEEL: u32 = 0x020E;
+
+/// Wheel movement of one notch.
+pub(crate) const WHEEL_DELTA: i16 = 120;
+
+/// `XBUTTON1` / `XBUTTON2` bits in the high word of `mouseData`.
+pub(crate) const XBUTTON1: u16 = 0x0001;
+pub(crate) const XBUTTON2: u16 = 0x0002;
+
+/// The fields of a `KBDLLHOOKSTRUCT` that are used, with its message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RawKeyboard {
+    pub(crate) message: u32,
+    pub(crate) vk_code: u32,
+    pub(crate) scan_code: u32,
+    /// The `LLKHF_EXTENDED` flag.
+    pub(crate) extended: bool,
+}
+
+/// The fields of an `MSLLHOOKSTRUCT` that are used, with its message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RawMouse {
+    pub(crate) message: u32,
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+    pub(crate) mouse_data: u32,
+}
+
+/// The modifier bit of a virtual key.
+fn modifier_mask(vk: u32) -> u32 {
+    match vk {
+        0xA0 | 0xA1 => MASK_SHIFT, // VK_LSHIFT, VK_RSHIFT
+        0xA2 | 0xA3 => MASK_CTRL,  // VK_LCONTROL, VK_RCONTROL
+        0xA4 | 0xA5 => MASK_ALT,   // VK_LMENU, VK_RMENU
+        0x5B | 0x5C => MASK_META,  // VK_LWIN, VK_RWIN
+        _ => 0,
+    }
+}
+
+/// Convert a keyboard hook message, resolving virtual keys (with their
+/// scan code) with `resolve_key`.
+pub(crate) fn convert_keyboard(
+    raw: &RawKeyboard,
+    mask: &mut u32,
+    resolve_key: impl FnOnce(u16, u32) -> Key,
+) -> Option<Event> {
+    let pressed = match raw.message {
+        WM_KEYDOWN | WM_SYSKEYDOWN => true,
+        WM_KEYUP | WM_SYSKEYUP => false,
+        _ => return None,
+    };
+    update(mask, modifier_mask(raw.vk_code), pressed);
+    let key = resolve_key(raw.vk_code as u16, raw.scan_code);
+    let mut event = if pressed {
+        Event::key_pressed(key, raw.vk_code)
+    } else {
+        Event::key_released(key, raw.vk_code)
+    };
+    if let Some(kb) = &mut event.keyboard {
+        kb.extended = raw.extended;
+    }
+    Some(with_mask(event, *mask))
+}
+
+/// Convert a mouse hook message. XBUTTON messages can carry several
+/// buttons; other messages give at most one event.
+pub(crate) fn convert_mouse(raw: &RawMouse, mask: &mut u32) -> Vec<Event> {
+    let (x, y) = (f64::from(raw.x), f64::from(raw.y));
+    let high_word = (raw.mouse_data >> 16) as u16;
+    let event = match raw.message {
+        WM_LBUTTONDOWN => button(Button::Left, true, x, y, mask),
+        WM_LBUTTONUP => button(Button::Left, false, x, y, mask),
+        WM_RBUTTONDOWN => button(Button::Right, true, x, y, mask),
+        WM_RBUTTONUP => button(Button::Right, false, x, y, mask),
+        WM_MBUTTONDOWN => button(Button::Middle, true, x, y, mask),
+        WM_MBUTTONUP => button(Button::Middle, false, x, y, mask),
+        WM_XBUTTONDOWN | WM_XBUTTONUP => {
+            return xbutton_events(high_word, raw.message == WM_XBUTTONDOWN, x, y, mask);
+        }
+        // THE KEY FIX: Check button state for drag detection
+        WM_MOUSEMOVE => motion(x, y, *mask),
+        WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+            let delta = f64::from(high_word as i16) / f64::from(WHEEL_DELTA);
+            let direction = match (raw.message == WM_MOUSEWHEEL, delta > 0.0) {
+                (true, true) => ScrollDirection::Up,
+                (true, false) => ScrollDirection::Down,
+                (false, true) => ScrollDirection::Right,
+                (false, false) => ScrollDirection::Left,
+            };
+            with_mask(Event::mouse_wheel(x, y, direction, delta.abs()), *mask)
+        }
+        _ => return Vec::new(),
+    };
+    vec![event]
+}
+
+/// Convert an XBUTTON message into one event per changed button.
+///
+/// `bits` is the high word of `mouseData`: a bitmask, not an index. Both
+/// bits can be set when the two buttons change together. Releases with no
+/// known bit clear both X button masks, so a malformed release can't leave
+/// one stuck.
+fn xbutton_events(bits: u16, pressed: bool, x: f64, y: f64, mask: &mut u32) -> Vec<Event> {
+    if !pressed && bits & (XBUTTON1 | XBUTTON2) == 0 {
+        *mask &= !(MASK_BUTTON4 | MASK_BUTTON5);
+    }
+
+    let mut events = Vec::new();
+    if bits & XBUTTON1 != 0 {
+        events.push(button(Button::Button4, pressed, x, y, mask));
+    }
+    if bits & XBUTTON2 != 0 {
+        events.push(button(Button::Button5, pressed, x, y, mask));
+    }
+    // Further bits count on from Button5: 0x0004 is Unknown(6)
+    for bit in 2..16u8 {
+        if bits & (1 << bit) != 0 {
+            events.push(button(Button::Unknown(bit + 4), pressed, x, y, mask));
+        }
+    }
+    // Every event carries the mask after the whole message
+    for event in &mut events {
+        event.mask = *mask;
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::invariants::{assert_consistent, assert_mask};
+    use crate::state::{MASK_ALL_BUTTONS, MASK_ALL_MODIFIERS, buttons_in_mask};
+    use proptest::prelude::*;
+
+    fn xbutton(message: u32, bits: u16) -> RawMouse {
+        RawMouse {
+            message,
+            x: 0,
+            y: 0,
+            mouse_data: (bits as u32) << 16,
+        }
+    }
+
+    fn buttons(events: &[Event]) -> Vec<Button> {
+        events
+            .iter()
+            .map(|e| e.mouse.as_ref().unwrap().button.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_xbutton_press_release_pairs() {
+        for (bits, expected) in [
+            (XBUTTON1, vec![Button::Button4]),
+            (XBUTTON2, vec![Button::Button5]),
+            (XBUTTON1 | XBUTTON2, vec![Button::Button4, Button::Button5]),
+        ] {
+            let mut mask = 0;
+            let pressed = convert_mouse(&xbutton(WM_XBUTTONDOWN, bits), &mut mask);
+            assert_eq!(buttons(&pressed), expected);
+            assert_eq!(buttons_in_mask(mask), expected);
+
+            let released = convert_mouse(&xbutton(WM_XBUTTONUP, bits), &mut mask);
+            assert_eq!(buttons(&released), expected);
+            assert_eq!(mask, 0);
+        }
+    }
+
+    #[test]
+    fn test_xbutton_unpaired_release_clears_mask() {
+        let mut mask = 0;
+        convert_mouse(&xbutton(WM_XBUTTONDOWN, XBUTTON1 | XBUTTON2), &mut mask);
+
+        // Low-word noise and an empty high word still release both
+        let mut release = xbutton(WM_XBUTTONUP, 0);
+        release.mouse_data |= 0x0078;
+        assert!(convert_mouse(&release, &mut mask).is_empty());
+        assert_eq!(mask, 0);
+    }
+
+    #[test]
+    fn test_xbutton_unknown_bits() {
+        let mut mask = 0;
+        let events = convert_mouse(&xbutton(WM_XBUTTONDOWN, 0x0004), &mut mask);
+        assert_eq!(buttons(&events), vec![Button::Unknown(6)]);
+        assert_eq!(mask, 0);
+
+        let events = convert_mouse(&xbutton(WM_XBUTTONDOWN, 0x0011), &mut mask);
+        assert_eq!(buttons(&events), vec![Button::Button4, Button::Unknown(8)]);
+        assert_eq!(mask, MASK_BUTTON4);
+    }
+
+    #[test]
+    fn test_wheel_notches() {
+        let mut mask = 0;
+        let wheel = RawMouse {
+            message: WM_MOUSEHWHEEL,
+            x: 3,
+            y: 4,
+            mouse_data: ((-240i16) as u16 as u32) << 16,
+        };
+        let events = convert_mouse(&wheel, &mut mask);
+        let wheel = events[0].wheel.as_ref().unwrap();
+        assert_eq!((wheel.direction, wheel.delta), (ScrollDirection::Left, 2.0));
+    }
+
+    proptest! {
+        #[test]
+        fn test_fuzz_convert_keeps_mask_and_events_consistent(
+            start in any::<u32>(),
+            events in proptest::collection::vec(
+                (any::<bool>(), prop_oneof![0x0100u32..0x0106, 0x0200u32..0x020F, any::<u32>()],
+                 any::<u32>(), any::<u32>(), any::<i32>(), any::<i32>()),
+                1..32,
+            ),
+        ) {
+            let mut mask = start & (MASK_ALL_BUTTONS | MASK_ALL_MODIFIERS);
+            for (keyboard, message, code, data, x, y) in events {
+                let converted = if keyboard {
+                    let raw = RawKeyboard { message, vk_code: code, scan_code: data, extended: x < 0 };
+                    convert_keyboard(&raw, &mut mask, |_, _| Key::Unknown(0)).into_iter().collect()
+                } else {
+                    convert_mouse(&RawMouse { message, x, y, mouse_data: data }, &mut mask)
+                };
+                for event in &converted {
+                    assert_consistent(event, mask);
+                }
+                assert_mask(mask);
+            }
+        }
+    }
+}
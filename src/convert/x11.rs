@@ -0,0 +1,356 @@
+//! X11: core protocol events as XRecord delivers them.
+
+#![warn(clippy::undocumented_unsafe_blocks)]
+
+use super::{button, motion, update, with_mask};
+use crate::event::{Backend, Button, Event, ScrollDirection};
+use crate::hook::EventMask;
+use crate::keycode::Key;
+use crate::state::{MASK_ALT, MASK_CTRL, MASK_META, MASK_SHIFT};
+
+/// Core protocol event codes (`X.h`).
+pub(crate) const KEY_PRESS: u8 = 2;
+pub(crate) const KEY_RELEASE: u8 = 3;
+pub(crate) const BUTTON_PRESS: u8 = 4;
+pub(crate) const BUTTON_RELEASE: u8 = 5;
+pub(crate) const MOTION_NOTIFY: u8 = 6;
+pub(crate) const MAPPING_NOTIFY: u8 = 34;
+
+/// Offsets of the fields read from a key, button or motion event on the
+/// wire (`xKeyButtonPointer` in `Xproto.h`, 32 bytes). They are the same on
+/// every architecture: the protocol fixes them, unlike the C structs Xlib
+/// lays out for the host.
+const TYPE: usize = 0;
+const DETAIL: usize = 1;
+const ROOT_X: usize = 20;
+const ROOT_Y: usize = 22;
+
+/// The fields of a key, button or motion event that are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RawEvent {
+    /// Event code, such as [`KEY_PRESS`].
+    pub(crate) type_: u8,
+    /// Keycode or button number.
+    pub(crate) detail: u8,
+    pub(crate) root_x: i16,
+    pub(crate) root_y: i16,
+}
+
+impl RawEvent {
+    /// Read an event in the wire layout, in the client's byte order.
+    ///
+    /// `None` if `data` is too short to hold the fields.
+    pub(crate) fn parse(data: &[u8]) -> Option<Self> {
+        let field = |offset: usize| -> Option<i16> {
+            let bytes = data.get(offset..offset + 2)?;
+            Some(i16::from_ne_bytes([bytes[0], bytes[1]]))
+        };
+        Some(Self {
+            type_: *data.get(TYPE)?,
+            detail: *data.get(DETAIL)?,
+            root_x: field(ROOT_X)?,
+            root_y: field(ROOT_Y)?,
+        })
+    }
+}
+
+/// The bytes of an XRecord datum: `units` 4-byte units at `data`, or `None`
+/// if there are none or they couldn't all be addressed.
+///
+/// # Safety
+///
+/// Unless null, `data` must point to `units * 4` initialized bytes that stay
+/// valid and unchanged for `'a`, as the data of an `XRecordInterceptData`
+/// does until it is freed.
+pub(crate) unsafe fn datum<'a>(data: *const u8, units: usize) -> Option<&'a [u8]> {
+    let len = units
+        .checked_mul(4)
+        .filter(|&len| len <= isize::MAX as usize)?;
+    if data.is_null() || len == 0 {
+        return None;
+    }
+    // SAFETY: non-null, and the caller vouches for `len` bytes living for
+    // `'a`; bytes need no alignment
+    Some(unsafe { std::slice::from_raw_parts(data, len) })
+}
+
+/// The span of core event codes to record for the categories in `events`,
+/// or `None` for none of them.
+///
+/// XRecord takes one span, so keys and motion record buttons too. Motion
+/// records buttons either way: a drag is told from a move by the buttons
+/// held.
+pub(crate) fn record_range(events: EventMask) -> Option<(u8, u8)> {
+    let keys = events
+        .contains(EventMask::KEYBOARD)
+        .then_some((KEY_PRESS, KEY_RELEASE));
+    let mouse = match (
+        events.intersects(EventMask::MOUSE_BUTTONS | EventMask::MOUSE_WHEEL),
+        events.contains(EventMask::MOUSE_MOTION),
+    ) {
+        (_, true) => Some((BUTTON_PRESS, MOTION_NOTIFY)),
+        (true, false) => Some((BUTTON_PRESS, BUTTON_RELEASE)),
+        (false, false) => None,
+    };
+    match (keys, mouse) {
+        (Some((first, _)), Some((_, last))) => Some((first, last)),
+        (keys, mouse) => keys.or(mouse),
+    }
+}
+
+/// The modifier bit of a keycode, on the standard evdev-based keymap.
+fn modifier_mask(code: u8) -> u32 {
+    match code {
+        50 | 62 => MASK_SHIFT,  // Shift L/R
+        37 | 105 => MASK_CTRL,  // Control L/R
+        64 | 108 => MASK_ALT,   // Alt L/R
+        133 | 134 => MASK_META, // Super L/R
+        _ => 0,
+    }
+}
+
+/// Convert an event, resolving keycodes with `resolve_key`.
+pub(crate) fn convert(
+    raw: &RawEvent,
+    mask: &mut u32,
+    resolve_key: impl FnOnce(u8) -> Key,
+) -> Option<Event> {
+    let (x, y) = (f64::from(raw.root_x), f64::from(raw.root_y));
+    let code = raw.detail;
+    match raw.type_ {
+        KEY_PRESS | KEY_RELEASE => {
+            let pressed = raw.type_ == KEY_PRESS;
+            update(mask, modifier_mask(code), pressed);
+            let key = resolve_key(code);
+            let event = if pressed {
+                Event::key_pressed(key, code.into())
+            } else {
+                Event::key_released(key, code.into())
+            };
+            Some(with_mask(event, *mask))
+        }
+
+        BUTTON_PRESS => {
+            // Scroll wheel events in X11
+            let direction = match code {
+                4 => Some(ScrollDirection::Up),
+                5 => Some(ScrollDirection::Down),
+                6 => Some(ScrollDirection::Left),
+                7 => Some(ScrollDirection::Right),
+                _ => None,
+            };
+            if let Some(direction) = direction {
+                return Some(with_mask(Event::mouse_wheel(x, y, direction, 1.0), *mask));
+            }
+            let pressed = Button::from_platform_code(Backend::X11, code.into())?;
+            Some(button(pressed, true, x, y, mask))
+        }
+
+        BUTTON_RELEASE => {
+            // Wheel "release" is ignored
+            let released = Button::from_platform_code(Backend::X11, code.into())?;
+            Some(button(released, false, x, y, mask))
+        }
+
+        // THE KEY FIX: Check button state for drag detection
+        MOTION_NOTIFY => Some(motion(x, y, *mask)),
+
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::invariants::{assert_consistent, assert_mask};
+    use crate::event::EventType;
+    use crate::state::{MASK_ALL_BUTTONS, MASK_ALL_MODIFIERS, MASK_BUTTON1};
+    use proptest::prelude::*;
+
+    fn raw(type_: u8, detail: u8) -> RawEvent {
+        RawEvent {
+            type_,
+            detail,
+            root_x: 10,
+            root_y: -5,
+        }
+    }
+
+    #[test]
+    fn test_parse_wire_layout() {
+        let mut data = [0u8; 32];
+        data[0] = BUTTON_PRESS;
+        data[1] = 1;
+        data[20..22].copy_from_slice(&300i16.to_ne_bytes());
+        data[22..24].copy_from_slice(&(-2i16).to_ne_bytes());
+        assert_eq!(
+            RawEvent::parse(&data),
+            Some(RawEvent {
+                type_: BUTTON_PRESS,
+                detail: 1,
+                root_x: 300,
+                root_y: -2,
+            })
+        );
+        assert_eq!(RawEvent::parse(&data[..23]), None);
+        assert_eq!(RawEvent::parse(&[]), None);
+    }
+
+    /// `xKeyButtonPointer` as `Xproto.h` declares it, to check the offsets
+    /// read against.
+    #[repr(C)]
+    #[derive(Default)]
+    struct WireEvent {
+        type_: u8,
+        detail: u8,
+        sequence: u16,
+        time: u32,
+        root: u32,
+        event: u32,
+        child: u32,
+        root_x: i16,
+        root_y: i16,
+        event_x: i16,
+        event_y: i16,
+        state: u16,
+        same_screen: u8,
+        pad: u8,
+    }
+
+    impl WireEvent {
+        /// The event's bytes, field by field in the client's byte order.
+        fn to_bytes(&self) -> Vec<u8> {
+            let mut bytes = vec![self.type_, self.detail];
+            bytes.extend(self.sequence.to_ne_bytes());
+            for word in [self.time, self.root, self.event, self.child] {
+                bytes.extend(word.to_ne_bytes());
+            }
+            for half in [self.root_x, self.root_y, self.event_x, self.event_y] {
+                bytes.extend(half.to_ne_bytes());
+            }
+            bytes.extend(self.state.to_ne_bytes());
+            bytes.extend([self.same_screen, self.pad]);
+            bytes
+        }
+    }
+
+    #[test]
+    fn test_offsets_match_protocol_layout() {
+        use std::mem::{offset_of, size_of};
+
+        assert_eq!(size_of::<WireEvent>(), 32);
+        assert_eq!(offset_of!(WireEvent, type_), TYPE);
+        assert_eq!(offset_of!(WireEvent, detail), DETAIL);
+        assert_eq!(offset_of!(WireEvent, root_x), ROOT_X);
+        assert_eq!(offset_of!(WireEvent, root_y), ROOT_Y);
+
+        let event = WireEvent {
+            type_: MOTION_NOTIFY,
+            sequence: 0xBEEF,
+            time: u32::MAX,
+            root_x: -1234,
+            root_y: 5678,
+            event_x: 1,
+            event_y: 2,
+            state: 0x0101,
+            ..WireEvent::default()
+        };
+        let bytes = event.to_bytes();
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(
+            RawEvent::parse(&bytes),
+            Some(RawEvent {
+                type_: MOTION_NOTIFY,
+                detail: 0,
+                root_x: -1234,
+                root_y: 5678,
+            })
+        );
+    }
+
+    #[test]
+    fn test_datum_bounds() {
+        let words = [0x0403_0201u32, 0x0807_0605];
+        let data = words.as_ptr().cast::<u8>();
+        // SAFETY: `words` holds 2 units and outlives the slices
+        unsafe {
+            assert_eq!(datum(data, 2).map(<[u8]>::len), Some(8));
+            assert_eq!(datum(data, 1), Some(&words[0].to_ne_bytes()[..]));
+            assert_eq!(datum(data, 0), None);
+            assert_eq!(datum(std::ptr::null(), 2), None);
+            // Too long to address, rejected before the pointer is used
+            assert_eq!(datum(data, isize::MAX as usize / 4 + 1), None);
+            assert_eq!(datum(data, usize::MAX), None);
+        }
+    }
+
+    #[test]
+    fn test_drag_between_press_and_release() {
+        let mut mask = 0;
+        let press = convert(&raw(BUTTON_PRESS, 1), &mut mask, |_| Key::KeyA).unwrap();
+        assert_eq!(press.event_type, EventType::MousePressed);
+        assert_eq!(mask, MASK_BUTTON1);
+
+        let drag = convert(&raw(MOTION_NOTIFY, 0), &mut mask, |_| Key::KeyA).unwrap();
+        assert_eq!(drag.event_type, EventType::MouseDragged);
+        assert_eq!(
+            (drag.mouse.as_ref().unwrap().x, drag.mask),
+            (10.0, MASK_BUTTON1)
+        );
+
+        convert(&raw(BUTTON_RELEASE, 1), &mut mask, |_| Key::KeyA).unwrap();
+        assert_eq!(mask, 0);
+        // Wheel buttons scroll and leave the mask alone
+        let wheel = convert(&raw(BUTTON_PRESS, 5), &mut mask, |_| Key::KeyA).unwrap();
+        assert_eq!(wheel.wheel.unwrap().direction, ScrollDirection::Down);
+        assert_eq!(mask, 0);
+    }
+
+    #[test]
+    fn test_record_range() {
+        assert_eq!(
+            record_range(EventMask::ALL),
+            Some((KEY_PRESS, MOTION_NOTIFY))
+        );
+        assert_eq!(
+            record_range(EventMask::KEYBOARD),
+            Some((KEY_PRESS, KEY_RELEASE))
+        );
+        assert_eq!(
+            record_range(EventMask::MOUSE_WHEEL),
+            Some((BUTTON_PRESS, BUTTON_RELEASE))
+        );
+        assert_eq!(
+            record_range(EventMask::MOUSE_MOTION),
+            Some((BUTTON_PRESS, MOTION_NOTIFY))
+        );
+        assert_eq!(
+            record_range(EventMask::KEYBOARD | EventMask::MOUSE_BUTTONS),
+            Some((KEY_PRESS, BUTTON_RELEASE))
+        );
+        assert_eq!(record_range(EventMask::TOUCH), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_fuzz_parse_never_panics(data in proptest::collection::vec(any::<u8>(), 0..40)) {
+            let parsed = RawEvent::parse(&data);
+            prop_assert_eq!(parsed.is_some(), data.len() >= 24);
+        }
+
+        #[test]
+        fn test_fuzz_convert_keeps_mask_and_events_consistent(
+            start in any::<u32>(),
+            events in proptest::collection::vec((0u8..40, any::<u8>(), any::<i16>(), any::<i16>()), 1..32),
+        ) {
+            let mut mask = start & (MASK_ALL_BUTTONS | MASK_ALL_MODIFIERS);
+            for (type_, detail, root_x, root_y) in events {
+                let raw = RawEvent { type_, detail, root_x, root_y };
+                if let Some(event) = convert(&raw, &mut mask, |_| Key::Unknown(0)) {
+                    assert_consistent(&event, mask);
+                }
+                assert_mask(mask);
+            }
+        }
+    }
+}
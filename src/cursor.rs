@@ -0,0 +1,221 @@
+//! Cursor visibility and warping.
+//!
+//! [`hide_cursor`] hides the cursor until every returned [`HiddenCursor`]
+//! guard is dropped, so nested hides from different parts of a program
+//! behave. Like cursor confinement, the cursor is shown again when the
+//! process exits, and when a thread panics in builds with `panic = "abort"`.
+//!
+//! [`warp_cursor`] moves the cursor without going through the input
+//! system, unlike [`mouse_move`](crate::mouse_move), which is what
+//! pointer-lock style code wants. Whether listeners see the warp depends on
+//! the platform:
+//!
+//! - **macOS** (`CGWarpMouseCursorPosition`): no event.
+//! - **Windows** (`SetCursorPos`): no event; low-level hooks aren't called.
+//! - **X11** (`XWarpPointer`): the server generates motion for warps, so
+//!   listeners receive a `MouseMoved`.
+//! - **evdev** (Wayland): not supported.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::cursor::{hide_cursor, warp_cursor};
+//!
+//! let hidden = hide_cursor()?;
+//! warp_cursor(500.0, 500.0)?;
+//! // ...
+//! drop(hidden);
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::platform;
+use std::sync::{Mutex, TryLockError};
+
+/// Outstanding [`HiddenCursor`] guards.
+static HIDDEN: Mutex<u32> = Mutex::new(0);
+
+/// Restores run when the process exits, see [`restore_on_exit`].
+static EXIT_RESTORES: Mutex<Vec<fn()>> = Mutex::new(Vec::new());
+
+unsafe extern "C" {
+    fn atexit(callback: extern "C" fn()) -> std::ffi::c_int;
+}
+
+/// Keeps the cursor hidden until dropped.
+#[derive(Debug)]
+#[must_use = "the cursor is shown again as soon as the guard is dropped"]
+pub struct HiddenCursor {
+    _private: (),
+}
+
+impl HiddenCursor {
+    /// Drop this guard, reporting platform errors.
+    pub fn show(self) -> Result<()> {
+        std::mem::forget(self);
+        show()
+    }
+}
+
+impl Drop for HiddenCursor {
+    fn drop(&mut self) {
+        if let Err(e) = show() {
+            log::warn!("Failed to show cursor: {}", e);
+        }
+    }
+}
+
+/// Hide the cursor until the guard (and any other outstanding ones) is dropped.
+///
+/// On Windows, `ShowCursor` only affects this process's windows and keeps a
+/// per-thread count, so hide and show from the same thread. On macOS, the
+/// cursor is only hidden while this process is the active application.
+pub fn hide_cursor() -> Result<HiddenCursor> {
+    let mut hidden = HIDDEN
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    acquire(&mut hidden, platform::hide_cursor)?;
+    restore_on_exit(show_now);
+    Ok(HiddenCursor { _private: () })
+}
+
+/// Move the cursor to `(x, y)` without simulating mouse input.
+///
+/// See the [module docs](self) for which platforms report warps to listeners.
+pub fn warp_cursor(x: f64, y: f64) -> Result<()> {
+    if !x.is_finite() || !y.is_finite() {
+        return Err(Error::Other(format!(
+            "cannot warp the cursor to ({}, {})",
+            x, y
+        )));
+    }
+    platform::warp_cursor(x, y)
+}
+
+fn show() -> Result<()> {
+    let mut hidden = HIDDEN
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    release(&mut hidden, platform::show_cursor)
+}
+
+/// Show the cursor regardless of outstanding guards, without waiting on the lock.
+fn show_now() {
+    let mut hidden = match HIDDEN.try_lock() {
+        Ok(hidden) => hidden,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+    if *hidden > 0 {
+        *hidden = 0;
+        let _ = platform::show_cursor();
+    }
+}
+
+/// Count a hide, calling `hide` for the first one.
+fn acquire(hidden: &mut u32, hide: impl FnOnce() -> Result<()>) -> Result<()> {
+    if *hidden == 0 {
+        hide()?;
+    }
+    *hidden += 1;
+    Ok(())
+}
+
+/// Count a show, calling `show` once the last hide is undone.
+fn release(hidden: &mut u32, show: impl FnOnce() -> Result<()>) -> Result<()> {
+    match *hidden {
+        0 => Ok(()),
+        1 => {
+            *hidden = 0;
+            show()
+        }
+        _ => {
+            *hidden -= 1;
+            Ok(())
+        }
+    }
+}
+
+extern "C" fn run_exit_restores() {
+    let restores = match EXIT_RESTORES.try_lock() {
+        Ok(restores) => restores.clone(),
+        Err(TryLockError::Poisoned(e)) => e.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+    for restore in restores {
+        restore();
+    }
+}
+
+fn install_exit_hooks() {
+    if unsafe { atexit(run_exit_restores) } != 0 {
+        log::warn!("Failed to register cursor exit handler");
+    }
+
+    #[cfg(panic = "abort")]
+    {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            run_exit_restores();
+            previous(info);
+        }));
+    }
+}
+
+/// Run `restore` when the process exits, or when a thread panics in builds
+/// with `panic = "abort"` (guards are dropped while unwinding otherwise).
+///
+/// `restore` must not block, as it may run while other threads hold locks.
+pub(crate) fn restore_on_exit(restore: fn()) {
+    let Ok(mut restores) = EXIT_RESTORES.lock() else {
+        return;
+    };
+    if restores.is_empty() {
+        install_exit_hooks();
+    }
+    if !restores.contains(&restore) {
+        restores.push(restore);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_nested_hides_are_counted() {
+        let hides = Cell::new(0);
+        let shows = Cell::new(0);
+        let hide = || {
+            hides.set(hides.get() + 1);
+            Ok(())
+        };
+        let show = || {
+            shows.set(shows.get() + 1);
+            Ok(())
+        };
+
+        let mut hidden = 0;
+        acquire(&mut hidden, hide).unwrap();
+        acquire(&mut hidden, hide).unwrap();
+        assert_eq!((hides.get(), hidden), (1, 2));
+
+        release(&mut hidden, show).unwrap();
+        assert_eq!(shows.get(), 0);
+        release(&mut hidden, show).unwrap();
+        assert_eq!((shows.get(), hidden), (1, 0));
+
+        // Unbalanced shows are ignored
+        release(&mut hidden, show).unwrap();
+        assert_eq!(shows.get(), 1);
+    }
+
+    #[test]
+    fn test_failed_hide_is_not_counted() {
+        let mut hidden = 0;
+        let result = acquire(&mut hidden, || Err(Error::NotSupported("test".into())));
+        assert!(result.is_err());
+        assert_eq!(hidden, 0);
+    }
+}
@@ -0,0 +1,531 @@
+//! Diagnostics for bug reports.
+//!
+//! When a backend can't map a key, it reports it as [`Key::Unknown`] with
+//! the raw platform code, which alone says little about the key. The first
+//! time each unknown code is seen, monio records what the platform knows
+//! about it (scan code, X11 keysym or evdev key name, the text it types)
+//! in [`unknown_keys_seen`], so applications can include it in bug reports.
+//!
+//! In debug builds, or with the `MONIO_DEBUG_UNKNOWN_KEYS` environment
+//! variable set, each record is also logged once with `log::warn!`.
+//!
+//! Run `cargo run --example report_unknown_keys`, press the key, and paste
+//! the output into the issue.
+//!
+//! # Bug report bundles
+//!
+//! [`bug_report_bundle`] writes everything a maintainer needs to triage a
+//! key-mapping or input issue into one directory, which users can zip and
+//! attach to the issue (`cargo run --example bug_report`). The layout is
+//! stable; [`BUNDLE_VERSION`] changes when files are renamed or removed:
+//!
+//! ```text
+//! <dir>/
+//!   README.txt             monio version, bundle version and this list
+//!   diagnostics.txt        OS, backend, permissions, policies and displays;
+//!                          on evdev also grab_status() and
+//!                          dropped_frame_count()
+//!   capabilities.txt       capabilities()
+//!   system_settings.txt    display::system_settings()
+//!   layout.tsv             code, key, logical key and character of every
+//!                          keycode the backend maps, see layout_dump()
+//!   unknown_keys.txt       unknown_keys_seen(), one key per line
+//!   metrics.txt            the hook's counters and process-wide counters
+//!   flight_recording.json  only with BugReport::flight_recorder; a
+//!                          Recording, redacted
+//! ```
+//!
+//! The flight recording has its keys replaced with placeholders and its
+//! text removed unless [`BugReport::keyboard_identities`] is enabled, so a
+//! bundle doesn't leak what was typed.
+//!
+//! [`Key::Unknown`]: crate::Key::Unknown
+
+use crate::error::{Error, Result};
+use crate::hook::Hook;
+use crate::keycode::Key;
+use crate::platform;
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// Environment variable enabling unknown key logging in release builds.
+pub const UNKNOWN_KEYS_ENV: &str = "MONIO_DEBUG_UNKNOWN_KEYS";
+
+/// What the platform knows about a key monio couldn't map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKeyReport {
+    /// The backend that saw the key: `"x11"`, `"evdev"`, `"windows"` or
+    /// `"macos"`.
+    pub platform: &'static str,
+    /// The raw code, as in `Key::Unknown(code)`.
+    pub code: u32,
+    /// The hardware scan code, if the platform reports one separately.
+    pub scan_code: Option<u32>,
+    /// The platform's name for the key: the X11 keysym or the evdev key.
+    pub name: Option<String>,
+    /// What the key types on the active layout, if anything.
+    pub text: Option<String>,
+    /// How many events had this code.
+    pub count: u64,
+}
+
+impl UnknownKeyReport {
+    fn new(platform: &'static str, code: u32) -> Self {
+        Self {
+            platform,
+            code,
+            scan_code: None,
+            name: None,
+            text: None,
+            count: 1,
+        }
+    }
+}
+
+impl fmt::Display for UnknownKeyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "platform={} code={}", self.platform, self.code)?;
+        if let Some(scan_code) = self.scan_code {
+            write!(f, " scan_code={scan_code:#x}")?;
+        }
+        if let Some(name) = &self.name {
+            write!(f, " name={name}")?;
+        }
+        if let Some(text) = &self.text {
+            write!(f, " text={text:?}")?;
+        }
+        write!(f, " count={}", self.count)
+    }
+}
+
+static SEEN: Mutex<Vec<UnknownKeyReport>> = Mutex::new(Vec::new());
+
+/// Every unknown key seen by a hook in this process, in order of first
+/// appearance.
+pub fn unknown_keys_seen() -> Vec<UnknownKeyReport> {
+    SEEN.lock().map(|seen| seen.clone()).unwrap_or_default()
+}
+
+fn logging_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| cfg!(debug_assertions) || std::env::var_os(UNKNOWN_KEYS_ENV).is_some())
+}
+
+/// Record an event with an unknown key.
+///
+/// `details` fills in what the backend knows about the key, and only runs
+/// the first time `code` is seen on `platform`.
+#[cfg_attr(
+    all(target_os = "linux", not(any(feature = "x11", feature = "evdev"))),
+    allow(dead_code)
+)]
+pub(crate) fn unknown_key(
+    platform: &'static str,
+    code: u32,
+    details: impl FnOnce(&mut UnknownKeyReport),
+) {
+    let Ok(mut seen) = SEEN.lock() else {
+        return;
+    };
+    if let Some(report) = seen
+        .iter_mut()
+        .find(|r| r.platform == platform && r.code == code)
+    {
+        report.count += 1;
+        return;
+    }
+
+    let mut report = UnknownKeyReport::new(platform, code);
+    details(&mut report);
+    if logging_enabled() {
+        log::warn!("Unknown key: {report}");
+    }
+    seen.push(report);
+}
+
+/// A keycode as the active backend maps it, from [`layout_dump`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutEntry {
+    /// The raw code, as in [`KeyboardData::raw_code`](crate::event::KeyboardData::raw_code).
+    pub code: u32,
+    /// The key events with this code report.
+    pub key: Key,
+    /// The key it types on the active layout, see
+    /// [`KeyboardData::logical_key`](crate::event::KeyboardData::logical_key).
+    pub logical: Key,
+    /// The character the logical key types, if any.
+    pub char: Option<char>,
+}
+
+impl fmt::Display for LayoutEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{:?}\t{:?}\t", self.code, self.key, self.logical)?;
+        match self.char {
+            Some(ch) => write!(f, "{ch:?}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Every keycode the active backend maps to a key, in code order.
+///
+/// Where the backend follows the layout (X11 while a hook runs, macOS),
+/// the logical keys and characters are those of the active layout.
+pub fn layout_dump() -> Vec<LayoutEntry> {
+    platform::KEYCODES
+        .filter_map(|code| {
+            let key = platform::key_for_code(code);
+            if matches!(key, Key::Unknown(_)) {
+                return None;
+            }
+            let logical = platform::logical_key(code, key);
+            Some(LayoutEntry {
+                code,
+                key,
+                logical,
+                char: logical.as_char(),
+            })
+        })
+        .collect()
+}
+
+/// Version of the [bundle layout](self#bug-report-bundles).
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// What goes into a bug report bundle, see [`bug_report_bundle`].
+///
+/// # Example
+///
+/// ```no_run
+/// use monio::Hook;
+/// use monio::debug::BugReport;
+///
+/// let hook = Hook::new();
+/// // ... run the hook until the problem shows up ...
+/// BugReport::new().hook(&hook).write("monio-bug-report").unwrap();
+/// ```
+#[derive(Default)]
+pub struct BugReport<'a> {
+    hook: Option<&'a Hook>,
+    #[cfg(all(feature = "recorder", feature = "serde"))]
+    flight: Option<&'a crate::recorder::FlightRecorder>,
+    keyboard_identities: bool,
+}
+
+impl<'a> BugReport<'a> {
+    /// A report of the process-wide state, without a flight recording.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Include the metrics of `hook`.
+    pub fn hook(mut self, hook: &'a Hook) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    /// Include what `recorder` kept, as `flight_recording.json`.
+    #[cfg(all(feature = "recorder", feature = "serde"))]
+    pub fn flight_recorder(mut self, recorder: &'a crate::recorder::FlightRecorder) -> Self {
+        self.flight = Some(recorder);
+        self
+    }
+
+    /// Keep which keys were pressed and what was typed in the flight
+    /// recording. Off by default: keys are replaced with placeholders and
+    /// text is removed.
+    pub fn keyboard_identities(mut self, enabled: bool) -> Self {
+        self.keyboard_identities = enabled;
+        self
+    }
+
+    /// How the flight recording is redacted.
+    #[cfg(all(feature = "recorder", feature = "serde"))]
+    fn redaction(&self) -> crate::recorder::RedactionOptions {
+        use crate::recorder::{KeyRedaction, RedactionOptions, TextRedaction};
+        if self.keyboard_identities {
+            RedactionOptions {
+                keyboard: KeyRedaction::Keep,
+                text: TextRedaction::Keep,
+                ..RedactionOptions::default()
+            }
+        } else {
+            RedactionOptions::default()
+        }
+    }
+
+    /// Write the bundle into the directory `dir`, creating it if needed.
+    /// Files from an earlier bundle in the same directory are replaced.
+    pub fn write(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)
+            .map_err(|e| Error::Other(format!("Failed to create bug report directory: {}", e)))?;
+        let write = |name: &str, contents: String| {
+            std::fs::write(dir.join(name), contents)
+                .map_err(|e| Error::Other(format!("Failed to write {}: {}", name, e)))
+        };
+
+        #[cfg_attr(not(all(feature = "recorder", feature = "serde")), allow(unused_mut))]
+        let mut files = vec![
+            "diagnostics.txt",
+            "capabilities.txt",
+            "system_settings.txt",
+            "layout.tsv",
+            "unknown_keys.txt",
+            "metrics.txt",
+        ];
+        write("diagnostics.txt", diagnostics())?;
+        write(
+            "capabilities.txt",
+            format!("{:#?}\n", crate::capabilities::capabilities()),
+        )?;
+        write(
+            "system_settings.txt",
+            match crate::display::system_settings() {
+                Ok(settings) => format!("{settings:#?}\n"),
+                Err(e) => format!("error: {e}\n"),
+            },
+        )?;
+        let mut layout = String::from("code\tkey\tlogical\tchar\n");
+        for entry in layout_dump() {
+            let _ = writeln!(layout, "{entry}");
+        }
+        write("layout.tsv", layout)?;
+        let mut unknown = String::new();
+        for report in unknown_keys_seen() {
+            let _ = writeln!(unknown, "{report}");
+        }
+        write("unknown_keys.txt", unknown)?;
+        write("metrics.txt", metrics(self.hook))?;
+
+        #[cfg(all(feature = "recorder", feature = "serde"))]
+        if let Some(recorder) = self.flight {
+            recorder
+                .dump()
+                .redact(self.redaction())
+                .save(dir.join("flight_recording.json"))?;
+            files.push("flight_recording.json");
+        }
+
+        let mut readme = format!(
+            "monio {} bug report (bundle version {})\n\n",
+            env!("CARGO_PKG_VERSION"),
+            BUNDLE_VERSION
+        );
+        for file in files {
+            let _ = writeln!(readme, "{file}");
+        }
+        let _ = writeln!(
+            readme,
+            "\nkeyboard identities: {}",
+            if self.keyboard_identities {
+                "included"
+            } else {
+                "redacted"
+            }
+        );
+        write("README.txt", readme)
+    }
+}
+
+/// Write a [bug report bundle](self#bug-report-bundles) of the
+/// process-wide state into the directory `dir`.
+///
+/// To include a hook's metrics or a flight recording, use [`BugReport`].
+pub fn bug_report_bundle(dir: impl AsRef<Path>) -> Result<()> {
+    BugReport::new().write(dir)
+}
+
+/// The contents of `diagnostics.txt`.
+fn diagnostics() -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "monio: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        out,
+        "os: {} ({})",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(out, "backend: {}", platform::BACKEND);
+    let _ = writeln!(
+        out,
+        "accessibility: {:?}",
+        crate::permissions::accessibility_status()
+    );
+    let _ = writeln!(out, "grab policy: {:?}", crate::policy::grab_policy());
+    let _ = writeln!(
+        out,
+        "simulation policy: {:?}",
+        crate::policy::simulation_policy()
+    );
+    let _ = writeln!(out, "suspended: {}", crate::suspend::is_suspended());
+    let _ = writeln!(
+        out,
+        "stuck button threshold: {:?}",
+        crate::watchdog::stuck_button_threshold()
+    );
+    match crate::display::displays() {
+        Ok(displays) => {
+            let _ = writeln!(out, "displays: {}", displays.len());
+            for display in displays {
+                let _ = writeln!(out, "  {display:?}");
+            }
+        }
+        Err(e) => {
+            let _ = writeln!(out, "displays: error: {e}");
+        }
+    }
+    #[cfg(all(target_os = "linux", feature = "evdev", not(feature = "x11")))]
+    {
+        let grabbed = platform::grab_status();
+        let _ = writeln!(out, "grabbed devices: {}", grabbed.len());
+        for device in grabbed {
+            let grabbed_at = device
+                .grabbed_at
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "  {} {:?} grabbed at {:.3} (unix)",
+                device.path.display(),
+                device.name,
+                grabbed_at.as_secs_f64()
+            );
+        }
+        let _ = writeln!(out, "dropped frames: {}", platform::dropped_frame_count());
+    }
+    out
+}
+
+/// The contents of `metrics.txt`.
+fn metrics(hook: Option<&Hook>) -> String {
+    let mut out = String::new();
+    match hook {
+        Some(hook) => {
+            let _ = writeln!(out, "hook running: {}", hook.is_running());
+            let _ = writeln!(out, "uptime: {:?}", hook.uptime());
+            let _ = writeln!(out, "handler timeouts: {}", hook.handler_timeouts());
+            let _ = writeln!(out, "dropped events: {}", hook.dropped_events());
+            let _ = writeln!(out, "queue latency p99: {:?}", hook.queue_latency_p99());
+            let _ = writeln!(out, "grab enabled: {}", hook.is_grab_enabled());
+            let _ = writeln!(out, "grab mode changes: {}", hook.grab_mode_changes());
+        }
+        None => {
+            let _ = writeln!(out, "hook: not included");
+        }
+    }
+    let _ = writeln!(
+        out,
+        "stuck button corrections: {}",
+        crate::watchdog::stuck_button_corrections()
+    );
+    #[cfg(feature = "failsafe")]
+    let _ = writeln!(
+        out,
+        "failsafe triggers: {}",
+        crate::failsafe::trigger_count()
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_keys_recorded_once() {
+        let mut calls = 0;
+        for _ in 0..3 {
+            unknown_key("test", 0xdead, |report| {
+                calls += 1;
+                report.scan_code = Some(0x5d);
+                report.name = Some("KEY_UNKNOWN_TEST".into());
+            });
+        }
+        assert_eq!(calls, 1);
+
+        let report = unknown_keys_seen()
+            .into_iter()
+            .find(|r| r.platform == "test" && r.code == 0xdead)
+            .unwrap();
+        assert_eq!(report.count, 3);
+        assert_eq!(
+            report.to_string(),
+            "platform=test code=57005 scan_code=0x5d name=KEY_UNKNOWN_TEST count=3"
+        );
+    }
+
+    #[test]
+    fn test_layout_dump_skips_unmapped_codes() {
+        let dump = layout_dump();
+        assert!(
+            dump.iter()
+                .all(|entry| !matches!(entry.key, Key::Unknown(_)))
+        );
+        assert!(dump.windows(2).all(|pair| pair[0].code < pair[1].code));
+        if platform::BACKEND != "none" {
+            assert!(dump.iter().any(|entry| entry.key == Key::KeyA));
+        }
+
+        let entry = LayoutEntry {
+            code: 38,
+            key: Key::KeyA,
+            logical: Key::KeyQ,
+            char: Some('q'),
+        };
+        assert_eq!(entry.to_string(), "38\tKeyA\tKeyQ\t'q'");
+    }
+
+    #[test]
+    fn test_bug_report_bundle_layout() {
+        let dir =
+            std::env::temp_dir().join(format!("monio_test_bug_report_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let hook = Hook::new();
+        BugReport::new().hook(&hook).write(&dir).unwrap();
+
+        let readme = std::fs::read_to_string(dir.join("README.txt")).unwrap();
+        for file in [
+            "diagnostics.txt",
+            "capabilities.txt",
+            "system_settings.txt",
+            "layout.tsv",
+            "unknown_keys.txt",
+            "metrics.txt",
+        ] {
+            assert!(dir.join(file).is_file(), "{file}");
+            assert!(readme.contains(file), "{file}");
+        }
+        assert!(!dir.join("flight_recording.json").exists());
+        assert!(readme.contains("keyboard identities: redacted"));
+
+        let layout = std::fs::read_to_string(dir.join("layout.tsv")).unwrap();
+        assert!(layout.starts_with("code\tkey\tlogical\tchar\n"));
+        let metrics = std::fs::read_to_string(dir.join("metrics.txt")).unwrap();
+        assert!(metrics.contains("hook running: false"));
+        #[cfg(all(target_os = "linux", feature = "evdev", not(feature = "x11")))]
+        {
+            let diagnostics = std::fs::read_to_string(dir.join("diagnostics.txt")).unwrap();
+            assert!(diagnostics.contains("grabbed devices: 0\n"));
+            assert!(diagnostics.contains("dropped frames: "));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(all(feature = "recorder", feature = "serde"))]
+    #[test]
+    fn test_bug_report_redacts_keyboard_by_default() {
+        use crate::recorder::{KeyRedaction, TextRedaction};
+
+        let redaction = BugReport::new().redaction();
+        assert_eq!(redaction.keyboard, KeyRedaction::ReplaceWithPlaceholder);
+        assert_eq!(redaction.text, TextRedaction::Remove);
+        let redaction = BugReport::new().keyboard_identities(true).redaction();
+        assert_eq!(
+            (redaction.keyboard, redaction.text),
+            (KeyRedaction::Keep, TextRedaction::Keep)
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! Scripted input for demos, without touching the OS.
+//!
+//! While a [`SyntheticSession`] is active, hooks started in this process
+//! get their events from a [`DemoScript`] instead of the OS: listeners,
+//! grabs, channels, subscriptions, statistics and the recorder all see the
+//! script play out in real time, through the same handler pipeline as real
+//! input. No permissions are needed, so examples and docs screenshots work
+//! in containers and CI.
+//!
+//! Scripts are built step by step, or converted from a recording (and so
+//! from xdotool and AutoHotkey scripts, with the `recorder` feature).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::demo::{self, DemoScript};
+//! use monio::{Button, listen};
+//! use std::time::Duration;
+//!
+//! let script = DemoScript::new()
+//!     .type_text("Hello!")
+//!     .glide_to(400.0, 300.0, Duration::from_millis(500))
+//!     .click(Button::Left)
+//!     .looping(true);
+//! let _session = demo::synthetic_session(script);
+//!
+//! // Sees the script, not the real keyboard and mouse
+//! listen(|event| println!("{:?}", event.event_type))?;
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::Result;
+use crate::event::{Button, Event, ScrollDirection};
+use crate::hook::EventHandler;
+use crate::keycode::{Key, us_keystroke};
+use crate::ordering::mask_change;
+use crate::state;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Time between two characters of [`DemoScript::type_text`].
+const KEYSTROKE: Duration = Duration::from_millis(80);
+
+/// How long buttons stay down in [`DemoScript::click`].
+const CLICK: Duration = Duration::from_millis(60);
+
+/// Time between two moves of [`DemoScript::glide_to`], about 60 Hz.
+const MOTION_STEP: Duration = Duration::from_millis(16);
+
+/// Pause between two rounds of a looping script.
+const LOOP_GAP: Duration = Duration::from_millis(500);
+
+/// How often a playing script checks whether its hook was stopped.
+const POLL: Duration = Duration::from_millis(50);
+
+/// The script of the active session, if any.
+static ACTIVE: Mutex<Option<Arc<DemoScript>>> = Mutex::new(None);
+
+/// A timed sequence of input events, see [`synthetic_session`].
+#[derive(Debug, Clone, Default)]
+pub struct DemoScript {
+    /// Events and when they happen, from the start of the script.
+    steps: Vec<(Duration, Event)>,
+    elapsed: Duration,
+    position: (f64, f64),
+    buttons_down: usize,
+    looping: bool,
+}
+
+impl DemoScript {
+    /// An empty script, with the pointer at (0, 0).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A short sample: typing, moving the mouse around, clicking and
+    /// scrolling, over and over.
+    pub fn sample() -> Self {
+        let ms = Duration::from_millis;
+        Self::new()
+            .move_to(200.0, 200.0)
+            .type_text("Hello from monio!")
+            .pause(ms(400))
+            .glide_to(600.0, 250.0, ms(600))
+            .click(Button::Left)
+            .glide_to(600.0, 450.0, ms(400))
+            .scroll(ScrollDirection::Down, 3)
+            .pause(ms(300))
+            .press(Key::ControlLeft)
+            .tap(Key::KeyS)
+            .release(Key::ControlLeft)
+            .pause(ms(300))
+            .glide_to(200.0, 200.0, ms(600))
+            .click(Button::Right)
+            .pause(ms(800))
+            .looping(true)
+    }
+
+    /// Replay a recording's events with their timing, e.g. one imported
+    /// with [`Recording::from_xdotool_script`].
+    ///
+    /// [`Recording::from_xdotool_script`]: crate::recorder::Recording::from_xdotool_script
+    #[cfg(feature = "recorder")]
+    pub fn from_recording(recording: &crate::recorder::Recording) -> Self {
+        let mut script = Self::new();
+        for recorded in &recording.events {
+            script.elapsed = recorded.elapsed;
+            if let Some(mouse) = &recorded.event.mouse {
+                script.position = (mouse.x, mouse.y);
+            }
+            script
+                .steps
+                .push((recorded.elapsed, recorded.event.clone()));
+        }
+        script
+    }
+
+    fn push(&mut self, event: Event) {
+        self.steps.push((self.elapsed, event));
+    }
+
+    /// Wait before the next step.
+    pub fn pause(mut self, duration: Duration) -> Self {
+        self.elapsed += duration;
+        self
+    }
+
+    /// Press a key.
+    pub fn press(mut self, key: Key) -> Self {
+        self.push(Event::key_pressed(key, 0));
+        self.pause(KEYSTROKE / 2)
+    }
+
+    /// Release a key.
+    pub fn release(mut self, key: Key) -> Self {
+        self.push(Event::key_released(key, 0));
+        self.pause(KEYSTROKE / 2)
+    }
+
+    /// Press and release a key.
+    pub fn tap(self, key: Key) -> Self {
+        self.press(key).release(key)
+    }
+
+    /// Type text on a US layout, with Shift for capitals and symbols.
+    pub fn type_text(mut self, text: &str) -> Self {
+        for ch in text.chars() {
+            let (key, shift) = us_keystroke(ch);
+            if shift {
+                self = self.press(Key::ShiftLeft);
+            }
+            self = self.tap(key);
+            if shift {
+                self = self.release(Key::ShiftLeft);
+            }
+        }
+        self
+    }
+
+    /// Move the pointer to `(x, y)` at once.
+    pub fn move_to(mut self, x: f64, y: f64) -> Self {
+        self.position = (x, y);
+        let event = if self.buttons_down > 0 {
+            Event::mouse_dragged(x, y)
+        } else {
+            Event::mouse_moved(x, y)
+        };
+        self.push(event);
+        self
+    }
+
+    /// Move the pointer to `(x, y)` in a straight line over `duration`.
+    pub fn glide_to(mut self, x: f64, y: f64, duration: Duration) -> Self {
+        let (x0, y0) = self.position;
+        let steps = (duration.as_millis() / MOTION_STEP.as_millis()).max(1) as u32;
+        for i in 1..=steps {
+            let t = f64::from(i) / f64::from(steps);
+            self = self
+                .pause(duration / steps)
+                .move_to(x0 + (x - x0) * t, y0 + (y - y0) * t);
+        }
+        self
+    }
+
+    /// Press a mouse button where the pointer is.
+    pub fn button_press(mut self, button: Button) -> Self {
+        let (x, y) = self.position;
+        self.buttons_down += 1;
+        self.push(Event::mouse_pressed(button, x, y));
+        self
+    }
+
+    /// Release a mouse button where the pointer is.
+    pub fn button_release(mut self, button: Button) -> Self {
+        let (x, y) = self.position;
+        self.buttons_down = self.buttons_down.saturating_sub(1);
+        self.push(Event::mouse_released(button, x, y));
+        self
+    }
+
+    /// Press and release a mouse button where the pointer is.
+    pub fn click(self, button: Button) -> Self {
+        self.button_press(button)
+            .pause(CLICK)
+            .button_release(button)
+            .pause(CLICK)
+    }
+
+    /// Turn the wheel by `clicks` notches, one at a time.
+    pub fn scroll(mut self, direction: ScrollDirection, clicks: u32) -> Self {
+        let (x, y) = self.position;
+        for _ in 0..clicks {
+            self.push(Event::mouse_wheel(x, y, direction, 1.0));
+            self = self.pause(CLICK);
+        }
+        self
+    }
+
+    /// Start over after the last step, until the hook stops. Otherwise the
+    /// hook goes quiet once the script is done.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// How long one round of the script takes.
+    pub fn duration(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Number of events in one round of the script.
+    pub fn event_count(&self) -> usize {
+        self.steps.len()
+    }
+}
+
+/// Feeds hooks from a script while alive, see [`synthetic_session`].
+#[must_use = "the session ends when dropped"]
+#[derive(Debug)]
+pub struct SyntheticSession {
+    _private: (),
+}
+
+impl Drop for SyntheticSession {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE.lock() {
+            *active = None;
+        }
+    }
+}
+
+/// Feed hooks started from now on with `script` instead of OS input, until
+/// the returned session is dropped.
+///
+/// Each hook plays the script from the start on its own thread, pacing the
+/// events in real time and stamping them as they are delivered. Hooks that
+/// were already running keep their OS input. Grab hooks can't consume
+/// anything, since nothing reaches the OS. Starting another session
+/// replaces the script for hooks started afterwards.
+pub fn synthetic_session(script: DemoScript) -> SyntheticSession {
+    if let Ok(mut active) = ACTIVE.lock() {
+        *active = Some(Arc::new(script));
+    }
+    SyntheticSession { _private: () }
+}
+
+/// The script hooks starting now play, if a session is active.
+pub(crate) fn active() -> Option<Arc<DemoScript>> {
+    ACTIVE.lock().ok()?.clone()
+}
+
+/// Play `script` to `handler` until `running` is cleared, as a hook would.
+pub(crate) fn run<H: EventHandler>(
+    running: &AtomicBool,
+    script: &DemoScript,
+    handler: H,
+) -> Result<()> {
+    handler.handle_event(&Event::hook_enabled());
+
+    let mut playback = Playback::default();
+    let mut start = Instant::now();
+    'rounds: loop {
+        for (at, event) in &script.steps {
+            if !sleep_until(running, start + *at) {
+                break 'rounds;
+            }
+            handler.handle_event(&playback.stamp(event));
+        }
+        if !script.looping || script.steps.is_empty() {
+            while sleep_until(running, Instant::now() + POLL) {}
+            break;
+        }
+        start += script.elapsed + LOOP_GAP;
+    }
+
+    playback.release_held(&handler);
+    handler.handle_event(&Event::hook_disabled());
+    Ok(())
+}
+
+/// Sleep until `deadline`, or return `false` once the hook is stopped.
+fn sleep_until(running: &AtomicBool, deadline: Instant) -> bool {
+    loop {
+        if !running.load(Ordering::SeqCst) {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        std::thread::sleep((deadline - now).min(POLL));
+    }
+}
+
+/// What a playing script holds, and where its pointer is.
+#[derive(Debug, Default)]
+struct Playback {
+    mask: u32,
+    position: (f64, f64),
+}
+
+impl Playback {
+    /// A script event as the hook delivers it now, updating the global
+    /// input state like a backend would.
+    fn stamp(&mut self, event: &Event) -> Event {
+        if let Some((bits, pressed)) = mask_change(event) {
+            if pressed {
+                self.mask |= bits;
+                state::set_mask(bits);
+            } else {
+                self.mask &= !bits;
+                state::unset_mask(bits);
+            }
+        }
+        if let Some(mouse) = &event.mouse {
+            self.position = (mouse.x, mouse.y);
+        }
+        Event {
+            time: SystemTime::now(),
+            mask: self.mask,
+            ..event.clone()
+        }
+    }
+
+    /// Release the modifiers and buttons a stopped script left held.
+    fn release_held<H: EventHandler>(&mut self, handler: &H) {
+        let held = self.mask;
+        let keys = [
+            (state::MASK_SHIFT, Key::ShiftLeft),
+            (state::MASK_CTRL, Key::ControlLeft),
+            (state::MASK_ALT, Key::AltLeft),
+            (state::MASK_META, Key::MetaLeft),
+        ];
+        for (bits, key) in keys {
+            if held & bits != 0 {
+                handler.handle_event(&self.stamp(&Event::key_released(key, 0)));
+            }
+        }
+        let (x, y) = self.position;
+        for button in state::buttons_in_mask(held) {
+            handler.handle_event(&self.stamp(&Event::mouse_released(button, x, y)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventType;
+
+    fn summary(script: &DemoScript) -> Vec<(u64, EventType)> {
+        script
+            .steps
+            .iter()
+            .map(|(at, event)| (at.as_millis() as u64, event.event_type))
+            .collect()
+    }
+
+    #[test]
+    fn test_typing_and_clicking() {
+        let script = DemoScript::new().type_text("A").click(Button::Left);
+        assert_eq!(
+            summary(&script),
+            [
+                (0, EventType::KeyPressed),
+                (40, EventType::KeyPressed),
+                (80, EventType::KeyReleased),
+                (120, EventType::KeyReleased),
+                (160, EventType::MousePressed),
+                (220, EventType::MouseReleased),
+            ]
+        );
+        let keys: Vec<_> = script
+            .steps
+            .iter()
+            .filter_map(|(_, e)| e.keyboard.as_ref().map(|kb| kb.key))
+            .collect();
+        assert_eq!(keys, [Key::ShiftLeft, Key::KeyA, Key::KeyA, Key::ShiftLeft]);
+        assert_eq!(script.duration(), Duration::from_millis(280));
+    }
+
+    #[test]
+    fn test_glide_drags_with_button_down() {
+        let script = DemoScript::new()
+            .button_press(Button::Left)
+            .glide_to(100.0, 50.0, Duration::from_millis(64))
+            .button_release(Button::Left);
+        let moves: Vec<_> = script
+            .steps
+            .iter()
+            .filter(|(_, e)| e.event_type == EventType::MouseDragged)
+            .map(|(at, e)| {
+                let mouse = e.mouse.as_ref().unwrap();
+                (at.as_millis() as u64, mouse.x, mouse.y)
+            })
+            .collect();
+        assert_eq!(
+            moves,
+            [
+                (16, 25.0, 12.5),
+                (32, 50.0, 25.0),
+                (48, 75.0, 37.5),
+                (64, 100.0, 50.0)
+            ]
+        );
+        let release = script.steps.last().unwrap().1.mouse.clone().unwrap();
+        assert_eq!((release.x, release.y), (100.0, 50.0));
+    }
+
+    #[test]
+    fn test_run_delivers_script_as_a_hook() {
+        let script = DemoScript::new()
+            .press(Key::ControlLeft)
+            .tap(Key::KeyC)
+            .button_press(Button::Left);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let handler = {
+            let seen = seen.clone();
+            let running = running.clone();
+            move |event: &Event| {
+                let mut seen = seen.lock().unwrap();
+                seen.push((event.event_type, event.mask));
+                // Stop once the button is down, leaving it and Ctrl held
+                if event.event_type == EventType::MousePressed {
+                    running.store(false, Ordering::SeqCst);
+                }
+            }
+        };
+        run(&running, &script, handler).unwrap();
+
+        let seen = seen.lock().unwrap();
+        let types: Vec<_> = seen.iter().map(|(t, _)| *t).collect();
+        assert_eq!(
+            types,
+            [
+                EventType::HookEnabled,
+                EventType::KeyPressed,
+                EventType::KeyPressed,
+                EventType::KeyReleased,
+                EventType::MousePressed,
+                EventType::KeyReleased,
+                EventType::MouseReleased,
+                EventType::HookDisabled,
+            ]
+        );
+        // Masks follow the held modifier and button
+        assert_eq!(seen[2].1, state::MASK_CTRL);
+        assert_eq!(seen[4].1, state::MASK_CTRL | state::MASK_BUTTON1);
+        assert_eq!(seen[6].1, 0);
+    }
+}
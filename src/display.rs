@@ -1,9 +1,15 @@
 //! Display and system property queries.
 
 use crate::error::Result;
+use crate::event::Event;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// A rectangle in screen coordinates.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Rect {
     /// Left coordinate.
     pub x: f64,
@@ -20,10 +26,18 @@ impl Rect {
     pub fn contains(&self, x: f64, y: f64) -> bool {
         x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
     }
+
+    /// The point inside this rectangle closest to `(x, y)`.
+    pub fn clamp(&self, x: f64, y: f64) -> (f64, f64) {
+        let right = (self.x + self.width - 1.0).max(self.x);
+        let bottom = (self.y + self.height - 1.0).max(self.y);
+        (x.clamp(self.x, right), y.clamp(self.y, bottom))
+    }
 }
 
 /// Information about a display/monitor.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DisplayInfo {
     /// Platform-specific identifier (best-effort).
     pub id: u32,
@@ -35,6 +49,26 @@ pub struct DisplayInfo {
     pub refresh_rate: Option<u32>,
     /// Whether this is the primary display.
     pub is_primary: bool,
+    /// Where the information came from, which says how far to trust it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source: DisplaySource,
+}
+
+/// Where [`DisplayInfo`] was read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DisplaySource {
+    /// The window system (Quartz, Win32, X11), with real positions and
+    /// scale factors.
+    #[default]
+    WindowSystem,
+    /// Kernel DRM connector state (Linux/evdev without a window system).
+    ///
+    /// Lower fidelity: outputs are laid out left to right in connector
+    /// order, the scale factor is always 1.0, the resolution is the
+    /// preferred mode rather than the current one, and there is no refresh
+    /// rate.
+    Drm,
 }
 
 /// System input settings (platform-specific units where noted).
@@ -49,11 +83,24 @@ pub struct SystemSettings {
     /// Mouse acceleration (platform-specific units).
     pub mouse_acceleration: Option<f64>,
     /// Mouse acceleration threshold (platform-specific units).
+    ///
+    /// On Windows this is the first of the two `SPI_GETMOUSE` thresholds.
     pub mouse_acceleration_threshold: Option<f64>,
+    /// Second mouse acceleration threshold, where the platform has one
+    /// (Windows: the second `SPI_GETMOUSE` threshold, in mickeys).
+    pub mouse_acceleration_threshold2: Option<f64>,
     /// Double-click time in milliseconds.
     pub double_click_time: Option<u32>,
+    /// How far the pointer must move with a button held before it counts
+    /// as a drag, in the units of event positions (Windows: the larger of
+    /// `SM_CXDRAG` and `SM_CYDRAG`).
+    pub drag_threshold: Option<f64>,
     /// Current keyboard layout identifier (best-effort).
     pub keyboard_layout: Option<String>,
+    /// Human-readable name of the current keyboard layout, e.g. `"US"`.
+    pub keyboard_layout_name: Option<String>,
+    /// Whether scrolling follows the content ("natural" scrolling), if known.
+    pub natural_scrolling: Option<bool>,
 }
 
 /// List all available displays.
@@ -75,3 +122,316 @@ pub fn display_at_point(x: f64, y: f64) -> Result<Option<DisplayInfo>> {
 pub fn system_settings() -> Result<SystemSettings> {
     crate::platform::system_settings()
 }
+
+/// How far (in screen points) the cursor must move past the edge of its
+/// display before it counts as being on the neighbouring one.
+const DISPLAY_HYSTERESIS: f64 = 4.0;
+
+/// How long the cached display layout is trusted.
+const DISPLAY_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Minimum time between refreshes triggered by the cursor leaving the layout.
+const DISPLAY_MISS_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The display layout, cached and re-read periodically, and sooner when a
+/// point shows up outside every known display (the layout changed).
+pub(crate) struct DisplayCache {
+    source: fn() -> Result<Vec<DisplayInfo>>,
+    displays: Vec<DisplayInfo>,
+    refreshed_at: Option<Instant>,
+}
+
+impl DisplayCache {
+    pub(crate) fn new(source: fn() -> Result<Vec<DisplayInfo>>) -> Self {
+        Self {
+            source,
+            displays: Vec::new(),
+            refreshed_at: None,
+        }
+    }
+
+    fn refresh(&mut self, now: Instant) {
+        match (self.source)() {
+            Ok(displays) => self.displays = displays,
+            Err(e) => log::debug!("Failed to refresh displays: {}", e),
+        }
+        self.refreshed_at = Some(now);
+    }
+
+    fn find(&self, x: f64, y: f64) -> Option<&DisplayInfo> {
+        self.displays.iter().find(|d| d.bounds.contains(x, y))
+    }
+
+    /// The display containing `(x, y)`, re-reading the layout first if it
+    /// is stale.
+    pub(crate) fn display_at(&mut self, x: f64, y: f64, now: Instant) -> Option<&DisplayInfo> {
+        let age = self
+            .refreshed_at
+            .map(|at| now.saturating_duration_since(at));
+        if age.is_none_or(|age| age >= DISPLAY_REFRESH_INTERVAL)
+            || (self.find(x, y).is_none()
+                && age.is_some_and(|age| age >= DISPLAY_MISS_REFRESH_INTERVAL))
+        {
+            self.refresh(now);
+        }
+        self.find(x, y)
+    }
+}
+
+/// Follows the cursor across displays, for [`EventType::DisplayChanged`].
+///
+/// [`EventType::DisplayChanged`]: crate::EventType::DisplayChanged
+pub(crate) struct DisplayTracker {
+    cache: DisplayCache,
+    current: Option<DisplayInfo>,
+}
+
+impl DisplayTracker {
+    pub(crate) fn new() -> Self {
+        Self::with_source(displays)
+    }
+
+    fn with_source(source: fn() -> Result<Vec<DisplayInfo>>) -> Self {
+        Self {
+            cache: DisplayCache::new(source),
+            current: None,
+        }
+    }
+
+    /// Move the cursor to `(x, y)`, returning `(from, to)` display ids if it
+    /// crossed onto another display.
+    pub(crate) fn update(&mut self, x: f64, y: f64, now: Instant) -> Option<(u32, u32)> {
+        let next = self.cache.display_at(x, y, now).cloned();
+
+        if let Some(current) = &self.current {
+            let b = current.bounds;
+            let margin = Rect {
+                x: b.x - DISPLAY_HYSTERESIS,
+                y: b.y - DISPLAY_HYSTERESIS,
+                width: b.width + 2.0 * DISPLAY_HYSTERESIS,
+                height: b.height + 2.0 * DISPLAY_HYSTERESIS,
+            };
+            if margin.contains(x, y) {
+                return None;
+            }
+        }
+
+        let next = next?;
+        let from = self.current.replace(next.clone())?.id;
+        (from != next.id).then_some((from, next.id))
+    }
+}
+
+/// Decides which events happened on one display, for
+/// [`Hook::restrict_to_display`](crate::Hook::restrict_to_display).
+pub(crate) struct DisplayRegion {
+    cache: DisplayCache,
+    id: u32,
+    restrict_keyboard: bool,
+    /// Whether the cursor was last seen on the display.
+    cursor_inside: bool,
+}
+
+impl DisplayRegion {
+    pub(crate) fn new(id: u32, restrict_keyboard: bool) -> Self {
+        Self::with_source(id, restrict_keyboard, displays)
+    }
+
+    pub(crate) fn with_source(
+        id: u32,
+        restrict_keyboard: bool,
+        source: fn() -> Result<Vec<DisplayInfo>>,
+    ) -> Self {
+        Self {
+            cache: DisplayCache::new(source),
+            id,
+            restrict_keyboard,
+            cursor_inside: false,
+        }
+    }
+
+    fn inside(&mut self, x: f64, y: f64, now: Instant) -> bool {
+        self.cache
+            .display_at(x, y, now)
+            .is_some_and(|display| display.id == self.id)
+    }
+
+    /// Whether `event` happened on the display.
+    ///
+    /// Mouse and touch events are placed by their position. Keyboard events
+    /// follow the cursor when keyboards are restricted too, and always count
+    /// otherwise, as do events with no position.
+    pub(crate) fn contains(&mut self, event: &Event, now: Instant) -> bool {
+        let cursor = match (&event.mouse, &event.wheel) {
+            (Some(mouse), _) => Some((mouse.x, mouse.y)),
+            (None, Some(wheel)) => Some((wheel.x, wheel.y)),
+            (None, None) => None,
+        };
+        if let Some((x, y)) = cursor {
+            self.cursor_inside = self.inside(x, y, now);
+            self.cursor_inside
+        } else if let Some(touch) = &event.touch {
+            self.inside(touch.x, touch.y, now)
+        } else if event.keyboard.is_some() {
+            !self.restrict_keyboard || self.cursor_inside
+        } else {
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{Button, EventType, ScrollDirection};
+    use crate::keycode::Key;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn display(id: u32, x: f64) -> DisplayInfo {
+        DisplayInfo {
+            id,
+            bounds: Rect {
+                x,
+                y: 0.0,
+                width: 100.0,
+                height: 100.0,
+            },
+            scale_factor: 1.0,
+            refresh_rate: None,
+            is_primary: id == 1,
+            source: DisplaySource::WindowSystem,
+        }
+    }
+
+    fn side_by_side() -> Result<Vec<DisplayInfo>> {
+        Ok(vec![display(1, 0.0), display(2, 100.0)])
+    }
+
+    #[test]
+    fn test_rect_clamp() {
+        let rect = Rect {
+            x: 10.0,
+            y: 20.0,
+            width: 100.0,
+            height: 50.0,
+        };
+        assert_eq!(rect.clamp(50.0, 30.0), (50.0, 30.0));
+        assert_eq!(rect.clamp(0.0, 0.0), (10.0, 20.0));
+        assert_eq!(rect.clamp(500.0, 500.0), (109.0, 69.0));
+        assert!(rect.contains(109.0, 69.0));
+    }
+
+    #[test]
+    fn test_display_tracker_crossing_with_hysteresis() {
+        let mut tracker = DisplayTracker::with_source(side_by_side);
+        let now = Instant::now();
+
+        // The first position only establishes the current display
+        assert_eq!(tracker.update(50.0, 50.0, now), None);
+        assert_eq!(tracker.update(99.0, 50.0, now), None);
+
+        // Jitter just across the edge doesn't count
+        assert_eq!(tracker.update(102.0, 50.0, now), None);
+        assert_eq!(tracker.update(98.0, 50.0, now), None);
+
+        assert_eq!(tracker.update(110.0, 50.0, now), Some((1, 2)));
+        assert_eq!(tracker.update(97.0, 50.0, now), None);
+        assert_eq!(tracker.update(90.0, 50.0, now), Some((2, 1)));
+
+        // Outside every display: stay put
+        assert_eq!(tracker.update(-500.0, 50.0, now), None);
+        assert_eq!(tracker.current.as_ref().map(|d| d.id), Some(1));
+    }
+
+    static REARRANGED: AtomicBool = AtomicBool::new(false);
+
+    /// Display 2 starts right of display 1 and moves to its left.
+    fn rearranged() -> Result<Vec<DisplayInfo>> {
+        let x = if REARRANGED.load(Ordering::SeqCst) {
+            -100.0
+        } else {
+            100.0
+        };
+        Ok(vec![display(1, 0.0), display(2, x)])
+    }
+
+    #[test]
+    fn test_display_tracker_follows_layout_changes() {
+        let mut tracker = DisplayTracker::with_source(rearranged);
+        let now = Instant::now();
+        assert_eq!(tracker.update(150.0, 50.0, now), None);
+
+        REARRANGED.store(true, Ordering::SeqCst);
+        // Off the cached layout: re-read it, but not on every event
+        assert_eq!(tracker.update(-50.0, 50.0, now), None);
+        assert_eq!(tracker.cache.displays[1].bounds.x, 100.0);
+        let later = now + DISPLAY_MISS_REFRESH_INTERVAL;
+        assert_eq!(tracker.update(-50.0, 50.0, later), None);
+        assert_eq!(tracker.cache.displays[1].bounds.x, -100.0);
+
+        assert_eq!(tracker.update(50.0, 50.0, later), Some((2, 1)));
+    }
+
+    #[test]
+    fn test_display_region_filters_by_position() {
+        let mut region = DisplayRegion::with_source(2, false, side_by_side);
+        let now = Instant::now();
+        assert!(!region.contains(&Event::mouse_moved(50.0, 50.0), now));
+        assert!(region.contains(&Event::mouse_moved(150.0, 50.0), now));
+        assert!(region.contains(&Event::mouse_pressed(Button::Left, 100.0, 0.0), now));
+        assert!(!region.contains(
+            &Event::mouse_wheel(99.0, 0.0, ScrollDirection::Up, 1.0),
+            now
+        ));
+        assert!(!region.contains(&Event::mouse_moved(250.0, 50.0), now));
+        let touch = |x| Event::touch(EventType::TouchBegan, 0, x, 50.0, None);
+        assert!(region.contains(&touch(150.0), now));
+        assert!(!region.contains(&touch(50.0), now));
+        // Keyboards aren't restricted by default, and lifecycle events pass
+        assert!(region.contains(&Event::key_pressed(Key::KeyA, 0), now));
+        assert!(region.contains(&Event::hook_enabled(), now));
+    }
+
+    #[test]
+    fn test_display_region_keyboard_follows_cursor() {
+        let mut region = DisplayRegion::with_source(2, true, side_by_side);
+        let now = Instant::now();
+        let key = Event::key_pressed(Key::KeyA, 0);
+        // The cursor hasn't been seen on the display yet
+        assert!(!region.contains(&key, now));
+        region.contains(&Event::mouse_moved(150.0, 50.0), now);
+        assert!(region.contains(&key, now));
+        // Touches don't move the cursor
+        region.contains(
+            &Event::touch(EventType::TouchBegan, 0, 50.0, 50.0, None),
+            now,
+        );
+        assert!(region.contains(&key, now));
+        region.contains(&Event::mouse_moved(50.0, 50.0), now);
+        assert!(!region.contains(&key, now));
+    }
+
+    static MOVED: AtomicBool = AtomicBool::new(false);
+
+    /// Display 2 moves from the right of display 1 to below it.
+    fn moved() -> Result<Vec<DisplayInfo>> {
+        let mut second = display(2, 100.0);
+        if MOVED.load(Ordering::SeqCst) {
+            second.bounds.x = 0.0;
+            second.bounds.y = 100.0;
+        }
+        Ok(vec![display(1, 0.0), second])
+    }
+
+    #[test]
+    fn test_display_region_follows_layout_changes() {
+        let mut region = DisplayRegion::with_source(2, false, moved);
+        let now = Instant::now();
+        assert!(region.contains(&Event::mouse_moved(150.0, 50.0), now));
+
+        MOVED.store(true, Ordering::SeqCst);
+        let later = now + DISPLAY_REFRESH_INTERVAL;
+        assert!(!region.contains(&Event::mouse_moved(150.0, 50.0), later));
+        assert!(region.contains(&Event::mouse_moved(50.0, 150.0), later));
+    }
+}
@@ -0,0 +1,402 @@
+//! Running a hook's mouse events through the pointer state machine.
+//!
+//! Backends report every move with a button held as
+//! [`EventType::MouseDragged`], so a click with a couple of pixels of jitter
+//! looks like a drag, especially on high-DPI displays and touchpads, and
+//! they never report clicks. [`PointerTracker`] feeds a hook's mouse events
+//! to a [`PointerStateMachine`] and reports what it derives: moves within
+//! the drag threshold as `MouseMoved`, [`EventType::DragStarted`] at the
+//! press position once the cursor leaves it, [`EventType::MouseClicked`]
+//! with the click count after a release that completes a click, and a
+//! `MouseReleased` for a release the hook missed.
+//!
+//! The threshold is set with
+//! [`Hook::drag_threshold`](crate::Hook::drag_threshold), or follows the
+//! system: the platform's drag setting where there is one (Windows),
+//! otherwise [`DEFAULT_DRAG_THRESHOLD`] points, scaled to the pixels of the
+//! display the button was pressed on. Clicks count together within the
+//! system's double-click time, or [`DEFAULT_CLICK_INTERVAL`].
+
+use crate::display::{DisplayCache, DisplayInfo};
+use crate::error::Result;
+use crate::event::{Event, EventType};
+use crate::tracking::{
+    DEFAULT_CLICK_INTERVAL, DEFAULT_DRAG_THRESHOLD, PointerConfig, PointerInput, PointerOutput,
+    PointerStateMachine,
+};
+use std::time::{Duration, Instant};
+
+/// Where the drag threshold comes from.
+enum Threshold {
+    /// A distance in the units of event positions.
+    Fixed(f64),
+    /// The system setting, or the default scaled to the display.
+    Adaptive { displays: DisplayCache },
+}
+
+/// The system's pointer settings, where it has them.
+#[derive(Debug, Clone, Copy, Default)]
+struct Settings {
+    drag_threshold: Option<f64>,
+    click_interval: Option<Duration>,
+}
+
+/// The system's settings, or none where they can't be read.
+fn system_settings() -> Settings {
+    crate::display::system_settings()
+        .map(|settings| Settings {
+            drag_threshold: settings.drag_threshold,
+            click_interval: settings
+                .double_click_time
+                .map(|ms| Duration::from_millis(ms.into())),
+        })
+        .unwrap_or_default()
+}
+
+/// Derives drags and clicks from a hook's mouse events.
+pub(crate) struct PointerTracker {
+    threshold: Threshold,
+    settings: fn() -> Settings,
+    /// The settings, read on the first press.
+    system: Option<Settings>,
+    machine: PointerStateMachine,
+    outputs: Vec<PointerOutput>,
+}
+
+impl PointerTracker {
+    /// A tracker with a fixed drag threshold.
+    pub(crate) fn new(threshold: f64) -> Self {
+        Self::with_settings(Threshold::Fixed(threshold), system_settings)
+    }
+
+    /// A tracker following the system's drag threshold and display scale.
+    pub(crate) fn adaptive() -> Self {
+        Self::adaptive_with(system_settings, crate::display::displays)
+    }
+
+    fn adaptive_with(
+        settings: fn() -> Settings,
+        displays: fn() -> Result<Vec<DisplayInfo>>,
+    ) -> Self {
+        let displays = DisplayCache::new(displays);
+        Self::with_settings(Threshold::Adaptive { displays }, settings)
+    }
+
+    fn with_settings(threshold: Threshold, settings: fn() -> Settings) -> Self {
+        Self {
+            threshold,
+            settings,
+            system: None,
+            machine: PointerStateMachine::default(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// The thresholds for a press at `(x, y)`.
+    fn config_at(&mut self, x: f64, y: f64) -> PointerConfig {
+        let system = *self.system.get_or_insert_with(self.settings);
+        let drag_threshold = match &mut self.threshold {
+            Threshold::Fixed(threshold) => *threshold,
+            Threshold::Adaptive { displays } => system.drag_threshold.unwrap_or_else(|| {
+                let scale = if crate::platform::POSITIONS_IN_POINTS {
+                    1.0
+                } else {
+                    displays
+                        .display_at(x, y, Instant::now())
+                        .map_or(1.0, |display| display.scale_factor)
+                };
+                DEFAULT_DRAG_THRESHOLD * scale
+            }),
+        };
+        PointerConfig {
+            drag_threshold,
+            click_interval: system.click_interval.unwrap_or(DEFAULT_CLICK_INTERVAL),
+            click_distance: drag_threshold.max(DEFAULT_DRAG_THRESHOLD),
+        }
+    }
+
+    /// The event to report in place of `event`, if it changes, pushing
+    /// events it implies to report before it to `before` (a drag starting,
+    /// a missed release) and after it to `after` (a click).
+    pub(crate) fn track(
+        &mut self,
+        event: &Event,
+        before: &mut Vec<Event>,
+        after: &mut Vec<Event>,
+    ) -> Option<Event> {
+        let mouse = event.mouse.as_ref()?;
+        let input = match (event.event_type, mouse.button) {
+            (EventType::MousePressed, Some(button)) => PointerInput::Press(button),
+            (EventType::MouseReleased, Some(button)) => PointerInput::Release(button),
+            (EventType::MouseMoved, _) => PointerInput::Move(None),
+            (EventType::MouseDragged, button) => {
+                PointerInput::Move(Some(button.or(self.machine.held())?))
+            }
+            _ => return None,
+        };
+        if let PointerInput::Press(_) = input
+            && self.machine.held().is_none()
+        {
+            let config = self.config_at(mouse.x, mouse.y);
+            self.machine.set_config(config);
+        }
+
+        self.outputs.clear();
+        self.machine
+            .feed(input, mouse.x, mouse.y, event.time, &mut self.outputs);
+
+        let mut replacement = None;
+        let mut reported = false;
+        for output in &self.outputs {
+            let mut derived = match *output {
+                PointerOutput::DragStarted { button, x, y } => Event::drag_started(button, x, y),
+                PointerOutput::Released {
+                    button,
+                    x,
+                    y,
+                    recovered: true,
+                } => Event::mouse_released(button, x, y),
+                PointerOutput::Clicked {
+                    button,
+                    x,
+                    y,
+                    count,
+                } => Event::mouse_clicked(button, x, y, count),
+                // No event of its own: the release that follows reports it
+                PointerOutput::DragEnded { .. } => continue,
+                PointerOutput::Moved { .. } => {
+                    reported = true;
+                    if event.event_type == EventType::MouseDragged {
+                        // Jitter within the threshold
+                        let mut moved = event.clone();
+                        moved.event_type = EventType::MouseMoved;
+                        if let Some(mouse) = &mut moved.mouse {
+                            mouse.button = None;
+                        }
+                        replacement = Some(moved);
+                    }
+                    continue;
+                }
+                PointerOutput::Pressed { .. }
+                | PointerOutput::Dragged { .. }
+                | PointerOutput::Released { .. } => {
+                    reported = true;
+                    continue;
+                }
+            };
+            derived.time = event.time;
+            derived.mask = event.mask;
+            derived.injected = event.injected;
+            if reported { &mut *after } else { &mut *before }.push(derived);
+        }
+        replacement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{DisplaySource, Rect};
+    use crate::event::Button;
+    use crate::state::MASK_BUTTON1;
+
+    /// Run `events` through `tracker`, returning what is reported.
+    fn run(tracker: &mut PointerTracker, events: &[Event]) -> Vec<(EventType, f64, f64)> {
+        let mut reported = Vec::new();
+        for event in events {
+            let (mut before, mut after) = (Vec::new(), Vec::new());
+            let event = tracker
+                .track(event, &mut before, &mut after)
+                .unwrap_or(event.clone());
+            reported.extend(before.into_iter().chain([event]).chain(after).map(|event| {
+                let mouse = event.mouse.unwrap();
+                (event.event_type, mouse.x, mouse.y)
+            }));
+        }
+        reported
+    }
+
+    fn fixed(threshold: f64) -> PointerTracker {
+        PointerTracker::with_settings(Threshold::Fixed(threshold), Settings::default)
+    }
+
+    fn dragged(x: f64, y: f64) -> Event {
+        let mut event = Event::mouse_dragged(x, y);
+        event.mask = MASK_BUTTON1;
+        if let Some(mouse) = &mut event.mouse {
+            mouse.button = Some(Button::Left);
+        }
+        event
+    }
+
+    fn released(x: f64, y: f64) -> Event {
+        let mut event = Event::mouse_released(Button::Left, x, y);
+        event.mask = 0;
+        event
+    }
+
+    #[test]
+    fn test_jitter_within_threshold_is_a_click() {
+        let mut tracker = fixed(4.0);
+        let reported = run(
+            &mut tracker,
+            &[
+                Event::mouse_pressed(Button::Left, 100.0, 100.0),
+                dragged(101.0, 100.0),
+                dragged(102.0, 101.0),
+                dragged(100.0, 99.0),
+                released(100.0, 99.0),
+            ],
+        );
+        assert_eq!(
+            reported,
+            vec![
+                (EventType::MousePressed, 100.0, 100.0),
+                (EventType::MouseMoved, 101.0, 100.0),
+                (EventType::MouseMoved, 102.0, 101.0),
+                (EventType::MouseMoved, 100.0, 99.0),
+                (EventType::MouseReleased, 100.0, 99.0),
+                (EventType::MouseClicked, 100.0, 99.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drag_starts_past_threshold() {
+        let mut tracker = fixed(4.0);
+        let reported = run(
+            &mut tracker,
+            &[
+                Event::mouse_pressed(Button::Left, 10.0, 10.0),
+                // Exactly at the threshold is still a move
+                dragged(14.0, 10.0),
+                dragged(13.0, 13.0),
+                dragged(8.0, 12.0),
+                released(8.0, 12.0),
+                // The next press starts over
+                Event::mouse_pressed(Button::Left, 50.0, 50.0),
+                dragged(51.0, 50.0),
+            ],
+        );
+        assert_eq!(
+            reported,
+            vec![
+                (EventType::MousePressed, 10.0, 10.0),
+                (EventType::MouseMoved, 14.0, 10.0),
+                (EventType::DragStarted, 10.0, 10.0),
+                (EventType::MouseDragged, 13.0, 13.0),
+                // Once started, coming back doesn't end the drag
+                (EventType::MouseDragged, 8.0, 12.0),
+                (EventType::MouseReleased, 8.0, 12.0),
+                (EventType::MousePressed, 50.0, 50.0),
+                (EventType::MouseMoved, 51.0, 50.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_derived_events_carry_input_fields() {
+        let mut tracker = fixed(2.0);
+        let (mut before, mut after) = (Vec::new(), Vec::new());
+        tracker.track(
+            &Event::mouse_pressed(Button::Right, 0.0, 0.0),
+            &mut before,
+            &mut after,
+        );
+        let mut drag = Event::mouse_dragged(5.0, 0.0);
+        drag.injected = Some(true);
+        if let Some(mouse) = &mut drag.mouse {
+            mouse.button = Some(Button::Right);
+        }
+        assert!(tracker.track(&drag, &mut before, &mut after).is_none());
+
+        let started = &before[0];
+        assert_eq!(started.mouse.as_ref().unwrap().button, Some(Button::Right));
+        assert_eq!((started.time, started.injected), (drag.time, Some(true)));
+        assert!(after.is_empty());
+    }
+
+    #[test]
+    fn test_double_click_and_missed_release() {
+        let mut tracker = fixed(4.0);
+        let mut events = vec![
+            Event::mouse_pressed(Button::Left, 0.0, 0.0),
+            released(0.0, 0.0),
+            Event::mouse_pressed(Button::Left, 1.0, 0.0),
+            released(1.0, 0.0),
+            Event::mouse_pressed(Button::Left, 1.0, 0.0),
+            // The release was lost
+            Event::mouse_moved(20.0, 0.0),
+        ];
+        let start = std::time::SystemTime::now();
+        for (i, event) in events.iter_mut().enumerate() {
+            event.time = start + Duration::from_millis(50 * i as u64);
+        }
+        let mut counts = Vec::new();
+        let mut reported = Vec::new();
+        for event in &events {
+            let (mut before, mut after) = (Vec::new(), Vec::new());
+            let event = tracker
+                .track(event, &mut before, &mut after)
+                .unwrap_or(event.clone());
+            for event in before.into_iter().chain([event]).chain(after) {
+                if event.event_type == EventType::MouseClicked {
+                    counts.push(event.mouse.as_ref().unwrap().clicks);
+                }
+                reported.push(event.event_type);
+            }
+        }
+        assert_eq!(counts, vec![1, 2]);
+        assert_eq!(
+            reported[reported.len() - 2..],
+            [EventType::MouseReleased, EventType::MouseMoved]
+        );
+    }
+
+    #[test]
+    fn test_adaptive_threshold() {
+        fn display() -> Result<Vec<DisplayInfo>> {
+            Ok(vec![DisplayInfo {
+                id: 1,
+                bounds: Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: 3840.0,
+                    height: 2160.0,
+                },
+                scale_factor: 2.0,
+                refresh_rate: None,
+                is_primary: true,
+                source: DisplaySource::WindowSystem,
+            }])
+        }
+
+        // The system setting wins
+        let mut tracker = PointerTracker::adaptive_with(
+            || Settings {
+                drag_threshold: Some(10.0),
+                click_interval: Some(Duration::from_millis(300)),
+            },
+            display,
+        );
+        let config = tracker.config_at(5.0, 5.0);
+        assert_eq!(config.drag_threshold, 10.0);
+        assert_eq!(config.click_interval, Duration::from_millis(300));
+
+        // Otherwise the default, in the display's pixels
+        let mut tracker = PointerTracker::adaptive_with(Settings::default, display);
+        let scaled = if crate::platform::POSITIONS_IN_POINTS {
+            DEFAULT_DRAG_THRESHOLD
+        } else {
+            DEFAULT_DRAG_THRESHOLD * 2.0
+        };
+        let config = tracker.config_at(5.0, 5.0);
+        assert_eq!(config.drag_threshold, scaled);
+        assert_eq!(config.click_interval, DEFAULT_CLICK_INTERVAL);
+        // Off every display, unscaled
+        assert_eq!(
+            tracker.config_at(-5.0, 5.0).drag_threshold,
+            DEFAULT_DRAG_THRESHOLD
+        );
+    }
+}
@@ -44,6 +44,27 @@ pub enum Error {
     #[error("not supported: {0}")]
     NotSupported(String),
 
+    /// A hook's settings differ from those of the hooks already running,
+    /// which the OS hook shares, see [`Hook::run`](crate::Hook::run).
+    #[error("conflicting hook settings: {0}")]
+    ConflictingConfig(String),
+
+    /// The operation is turned off for this process, see
+    /// [`policy`](crate::policy).
+    #[error("disabled by policy: {0}")]
+    DisabledByPolicy(String),
+
+    /// A check of
+    /// [`PlaybackOptions::verify_position`](crate::recorder::PlaybackOptions::verify_position)
+    /// failed, aborting playback.
+    #[cfg(feature = "recorder")]
+    #[error("playback verification failed: {0}")]
+    VerificationFailed(crate::recorder::VerificationFailure),
+
+    /// An event filter expression failed to parse.
+    #[error("invalid filter: {0}")]
+    InvalidFilter(#[from] crate::filter::FilterError),
+
     /// Other errors.
     #[error("{0}")]
     Other(String),
@@ -1,103 +1,27 @@
 //! Event types and enums for the input hook library.
 
+use crate::error::Result;
 use crate::keycode::Key;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-#[cfg(feature = "recorder")]
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-/// The type of input event.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[cfg_attr(feature = "recorder", derive(Serialize, Deserialize))]
-pub enum EventType {
-    /// Hook has been enabled and is now listening.
-    HookEnabled,
-    /// Hook has been disabled and is no longer listening.
-    HookDisabled,
-
-    /// A key was pressed down.
-    KeyPressed,
-    /// A key was released.
-    KeyReleased,
-    /// A character was typed (after dead key processing).
-    KeyTyped,
-
-    /// A mouse button was pressed.
-    MousePressed,
-    /// A mouse button was released.
-    MouseReleased,
-    /// A mouse button was clicked (press + release without movement).
-    MouseClicked,
-    /// The mouse was moved (no buttons held).
-    MouseMoved,
-    /// The mouse was moved while a button was held (drag).
-    MouseDragged,
-
-    /// The mouse wheel was scrolled.
-    MouseWheel,
-}
-
-/// Mouse button identifiers.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[cfg_attr(feature = "recorder", derive(Serialize, Deserialize))]
-pub enum Button {
-    /// Left mouse button (Button 1).
-    Left,
-    /// Right mouse button (Button 2).
-    Right,
-    /// Middle mouse button (Button 3).
-    Middle,
-    /// Extra button 1 (typically back).
-    Button4,
-    /// Extra button 2 (typically forward).
-    Button5,
-    /// Unknown or unsupported button.
-    Unknown(u8),
-}
-
-impl Button {
-    /// Get the button number (1-indexed).
-    pub fn number(&self) -> u8 {
-        match self {
-            Button::Left => 1,
-            Button::Right => 2,
-            Button::Middle => 3,
-            Button::Button4 => 4,
-            Button::Button5 => 5,
-            Button::Unknown(n) => *n,
-        }
-    }
-
-    /// Create a Button from a number (1-indexed).
-    pub fn from_number(n: u8) -> Self {
-        match n {
-            1 => Button::Left,
-            2 => Button::Right,
-            3 => Button::Middle,
-            4 => Button::Button4,
-            5 => Button::Button5,
-            _ => Button::Unknown(n),
-        }
-    }
-}
+/// Version of the serialized form of events, recordings and event logs.
+///
+/// Bumped whenever a representation changes in a way older versions of
+/// monio can't read. Saved in recording metadata and in the header line of
+/// event logs.
+pub const FORMAT_VERSION: u32 = 1;
 
-/// Scroll direction for mouse wheel events.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[cfg_attr(feature = "recorder", derive(Serialize, Deserialize))]
-pub enum ScrollDirection {
-    /// Scrolling up (away from user).
-    Up,
-    /// Scrolling down (toward user).
-    Down,
-    /// Scrolling left.
-    Left,
-    /// Scrolling right.
-    Right,
-}
+pub use monio_core::{
+    Backend, Button, DisplayChange, EventType, MouseData, ScrollDirection, ScrollPhase, TouchData,
+    WheelData,
+};
 
 /// Keyboard event data.
 #[derive(Debug, Clone, PartialEq, Eq)]
-#[cfg_attr(feature = "recorder", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct KeyboardData {
     /// The virtual key code.
     pub key: Key,
@@ -105,39 +29,38 @@ pub struct KeyboardData {
     pub raw_code: u32,
     /// The Unicode character, if this is a KeyTyped event.
     pub char: Option<char>,
+    /// Whether the key carries the extended-key flag, which tells apart e.g.
+    /// the right Ctrl, the arrow keys and numpad Enter from their twins.
+    /// Windows only; always `false` on other platforms.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extended: bool,
+    /// Whether an input method was composing when the key was pressed, so
+    /// the key went to the composer rather than the app. `None` where the
+    /// platform can't tell; see [`ime`](crate::ime).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ime_active: Option<bool>,
 }
 
-/// Mouse event data.
-#[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(feature = "recorder", derive(Serialize, Deserialize))]
-pub struct MouseData {
-    /// The mouse button (for press/release/click events).
-    pub button: Option<Button>,
-    /// X coordinate (screen coordinates).
-    pub x: f64,
-    /// Y coordinate (screen coordinates).
-    pub y: f64,
-    /// Click count (for click events).
-    pub clicks: u8,
-}
+impl KeyboardData {
+    /// The key at this key's position on a US layout, whatever it types.
+    ///
+    /// Derived from [`raw_code`](Self::raw_code); events built by hand whose
+    /// code doesn't match their key are returned as-is.
+    pub fn physical_key(&self) -> Key {
+        crate::platform::physical_key(self.raw_code, self.key)
+    }
 
-/// Mouse wheel event data.
-#[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(feature = "recorder", derive(Serialize, Deserialize))]
-pub struct WheelData {
-    /// X coordinate (screen coordinates).
-    pub x: f64,
-    /// Y coordinate (screen coordinates).
-    pub y: f64,
-    /// Scroll direction.
-    pub direction: ScrollDirection,
-    /// Amount of rotation (in platform-specific units).
-    pub delta: f64,
+    /// The key as labelled on the active keyboard layout.
+    ///
+    /// The evdev backend can't see the layout and reports the physical key.
+    pub fn logical_key(&self) -> Key {
+        crate::platform::logical_key(self.raw_code, self.key)
+    }
 }
 
 /// A complete input event.
 #[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(feature = "recorder", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Event {
     /// The type of event.
     pub event_type: EventType,
@@ -151,6 +74,41 @@ pub struct Event {
     pub mouse: Option<MouseData>,
     /// Wheel-specific data.
     pub wheel: Option<WheelData>,
+    /// Display change data.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub display: Option<DisplayChange>,
+    /// Touch-specific data.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub touch: Option<TouchData>,
+    /// Whether the event was generated by software rather than a physical
+    /// device, or `None` where the platform can't tell.
+    ///
+    /// - **Windows**: the `LLKHF_INJECTED` / `LLMHF_INJECTED` hook flags
+    /// - **macOS**: approximated from the event source state and monio's own
+    ///   marker on simulated events
+    /// - **Linux/evdev**: events from virtual (uinput) devices
+    /// - **Linux/X11**: always `None`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub injected: Option<bool>,
+    /// Process ID of the app the event is delivered to, where the platform
+    /// says.
+    ///
+    /// Cheaper than polling the active window to attribute input to apps;
+    /// on macOS, `monio::process_name` (with the `macos-targeted` feature)
+    /// names the process.
+    ///
+    /// - **macOS**: the event's `kCGEventTargetUnixProcessID`
+    /// - **Windows, Linux**: always `None`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub target_pid: Option<i32>,
+    /// How long after it happened the event reached the handler, or was
+    /// taken from a channel with
+    /// [`recv_with_latency`](crate::channel::RecvWithLatency::recv_with_latency).
+    ///
+    /// Only known where the backend timestamps events natively (Windows);
+    /// elsewhere `None`. Not serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub queue_latency: Option<Duration>,
 }
 
 impl Event {
@@ -163,6 +121,11 @@ impl Event {
             keyboard: None,
             mouse: None,
             wheel: None,
+            display: None,
+            touch: None,
+            injected: None,
+            target_pid: None,
+            queue_latency: None,
         }
     }
 
@@ -176,6 +139,16 @@ impl Event {
         Self::new(EventType::HookDisabled)
     }
 
+    /// Create a grab suspended event.
+    pub fn grab_suspended() -> Self {
+        Self::new(EventType::GrabSuspended)
+    }
+
+    /// Create a grab degraded event.
+    pub fn grab_degraded() -> Self {
+        Self::new(EventType::GrabDegraded)
+    }
+
     /// Create a key pressed event.
     pub fn key_pressed(key: Key, raw_code: u32) -> Self {
         let mut event = Self::new(EventType::KeyPressed);
@@ -183,6 +156,8 @@ impl Event {
             key,
             raw_code,
             char: None,
+            extended: false,
+            ime_active: None,
         });
         event
     }
@@ -194,6 +169,8 @@ impl Event {
             key,
             raw_code,
             char: None,
+            extended: false,
+            ime_active: None,
         });
         event
     }
@@ -205,10 +182,22 @@ impl Event {
             key,
             raw_code,
             char: Some(char),
+            extended: false,
+            ime_active: None,
         });
         event
     }
 
+    /// Create an IME composition started event.
+    pub fn ime_composition_started() -> Self {
+        Self::new(EventType::ImeCompositionStarted)
+    }
+
+    /// Create an IME composition ended event.
+    pub fn ime_composition_ended() -> Self {
+        Self::new(EventType::ImeCompositionEnded)
+    }
+
     /// Create a mouse pressed event.
     pub fn mouse_pressed(button: Button, x: f64, y: f64) -> Self {
         let mut event = Self::new(EventType::MousePressed);
@@ -258,10 +247,24 @@ impl Event {
     }
 
     /// Create a mouse dragged event.
+    ///
+    /// The button is derived from the buttons held in the current mask.
     pub fn mouse_dragged(x: f64, y: f64) -> Self {
         let mut event = Self::new(EventType::MouseDragged);
         event.mouse = Some(MouseData {
-            button: None,
+            button: crate::state::buttons_in_mask(event.mask).first().copied(),
+            x,
+            y,
+            clicks: 0,
+        });
+        event
+    }
+
+    /// Create a drag started event at the position `button` was pressed at.
+    pub fn drag_started(button: Button, x: f64, y: f64) -> Self {
+        let mut event = Self::new(EventType::DragStarted);
+        event.mouse = Some(MouseData {
+            button: Some(button),
             x,
             y,
             clicks: 0,
@@ -277,10 +280,37 @@ impl Event {
             y,
             direction,
             delta,
+            pixel_delta: None,
+            phase: None,
         });
         event
     }
 
+    /// Create a touch event of `event_type` (one of the `Touch*` types).
+    pub fn touch(event_type: EventType, id: u32, x: f64, y: f64, pressure: Option<f32>) -> Self {
+        let mut event = Self::new(event_type);
+        event.touch = Some(TouchData { id, x, y, pressure });
+        event
+    }
+
+    /// Create a display changed event at the cursor position.
+    pub fn display_changed(from: u32, to: u32, x: f64, y: f64) -> Self {
+        let mut event = Self::new(EventType::DisplayChanged);
+        event.mouse = Some(MouseData {
+            button: None,
+            x,
+            y,
+            clicks: 0,
+        });
+        event.display = Some(DisplayChange { from, to });
+        event
+    }
+
+    /// The mouse buttons held when this event occurred.
+    pub fn held_buttons(&self) -> Vec<Button> {
+        crate::state::buttons_in_mask(self.mask)
+    }
+
     /// Check if this is a keyboard event.
     pub fn is_keyboard(&self) -> bool {
         matches!(
@@ -298,7 +328,86 @@ impl Event {
                 | EventType::MouseClicked
                 | EventType::MouseMoved
                 | EventType::MouseDragged
+                | EventType::DragStarted
                 | EventType::MouseWheel
         )
     }
+
+    /// Check if this is a touch event.
+    pub fn is_touch(&self) -> bool {
+        matches!(
+            self.event_type,
+            EventType::TouchBegan | EventType::TouchMoved | EventType::TouchEnded
+        )
+    }
+
+    /// Post this event again, as it was captured.
+    ///
+    /// Meant for events a grab handler consumed: the event is re-posted
+    /// marked as synthetic, so the grab hook passes it on without showing it
+    /// to the handler again. It can be called any time after the event was
+    /// consumed, from any thread, and again to post it more than once.
+    ///
+    /// Unlike [`simulate`](crate::simulate), which goes through [`Key`]
+    /// and the current state, this keeps what the platform reported:
+    ///
+    /// - **Keys** use their [`raw_code`](KeyboardData::raw_code). On macOS
+    ///   the modifier flags come from the event's [`mask`](Self::mask); on
+    ///   Windows the [`extended`](KeyboardData::extended) flag is kept. On
+    ///   Windows and Linux, modifiers are system state, so the ones held when
+    ///   the event is re-posted apply.
+    /// - **Mouse button events** happen at their original coordinates,
+    ///   except on Linux/evdev, whose virtual pointer only moves relatively.
+    ///   On Windows and X11 the cursor is moved there first.
+    /// - Other events are simulated as usual.
+    ///
+    /// # Ordering
+    ///
+    /// The event is delivered after input the hook already passed on, and
+    /// before input that arrives after this call returns. Input arriving
+    /// while it runs may come first:
+    ///
+    /// - **macOS**: posted at the HID tap location, behind events the tap
+    ///   already passed on.
+    /// - **Windows**: appended to the system input queue with `SendInput`.
+    /// - **Linux/evdev**: written to the uinput device that also carries
+    ///   passed-through events, so it lands exactly in write order.
+    /// - **Linux/X11**: events can't be consumed, so re-posting delivers the
+    ///   event a second time.
+    ///
+    /// Re-posting a key press whose release was passed on (or the other way
+    /// round) leaves the key state out of step, so consume and re-post both
+    /// halves together.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::{Event, EventType, Hook, Key};
+    /// use std::sync::mpsc;
+    /// use std::time::Duration;
+    ///
+    /// // Delay every Enter by half a second
+    /// let (tx, rx) = mpsc::channel::<Event>();
+    /// std::thread::spawn(move || {
+    ///     for event in rx {
+    ///         std::thread::sleep(Duration::from_millis(500));
+    ///         event.reinject().unwrap();
+    ///     }
+    /// });
+    ///
+    /// Hook::new()
+    ///     .grab(move |event: &Event| {
+    ///         let is_enter = event.keyboard.as_ref().is_some_and(|kb| kb.key == Key::Enter);
+    ///         if is_enter && event.is_keyboard() {
+    ///             tx.send(event.clone()).unwrap();
+    ///             return None;
+    ///         }
+    ///         Some(event.clone())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn reinject(&self) -> Result<()> {
+        crate::policy::check_simulation()?;
+        crate::platform::reinject(self)
+    }
 }
@@ -0,0 +1,191 @@
+//! Emergency stop that works even when a grab handler is stuck.
+//!
+//! An abort chord handled by a grab hook can't fire once that hook's handler
+//! deadlocks: every event waits behind the stuck call. [`arm`] starts a
+//! separate thread that doesn't go through hooks at all. It polls the OS for
+//! which keys are physically held and, when the whole chord is, tears down
+//! every grab with direct platform calls. No user code runs on that path, and
+//! it takes none of the dispatch locks, only atomics, so a handler holding
+//! them can't block it.
+//!
+//! Tearing down releases input, it doesn't stop hooks: the stuck handler
+//! stays stuck, but input reaches other applications again.
+//!
+//! | Platform | Chord detection | Teardown |
+//! |----------|-----------------|----------|
+//! | Linux (evdev) | `EVIOCGKEY` on its own device handles | `EVIOCGRAB` off on the grabbed handles |
+//! | macOS | HID system key state | Disables the event tap |
+//! | Windows | Its own low-level keyboard hook | Unhooks monio's hooks |
+//! | Linux (X11) | Not supported: X11 grabs can't block input | |
+//!
+//! # Limitations
+//!
+//! - **Windows**: low-level hooks share the input thread's queue and are
+//!   called one after another, most recently installed first. Arm the
+//!   failsafe after starting grab hooks, so its hook sees keys before a
+//!   stuck one does. Otherwise keys only reach it once Windows gives up on
+//!   the stuck hook (`LowLevelHooksTimeout`), and input lags meanwhile.
+//! - **macOS**: a stuck tap holds up input until macOS times it out (about
+//!   a second per event) whatever the failsafe does. The failsafe makes sure
+//!   the tap stays disabled afterwards.
+//! - **evdev**: [`grab_status`](crate::grab_status) keeps listing the
+//!   devices until the stuck hook gets to run again.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::{Key, failsafe};
+//!
+//! // Ctrl+Alt+Escape releases all grabs, whatever the handlers are doing
+//! let _failsafe = failsafe::arm(&[Key::ControlLeft, Key::AltLeft, Key::Escape])?;
+//! monio::grab(|event| Some(event.clone()))?;
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::keycode::Key;
+use crate::platform::{self, KeyProbe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often the failsafe checks the keys.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Whether a failsafe is armed; there can be one at a time.
+static ARMED: AtomicBool = AtomicBool::new(false);
+
+static TRIGGERS: AtomicU64 = AtomicU64::new(0);
+
+/// An armed failsafe, disarmed when dropped.
+///
+/// Created by [`arm`].
+#[derive(Debug)]
+pub struct Failsafe {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Failsafe {
+    /// Stop watching for the chord.
+    pub fn disarm(mut self) {
+        self.disarm_inner();
+    }
+
+    fn disarm_inner(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Failsafe {
+    fn drop(&mut self) {
+        self.disarm_inner();
+    }
+}
+
+/// Release every grab when all of `chord` is held.
+///
+/// Fails with [`Error::AlreadyRunning`] if a failsafe is already armed, and
+/// with [`Error::NotSupported`] where keys can't be watched this way.
+pub fn arm(chord: &[Key]) -> Result<Failsafe> {
+    if chord.is_empty() {
+        return Err(Error::Other(
+            "The failsafe chord needs at least one key".into(),
+        ));
+    }
+    if ARMED.swap(true, Ordering::SeqCst) {
+        return Err(Error::AlreadyRunning);
+    }
+    let armed = (|| {
+        let mut probe = KeyProbe::open(chord)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("monio-failsafe".into())
+                .spawn(move || {
+                    watch(|| probe.all_held(), &stop, release_all);
+                    ARMED.store(false, Ordering::SeqCst);
+                })
+                .map_err(|e| Error::ThreadError(format!("failed to spawn failsafe: {}", e)))?
+        };
+        Ok(Failsafe {
+            stop,
+            thread: Some(thread),
+        })
+    })();
+    if armed.is_err() {
+        ARMED.store(false, Ordering::SeqCst);
+    }
+    armed
+}
+
+/// Release every grab now, the way the failsafe does.
+///
+/// Safe to call from any thread, including while a handler is stuck.
+pub fn release_all() {
+    TRIGGERS.fetch_add(1, Ordering::SeqCst);
+    platform::emergency_release();
+}
+
+/// How many times grabs were released by the failsafe or [`release_all`].
+pub fn trigger_count() -> u64 {
+    TRIGGERS.load(Ordering::SeqCst)
+}
+
+/// Poll `chord_held` until `stop` is set, calling `release` each time the
+/// chord goes down.
+fn watch(mut chord_held: impl FnMut() -> bool, stop: &AtomicBool, mut release: impl FnMut()) {
+    let mut was_held = false;
+    while !stop.load(Ordering::SeqCst) {
+        let held = chord_held();
+        if held && !was_held {
+            release();
+        }
+        was_held = held;
+        thread::park_timeout(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_releases_once_per_chord() {
+        // Held for a while, released, then held again
+        let samples = [false, true, true, true, false, true, false];
+        let stop = AtomicBool::new(false);
+        let polls = Cell::new(0);
+        let releases = Cell::new(Vec::new());
+
+        watch(
+            || {
+                let i = polls.get();
+                polls.set(i + 1);
+                if i + 1 == samples.len() {
+                    stop.store(true, Ordering::SeqCst);
+                }
+                samples[i]
+            },
+            &stop,
+            || {
+                let mut at = releases.take();
+                at.push(polls.get() - 1);
+                releases.set(at);
+            },
+        );
+        assert_eq!(releases.take(), [1, 5]);
+    }
+
+    #[test]
+    fn test_arm_rejects_empty_chord() {
+        assert!(matches!(arm(&[]), Err(Error::Other(_))));
+    }
+}
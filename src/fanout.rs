@@ -0,0 +1,1007 @@
+//! One OS hook shared by every listener in the process.
+//!
+//! Listening [`Hook`](crate::Hook)s and channel hooks don't install a hook
+//! of their own: the first to start installs one on a thread of its own,
+//! later ones join it, and the last to stop tears it down. Each listener
+//! has a queue of events, drained by its own hook thread, so a slow handler
+//! only delays itself; when its queue is full, events for it are dropped
+//! and counted.
+//!
+//! Every listener sees `HookEnabled` once it has joined a hook that is up,
+//! and `HookDisabled` once it leaves, whether or not the OS hook stays up
+//! for the others.
+//!
+//! The OS hook registers for the events all listeners want together, see
+//! [`Hook::events`](crate::Hook::events). If a listener joins wanting
+//! events it doesn't register for, it is installed again with the wider
+//! set, which may miss a few events in between.
+//!
+//! How devices are read (touch as mouse, calibration) is kept by the
+//! platform for the whole process, so hooks running at once, listeners or
+//! grabs, must agree on it: one that doesn't fails with
+//! [`Error::ConflictingConfig`]. Only one grab runs at a time.
+//!
+//! What follows from an OS event for the whole process is done once, on
+//! the OS hook thread, before it is sent to the listeners: releases of
+//! buttons the hook lost track of are made up (see
+//! [`watchdog`](crate::watchdog)), and the
+//! [`input_snapshot`](crate::input_snapshot) is fed.
+//!
+//! Grabs still install a hook each, since each decides the fate of the
+//! events it sees.
+
+use crate::calibration::DeviceTransform;
+use crate::error::{Error, Result};
+use crate::event::{Event, EventType};
+use crate::hook::{EventHandler, EventMask};
+use crate::platform;
+use crate::source;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+/// Events a listener may fall behind by before further ones are dropped.
+const QUEUE_CAPACITY: usize = 4096;
+
+/// The OS hook shared by the listeners of this process.
+static SHARED: LazyLock<Fanout> = LazyLock::new(|| Fanout::new(Arc::new(Os)));
+
+/// Listen for events on `running`'s behalf until it leaves the shared hook,
+/// with [`request_stop`](crate::hook::request_stop). Runs `handler` on the
+/// calling thread, and counts events dropped for it in `dropped`.
+pub(crate) fn listen<H: EventHandler>(
+    running: &Arc<AtomicBool>,
+    events: EventMask,
+    config: Config,
+    dropped: Arc<AtomicU64>,
+    handler: H,
+) -> Result<()> {
+    SHARED.listen(running, events, config, dropped, handler)
+}
+
+/// Claim the platform for a grab with `config`, until the claim drops.
+///
+/// Fails with [`Error::AlreadyRunning`] while another grab runs, and with
+/// [`Error::ConflictingConfig`] if running hooks use another config.
+pub(crate) fn claim_grab(config: &Config) -> Result<GrabClaim<'static>> {
+    SHARED.claim_grab(config)
+}
+
+/// Take the listener of `running` off the shared hook, tearing it down if
+/// it was the last. Returns `false` if `running` isn't listening there.
+pub(crate) fn leave(running: &AtomicBool) -> bool {
+    SHARED.leave(running)
+}
+
+/// How the platform reads devices, for every hook running at once.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Config {
+    /// See [`Hook::touch_as_mouse`](crate::Hook::touch_as_mouse).
+    pub(crate) touch_as_mouse: bool,
+    /// See [`Hook::calibration`](crate::Hook::calibration).
+    pub(crate) transforms: Vec<DeviceTransform>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            touch_as_mouse: true,
+            transforms: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Fail with [`Error::ConflictingConfig`] unless this agrees with the
+    /// config of the running hooks.
+    fn check(&self, running: &Self) -> Result<()> {
+        if self.touch_as_mouse != running.touch_as_mouse {
+            Err(Error::ConflictingConfig(format!(
+                "running hooks have touch_as_mouse set to {}",
+                running.touch_as_mouse
+            )))
+        } else if self.transforms != running.transforms {
+            Err(Error::ConflictingConfig(
+                "running hooks calibrate devices differently".into(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Installs and removes the OS hook.
+pub(crate) trait Backend: Send + Sync + 'static {
+    /// Run the hook for `events` until `running` is cleared, sending what
+    /// it sees to `router`.
+    fn run(&self, running: &Arc<AtomicBool>, events: EventMask, router: Router) -> Result<()>;
+
+    /// Ask the hook run by [`run`](Self::run) to return.
+    fn stop(&self) -> Result<()>;
+
+    /// Get ready for the first hook to start while none runs: read devices
+    /// with `config`, and forget the input state hooks before left behind.
+    fn configure(&self, config: &Config);
+
+    /// Releases to send before `event`, and what `event` really is, if the
+    /// hook lost track of a button.
+    fn correct(&self, event: &Event) -> Option<(Vec<Event>, Event)>;
+
+    /// Feed an event the hook saw into the input snapshot.
+    fn observe(&self, event: &Event);
+}
+
+/// The platform's hook, or the demo script while one plays.
+struct Os;
+
+impl Backend for Os {
+    fn run(&self, running: &Arc<AtomicBool>, events: EventMask, router: Router) -> Result<()> {
+        platform::set_event_mask(events);
+        source::run_hook(running, router)
+    }
+
+    fn stop(&self) -> Result<()> {
+        platform::stop_hook()
+    }
+
+    fn configure(&self, config: &Config) {
+        platform::set_touch_as_mouse(config.touch_as_mouse);
+        platform::set_input_transforms(config.transforms.clone());
+        crate::state::reset_mask();
+    }
+
+    fn correct(&self, event: &Event) -> Option<(Vec<Event>, Event)> {
+        crate::watchdog::check(event)
+    }
+
+    fn observe(&self, event: &Event) {
+        crate::snapshot::observe(event);
+    }
+}
+
+/// A hook's place on the shared hook.
+struct Listener {
+    /// The hook's running flag, which identifies it.
+    running: Arc<AtomicBool>,
+    events: EventMask,
+    queue: SyncSender<Event>,
+    /// Why the shared hook stopped under the listener, if it failed.
+    error: Arc<Mutex<Option<Error>>>,
+    /// Whether it was sent `HookEnabled`.
+    enabled: bool,
+    /// Events dropped because its queue was full.
+    dropped: Arc<AtomicU64>,
+    /// Whether its queue has overflowed, to warn only once.
+    overflowed: bool,
+}
+
+impl Listener {
+    fn send(&mut self, event: Event) {
+        let Err(TrySendError::Full(event)) = self.queue.try_send(event) else {
+            return;
+        };
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        if !self.overflowed {
+            self.overflowed = true;
+            log::warn!(
+                "A listener fell {} events behind; dropping events for it, starting with {:?}",
+                QUEUE_CAPACITY,
+                event.event_type
+            );
+        }
+    }
+}
+
+/// The OS hook while it is up.
+struct Installed {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    /// Events it registered for.
+    events: EventMask,
+    /// Whether it has sent `HookEnabled`.
+    enabled: bool,
+    /// Whether it is to be installed again for more events.
+    reinstall: bool,
+}
+
+impl Installed {
+    /// Start installing it again, if it is up and asked to be. Listeners
+    /// joining meanwhile wait for it to be enabled again.
+    fn begin_reinstall(&mut self) -> bool {
+        let begin = self.reinstall && self.enabled;
+        if begin {
+            self.enabled = false;
+            self.running.store(false, Ordering::SeqCst);
+        }
+        begin
+    }
+}
+
+#[derive(Default)]
+struct State {
+    listeners: Vec<Listener>,
+    installed: Option<Installed>,
+    /// The thread of an OS hook being torn down, for whoever starts the
+    /// next one, or stopped it last, to wait for.
+    retiring: Option<JoinHandle<()>>,
+    /// Whether the input snapshot was told the OS hook is enabled. Not
+    /// told again when it is installed again for more events.
+    observed: bool,
+    /// The config of the running hooks, see [`Config`].
+    config: Option<Config>,
+    /// Hooks using `config`: the OS hook while installed, and a grab.
+    users: usize,
+    /// Whether a grab runs.
+    grabbing: bool,
+}
+
+impl State {
+    /// Use `config` for one more hook, applying it if none runs.
+    fn claim_config(&mut self, backend: &dyn Backend, config: &Config) -> Result<()> {
+        match &self.config {
+            Some(running) => config.check(running)?,
+            None => {
+                backend.configure(config);
+                self.config = Some(config.clone());
+            }
+        }
+        self.users += 1;
+        Ok(())
+    }
+
+    /// Stop using the config for a hook that came down.
+    fn release_config(&mut self) {
+        self.users = self.users.saturating_sub(1);
+        if self.users == 0 {
+            self.config = None;
+        }
+    }
+
+    /// Tell the input snapshot the OS hook is disabled, if it was told it
+    /// was enabled.
+    fn unobserve(&mut self, backend: &dyn Backend) {
+        if std::mem::take(&mut self.observed) {
+            backend.observe(&Event::hook_disabled());
+        }
+    }
+
+    /// Events the listeners want together.
+    fn events(&self) -> EventMask {
+        self.listeners
+            .iter()
+            .fold(EventMask::from_bits_truncate(0), |events, listener| {
+                events | listener.events
+            })
+    }
+}
+
+/// Sends what the OS hook sees to the listeners.
+#[derive(Clone)]
+pub(crate) struct Router {
+    state: Arc<Mutex<State>>,
+    backend: Arc<dyn Backend>,
+}
+
+impl Router {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Body of the OS hook thread: run the hook, again for as long as
+    /// listeners want more events than it registered for, then see the
+    /// listeners left off if it came down under them.
+    fn run_os(
+        self,
+        running: Arc<AtomicBool>,
+        mut events: EventMask,
+        previous: Option<JoinHandle<()>>,
+    ) {
+        if let Some(previous) = previous {
+            let _ = previous.join();
+        }
+        loop {
+            let result = self.backend.run(&running, events, self.clone());
+            let mut state = self.state();
+            let current = state
+                .installed
+                .as_ref()
+                .is_some_and(|installed| Arc::ptr_eq(&installed.running, &running));
+            if !current {
+                // Torn down by the last listener leaving
+                state.unobserve(&*self.backend);
+                return;
+            }
+            let reinstall = state
+                .installed
+                .as_ref()
+                .is_some_and(|installed| installed.reinstall);
+            if result.is_ok() && reinstall && !state.listeners.is_empty() {
+                events = state.events();
+                if let Some(installed) = &mut state.installed {
+                    installed.events = events;
+                    installed.enabled = false;
+                    installed.reinstall = false;
+                }
+                running.store(true, Ordering::SeqCst);
+                continue;
+            }
+
+            // Came down on its own: the listeners stop with it
+            state.installed = None;
+            state.release_config();
+            state.unobserve(&*self.backend);
+            let message = result.as_ref().err().map(ToString::to_string);
+            let mut error = result.err();
+            for listener in state.listeners.drain(..) {
+                let reason = error
+                    .take()
+                    .or_else(|| message.clone().map(Error::HookStartFailed));
+                *listener.error.lock().unwrap_or_else(|e| e.into_inner()) = reason;
+            }
+            return;
+        }
+    }
+}
+
+impl Router {
+    /// Send `event` to the listeners that want it.
+    fn send(&self, event: &Event) {
+        self.backend.observe(event);
+        for listener in &mut self.state().listeners {
+            if listener.enabled && listener.events.includes(event.event_type) {
+                listener.send(event.clone());
+            }
+        }
+    }
+}
+
+impl EventHandler for Router {
+    fn handle_event(&self, event: &Event) {
+        match event.event_type {
+            EventType::HookEnabled => {
+                let mut state = self.state();
+                if !state.observed {
+                    state.observed = true;
+                    self.backend.observe(event);
+                }
+                for listener in state.listeners.iter_mut().filter(|l| !l.enabled) {
+                    listener.enabled = true;
+                    listener.send(event.clone());
+                }
+                let Some(installed) = &mut state.installed else {
+                    return;
+                };
+                installed.enabled = true;
+                let reinstall = installed.begin_reinstall();
+                drop(state);
+                if reinstall {
+                    let _ = self.backend.stop();
+                }
+            }
+            // Each listener is told when it leaves
+            EventType::HookDisabled => {}
+            _ => match self.backend.correct(event) {
+                Some((releases, corrected)) => {
+                    for release in &releases {
+                        self.send(release);
+                    }
+                    self.send(&corrected);
+                }
+                None => self.send(event),
+            },
+        }
+    }
+}
+
+/// Listeners sharing one OS hook, see the [module documentation](self).
+pub(crate) struct Fanout {
+    router: Router,
+}
+
+impl Fanout {
+    pub(crate) fn new(backend: Arc<dyn Backend>) -> Self {
+        Self {
+            router: Router {
+                state: Arc::default(),
+                backend,
+            },
+        }
+    }
+
+    /// Join the OS hook, installing it with `config` if it isn't up, and
+    /// run `handler` on its events until [`leave`](Self::leave) is called
+    /// with `running`. Events dropped for `handler` are counted in
+    /// `dropped`.
+    pub(crate) fn listen<H: EventHandler>(
+        &self,
+        running: &Arc<AtomicBool>,
+        events: EventMask,
+        config: Config,
+        dropped: Arc<AtomicU64>,
+        handler: H,
+    ) -> Result<()> {
+        let (queue, inbox) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let error = Arc::new(Mutex::new(None));
+        let mut state = self.router.state();
+        match (&state.installed, &state.config) {
+            (Some(_), Some(running)) => config.check(running)?,
+            _ => state.claim_config(&*self.router.backend, &config)?,
+        }
+        let mut listener = Listener {
+            running: running.clone(),
+            events,
+            queue,
+            error: error.clone(),
+            enabled: false,
+            dropped,
+            overflowed: false,
+        };
+        let mut reinstall = false;
+        match &mut state.installed {
+            Some(installed) => {
+                if installed.enabled {
+                    listener.enabled = true;
+                    listener.send(Event::hook_enabled());
+                }
+                if !installed.events.contains(events) {
+                    installed.reinstall = true;
+                    reinstall = installed.begin_reinstall();
+                }
+                state.listeners.push(listener);
+            }
+            None => {
+                state.listeners.push(listener);
+                let events = state.events();
+                let hook_running = Arc::new(AtomicBool::new(true));
+                let previous = state.retiring.take();
+                let router = self.router.clone();
+                let thread = {
+                    let running = hook_running.clone();
+                    std::thread::spawn(move || router.run_os(running, events, previous))
+                };
+                state.installed = Some(Installed {
+                    running: hook_running,
+                    thread: Some(thread),
+                    events,
+                    enabled: false,
+                    reinstall: false,
+                });
+            }
+        }
+        drop(state);
+        if reinstall {
+            let _ = self.router.backend.stop();
+        }
+
+        drain(&inbox, running, handler);
+        // The last to leave waits for the OS hook to come down
+        let retiring = self.router.state().retiring.take();
+        if let Some(thread) = retiring {
+            let _ = thread.join();
+        }
+        match error.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Take the listener of `running` off the OS hook, tearing it down if it
+    /// was the last. Returns `false` if `running` isn't listening.
+    pub(crate) fn leave(&self, running: &AtomicBool) -> bool {
+        let mut state = self.router.state();
+        let Some(index) = state
+            .listeners
+            .iter()
+            .position(|listener| std::ptr::eq(&*listener.running, running))
+        else {
+            return false;
+        };
+        // Dropping the queue ends the listener's thread
+        state.listeners.remove(index);
+        if state.listeners.is_empty()
+            && let Some(installed) = state.installed.take()
+        {
+            state.release_config();
+            installed.running.store(false, Ordering::SeqCst);
+            state.retiring = installed.thread;
+            drop(state);
+            let _ = self.router.backend.stop();
+        }
+        true
+    }
+}
+
+impl Fanout {
+    /// See [`claim_grab`].
+    pub(crate) fn claim_grab(&self, config: &Config) -> Result<GrabClaim<'_>> {
+        let mut state = self.router.state();
+        if state.grabbing {
+            return Err(Error::AlreadyRunning);
+        }
+        state.claim_config(&*self.router.backend, config)?;
+        state.grabbing = true;
+        Ok(GrabClaim(self))
+    }
+}
+
+/// A grab's hold on the platform, released on drop.
+pub(crate) struct GrabClaim<'a>(&'a Fanout);
+
+impl Drop for GrabClaim<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.router.state();
+        state.grabbing = false;
+        state.release_config();
+    }
+}
+
+/// Run `handler` on the events of `inbox` until it closes, skipping those
+/// that arrive once `running` is cleared, and tell it the hook is disabled
+/// if it was told it was enabled.
+fn drain<H: EventHandler>(inbox: &Receiver<Event>, running: &AtomicBool, handler: H) {
+    let mut enabled = false;
+    for event in inbox {
+        if event.event_type == EventType::HookEnabled {
+            enabled = true;
+        } else if !running.load(Ordering::SeqCst) {
+            continue;
+        }
+        handler.handle_event(&event);
+    }
+    if enabled {
+        handler.handle_event(&Event::hook_disabled());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibration::{CalibrationConfig, InputTransform};
+    use crate::keycode::Key;
+    use crate::state::MASK_BUTTON1;
+    use std::sync::atomic::{AtomicU32, AtomicUsize};
+    use std::time::{Duration, Instant};
+
+    /// A backend that counts what it's asked to do, and sends events given
+    /// to [`emit`](Fake::emit) while installed.
+    #[derive(Default)]
+    struct Fake {
+        installs: Mutex<Vec<EventMask>>,
+        stops: AtomicUsize,
+        router: Mutex<Option<Router>>,
+        fail: bool,
+        /// Buttons the next drag finds released without the hook noticing.
+        stuck: AtomicU32,
+        corrections: AtomicUsize,
+        observed: Mutex<Vec<EventType>>,
+        configs: Mutex<Vec<Config>>,
+    }
+
+    impl Fake {
+        fn emit(&self, event: Event) {
+            let router = self.router.lock().unwrap().clone();
+            router.expect("not installed").handle_event(&event);
+        }
+    }
+
+    impl Backend for Fake {
+        fn run(&self, running: &Arc<AtomicBool>, events: EventMask, router: Router) -> Result<()> {
+            self.installs.lock().unwrap().push(events);
+            if self.fail {
+                return Err(Error::HookStartFailed("no input devices".into()));
+            }
+            *self.router.lock().unwrap() = Some(router.clone());
+            router.handle_event(&Event::hook_enabled());
+            while running.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            *self.router.lock().unwrap() = None;
+            router.handle_event(&Event::hook_disabled());
+            Ok(())
+        }
+
+        fn stop(&self) -> Result<()> {
+            self.stops.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn configure(&self, config: &Config) {
+            self.configs.lock().unwrap().push(config.clone());
+        }
+
+        fn correct(&self, event: &Event) -> Option<(Vec<Event>, Event)> {
+            if event.event_type != EventType::MouseDragged {
+                return None;
+            }
+            let stale = self.stuck.swap(0, Ordering::SeqCst);
+            (stale != 0).then(|| {
+                self.corrections.fetch_add(1, Ordering::SeqCst);
+                crate::watchdog::correct(event, stale)
+            })
+        }
+
+        fn observe(&self, event: &Event) {
+            self.observed.lock().unwrap().push(event.event_type);
+        }
+    }
+
+    type Seen = Arc<Mutex<Vec<EventType>>>;
+
+    fn recorder() -> (Seen, impl EventHandler) {
+        let seen = Seen::default();
+        let log = seen.clone();
+        (seen, move |event: &Event| {
+            log.lock().unwrap().push(event.event_type)
+        })
+    }
+
+    fn wait_for(seen: &Seen, count: usize) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while seen.lock().unwrap().len() < count {
+            assert!(
+                Instant::now() < deadline,
+                "saw only {:?}",
+                seen.lock().unwrap()
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Listen with the default config, not counting dropped events.
+    fn listen(
+        fanout: &Fanout,
+        running: &Arc<AtomicBool>,
+        events: EventMask,
+        handler: impl EventHandler,
+    ) -> Result<()> {
+        fanout.listen(running, events, Config::default(), Arc::default(), handler)
+    }
+
+    fn stop(fanout: &Fanout, running: &AtomicBool) {
+        running.store(false, Ordering::SeqCst);
+        assert!(fanout.leave(running));
+    }
+
+    #[test]
+    fn test_listeners_share_one_hook() {
+        let fake = Arc::new(Fake::default());
+        let fanout = Fanout::new(fake.clone());
+        let (first, second) = (
+            Arc::new(AtomicBool::new(true)),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let (first_seen, first_handler) = recorder();
+        let (second_seen, second_handler) = recorder();
+
+        std::thread::scope(|scope| {
+            let first_thread =
+                scope.spawn(|| listen(&fanout, &first, EventMask::ALL, first_handler));
+            wait_for(&first_seen, 1);
+            let second_thread =
+                scope.spawn(|| listen(&fanout, &second, EventMask::ALL, second_handler));
+            wait_for(&second_seen, 1);
+
+            fake.emit(Event::key_pressed(Key::KeyA, 0));
+            wait_for(&first_seen, 2);
+            wait_for(&second_seen, 2);
+
+            // Stopping one leaves the hook up for the other
+            stop(&fanout, &first);
+            first_thread.join().unwrap().unwrap();
+            assert_eq!(fake.stops.load(Ordering::SeqCst), 0);
+            fake.emit(Event::key_released(Key::KeyA, 0));
+            wait_for(&second_seen, 3);
+
+            stop(&fanout, &second);
+            second_thread.join().unwrap().unwrap();
+            assert_eq!(fake.stops.load(Ordering::SeqCst), 1);
+        });
+        assert!(!fanout.leave(&first));
+        assert_eq!(*fake.installs.lock().unwrap(), vec![EventMask::ALL]);
+        assert!(fake.router.lock().unwrap().is_none());
+
+        use EventType::*;
+        assert_eq!(
+            *first_seen.lock().unwrap(),
+            vec![HookEnabled, KeyPressed, HookDisabled]
+        );
+        assert_eq!(
+            *second_seen.lock().unwrap(),
+            vec![HookEnabled, KeyPressed, KeyReleased, HookDisabled]
+        );
+    }
+
+    #[test]
+    fn test_slow_listener_delays_only_itself() {
+        let fake = Arc::new(Fake::default());
+        let fanout = Fanout::new(fake.clone());
+        let (slow, fast) = (
+            Arc::new(AtomicBool::new(true)),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let (fast_seen, fast_handler) = recorder();
+        let blocked = Mutex::new(());
+        let slow_seen = AtomicUsize::new(0);
+        let slow_handler = |_: &Event| {
+            drop(blocked.lock().unwrap());
+            slow_seen.fetch_add(1, Ordering::SeqCst);
+        };
+
+        std::thread::scope(|scope| {
+            let gate = blocked.lock().unwrap();
+            let slow_thread = scope.spawn(|| listen(&fanout, &slow, EventMask::ALL, slow_handler));
+            let fast_thread =
+                scope.spawn(|| listen(&fanout, &fast, EventMask::KEYBOARD, fast_handler));
+            wait_for(&fast_seen, 1);
+            for _ in 0..10 {
+                fake.emit(Event::key_pressed(Key::KeyA, 0));
+            }
+            wait_for(&fast_seen, 11);
+            assert_eq!(slow_seen.load(Ordering::SeqCst), 0);
+
+            drop(gate);
+            stop(&fanout, &fast);
+            fast_thread.join().unwrap().unwrap();
+            while slow_seen.load(Ordering::SeqCst) < 11 {
+                std::thread::yield_now();
+            }
+            stop(&fanout, &slow);
+            slow_thread.join().unwrap().unwrap();
+        });
+        // HookEnabled, ten keys and HookDisabled
+        assert_eq!(slow_seen.load(Ordering::SeqCst), 12);
+    }
+
+    #[test]
+    fn test_events_past_a_full_queue_are_counted() {
+        let fake = Arc::new(Fake::default());
+        let fanout = Fanout::new(fake.clone());
+        let running = Arc::new(AtomicBool::new(true));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let blocked = Mutex::new(());
+        let seen = AtomicUsize::new(0);
+        let handler = |_: &Event| {
+            seen.fetch_add(1, Ordering::SeqCst);
+            drop(blocked.lock().unwrap());
+        };
+
+        std::thread::scope(|scope| {
+            let gate = blocked.lock().unwrap();
+            let thread = scope.spawn(|| {
+                fanout.listen(
+                    &running,
+                    EventMask::ALL,
+                    Config::default(),
+                    dropped.clone(),
+                    handler,
+                )
+            });
+            // Held up by HookEnabled, with nothing queued
+            while seen.load(Ordering::SeqCst) < 1 {
+                std::thread::yield_now();
+            }
+            for _ in 0..QUEUE_CAPACITY + 10 {
+                fake.emit(Event::key_pressed(Key::KeyA, 0));
+            }
+            assert_eq!(dropped.load(Ordering::Relaxed), 10);
+
+            drop(gate);
+            while seen.load(Ordering::SeqCst) < 1 + QUEUE_CAPACITY {
+                std::thread::yield_now();
+            }
+            stop(&fanout, &running);
+            thread.join().unwrap().unwrap();
+        });
+        assert_eq!(seen.load(Ordering::SeqCst), QUEUE_CAPACITY + 2);
+    }
+
+    #[test]
+    fn test_wider_listener_reinstalls_hook() {
+        let fake = Arc::new(Fake::default());
+        let fanout = Fanout::new(fake.clone());
+        let (keys, all) = (
+            Arc::new(AtomicBool::new(true)),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let (keys_seen, keys_handler) = recorder();
+        let (all_seen, all_handler) = recorder();
+
+        std::thread::scope(|scope| {
+            let keys_thread =
+                scope.spawn(|| listen(&fanout, &keys, EventMask::KEYBOARD, keys_handler));
+            wait_for(&keys_seen, 1);
+            let all_thread = scope.spawn(|| listen(&fanout, &all, EventMask::ALL, all_handler));
+            wait_for(&all_seen, 1);
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while fake.installs.lock().unwrap().len() < 2 || fake.router.lock().unwrap().is_none() {
+                assert!(Instant::now() < deadline, "never installed again");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+
+            fake.emit(Event::mouse_moved(1.0, 2.0));
+            fake.emit(Event::key_pressed(Key::KeyA, 0));
+            wait_for(&all_seen, 3);
+            wait_for(&keys_seen, 2);
+
+            stop(&fanout, &all);
+            stop(&fanout, &keys);
+            keys_thread.join().unwrap().unwrap();
+            all_thread.join().unwrap().unwrap();
+        });
+        assert_eq!(
+            *fake.installs.lock().unwrap(),
+            vec![EventMask::KEYBOARD, EventMask::ALL]
+        );
+        // Nor is the input state forgotten
+        assert_eq!(fake.configs.lock().unwrap().len(), 1);
+        // The snapshot doesn't see the hook go down in between
+        assert_eq!(
+            *fake.observed.lock().unwrap(),
+            vec![
+                EventType::HookEnabled,
+                EventType::MouseMoved,
+                EventType::KeyPressed,
+                EventType::HookDisabled
+            ]
+        );
+        // Enabled once, and motion it didn't ask for is left out
+        assert_eq!(
+            *keys_seen.lock().unwrap(),
+            vec![
+                EventType::HookEnabled,
+                EventType::KeyPressed,
+                EventType::HookDisabled
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stuck_button_is_released_once_for_all_listeners() {
+        let fake = Arc::new(Fake::default());
+        let fanout = Fanout::new(fake.clone());
+        let (first, second) = (
+            Arc::new(AtomicBool::new(true)),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let (first_seen, first_handler) = recorder();
+        let (second_seen, second_handler) = recorder();
+
+        std::thread::scope(|scope| {
+            let first_thread =
+                scope.spawn(|| listen(&fanout, &first, EventMask::ALL, first_handler));
+            let second_thread =
+                scope.spawn(|| listen(&fanout, &second, EventMask::ALL, second_handler));
+            wait_for(&first_seen, 1);
+            wait_for(&second_seen, 1);
+
+            fake.stuck.store(MASK_BUTTON1, Ordering::SeqCst);
+            let mut drag = Event::mouse_dragged(1.0, 2.0);
+            drag.mask = MASK_BUTTON1;
+            fake.emit(drag);
+            wait_for(&first_seen, 3);
+            wait_for(&second_seen, 3);
+
+            stop(&fanout, &first);
+            stop(&fanout, &second);
+            first_thread.join().unwrap().unwrap();
+            second_thread.join().unwrap().unwrap();
+        });
+        assert_eq!(fake.corrections.load(Ordering::SeqCst), 1);
+
+        use EventType::*;
+        for seen in [first_seen, second_seen] {
+            assert_eq!(
+                *seen.lock().unwrap(),
+                vec![HookEnabled, MouseReleased, MouseMoved, HookDisabled]
+            );
+        }
+        // The snapshot is fed each event once, not once per listener
+        assert_eq!(
+            *fake.observed.lock().unwrap(),
+            vec![HookEnabled, MouseReleased, MouseMoved, HookDisabled]
+        );
+    }
+
+    #[test]
+    fn test_listener_with_other_config_is_refused() {
+        let fake = Arc::new(Fake::default());
+        let fanout = Fanout::new(fake.clone());
+        let (first, second) = (
+            Arc::new(AtomicBool::new(true)),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let (first_seen, first_handler) = recorder();
+        let touches = Config {
+            touch_as_mouse: false,
+            ..Config::default()
+        };
+        let mut calibration = CalibrationConfig::new();
+        calibration.set("touchscreen", InputTransform::flip_x());
+        let calibrated = Config {
+            transforms: calibration.devices,
+            ..touches.clone()
+        };
+
+        std::thread::scope(|scope| {
+            let first_thread = scope.spawn(|| {
+                fanout.listen(
+                    &first,
+                    EventMask::ALL,
+                    touches.clone(),
+                    Arc::default(),
+                    first_handler,
+                )
+            });
+            wait_for(&first_seen, 1);
+
+            for config in [Config::default(), calibrated] {
+                let (seen, handler) = recorder();
+                let result =
+                    fanout.listen(&second, EventMask::ALL, config, Arc::default(), handler);
+                assert!(matches!(result, Err(Error::ConflictingConfig(_))));
+                assert!(seen.lock().unwrap().is_empty());
+                assert!(!fanout.leave(&second));
+            }
+            // Grabs too, as the platform reads devices for both
+            assert!(matches!(
+                fanout.claim_grab(&Config::default()),
+                Err(Error::ConflictingConfig(_))
+            ));
+            let claim = fanout.claim_grab(&touches).unwrap();
+            assert!(matches!(
+                fanout.claim_grab(&touches),
+                Err(Error::AlreadyRunning)
+            ));
+
+            // The same config joins
+            let (seen, handler) = recorder();
+            let second_thread = scope.spawn(|| {
+                fanout.listen(
+                    &second,
+                    EventMask::ALL,
+                    touches.clone(),
+                    Arc::default(),
+                    handler,
+                )
+            });
+            wait_for(&seen, 1);
+            fake.emit(Event::key_pressed(Key::KeyA, 0));
+            wait_for(&first_seen, 2);
+            wait_for(&seen, 2);
+
+            stop(&fanout, &first);
+            stop(&fanout, &second);
+            first_thread.join().unwrap().unwrap();
+            second_thread.join().unwrap().unwrap();
+            drop(claim);
+        });
+        assert_eq!(*fake.configs.lock().unwrap(), vec![touches]);
+
+        // Once nothing runs, any config goes
+        let claim = fanout.claim_grab(&Config::default()).unwrap();
+        drop(claim);
+        assert_eq!(fake.configs.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_failed_install_stops_listener() {
+        let fake = Arc::new(Fake {
+            fail: true,
+            ..Fake::default()
+        });
+        let fanout = Fanout::new(fake.clone());
+        let running = Arc::new(AtomicBool::new(true));
+        let (seen, handler) = recorder();
+
+        let result = listen(&fanout, &running, EventMask::ALL, handler);
+        assert!(matches!(result, Err(Error::HookStartFailed(_))));
+        assert!(seen.lock().unwrap().is_empty());
+        assert!(!fanout.leave(&running));
+
+        // The next listener tries again
+        let (_, handler) = recorder();
+        assert!(listen(&fanout, &running, EventMask::ALL, handler).is_err());
+        assert_eq!(fake.installs.lock().unwrap().len(), 2);
+    }
+}
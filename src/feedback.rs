@@ -0,0 +1,366 @@
+//! Side effects for input, off the hook thread.
+//!
+//! [`Feedback`] runs actions, such as playing a click sound or pulsing a
+//! haptic motor, when events of a kind come in. monio doesn't produce the
+//! feedback itself; the actions are your closures. What it does is keep them
+//! off the hook thread: actions run one at a time on a worker thread of their
+//! own, so a slow one can't delay input.
+//!
+//! Each action is rate limited: it runs at most
+//! [`max_rate`](Feedback::max_rate) times a second, and events that come in
+//! while it is still queued or running are dropped rather than queued up, so
+//! a burst of keys makes a handful of clicks, not a thousand.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::EventType;
+//! use monio::feedback::Feedback;
+//!
+//! let feedback = Feedback::new()
+//!     .on(EventType::KeyPressed, |_| println!("tick"))
+//!     .on_click(|event| println!("click at {:?}", event.mouse.as_ref().map(|m| (m.x, m.y))))
+//!     .max_rate(15)
+//!     .start()?;
+//! # drop(feedback);
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::event::{Event, EventType};
+use crate::hook::{EventHandler, Hook};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How many times a second each action runs at most, unless changed with
+/// [`Feedback::max_rate`].
+pub const DEFAULT_MAX_RATE: u32 = 20;
+
+type Action = Box<dyn FnMut(&Event) + Send>;
+
+/// Builder for actions to run on input.
+pub struct Feedback {
+    rules: Vec<(EventType, Action)>,
+    max_rate: u32,
+    ignore_injected: bool,
+}
+
+impl Default for Feedback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Feedback {
+    /// No actions, each limited to [`DEFAULT_MAX_RATE`] runs a second.
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            max_rate: DEFAULT_MAX_RATE,
+            ignore_injected: false,
+        }
+    }
+
+    /// Run `action` with each event of `event_type`, subject to the rate
+    /// limit.
+    pub fn on<F>(mut self, event_type: EventType, action: F) -> Self
+    where
+        F: FnMut(&Event) + Send + 'static,
+    {
+        self.rules.push((event_type, Box::new(action)));
+        self
+    }
+
+    /// Run `action` whenever a mouse button is pressed.
+    pub fn on_click<F>(self, action: F) -> Self
+    where
+        F: FnMut(&Event) + Send + 'static,
+    {
+        self.on(EventType::MousePressed, action)
+    }
+
+    /// Run each action at most `per_second` times a second (at least once).
+    pub fn max_rate(mut self, per_second: u32) -> Self {
+        self.max_rate = per_second.max(1);
+        self
+    }
+
+    /// Whether to skip events monio or another program injected (off by
+    /// default). See [`Hook::ignore_injected`].
+    pub fn ignore_injected(mut self, ignore: bool) -> Self {
+        self.ignore_injected = ignore;
+        self
+    }
+
+    /// Start a listen hook and the worker that runs the actions.
+    pub fn start(self) -> Result<FeedbackHandle> {
+        let hook = Hook::new().ignore_injected(self.ignore_injected);
+        let (dispatcher, worker) = self.spawn()?;
+        let handle = FeedbackHandle {
+            hook,
+            control: dispatcher.sender.clone(),
+            worker: Some(worker),
+        };
+        handle.hook.run_async(dispatcher)?;
+        Ok(handle)
+    }
+
+    /// Start the worker, returning the handler that feeds it.
+    fn spawn(self) -> Result<(Dispatcher, JoinHandle<()>)> {
+        let interval = Duration::from_secs(1) / self.max_rate;
+        let (types, actions): (Vec<_>, Vec<_>) = self.rules.into_iter().unzip();
+        let rules: Arc<[Rule]> = types
+            .into_iter()
+            .map(|event_type| Rule {
+                event_type,
+                busy: AtomicBool::new(false),
+                next_at: AtomicU64::new(0),
+            })
+            .collect();
+        // Each rule has at most one run queued or in progress, plus the stop
+        let (sender, receiver) = mpsc::sync_channel(rules.len() + 1);
+
+        let worker_rules = rules.clone();
+        let worker = thread::Builder::new()
+            .name("monio-feedback".into())
+            .spawn(move || run_actions(&worker_rules, actions, receiver))
+            .map_err(|e| Error::ThreadError(format!("failed to spawn feedback thread: {}", e)))?;
+
+        let dispatcher = Dispatcher {
+            rules,
+            interval: u64::try_from(interval.as_nanos()).unwrap_or(u64::MAX),
+            start: Instant::now(),
+            sender,
+        };
+        Ok((dispatcher, worker))
+    }
+}
+
+impl std::fmt::Debug for Feedback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Feedback")
+            .field(
+                "rules",
+                &self.rules.iter().map(|(t, _)| t).collect::<Vec<_>>(),
+            )
+            .field("max_rate", &self.max_rate)
+            .field("ignore_injected", &self.ignore_injected)
+            .finish()
+    }
+}
+
+/// An action's trigger and rate limit, shared by the hook and the worker.
+struct Rule {
+    event_type: EventType,
+    /// Set while a run is queued or in progress.
+    busy: AtomicBool,
+    /// Earliest next run, in nanoseconds since the dispatcher started.
+    next_at: AtomicU64,
+}
+
+/// The hook side: decides which actions to run and queues them without
+/// blocking.
+struct Dispatcher {
+    rules: Arc<[Rule]>,
+    interval: u64,
+    start: Instant,
+    sender: SyncSender<Job>,
+}
+
+enum Job {
+    Run(usize, Event),
+    Stop,
+}
+
+impl EventHandler for Dispatcher {
+    fn handle_event(&self, event: &Event) {
+        let now = u64::try_from(self.start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.event_type != event.event_type
+                || now < rule.next_at.load(Ordering::Relaxed)
+                || rule.busy.swap(true, Ordering::AcqRel)
+            {
+                continue;
+            }
+            rule.next_at
+                .store(now.saturating_add(self.interval), Ordering::Relaxed);
+            if self
+                .sender
+                .try_send(Job::Run(index, event.clone()))
+                .is_err()
+            {
+                rule.busy.store(false, Ordering::Release);
+            }
+        }
+    }
+}
+
+/// The worker side: run queued actions until stopped.
+fn run_actions(rules: &[Rule], mut actions: Vec<Action>, queue: Receiver<Job>) {
+    while let Ok(Job::Run(index, event)) = queue.recv() {
+        let action = &mut actions[index];
+        if catch_unwind(AssertUnwindSafe(|| action(&event))).is_err() {
+            log::warn!("Feedback action for {:?} panicked", event.event_type);
+        }
+        rules[index].busy.store(false, Ordering::Release);
+    }
+}
+
+/// Running feedback, stopped when dropped.
+///
+/// Created by [`Feedback::start`].
+pub struct FeedbackHandle {
+    hook: Hook,
+    control: SyncSender<Job>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl FeedbackHandle {
+    /// Stop listening and wait for the running action to return. Queued
+    /// actions still run first.
+    pub fn stop(mut self) -> Result<()> {
+        self.stop_inner()
+    }
+
+    fn stop_inner(&mut self) -> Result<()> {
+        let stopped = match self.hook.stop() {
+            Ok(()) | Err(Error::NotRunning) => Ok(()),
+            Err(e) => Err(e),
+        };
+        let _ = self.control.send(Job::Stop);
+        if let Some(worker) = self.worker.take() {
+            worker
+                .join()
+                .map_err(|_| Error::ThreadError("feedback worker panicked".into()))?;
+        }
+        stopped
+    }
+}
+
+impl Drop for FeedbackHandle {
+    fn drop(&mut self) {
+        let _ = self.stop_inner();
+    }
+}
+
+impl std::fmt::Debug for FeedbackHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FeedbackHandle")
+            .field("running", &self.hook.is_running())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Button;
+    use crate::keycode::Key;
+    use std::sync::Mutex;
+    use std::sync::atomic::AtomicUsize;
+
+    fn key() -> Event {
+        Event::key_pressed(Key::KeyA, 30)
+    }
+
+    #[test]
+    fn test_slow_action_never_blocks_the_hook() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let (dispatcher, worker) = Feedback::new()
+            .on(EventType::KeyPressed, move |_| {
+                thread::sleep(Duration::from_millis(300));
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .max_rate(1000)
+            .spawn()
+            .unwrap();
+
+        let started = Instant::now();
+        for _ in 0..1000 {
+            dispatcher.handle_event(&key());
+        }
+        assert!(started.elapsed() < Duration::from_millis(200));
+
+        drop(dispatcher);
+        worker.join().unwrap();
+        // Everything that came in while the action ran was dropped
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let (dispatcher, worker) = Feedback::new()
+            .on(EventType::KeyPressed, move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .max_rate(5)
+            .spawn()
+            .unwrap();
+
+        // A 100 ms flood falls within one 200 ms interval
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_millis(100) {
+            dispatcher.handle_event(&key());
+            thread::sleep(Duration::from_millis(1));
+        }
+        thread::sleep(Duration::from_millis(150));
+        dispatcher.handle_event(&key());
+
+        drop(dispatcher);
+        worker.join().unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_actions_match_event_type() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (keys, clicks) = (seen.clone(), seen.clone());
+        let (dispatcher, worker) = Feedback::new()
+            .on(EventType::KeyPressed, move |event| {
+                keys.lock().unwrap().push(event.event_type);
+            })
+            .on_click(move |event| {
+                clicks.lock().unwrap().push(event.event_type);
+            })
+            .spawn()
+            .unwrap();
+
+        dispatcher.handle_event(&Event::mouse_moved(1.0, 1.0));
+        dispatcher.handle_event(&Event::mouse_pressed(Button::Left, 1.0, 1.0));
+        dispatcher.handle_event(&key());
+
+        drop(dispatcher);
+        worker.join().unwrap();
+        let mut seen = seen.lock().unwrap().clone();
+        seen.sort_by_key(|t| format!("{t:?}"));
+        assert_eq!(seen, [EventType::KeyPressed, EventType::MousePressed]);
+    }
+
+    #[test]
+    fn test_panicking_action_keeps_worker_alive() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let (dispatcher, worker) = Feedback::new()
+            .on(EventType::KeyPressed, |_| panic!("no sound card"))
+            .on_click(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .spawn()
+            .unwrap();
+
+        dispatcher.handle_event(&key());
+        thread::sleep(Duration::from_millis(50));
+        dispatcher.handle_event(&Event::mouse_pressed(Button::Left, 0.0, 0.0));
+
+        drop(dispatcher);
+        worker.join().unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+}
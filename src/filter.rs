@@ -0,0 +1,839 @@
+//! Event filters written as expressions, for configuration files.
+//!
+//! An [`EventFilter`] is parsed from a string such as
+//!
+//! ```text
+//! key:pressed && (key == F1 || mods.ctrl) && !injected
+//! ```
+//!
+//! and tells whether an event matches with [`EventFilter::matches`]. Its
+//! `Display` writes it back in a form that parses to the same filter, and
+//! with the `serde` feature it is saved as that string, so filters can sit
+//! in TOML or JSON configuration next to everything else.
+//!
+//! # Grammar
+//!
+//! Predicates are combined with `!`, `&&` (binding tighter) and `||`, and
+//! grouped with parentheses:
+//!
+//! | Predicate | Matches |
+//! |---|---|
+//! | `key:pressed`, `mouse:moved`, ... | events of one type, see below |
+//! | `keyboard`, `mouse`, `touch` | events of a kind |
+//! | `key == Escape`, `key != KeyA` | key events of a key, by [`Key`] name |
+//! | `button == left`, `button != right` | mouse events of a button: `left`, `right`, `middle`, `button4`, ... |
+//! | `mods.shift`, `mods.ctrl`, `mods.alt`, `mods.meta` | events with the modifier held |
+//! | `injected` | events generated by software |
+//! | `region(x, y, width, height)` | pointer events inside the rectangle |
+//! | `true`, `false` | every event, or none |
+//!
+//! Event types are written `key:pressed`, `key:released`, `key:typed`,
+//! `ime:started`, `ime:ended`, `mouse:pressed`, `mouse:released`,
+//! `mouse:clicked`, `mouse:moved`, `mouse:dragged`, `drag:started`,
+//! `mouse:wheel`, `touch:began`, `touch:moved`, `touch:ended`,
+//! `display:changed`, `hook:enabled`, `hook:disabled`, `grab:suspended`,
+//! `grab:degraded` and `suspend:changed`.
+//!
+//! Keys are parsed with [`Key`]'s `FromStr`, which takes the variant names
+//! and the key names xdotool and AutoHotkey scripts use (`key == ctrl`,
+//! `key == Return`). Names that aren't a plain word, like `Char('ö')`, are
+//! quoted: `key == "Char('ö')"`.
+//!
+//! # Example
+//!
+//! ```
+//! use monio::filter::EventFilter;
+//! use monio::{Event, Key};
+//!
+//! let filter = EventFilter::parse("key:pressed && key == F1 && !injected").unwrap();
+//! assert!(filter.matches(&Event::key_pressed(Key::F1, 0)));
+//! assert!(!filter.matches(&Event::key_released(Key::F1, 0)));
+//!
+//! let err = EventFilter::parse("key == Hyper").unwrap_err();
+//! assert_eq!(err.span(), 7..12);
+//! ```
+
+use crate::display::Rect;
+use crate::event::{Button, Event, EventType};
+use crate::keycode::Key;
+use crate::state::Modifiers;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Event types by the names filters use for them.
+const EVENT_TYPES: [(&str, EventType); 21] = [
+    ("key:pressed", EventType::KeyPressed),
+    ("key:released", EventType::KeyReleased),
+    ("key:typed", EventType::KeyTyped),
+    ("ime:started", EventType::ImeCompositionStarted),
+    ("ime:ended", EventType::ImeCompositionEnded),
+    ("mouse:pressed", EventType::MousePressed),
+    ("mouse:released", EventType::MouseReleased),
+    ("mouse:clicked", EventType::MouseClicked),
+    ("mouse:moved", EventType::MouseMoved),
+    ("mouse:dragged", EventType::MouseDragged),
+    ("drag:started", EventType::DragStarted),
+    ("mouse:wheel", EventType::MouseWheel),
+    ("touch:began", EventType::TouchBegan),
+    ("touch:moved", EventType::TouchMoved),
+    ("touch:ended", EventType::TouchEnded),
+    ("display:changed", EventType::DisplayChanged),
+    ("hook:enabled", EventType::HookEnabled),
+    ("hook:disabled", EventType::HookDisabled),
+    ("grab:suspended", EventType::GrabSuspended),
+    ("grab:degraded", EventType::GrabDegraded),
+    ("suspend:changed", EventType::GlobalSuspendChanged),
+];
+
+/// Kinds of events, see [`Event::is_keyboard`] and its siblings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Keyboard,
+    Mouse,
+    Touch,
+}
+
+/// A modifier key, in `mods.<name>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    Meta,
+}
+
+const KINDS: [(&str, Kind); 3] = [
+    ("keyboard", Kind::Keyboard),
+    ("mouse", Kind::Mouse),
+    ("touch", Kind::Touch),
+];
+
+const MODIFIERS: [(&str, Modifier); 4] = [
+    ("mods.shift", Modifier::Shift),
+    ("mods.ctrl", Modifier::Ctrl),
+    ("mods.alt", Modifier::Alt),
+    ("mods.meta", Modifier::Meta),
+];
+
+/// The name of `value` in `table`.
+fn name<T: PartialEq>(table: &[(&'static str, T)], value: &T) -> &'static str {
+    table
+        .iter()
+        .find(|(_, v)| v == value)
+        .map_or("?", |(name, _)| name)
+}
+
+/// The value named `word` in `table`, ignoring case.
+fn lookup<T: Copy>(table: &[(&str, T)], word: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(word))
+        .map(|(_, value)| *value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Const(bool),
+    Type(EventType),
+    Kind(Kind),
+    Key(Key),
+    Button(Button),
+    Modifier(Modifier),
+    Injected,
+    Region(Rect),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// Where an event is, for `region(..)`.
+fn position(event: &Event) -> Option<(f64, f64)> {
+    if let Some(mouse) = &event.mouse {
+        Some((mouse.x, mouse.y))
+    } else if let Some(wheel) = &event.wheel {
+        Some((wheel.x, wheel.y))
+    } else {
+        event.touch.as_ref().map(|touch| (touch.x, touch.y))
+    }
+}
+
+impl Expr {
+    fn matches(&self, event: &Event) -> bool {
+        match self {
+            Expr::Const(value) => *value,
+            Expr::Type(event_type) => event.event_type == *event_type,
+            Expr::Kind(Kind::Keyboard) => event.is_keyboard(),
+            Expr::Kind(Kind::Mouse) => event.is_mouse(),
+            Expr::Kind(Kind::Touch) => event.is_touch(),
+            Expr::Key(key) => event.keyboard.as_ref().is_some_and(|kb| kb.key == *key),
+            Expr::Button(button) => event
+                .mouse
+                .as_ref()
+                .is_some_and(|mouse| mouse.button == Some(*button)),
+            Expr::Modifier(modifier) => {
+                let mods = Modifiers::from_mask(event.mask);
+                match modifier {
+                    Modifier::Shift => mods.shift,
+                    Modifier::Ctrl => mods.ctrl,
+                    Modifier::Alt => mods.alt,
+                    Modifier::Meta => mods.meta,
+                }
+            }
+            Expr::Injected => event.injected == Some(true),
+            Expr::Region(rect) => position(event).is_some_and(|(x, y)| rect.contains(x, y)),
+            Expr::Not(inner) => !inner.matches(event),
+            Expr::And(left, right) => left.matches(event) && right.matches(event),
+            Expr::Or(left, right) => left.matches(event) || right.matches(event),
+        }
+    }
+
+    /// How tightly the expression binds, for parenthesizing.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(..) => 1,
+            Expr::And(..) => 2,
+            _ => 3,
+        }
+    }
+
+    /// Write the expression, in parentheses if it binds looser than `min`.
+    fn write(&self, f: &mut fmt::Formatter<'_>, min: u8) -> fmt::Result {
+        if self.precedence() < min {
+            f.write_str("(")?;
+            self.write(f, 0)?;
+            return f.write_str(")");
+        }
+        match self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Type(event_type) => f.write_str(name(&EVENT_TYPES, event_type)),
+            Expr::Kind(kind) => f.write_str(name(&KINDS, kind)),
+            Expr::Modifier(modifier) => f.write_str(name(&MODIFIERS, modifier)),
+            Expr::Key(key) => write!(f, "key == {}", KeyName(*key)),
+            Expr::Button(button) => write!(f, "button == {}", ButtonName(*button)),
+            Expr::Injected => f.write_str("injected"),
+            Expr::Region(rect) => write!(
+                f,
+                "region({}, {}, {}, {})",
+                rect.x, rect.y, rect.width, rect.height
+            ),
+            Expr::Not(inner) => match inner.as_ref() {
+                Expr::Key(key) => write!(f, "key != {}", KeyName(*key)),
+                Expr::Button(button) => write!(f, "button != {}", ButtonName(*button)),
+                inner => {
+                    f.write_str("!")?;
+                    inner.write(f, 3)
+                }
+            },
+            Expr::And(left, right) => {
+                left.write(f, 2)?;
+                f.write_str(" && ")?;
+                right.write(f, 3)
+            }
+            Expr::Or(left, right) => {
+                left.write(f, 1)?;
+                f.write_str(" || ")?;
+                right.write(f, 2)
+            }
+        }
+    }
+}
+
+/// A key as filters write it: bare if it is a word, quoted otherwise.
+struct KeyName(Key);
+
+impl fmt::Display for KeyName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = format!("{:?}", self.0);
+        if name.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+            f.write_str(&name)
+        } else {
+            write!(f, "\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+    }
+}
+
+/// A button as filters write it.
+struct ButtonName(Button);
+
+impl fmt::Display for ButtonName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Button::Left => f.write_str("left"),
+            Button::Right => f.write_str("right"),
+            Button::Middle => f.write_str("middle"),
+            button => write!(f, "button{}", button.number()),
+        }
+    }
+}
+
+/// A filter expression that failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError {
+    message: String,
+    span: Range<usize>,
+}
+
+impl FilterError {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// What is wrong.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Byte range of the offending part of the expression.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Text(String),
+    Not,
+    And,
+    Or,
+    Equal,
+    NotEqual,
+    Open,
+    Close,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Number(number) => write!(f, "`{}`", number),
+            Token::Text(text) => write!(f, "{:?}", text),
+            Token::Not => f.write_str("`!`"),
+            Token::And => f.write_str("`&&`"),
+            Token::Or => f.write_str("`||`"),
+            Token::Equal => f.write_str("`==`"),
+            Token::NotEqual => f.write_str("`!=`"),
+            Token::Open => f.write_str("`(`"),
+            Token::Close => f.write_str("`)`"),
+            Token::Comma => f.write_str("`,`"),
+        }
+    }
+}
+
+/// Split `source` into tokens with their byte ranges.
+fn tokenize(source: &str) -> Result<Vec<(Token, Range<usize>)>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let symbol = match (ch, source[start..].chars().nth(1)) {
+            ('&', Some('&')) => Some((Token::And, 2)),
+            ('|', Some('|')) => Some((Token::Or, 2)),
+            ('=', Some('=')) => Some((Token::Equal, 2)),
+            ('!', Some('=')) => Some((Token::NotEqual, 2)),
+            ('!', _) => Some((Token::Not, 1)),
+            ('(', _) => Some((Token::Open, 1)),
+            (')', _) => Some((Token::Close, 1)),
+            (',', _) => Some((Token::Comma, 1)),
+            _ => None,
+        };
+        if let Some((token, len)) = symbol {
+            for _ in 0..len {
+                chars.next();
+            }
+            tokens.push((token, start..start + len));
+        } else if ch.is_ascii_alphabetic() || ch == '_' {
+            let mut end = start;
+            while let Some(&(i, ch)) = chars.peek() {
+                if !(ch.is_ascii_alphanumeric() || matches!(ch, '_' | ':' | '.')) {
+                    break;
+                }
+                end = i + ch.len_utf8();
+                chars.next();
+            }
+            tokens.push((Token::Word(source[start..end].to_string()), start..end));
+        } else if ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.') {
+            let mut end = start;
+            while let Some(&(i, ch)) = chars.peek() {
+                let exponent_sign = matches!(ch, '-' | '+')
+                    && i > start
+                    && matches!(source.as_bytes()[i - 1], b'e' | b'E');
+                if !(ch.is_ascii_alphanumeric() || ch == '.' || exponent_sign || i == start) {
+                    break;
+                }
+                end = i + ch.len_utf8();
+                chars.next();
+            }
+            let number = source[start..end].parse().map_err(|_| {
+                FilterError::new(
+                    format!("invalid number `{}`", &source[start..end]),
+                    start..end,
+                )
+            })?;
+            tokens.push((Token::Number(number), start..end));
+        } else if ch == '"' {
+            chars.next();
+            let mut text = String::new();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i + 1,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, escaped)) => text.push(escaped),
+                        None => break source.len() + 1,
+                    },
+                    Some((_, ch)) => text.push(ch),
+                    None => break source.len() + 1,
+                }
+            };
+            if end > source.len() {
+                return Err(FilterError::new("unterminated string", start..source.len()));
+            }
+            tokens.push((Token::Text(text), start..end));
+        } else {
+            let end = start + ch.len_utf8();
+            return Err(FilterError::new(
+                format!("unexpected character `{}`", ch),
+                start..end,
+            ));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens of one expression.
+struct Parser<'a> {
+    tokens: &'a [(Token, Range<usize>)],
+    next: usize,
+    /// Length of the source, where a missing token is reported.
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(token, _)| token)
+    }
+
+    fn advance(&mut self) -> Option<(Token, Range<usize>)> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    /// The next token, which something `expected` must be.
+    fn take(&mut self, expected: &str) -> Result<(Token, Range<usize>), FilterError> {
+        self.advance().ok_or_else(|| {
+            FilterError::new(
+                format!("expected {}, found the end", expected),
+                self.end..self.end,
+            )
+        })
+    }
+
+    fn expect(&mut self, token: Token) -> Result<Range<usize>, FilterError> {
+        let (found, span) = self.take(&token.to_string())?;
+        if found == token {
+            Ok(span)
+        } else {
+            Err(FilterError::new(
+                format!("expected {}, found {}", token, found),
+                span,
+            ))
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.advance();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.advance();
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterError> {
+        if self.peek() == Some(&Token::Not) {
+            self.advance();
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, FilterError> {
+        let (token, span) = self.take("a predicate")?;
+        let word = match token {
+            Token::Open => {
+                let expr = self.or()?;
+                self.expect(Token::Close)?;
+                return Ok(expr);
+            }
+            Token::Word(word) => word,
+            other => {
+                return Err(FilterError::new(
+                    format!("expected a predicate, found {}", other),
+                    span,
+                ));
+            }
+        };
+        if let Some(event_type) = lookup(&EVENT_TYPES, &word) {
+            return Ok(Expr::Type(event_type));
+        }
+        if let Some(kind) = lookup(&KINDS, &word) {
+            return Ok(Expr::Kind(kind));
+        }
+        if let Some(modifier) = lookup(&MODIFIERS, &word) {
+            return Ok(Expr::Modifier(modifier));
+        }
+        match word.to_ascii_lowercase().as_str() {
+            "true" => Ok(Expr::Const(true)),
+            "false" => Ok(Expr::Const(false)),
+            "injected" => Ok(Expr::Injected),
+            "key" => self.comparison(|name| name.parse().ok().map(Expr::Key), "key"),
+            "button" => self.comparison(|name| parse_button(name).map(Expr::Button), "button"),
+            "region" => self.region(),
+            _ => Err(FilterError::new(
+                format!(
+                    "unknown predicate `{}`, expected an event type like `key:pressed`, \
+                     `keyboard`, `key == ..`, `button == ..`, `mods.ctrl`, `injected` \
+                     or `region(..)`",
+                    word
+                ),
+                span,
+            )),
+        }
+    }
+
+    /// `== value` or `!= value` after `subject`, with `value` parsed by
+    /// `parse`.
+    fn comparison(
+        &mut self,
+        parse: impl FnOnce(&str) -> Option<Expr>,
+        subject: &str,
+    ) -> Result<Expr, FilterError> {
+        let (operator, span) = self.take("`==` or `!=`")?;
+        let negate = match operator {
+            Token::Equal => false,
+            Token::NotEqual => true,
+            other => {
+                return Err(FilterError::new(
+                    format!("expected `==` or `!=` after `{}`, found {}", subject, other),
+                    span,
+                ));
+            }
+        };
+        let (value, span) = self.take(&format!("a {} name", subject))?;
+        let name = match value {
+            Token::Word(name) | Token::Text(name) => name,
+            Token::Number(number) => number.to_string(),
+            other => {
+                return Err(FilterError::new(
+                    format!("expected a {} name, found {}", subject, other),
+                    span,
+                ));
+            }
+        };
+        let expr = parse(&name)
+            .ok_or_else(|| FilterError::new(format!("unknown {} `{}`", subject, name), span))?;
+        Ok(if negate {
+            Expr::Not(Box::new(expr))
+        } else {
+            expr
+        })
+    }
+
+    /// The `(x, y, width, height)` of `region`.
+    fn region(&mut self) -> Result<Expr, FilterError> {
+        self.expect(Token::Open)?;
+        let mut values = [0.0; 4];
+        for (i, value) in values.iter_mut().enumerate() {
+            if i > 0 {
+                self.expect(Token::Comma)?;
+            }
+            *value = match self.take("a number")? {
+                (Token::Number(number), _) => number,
+                (other, span) => {
+                    return Err(FilterError::new(
+                        format!("expected a number, found {}", other),
+                        span,
+                    ));
+                }
+            };
+        }
+        self.expect(Token::Close)?;
+        let [x, y, width, height] = values;
+        Ok(Expr::Region(Rect {
+            x,
+            y,
+            width,
+            height,
+        }))
+    }
+}
+
+/// A button by its filter name.
+fn parse_button(name: &str) -> Option<Button> {
+    match name.to_ascii_lowercase().as_str() {
+        "left" => Some(Button::Left),
+        "right" => Some(Button::Right),
+        "middle" => Some(Button::Middle),
+        other => {
+            let number = other.strip_prefix("button")?.parse().ok()?;
+            (number > 0).then(|| Button::from_number(number))
+        }
+    }
+}
+
+/// Which events to act on, parsed from an expression.
+///
+/// See the [module documentation](self) for the grammar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventFilter {
+    expr: Expr,
+}
+
+impl EventFilter {
+    /// Parse a filter expression.
+    pub fn parse(source: &str) -> Result<Self, FilterError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            next: 0,
+            end: source.len(),
+        };
+        let expr = parser.or()?;
+        if let Some((token, span)) = parser.advance() {
+            return Err(FilterError::new(
+                format!("expected `&&`, `||` or the end, found {}", token),
+                span,
+            ));
+        }
+        Ok(Self { expr })
+    }
+
+    /// A filter matching every event.
+    pub fn all() -> Self {
+        Self {
+            expr: Expr::Const(true),
+        }
+    }
+
+    /// Whether `event` matches.
+    pub fn matches(&self, event: &Event) -> bool {
+        self.expr.matches(event)
+    }
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl FromStr for EventFilter {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for EventFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.expr.write(f, 0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for EventFilter {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for EventFilter {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Self::parse(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{MASK_CTRL, MASK_SHIFT};
+    use proptest::prelude::*;
+
+    fn filter(source: &str) -> EventFilter {
+        EventFilter::parse(source).unwrap()
+    }
+
+    #[test]
+    fn test_matches() {
+        let hotkey = filter("key:pressed && (key == F1 || mods.ctrl) && !injected");
+        let mut ctrl_a = Event::key_pressed(Key::KeyA, 0);
+        ctrl_a.mask = MASK_CTRL;
+        assert!(hotkey.matches(&Event::key_pressed(Key::F1, 0)));
+        assert!(hotkey.matches(&ctrl_a));
+        assert!(!hotkey.matches(&Event::key_pressed(Key::KeyA, 0)));
+        assert!(!hotkey.matches(&Event::key_released(Key::F1, 0)));
+        let mut injected = Event::key_pressed(Key::F1, 0);
+        injected.injected = Some(true);
+        assert!(!hotkey.matches(&injected));
+
+        let clicks = filter("mouse:pressed && button != left && region(0, 0, 100, 50)");
+        assert!(clicks.matches(&Event::mouse_pressed(Button::Right, 10.0, 10.0)));
+        assert!(!clicks.matches(&Event::mouse_pressed(Button::Left, 10.0, 10.0)));
+        assert!(!clicks.matches(&Event::mouse_pressed(Button::Right, 10.0, 50.0)));
+
+        let mut shifted = Event::mouse_moved(1.0, 1.0);
+        shifted.mask = MASK_SHIFT;
+        assert!(filter("mouse && MODS.SHIFT").matches(&shifted));
+        assert!(!filter("keyboard || touch").matches(&shifted));
+        assert!(filter("key == \"Char('ö')\"").matches(&Event::key_pressed(Key::Char('ö'), 0)));
+
+        // Script key names, written back as variant names
+        let aliases = filter("key == ctrl || key == Return || key == esc");
+        for key in [Key::ControlLeft, Key::Enter, Key::Escape] {
+            assert!(aliases.matches(&Event::key_pressed(key, 0)));
+        }
+        assert_eq!(
+            aliases.to_string(),
+            "key == ControlLeft || key == Enter || key == Escape"
+        );
+    }
+
+    #[test]
+    fn test_errors_point_at_the_problem() {
+        let error = |source: &str| EventFilter::parse(source).unwrap_err();
+
+        let unknown = error("key:pressed && key == Hyper");
+        assert_eq!(unknown.message(), "unknown key `Hyper`");
+        assert_eq!(unknown.span(), 22..27);
+
+        assert_eq!(error("key:presed").span(), 0..10);
+        assert_eq!(
+            error("(mouse").to_string(),
+            "expected `)`, found the end at 6..6"
+        );
+        assert_eq!(error("mouse mouse").span(), 6..11);
+        assert_eq!(error("key = F1").span(), 4..5);
+        assert_eq!(error("region(1, 2, x, 4)").span(), 13..14);
+        assert_eq!(error("key == \"F1").message(), "unterminated string");
+        assert_eq!(error("").span(), 0..0);
+    }
+
+    #[test]
+    fn test_display_adds_needed_parentheses() {
+        for (source, written) in [
+            ("(keyboard || mouse) && !injected", None),
+            ("keyboard || mouse && injected", None),
+            ("!(keyboard && mouse)", None),
+            ("keyboard && (mouse && touch)", None),
+            ("((keyboard)) && !!injected", Some("keyboard && !!injected")),
+            ("!(key == F1)", Some("key != F1")),
+            (
+                "button == button7 || region(-1.5, 2, 1e3, 4)",
+                Some("button == button7 || region(-1.5, 2, 1000, 4)"),
+            ),
+        ] {
+            assert_eq!(filter(source).to_string(), written.unwrap_or(source));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_as_string() {
+        let parsed: EventFilter = serde_json::from_str("\"key:pressed && key == F1\"").unwrap();
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            "\"key:pressed && key == F1\""
+        );
+        assert!(serde_json::from_str::<EventFilter>("\"key ==\"").is_err());
+    }
+
+    fn expr() -> impl Strategy<Value = Expr> {
+        let key = prop_oneof![
+            (0..NAMED_KEYS.len()).prop_map(|i| NAMED_KEYS[i]),
+            any::<char>().prop_map(Key::Char),
+            any::<u32>().prop_map(Key::Unknown),
+        ];
+        let leaf = prop_oneof![
+            any::<bool>().prop_map(Expr::Const),
+            (0..EVENT_TYPES.len()).prop_map(|i| Expr::Type(EVENT_TYPES[i].1)),
+            (0..KINDS.len()).prop_map(|i| Expr::Kind(KINDS[i].1)),
+            (0..MODIFIERS.len()).prop_map(|i| Expr::Modifier(MODIFIERS[i].1)),
+            key.prop_map(Expr::Key),
+            (1u8..=255).prop_map(|n| Expr::Button(Button::from_number(n))),
+            Just(Expr::Injected),
+            (-1e6f64..1e6, -1e6f64..1e6, 0f64..1e4, 0f64..1e4).prop_map(|(x, y, width, height)| {
+                Expr::Region(Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+            }),
+        ];
+        leaf.prop_recursive(4, 32, 2, |inner| {
+            prop_oneof![
+                inner.clone().prop_map(|e| Expr::Not(Box::new(e))),
+                (inner.clone(), inner.clone())
+                    .prop_map(|(l, r)| Expr::And(Box::new(l), Box::new(r))),
+                (inner.clone(), inner).prop_map(|(l, r)| Expr::Or(Box::new(l), Box::new(r))),
+            ]
+        })
+    }
+
+    /// A sample of named keys for generated filters.
+    const NAMED_KEYS: [Key; 8] = [
+        Key::KeyA,
+        Key::Num1,
+        Key::F12,
+        Key::ShiftLeft,
+        Key::Escape,
+        Key::NumpadEnter,
+        Key::MediaPlayPause,
+        Key::IntlYen,
+    ];
+
+    proptest! {
+        #[test]
+        fn test_parse_display_round_trips(expr in expr()) {
+            let filter = EventFilter { expr };
+            let written = filter.to_string();
+            let parsed = EventFilter::parse(&written);
+            prop_assert_eq!(parsed.as_ref(), Ok(&filter), "{}", written);
+            prop_assert_eq!(parsed.unwrap().to_string(), written);
+        }
+    }
+}
@@ -0,0 +1,683 @@
+//! Shortcut text such as `⇧⌘P` or `Ctrl+Shift+P`.
+//!
+//! [`format_chord`] renders held modifiers plus a key, mouse button or wheel
+//! direction the way each platform writes shortcuts:
+//!
+//! | Style | Example | Modifier order |
+//! |-------|---------|----------------|
+//! | [`MacSymbols`](ChordStyle::MacSymbols) | `⌃⌥⇧⌘P` | `⌃ ⌥ ⇧ ⌘`, no separator |
+//! | [`WindowsText`](ChordStyle::WindowsText) | `Ctrl+Alt+Shift+Win+P` | Ctrl, Alt, Shift, Win |
+//! | [`LinuxText`](ChordStyle::LinuxText) | `Ctrl+Alt+Shift+Super+P` | Ctrl, Alt, Shift, Super |
+//!
+//! Labels are English. To translate them, or to rename single keys, use a
+//! [`ChordFormatter`] with overridden labels.
+//!
+//! # Example
+//!
+//! ```
+//! use monio::format::{ChordStyle, format_chord};
+//! use monio::{Key, Modifiers, ScrollDirection};
+//!
+//! let mods = Modifiers { shift: true, meta: true, ..Default::default() };
+//! assert_eq!(format_chord(&mods, Key::KeyP, ChordStyle::MacSymbols), "⇧⌘P");
+//! assert_eq!(format_chord(&mods, Key::KeyP, ChordStyle::WindowsText), "Shift+Win+P");
+//!
+//! let ctrl = Modifiers { ctrl: true, ..Default::default() };
+//! assert_eq!(
+//!     format_chord(&ctrl, ScrollDirection::Up, ChordStyle::LinuxText),
+//!     "Ctrl+Wheel Up"
+//! );
+//! ```
+
+use crate::event::{Button, Event, EventType, ScrollDirection};
+use crate::keycode::Key;
+use crate::state::Modifiers;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// How chords are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChordStyle {
+    /// macOS menu style: `⌃⌥⇧⌘` symbols followed by the key, e.g. `⇧⌘P`.
+    MacSymbols,
+    /// Windows style: words joined with `+`, e.g. `Ctrl+Shift+P`.
+    WindowsText,
+    /// Linux desktop style: like Windows, with `Super` for the meta key.
+    LinuxText,
+}
+
+impl ChordStyle {
+    /// The style of the platform this was built for.
+    pub fn native() -> Self {
+        if cfg!(target_os = "macos") {
+            ChordStyle::MacSymbols
+        } else if cfg!(target_os = "windows") {
+            ChordStyle::WindowsText
+        } else {
+            ChordStyle::LinuxText
+        }
+    }
+
+    /// Placed between the parts of a chord.
+    fn separator(self) -> &'static str {
+        match self {
+            ChordStyle::MacSymbols => "",
+            ChordStyle::WindowsText | ChordStyle::LinuxText => "+",
+        }
+    }
+}
+
+/// A keyboard modifier, as named in a chord.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChordModifier {
+    /// Control.
+    Ctrl,
+    /// Alt/Option.
+    Alt,
+    /// Shift.
+    Shift,
+    /// Meta/Command/Windows/Super.
+    Meta,
+}
+
+impl ChordModifier {
+    /// Every modifier, in the order all styles write them.
+    pub const ALL: [ChordModifier; 4] = [
+        ChordModifier::Ctrl,
+        ChordModifier::Alt,
+        ChordModifier::Shift,
+        ChordModifier::Meta,
+    ];
+
+    fn is_held(self, modifiers: &Modifiers) -> bool {
+        match self {
+            ChordModifier::Ctrl => modifiers.ctrl,
+            ChordModifier::Alt => modifiers.alt,
+            ChordModifier::Shift => modifiers.shift,
+            ChordModifier::Meta => modifiers.meta,
+        }
+    }
+
+    /// The modifier a modifier key sets, if `key` is one.
+    fn of_key(key: Key) -> Option<Self> {
+        match key {
+            Key::ControlLeft | Key::ControlRight => Some(ChordModifier::Ctrl),
+            Key::AltLeft | Key::AltRight => Some(ChordModifier::Alt),
+            Key::ShiftLeft | Key::ShiftRight => Some(ChordModifier::Shift),
+            Key::MetaLeft | Key::MetaRight => Some(ChordModifier::Meta),
+            _ => None,
+        }
+    }
+}
+
+/// One labelled part of a chord: a modifier or what it is combined with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChordPart {
+    /// A held modifier.
+    Modifier(ChordModifier),
+    /// A key.
+    Key(Key),
+    /// A mouse button.
+    Button(Button),
+    /// A wheel direction.
+    Wheel(ScrollDirection),
+}
+
+impl From<ChordModifier> for ChordPart {
+    fn from(modifier: ChordModifier) -> Self {
+        ChordPart::Modifier(modifier)
+    }
+}
+
+impl From<Key> for ChordPart {
+    fn from(key: Key) -> Self {
+        ChordPart::Key(key)
+    }
+}
+
+impl From<Button> for ChordPart {
+    fn from(button: Button) -> Self {
+        ChordPart::Button(button)
+    }
+}
+
+impl From<ScrollDirection> for ChordPart {
+    fn from(direction: ScrollDirection) -> Self {
+        ChordPart::Wheel(direction)
+    }
+}
+
+/// Write `modifiers` plus `trigger` (a key, button or wheel direction) in
+/// `style`, with the built-in English labels.
+///
+/// A modifier key held on its own reads as just that modifier: `Shift`, not
+/// `Shift+Shift`.
+pub fn format_chord(
+    modifiers: &Modifiers,
+    trigger: impl Into<ChordPart>,
+    style: ChordStyle,
+) -> String {
+    ChordFormatter::new(style).format(modifiers, trigger)
+}
+
+/// The chord a key, mouse button or wheel event shows, with the modifiers
+/// held at the time.
+pub fn format_event(event: &Event, style: ChordStyle) -> Option<String> {
+    ChordFormatter::new(style).format_event(event)
+}
+
+/// Formats chords in one style, with optional label overrides.
+///
+/// # Example
+///
+/// ```
+/// use monio::format::{ChordFormatter, ChordModifier, ChordStyle};
+/// use monio::{Key, Modifiers};
+///
+/// let german = ChordFormatter::new(ChordStyle::WindowsText)
+///     .with_label(ChordModifier::Ctrl, "Strg")
+///     .with_label(Key::Space, "Leertaste");
+/// let ctrl = Modifiers { ctrl: true, ..Default::default() };
+/// assert_eq!(german.format(&ctrl, Key::Space), "Strg+Leertaste");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChordFormatter {
+    style: ChordStyle,
+    labels: HashMap<ChordPart, String>,
+}
+
+impl ChordFormatter {
+    /// A formatter using the built-in labels of `style`.
+    pub fn new(style: ChordStyle) -> Self {
+        Self {
+            style,
+            labels: HashMap::new(),
+        }
+    }
+
+    /// Show `part` as `label` instead of its built-in label.
+    pub fn with_label(mut self, part: impl Into<ChordPart>, label: impl Into<String>) -> Self {
+        self.labels.insert(part.into(), label.into());
+        self
+    }
+
+    /// The style chords are written in.
+    pub fn style(&self) -> ChordStyle {
+        self.style
+    }
+
+    /// How `part` is shown.
+    pub fn label(&self, part: impl Into<ChordPart>) -> Cow<'_, str> {
+        let part = part.into();
+        if let Some(label) = self.labels.get(&part) {
+            return Cow::Borrowed(label);
+        }
+        match part {
+            ChordPart::Modifier(modifier) => modifier_label(modifier, self.style).into(),
+            ChordPart::Key(key) => key_label(key, self.style),
+            ChordPart::Button(button) => button_label(button, self.style),
+            ChordPart::Wheel(direction) => wheel_label(direction, self.style).into(),
+        }
+    }
+
+    /// Write `modifiers` plus `trigger`; see [`format_chord`].
+    pub fn format(&self, modifiers: &Modifiers, trigger: impl Into<ChordPart>) -> String {
+        let trigger = trigger.into();
+        let own = match trigger {
+            ChordPart::Modifier(modifier) => Some(modifier),
+            ChordPart::Key(key) => ChordModifier::of_key(key),
+            _ => None,
+        };
+        let mut parts: Vec<Cow<'_, str>> = ChordModifier::ALL
+            .into_iter()
+            .filter(|&modifier| modifier.is_held(modifiers) && Some(modifier) != own)
+            .map(|modifier| self.label(modifier))
+            .collect();
+        parts.push(self.label(trigger));
+        parts.join(self.style.separator())
+    }
+
+    /// The chord an event shows; see [`format_event`].
+    pub fn format_event(&self, event: &Event) -> Option<String> {
+        let trigger: ChordPart = match event.event_type {
+            EventType::KeyPressed | EventType::KeyReleased | EventType::KeyTyped => {
+                event.keyboard.as_ref()?.key.into()
+            }
+            EventType::MousePressed | EventType::MouseReleased | EventType::MouseClicked => {
+                event.mouse.as_ref()?.button?.into()
+            }
+            EventType::MouseWheel => event.wheel.as_ref()?.direction.into(),
+            _ => return None,
+        };
+        Some(self.format(&Modifiers::from_mask(event.mask), trigger))
+    }
+}
+
+/// The built-in label of a modifier.
+pub fn modifier_label(modifier: ChordModifier, style: ChordStyle) -> &'static str {
+    match (modifier, style) {
+        (ChordModifier::Ctrl, ChordStyle::MacSymbols) => "⌃",
+        (ChordModifier::Alt, ChordStyle::MacSymbols) => "⌥",
+        (ChordModifier::Shift, ChordStyle::MacSymbols) => "⇧",
+        (ChordModifier::Meta, ChordStyle::MacSymbols) => "⌘",
+        (ChordModifier::Ctrl, _) => "Ctrl",
+        (ChordModifier::Alt, _) => "Alt",
+        (ChordModifier::Shift, _) => "Shift",
+        (ChordModifier::Meta, ChordStyle::WindowsText) => "Win",
+        (ChordModifier::Meta, ChordStyle::LinuxText) => "Super",
+    }
+}
+
+/// The built-in label of a key.
+///
+/// Letters are upper case and punctuation is its US character. Modifier
+/// keys are labelled like the modifier they set.
+pub fn key_label(key: Key, style: ChordStyle) -> Cow<'static, str> {
+    if let Some(modifier) = ChordModifier::of_key(key) {
+        return modifier_label(modifier, style).into();
+    }
+    if style == ChordStyle::MacSymbols
+        && let Some(symbol) = mac_symbol(key)
+    {
+        return symbol.into();
+    }
+    let windows = style == ChordStyle::WindowsText;
+    let label = match key {
+        Key::F1 => "F1",
+        Key::F2 => "F2",
+        Key::F3 => "F3",
+        Key::F4 => "F4",
+        Key::F5 => "F5",
+        Key::F6 => "F6",
+        Key::F7 => "F7",
+        Key::F8 => "F8",
+        Key::F9 => "F9",
+        Key::F10 => "F10",
+        Key::F11 => "F11",
+        Key::F12 => "F12",
+        Key::F13 => "F13",
+        Key::F14 => "F14",
+        Key::F15 => "F15",
+        Key::F16 => "F16",
+        Key::F17 => "F17",
+        Key::F18 => "F18",
+        Key::F19 => "F19",
+        Key::F20 => "F20",
+        Key::F21 => "F21",
+        Key::F22 => "F22",
+        Key::F23 => "F23",
+        Key::F24 => "F24",
+        Key::Escape => "Esc",
+        Key::Tab => "Tab",
+        Key::CapsLock => "Caps Lock",
+        Key::Space => "Space",
+        Key::Enter => "Enter",
+        Key::Backspace => "Backspace",
+        Key::Insert if windows => "Ins",
+        Key::Insert => "Insert",
+        Key::Delete if windows => "Del",
+        Key::Delete => "Delete",
+        Key::Home => "Home",
+        Key::End => "End",
+        Key::PageUp if windows => "PgUp",
+        Key::PageUp => "Page Up",
+        Key::PageDown if windows => "PgDn",
+        Key::PageDown => "Page Down",
+        Key::ArrowUp => "Up",
+        Key::ArrowDown => "Down",
+        Key::ArrowLeft => "Left",
+        Key::ArrowRight => "Right",
+        Key::NumLock => "Num Lock",
+        Key::ScrollLock => "Scroll Lock",
+        Key::PrintScreen if windows => "PrtScn",
+        Key::PrintScreen => "Print",
+        Key::Pause => "Pause",
+        Key::Numpad0 => "Numpad 0",
+        Key::Numpad1 => "Numpad 1",
+        Key::Numpad2 => "Numpad 2",
+        Key::Numpad3 => "Numpad 3",
+        Key::Numpad4 => "Numpad 4",
+        Key::Numpad5 => "Numpad 5",
+        Key::Numpad6 => "Numpad 6",
+        Key::Numpad7 => "Numpad 7",
+        Key::Numpad8 => "Numpad 8",
+        Key::Numpad9 => "Numpad 9",
+        Key::NumpadAdd => "Numpad +",
+        Key::NumpadSubtract => "Numpad -",
+        Key::NumpadMultiply => "Numpad *",
+        Key::NumpadDivide => "Numpad /",
+        Key::NumpadDecimal => "Numpad .",
+        Key::NumpadEnter => "Numpad Enter",
+        Key::NumpadEqual => "Numpad =",
+        Key::VolumeUp => "Volume Up",
+        Key::VolumeDown => "Volume Down",
+        Key::VolumeMute => "Mute",
+        Key::MediaPlayPause => "Play/Pause",
+        Key::MediaStop => "Stop",
+        Key::MediaNext => "Next Track",
+        Key::MediaPrevious => "Previous Track",
+        Key::BrowserBack => "Browser Back",
+        Key::BrowserForward => "Browser Forward",
+        Key::BrowserRefresh => "Browser Refresh",
+        Key::BrowserStop => "Browser Stop",
+        Key::BrowserSearch => "Browser Search",
+        Key::BrowserFavorites => "Browser Favorites",
+        Key::BrowserHome => "Browser Home",
+        Key::LaunchMail => "Mail",
+        Key::LaunchApp1 => "App 1",
+        Key::LaunchApp2 => "App 2",
+        Key::IntlBackslash => "Intl \\",
+        Key::IntlYen => "¥",
+        Key::IntlRo => "Ro",
+        Key::ContextMenu => "Menu",
+        Key::Unknown(code) => return format!("Key {code}").into(),
+        Key::KeyA
+        | Key::KeyB
+        | Key::KeyC
+        | Key::KeyD
+        | Key::KeyE
+        | Key::KeyF
+        | Key::KeyG
+        | Key::KeyH
+        | Key::KeyI
+        | Key::KeyJ
+        | Key::KeyK
+        | Key::KeyL
+        | Key::KeyM
+        | Key::KeyN
+        | Key::KeyO
+        | Key::KeyP
+        | Key::KeyQ
+        | Key::KeyR
+        | Key::KeyS
+        | Key::KeyT
+        | Key::KeyU
+        | Key::KeyV
+        | Key::KeyW
+        | Key::KeyX
+        | Key::KeyY
+        | Key::KeyZ
+        | Key::Num0
+        | Key::Num1
+        | Key::Num2
+        | Key::Num3
+        | Key::Num4
+        | Key::Num5
+        | Key::Num6
+        | Key::Num7
+        | Key::Num8
+        | Key::Num9
+        | Key::Grave
+        | Key::Minus
+        | Key::Equal
+        | Key::BracketLeft
+        | Key::BracketRight
+        | Key::Backslash
+        | Key::Semicolon
+        | Key::Quote
+        | Key::Comma
+        | Key::Period
+        | Key::Slash
+        | Key::Char(_) => {
+            return match key.as_char() {
+                Some(ch) => ch.to_uppercase().collect::<String>().into(),
+                None => format!("{key:?}").into(),
+            };
+        }
+        Key::ShiftLeft
+        | Key::ShiftRight
+        | Key::ControlLeft
+        | Key::ControlRight
+        | Key::AltLeft
+        | Key::AltRight
+        | Key::MetaLeft
+        | Key::MetaRight => unreachable!("modifier keys are labelled above"),
+    };
+    label.into()
+}
+
+/// Keys macOS menus show as symbols.
+fn mac_symbol(key: Key) -> Option<&'static str> {
+    Some(match key {
+        Key::Escape => "⎋",
+        Key::Tab => "⇥",
+        Key::CapsLock => "⇪",
+        Key::Enter => "↩",
+        Key::NumpadEnter => "⌤",
+        Key::Backspace => "⌫",
+        Key::Delete => "⌦",
+        Key::Home => "↖",
+        Key::End => "↘",
+        Key::PageUp => "⇞",
+        Key::PageDown => "⇟",
+        Key::ArrowUp => "↑",
+        Key::ArrowDown => "↓",
+        Key::ArrowLeft => "←",
+        Key::ArrowRight => "→",
+        _ => return None,
+    })
+}
+
+/// The built-in label of a mouse button.
+pub fn button_label(button: Button, _style: ChordStyle) -> Cow<'static, str> {
+    match button {
+        Button::Left => "Left Click".into(),
+        Button::Right => "Right Click".into(),
+        Button::Middle => "Middle Click".into(),
+        Button::Button4 => "Mouse Back".into(),
+        Button::Button5 => "Mouse Forward".into(),
+        Button::Unknown(n) => format!("Mouse {n}").into(),
+    }
+}
+
+/// The built-in label of a wheel direction.
+pub fn wheel_label(direction: ScrollDirection, style: ChordStyle) -> &'static str {
+    match (direction, style) {
+        (ScrollDirection::Up, ChordStyle::MacSymbols) => "Scroll Up",
+        (ScrollDirection::Down, ChordStyle::MacSymbols) => "Scroll Down",
+        (ScrollDirection::Left, ChordStyle::MacSymbols) => "Scroll Left",
+        (ScrollDirection::Right, ChordStyle::MacSymbols) => "Scroll Right",
+        (ScrollDirection::Up, _) => "Wheel Up",
+        (ScrollDirection::Down, _) => "Wheel Down",
+        (ScrollDirection::Left, _) => "Wheel Left",
+        (ScrollDirection::Right, _) => "Wheel Right",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{MASK_ALT, MASK_CTRL, MASK_META, MASK_SHIFT};
+
+    const STYLES: [ChordStyle; 3] = [
+        ChordStyle::MacSymbols,
+        ChordStyle::WindowsText,
+        ChordStyle::LinuxText,
+    ];
+
+    /// Each chord in every style, one line per chord.
+    fn snapshot(chords: &[(u32, ChordPart)]) -> String {
+        chords
+            .iter()
+            .map(|&(mask, part)| {
+                let mods = Modifiers::from_mask(mask);
+                STYLES
+                    .iter()
+                    .map(|&style| format_chord(&mods, part, style))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_modifier_order() {
+        let all = MASK_CTRL | MASK_ALT | MASK_SHIFT | MASK_META;
+        let chords = [
+            (0, Key::KeyP.into()),
+            (MASK_META | MASK_SHIFT, Key::KeyP.into()),
+            (all, Key::KeyP.into()),
+            (MASK_SHIFT | MASK_ALT, Key::Num1.into()),
+            (MASK_CTRL, Key::Slash.into()),
+            (MASK_META, Key::Char('é').into()),
+        ];
+        assert_eq!(
+            snapshot(&chords),
+            "\
+P | P | P
+⇧⌘P | Shift+Win+P | Shift+Super+P
+⌃⌥⇧⌘P | Ctrl+Alt+Shift+Win+P | Ctrl+Alt+Shift+Super+P
+⌥⇧1 | Alt+Shift+1 | Alt+Shift+1
+⌃/ | Ctrl+/ | Ctrl+/
+⌘É | Win+É | Super+É"
+        );
+    }
+
+    #[test]
+    fn test_key_labels() {
+        let chords = [
+            (MASK_CTRL, Key::Enter.into()),
+            (MASK_CTRL, Key::NumpadEnter.into()),
+            (0, Key::Escape.into()),
+            (MASK_SHIFT, Key::Tab.into()),
+            (0, Key::CapsLock.into()),
+            (MASK_ALT, Key::Backspace.into()),
+            (0, Key::Delete.into()),
+            (0, Key::Insert.into()),
+            (0, Key::Home.into()),
+            (0, Key::End.into()),
+            (0, Key::PageUp.into()),
+            (0, Key::PageDown.into()),
+            (MASK_META, Key::ArrowLeft.into()),
+            (0, Key::ArrowUp.into()),
+            (MASK_CTRL, Key::Space.into()),
+            (0, Key::PrintScreen.into()),
+            (0, Key::F12.into()),
+            (0, Key::Numpad7.into()),
+            (0, Key::NumpadAdd.into()),
+            (0, Key::MediaPlayPause.into()),
+            (0, Key::Unknown(300).into()),
+        ];
+        assert_eq!(
+            snapshot(&chords),
+            "\
+⌃↩ | Ctrl+Enter | Ctrl+Enter
+⌃⌤ | Ctrl+Numpad Enter | Ctrl+Numpad Enter
+⎋ | Esc | Esc
+⇧⇥ | Shift+Tab | Shift+Tab
+⇪ | Caps Lock | Caps Lock
+⌥⌫ | Alt+Backspace | Alt+Backspace
+⌦ | Del | Delete
+Insert | Ins | Insert
+↖ | Home | Home
+↘ | End | End
+⇞ | PgUp | Page Up
+⇟ | PgDn | Page Down
+⌘← | Win+Left | Super+Left
+↑ | Up | Up
+⌃Space | Ctrl+Space | Ctrl+Space
+Print | PrtScn | Print
+F12 | F12 | F12
+Numpad 7 | Numpad 7 | Numpad 7
+Numpad + | Numpad + | Numpad +
+Play/Pause | Play/Pause | Play/Pause
+Key 300 | Key 300 | Key 300"
+        );
+    }
+
+    #[test]
+    fn test_modifier_keys() {
+        let chords = [
+            (MASK_SHIFT, Key::ShiftLeft.into()),
+            (MASK_CTRL | MASK_SHIFT, Key::ShiftRight.into()),
+            (MASK_META, Key::MetaLeft.into()),
+            (MASK_CTRL, ChordModifier::Alt.into()),
+        ];
+        assert_eq!(
+            snapshot(&chords),
+            "\
+⇧ | Shift | Shift
+⌃⇧ | Ctrl+Shift | Ctrl+Shift
+⌘ | Win | Super
+⌃⌥ | Ctrl+Alt | Ctrl+Alt"
+        );
+    }
+
+    #[test]
+    fn test_mouse_chords() {
+        let chords = [
+            (MASK_CTRL, ScrollDirection::Up.into()),
+            (MASK_SHIFT, ScrollDirection::Right.into()),
+            (0, ScrollDirection::Down.into()),
+            (MASK_META, Button::Left.into()),
+            (MASK_ALT, Button::Middle.into()),
+            (0, Button::Button4.into()),
+            (0, Button::Unknown(8).into()),
+        ];
+        assert_eq!(
+            snapshot(&chords),
+            "\
+⌃Scroll Up | Ctrl+Wheel Up | Ctrl+Wheel Up
+⇧Scroll Right | Shift+Wheel Right | Shift+Wheel Right
+Scroll Down | Wheel Down | Wheel Down
+⌘Left Click | Win+Left Click | Super+Left Click
+⌥Middle Click | Alt+Middle Click | Alt+Middle Click
+Mouse Back | Mouse Back | Mouse Back
+Mouse 8 | Mouse 8 | Mouse 8"
+        );
+    }
+
+    #[test]
+    fn test_character_keys() {
+        // Every named key has a label by construction (the match has no
+        // fallback); character keys show their US character
+        for ch in ' '..='~' {
+            let key = Key::from_char(ch);
+            if ch == ' ' || key.as_char().is_none() {
+                continue;
+            }
+            for style in STYLES {
+                assert_eq!(
+                    key_label(key, style),
+                    ch.to_ascii_uppercase().to_string(),
+                    "{key:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_formatter_overrides() {
+        let formatter = ChordFormatter::new(ChordStyle::MacSymbols)
+            .with_label(ChordModifier::Meta, "Cmd-")
+            .with_label(ScrollDirection::Up, "Molette haut");
+        let mods = Modifiers::from_mask(MASK_META | MASK_SHIFT);
+        assert_eq!(formatter.format(&mods, Key::KeyS), "⇧Cmd-S");
+        assert_eq!(
+            formatter.format(&mods, ScrollDirection::Up),
+            "⇧Cmd-Molette haut"
+        );
+        assert_eq!(formatter.label(Key::KeyS), "S");
+    }
+
+    #[test]
+    fn test_format_event() {
+        let mut event = Event::key_pressed(Key::KeyZ, 0);
+        event.mask = MASK_CTRL | MASK_SHIFT;
+        assert_eq!(
+            format_event(&event, ChordStyle::LinuxText).as_deref(),
+            Some("Ctrl+Shift+Z")
+        );
+        let mut wheel = Event::mouse_wheel(0.0, 0.0, ScrollDirection::Down, 1.0);
+        wheel.mask = MASK_CTRL;
+        assert_eq!(
+            format_event(&wheel, ChordStyle::MacSymbols).as_deref(),
+            Some("⌃Scroll Down")
+        );
+        assert_eq!(
+            format_event(&Event::mouse_moved(1.0, 2.0), ChordStyle::WindowsText),
+            None
+        );
+    }
+}
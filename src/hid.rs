@@ -0,0 +1,640 @@
+//! Keys from raw HID devices that the OS doesn't map to anything.
+//!
+//! Keyboards running custom firmware (QMK, ZMK) can send extra keys as HID
+//! usage codes on a vendor-defined usage page. The OS has no meaning for
+//! them, so hooks never see them. A [`HidBridge`] opens those devices
+//! directly, turns the usage codes it is configured with into key events,
+//! and delivers them to every running hook alongside the OS's events, where
+//! [`Hook::on_key_pressed`](crate::Hook::on_key_pressed) and the other
+//! helpers can bind them.
+//!
+//! The bridge only reads: it never grabs the device, so whatever the OS does
+//! with the device's other input is unaffected, and grab hooks can't
+//! consume the bridged keys (their verdicts are ignored).
+//!
+//! # Reports
+//!
+//! Input reports are read as an array of the usage codes held right now,
+//! each a little-endian `u16`, with zero for unused slots, the way HID
+//! array fields such as the consumer page are laid out. A code appearing
+//! in a report is a [`KeyPressed`](crate::EventType::KeyPressed), and
+//! disappearing a [`KeyReleased`](crate::EventType::KeyReleased). When the
+//! device uses numbered reports, set [`HidBridge::report_id`]; reports with
+//! other IDs are skipped.
+//!
+//! Bridged events carry the usage code as their
+//! [`raw_code`](crate::event::KeyboardData::raw_code) and are delivered on
+//! the device's reader thread.
+//!
+//! # Hot-plug
+//!
+//! Devices are looked for when the bridge starts and every
+//! [`rescan_interval`](HidBridge::rescan_interval) after. A device that
+//! goes away releases the keys it held.
+//!
+//! # Permissions
+//!
+//! On Linux the `hidraw` nodes are usually root-only; give your user access
+//! with a udev rule for the device's vendor ID.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::hid::HidBridge;
+//! use monio::{Hook, Key};
+//!
+//! // QMK raw HID interface of a keyboard with vendor ID 0xFEED
+//! let _bridge = HidBridge::new(0xFEED)
+//!     .usage_page(0xFF60)
+//!     .map(0x0100, Key::F13)
+//!     .map_raw(0x0101)
+//!     .start()?;
+//!
+//! let hook = Hook::new();
+//! let _macro1 = hook.on_key_pressed(Key::F13, || println!("macro 1"));
+//! let _macro2 = hook.on_key_pressed(Key::Unknown(0x0101), || println!("macro 2"));
+//! hook.run(|_: &monio::Event| {})?;
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::event::Event;
+use crate::keycode::Key;
+use crate::source;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often devices are looked for by default.
+pub const DEFAULT_RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a reader waits for a report before checking whether to stop.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Largest input report read. Full-speed USB reports are at most 64 bytes.
+const MAX_REPORT: usize = 64;
+
+/// A HID device interface found by a [`HidTransport`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HidDeviceInfo {
+    /// Platform path the interface is opened by.
+    pub path: String,
+    /// USB vendor ID.
+    pub vendor_id: u16,
+    /// USB product ID.
+    pub product_id: u16,
+    /// Usage page of the interface's top-level collection.
+    pub usage_page: u16,
+}
+
+/// Finds and opens HID devices for a [`HidBridge`].
+///
+/// [`HidBridge::start`] uses the system's HID API; tests can pass their own
+/// to [`HidBridge::start_with`].
+pub trait HidTransport: Send {
+    /// The HID interfaces connected right now.
+    fn devices(&mut self) -> Result<Vec<HidDeviceInfo>>;
+
+    /// Open the interface at `path` for reading.
+    fn open(&mut self, path: &str) -> Result<Box<dyn HidReader>>;
+}
+
+/// An open HID interface.
+pub trait HidReader: Send {
+    /// Read one input report into `buf`, waiting up to `timeout`.
+    ///
+    /// Returns the report's length, or 0 if none came in. An error means
+    /// the device is gone.
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize>;
+}
+
+/// Configures which HID devices to read and which usage codes become keys.
+///
+/// Created with [`HidBridge::new`], started with [`start`](Self::start).
+#[derive(Debug, Clone)]
+pub struct HidBridge {
+    vendor_id: u16,
+    product_id: Option<u16>,
+    usage_page: Option<u16>,
+    report_id: Option<u8>,
+    keys: HashMap<u16, Key>,
+    rescan_interval: Duration,
+}
+
+impl HidBridge {
+    /// A bridge for the devices with a USB vendor ID, mapping no codes yet.
+    pub fn new(vendor_id: u16) -> Self {
+        Self {
+            vendor_id,
+            product_id: None,
+            usage_page: None,
+            report_id: None,
+            keys: HashMap::new(),
+            rescan_interval: DEFAULT_RESCAN_INTERVAL,
+        }
+    }
+
+    /// Only read devices with this USB product ID.
+    pub fn product_id(mut self, product_id: u16) -> Self {
+        self.product_id = Some(product_id);
+        self
+    }
+
+    /// Only read interfaces on this usage page, such as QMK's raw HID page
+    /// `0xFF60`.
+    ///
+    /// A keyboard usually has several interfaces, and the regular keyboard
+    /// one is best left to the OS.
+    pub fn usage_page(mut self, usage_page: u16) -> Self {
+        self.usage_page = Some(usage_page);
+        self
+    }
+
+    /// Expect reports to start with this report ID, and skip others.
+    pub fn report_id(mut self, report_id: u8) -> Self {
+        self.report_id = Some(report_id);
+        self
+    }
+
+    /// Report usage code `usage` as `key`.
+    pub fn map(mut self, usage: u16, key: Key) -> Self {
+        self.keys.insert(usage, key);
+        self
+    }
+
+    /// Report usage code `usage` as [`Key::Unknown`] carrying the code.
+    pub fn map_raw(self, usage: u16) -> Self {
+        self.map(usage, Key::Unknown(u32::from(usage)))
+    }
+
+    /// How often to look for devices plugged in (default
+    /// [`DEFAULT_RESCAN_INTERVAL`]).
+    pub fn rescan_interval(mut self, interval: Duration) -> Self {
+        self.rescan_interval = interval;
+        self
+    }
+
+    /// Start reading the matching devices through the system's HID API.
+    pub fn start(self) -> Result<HidBridgeHandle> {
+        self.start_with(HidApiTransport::new()?)
+    }
+
+    /// Start reading the matching devices through `transport`.
+    pub fn start_with(self, transport: impl HidTransport + 'static) -> Result<HidBridgeHandle> {
+        self.spawn(transport, source::deliver)
+    }
+
+    fn spawn(
+        self,
+        mut transport: impl HidTransport + 'static,
+        emit: fn(&Event),
+    ) -> Result<HidBridgeHandle> {
+        if self.keys.is_empty() {
+            return Err(Error::Other(
+                "The HID bridge needs at least one mapped usage code".into(),
+            ));
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let open = Arc::new(Mutex::new(HashSet::new()));
+        let scanner = {
+            let stop = stop.clone();
+            let open = open.clone();
+            let config = Arc::new(self);
+            thread::Builder::new()
+                .name("monio-hid".into())
+                .spawn(move || {
+                    let mut readers = Vec::new();
+                    while !stop.load(Ordering::SeqCst) {
+                        readers.retain(|reader: &JoinHandle<()>| !reader.is_finished());
+                        for device in config.new_devices(&mut transport, &open) {
+                            match read_device(&config, &mut transport, device, &stop, &open, emit) {
+                                Ok(reader) => readers.push(reader),
+                                Err(e) => log::warn!("Can't read HID device: {}", e),
+                            }
+                        }
+                        thread::park_timeout(config.rescan_interval);
+                    }
+                    for reader in readers {
+                        let _ = reader.join();
+                    }
+                })
+                .map_err(|e| Error::ThreadError(format!("failed to spawn HID bridge: {}", e)))?
+        };
+        Ok(HidBridgeHandle {
+            stop,
+            open,
+            scanner: Some(scanner),
+        })
+    }
+
+    fn matches(&self, device: &HidDeviceInfo) -> bool {
+        device.vendor_id == self.vendor_id
+            && self.product_id.is_none_or(|id| id == device.product_id)
+            && self.usage_page.is_none_or(|page| page == device.usage_page)
+    }
+
+    /// Emit the key event for a usage code, if it's mapped.
+    fn emit(&self, usage: u16, pressed: bool, emit: fn(&Event)) {
+        let Some(&key) = self.keys.get(&usage) else {
+            return;
+        };
+        let mut event = if pressed {
+            Event::key_pressed(key, u32::from(usage))
+        } else {
+            Event::key_released(key, u32::from(usage))
+        };
+        event.injected = Some(false);
+        emit(&event);
+    }
+
+    /// Matching devices that aren't open yet.
+    fn new_devices(
+        &self,
+        transport: &mut impl HidTransport,
+        open: &Mutex<HashSet<HidDeviceInfo>>,
+    ) -> Vec<HidDeviceInfo> {
+        let devices = match transport.devices() {
+            Ok(devices) => devices,
+            Err(e) => {
+                log::warn!("Can't list HID devices: {}", e);
+                return Vec::new();
+            }
+        };
+        let open = open.lock().unwrap_or_else(|e| e.into_inner());
+        devices
+            .into_iter()
+            .filter(|device| self.matches(device) && !open.contains(device))
+            .collect()
+    }
+}
+
+/// Open `device` and read it on a thread of its own until it goes away or
+/// the bridge stops.
+fn read_device(
+    config: &Arc<HidBridge>,
+    transport: &mut impl HidTransport,
+    device: HidDeviceInfo,
+    stop: &Arc<AtomicBool>,
+    open: &Arc<Mutex<HashSet<HidDeviceInfo>>>,
+    emit: fn(&Event),
+) -> Result<JoinHandle<()>> {
+    let mut reader = transport.open(&device.path)?;
+    open.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(device.clone());
+    let config = config.clone();
+    let stop = stop.clone();
+    let open = open.clone();
+    thread::Builder::new()
+        .name("monio-hid-reader".into())
+        .spawn(move || {
+            let mut decoder = UsageDecoder::new(config.report_id);
+            let mut buf = [0u8; MAX_REPORT];
+            while !stop.load(Ordering::SeqCst) {
+                match reader.read(&mut buf, READ_TIMEOUT) {
+                    Ok(0) => {}
+                    Ok(len) => {
+                        for (usage, pressed) in decoder.decode(&buf[..len]) {
+                            config.emit(usage, pressed, emit);
+                        }
+                    }
+                    Err(e) => {
+                        log::info!("HID device {} went away: {}", device.path, e);
+                        break;
+                    }
+                }
+            }
+            for usage in decoder.release_all() {
+                config.emit(usage, false, emit);
+            }
+            open.lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&device);
+        })
+        .map_err(|e| Error::ThreadError(format!("failed to spawn HID reader: {}", e)))
+}
+
+/// A running [`HidBridge`], stopped when dropped.
+#[derive(Debug)]
+pub struct HidBridgeHandle {
+    stop: Arc<AtomicBool>,
+    open: Arc<Mutex<HashSet<HidDeviceInfo>>>,
+    scanner: Option<JoinHandle<()>>,
+}
+
+impl HidBridgeHandle {
+    /// The devices being read right now.
+    pub fn devices(&self) -> Vec<HidDeviceInfo> {
+        self.open
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Stop reading, releasing any keys still held.
+    pub fn stop(mut self) {
+        self.stop_inner();
+    }
+
+    fn stop_inner(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(scanner) = self.scanner.take() {
+            scanner.thread().unpark();
+            let _ = scanner.join();
+        }
+    }
+}
+
+impl Drop for HidBridgeHandle {
+    fn drop(&mut self) {
+        self.stop_inner();
+    }
+}
+
+/// Turns array reports of held usage codes into presses and releases.
+#[derive(Debug)]
+struct UsageDecoder {
+    report_id: Option<u8>,
+    held: Vec<u16>,
+}
+
+impl UsageDecoder {
+    fn new(report_id: Option<u8>) -> Self {
+        Self {
+            report_id,
+            held: Vec::new(),
+        }
+    }
+
+    /// The codes pressed (`true`) and released (`false`) since the last
+    /// report, releases first.
+    fn decode(&mut self, report: &[u8]) -> Vec<(u16, bool)> {
+        let usages = match (self.report_id, report.split_first()) {
+            (None, _) => report,
+            (Some(id), Some((&first, rest))) if first == id => rest,
+            (Some(_), _) => return Vec::new(),
+        };
+        let mut now: Vec<u16> = usages
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .filter(|&usage| usage != 0)
+            .collect();
+        now.dedup();
+        let changes = self
+            .held
+            .iter()
+            .filter(|usage| !now.contains(usage))
+            .map(|&usage| (usage, false))
+            .chain(
+                now.iter()
+                    .filter(|usage| !self.held.contains(usage))
+                    .map(|&usage| (usage, true)),
+            )
+            .collect();
+        self.held = now;
+        changes
+    }
+
+    /// Forget the held codes, returning them.
+    fn release_all(&mut self) -> Vec<u16> {
+        std::mem::take(&mut self.held)
+    }
+}
+
+/// The system's HID API.
+struct HidApiTransport(hidapi::HidApi);
+
+impl HidApiTransport {
+    fn new() -> Result<Self> {
+        hidapi::HidApi::new()
+            .map(Self)
+            .map_err(|e| Error::Platform(format!("Failed to open the HID API: {}", e)))
+    }
+}
+
+impl HidTransport for HidApiTransport {
+    fn devices(&mut self) -> Result<Vec<HidDeviceInfo>> {
+        self.0
+            .refresh_devices()
+            .map_err(|e| Error::Platform(format!("Failed to list HID devices: {}", e)))?;
+        Ok(self
+            .0
+            .device_list()
+            .map(|device| HidDeviceInfo {
+                path: device.path().to_string_lossy().into_owned(),
+                vendor_id: device.vendor_id(),
+                product_id: device.product_id(),
+                usage_page: device.usage_page(),
+            })
+            .collect())
+    }
+
+    fn open(&mut self, path: &str) -> Result<Box<dyn HidReader>> {
+        let path = CString::new(path)
+            .map_err(|_| Error::Other(format!("Invalid HID device path: {:?}", path)))?;
+        let device = self
+            .0
+            .open_path(&path)
+            .map_err(|e| Error::Platform(format!("Failed to open HID device: {}", e)))?;
+        Ok(Box::new(HidApiReader(device)))
+    }
+}
+
+struct HidApiReader(hidapi::HidDevice);
+
+impl HidReader for HidApiReader {
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        let timeout = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+        self.0
+            .read_timeout(buf, timeout)
+            .map_err(|e| Error::Platform(format!("Failed to read HID report: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventType;
+    use std::collections::VecDeque;
+    use std::time::Instant;
+
+    /// Tests share the event sink.
+    static SERIAL: Mutex<()> = Mutex::new(());
+    static EMITTED: Mutex<Vec<(EventType, Key, u32)>> = Mutex::new(Vec::new());
+
+    fn record(event: &Event) {
+        let kb = event.keyboard.as_ref().unwrap();
+        EMITTED
+            .lock()
+            .unwrap()
+            .push((event.event_type, kb.key, kb.raw_code));
+    }
+
+    /// Reports queued per device path; a device is unplugged once its
+    /// queue is removed.
+    type Reports = Arc<Mutex<HashMap<String, VecDeque<Vec<u8>>>>>;
+
+    struct MockTransport {
+        devices: Arc<Mutex<Vec<HidDeviceInfo>>>,
+        reports: Reports,
+    }
+
+    impl HidTransport for MockTransport {
+        fn devices(&mut self) -> Result<Vec<HidDeviceInfo>> {
+            Ok(self.devices.lock().unwrap().clone())
+        }
+
+        fn open(&mut self, path: &str) -> Result<Box<dyn HidReader>> {
+            self.reports
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), VecDeque::new());
+            Ok(Box::new(MockReader {
+                path: path.to_string(),
+                reports: self.reports.clone(),
+            }))
+        }
+    }
+
+    struct MockReader {
+        path: String,
+        reports: Reports,
+    }
+
+    impl HidReader for MockReader {
+        fn read(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+            let report = match self.reports.lock().unwrap().get_mut(&self.path) {
+                Some(queue) => queue.pop_front(),
+                None => return Err(Error::Platform("unplugged".into())),
+            };
+            match report {
+                Some(report) => {
+                    buf[..report.len()].copy_from_slice(&report);
+                    Ok(report.len())
+                }
+                None => {
+                    thread::sleep(timeout.min(Duration::from_millis(5)));
+                    Ok(0)
+                }
+            }
+        }
+    }
+
+    fn device(path: &str, vendor_id: u16, usage_page: u16) -> HidDeviceInfo {
+        HidDeviceInfo {
+            path: path.into(),
+            vendor_id,
+            product_id: 1,
+            usage_page,
+        }
+    }
+
+    fn wait_until(what: &str, mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn emitted() -> Vec<(EventType, Key, u32)> {
+        EMITTED.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn test_decoder_reports_changes() {
+        let mut decoder = UsageDecoder::new(None);
+        assert_eq!(decoder.decode(&[0x00, 0x01, 0, 0]), [(0x0100, true)]);
+        // Still held, and a second code joins
+        assert_eq!(decoder.decode(&[0x00, 0x01, 0x01, 0x01]), [(0x0101, true)]);
+        assert_eq!(decoder.decode(&[0x01, 0x01, 0, 0]), [(0x0100, false)]);
+        assert_eq!(decoder.decode(&[0; 4]), [(0x0101, false)]);
+    }
+
+    #[test]
+    fn test_decoder_checks_report_id() {
+        let mut decoder = UsageDecoder::new(Some(3));
+        assert!(decoder.decode(&[4, 0x20, 0]).is_empty());
+        assert_eq!(decoder.decode(&[3, 0x20, 0]), [(0x20, true)]);
+        assert_eq!(decoder.release_all(), [0x20]);
+    }
+
+    #[test]
+    fn test_bridge_maps_codes_and_follows_hot_plug() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        EMITTED.lock().unwrap().clear();
+
+        let devices = Arc::new(Mutex::new(vec![device("other", 0x1234, 0xFF60)]));
+        let reports: Reports = Arc::default();
+        let bridge = HidBridge::new(0xFEED)
+            .usage_page(0xFF60)
+            .map(0x0100, Key::F13)
+            .map_raw(0x0101)
+            .rescan_interval(Duration::from_millis(10))
+            .spawn(
+                MockTransport {
+                    devices: devices.clone(),
+                    reports: reports.clone(),
+                },
+                record,
+            )
+            .unwrap();
+        assert!(bridge.devices().is_empty());
+
+        // Plugged in after the bridge started; the keyboard interface of the
+        // same device is left alone
+        devices.lock().unwrap().extend([
+            device("macros", 0xFEED, 0xFF60),
+            device("keyboard", 0xFEED, 0x0001),
+        ]);
+        wait_until("the device to open", || bridge.devices().len() == 1);
+        assert_eq!(bridge.devices()[0].path, "macros");
+
+        let send = |report: Vec<u8>| {
+            reports
+                .lock()
+                .unwrap()
+                .get_mut("macros")
+                .unwrap()
+                .push_back(report)
+        };
+        send(vec![0x00, 0x01, 0, 0]);
+        // Not mapped, so ignored
+        send(vec![0x00, 0x01, 0x02, 0x01]);
+        send(vec![0x02, 0x01, 0, 0]);
+        send(vec![0x01, 0x01, 0, 0]);
+        wait_until("the reports", || emitted().len() == 3);
+
+        // Unplugging releases what was held
+        devices.lock().unwrap().clear();
+        reports.lock().unwrap().remove("macros");
+        wait_until("the device to close", || bridge.devices().is_empty());
+        assert_eq!(
+            emitted(),
+            [
+                (EventType::KeyPressed, Key::F13, 0x0100),
+                (EventType::KeyReleased, Key::F13, 0x0100),
+                (EventType::KeyPressed, Key::Unknown(0x0101), 0x0101),
+                (EventType::KeyReleased, Key::Unknown(0x0101), 0x0101),
+            ]
+        );
+        bridge.stop();
+    }
+
+    #[test]
+    fn test_bridge_needs_a_mapping() {
+        let transport = MockTransport {
+            devices: Arc::default(),
+            reports: Arc::default(),
+        };
+        assert!(matches!(
+            HidBridge::new(0xFEED).spawn(transport, record),
+            Err(Error::Other(_))
+        ));
+    }
+}
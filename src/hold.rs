@@ -0,0 +1,335 @@
+//! Hold-and-replay: deciding about a grabbed event later.
+//!
+//! A [`HoldHandler`] returns a [`Verdict`] for every event. [`Verdict::Hold`]
+//! suppresses the event like [`Verdict::Consume`], but keeps it buffered under
+//! a [`HoldToken`]. Once more input has arrived (or a timer fires), the events
+//! held under that token are released with [`HoldRelease::Replay`] to
+//! re-inject them in their original order, or [`HoldRelease::Drop`] to discard
+//! them.
+//!
+//! Replayed events are tagged as synthetic, so the grab hook passes them on
+//! without showing them to the handler a second time.
+//!
+//! # Ordering
+//!
+//! Events held under one token are replayed in the order they were held.
+//! Relative to newer native input:
+//!
+//! - **macOS**: replayed events are posted at the HID tap location. Native
+//!   events the tap already passed on are delivered first; events still in
+//!   flight when `release()` is called may interleave.
+//! - **Windows**: replayed events are appended to the system input queue with
+//!   `SendInput`, behind native events the hook already passed on. Input that
+//!   arrives while `release()` runs may interleave.
+//! - **Linux/evdev**: replayed and passed-through events share one uinput
+//!   device, so they reach the system in exactly the order they are written.
+//! - **Linux/X11**: events can't be suppressed, so holding has no effect on
+//!   delivery. `Replay` does nothing (the event was already delivered) and
+//!   `Drop` can't take it back.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::hold::{HoldRelease, HoldToken, Verdict};
+//! use monio::{Event, EventType, Hook, Key};
+//! use std::sync::Mutex;
+//!
+//! let hook = Hook::new();
+//! let held = hook.held_events();
+//! let pending: Mutex<Option<HoldToken>> = Mutex::new(None);
+//!
+//! // Swallow a lone Space tap, but let Space through when another key follows
+//! hook.grab_with_holds(move |event: &Event| {
+//!     let is_space = event.keyboard.as_ref().is_some_and(|kb| kb.key == Key::Space);
+//!     let mut pending = pending.lock().unwrap();
+//!     match event.event_type {
+//!         EventType::KeyPressed if is_space => {
+//!             let token = HoldToken::new();
+//!             *pending = Some(token);
+//!             Verdict::Hold(token)
+//!         }
+//!         EventType::KeyReleased if is_space => match pending.take() {
+//!             Some(token) => {
+//!                 let _ = held.release(token, HoldRelease::Drop);
+//!                 Verdict::Consume
+//!             }
+//!             None => Verdict::Pass,
+//!         },
+//!         EventType::KeyPressed => {
+//!             if let Some(token) = pending.take() {
+//!                 let _ = held.release(token, HoldRelease::Replay);
+//!             }
+//!             Verdict::Pass
+//!         }
+//!         _ => Verdict::Pass,
+//!     }
+//! }).expect("Failed to start grab");
+//! ```
+
+use crate::error::{Error, Result};
+use crate::event::Event;
+use crate::hook::GrabHandler;
+use crate::platform;
+use crate::transform::Injector;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Identifies a group of held events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HoldToken(u64);
+
+impl HoldToken {
+    /// Create a token distinct from every other token in this process.
+    pub fn new() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for HoldToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What a [`HoldHandler`] decides to do with an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Let the event through.
+    Pass,
+    /// Suppress the event.
+    Consume,
+    /// Suppress the event for now, buffering it under the token.
+    Hold(HoldToken),
+}
+
+/// How to release held events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldRelease {
+    /// Re-inject the events in the order they were held.
+    Replay,
+    /// Discard the events.
+    Drop,
+}
+
+/// Trait for grab handlers that can defer their decision.
+pub trait HoldHandler: Send + Sync {
+    /// Called when an input event occurs.
+    fn handle_event(&self, event: &Event) -> Verdict;
+}
+
+/// Implement HoldHandler for closures.
+impl<F> HoldHandler for F
+where
+    F: Fn(&Event) -> Verdict + Send + Sync,
+{
+    fn handle_event(&self, event: &Event) -> Verdict {
+        self(event)
+    }
+}
+
+/// Injector for replays: tagged so the grab hook doesn't handle them again.
+struct SyntheticInjector;
+
+impl Injector for SyntheticInjector {
+    fn inject(&self, event: &Event) -> Result<()> {
+        platform::inject_synthetic(event)
+    }
+}
+
+/// Shared buffer of held events.
+///
+/// Cheap to clone; clones share the buffer, so one can be moved into the
+/// handler or a timer thread while the [`Hook`](crate::Hook) keeps another.
+#[derive(Clone)]
+pub struct HeldEvents {
+    events: Arc<Mutex<Vec<(HoldToken, Event)>>>,
+    /// `None` where grab mode can't suppress events, so there is nothing to replay.
+    injector: Option<Arc<dyn Injector>>,
+    /// Set while the grab runs as a listener because of the grab policy, so
+    /// held events were delivered already.
+    listen_only: Arc<AtomicBool>,
+}
+
+impl HeldEvents {
+    /// Create an empty buffer replaying through the platform's injection path.
+    pub(crate) fn new() -> Self {
+        let injector: Option<Arc<dyn Injector>> = if platform::GRAB_SUPPRESSES {
+            Some(Arc::new(SyntheticInjector))
+        } else {
+            None
+        };
+        Self {
+            events: Arc::new(Mutex::new(Vec::new())),
+            injector,
+            listen_only: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Whether the grab holding events runs as a listener, see
+    /// [`policy`](crate::policy).
+    pub(crate) fn set_listen_only(&self, listen_only: bool) {
+        self.listen_only.store(listen_only, Ordering::SeqCst);
+    }
+
+    /// Replay through `injector` instead of the platform.
+    #[cfg(test)]
+    pub(crate) fn with_injector(injector: Arc<dyn Injector>) -> Self {
+        Self {
+            injector: Some(injector),
+            ..Self::new()
+        }
+    }
+
+    fn push(&self, token: HoldToken, event: Event) {
+        if let Ok(mut events) = self.events.lock() {
+            events.push((token, event));
+        }
+    }
+
+    /// Release the events held under `token`, returning how many there were.
+    ///
+    /// Released events leave the buffer even if replaying one of them fails;
+    /// the rest are not replayed. Releasing an unknown (or already released)
+    /// token is a no-op.
+    pub fn release(&self, token: HoldToken, how: HoldRelease) -> Result<usize> {
+        let released: Vec<Event> = {
+            let mut events = self
+                .events
+                .lock()
+                .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+            let (released, kept) = std::mem::take(&mut *events)
+                .into_iter()
+                .partition(|(held, _)| *held == token);
+            *events = kept;
+            released.into_iter().map(|(_, event)| event).collect()
+        };
+
+        if how == HoldRelease::Replay
+            && !self.listen_only.load(Ordering::SeqCst)
+            && let Some(injector) = &self.injector
+        {
+            for event in &released {
+                injector.inject(event)?;
+            }
+        }
+        Ok(released.len())
+    }
+
+    /// Number of events currently held.
+    pub fn len(&self) -> usize {
+        self.events.lock().map(|events| events.len()).unwrap_or(0)
+    }
+
+    /// Whether no events are held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Discard everything, e.g. when the hook stops.
+    pub(crate) fn clear(&self) {
+        if let Ok(mut events) = self.events.lock() {
+            events.clear();
+        }
+    }
+}
+
+/// Runs a [`HoldHandler`] as a [`GrabHandler`], buffering held events.
+pub(crate) struct HoldAdapter<H> {
+    pub(crate) handler: H,
+    pub(crate) held: HeldEvents,
+}
+
+impl<H: HoldHandler> GrabHandler for HoldAdapter<H> {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        match self.handler.handle_event(event) {
+            Verdict::Pass => Some(event.clone()),
+            Verdict::Consume => None,
+            Verdict::Hold(token) => {
+                self.held.push(token, event.clone());
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycode::Key;
+    use crate::transform::tests::RecordingInjector;
+
+    fn key_of(event: &Event) -> Key {
+        event.keyboard.as_ref().unwrap().key
+    }
+
+    #[test]
+    fn test_hold_then_replay_in_order() {
+        let injector = Arc::new(RecordingInjector::default());
+        let held = HeldEvents::with_injector(injector.clone());
+        let first = HoldToken::new();
+        let second = HoldToken::new();
+        assert_ne!(first, second);
+
+        let adapter = HoldAdapter {
+            handler: move |event: &Event| match event.keyboard.as_ref().map(|kb| kb.key) {
+                Some(Key::KeyA | Key::KeyB) => Verdict::Hold(first),
+                Some(Key::KeyC) => Verdict::Hold(second),
+                Some(Key::KeyD) => Verdict::Consume,
+                _ => Verdict::Pass,
+            },
+            held: held.clone(),
+        };
+
+        assert!(
+            adapter
+                .handle_event(&Event::key_pressed(Key::KeyA, 0))
+                .is_none()
+        );
+        assert!(
+            adapter
+                .handle_event(&Event::key_pressed(Key::KeyC, 0))
+                .is_none()
+        );
+        assert!(
+            adapter
+                .handle_event(&Event::key_pressed(Key::KeyB, 0))
+                .is_none()
+        );
+        assert!(
+            adapter
+                .handle_event(&Event::key_pressed(Key::KeyD, 0))
+                .is_none()
+        );
+        assert!(
+            adapter
+                .handle_event(&Event::key_pressed(Key::KeyE, 0))
+                .is_some()
+        );
+        assert_eq!(held.len(), 3);
+
+        assert_eq!(held.release(first, HoldRelease::Replay).unwrap(), 2);
+        let replayed: Vec<Key> = injector.take().iter().map(key_of).collect();
+        assert_eq!(replayed, vec![Key::KeyA, Key::KeyB]);
+        assert_eq!(held.len(), 1);
+
+        assert_eq!(held.release(second, HoldRelease::Drop).unwrap(), 1);
+        assert!(injector.take().is_empty());
+        assert!(held.is_empty());
+
+        // Released tokens are spent
+        assert_eq!(held.release(first, HoldRelease::Replay).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_listen_only_does_not_replay() {
+        let injector = Arc::new(RecordingInjector::default());
+        let held = HeldEvents::with_injector(injector.clone());
+        held.set_listen_only(true);
+
+        let token = HoldToken::new();
+        held.push(token, Event::key_pressed(Key::KeyA, 0));
+        assert_eq!(held.release(token, HoldRelease::Replay).unwrap(), 1);
+        assert!(injector.take().is_empty());
+    }
+}
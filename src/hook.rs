@@ -1,11 +1,31 @@
 //! Main Hook struct and EventHandler trait.
 
+use crate::calibration::{CalibrationConfig, InputTransform};
+use crate::chord::MouseChord;
+use crate::display::{DisplayRegion, DisplayTracker};
+use crate::drag::PointerTracker;
 use crate::error::{Error, Result};
-use crate::event::Event;
+use crate::event::{Button, Event, EventType, ScrollDirection};
+use crate::fanout::{self, GrabClaim};
+use crate::filter::EventFilter;
+use crate::hold::{HeldEvents, HoldAdapter, HoldHandler, HoldRelease, HoldToken};
+use crate::ime::ImeTracker;
+use crate::intercept::{GrabCapabilities, GrabOptions, Intercept};
+use crate::keycode::{HotkeyMatchMode, Key};
+use crate::latency::LatencyMonitor;
+use crate::ordering::Ordered;
 use crate::platform;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock};
+use crate::policy;
+use crate::shutdown::{Registration, ShutdownToken};
+use crate::state::Modifiers;
+use crate::subscribe::{Dispatcher, MotionFilter, Subscribers, Subscription};
+use crate::suspend::SuspendGate;
+use crate::tap::DoubleTapDetector;
+use crate::timeout::{TimedGrab, TimedListen};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
 
 /// Trait for handling input events (listen-only mode).
 ///
@@ -72,10 +92,399 @@ where
     }
 }
 
+/// Hook lifecycle timestamps, filled in as the platform layer dispatches
+/// `HookEnabled` / `HookDisabled`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct LifecycleTimes {
+    pub(crate) started_at: Option<SystemTime>,
+    pub(crate) stopped_at: Option<SystemTime>,
+    /// Whether `HookEnabled` has been dispatched for the current run.
+    pub(crate) enabled: bool,
+}
+
+impl LifecycleTimes {
+    /// Time since the hook was enabled, or the enabled span once stopped.
+    pub(crate) fn uptime(&self) -> Option<Duration> {
+        let started = self.started_at?;
+        let end = self.stopped_at.unwrap_or_else(SystemTime::now);
+        Some(end.duration_since(started).unwrap_or(Duration::ZERO))
+    }
+}
+
+/// Handler wrapper that records lifecycle timestamps before forwarding, and
+/// holds events back while hooks are suspended (see [`crate::suspend`]) or
+/// paused (see [`Hook::pause`]).
+///
+/// Grabs also correct stuck buttons (see [`crate::watchdog`]) and feed the
+/// [`input_snapshot`](crate::input_snapshot) here; listeners get that done
+/// once for all of them by the shared hook, see [`crate::fanout`].
+pub(crate) struct LifecycleTracker<H> {
+    pub(crate) times: Arc<Mutex<LifecycleTimes>>,
+    pub(crate) paused: Arc<AtomicBool>,
+    pub(crate) suspend: SuspendGate,
+    pub(crate) handler: H,
+}
+
+impl<H> LifecycleTracker<H> {
+    fn observe(&self, event: &Event) {
+        let Ok(mut times) = self.times.lock() else {
+            return;
+        };
+        match event.event_type {
+            EventType::HookEnabled => {
+                times.started_at = Some(event.time);
+                times.stopped_at = None;
+                times.enabled = true;
+            }
+            EventType::HookDisabled => {
+                times.stopped_at = Some(event.time);
+                times.enabled = false;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<H> LifecycleTracker<H> {
+    /// A notice to dispatch before `event`, and whether to dispatch `event`
+    /// itself. Events about the hook itself are dispatched while paused.
+    fn check(&self, event: &Event) -> (Option<Event>, bool) {
+        let (notice, deliver) = self.suspend.check(event);
+        let paused =
+            self.paused.load(Ordering::SeqCst) && EventMask::of(event.event_type).is_some();
+        (notice, deliver && !paused)
+    }
+}
+
+impl<H: EventHandler> EventHandler for LifecycleTracker<H> {
+    fn handle_event(&self, event: &Event) {
+        self.observe(event);
+        let (notice, deliver) = self.check(event);
+        if let Some(notice) = notice {
+            self.handler.handle_event(&notice);
+        }
+        if deliver {
+            self.handler.handle_event(event);
+        }
+    }
+}
+
+impl<H: GrabHandler> LifecycleTracker<H> {
+    fn grab(&self, event: &Event) -> Option<Event> {
+        crate::snapshot::observe(event);
+        self.observe(event);
+        let (notice, deliver) = self.check(event);
+        if let Some(notice) = notice {
+            // Informational only, like display changes
+            let _ = self.handler.handle_event(&notice);
+        }
+        if deliver {
+            self.handler.handle_event(event)
+        } else {
+            Some(event.clone())
+        }
+    }
+}
+
+impl<H: GrabHandler> GrabHandler for LifecycleTracker<H> {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        match crate::watchdog::check(event) {
+            Some((releases, corrected)) => {
+                // The releases never happened as OS events; only the move
+                // can be passed on or consumed
+                for release in &releases {
+                    let _ = self.grab(release);
+                }
+                self.grab(&corrected).map(|_| event.clone())
+            }
+            None => self.grab(event),
+        }
+    }
+}
+
+/// Grab handler wrapper that passes every event through while grabbing is
+/// disabled, see [`Hook::set_grab_enabled`], or the hook is paused, see
+/// [`Hook::pause`].
+pub(crate) struct GrabSwitch<H> {
+    pub(crate) enabled: Arc<AtomicBool>,
+    pub(crate) paused: Arc<AtomicBool>,
+    pub(crate) handler: H,
+}
+
+impl<H: GrabHandler> GrabHandler for GrabSwitch<H> {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        // The handler sees every event either way
+        let result = self.handler.handle_event(event);
+        if self.enabled.load(Ordering::SeqCst) && !self.paused.load(Ordering::SeqCst) {
+            result
+        } else {
+            result.or_else(|| Some(event.clone()))
+        }
+    }
+}
+
+/// A handler shared between runs of a hook, see [`Hook::restart`].
+pub(crate) struct Shared<H>(pub(crate) Arc<H>);
+
+impl<H: EventHandler> EventHandler for Shared<H> {
+    fn handle_event(&self, event: &Event) {
+        self.0.handle_event(event);
+    }
+}
+
+impl<H: GrabHandler> GrabHandler for Shared<H> {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        self.0.handle_event(event)
+    }
+}
+
+/// Starts a hook with the handler it was last started with.
+type Relaunch = Arc<dyn Fn(&Hook) -> Result<()> + Send + Sync>;
+
+/// How long `Hook::stop()` waits for a starting hook to become ready.
+const READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Block until a running hook has dispatched `HookEnabled`.
+///
+/// Returns early if the hook stops (e.g. failed to start), and errors if it
+/// neither starts nor fails within [`READY_TIMEOUT`].
+pub(crate) fn wait_until_ready(running: &AtomicBool, times: &Mutex<LifecycleTimes>) -> Result<()> {
+    let deadline = std::time::Instant::now() + READY_TIMEOUT;
+    while running.load(Ordering::SeqCst) {
+        if times.lock().map(|times| times.enabled).unwrap_or(true) {
+            return Ok(());
+        }
+        if std::time::Instant::now() >= deadline {
+            return Err(Error::HookStopFailed(
+                "hook did not become ready; stop() called before it started".into(),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    Ok(())
+}
+
+/// Ask a hook to stop from any thread, without joining its thread.
+///
+/// Waits for a starting hook to become ready, like [`Hook::stop`].
+pub(crate) fn request_stop(running: &AtomicBool, times: &Mutex<LifecycleTimes>) -> Result<()> {
+    wait_until_ready(running, times)?;
+
+    if !running.swap(false, Ordering::SeqCst) {
+        return Err(Error::NotRunning);
+    }
+
+    // Listeners share the OS hook, which stays up for the others
+    if fanout::leave(running) {
+        return Ok(());
+    }
+    platform::stop_hook()
+}
+
+/// Pause or resume a running hook, see [`Hook::pause`].
+pub(crate) fn set_paused(running: &AtomicBool, paused: &AtomicBool, pause: bool) -> Result<()> {
+    if !running.load(Ordering::SeqCst) {
+        return Err(Error::NotRunning);
+    }
+    paused.store(pause, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Handler wrapper that stops its hook once ready if the token was
+/// cancelled while it was starting.
+struct StopWhenCancelled<H> {
+    token: ShutdownToken,
+    running: Arc<AtomicBool>,
+    times: Arc<Mutex<LifecycleTimes>>,
+    handler: H,
+}
+
+impl<H> StopWhenCancelled<H> {
+    fn check(&self, event: &Event) {
+        if event.event_type == EventType::HookEnabled && self.token.is_cancelled() {
+            let _ = request_stop(&self.running, &self.times);
+        }
+    }
+}
+
+impl<H: EventHandler> EventHandler for StopWhenCancelled<H> {
+    fn handle_event(&self, event: &Event) {
+        self.handler.handle_event(event);
+        self.check(event);
+    }
+}
+
+impl<H: GrabHandler> GrabHandler for StopWhenCancelled<H> {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        let result = self.handler.handle_event(event);
+        self.check(event);
+        result
+    }
+}
+
+/// Categories of events a hook delivers, see [`Hook::events`].
+///
+/// Combine categories with `|`. Events about the hook itself
+/// (`HookEnabled`, `HookDisabled`, `GrabSuspended`, `GrabDegraded`,
+/// `GlobalSuspendChanged`) belong to no category and are always delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventMask(u32);
+
+impl EventMask {
+    /// Key presses, releases and typed characters, and input method
+    /// composition changes.
+    pub const KEYBOARD: Self = Self(1 << 0);
+    /// Mouse button presses, releases and clicks.
+    pub const MOUSE_BUTTONS: Self = Self(1 << 1);
+    /// Mouse moves and drags, including `DragStarted`, and the
+    /// `DisplayChanged` they cause.
+    pub const MOUSE_MOTION: Self = Self(1 << 2);
+    /// Mouse wheel scrolling.
+    pub const MOUSE_WHEEL: Self = Self(1 << 3);
+    /// Touchscreen contacts.
+    pub const TOUCH: Self = Self(1 << 4);
+    /// Every mouse event.
+    pub const MOUSE: Self =
+        Self(Self::MOUSE_BUTTONS.0 | Self::MOUSE_MOTION.0 | Self::MOUSE_WHEEL.0);
+    /// Every event.
+    pub const ALL: Self = Self(Self::KEYBOARD.0 | Self::MOUSE.0 | Self::TOUCH.0);
+
+    /// The category of `event_type`, or `None` for events about the hook
+    /// itself.
+    pub fn of(event_type: EventType) -> Option<Self> {
+        match event_type {
+            EventType::KeyPressed
+            | EventType::KeyReleased
+            | EventType::KeyTyped
+            | EventType::ImeCompositionStarted
+            | EventType::ImeCompositionEnded => Some(Self::KEYBOARD),
+            EventType::MousePressed | EventType::MouseReleased | EventType::MouseClicked => {
+                Some(Self::MOUSE_BUTTONS)
+            }
+            EventType::MouseMoved
+            | EventType::MouseDragged
+            | EventType::DragStarted
+            | EventType::DisplayChanged => Some(Self::MOUSE_MOTION),
+            EventType::MouseWheel => Some(Self::MOUSE_WHEEL),
+            EventType::TouchBegan | EventType::TouchMoved | EventType::TouchEnded => {
+                Some(Self::TOUCH)
+            }
+            EventType::HookEnabled
+            | EventType::HookDisabled
+            | EventType::GrabSuspended
+            | EventType::GrabDegraded
+            | EventType::GlobalSuspendChanged => None,
+        }
+    }
+
+    /// Whether events of `event_type` are delivered with this mask.
+    pub fn includes(self, event_type: EventType) -> bool {
+        Self::of(event_type).is_none_or(|category| self.intersects(category))
+    }
+
+    /// Whether every category in `other` is in this mask.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any category in `other` is in this mask.
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// The categories as bits.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// The categories in `bits`, ignoring unknown bits.
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Self(bits & Self::ALL.0)
+    }
+}
+
+impl Default for EventMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl std::ops::BitOr for EventMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for EventMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl std::ops::BitAnd for EventMask {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
 /// Input hook that captures keyboard and mouse events.
+///
+/// # Threads
+///
+/// `run()` / `grab()` block the calling thread, which can be any thread;
+/// `stop()` may then be called from any other thread. `run_async()` /
+/// `grab_async()` spawn a dedicated hook thread. Calling `stop()` while the
+/// hook is still starting waits until it is enabled, so the stop request
+/// can't be lost.
+///
+/// # Several hooks
+///
+/// Listening hooks, including [channel](crate::channel) hooks, share one OS
+/// hook: the first to start installs it on a thread of its own, the others
+/// join it, and the last to stop removes it, so each running listener
+/// costs a queue and a thread rather than an event tap. Handlers run on
+/// their hook's thread, so a slow one delays only its own events, and
+/// stopping one hook leaves the others running. Each hook still sees its
+/// own `HookEnabled` and `HookDisabled`. Grabs install a hook each.
+///
+/// Backends run one OS hook at a time, and evdev one grab at a time next to
+/// its listeners. A hook started while that's taken fails with
+/// [`Error::AlreadyRunning`] instead of taking over the running hook's
+/// events, and stopping it leaves the running hook be.
 pub struct Hook {
     running: Arc<AtomicBool>,
     thread_handle: RwLock<Option<JoinHandle<()>>>,
+    relaunch: Mutex<Option<Relaunch>>,
+    start_error: Arc<Mutex<Option<Error>>>,
+    times: Arc<Mutex<LifecycleTimes>>,
+    paused: Arc<AtomicBool>,
+    held: HeldEvents,
+    pub(crate) subscribers: Subscribers,
+    ignore_injected: bool,
+    track_displays: bool,
+    display_region: Option<u32>,
+    restrict_keyboard: bool,
+    motion_min_distance: f64,
+    drag_threshold: Option<f64>,
+    event_mask: EventMask,
+    match_mode: HotkeyMatchMode,
+    hotkeys_while_composing: bool,
+    touch_as_mouse: bool,
+    calibration: CalibrationConfig,
+    grab_enabled: Arc<AtomicBool>,
+    grab_mode_changes: Arc<AtomicU64>,
+    grab_denied: AtomicBool,
+    ignore_global_suspend: bool,
+    grab_options: GrabOptions,
+    handler_timeout: Option<Duration>,
+    handler_timeouts: Arc<AtomicU64>,
+    dropped_events: Arc<AtomicU64>,
+    latency: Arc<LatencyMonitor>,
+    strict_ordering: bool,
 }
 
 impl Default for Hook {
@@ -90,6 +499,383 @@ impl Hook {
         Self {
             running: Arc::new(AtomicBool::new(false)),
             thread_handle: RwLock::new(None),
+            relaunch: Mutex::new(None),
+            start_error: Arc::default(),
+            times: Arc::new(Mutex::new(LifecycleTimes::default())),
+            paused: Arc::new(AtomicBool::new(false)),
+            held: HeldEvents::new(),
+            subscribers: Subscribers::default(),
+            ignore_injected: false,
+            track_displays: false,
+            display_region: None,
+            restrict_keyboard: false,
+            motion_min_distance: 0.0,
+            drag_threshold: None,
+            event_mask: EventMask::ALL,
+            match_mode: HotkeyMatchMode::default(),
+            hotkeys_while_composing: true,
+            touch_as_mouse: true,
+            calibration: CalibrationConfig::new(),
+            grab_enabled: Arc::new(AtomicBool::new(true)),
+            grab_mode_changes: Arc::new(AtomicU64::new(0)),
+            grab_denied: AtomicBool::new(false),
+            ignore_global_suspend: false,
+            grab_options: GrabOptions::default(),
+            handler_timeout: None,
+            handler_timeouts: Arc::new(AtomicU64::new(0)),
+            dropped_events: Arc::new(AtomicU64::new(0)),
+            latency: Arc::default(),
+            strict_ordering: false,
+        }
+    }
+
+    /// Skip events generated by software (see [`Event::injected`]).
+    ///
+    /// Ignored events are neither dispatched to the handler and
+    /// subscriptions nor consumed in grab mode. Useful to tell physical input
+    /// from macros, and to avoid feedback loops with other automation tools.
+    /// Events of unknown origin (`injected == None`) are always dispatched.
+    pub fn ignore_injected(mut self, ignore: bool) -> Self {
+        self.ignore_injected = ignore;
+        self
+    }
+
+    /// Keep dispatching while hooks are suspended with
+    /// [`suspend_all`](crate::suspend_all), e.g. for an abort chord.
+    ///
+    /// The hook still reports
+    /// [`EventType::GlobalSuspendChanged`] when the suspension starts or ends.
+    pub fn ignore_global_suspend(mut self, ignore: bool) -> Self {
+        self.ignore_global_suspend = ignore;
+        self
+    }
+
+    /// Report [`EventType::DisplayChanged`] when the cursor moves onto another
+    /// display.
+    ///
+    /// Off by default, so plain listeners don't pay for looking up displays
+    /// on every mouse move. The change is reported just before the move that
+    /// caused it; in grab mode, whatever the handler returns for it is
+    /// ignored.
+    pub fn track_displays(mut self, track: bool) -> Self {
+        self.track_displays = track;
+        self
+    }
+
+    /// Only dispatch mouse and touch events on the display with this
+    /// [`DisplayInfo::id`](crate::DisplayInfo::id), e.g. for a kiosk app
+    /// on a secondary display.
+    ///
+    /// Events elsewhere reach neither the handler nor subscriptions, and are
+    /// passed on untouched in grab mode. The display layout is cached and
+    /// re-read every couple of seconds, or sooner when the cursor leaves
+    /// every known display, so the bounds follow layout changes. While the
+    /// display is disconnected, no mouse events are dispatched. Keyboard
+    /// events are dispatched wherever the cursor is, unless
+    /// [`restrict_keyboard`](Self::restrict_keyboard) is set.
+    pub fn restrict_to_display(mut self, display_id: u32) -> Self {
+        self.display_region = Some(display_id);
+        self
+    }
+
+    /// With [`restrict_to_display`](Self::restrict_to_display), only
+    /// dispatch keyboard events while the cursor is on the display.
+    ///
+    /// Keyboards aren't spatial, so this follows the last mouse event: keys
+    /// are dropped until the cursor has been seen on the display.
+    pub fn restrict_keyboard(mut self, restrict: bool) -> Self {
+        self.restrict_keyboard = restrict;
+        self
+    }
+
+    /// Only report mouse moves and drags once the cursor is at least
+    /// `distance` screen points from the last reported position.
+    ///
+    /// Paths keep their shape with far fewer events. The latest unreported
+    /// position is still delivered before the next button or wheel event, so
+    /// clicks are never seen at a stale position. Unreported moves are
+    /// passed on in grab mode. `0.0` (the default) reports every move.
+    pub fn motion_min_distance(mut self, distance: f64) -> Self {
+        self.motion_min_distance = distance;
+        self
+    }
+
+    /// Only report [`EventType::MouseDragged`] once the cursor is more than
+    /// `distance` from where the button was pressed, in the units of event
+    /// positions.
+    ///
+    /// Moves within the threshold are reported as `MouseMoved`, so a click
+    /// with some jitter isn't a drag. Crossing it reports
+    /// [`EventType::DragStarted`] at the press position, then the drag. By
+    /// default the threshold follows the system: Windows' drag setting
+    /// (`SM_CXDRAG`/`SM_CYDRAG`), elsewhere 4 points in the pixels of the
+    /// display the button was pressed on. `0.0` reports every move with a
+    /// button held as a drag.
+    ///
+    /// A release within the threshold of its press is a click, reported as
+    /// [`EventType::MouseClicked`] after the release, with the number of
+    /// clicks in a row. See [`tracking`](crate::tracking) for the full
+    /// lifecycle.
+    pub fn drag_threshold(mut self, distance: f64) -> Self {
+        self.drag_threshold = Some(distance);
+        self
+    }
+
+    /// Only deliver events in `mask`, to the handler and to subscriptions.
+    ///
+    /// Where the platform allows, the hook doesn't register for the other
+    /// events at all, so a keyboard-only tool doesn't pay for mouse motion:
+    /// the event tap mask on macOS, the keyboard and mouse hooks on Windows,
+    /// the devices opened on evdev, and the XRecord range on X11 (which is
+    /// one span of event codes, so keyboard and motion without buttons
+    /// still records buttons). Anything still reported is dropped before
+    /// the handler; in grab mode it passes through untouched. Defaults to
+    /// [`EventMask::ALL`].
+    ///
+    /// ```no_run
+    /// use monio::hook::EventMask;
+    /// use monio::{Event, Hook};
+    ///
+    /// let hook = Hook::new().events(EventMask::KEYBOARD | EventMask::MOUSE_BUTTONS);
+    /// hook.run(|event: &Event| println!("{:?}", event.event_type)).unwrap();
+    /// ```
+    pub fn events(mut self, mask: EventMask) -> Self {
+        self.event_mask = mask;
+        self
+    }
+
+    /// How [`on_key_pressed`](Self::on_key_pressed) and
+    /// [`on_any_key`](Self::on_any_key) identify keys on non-US layouts.
+    ///
+    /// Defaults to [`HotkeyMatchMode::Logical`]: keys are matched as
+    /// labelled on the active layout.
+    pub fn hotkey_match_mode(mut self, mode: HotkeyMatchMode) -> Self {
+        self.match_mode = mode;
+        self
+    }
+
+    /// Whether [`on_key_pressed`](Self::on_key_pressed) and
+    /// [`on_any_key`](Self::on_any_key) fire for keys pressed while an input
+    /// method is composing, which go to the composer rather than the app.
+    ///
+    /// On by default. Turned off, keys marked with
+    /// [`ime_active`](crate::event::KeyboardData::ime_active) are skipped;
+    /// keys where the state is unknown still fire. See [`ime`](crate::ime)
+    /// for where composition is detected.
+    pub fn hotkeys_while_composing(mut self, enabled: bool) -> Self {
+        self.hotkeys_while_composing = enabled;
+        self
+    }
+
+    /// Whether single-touch touchscreens move the mouse (the default), or are
+    /// reported as [`EventType::TouchBegan`]/`TouchMoved`/`TouchEnded`.
+    ///
+    /// Multitouch contacts are always reported as touches; with this off,
+    /// the mouse emulation multitouch screens also send is dropped. Only the
+    /// evdev backend reports touches.
+    ///
+    /// Devices are read the same way for every hook of the process, so a
+    /// hook started while others with another setting run fails with
+    /// [`Error::ConflictingConfig`].
+    ///
+    /// [`EventType::TouchBegan`]: crate::EventType::TouchBegan
+    pub fn touch_as_mouse(mut self, enabled: bool) -> Self {
+        self.touch_as_mouse = enabled;
+        self
+    }
+
+    /// Calibrate an absolute device (touchscreen, tablet), picked by the
+    /// name it reports or its node (e.g. `/dev/input/event5`).
+    ///
+    /// The transform is applied to the device's positions before events are
+    /// emitted; see [`calibration`](crate::calibration). Setting a device
+    /// again replaces its transform. Only the evdev backend applies
+    /// transforms.
+    ///
+    /// Hooks running at once must calibrate the same way, see
+    /// [`touch_as_mouse`](Self::touch_as_mouse).
+    pub fn input_transform(mut self, device: impl Into<String>, transform: InputTransform) -> Self {
+        self.calibration.set(device, transform);
+        self
+    }
+
+    /// Calibrate absolute devices from a stored configuration, replacing any
+    /// transforms set before.
+    pub fn calibration(mut self, config: CalibrationConfig) -> Self {
+        self.calibration = config;
+        self
+    }
+
+    /// When grab hooks intercept events, e.g. only while a key is held
+    /// (see [`GrabOptions`]). By default they always do.
+    ///
+    /// Toggle keys are matched according to
+    /// [`hotkey_match_mode`](Self::hotkey_match_mode).
+    pub fn grab_options(mut self, options: GrabOptions) -> Self {
+        self.grab_options = options;
+        self
+    }
+
+    /// Run the handler on a worker thread and stop waiting for it after
+    /// `timeout`, so a slow handler can't stall input.
+    ///
+    /// Listen hooks abandon events the handler can't start within `timeout`;
+    /// grab hooks let events through when the handler takes longer. Either
+    /// way, [`handler_timeouts`](Self::handler_timeouts) counts them. Events
+    /// reach the handler in order, a little later than without a timeout;
+    /// subscriptions still run on the hook thread. See
+    /// [`timeout`](crate::timeout) for the details.
+    pub fn handler_timeout(mut self, timeout: Duration) -> Self {
+        self.handler_timeout = Some(timeout);
+        self
+    }
+
+    /// How many events were abandoned because the handler was too slow, see
+    /// [`handler_timeout`](Self::handler_timeout).
+    pub fn handler_timeouts(&self) -> u64 {
+        self.handler_timeouts.load(Ordering::Relaxed)
+    }
+
+    /// How many events were dropped because a listening handler fell too
+    /// far behind, see [`run`](Self::run).
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped_events.load(Ordering::Relaxed)
+    }
+
+    /// Log a warning when an event reaches the handler more than
+    /// `threshold` after it happened, at most once a second.
+    ///
+    /// Only works where [`Event::queue_latency`] is known (Windows). A hook
+    /// thread falling behind makes input lag, and on macOS gets the event
+    /// tap disabled.
+    pub fn latency_warning(self, threshold: Duration) -> Self {
+        self.latency.set_warning(Some(threshold));
+        self
+    }
+
+    /// 99th percentile of the [`queue_latency`](Event::queue_latency) of
+    /// recent events, or `None` where it isn't known.
+    pub fn queue_latency_p99(&self) -> Option<Duration> {
+        self.latency.p99()
+    }
+
+    /// Deliver events to listen hooks in the order they happened rather
+    /// than the order they arrived, with modifier masks to match.
+    ///
+    /// On Windows, X11 and evdev, a key can arrive just before the modifier
+    /// pressed with it and carry a mask without that modifier. With strict
+    /// ordering, events are held back for a couple of milliseconds and
+    /// sorted by timestamp, and the masks of events that were overtaken are
+    /// corrected. Off by default, since every event is delivered that much
+    /// later. Grab hooks can't hold events back and ignore this. See
+    /// [`ordering`](crate::ordering) for the details.
+    pub fn strict_ordering(mut self, strict: bool) -> Self {
+        self.strict_ordering = strict;
+        self
+    }
+
+    /// Wrap a listen handler in the [`handler_timeout`](Self::handler_timeout)
+    /// and track it, in [`strict_ordering`](Self::strict_ordering) if set.
+    fn track_listen<H: EventHandler + 'static>(
+        &self,
+        handler: H,
+    ) -> Ordered<LifecycleTracker<Dispatcher<TimedListen<H>>>> {
+        let handler = self.track(TimedListen::new(
+            handler,
+            self.handler_timeout,
+            self.handler_timeouts.clone(),
+        ));
+        Ordered::new(handler, self.strict_ordering)
+    }
+
+    /// Wrap a grab handler so it follows the
+    /// [`handler_timeout`](Self::handler_timeout),
+    /// [`grab_options`](Self::grab_options) and
+    /// [`set_grab_enabled`](Self::set_grab_enabled), and track it. Only
+    /// once [`begin_grab`](Self::begin_grab) claimed the platform, which
+    /// keeps these settings for the one grab running.
+    fn grab_switch<H: GrabHandler + 'static>(
+        &self,
+        handler: H,
+    ) -> GrabSwitch<Intercept<LifecycleTracker<Dispatcher<TimedGrab<H>>>>> {
+        platform::set_grab_enabled(self.grab_enabled.load(Ordering::SeqCst));
+        platform::set_event_mask(self.event_mask);
+        platform::set_scroll_only(self.grab_options.is_scroll_only());
+        let handler = TimedGrab::new(handler, self.handler_timeout, self.handler_timeouts.clone());
+        GrabSwitch {
+            enabled: self.grab_enabled.clone(),
+            paused: self.paused.clone(),
+            handler: Intercept::new(&self.grab_options, self.match_mode, self.track(handler)),
+        }
+    }
+
+    /// Mark the hook running for a grab, and return whether it must listen
+    /// instead, see [`is_grab_denied`](Self::is_grab_denied), with its claim
+    /// on the platform.
+    ///
+    /// Fails if the grab couldn't consume but
+    /// [`GrabOptions::require_consumption`] is set, or if it can't claim the
+    /// platform, see [`fanout::claim_grab`].
+    fn begin_grab(&self) -> Result<(bool, GrabClaim<'static>)> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err(Error::AlreadyRunning);
+        }
+        let denied = policy::grab_denied();
+        let capabilities = GrabCapabilities::new(denied);
+        if self.grab_options.requires_consumption() && !capabilities.can_consume {
+            self.running.store(false, Ordering::SeqCst);
+            return Err(Error::NotSupported(if denied {
+                "grabbing is disabled by policy".into()
+            } else {
+                format!("the {} backend can't consume events", capabilities.backend)
+            }));
+        }
+        let claim = fanout::claim_grab(&self.config()).inspect_err(|_| {
+            self.running.store(false, Ordering::SeqCst);
+        })?;
+        self.grab_denied.store(denied, Ordering::SeqCst);
+        self.held.set_listen_only(denied);
+        Ok((denied, claim))
+    }
+
+    /// How this hook reads devices, which hooks running at once share.
+    fn config(&self) -> fanout::Config {
+        fanout::Config {
+            touch_as_mouse: self.touch_as_mouse,
+            transforms: self.calibration.devices.clone(),
+        }
+    }
+
+    fn track<H>(&self, handler: H) -> LifecycleTracker<Dispatcher<H>> {
+        if let Ok(mut times) = self.times.lock() {
+            times.enabled = false;
+        }
+        // Every run starts unpaused
+        self.paused.store(false, Ordering::SeqCst);
+        LifecycleTracker {
+            times: self.times.clone(),
+            paused: self.paused.clone(),
+            suspend: SuspendGate::new(self.ignore_global_suspend),
+            handler: Dispatcher {
+                subscribers: self.subscribers.clone(),
+                ignore_injected: self.ignore_injected,
+                events: self.event_mask,
+                displays: self
+                    .track_displays
+                    .then(|| Mutex::new(DisplayTracker::new())),
+                region: self
+                    .display_region
+                    .map(|id| Mutex::new(DisplayRegion::new(id, self.restrict_keyboard))),
+                ime: Some(Mutex::new(ImeTracker::new())),
+                pointer: Some(Mutex::new(match self.drag_threshold {
+                    None => PointerTracker::adaptive(),
+                    Some(distance) => PointerTracker::new(distance),
+                })),
+                motion: (self.motion_min_distance > 0.0)
+                    .then(|| Mutex::new(MotionFilter::new(self.motion_min_distance))),
+                latency: self.latency.clone(),
+                handler,
+            },
         }
     }
 
@@ -97,40 +883,75 @@ impl Hook {
     ///
     /// This will block the current thread until `stop()` is called
     /// from another thread. Events are passed through to other applications.
+    ///
+    /// Listening hooks of the process share one OS hook, which queues
+    /// events for each handler. A handler 4096 events behind has further
+    /// events dropped until it catches up, rather than holding up the OS
+    /// hook and the other handlers; [`dropped_events`](Self::dropped_events)
+    /// counts them.
+    ///
+    /// Fails with [`Error::ConflictingConfig`] if the hooks already running
+    /// read devices differently, see [`touch_as_mouse`](Self::touch_as_mouse)
+    /// and [`calibration`](Self::calibration).
     pub fn run<H: EventHandler + 'static>(&self, handler: H) -> Result<()> {
         if self.running.swap(true, Ordering::SeqCst) {
             return Err(Error::AlreadyRunning);
         }
 
-        // Reset state before starting
-        crate::state::reset_mask();
-
-        let result = platform::run_hook(&self.running, handler);
+        let result = fanout::listen(
+            &self.running,
+            self.event_mask,
+            self.config(),
+            self.dropped_events.clone(),
+            self.track_listen(handler),
+        );
 
         self.running.store(false, Ordering::SeqCst);
         result
     }
 
+    /// Start listening until `token` is cancelled (blocking, listen-only mode).
+    ///
+    /// Cancelling the token from any thread stops the hook as
+    /// [`stop()`](Self::stop) would; so does calling `stop()`. Returns right
+    /// away if the token is already cancelled.
+    pub fn run_with_token<H: EventHandler + 'static>(
+        &self,
+        handler: H,
+        token: &ShutdownToken,
+    ) -> Result<()> {
+        if token.is_cancelled() {
+            return Ok(());
+        }
+        let _registration = self.stop_on(token);
+        self.run(self.stop_when_cancelled(handler, token))
+    }
+
     /// Start listening in a background thread (non-blocking, listen-only mode).
     ///
     /// Returns immediately. Use `stop()` to terminate the hook.
     /// Events are passed through to other applications.
     pub fn run_async<H: EventHandler + 'static>(&self, handler: H) -> Result<()> {
-        if self.running.swap(true, Ordering::SeqCst) {
-            return Err(Error::AlreadyRunning);
-        }
-
-        // Reset state before starting
-        crate::state::reset_mask();
-
-        let running = self.running.clone();
-        let handle = std::thread::spawn(move || {
-            let _ = platform::run_hook(&running, handler);
-            running.store(false, Ordering::SeqCst);
-        });
+        let handler = Arc::new(handler);
+        self.launch(Arc::new(move |hook: &Hook| {
+            if hook.running.swap(true, Ordering::SeqCst) {
+                return Err(Error::AlreadyRunning);
+            }
 
-        *self.thread_handle.write().unwrap() = Some(handle);
-        Ok(())
+            let running = hook.running.clone();
+            let start_error = hook.start_error.clone();
+            let events = hook.event_mask;
+            let config = hook.config();
+            let dropped = hook.dropped_events.clone();
+            let handler = hook.track_listen(Shared(handler.clone()));
+            hook.spawn(move || {
+                if let Err(e) = fanout::listen(&running, events, config, dropped, handler) {
+                    *start_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(e);
+                }
+                running.store(false, Ordering::SeqCst);
+            });
+            Ok(())
+        }))
     }
 
     /// Start grabbing events (blocking, can consume events).
@@ -144,64 +965,477 @@ impl Hook {
     /// - **macOS**: Full support
     /// - **Windows**: Full support
     /// - **Linux/X11**: Falls back to listen mode (XRecord cannot grab)
+    ///
+    /// Also falls back to listen mode where grabbing is denied by
+    /// [`policy`](crate::policy), see [`is_grab_denied`](Self::is_grab_denied).
     pub fn grab<H: GrabHandler + 'static>(&self, handler: H) -> Result<()> {
-        if self.running.swap(true, Ordering::SeqCst) {
-            return Err(Error::AlreadyRunning);
-        }
-
-        // Reset state before starting
-        crate::state::reset_mask();
-
-        let result = platform::run_grab_hook(&self.running, handler);
+        let (denied, _claim) = self.begin_grab()?;
+        let result = policy::run_grab_hook(&self.running, self.grab_switch(handler), denied);
 
         self.running.store(false, Ordering::SeqCst);
         result
     }
 
+    /// Start grabbing events until `token` is cancelled (blocking).
+    ///
+    /// Like [`run_with_token()`](Self::run_with_token), for
+    /// [`grab()`](Self::grab).
+    pub fn grab_with_token<H: GrabHandler + 'static>(
+        &self,
+        handler: H,
+        token: &ShutdownToken,
+    ) -> Result<()> {
+        if token.is_cancelled() {
+            return Ok(());
+        }
+        let _registration = self.stop_on(token);
+        self.grab(self.stop_when_cancelled(handler, token))
+    }
+
     /// Start grabbing events in a background thread (non-blocking).
     ///
     /// Returns immediately. Use `stop()` to terminate the hook.
     /// The handler can return `None` to consume events.
     pub fn grab_async<H: GrabHandler + 'static>(&self, handler: H) -> Result<()> {
-        if self.running.swap(true, Ordering::SeqCst) {
-            return Err(Error::AlreadyRunning);
+        let handler = Arc::new(handler);
+        self.launch(Arc::new(move |hook: &Hook| {
+            let (denied, claim) = hook.begin_grab()?;
+            let running = hook.running.clone();
+            let start_error = hook.start_error.clone();
+            let held = hook.held.clone();
+            let handler = hook.grab_switch(Shared(handler.clone()));
+            hook.spawn(move || {
+                if let Err(e) = policy::run_grab_hook(&running, handler, denied) {
+                    *start_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(e);
+                }
+                // Events a holding handler still holds are dropped
+                held.clear();
+                drop(claim);
+                running.store(false, Ordering::SeqCst);
+            });
+            Ok(())
+        }))
+    }
+
+    /// Start grabbing events with a handler that can hold them (blocking).
+    ///
+    /// Like [`grab()`](Self::grab), but the handler returns a
+    /// [`Verdict`](crate::hold::Verdict) and may hold events to decide about
+    /// them later with [`release()`](Self::release). Events still held when
+    /// the hook stops are dropped. See [`crate::hold`] for ordering guarantees.
+    pub fn grab_with_holds<H: HoldHandler + 'static>(&self, handler: H) -> Result<()> {
+        let result = self.grab(HoldAdapter {
+            handler,
+            held: self.held.clone(),
+        });
+        self.held.clear();
+        result
+    }
+
+    /// Start grabbing events with a holding handler in a background thread.
+    ///
+    /// Returns immediately. Use `stop()` to terminate the hook.
+    pub fn grab_with_holds_async<H: HoldHandler + 'static>(&self, handler: H) -> Result<()> {
+        self.grab_async(HoldAdapter {
+            handler,
+            held: self.held.clone(),
+        })
+    }
+
+    /// Start the hook with `relaunch`, keeping it for
+    /// [`restart`](Self::restart) unless the hook was already running.
+    fn launch(&self, relaunch: Relaunch) -> Result<()> {
+        let result = relaunch(self);
+        if !matches!(result, Err(Error::AlreadyRunning)) {
+            *self.relaunch.lock().unwrap_or_else(|e| e.into_inner()) = Some(relaunch);
+        }
+        result
+    }
+
+    /// Run `body` as the hook thread.
+    fn spawn(&self, body: impl FnOnce() + Send + 'static) {
+        if let Ok(mut error) = self.start_error.lock() {
+            *error = None;
+        }
+        let handle = std::thread::spawn(body);
+        *self.thread_handle.write().unwrap() = Some(handle);
+    }
+
+    /// Stop the hook and start it again with the handler it was last started
+    /// with, keeping subscriptions and channels attached.
+    ///
+    /// Meant for retrying once the permissions input hooks need are granted,
+    /// see [`permissions::on_granted`](crate::permissions::on_granted). Waits
+    /// until the hook is enabled, and its handler sees a new `HookEnabled`.
+    /// If starting fails, the error is returned and the handler is kept, so
+    /// `restart()` can be called again.
+    ///
+    /// Only hooks started with [`run_async`](Self::run_async),
+    /// [`grab_async`](Self::grab_async) or
+    /// [`grab_with_holds_async`](Self::grab_with_holds_async) can be
+    /// restarted, and they restart with the options the hook has now.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::{Event, Hook, permissions};
+    /// use std::sync::Arc;
+    ///
+    /// let hook = Arc::new(Hook::new());
+    /// hook.on_any_key(|key, _| println!("{key:?}")).detach();
+    /// hook.run_async(|_: &Event| {})?;
+    ///
+    /// // Start over once the user grants Accessibility
+    /// let retry = hook.clone();
+    /// let _watcher = permissions::on_granted(move || {
+    ///     if let Err(e) = retry.restart() {
+    ///         eprintln!("still can't listen: {e}");
+    ///     }
+    /// })?;
+    /// # Ok::<(), monio::Error>(())
+    /// ```
+    pub fn restart(&self) -> Result<()> {
+        let relaunch = self
+            .relaunch
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .ok_or_else(|| {
+                Error::HookStartFailed(
+                    "nothing to restart; start the hook with run_async or grab_async first".into(),
+                )
+            })?;
+        match self.stop() {
+            Ok(()) | Err(Error::NotRunning) => {}
+            Err(e) => return Err(e),
+        }
+
+        relaunch(self)?;
+        wait_until_ready(&self.running, &self.times)?;
+        if self.is_running() {
+            return Ok(());
+        }
+        // Failed to start: wait for the thread and report why
+        let handle = self.thread_handle.write().unwrap().take();
+        if let Some(handle) = handle {
+            let _ = handle.join();
+        }
+        Err(self
+            .start_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .unwrap_or_else(|| Error::HookStartFailed("hook stopped while starting".into())))
+    }
+
+    /// Turn consuming events on or off while a grab hook keeps running.
+    ///
+    /// While disabled, the grab handler still sees every event but all of
+    /// them reach other applications, as with a listen hook; re-enabling
+    /// restores the handler's verdicts. No events are missed across the
+    /// switch, unlike stopping the grab and starting a listener. On evdev the
+    /// devices are ungrabbed while disabled and grabbed again afterwards.
+    /// Can be called from any thread, including the handler, and before the
+    /// hook starts. Grabbing is enabled by default.
+    pub fn set_grab_enabled(&self, enabled: bool) {
+        if self.grab_enabled.swap(enabled, Ordering::SeqCst) != enabled {
+            self.grab_mode_changes.fetch_add(1, Ordering::Relaxed);
+            platform::set_grab_enabled(enabled);
         }
+    }
+
+    /// Whether grab hooks consume events, see
+    /// [`set_grab_enabled`](Self::set_grab_enabled).
+    pub fn is_grab_enabled(&self) -> bool {
+        self.grab_enabled.load(Ordering::SeqCst)
+    }
+
+    /// Whether the last grab started on this hook runs as a listener because
+    /// grabbing is denied by [`policy`](crate::policy).
+    ///
+    /// Its handler sees every event, but nothing is consumed.
+    pub fn is_grab_denied(&self) -> bool {
+        self.grab_denied.load(Ordering::SeqCst)
+    }
+
+    /// What the last grab started on this hook can do; before the first
+    /// grab, what the backend can do.
+    ///
+    /// A grab that can't consume also reports
+    /// [`EventType::GrabDegraded`] to its handler.
+    pub fn grab_capabilities(&self) -> GrabCapabilities {
+        GrabCapabilities::new(self.is_grab_denied())
+    }
 
-        // Reset state before starting
-        crate::state::reset_mask();
+    /// How many times grabbing was switched on or off.
+    pub fn grab_mode_changes(&self) -> u64 {
+        self.grab_mode_changes.load(Ordering::Relaxed)
+    }
+
+    /// Replay or drop the events held under `token`.
+    ///
+    /// Returns how many events were released. Can be called from the handler
+    /// itself or any other thread; use [`held_events()`](Self::held_events)
+    /// to get a handle that can be moved into the handler.
+    pub fn release(&self, token: HoldToken, how: HoldRelease) -> Result<usize> {
+        self.held.release(token, how)
+    }
+
+    /// Handle to the buffer of held events, shared with this hook.
+    pub fn held_events(&self) -> HeldEvents {
+        self.held.clone()
+    }
+
+    /// Call `callback` for every event this hook dispatches, in any mode.
+    ///
+    /// Subscriptions are offered each event before the handler passed to
+    /// `run()` / `grab()`, and can be added or dropped while the hook runs.
+    /// Dropping the returned [`Subscription`] unsubscribes.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::{Event, Hook, Key};
+    ///
+    /// let hook = Hook::new();
+    /// let _quit = hook.on_key_pressed(Key::Escape, || println!("Escape!"));
+    /// let _click = hook.on_click(monio::Button::Left, |x, y| println!("click at ({x}, {y})"));
+    /// hook.run(|_: &Event| {}).expect("Failed to start hook");
+    /// ```
+    pub fn subscribe<F>(&self, callback: F) -> Subscription
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.subscribers.add(Arc::new(callback))
+    }
+
+    /// Call `callback` whenever `key` is pressed (including autorepeat).
+    ///
+    /// Keys are matched according to [`hotkey_match_mode`](Self::hotkey_match_mode).
+    pub fn on_key_pressed<F>(&self, key: Key, callback: F) -> Subscription
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let mode = self.match_mode;
+        let while_composing = self.hotkeys_while_composing;
+        self.subscribe(move |event: &Event| {
+            if event.event_type == EventType::KeyPressed
+                && event.keyboard.as_ref().is_some_and(|kb| {
+                    (while_composing || kb.ime_active != Some(true)) && mode.matches(key, kb)
+                })
+            {
+                callback();
+            }
+        })
+    }
+
+    /// Call `callback` with the key and held modifiers on every key press.
+    ///
+    /// The key is identified according to [`hotkey_match_mode`](Self::hotkey_match_mode).
+    pub fn on_any_key<F>(&self, callback: F) -> Subscription
+    where
+        F: Fn(Key, Modifiers) + Send + Sync + 'static,
+    {
+        let mode = self.match_mode;
+        let while_composing = self.hotkeys_while_composing;
+        self.subscribe(move |event: &Event| {
+            if event.event_type == EventType::KeyPressed
+                && let Some(kb) = &event.keyboard
+                && (while_composing || kb.ime_active != Some(true))
+            {
+                callback(mode.key_of(kb), Modifiers::from_mask(event.mask));
+            }
+        })
+    }
+
+    /// Call `callback` with the cursor position whenever `button` is pressed.
+    pub fn on_click<F>(&self, button: Button, callback: F) -> Subscription
+    where
+        F: Fn(f64, f64) + Send + Sync + 'static,
+    {
+        self.subscribe(move |event: &Event| {
+            if event.event_type == EventType::MousePressed
+                && let Some(mouse) = &event.mouse
+                && mouse.button == Some(button)
+            {
+                callback(mouse.x, mouse.y);
+            }
+        })
+    }
+
+    /// Call `callback` with the cursor position whenever `chord` completes,
+    /// e.g. Right pressed while Left is held.
+    ///
+    /// See [`chord`](crate::chord) for what consuming chords in grab mode
+    /// can and can't do.
+    pub fn on_mouse_chord<F>(&self, chord: MouseChord, callback: F) -> Subscription
+    where
+        F: Fn(f64, f64) + Send + Sync + 'static,
+    {
+        self.subscribe(move |event: &Event| {
+            if chord.matches(event)
+                && let Some(mouse) = &event.mouse
+            {
+                callback(mouse.x, mouse.y);
+            }
+        })
+    }
 
+    /// Call `callback` whenever `detector` sees a double tap, e.g. Shift
+    /// tapped twice.
+    pub fn on_double_tap<F>(&self, detector: DoubleTapDetector, callback: F) -> Subscription
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let detector = Mutex::new(detector);
+        self.subscribe(move |event: &Event| {
+            let fired = detector
+                .lock()
+                .is_ok_and(|mut detector| detector.process_event(event));
+            if fired {
+                callback();
+            }
+        })
+    }
+
+    /// Call `callback` with the direction and amount of every wheel scroll.
+    pub fn on_wheel<F>(&self, callback: F) -> Subscription
+    where
+        F: Fn(ScrollDirection, f64) + Send + Sync + 'static,
+    {
+        self.subscribe(move |event: &Event| {
+            if event.event_type == EventType::MouseWheel
+                && let Some(wheel) = &event.wheel
+            {
+                callback(wheel.direction, wheel.delta);
+            }
+        })
+    }
+
+    /// Call `callback` with every event `filter` matches.
+    ///
+    /// Filters can come from configuration, see [`filter`](crate::filter):
+    ///
+    /// ```no_run
+    /// use monio::{Event, EventFilter, Hook};
+    ///
+    /// let hook = Hook::new();
+    /// let filter = EventFilter::parse("key:pressed && key == F1 && mods.ctrl").unwrap();
+    /// let _help = hook.on_filter(filter, |_: &Event| println!("Ctrl+F1"));
+    /// hook.run(|_: &Event| {}).expect("Failed to start hook");
+    /// ```
+    pub fn on_filter<F>(&self, filter: EventFilter, callback: F) -> Subscription
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.subscribe(move |event: &Event| {
+            if filter.matches(event) {
+                callback(event);
+            }
+        })
+    }
+
+    /// Stop this hook when `token` is cancelled, until the registration is
+    /// dropped.
+    ///
+    /// A hook that hasn't started yet when the token is cancelled is stopped
+    /// by [`stop_when_cancelled`](Self::stop_when_cancelled) instead.
+    pub(crate) fn stop_on(&self, token: &ShutdownToken) -> Registration {
         let running = self.running.clone();
-        let handle = std::thread::spawn(move || {
-            let _ = platform::run_grab_hook(&running, handler);
-            running.store(false, Ordering::SeqCst);
-        });
+        let times = self.times.clone();
+        token.on_cancel(move || {
+            let _ = request_stop(&running, &times);
+        })
+    }
 
-        *self.thread_handle.write().unwrap() = Some(handle);
-        Ok(())
+    fn stop_when_cancelled<H>(&self, handler: H, token: &ShutdownToken) -> StopWhenCancelled<H> {
+        StopWhenCancelled {
+            token: token.clone(),
+            running: self.running.clone(),
+            times: self.times.clone(),
+            handler,
+        }
     }
 
     /// Stop the hook.
+    ///
+    /// If the hook is still starting, this waits for it to be enabled first;
+    /// platform stop requests sent before the hook loop exists would be lost.
+    /// Calling this from the handler of an async hook signals the stop
+    /// without joining the hook thread (which would deadlock).
     pub fn stop(&self) -> Result<()> {
-        if !self.running.swap(false, Ordering::SeqCst) {
-            return Err(Error::NotRunning);
+        let stopped = request_stop(&self.running, &self.times);
+        // A hook stopped by a shutdown token may still be winding down
+        if let Err(e) = &stopped
+            && !matches!(e, Error::NotRunning)
+        {
+            return stopped;
         }
 
-        platform::stop_hook()?;
-
         // Wait for the thread to finish if running async
-        if let Some(handle) = self.thread_handle.write().unwrap().take() {
+        let handle = self.thread_handle.write().unwrap().take();
+        if let Some(handle) = handle {
+            if handle.thread().id() == std::thread::current().id() {
+                return stopped;
+            }
             handle
                 .join()
                 .map_err(|_| Error::ThreadError("failed to join hook thread".into()))?;
         }
 
-        Ok(())
+        stopped
+    }
+
+    /// Stop dispatching events while keeping the OS hook installed.
+    ///
+    /// Neither the handler nor subscriptions see events until
+    /// [`resume`](Self::resume), and a grab hook lets every event through
+    /// meanwhile. Unlike [`stop`](Self::stop) and starting again, nothing is
+    /// removed and re-installed, so there is no gap when resuming and no new
+    /// permission check. `HookEnabled`, `HookDisabled` and the other events
+    /// about the hook itself are still dispatched. Each run of the hook
+    /// starts unpaused.
+    ///
+    /// Fails with [`Error::NotRunning`] if the hook isn't running.
+    pub fn pause(&self) -> Result<()> {
+        set_paused(&self.running, &self.paused, true)
+    }
+
+    /// Dispatch events again after [`pause`](Self::pause).
+    ///
+    /// Fails with [`Error::NotRunning`] if the hook isn't running.
+    pub fn resume(&self) -> Result<()> {
+        set_paused(&self.running, &self.paused, false)
+    }
+
+    /// Whether the hook is running but paused, see [`pause`](Self::pause).
+    pub fn is_paused(&self) -> bool {
+        self.is_running() && self.paused.load(Ordering::SeqCst)
     }
 
     /// Check if the hook is currently running.
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
     }
+
+    /// When the hook was last enabled (the `HookEnabled` event was dispatched).
+    pub fn started_at(&self) -> Option<SystemTime> {
+        self.times.lock().ok()?.started_at
+    }
+
+    /// When the hook was last disabled, if it has stopped since it was enabled.
+    pub fn stopped_at(&self) -> Option<SystemTime> {
+        self.times.lock().ok()?.stopped_at
+    }
+
+    /// How long the hook has been enabled.
+    ///
+    /// While running this is the time since `started_at()`; after stopping it
+    /// is the span between `started_at()` and `stopped_at()`. Returns `None`
+    /// if the hook has never been enabled.
+    pub fn uptime(&self) -> Option<Duration> {
+        self.times.lock().ok()?.uptime()
+    }
 }
 
 impl Drop for Hook {
@@ -282,3 +1516,230 @@ where
     let hook = Hook::new();
     hook.grab(callback)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_blocking_run_from_other_thread() {
+        let hook = Arc::new(Hook::new());
+
+        let runner = {
+            let hook = hook.clone();
+            std::thread::spawn(move || hook.run(|_: &Event| {}))
+        };
+
+        // Wait for the hook to start, or to fail to start on backends that
+        // aren't available here (no display / no input device access)
+        let deadline = std::time::Instant::now() + READY_TIMEOUT;
+        while !runner.is_finished() && hook.started_at().is_none() {
+            assert!(std::time::Instant::now() < deadline, "hook never started");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        match hook.stop() {
+            Ok(()) => {
+                runner.join().unwrap().unwrap();
+                assert!(hook.stopped_at().is_some());
+            }
+            Err(Error::NotRunning) => assert!(runner.join().unwrap().is_err()),
+            Err(e) => panic!("unexpected stop error: {e}"),
+        }
+        assert!(!hook.is_running());
+    }
+
+    #[test]
+    fn test_shutdown_token_stops_run() {
+        let hook = Hook::new();
+        let token = ShutdownToken::new();
+        token.cancel();
+        // Cancelled before starting: never starts
+        hook.run_with_token(|_: &Event| {}, &token).unwrap();
+        assert!(hook.started_at().is_none());
+
+        let hook = Arc::new(hook);
+        let token = ShutdownToken::new();
+        let runner = {
+            let hook = hook.clone();
+            let token = token.clone();
+            std::thread::spawn(move || hook.run_with_token(|_: &Event| {}, &token))
+        };
+        let deadline = std::time::Instant::now() + READY_TIMEOUT;
+        while !runner.is_finished() && hook.started_at().is_none() {
+            assert!(std::time::Instant::now() < deadline, "hook never started");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let started = hook.started_at().is_some();
+        token.cancel();
+        let result = runner.join().unwrap();
+        // Backends that aren't available here fail to start instead
+        assert_eq!(result.is_ok(), started);
+        assert!(!hook.is_running());
+        assert!(matches!(hook.stop(), Err(Error::NotRunning)));
+    }
+
+    #[test]
+    fn test_pause_keeps_events_from_handlers() {
+        let hook = Hook::new();
+        assert!(matches!(hook.pause(), Err(Error::NotRunning)));
+        assert!(matches!(hook.resume(), Err(Error::NotRunning)));
+
+        let hotkeys = Arc::new(AtomicU64::new(0));
+        let _hotkey = {
+            let hotkeys = hotkeys.clone();
+            hook.on_key_pressed(Key::KeyA, move || {
+                hotkeys.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let listener = {
+            let seen = seen.clone();
+            hook.track(move |event: &Event| seen.lock().unwrap().push(event.event_type))
+        };
+        let grabber = hook.grab_switch(|_: &Event| None);
+        let key = Event::key_pressed(Key::KeyA, 0);
+
+        hook.running.store(true, Ordering::SeqCst);
+        hook.pause().unwrap();
+        assert!(hook.is_paused());
+        EventHandler::handle_event(&listener, &key);
+        EventHandler::handle_event(&listener, &Event::hook_enabled());
+        // Grabs let everything through, even keys they'd intercept
+        assert_eq!(grabber.handle_event(&key), Some(key.clone()));
+        assert_eq!(*seen.lock().unwrap(), vec![EventType::HookEnabled]);
+        assert_eq!(hotkeys.load(Ordering::SeqCst), 0);
+
+        hook.resume().unwrap();
+        assert!(!hook.is_paused());
+        EventHandler::handle_event(&listener, &key);
+        assert!(grabber.handle_event(&key).is_none());
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![EventType::HookEnabled, EventType::KeyPressed]
+        );
+        assert_eq!(hotkeys.load(Ordering::SeqCst), 2);
+
+        hook.pause().unwrap();
+        hook.running.store(false, Ordering::SeqCst);
+        assert!(!hook.is_paused());
+    }
+
+    #[test]
+    fn test_grab_toggle_keeps_events_flowing() {
+        let hook = Hook::new();
+        let seen = Arc::new(AtomicU64::new(0));
+        let handler = {
+            let seen = seen.clone();
+            hook.grab_switch(move |_: &Event| {
+                seen.fetch_add(1, Ordering::SeqCst);
+                None
+            })
+        };
+        let event = Event::key_pressed(Key::KeyA, 0);
+
+        assert!(handler.handle_event(&event).is_none());
+        hook.set_grab_enabled(false);
+        assert!(!hook.is_grab_enabled());
+        assert_eq!(handler.handle_event(&event), Some(event.clone()));
+        hook.set_grab_enabled(false);
+        hook.set_grab_enabled(true);
+        assert!(handler.handle_event(&event).is_none());
+        assert_eq!(seen.load(Ordering::SeqCst), 3);
+        assert_eq!(hook.grab_mode_changes(), 2);
+
+        // Toggling from another thread mid-stream drops nothing
+        let hook = Arc::new(hook);
+        let toggler = {
+            let hook = hook.clone();
+            std::thread::spawn(move || {
+                for i in 0..1000 {
+                    hook.set_grab_enabled(i % 2 == 1);
+                }
+            })
+        };
+        for _ in 0..10_000 {
+            handler.handle_event(&event);
+        }
+        toggler.join().unwrap();
+        assert_eq!(seen.load(Ordering::SeqCst), 10_003);
+        assert!(hook.is_grab_enabled());
+    }
+
+    #[test]
+    fn test_global_suspend_reaches_every_consumer() {
+        let state: &'static crate::suspend::SuspendState =
+            Box::leak(Box::new(crate::suspend::SuspendState::new()));
+        let key = Event::key_pressed(Key::KeyA, 0);
+
+        let hook = Hook::new();
+        let hotkeys = Arc::new(AtomicU64::new(0));
+        let _hotkey = {
+            let hotkeys = hotkeys.clone();
+            hook.on_key_pressed(Key::KeyA, move || {
+                hotkeys.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut listener = {
+            let seen = seen.clone();
+            hook.track(move |event: &Event| seen.lock().unwrap().push(event.event_type))
+        };
+        listener.suspend = SuspendGate::with_state(state, false);
+        let mut grabber = hook.track(|_: &Event| None);
+        grabber.suspend = SuspendGate::with_state(state, false);
+
+        let abort = Hook::new().ignore_global_suspend(true);
+        let aborts = Arc::new(AtomicU64::new(0));
+        let mut abort_chord = {
+            let aborts = aborts.clone();
+            abort.track(move |event: &Event| {
+                if event.event_type == EventType::KeyPressed {
+                    aborts.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+        abort_chord.suspend = SuspendGate::with_state(state, true);
+
+        state.suspend(Duration::from_secs(60));
+        EventHandler::handle_event(&listener, &key);
+        EventHandler::handle_event(&listener, &Event::hook_disabled());
+        assert_eq!(GrabHandler::handle_event(&grabber, &key), Some(key.clone()));
+        EventHandler::handle_event(&abort_chord, &key);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![EventType::GlobalSuspendChanged, EventType::HookDisabled]
+        );
+        assert_eq!(hotkeys.load(Ordering::SeqCst), 0);
+        assert_eq!(aborts.load(Ordering::SeqCst), 1);
+
+        state.resume();
+        EventHandler::handle_event(&listener, &key);
+        assert!(GrabHandler::handle_event(&grabber, &key).is_none());
+        assert_eq!(
+            seen.lock().unwrap()[2..],
+            [EventType::GlobalSuspendChanged, EventType::KeyPressed]
+        );
+        assert_eq!(hotkeys.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_event_mask_categories() {
+        let keys_and_clicks = EventMask::KEYBOARD | EventMask::MOUSE_BUTTONS;
+        assert!(keys_and_clicks.includes(EventType::KeyTyped));
+        assert!(keys_and_clicks.includes(EventType::ImeCompositionStarted));
+        assert!(keys_and_clicks.includes(EventType::MouseClicked));
+        assert!(!keys_and_clicks.includes(EventType::MouseMoved));
+        assert!(!keys_and_clicks.includes(EventType::DragStarted));
+        assert!(!keys_and_clicks.includes(EventType::MouseWheel));
+        // Lifecycle events are always delivered
+        assert!(EventMask::from_bits_truncate(0).includes(EventType::HookDisabled));
+
+        assert!(EventMask::ALL.contains(EventMask::MOUSE | EventMask::TOUCH));
+        assert!(EventMask::MOUSE.intersects(EventMask::MOUSE_WHEEL));
+        assert!(!keys_and_clicks.contains(EventMask::MOUSE));
+        assert_eq!(EventMask::default(), EventMask::ALL);
+        assert_eq!(EventMask::from_bits_truncate(u32::MAX), EventMask::ALL);
+    }
+}
@@ -0,0 +1,193 @@
+//! Input method (IME) composition state.
+//!
+//! While an input method composes text (Japanese kana, Chinese pinyin,
+//! Korean hangul), keys go to the composer rather than the app: typing
+//! `k` `a` produces `か`, and a shortcut key may never reach its target.
+//! Hooks report [`EventType::ImeCompositionStarted`] and
+//! [`EventType::ImeCompositionEnded`] when this changes, and mark key events
+//! with [`KeyboardData::ime_active`]. With
+//! [`Hook::hotkeys_while_composing`](crate::Hook::hotkeys_while_composing)
+//! off, `on_key_pressed` and `on_any_key` skip keys meant for the composer.
+//!
+//! Detection is best-effort, and where the platform doesn't expose the
+//! state it is reported as unknown (`None`) rather than guessed:
+//!
+//! - **Windows**: the input method of the foreground window is open and in
+//!   native (not alphanumeric) mode, read through IMM
+//! - **macOS**: not detected. Composition happens in each app's text input
+//!   context, which an event tap can't see
+//! - **Linux**: not detected. IBus and Fcitx talk to apps directly, so
+//!   neither XRecord nor evdev sees their state
+//!
+//! The state is sampled when key events arrive, so a change is reported
+//! just before the first key event after it.
+//!
+//! [`KeyboardData::ime_active`]: crate::event::KeyboardData::ime_active
+
+use crate::event::{Event, EventType};
+use crate::platform;
+use std::time::{Duration, Instant};
+
+/// How long a sample of the state is reused for key events that follow.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Whether an input method is composing right now, or `None` where it
+/// can't be detected.
+pub fn composition_active() -> Option<bool> {
+    platform::ime_composing()
+}
+
+/// Follows the composition state for a hook's key events.
+pub(crate) struct ImeTracker {
+    probe: fn() -> Option<bool>,
+    state: Option<bool>,
+    sampled: Option<Instant>,
+}
+
+impl ImeTracker {
+    pub(crate) fn new() -> Self {
+        Self::with_probe(platform::ime_composing)
+    }
+
+    fn with_probe(probe: fn() -> Option<bool>) -> Self {
+        Self {
+            probe,
+            state: None,
+            sampled: None,
+        }
+    }
+
+    /// The state at `now`, sampled again if the last sample is stale.
+    fn state_at(&mut self, now: Instant) -> (Option<bool>, Option<bool>) {
+        let previous = self.state;
+        let stale = self
+            .sampled
+            .is_none_or(|sampled| now.saturating_duration_since(sampled) >= SAMPLE_INTERVAL);
+        if stale {
+            self.state = (self.probe)();
+            self.sampled = Some(now);
+        }
+        (previous, self.state)
+    }
+
+    /// The key event to report in place of `event`, marked with the
+    /// composition state, pushing a composition change to report first to
+    /// `out`.
+    pub(crate) fn observe(
+        &mut self,
+        event: &Event,
+        now: Instant,
+        out: &mut Vec<Event>,
+    ) -> Option<Event> {
+        if !matches!(
+            event.event_type,
+            EventType::KeyPressed | EventType::KeyReleased | EventType::KeyTyped
+        ) {
+            return None;
+        }
+        let (previous, state) = self.state_at(now);
+        let change = match (previous == Some(true), state == Some(true)) {
+            (false, true) => Some(Event::ime_composition_started()),
+            (true, false) => Some(Event::ime_composition_ended()),
+            _ => None,
+        };
+        if let Some(mut change) = change {
+            change.time = event.time;
+            change.mask = event.mask;
+            out.push(change);
+        }
+
+        state?;
+        let mut marked = event.clone();
+        if let Some(keyboard) = &mut marked.keyboard {
+            keyboard.ime_active = state;
+        }
+        Some(marked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycode::Key;
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    /// Run key presses through `tracker`, a sample interval apart, returning
+    /// the types reported and the marks on the keys.
+    fn run(tracker: &mut ImeTracker, presses: usize) -> Vec<(EventType, Option<bool>)> {
+        let start = Instant::now();
+        let mut reported = Vec::new();
+        for i in 0..presses {
+            let event = Event::key_pressed(Key::KeyA, 38);
+            let mut before = Vec::new();
+            let now = start + SAMPLE_INTERVAL * i as u32;
+            let event = tracker.observe(&event, now, &mut before).unwrap_or(event);
+            reported.extend(
+                before
+                    .into_iter()
+                    .chain([event])
+                    .map(|e| (e.event_type, e.keyboard.and_then(|kb| kb.ime_active))),
+            );
+        }
+        reported
+    }
+
+    #[test]
+    fn test_reports_composition_changes() {
+        // Composing on the second and third key
+        static CALLS: AtomicU8 = AtomicU8::new(0);
+        fn probe() -> Option<bool> {
+            Some(matches!(CALLS.fetch_add(1, Ordering::Relaxed), 1 | 2))
+        }
+        let mut tracker = ImeTracker::with_probe(probe);
+        assert_eq!(
+            run(&mut tracker, 4),
+            vec![
+                (EventType::KeyPressed, Some(false)),
+                (EventType::ImeCompositionStarted, None),
+                (EventType::KeyPressed, Some(true)),
+                (EventType::KeyPressed, Some(true)),
+                (EventType::ImeCompositionEnded, None),
+                (EventType::KeyPressed, Some(false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_undetectable_state_is_unknown() {
+        let mut tracker = ImeTracker::with_probe(|| None);
+        assert_eq!(
+            run(&mut tracker, 2),
+            vec![(EventType::KeyPressed, None), (EventType::KeyPressed, None)]
+        );
+
+        // Mouse events are left alone
+        let mut before = Vec::new();
+        let mut tracker = ImeTracker::with_probe(|| Some(true));
+        let click = Event::mouse_pressed(crate::Button::Left, 1.0, 2.0);
+        assert!(
+            tracker
+                .observe(&click, Instant::now(), &mut before)
+                .is_none()
+        );
+        assert!(before.is_empty());
+    }
+
+    #[test]
+    fn test_samples_are_reused_within_interval() {
+        static CALLS: AtomicU8 = AtomicU8::new(0);
+        fn probe() -> Option<bool> {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            Some(false)
+        }
+        let mut tracker = ImeTracker::with_probe(probe);
+        let now = Instant::now();
+        let key = Event::key_pressed(Key::KeyA, 38);
+        for _ in 0..3 {
+            tracker.observe(&key, now, &mut Vec::new());
+        }
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+        tracker.observe(&key, now + SAMPLE_INTERVAL, &mut Vec::new());
+        assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+    }
+}
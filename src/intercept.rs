@@ -0,0 +1,471 @@
+//! Grabbing only while a key is held ("push-to-intercept"), or only the
+//! mouse wheel.
+//!
+//! A grab hook given [`GrabOptions::active_while_held`] consumes events only
+//! while the toggle key (or every key of a chord) is physically held, and
+//! passes everything through otherwise, like push-to-talk. Useful for
+//! temporary modes such as dictation or command keys, without writing
+//! rules. The handler sees every event either way.
+//!
+//! Key and button releases always follow their press: if the press was
+//! consumed, so is the release, and if it reached other applications, so
+//! does the release, even when the toggle changed in between. That way no
+//! key gets stuck in the focused application.
+//!
+//! # Scroll-only grabs
+//!
+//! [`GrabOptions::scroll_only`] is for utilities that repurpose the wheel,
+//! like changing the volume when scrolling over the taskbar. Every other
+//! event passes through before any handler or subscription sees it, so such
+//! a utility can never swallow a click or a key. On Windows the keyboard
+//! hook isn't even installed, and other mouse messages are passed on before
+//! they are converted to events, keeping the added latency to a minimum.
+//! Lifecycle events are still reported.
+//!
+//! ```no_run
+//! use monio::{Event, EventType, GrabOptions, Hook};
+//!
+//! let hook = Hook::new().grab_options(GrabOptions::scroll_only());
+//! hook.grab(|event: &Event| {
+//!     // Only wheel and lifecycle events get here
+//!     if event.event_type == EventType::MouseWheel {
+//!         println!("volume {:?}", event.wheel.as_ref().map(|w| w.direction));
+//!         return None;
+//!     }
+//!     Some(event.clone())
+//! })?;
+//! # Ok::<(), monio::Error>(())
+//! ```
+//!
+//! # Grabs that can't consume
+//!
+//! Where the backend can't consume events (X11) or
+//! [`policy`](crate::policy) denies grabbing, a grab hook runs as a listener:
+//! its handler sees every event, but everything reaches other applications.
+//! The handler is told with an [`EventType::GrabDegraded`] event right after
+//! `HookEnabled`, and [`Hook::grab_capabilities`](crate::Hook::grab_capabilities)
+//! reports it. Grabs that are pointless without consuming can refuse to start
+//! instead, with [`GrabOptions::require_consumption`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::{Event, GrabOptions, Hook, Key};
+//!
+//! // Swallow everything typed while F24 is held
+//! let hook = Hook::new().grab_options(GrabOptions::active_while_held(Key::F24));
+//! hook.grab(|event: &Event| {
+//!     println!("command mode: {:?}", event.event_type);
+//!     None
+//! })?;
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::event::{Button, Event, EventType};
+use crate::hook::GrabHandler;
+use crate::keycode::{HotkeyMatchMode, Key};
+use crate::platform;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// How a grab hook intercepts events, see [`Hook::grab_options`].
+///
+/// The default intercepts all the time.
+///
+/// [`Hook::grab_options`]: crate::Hook::grab_options
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GrabOptions {
+    toggle: Vec<Key>,
+    pass_toggle: bool,
+    scroll_only: bool,
+    require_consumption: bool,
+}
+
+impl GrabOptions {
+    /// Intercept only while `key` is held.
+    pub fn active_while_held(key: Key) -> Self {
+        Self::active_while_chord_held(&[key])
+    }
+
+    /// Intercept only while every key of `chord` is held, e.g.
+    /// `[Key::ControlLeft, Key::AltLeft]`.
+    pub fn active_while_chord_held(chord: &[Key]) -> Self {
+        Self {
+            toggle: chord.to_vec(),
+            ..Self::default()
+        }
+    }
+
+    /// Intercept only mouse wheel events, passing everything else on without
+    /// showing it to the handler; see
+    /// [scroll-only grabs](crate::intercept#scroll-only-grabs).
+    pub fn scroll_only() -> Self {
+        Self {
+            scroll_only: true,
+            ..Self::default()
+        }
+    }
+
+    /// Whether only the mouse wheel is intercepted.
+    pub fn is_scroll_only(&self) -> bool {
+        self.scroll_only
+    }
+
+    /// Whether the toggle keys reach other applications. By default they
+    /// are consumed.
+    pub fn consume_toggle(mut self, consume: bool) -> Self {
+        self.pass_toggle = !consume;
+        self
+    }
+
+    /// Fail with [`Error::NotSupported`](crate::Error::NotSupported) instead
+    /// of listening when the grab couldn't consume events; see
+    /// [grabs that can't consume](crate::intercept#grabs-that-cant-consume).
+    pub fn require_consumption(mut self, require: bool) -> Self {
+        self.require_consumption = require;
+        self
+    }
+
+    /// Whether the grab fails rather than listen, see
+    /// [`require_consumption`](Self::require_consumption).
+    pub fn requires_consumption(&self) -> bool {
+        self.require_consumption
+    }
+}
+
+/// What a grab hook can do, from
+/// [`Hook::grab_capabilities`](crate::Hook::grab_capabilities) or
+/// [`ChannelHookHandle::grab_capabilities`](crate::channel::ChannelHookHandle::grab_capabilities).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GrabCapabilities {
+    /// Whether the handler's verdicts take effect. If not, the grab runs as a
+    /// listener and every event reaches other applications.
+    pub can_consume: bool,
+    /// The input backend, such as `"evdev"` or `"x11"`.
+    pub backend: &'static str,
+}
+
+impl GrabCapabilities {
+    /// A grab started on this backend, with grabbing `denied` by policy or not.
+    pub(crate) fn new(denied: bool) -> Self {
+        Self {
+            can_consume: platform::GRAB_SUPPRESSES && !denied,
+            backend: platform::BACKEND,
+        }
+    }
+}
+
+/// Something with a press and a release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Pressable {
+    Key(Key),
+    Button(Button),
+}
+
+/// State of push-to-intercept.
+#[derive(Debug)]
+struct PushToIntercept {
+    options: GrabOptions,
+    /// Which toggle keys are held.
+    toggle_held: Vec<bool>,
+    /// Whether each held key or button had its press consumed.
+    pressed: HashMap<Pressable, bool>,
+}
+
+/// What to do with an event.
+enum Route {
+    /// Use the handler's verdict.
+    Handler,
+    /// Pass it through, whatever the handler says.
+    Pass,
+    /// Consume it, whatever the handler says.
+    Consume,
+}
+
+impl PushToIntercept {
+    fn new(options: GrabOptions) -> Self {
+        Self {
+            toggle_held: vec![false; options.toggle.len()],
+            options,
+            pressed: HashMap::new(),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.toggle_held.iter().all(|&held| held)
+    }
+
+    /// Update the toggle for `event`, and route it.
+    fn route(&mut self, event: &Event, mode: HotkeyMatchMode) -> Route {
+        let (pressable, pressed) = match event.event_type {
+            EventType::KeyPressed | EventType::KeyReleased => {
+                let Some(kb) = &event.keyboard else {
+                    return self.route_other();
+                };
+                let pressed = event.event_type == EventType::KeyPressed;
+                if let Some(i) = self
+                    .options
+                    .toggle
+                    .iter()
+                    .position(|&k| mode.matches(k, kb))
+                {
+                    return self.route_toggle(i, kb.key, pressed);
+                }
+                (Pressable::Key(kb.key), pressed)
+            }
+            EventType::MousePressed | EventType::MouseReleased => {
+                let Some(button) = event.mouse.as_ref().and_then(|m| m.button) else {
+                    return self.route_other();
+                };
+                (
+                    Pressable::Button(button),
+                    event.event_type == EventType::MousePressed,
+                )
+            }
+            _ => return self.route_other(),
+        };
+
+        if pressed {
+            // Autorepeat keeps the first press's route
+            match self.pressed.get(&pressable) {
+                Some(true) => Route::Consume,
+                Some(false) => Route::Pass,
+                None if self.is_active() => Route::Handler,
+                None => {
+                    self.pressed.insert(pressable, false);
+                    Route::Pass
+                }
+            }
+        } else {
+            match self.pressed.remove(&pressable) {
+                Some(true) => Route::Consume,
+                // Pressed before the hook started, or passed
+                _ => Route::Pass,
+            }
+        }
+    }
+
+    fn route_toggle(&mut self, index: usize, key: Key, pressed: bool) -> Route {
+        self.toggle_held[index] = pressed;
+        let pressable = Pressable::Key(key);
+        let consume = if pressed {
+            *self
+                .pressed
+                .entry(pressable)
+                .or_insert(!self.options.pass_toggle)
+        } else {
+            self.pressed.remove(&pressable).unwrap_or(false)
+        };
+        if consume { Route::Consume } else { Route::Pass }
+    }
+
+    fn route_other(&self) -> Route {
+        if self.is_active() {
+            Route::Handler
+        } else {
+            Route::Pass
+        }
+    }
+
+    /// Remember whether a press routed to the handler was consumed.
+    fn record(&mut self, event: &Event, consumed: bool) {
+        let pressable = match event.event_type {
+            EventType::KeyPressed => event.keyboard.as_ref().map(|kb| Pressable::Key(kb.key)),
+            EventType::MousePressed => event
+                .mouse
+                .as_ref()
+                .and_then(|m| m.button)
+                .map(Pressable::Button),
+            _ => None,
+        };
+        if let Some(pressable) = pressable {
+            self.pressed.insert(pressable, consumed);
+        }
+    }
+}
+
+/// Grab handler wrapper applying [`GrabOptions`].
+pub(crate) struct Intercept<H> {
+    state: Option<Mutex<PushToIntercept>>,
+    scroll_only: bool,
+    mode: HotkeyMatchMode,
+    handler: H,
+}
+
+impl<H> Intercept<H> {
+    pub(crate) fn new(options: &GrabOptions, mode: HotkeyMatchMode, handler: H) -> Self {
+        Self {
+            state: (!options.toggle.is_empty())
+                .then(|| Mutex::new(PushToIntercept::new(options.clone()))),
+            scroll_only: options.scroll_only,
+            mode,
+            handler,
+        }
+    }
+}
+
+impl<H: GrabHandler> GrabHandler for Intercept<H> {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        if self.scroll_only
+            && !matches!(
+                event.event_type,
+                EventType::MouseWheel
+                    | EventType::HookEnabled
+                    | EventType::HookDisabled
+                    | EventType::GrabSuspended
+                    | EventType::GrabDegraded
+                    | EventType::GlobalSuspendChanged
+            )
+        {
+            return Some(event.clone());
+        }
+        let Some(state) = &self.state else {
+            return self.handler.handle_event(event);
+        };
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let route = state.route(event, self.mode);
+        // The handler sees every event either way
+        let result = self.handler.handle_event(event);
+        match route {
+            Route::Handler => {
+                state.record(event, result.is_none());
+                result
+            }
+            Route::Pass => result.or_else(|| Some(event.clone())),
+            Route::Consume => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A handler consuming everything but `passed`.
+    fn handler(options: GrabOptions, passed: Key) -> Intercept<impl GrabHandler> {
+        Intercept::new(&options, HotkeyMatchMode::Physical, move |event: &Event| {
+            let is_passed = event.keyboard.as_ref().is_some_and(|kb| kb.key == passed);
+            is_passed.then(|| event.clone())
+        })
+    }
+
+    fn press(key: Key) -> Event {
+        Event::key_pressed(key, 0)
+    }
+
+    fn release(key: Key) -> Event {
+        Event::key_released(key, 0)
+    }
+
+    fn consumed(handler: &impl GrabHandler, event: Event) -> bool {
+        handler.handle_event(&event).is_none()
+    }
+
+    #[test]
+    fn test_intercepts_only_while_held() {
+        let h = handler(GrabOptions::active_while_held(Key::F24), Key::KeyZ);
+        assert!(!consumed(&h, press(Key::KeyA)));
+        assert!(!consumed(&h, release(Key::KeyA)));
+        assert!(!consumed(&h, Event::mouse_moved(1.0, 1.0)));
+
+        assert!(consumed(&h, press(Key::F24)));
+        assert!(consumed(&h, press(Key::KeyA)));
+        assert!(consumed(&h, release(Key::KeyA)));
+        assert!(consumed(&h, Event::mouse_moved(2.0, 2.0)));
+        // The handler's verdict still counts
+        assert!(!consumed(&h, press(Key::KeyZ)));
+        assert!(!consumed(&h, release(Key::KeyZ)));
+        assert!(consumed(&h, press(Key::F24)), "autorepeat");
+        assert!(consumed(&h, release(Key::F24)));
+
+        assert!(!consumed(&h, press(Key::KeyA)));
+        assert!(!consumed(&h, release(Key::KeyA)));
+    }
+
+    #[test]
+    fn test_releases_follow_their_press() {
+        let h = handler(GrabOptions::active_while_held(Key::F24), Key::KeyZ);
+
+        // Pressed before the toggle: released to the app, autorepeat too
+        assert!(!consumed(&h, press(Key::KeyB)));
+        assert!(consumed(&h, press(Key::F24)));
+        assert!(!consumed(&h, press(Key::KeyB)));
+        assert!(!consumed(&h, release(Key::KeyB)));
+
+        // Consumed while held, released after the toggle
+        assert!(consumed(&h, press(Key::KeyA)));
+        assert!(consumed(&h, Event::mouse_pressed(Button::Left, 0.0, 0.0)));
+        assert!(consumed(&h, release(Key::F24)));
+        assert!(consumed(&h, press(Key::KeyA)), "autorepeat");
+        assert!(consumed(&h, release(Key::KeyA)));
+        assert!(consumed(&h, Event::mouse_released(Button::Left, 0.0, 0.0)));
+
+        // Passed by the handler while held, released after the toggle
+        assert!(consumed(&h, press(Key::F24)));
+        assert!(!consumed(&h, press(Key::KeyZ)));
+        assert!(consumed(&h, release(Key::F24)));
+        assert!(!consumed(&h, release(Key::KeyZ)));
+
+        // Released without a press seen, e.g. held when the hook started
+        assert!(!consumed(&h, release(Key::F24)));
+        assert!(!consumed(&h, release(Key::KeyC)));
+    }
+
+    #[test]
+    fn test_chord_and_passed_toggle() {
+        let options = GrabOptions::active_while_chord_held(&[Key::ControlLeft, Key::AltLeft])
+            .consume_toggle(false);
+        let h = handler(options, Key::KeyZ);
+
+        assert!(!consumed(&h, press(Key::ControlLeft)));
+        assert!(!consumed(&h, press(Key::KeyA)));
+        assert!(!consumed(&h, release(Key::KeyA)));
+        assert!(!consumed(&h, press(Key::AltLeft)));
+        assert!(consumed(&h, press(Key::KeyA)));
+        assert!(!consumed(&h, release(Key::ControlLeft)));
+        assert!(consumed(&h, release(Key::KeyA)));
+        assert!(!consumed(&h, press(Key::KeyA)));
+        assert!(!consumed(&h, release(Key::AltLeft)));
+    }
+
+    #[test]
+    fn test_default_intercepts_always() {
+        let h = handler(GrabOptions::default(), Key::KeyZ);
+        assert!(consumed(&h, press(Key::KeyA)));
+        assert!(!consumed(&h, press(Key::KeyZ)));
+        assert!(consumed(&h, release(Key::KeyA)));
+    }
+
+    #[test]
+    fn test_scroll_only_never_shows_other_events() {
+        use crate::event::ScrollDirection;
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let seen = AtomicU64::new(0);
+        let h = Intercept::new(
+            &GrabOptions::scroll_only(),
+            HotkeyMatchMode::Physical,
+            |event: &Event| {
+                seen.fetch_add(1, Ordering::Relaxed);
+                (event.event_type != EventType::MouseWheel).then(|| event.clone())
+            },
+        );
+        for event in [
+            press(Key::KeyA),
+            release(Key::KeyA),
+            Event::mouse_pressed(Button::Left, 0.0, 0.0),
+            Event::mouse_released(Button::Left, 0.0, 0.0),
+            Event::mouse_moved(1.0, 1.0),
+        ] {
+            assert!(!consumed(&h, event));
+        }
+        assert_eq!(seen.load(Ordering::Relaxed), 0);
+
+        assert!(consumed(
+            &h,
+            Event::mouse_wheel(0.0, 0.0, ScrollDirection::Up, 1.0)
+        ));
+        assert!(!consumed(&h, Event::hook_enabled()));
+        assert_eq!(seen.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,889 @@
+//! Sharing events with another process through shared memory.
+//!
+//! For a capture daemon feeding a separate (e.g. sandboxed) UI process,
+//! [`publish_events_shm`] writes every event into a named POSIX shared
+//! memory ring, and [`subscribe_events_shm`] reads them on the other side.
+//! Events are stored as fixed-size binary records, so there is no
+//! serialization and no pipe in between.
+//!
+//! The ring has one writer and one reader. When the reader falls a full
+//! ring behind, new events are dropped rather than blocking the hook.
+//! Every event gets a sequence number, including dropped ones, so the
+//! reader can tell what it missed: see
+//! [`ShmSubscriber::try_recv_with_sequence`] and
+//! [`ShmSubscriber::lost_count`].
+//!
+//! The shared memory starts with a header holding a format version; a
+//! subscriber refuses rings of another version.
+//!
+//! Requires the `ipc` feature. Only available on Linux and macOS for now.
+//!
+//! # Example
+//!
+//! ```no_run
+//! // Capture process
+//! let publisher = monio::ipc::publish_events_shm("monio-events", 4096)?;
+//!
+//! // UI process
+//! let subscriber = monio::ipc::subscribe_events_shm("monio-events")?;
+//! for event in subscriber {
+//!     println!("{:?}", event.event_type);
+//! }
+//! # publisher.stop()?;
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::event::{
+    Button, DisplayChange, Event, EventType, KeyboardData, MouseData, ScrollDirection, ScrollPhase,
+    TouchData, WheelData,
+};
+use crate::hook::Hook;
+use crate::keycode::Key;
+use std::ffi::CString;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Identifies a monio event ring.
+const MAGIC: [u8; 8] = *b"MONIOSHM";
+
+/// Version of the header and record layout.
+///
+/// Keys are stored by their position in [`Key`], so adding keys (not only
+/// changing the layout) needs a new version.
+pub const SHM_FORMAT_VERSION: u32 = 1;
+
+/// Longest ring name accepted; macOS allows 31 bytes including the `/`.
+const MAX_NAME_LEN: usize = 30;
+
+/// How often a blocked subscriber checks for new events.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Start of the shared memory.
+#[repr(C)]
+struct Header {
+    magic: [u8; 8],
+    version: u32,
+    record_size: u32,
+    capacity: u64,
+    /// Next record to read. Only advanced by the subscriber.
+    head: AtomicU64,
+    /// Next record to write. Only advanced by the publisher.
+    tail: AtomicU64,
+    dropped: AtomicU64,
+    /// Set once the publisher is gone.
+    closed: AtomicU32,
+    /// Set while a subscriber is attached.
+    subscribed: AtomicU32,
+    publisher_pid: u32,
+    _reserved: u32,
+}
+
+const HEADER_SIZE: usize = std::mem::size_of::<Header>();
+const _: () = assert!(HEADER_SIZE == 64);
+
+const HAS_KEYBOARD: u32 = 1 << 0;
+const HAS_MOUSE: u32 = 1 << 1;
+const HAS_WHEEL: u32 = 1 << 2;
+const HAS_TOUCH: u32 = 1 << 3;
+const HAS_DISPLAY: u32 = 1 << 4;
+const HAS_CHAR: u32 = 1 << 5;
+const HAS_BUTTON: u32 = 1 << 6;
+const HAS_PRESSURE: u32 = 1 << 7;
+const EXTENDED: u32 = 1 << 8;
+const INJECTED_KNOWN: u32 = 1 << 9;
+const INJECTED: u32 = 1 << 10;
+const HAS_TARGET_PID: u32 = 1 << 11;
+const HAS_PIXEL_DELTA: u32 = 1 << 12;
+const HAS_PHASE: u32 = 1 << 13;
+const IME_KNOWN: u32 = 1 << 14;
+const IME_ACTIVE: u32 = 1 << 15;
+
+/// One event, packed.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Record {
+    seq: u64,
+    /// Nanoseconds since the Unix epoch, negative before it.
+    time_nanos: i64,
+    mask: u32,
+    flags: u32,
+    event_type: u16,
+    key_tag: u16,
+    key_payload: u32,
+    raw_code: u32,
+    ch: u32,
+    mouse_x: f64,
+    mouse_y: f64,
+    wheel_x: f64,
+    wheel_y: f64,
+    wheel_delta: f64,
+    touch_x: f64,
+    touch_y: f64,
+    touch_id: u32,
+    pressure: f32,
+    display_from: u32,
+    display_to: u32,
+    button: u8,
+    clicks: u8,
+    direction: u8,
+    phase: u8,
+    target_pid: i32,
+    pixel_delta: f64,
+}
+
+const RECORD_SIZE: usize = std::mem::size_of::<Record>();
+const _: () = assert!(RECORD_SIZE == 128);
+
+/// Every event type, indexed by its wire code.
+const EVENT_TYPES: [EventType; 21] = [
+    EventType::HookEnabled,
+    EventType::HookDisabled,
+    EventType::GrabSuspended,
+    EventType::GlobalSuspendChanged,
+    EventType::KeyPressed,
+    EventType::KeyReleased,
+    EventType::KeyTyped,
+    EventType::MousePressed,
+    EventType::MouseReleased,
+    EventType::MouseClicked,
+    EventType::MouseMoved,
+    EventType::MouseDragged,
+    EventType::MouseWheel,
+    EventType::TouchBegan,
+    EventType::TouchMoved,
+    EventType::TouchEnded,
+    // New types go at the end
+    EventType::DisplayChanged,
+    EventType::GrabDegraded,
+    EventType::DragStarted,
+    EventType::ImeCompositionStarted,
+    EventType::ImeCompositionEnded,
+];
+
+const DIRECTIONS: [ScrollDirection; 4] = [
+    ScrollDirection::Up,
+    ScrollDirection::Down,
+    ScrollDirection::Left,
+    ScrollDirection::Right,
+];
+
+const PHASES: [ScrollPhase; 3] = [ScrollPhase::Began, ScrollPhase::Changed, ScrollPhase::Ended];
+
+/// The `#[repr(u16)]` discriminant of `key`.
+fn key_tag(key: &Key) -> u16 {
+    // `Key` is `#[repr(u16)]`, so it starts with its u16 discriminant.
+    unsafe { *(key as *const Key as *const u16) }
+}
+
+/// Discriminant of `Key::Char`; `Key::Unknown` follows it, and every
+/// variant before it carries no data.
+fn char_tag() -> u16 {
+    key_tag(&Key::Char('\0'))
+}
+
+fn key_to_wire(key: Key) -> (u16, u32) {
+    let payload = match key {
+        Key::Char(ch) => ch as u32,
+        Key::Unknown(code) => code,
+        _ => 0,
+    };
+    (key_tag(&key), payload)
+}
+
+fn key_from_wire(tag: u16, payload: u32) -> Key {
+    let char_tag = char_tag();
+    if tag == char_tag {
+        return char::from_u32(payload).map_or(Key::Unknown(0), Key::Char);
+    }
+    if tag > char_tag {
+        return Key::Unknown(payload);
+    }
+    let mut key = Key::Unknown(0);
+    // Tags below `Key::Char` are data-less variants, so only the
+    // discriminant needs to change.
+    unsafe { (&mut key as *mut Key as *mut u16).write(tag) };
+    key
+}
+
+impl Record {
+    fn encode(event: &Event, seq: u64) -> Self {
+        let time_nanos = match event.time.duration_since(UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_nanos()).unwrap_or(i64::MAX),
+            Err(before) => -i64::try_from(before.duration().as_nanos()).unwrap_or(i64::MAX),
+        };
+        let mut record = Record {
+            seq,
+            time_nanos,
+            mask: event.mask,
+            event_type: EVENT_TYPES
+                .iter()
+                .position(|&t| t == event.event_type)
+                .unwrap_or(0) as u16,
+            ..Record::default()
+        };
+        match event.injected {
+            Some(true) => record.flags |= INJECTED_KNOWN | INJECTED,
+            Some(false) => record.flags |= INJECTED_KNOWN,
+            None => {}
+        }
+        if let Some(pid) = event.target_pid {
+            record.flags |= HAS_TARGET_PID;
+            record.target_pid = pid;
+        }
+        if let Some(kb) = &event.keyboard {
+            record.flags |= HAS_KEYBOARD;
+            (record.key_tag, record.key_payload) = key_to_wire(kb.key);
+            record.raw_code = kb.raw_code;
+            if let Some(ch) = kb.char {
+                record.flags |= HAS_CHAR;
+                record.ch = ch as u32;
+            }
+            if kb.extended {
+                record.flags |= EXTENDED;
+            }
+            match kb.ime_active {
+                Some(true) => record.flags |= IME_KNOWN | IME_ACTIVE,
+                Some(false) => record.flags |= IME_KNOWN,
+                None => {}
+            }
+        }
+        if let Some(mouse) = &event.mouse {
+            record.flags |= HAS_MOUSE;
+            record.mouse_x = mouse.x;
+            record.mouse_y = mouse.y;
+            record.clicks = mouse.clicks;
+            if let Some(button) = mouse.button {
+                record.flags |= HAS_BUTTON;
+                record.button = button.number();
+            }
+        }
+        if let Some(wheel) = &event.wheel {
+            record.flags |= HAS_WHEEL;
+            record.wheel_x = wheel.x;
+            record.wheel_y = wheel.y;
+            record.wheel_delta = wheel.delta;
+            record.direction = DIRECTIONS
+                .iter()
+                .position(|&d| d == wheel.direction)
+                .unwrap_or(0) as u8;
+            if let Some(pixels) = wheel.pixel_delta {
+                record.flags |= HAS_PIXEL_DELTA;
+                record.pixel_delta = pixels;
+            }
+            if let Some(phase) = wheel.phase {
+                record.flags |= HAS_PHASE;
+                record.phase = PHASES.iter().position(|&p| p == phase).unwrap_or(0) as u8;
+            }
+        }
+        if let Some(touch) = &event.touch {
+            record.flags |= HAS_TOUCH;
+            record.touch_id = touch.id;
+            record.touch_x = touch.x;
+            record.touch_y = touch.y;
+            if let Some(pressure) = touch.pressure {
+                record.flags |= HAS_PRESSURE;
+                record.pressure = pressure;
+            }
+        }
+        if let Some(display) = &event.display {
+            record.flags |= HAS_DISPLAY;
+            record.display_from = display.from;
+            record.display_to = display.to;
+        }
+        record
+    }
+
+    fn decode(&self) -> Event {
+        let since = Duration::from_nanos(self.time_nanos.unsigned_abs());
+        let time = if self.time_nanos >= 0 {
+            UNIX_EPOCH + since
+        } else {
+            UNIX_EPOCH - since
+        };
+        let has = |flag: u32| self.flags & flag != 0;
+        Event {
+            event_type: EVENT_TYPES
+                .get(self.event_type as usize)
+                .copied()
+                .unwrap_or(EventType::HookEnabled),
+            time,
+            mask: self.mask,
+            keyboard: has(HAS_KEYBOARD).then(|| KeyboardData {
+                key: key_from_wire(self.key_tag, self.key_payload),
+                raw_code: self.raw_code,
+                char: has(HAS_CHAR).then(|| char::from_u32(self.ch)).flatten(),
+                extended: has(EXTENDED),
+                ime_active: has(IME_KNOWN).then_some(has(IME_ACTIVE)),
+            }),
+            mouse: has(HAS_MOUSE).then(|| MouseData {
+                button: has(HAS_BUTTON).then(|| Button::from_number(self.button)),
+                x: self.mouse_x,
+                y: self.mouse_y,
+                clicks: self.clicks,
+            }),
+            wheel: has(HAS_WHEEL).then(|| WheelData {
+                x: self.wheel_x,
+                y: self.wheel_y,
+                direction: DIRECTIONS[self.direction as usize % DIRECTIONS.len()],
+                delta: self.wheel_delta,
+                pixel_delta: has(HAS_PIXEL_DELTA).then_some(self.pixel_delta),
+                phase: has(HAS_PHASE)
+                    .then(|| PHASES.get(self.phase as usize).copied())
+                    .flatten(),
+            }),
+            display: has(HAS_DISPLAY).then_some(DisplayChange {
+                from: self.display_from,
+                to: self.display_to,
+            }),
+            touch: has(HAS_TOUCH).then(|| TouchData {
+                id: self.touch_id,
+                x: self.touch_x,
+                y: self.touch_y,
+                pressure: has(HAS_PRESSURE).then_some(self.pressure),
+            }),
+            injected: has(INJECTED_KNOWN).then_some(has(INJECTED)),
+            target_pid: has(HAS_TARGET_PID).then_some(self.target_pid),
+            queue_latency: None,
+        }
+    }
+}
+
+/// The POSIX name for ring `name`.
+fn shm_name(name: &str) -> Result<CString> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN || name.contains('/') {
+        return Err(Error::Other(format!(
+            "invalid shared memory name {name:?}: use 1 to {MAX_NAME_LEN} bytes without '/'"
+        )));
+    }
+    CString::new(format!("/{name}"))
+        .map_err(|_| Error::Other(format!("invalid shared memory name {name:?}")))
+}
+
+fn os_error(what: &str, name: &str) -> Error {
+    Error::Platform(format!(
+        "{what} {name:?}: {}",
+        std::io::Error::last_os_error()
+    ))
+}
+
+fn shm_open(name: &CString, flags: libc::c_int) -> libc::c_int {
+    #[cfg(target_os = "macos")]
+    let mode = 0o600 as libc::c_uint;
+    #[cfg(not(target_os = "macos"))]
+    let mode = 0o600 as libc::mode_t;
+    unsafe { libc::shm_open(name.as_ptr(), flags, mode) }
+}
+
+/// A shared memory mapping, unmapped on drop.
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+// The mapping is only accessed through the header's atomics and the
+// ownership protocol of the ring.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn map(fd: libc::c_int, len: usize, name: &str) -> Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(os_error("failed to map shared memory", name));
+        }
+        Ok(Self {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*(self.ptr as *const Header) }
+    }
+
+    fn slot(&self, capacity: u64, index: u64) -> *mut Record {
+        let offset = HEADER_SIZE + (index & (capacity - 1)) as usize * RECORD_SIZE;
+        unsafe { self.ptr.add(offset).cast() }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.cast(), self.len) };
+    }
+}
+
+/// The writing end of a shared memory event ring.
+///
+/// Removes the ring's name when dropped; attached subscribers keep reading
+/// what is left and then see the ring closed.
+pub struct ShmWriter {
+    map: Mapping,
+    name: CString,
+    capacity: u64,
+    /// Sequence number of the next event, held while writing.
+    next_seq: Mutex<u64>,
+}
+
+impl ShmWriter {
+    /// Create ring `name` holding `capacity` events (rounded up to a power
+    /// of two), replacing any ring of that name.
+    pub fn create(name: &str, capacity: usize) -> Result<Self> {
+        let c_name = shm_name(name)?;
+        let capacity = capacity.max(2).next_power_of_two() as u64;
+        let len = HEADER_SIZE + capacity as usize * RECORD_SIZE;
+
+        unsafe { libc::shm_unlink(c_name.as_ptr()) };
+        let fd = shm_open(&c_name, libc::O_CREAT | libc::O_EXCL | libc::O_RDWR);
+        if fd < 0 {
+            return Err(os_error("failed to create shared memory", name));
+        }
+        let map = if unsafe { libc::ftruncate(fd, len as libc::off_t) } == 0 {
+            Mapping::map(fd, len, name)
+        } else {
+            Err(os_error("failed to size shared memory", name))
+        };
+        unsafe { libc::close(fd) };
+        let map = map.inspect_err(|_| unsafe {
+            libc::shm_unlink(c_name.as_ptr());
+        })?;
+
+        // The memory starts zeroed; the magic goes last so a subscriber
+        // never sees a half-written header
+        unsafe {
+            let header = map.ptr as *mut Header;
+            (*header).version = SHM_FORMAT_VERSION;
+            (*header).record_size = RECORD_SIZE as u32;
+            (*header).capacity = capacity;
+            (*header).publisher_pid = std::process::id();
+            std::sync::atomic::fence(Ordering::Release);
+            (*header).magic = MAGIC;
+        }
+
+        Ok(Self {
+            map,
+            name: c_name,
+            capacity,
+            next_seq: Mutex::new(0),
+        })
+    }
+
+    /// Append `event`, or count it as dropped if the subscriber is a full
+    /// ring behind (or none is attached and the ring is full).
+    pub fn write(&self, event: &Event) -> bool {
+        let mut next_seq = self.next_seq.lock().unwrap_or_else(|e| e.into_inner());
+        let seq = *next_seq;
+        *next_seq += 1;
+
+        let header = self.map.header();
+        let tail = header.tail.load(Ordering::Relaxed);
+        let head = header.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) >= self.capacity {
+            header.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        // The slot at `tail` is outside `head..tail`, so the subscriber
+        // won't read it until the store below publishes it.
+        unsafe {
+            self.map
+                .slot(self.capacity, tail)
+                .write(Record::encode(event, seq))
+        };
+        header.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Events dropped because the ring was full.
+    pub fn dropped_count(&self) -> u64 {
+        self.map.header().dropped.load(Ordering::Relaxed)
+    }
+
+    /// Whether a subscriber is attached.
+    pub fn has_subscriber(&self) -> bool {
+        self.map.header().subscribed.load(Ordering::Acquire) != 0
+    }
+}
+
+impl Drop for ShmWriter {
+    fn drop(&mut self) {
+        self.map.header().closed.store(1, Ordering::Release);
+        unsafe { libc::shm_unlink(self.name.as_ptr()) };
+    }
+}
+
+/// A hook publishing every event to a shared memory ring. Stops when
+/// dropped.
+pub struct ShmPublisher {
+    writer: Arc<ShmWriter>,
+    hook: Option<Hook>,
+}
+
+/// Publish every event to shared memory ring `name`, fed by a new hook.
+///
+/// `name` is 1 to 30 bytes without `/`; `capacity` is rounded up to a
+/// power of two.
+pub fn publish_events_shm(name: &str, capacity: usize) -> Result<ShmPublisher> {
+    let writer = Arc::new(ShmWriter::create(name, capacity)?);
+    let hook = Hook::new();
+    let hook_writer = writer.clone();
+    hook.run_async(move |event: &Event| {
+        hook_writer.write(event);
+    })?;
+    Ok(ShmPublisher {
+        writer,
+        hook: Some(hook),
+    })
+}
+
+impl ShmPublisher {
+    /// Events dropped because the subscriber fell behind.
+    pub fn dropped_count(&self) -> u64 {
+        self.writer.dropped_count()
+    }
+
+    /// Whether a subscriber is attached.
+    pub fn has_subscriber(&self) -> bool {
+        self.writer.has_subscriber()
+    }
+
+    /// Stop the hook and close the ring.
+    pub fn stop(mut self) -> Result<()> {
+        match self.hook.take() {
+            Some(hook) => hook.stop(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ShmPublisher {
+    fn drop(&mut self) {
+        if let Some(hook) = self.hook.take() {
+            let _ = hook.stop();
+        }
+    }
+}
+
+/// The reading end of a shared memory event ring.
+///
+/// Iterating blocks until the next event and ends once the publisher is
+/// gone and every event has been read.
+pub struct ShmSubscriber {
+    map: Mapping,
+    capacity: u64,
+    last_seq: Option<u64>,
+    lost: u64,
+}
+
+/// Attach to shared memory ring `name`, created by [`publish_events_shm`]
+/// or [`ShmWriter::create`].
+///
+/// Only events published after attaching are received. A ring takes one
+/// subscriber at a time.
+pub fn subscribe_events_shm(name: &str) -> Result<ShmSubscriber> {
+    let c_name = shm_name(name)?;
+    let fd = shm_open(&c_name, libc::O_RDWR);
+    if fd < 0 {
+        return Err(os_error("failed to open shared memory", name));
+    }
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    let map = if unsafe { libc::fstat(fd, &mut stat) } != 0 {
+        Err(os_error("failed to inspect shared memory", name))
+    } else if (stat.st_size as usize) < HEADER_SIZE {
+        Err(Error::Other(format!("{name:?} is not a monio event ring")))
+    } else {
+        Mapping::map(fd, stat.st_size as usize, name)
+    };
+    unsafe { libc::close(fd) };
+    let map = map?;
+
+    let header = map.header();
+    if header.magic != MAGIC {
+        return Err(Error::Other(format!("{name:?} is not a monio event ring")));
+    }
+    std::sync::atomic::fence(Ordering::Acquire);
+    if header.version != SHM_FORMAT_VERSION || header.record_size as usize != RECORD_SIZE {
+        return Err(Error::NotSupported(format!(
+            "event ring {name:?} has format version {}, expected {SHM_FORMAT_VERSION}",
+            header.version
+        )));
+    }
+    let capacity = header.capacity;
+    if !capacity.is_power_of_two() || (map.len - HEADER_SIZE) / RECORD_SIZE < capacity as usize {
+        return Err(Error::Other(format!("event ring {name:?} is corrupt")));
+    }
+    if header
+        .subscribed
+        .compare_exchange(0, 1, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        return Err(Error::Other(format!(
+            "event ring {name:?} already has a subscriber"
+        )));
+    }
+    // Skip whatever was written before we attached
+    header
+        .head
+        .store(header.tail.load(Ordering::Acquire), Ordering::Release);
+
+    Ok(ShmSubscriber {
+        map,
+        capacity,
+        last_seq: None,
+        lost: 0,
+    })
+}
+
+impl ShmSubscriber {
+    /// The next event and its sequence number, if one is waiting.
+    ///
+    /// Sequence numbers count every event the publisher saw; a jump means
+    /// events were dropped in between.
+    pub fn try_recv_with_sequence(&mut self) -> Option<(u64, Event)> {
+        let header = self.map.header();
+        let head = header.head.load(Ordering::Relaxed);
+        let tail = header.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        // Slots in `head..tail` were written by the publisher, which won't
+        // reuse them until `head` moves past them.
+        let record = unsafe { self.map.slot(self.capacity, head).read() };
+        header.head.store(head.wrapping_add(1), Ordering::Release);
+
+        if let Some(last) = self.last_seq {
+            self.lost += record.seq.saturating_sub(last + 1);
+        }
+        self.last_seq = Some(record.seq);
+        Some((record.seq, record.decode()))
+    }
+
+    /// The next event, if one is waiting.
+    pub fn try_recv(&mut self) -> Option<Event> {
+        self.try_recv_with_sequence().map(|(_, event)| event)
+    }
+
+    /// Wait up to `timeout` for the next event.
+    ///
+    /// Returns `None` on timeout, or early once the publisher is gone and
+    /// every event has been read.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Event> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // Check before reading, so events written just before closing
+            // aren't missed
+            let closed = self.is_closed();
+            if let Some(event) = self.try_recv() {
+                return Some(event);
+            }
+            if closed || Instant::now() >= deadline {
+                return None;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Events the publisher dropped since this subscriber attached, as told
+    /// by gaps in the sequence numbers.
+    pub fn lost_count(&self) -> u64 {
+        self.lost
+    }
+
+    /// Whether the publisher is gone.
+    pub fn is_closed(&self) -> bool {
+        self.map.header().closed.load(Ordering::Acquire) != 0
+    }
+}
+
+impl Iterator for ShmSubscriber {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        loop {
+            let closed = self.is_closed();
+            if let Some(event) = self.try_recv() {
+                return Some(event);
+            }
+            if closed {
+                return None;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for ShmSubscriber {
+    fn drop(&mut self) {
+        self.map.header().subscribed.store(0, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    /// Set for the child process of `test_across_processes`.
+    const CHILD_ENV: &str = "MONIO_SHM_TEST_RING";
+
+    fn unique_name(tag: &str) -> String {
+        format!("monio-{tag}-{}", std::process::id())
+    }
+
+    fn sample_events() -> Vec<Event> {
+        let mut typed = Event::key_typed(Key::Char('é'), 26, 'é');
+        typed.injected = Some(true);
+        typed.target_pid = Some(4242);
+        let mut touch = Event::new(EventType::TouchMoved);
+        touch.touch = Some(TouchData {
+            id: 3,
+            x: 10.5,
+            y: -2.0,
+            pressure: Some(0.5),
+        });
+        let mut display = Event::new(EventType::DisplayChanged);
+        display.display = Some(DisplayChange { from: 1, to: 2 });
+        let mut pan = Event::mouse_wheel(3.0, 4.0, ScrollDirection::Up, 0.5);
+        if let Some(wheel) = &mut pan.wheel {
+            wheel.pixel_delta = Some(12.5);
+            wheel.phase = Some(ScrollPhase::Changed);
+        }
+        let mut before_epoch = Event::key_released(Key::Unknown(999), 999);
+        before_epoch.injected = Some(false);
+        let mut composing = Event::key_pressed(Key::KeyK, 37);
+        composing.keyboard.as_mut().unwrap().ime_active = Some(true);
+        let mut events = vec![
+            Event::hook_enabled(),
+            Event::key_pressed(Key::KeyA, 30),
+            typed,
+            Event::mouse_pressed(Button::Unknown(9), 1.5, 2.5),
+            Event::mouse_dragged(100.0, 200.0),
+            Event::mouse_wheel(3.0, 4.0, ScrollDirection::Left, 1.25),
+            pan,
+            touch,
+            display,
+            composing,
+            before_epoch,
+        ];
+        // The same in every process
+        for (i, event) in events.iter_mut().enumerate() {
+            event.time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_000 + i as u64);
+            event.mask = i as u32;
+        }
+        events.last_mut().unwrap().time = UNIX_EPOCH - Duration::from_secs(5);
+        events
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        for event in sample_events() {
+            assert_eq!(Record::encode(&event, 7).decode(), event);
+        }
+        for &event_type in &EVENT_TYPES {
+            let event = Event::new(event_type);
+            assert_eq!(Record::encode(&event, 0).decode(), event);
+        }
+        assert_eq!(key_tag(&Key::Unknown(0)), char_tag() + 1);
+        for ch in ' '..='~' {
+            let key = Key::from_char(ch);
+            let (tag, payload) = key_to_wire(key);
+            assert_eq!(key_from_wire(tag, payload), key);
+        }
+        for key in [
+            Key::F24,
+            Key::ContextMenu,
+            Key::ShiftRight,
+            Key::NumpadEqual,
+        ] {
+            let (tag, payload) = key_to_wire(key);
+            assert_eq!(key_from_wire(tag, payload), key);
+        }
+    }
+
+    #[test]
+    fn test_ring_drops_when_full_and_reports_gaps() {
+        let name = unique_name("full");
+        let writer = ShmWriter::create(&name, 4).unwrap();
+        let mut subscriber = subscribe_events_shm(&name).unwrap();
+        assert!(writer.has_subscriber());
+        assert!(subscribe_events_shm(&name).is_err());
+        assert!(subscriber.try_recv().is_none());
+
+        for i in 0..6 {
+            writer.write(&Event::mouse_moved(i as f64, 0.0));
+        }
+        assert_eq!(writer.dropped_count(), 2);
+        let seqs: Vec<u64> = std::iter::from_fn(|| subscriber.try_recv_with_sequence())
+            .map(|(seq, _)| seq)
+            .collect();
+        assert_eq!(seqs, [0, 1, 2, 3]);
+
+        writer.write(&Event::mouse_moved(6.0, 0.0));
+        assert_eq!(subscriber.try_recv_with_sequence().unwrap().0, 6);
+        assert_eq!(subscriber.lost_count(), 2);
+
+        drop(writer);
+        assert!(subscriber.is_closed());
+        assert_eq!(subscriber.next(), None);
+        assert!(subscribe_events_shm(&name).is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_names() {
+        assert!(ShmWriter::create("", 4).is_err());
+        assert!(ShmWriter::create("a/b", 4).is_err());
+        assert!(ShmWriter::create(&"x".repeat(31), 4).is_err());
+        assert!(subscribe_events_shm("monio-does-not-exist").is_err());
+    }
+
+    #[test]
+    fn test_across_processes() {
+        let name = unique_name("ipc");
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "ipc::tests::publish_from_child", "--ignored"])
+            .env(CHILD_ENV, &name)
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let subscriber = loop {
+            match subscribe_events_shm(&name) {
+                Ok(subscriber) => break subscriber,
+                Err(_) if Instant::now() < deadline => std::thread::sleep(POLL_INTERVAL),
+                Err(e) => panic!("child never created the ring: {e}"),
+            }
+        };
+        let received: Vec<Event> = subscriber.collect();
+        assert!(child.wait().unwrap().success());
+        assert_eq!(received, sample_events());
+    }
+
+    /// The publishing side of `test_across_processes`.
+    #[test]
+    #[ignore = "run by test_across_processes"]
+    fn publish_from_child() {
+        let Ok(name) = std::env::var(CHILD_ENV) else {
+            return;
+        };
+        let writer = ShmWriter::create(&name, 16).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !writer.has_subscriber() {
+            assert!(Instant::now() < deadline, "no subscriber attached");
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        for event in sample_events() {
+            assert!(writer.write(&event));
+        }
+    }
+}
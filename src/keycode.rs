@@ -1,333 +1,186 @@
 //! Virtual key code definitions.
 
-#[cfg(feature = "recorder")]
-use serde::{Deserialize, Serialize};
-
-/// Virtual key codes for keyboard keys.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[repr(u16)]
-#[cfg_attr(feature = "recorder", derive(Serialize, Deserialize))]
-pub enum Key {
-    // Letters
-    KeyA,
-    KeyB,
-    KeyC,
-    KeyD,
-    KeyE,
-    KeyF,
-    KeyG,
-    KeyH,
-    KeyI,
-    KeyJ,
-    KeyK,
-    KeyL,
-    KeyM,
-    KeyN,
-    KeyO,
-    KeyP,
-    KeyQ,
-    KeyR,
-    KeyS,
-    KeyT,
-    KeyU,
-    KeyV,
-    KeyW,
-    KeyX,
-    KeyY,
-    KeyZ,
-
-    // Numbers (top row)
-    Num0,
-    Num1,
-    Num2,
-    Num3,
-    Num4,
-    Num5,
-    Num6,
-    Num7,
-    Num8,
-    Num9,
-
-    // Function keys
-    F1,
-    F2,
-    F3,
-    F4,
-    F5,
-    F6,
-    F7,
-    F8,
-    F9,
-    F10,
-    F11,
-    F12,
-    F13,
-    F14,
-    F15,
-    F16,
-    F17,
-    F18,
-    F19,
-    F20,
-    F21,
-    F22,
-    F23,
-    F24,
-
-    // Modifiers
-    ShiftLeft,
-    ShiftRight,
-    ControlLeft,
-    ControlRight,
-    AltLeft,
-    AltRight,
-    MetaLeft, // Windows/Command/Super
-    MetaRight,
+pub use monio_core::{Key, ParseKeyError};
+
+/// US-layout characters typed with Shift, and the character of their key.
+const SHIFTED: [(char, char); 21] = [
+    ('~', '`'),
+    ('!', '1'),
+    ('@', '2'),
+    ('#', '3'),
+    ('$', '4'),
+    ('%', '5'),
+    ('^', '6'),
+    ('&', '7'),
+    ('*', '8'),
+    ('(', '9'),
+    (')', '0'),
+    ('_', '-'),
+    ('+', '='),
+    ('{', '['),
+    ('}', ']'),
+    ('|', '\\'),
+    (':', ';'),
+    ('"', '\''),
+    ('<', ','),
+    ('>', '.'),
+    ('?', '/'),
+];
+
+/// The key typing `ch` on a US layout, and whether it takes Shift.
+/// Newlines and tabs map to Enter and Tab.
+pub(crate) fn us_keystroke(ch: char) -> (Key, bool) {
+    match ch {
+        '\n' | '\r' => (Key::Enter, false),
+        '\t' => (Key::Tab, false),
+        _ if ch.is_ascii_uppercase() => (Key::from_char(ch), true),
+        _ => match SHIFTED.iter().find(|(shifted, _)| *shifted == ch) {
+            Some((_, base)) => (Key::from_char(*base), true),
+            None => (Key::from_char(ch), false),
+        },
+    }
+}
 
-    // Navigation
-    Escape,
-    Tab,
-    CapsLock,
-    Space,
-    Enter,
-    Backspace,
-    Insert,
-    Delete,
-    Home,
-    End,
-    PageUp,
-    PageDown,
-    ArrowUp,
-    ArrowDown,
-    ArrowLeft,
-    ArrowRight,
+/// How a hotkey's [`Key`] is matched against key presses.
+///
+/// The two differ on non-US layouts. On German QWERTZ, where Y and Z are
+/// swapped, `Ctrl+Z` in [`Logical`](Self::Logical) mode fires for the key
+/// labelled Z, while in [`Physical`](Self::Physical) mode it fires for the
+/// key where Z is on a US keyboard (labelled Y).
+///
+/// Logical matching is what users expect from shortcuts like `Ctrl+Z`;
+/// physical matching suits position-based bindings such as WASD movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HotkeyMatchMode {
+    /// Match the key as labelled on the active layout.
+    #[default]
+    Logical,
+    /// Match the key at a position, named after the US layout.
+    Physical,
+}
 
-    // Lock keys
-    NumLock,
-    ScrollLock,
-    PrintScreen,
-    Pause,
+/// Resolves raw keycodes on a keyboard layout.
+pub(crate) trait KeyLayout {
+    fn physical_key(&self, raw_code: u32, key: Key) -> Key;
+    fn logical_key(&self, raw_code: u32, key: Key) -> Key;
+}
 
-    // Punctuation and symbols
-    Grave,        // ` ~
-    Minus,        // - _
-    Equal,        // = +
-    BracketLeft,  // [ {
-    BracketRight, // ] }
-    Backslash,    // \ |
-    Semicolon,    // ; :
-    Quote,        // ' "
-    Comma,        // , <
-    Period,       // . >
-    Slash,        // / ?
+/// The layout the platform is currently using.
+struct ActiveLayout;
 
-    // Numpad
-    Numpad0,
-    Numpad1,
-    Numpad2,
-    Numpad3,
-    Numpad4,
-    Numpad5,
-    Numpad6,
-    Numpad7,
-    Numpad8,
-    Numpad9,
-    NumpadAdd,
-    NumpadSubtract,
-    NumpadMultiply,
-    NumpadDivide,
-    NumpadDecimal,
-    NumpadEnter,
-    NumpadEqual,
+impl KeyLayout for ActiveLayout {
+    fn physical_key(&self, raw_code: u32, key: Key) -> Key {
+        crate::platform::physical_key(raw_code, key)
+    }
 
-    // Media keys
-    VolumeUp,
-    VolumeDown,
-    VolumeMute,
-    MediaPlayPause,
-    MediaStop,
-    MediaNext,
-    MediaPrevious,
+    fn logical_key(&self, raw_code: u32, key: Key) -> Key {
+        crate::platform::logical_key(raw_code, key)
+    }
+}
 
-    // Browser keys
-    BrowserBack,
-    BrowserForward,
-    BrowserRefresh,
-    BrowserStop,
-    BrowserSearch,
-    BrowserFavorites,
-    BrowserHome,
+impl HotkeyMatchMode {
+    /// The key a key event is matched as in this mode.
+    pub fn key_of(self, keyboard: &crate::event::KeyboardData) -> Key {
+        self.key_on(keyboard, &ActiveLayout)
+    }
 
-    // Application keys
-    LaunchMail,
-    LaunchApp1,
-    LaunchApp2,
+    /// Whether a key event matches `key` in this mode.
+    pub fn matches(self, key: Key, keyboard: &crate::event::KeyboardData) -> bool {
+        self.key_of(keyboard) == key
+    }
 
-    // International / special
-    IntlBackslash,
-    IntlYen,
-    IntlRo,
+    fn key_on(self, keyboard: &crate::event::KeyboardData, layout: &impl KeyLayout) -> Key {
+        match self {
+            HotkeyMatchMode::Logical => layout.logical_key(keyboard.raw_code, keyboard.key),
+            HotkeyMatchMode::Physical => layout.physical_key(keyboard.raw_code, keyboard.key),
+        }
+    }
+}
 
-    // Context menu
-    ContextMenu,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::KeyboardData;
 
-    // Unknown key with raw code
-    Unknown(u32),
-}
+    /// Keycodes of the keys in the US Y and Z positions (macOS numbering).
+    const CODE_Y: u32 = 16;
+    const CODE_Z: u32 = 6;
+    const CODE_ESCAPE: u32 = 53;
 
-impl Key {
-    /// Check if this is a modifier key.
-    pub fn is_modifier(&self) -> bool {
-        matches!(
-            self,
-            Key::ShiftLeft
-                | Key::ShiftRight
-                | Key::ControlLeft
-                | Key::ControlRight
-                | Key::AltLeft
-                | Key::AltRight
-                | Key::MetaLeft
-                | Key::MetaRight
-        )
+    /// Positions as on a US keyboard; `labels` is what each position types.
+    struct MockLayout {
+        labels: &'static [(u32, Key)],
     }
 
-    /// Check if this is a letter key.
-    pub fn is_letter(&self) -> bool {
-        matches!(
-            self,
-            Key::KeyA
-                | Key::KeyB
-                | Key::KeyC
-                | Key::KeyD
-                | Key::KeyE
-                | Key::KeyF
-                | Key::KeyG
-                | Key::KeyH
-                | Key::KeyI
-                | Key::KeyJ
-                | Key::KeyK
-                | Key::KeyL
-                | Key::KeyM
-                | Key::KeyN
-                | Key::KeyO
-                | Key::KeyP
-                | Key::KeyQ
-                | Key::KeyR
-                | Key::KeyS
-                | Key::KeyT
-                | Key::KeyU
-                | Key::KeyV
-                | Key::KeyW
-                | Key::KeyX
-                | Key::KeyY
-                | Key::KeyZ
-        )
+    const POSITIONS: &[(u32, Key)] = &[
+        (CODE_Y, Key::KeyY),
+        (CODE_Z, Key::KeyZ),
+        (CODE_ESCAPE, Key::Escape),
+    ];
+    const QWERTY: MockLayout = MockLayout { labels: POSITIONS };
+    const QWERTZ: MockLayout = MockLayout {
+        labels: &[
+            (CODE_Y, Key::KeyZ),
+            (CODE_Z, Key::KeyY),
+            (CODE_ESCAPE, Key::Escape),
+        ],
+    };
+
+    fn lookup(table: &[(u32, Key)], code: u32, key: Key) -> Key {
+        table
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map_or(key, |(_, k)| *k)
     }
 
-    /// Check if this is a number key (top row).
-    pub fn is_number(&self) -> bool {
-        matches!(
-            self,
-            Key::Num0
-                | Key::Num1
-                | Key::Num2
-                | Key::Num3
-                | Key::Num4
-                | Key::Num5
-                | Key::Num6
-                | Key::Num7
-                | Key::Num8
-                | Key::Num9
-        )
-    }
+    impl KeyLayout for MockLayout {
+        fn physical_key(&self, raw_code: u32, key: Key) -> Key {
+            lookup(POSITIONS, raw_code, key)
+        }
 
-    /// Check if this is a function key.
-    pub fn is_function_key(&self) -> bool {
-        matches!(
-            self,
-            Key::F1
-                | Key::F2
-                | Key::F3
-                | Key::F4
-                | Key::F5
-                | Key::F6
-                | Key::F7
-                | Key::F8
-                | Key::F9
-                | Key::F10
-                | Key::F11
-                | Key::F12
-                | Key::F13
-                | Key::F14
-                | Key::F15
-                | Key::F16
-                | Key::F17
-                | Key::F18
-                | Key::F19
-                | Key::F20
-                | Key::F21
-                | Key::F22
-                | Key::F23
-                | Key::F24
-        )
+        fn logical_key(&self, raw_code: u32, key: Key) -> Key {
+            lookup(self.labels, raw_code, key)
+        }
     }
 
-    /// Check if this is a numpad key.
-    pub fn is_numpad(&self) -> bool {
-        matches!(
-            self,
-            Key::Numpad0
-                | Key::Numpad1
-                | Key::Numpad2
-                | Key::Numpad3
-                | Key::Numpad4
-                | Key::Numpad5
-                | Key::Numpad6
-                | Key::Numpad7
-                | Key::Numpad8
-                | Key::Numpad9
-                | Key::NumpadAdd
-                | Key::NumpadSubtract
-                | Key::NumpadMultiply
-                | Key::NumpadDivide
-                | Key::NumpadDecimal
-                | Key::NumpadEnter
-                | Key::NumpadEqual
-        )
+    /// A key press as reported by a backend that names keys by position.
+    fn press(code: u32) -> KeyboardData {
+        KeyboardData {
+            key: lookup(POSITIONS, code, Key::Unknown(code)),
+            raw_code: code,
+            char: None,
+            extended: false,
+            ime_active: None,
+        }
     }
 
-    /// Check if this is a media key.
-    pub fn is_media(&self) -> bool {
-        matches!(
-            self,
-            Key::VolumeUp
-                | Key::VolumeDown
-                | Key::VolumeMute
-                | Key::MediaPlayPause
-                | Key::MediaStop
-                | Key::MediaNext
-                | Key::MediaPrevious
-        )
+    #[test]
+    fn test_modes_agree_on_qwerty() {
+        for mode in [HotkeyMatchMode::Logical, HotkeyMatchMode::Physical] {
+            assert_eq!(mode.key_on(&press(CODE_Z), &QWERTY), Key::KeyZ);
+            assert_eq!(mode.key_on(&press(CODE_Y), &QWERTY), Key::KeyY);
+        }
     }
 
-    /// Check if this is a navigation key.
-    pub fn is_navigation(&self) -> bool {
-        matches!(
-            self,
-            Key::ArrowUp
-                | Key::ArrowDown
-                | Key::ArrowLeft
-                | Key::ArrowRight
-                | Key::Home
-                | Key::End
-                | Key::PageUp
-                | Key::PageDown
-        )
+    #[test]
+    fn test_modes_differ_on_qwertz() {
+        // The key labelled Z sits where Y is on a US keyboard
+        let logical = HotkeyMatchMode::Logical;
+        assert_eq!(logical.key_on(&press(CODE_Y), &QWERTZ), Key::KeyZ);
+        assert_eq!(logical.key_on(&press(CODE_Z), &QWERTZ), Key::KeyY);
+
+        let physical = HotkeyMatchMode::Physical;
+        assert_eq!(physical.key_on(&press(CODE_Z), &QWERTZ), Key::KeyZ);
+        assert_eq!(physical.key_on(&press(CODE_Y), &QWERTZ), Key::KeyY);
+
+        // Keys that don't move are the same either way
+        for mode in [logical, physical] {
+            assert_eq!(mode.key_on(&press(CODE_ESCAPE), &QWERTZ), Key::Escape);
+        }
     }
-}
 
-impl Default for Key {
-    fn default() -> Self {
-        Key::Unknown(0)
+    #[test]
+    fn test_default_mode_is_logical() {
+        assert_eq!(HotkeyMatchMode::default(), HotkeyMatchMode::Logical);
     }
 }
@@ -0,0 +1,155 @@
+//! Delivery latency of events, see [`Event::queue_latency`].
+//!
+//! Latency is measured from the event's native timestamp, so it is only
+//! known where the backend timestamps events itself (Windows). Elsewhere
+//! events are timestamped when monio sees them and latency stays `None`.
+//!
+//! Hooks keep the latency of recent events: [`Hook::queue_latency_p99`]
+//! reports the 99th percentile, and [`Hook::latency_warning`] logs a warning
+//! when an event arrives later than a threshold. Slow delivery is also an
+//! early sign of a handler that's about to get the macOS event tap disabled.
+//!
+//! [`Hook::queue_latency_p99`]: crate::Hook::queue_latency_p99
+//! [`Hook::latency_warning`]: crate::Hook::latency_warning
+
+use crate::event::Event;
+use crate::platform;
+use std::borrow::Cow;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How many recent latencies the percentile is taken over.
+const SAMPLES: usize = 1024;
+
+/// Minimum time between two latency warnings.
+const WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Latency of an event stamped `time`, observed at `now`.
+pub(crate) fn latency(time: SystemTime, now: SystemTime) -> Option<Duration> {
+    platform::NATIVE_TIMESTAMPS.then(|| now.duration_since(time).unwrap_or_default())
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    recent: Vec<Duration>,
+    /// Where the next sample goes once `recent` is full.
+    next: usize,
+    warn_above: Option<Duration>,
+    last_warning: Option<Instant>,
+}
+
+/// Recent latencies of a hook's events.
+#[derive(Debug, Default)]
+pub(crate) struct LatencyMonitor {
+    samples: Mutex<Samples>,
+}
+
+impl LatencyMonitor {
+    pub(crate) fn set_warning(&self, threshold: Option<Duration>) {
+        if let Ok(mut samples) = self.samples.lock() {
+            samples.warn_above = threshold;
+        }
+    }
+
+    /// `event` with its [`queue_latency`](Event::queue_latency) set, if known.
+    pub(crate) fn stamp<'a>(&self, event: &'a Event) -> Cow<'a, Event> {
+        let Some(latency) = latency(event.time, SystemTime::now()) else {
+            return Cow::Borrowed(event);
+        };
+        self.record(latency, Instant::now());
+        let mut event = event.clone();
+        event.queue_latency = Some(latency);
+        Cow::Owned(event)
+    }
+
+    /// Add a sample, warning if it's over the threshold. Returns whether it
+    /// warned.
+    fn record(&self, latency: Duration, now: Instant) -> bool {
+        let Ok(mut samples) = self.samples.lock() else {
+            return false;
+        };
+        if samples.recent.len() < SAMPLES {
+            samples.recent.push(latency);
+        } else {
+            let next = samples.next;
+            samples.recent[next] = latency;
+            samples.next = (next + 1) % SAMPLES;
+        }
+
+        let over = samples.warn_above.is_some_and(|limit| latency > limit);
+        let due = samples
+            .last_warning
+            .is_none_or(|last| now.duration_since(last) >= WARNING_INTERVAL);
+        if !(over && due) {
+            return false;
+        }
+        samples.last_warning = Some(now);
+        log::warn!(
+            "Event reached the handler {:?} after it happened; the hook thread may be \
+             falling behind",
+            latency
+        );
+        true
+    }
+
+    /// 99th percentile of the recent latencies.
+    pub(crate) fn p99(&self) -> Option<Duration> {
+        let mut recent = self.samples.lock().ok()?.recent.clone();
+        if recent.is_empty() {
+            return None;
+        }
+        recent.sort_unstable();
+        let rank = (recent.len() * 99).div_ceil(100);
+        Some(recent[rank - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_latency_follows_native_timestamps() {
+        let now = SystemTime::now();
+        let expected = platform::NATIVE_TIMESTAMPS.then_some(ms(5));
+        assert_eq!(latency(now - ms(5), now), expected);
+        // A timestamp from the future doesn't underflow
+        let expected = platform::NATIVE_TIMESTAMPS.then_some(Duration::ZERO);
+        assert_eq!(latency(now + ms(5), now), expected);
+    }
+
+    #[test]
+    fn test_p99() {
+        let monitor = LatencyMonitor::default();
+        assert_eq!(monitor.p99(), None);
+
+        let now = Instant::now();
+        for i in 1..=100 {
+            monitor.record(ms(i), now);
+        }
+        assert_eq!(monitor.p99(), Some(ms(99)));
+
+        // Only recent samples count
+        for _ in 0..SAMPLES {
+            monitor.record(ms(1), now);
+        }
+        assert_eq!(monitor.p99(), Some(ms(1)));
+    }
+
+    #[test]
+    fn test_warnings_are_rate_limited() {
+        let monitor = LatencyMonitor::default();
+        let now = Instant::now();
+        assert!(!monitor.record(ms(500), now));
+
+        monitor.set_warning(Some(ms(100)));
+        assert!(!monitor.record(ms(100), now));
+        assert!(monitor.record(ms(101), now));
+        assert!(!monitor.record(ms(200), now + ms(500)));
+        assert!(monitor.record(ms(200), now + ms(1000)));
+    }
+}
@@ -61,35 +61,118 @@
 //! of drag events - when a mouse move occurs while a button is held, we emit
 //! `MouseDragged` instead of `MouseMoved`.
 
+pub mod activity;
+pub mod calibration;
+pub mod capabilities;
 pub mod channel;
+pub mod chord;
+pub mod confine;
+pub mod cursor;
+pub mod debug;
+pub mod demo;
 pub mod display;
 pub mod error;
 pub mod event;
+#[cfg(feature = "failsafe")]
+pub mod failsafe;
+pub mod feedback;
+pub mod filter;
+pub mod format;
+#[cfg(feature = "hid")]
+pub mod hid;
+pub mod hold;
 pub mod hook;
+pub mod ime;
+pub mod intercept;
+#[cfg(all(feature = "ipc", unix))]
+pub mod ipc;
 pub mod keycode;
+pub mod lockdown;
+pub mod media;
+pub mod ordering;
+#[cfg(feature = "overlay")]
+pub mod overlay;
+pub mod permissions;
+pub mod policy;
+pub mod poll;
+pub mod presence;
 #[cfg(feature = "recorder")]
 pub mod recorder;
+pub mod relative;
+pub mod rollover;
+#[cfg(any(feature = "statistics", feature = "recorder", feature = "serde"))]
+pub mod session;
+pub mod shutdown;
+pub mod simulation;
+pub mod snapshot;
 pub mod state;
 #[cfg(feature = "statistics")]
 pub mod statistics;
+pub mod subscribe;
+pub mod suspend;
+pub mod tap;
+#[cfg(all(feature = "test-util", target_os = "linux", feature = "evdev"))]
+pub mod testsupport;
+pub mod timeout;
+pub mod tracking;
+pub mod transform;
+pub mod watchdog;
 
+mod convert;
+mod drag;
+mod fanout;
+mod latency;
 mod platform;
+mod source;
 
 // Re-exports
+pub use activity::{ActivityHandle, ActivityKind, ActivityStats, on_activity};
+pub use capabilities::{Capabilities, capabilities};
+pub use chord::MouseChord;
+pub use confine::{ConfineGuard, confine_cursor};
+pub use cursor::{HiddenCursor, hide_cursor, warp_cursor};
 pub use display::{
-    DisplayInfo, Rect, SystemSettings, display_at_point, displays, primary_display, system_settings,
+    DisplayInfo, DisplaySource, Rect, SystemSettings, display_at_point, displays, primary_display,
+    system_settings,
 };
 pub use error::{Error, Result};
-pub use event::{Button, Event, EventType, KeyboardData, MouseData, ScrollDirection, WheelData};
-pub use hook::{EventHandler, GrabHandler, Hook, grab, listen};
-pub use keycode::Key;
+pub use event::{
+    Backend, Button, DisplayChange, Event, EventType, FORMAT_VERSION, KeyboardData, MouseData,
+    ScrollDirection, ScrollPhase, TouchData, WheelData,
+};
+pub use filter::{EventFilter, FilterError};
+pub use hold::{HeldEvents, HoldHandler, HoldRelease, HoldToken, Verdict};
+pub use hook::{EventHandler, EventMask, GrabHandler, Hook, grab, listen};
+pub use intercept::{GrabCapabilities, GrabOptions};
+pub use keycode::{HotkeyMatchMode, Key, ParseKeyError};
+pub use poll::{InputState, PolledHook};
 #[cfg(feature = "recorder")]
-pub use recorder::{EventRecorder, RecordedEvent, Recording};
+pub use recorder::{
+    DiffTolerance, EventRecorder, FlightRecorder, RecordedEvent, Recording, RecordingDiff,
+    RecordingMetadata, RepeatedSegment,
+};
+pub use relative::{
+    MouseDelta, RelativeModeGuard, relative_pointer_channel, relative_pointer_mode,
+};
+pub use shutdown::ShutdownToken;
+pub use snapshot::{ButtonStates, InputSnapshot, LockStates, input_snapshot};
+pub use state::Modifiers;
 #[cfg(feature = "statistics")]
 pub use statistics::{EventStatistics, StatisticsCollector};
+pub use subscribe::Subscription;
+pub use suspend::{is_suspended, resume_all, suspend_all, suspended_until};
 
 // Simulation functions
-pub use platform::{
-    key_press, key_release, key_tap, mouse_click, mouse_move, mouse_position, mouse_press,
-    mouse_release, simulate,
+pub use platform::mouse_position;
+pub use simulation::{
+    key_press, key_release, key_tap, mouse_click, mouse_move, mouse_press, mouse_release, simulate,
+    simulate_batch,
 };
+
+/// Names the process behind [`Event::target_pid`].
+#[cfg(all(target_os = "macos", feature = "macos-targeted"))]
+pub use platform::process_name;
+
+/// Evdev backend diagnostics.
+#[cfg(all(target_os = "linux", feature = "evdev", not(feature = "x11")))]
+pub use platform::{GrabbedDevice, dropped_frame_count, force_ungrab_all, grab_status};
@@ -0,0 +1,510 @@
+//! Blocking all input until an unlock chord, for focus and parental-control
+//! apps.
+//!
+//! [`engage`] starts a grab hook that consumes every key, button, wheel and
+//! touch event, watching only for the unlock chord, until the chord is
+//! pressed, the lockdown expires or the [`LockdownGuard`] is dropped. monio
+//! draws nothing meanwhile; show your own overlay while the guard is
+//! engaged.
+//!
+//! The lockdown fails closed: where input can't be blocked, [`engage`]
+//! returns an error instead of a lockdown that only pretends. That is the
+//! case on X11, whose hooks can only listen, and where grabbing is denied by
+//! [`policy`](crate::policy). If blocking stops working later, because the
+//! grab was released from outside, the lockdown ends with
+//! [`LockdownEnd::GrabLost`] so the app can tell.
+//!
+//! The lockdown keeps blocking while hooks are suspended with
+//! [`suspend_all`](crate::suspend_all). It never stands in the way of the
+//! `failsafe` module's emergency chord (with the `failsafe` feature), which
+//! releases the grab without going through the lockdown's handler; the
+//! lockdown then ends with [`LockdownEnd::Failsafe`].
+//!
+//! During a [`synthetic_session`](crate::demo::synthetic_session) the
+//! lockdown runs against the script, so an app's `--demo` mode works;
+//! nothing is blocked then.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::lockdown::{self, LockdownOptions};
+//! use monio::Key;
+//! use std::time::Duration;
+//!
+//! // Block input for up to 25 minutes, or until Ctrl+Alt+U
+//! let guard = lockdown::engage(
+//!     &[Key::ControlLeft, Key::AltLeft, Key::KeyU],
+//!     LockdownOptions::new().max_duration(Duration::from_secs(25 * 60)),
+//! )?;
+//! println!("lockdown ended: {:?}", guard.wait());
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::demo;
+use crate::error::{Error, Result};
+use crate::event::{Event, EventType};
+use crate::hook::{GrabHandler, Hook};
+use crate::keycode::{HotkeyMatchMode, Key};
+use crate::platform;
+use crate::policy::{self, Policy};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long [`engage`] waits for the grab to take effect.
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the lockdown checks whether it expired or lost its grab.
+const POLL: Duration = Duration::from_millis(50);
+
+/// Options for [`engage`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LockdownOptions {
+    max_duration: Option<Duration>,
+    match_mode: HotkeyMatchMode,
+}
+
+impl LockdownOptions {
+    /// No time limit, with the unlock chord matched by logical key.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// End the lockdown after `duration`, unlocked or not.
+    pub fn max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+
+    /// How the keys of the unlock chord are matched, see [`HotkeyMatchMode`].
+    pub fn hotkey_match_mode(mut self, mode: HotkeyMatchMode) -> Self {
+        self.match_mode = mode;
+        self
+    }
+}
+
+/// Why a lockdown ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockdownEnd {
+    /// The unlock chord was pressed.
+    Unlocked,
+    /// [`LockdownOptions::max_duration`] ran out.
+    Expired,
+    /// The `failsafe` module released the grab.
+    Failsafe,
+    /// Input stopped being blocked for another reason: the hook stopped or
+    /// the grab was released from outside, e.g. with
+    /// [`force_ungrab_all`](crate::force_ungrab_all) on evdev.
+    GrabLost,
+    /// The guard was disengaged or dropped.
+    Disengaged,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Whether the grab took effect.
+    ready: bool,
+    end: Option<LockdownEnd>,
+    /// Why the hook couldn't start.
+    failure: Option<Error>,
+    /// Which keys of the unlock chord are held.
+    held: Vec<bool>,
+}
+
+/// The lockdown's grab handler and its state.
+#[derive(Debug)]
+struct Lock {
+    unlock: Vec<Key>,
+    match_mode: HotkeyMatchMode,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Lock {
+    fn new(unlock: &[Key], match_mode: HotkeyMatchMode) -> Self {
+        Self {
+            unlock: unlock.to_vec(),
+            match_mode,
+            state: Mutex::new(State {
+                held: vec![false; unlock.len()],
+                ..State::default()
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// End the lockdown for `why`, unless it already ended.
+    fn finish(&self, why: LockdownEnd) {
+        let mut state = self.state();
+        if state.end.is_none() {
+            state.end = Some(why);
+            self.changed.notify_all();
+        }
+    }
+
+    fn ended(&self) -> Option<LockdownEnd> {
+        self.state().end
+    }
+
+    fn wait(&self) -> LockdownEnd {
+        let mut state = self.state();
+        loop {
+            if let Some(end) = state.end {
+                return end;
+            }
+            state = self.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Wait until the grab took effect or the hook failed.
+    fn wait_ready(&self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state();
+        loop {
+            if let Some(e) = state.failure.take() {
+                return Err(e);
+            }
+            if state.ready {
+                return Ok(());
+            }
+            if state.end.is_some() {
+                return Err(Error::HookStartFailed(
+                    "the lockdown hook stopped before it started".into(),
+                ));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::HookStartFailed(
+                    "the lockdown hook did not start in time".into(),
+                ));
+            }
+            state = self
+                .changed
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Track the unlock chord.
+    fn observe_key(&self, event: &Event) {
+        let Some(kb) = &event.keyboard else {
+            return;
+        };
+        let pressed = match event.event_type {
+            EventType::KeyPressed => true,
+            EventType::KeyReleased => false,
+            _ => return,
+        };
+        let mut state = self.state();
+        for (held, &key) in state.held.iter_mut().zip(&self.unlock) {
+            if self.match_mode.matches(key, kb) {
+                *held = pressed;
+            }
+        }
+        if pressed && state.held.iter().all(|&held| held) && state.end.is_none() {
+            state.end = Some(LockdownEnd::Unlocked);
+            self.changed.notify_all();
+        }
+    }
+}
+
+/// Grab handler of a lockdown.
+struct Blocker(Arc<Lock>);
+
+impl GrabHandler for Blocker {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        match event.event_type {
+            EventType::HookEnabled => {
+                self.0.state().ready = true;
+                self.0.changed.notify_all();
+                Some(event.clone())
+            }
+            EventType::GrabSuspended => {
+                self.0.finish(LockdownEnd::GrabLost);
+                Some(event.clone())
+            }
+            EventType::HookDisabled
+            | EventType::GrabDegraded
+            | EventType::GlobalSuspendChanged
+            | EventType::DisplayChanged => Some(event.clone()),
+            _ => {
+                self.0.observe_key(event);
+                None
+            }
+        }
+    }
+}
+
+/// An engaged lockdown, disengaged when dropped.
+///
+/// Created by [`engage`].
+#[derive(Debug)]
+pub struct LockdownGuard {
+    lock: Arc<Lock>,
+    supervisor: Option<JoinHandle<()>>,
+}
+
+impl LockdownGuard {
+    /// Whether input is still blocked.
+    pub fn is_engaged(&self) -> bool {
+        self.lock.ended().is_none()
+    }
+
+    /// Why the lockdown ended, or `None` while it's engaged.
+    pub fn ended(&self) -> Option<LockdownEnd> {
+        self.lock.ended()
+    }
+
+    /// Block until the lockdown ends, and return why.
+    pub fn wait(&self) -> LockdownEnd {
+        self.lock.wait()
+    }
+
+    /// Stop blocking input.
+    pub fn disengage(mut self) {
+        self.disengage_inner();
+    }
+
+    fn disengage_inner(&mut self) {
+        self.lock.finish(LockdownEnd::Disengaged);
+        if let Some(supervisor) = self.supervisor.take() {
+            let _ = supervisor.join();
+        }
+    }
+}
+
+impl Drop for LockdownGuard {
+    fn drop(&mut self) {
+        self.disengage_inner();
+    }
+}
+
+/// Consume all input except the `unlock` chord until the chord is pressed,
+/// or the lockdown ends otherwise; see [`LockdownEnd`].
+///
+/// Returns once input is blocked. Fails with [`Error::NotSupported`] where
+/// grab hooks can't block input (X11), with [`Error::DisabledByPolicy`]
+/// where grabbing is denied, and with the hook's error if it can't start,
+/// e.g. [`Error::PermissionDenied`].
+pub fn engage(unlock: &[Key], options: LockdownOptions) -> Result<LockdownGuard> {
+    if unlock.is_empty() {
+        return Err(Error::Other(
+            "The unlock chord needs at least one key".into(),
+        ));
+    }
+    if !platform::GRAB_SUPPRESSES && demo::active().is_none() {
+        return Err(Error::NotSupported(
+            "A lockdown can't block input here: grab hooks only listen on this backend".into(),
+        ));
+    }
+    if policy::grab_policy() == Policy::Deny {
+        return Err(Error::DisabledByPolicy(
+            "grabbing, which a lockdown needs, is denied".into(),
+        ));
+    }
+
+    let lock = Arc::new(Lock::new(unlock, options.match_mode));
+    let hook = Arc::new(
+        Hook::new()
+            .ignore_global_suspend(true)
+            .hotkey_match_mode(options.match_mode),
+    );
+    let grab = {
+        let hook = hook.clone();
+        let lock = lock.clone();
+        thread::Builder::new()
+            .name("monio-lockdown".into())
+            .spawn(move || {
+                let result = hook.grab(Blocker(lock.clone()));
+                let mut state = lock.state();
+                if !state.ready
+                    && let Err(e) = result
+                {
+                    state.failure = Some(e);
+                }
+                drop(state);
+                lock.finish(LockdownEnd::GrabLost);
+            })
+            .map_err(|e| Error::ThreadError(format!("failed to spawn lockdown hook: {}", e)))?
+    };
+    let started = lock.wait_ready(START_TIMEOUT).and_then(|()| {
+        // The policy may have changed since it was checked
+        if hook.is_grab_denied() {
+            return Err(Error::DisabledByPolicy(
+                "grabbing, which a lockdown needs, is denied".into(),
+            ));
+        }
+        Ok(())
+    });
+    let stop = move || {
+        let _ = hook.stop();
+        let _ = grab.join();
+    };
+    if let Err(e) = started {
+        lock.finish(LockdownEnd::GrabLost);
+        stop();
+        return Err(e);
+    }
+
+    let expires = options.max_duration.map(|d| Instant::now() + d);
+    supervise(lock, expires, stop)
+}
+
+/// Watch `lock` until it ends or `expires`, then `stop` its hook.
+fn supervise(
+    lock: Arc<Lock>,
+    expires: Option<Instant>,
+    stop: impl FnOnce() + Send + 'static,
+) -> Result<LockdownGuard> {
+    #[cfg(feature = "failsafe")]
+    let triggers = crate::failsafe::trigger_count();
+    let overdue = move |now: Instant| {
+        #[cfg(feature = "failsafe")]
+        if crate::failsafe::trigger_count() != triggers {
+            return Some(LockdownEnd::Failsafe);
+        }
+        expires
+            .is_some_and(|at| now >= at)
+            .then_some(LockdownEnd::Expired)
+    };
+    let supervisor = {
+        let lock = lock.clone();
+        thread::Builder::new()
+            .name("monio-lockdown-watch".into())
+            .spawn(move || {
+                let mut state = lock.state();
+                while state.end.is_none() {
+                    let now = Instant::now();
+                    if let Some(why) = overdue(now) {
+                        state.end = Some(why);
+                        break;
+                    }
+                    let wait = expires.map_or(POLL, |at| (at - now).min(POLL));
+                    state = lock
+                        .changed
+                        .wait_timeout(state, wait)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0;
+                }
+                lock.changed.notify_all();
+                drop(state);
+                stop();
+            })
+            .map_err(|e| Error::ThreadError(format!("failed to spawn lockdown watch: {}", e)))?
+    };
+    Ok(LockdownGuard {
+        lock,
+        supervisor: Some(supervisor),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    const CHORD: [Key; 2] = [Key::ControlLeft, Key::KeyU];
+
+    fn key(key: Key, pressed: bool) -> Event {
+        if pressed {
+            Event::key_pressed(key, 0)
+        } else {
+            Event::key_released(key, 0)
+        }
+    }
+
+    /// A guard over a pretend hook, and whether it was stopped.
+    fn guard(lock: &Arc<Lock>, expires: Option<Instant>) -> (LockdownGuard, Arc<AtomicBool>) {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let flag = stopped.clone();
+        let guard = supervise(lock.clone(), expires, move || {
+            flag.store(true, Ordering::SeqCst)
+        })
+        .unwrap();
+        (guard, stopped)
+    }
+
+    #[test]
+    fn test_consumes_everything_until_unlocked() {
+        let lock = Arc::new(Lock::new(&CHORD, HotkeyMatchMode::Logical));
+        let blocker = Blocker(lock.clone());
+
+        let enabled = Event::hook_enabled();
+        assert!(blocker.handle_event(&enabled).is_some());
+        assert!(lock.wait_ready(Duration::ZERO).is_ok());
+
+        for event in [
+            Event::mouse_moved(1.0, 2.0),
+            Event::mouse_pressed(crate::event::Button::Left, 1.0, 2.0),
+            key(Key::KeyU, true),
+            key(Key::KeyU, false),
+            // Keys of the chord one after the other don't unlock
+            key(Key::ControlLeft, true),
+            key(Key::ControlLeft, false),
+            key(Key::KeyU, true),
+        ] {
+            assert!(blocker.handle_event(&event).is_none());
+        }
+        assert_eq!(lock.ended(), None);
+
+        assert!(blocker.handle_event(&key(Key::ControlLeft, true)).is_none());
+        assert_eq!(lock.ended(), Some(LockdownEnd::Unlocked));
+    }
+
+    #[test]
+    fn test_unlock_stops_the_hook() {
+        let lock = Arc::new(Lock::new(&CHORD, HotkeyMatchMode::Logical));
+        let (guard, stopped) = guard(&lock, None);
+        assert!(guard.is_engaged());
+
+        let blocker = Blocker(lock.clone());
+        blocker.handle_event(&key(Key::ControlLeft, true));
+        blocker.handle_event(&key(Key::KeyU, true));
+        assert_eq!(guard.wait(), LockdownEnd::Unlocked);
+        drop(guard);
+        assert!(stopped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_expires() {
+        let lock = Arc::new(Lock::new(&CHORD, HotkeyMatchMode::Logical));
+        let started = Instant::now();
+        let (guard, stopped) = guard(&lock, Some(started + Duration::from_millis(30)));
+
+        assert_eq!(guard.wait(), LockdownEnd::Expired);
+        assert!(started.elapsed() >= Duration::from_millis(30));
+        assert!(!guard.is_engaged());
+        drop(guard);
+        assert!(stopped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_drop_disengages() {
+        let lock = Arc::new(Lock::new(&CHORD, HotkeyMatchMode::Logical));
+        let (guard, stopped) = guard(&lock, None);
+        drop(guard);
+        assert!(stopped.load(Ordering::SeqCst));
+        assert_eq!(lock.ended(), Some(LockdownEnd::Disengaged));
+    }
+
+    #[test]
+    fn test_lost_grab_ends_the_lockdown() {
+        let lock = Arc::new(Lock::new(&CHORD, HotkeyMatchMode::Logical));
+        let (guard, _) = guard(&lock, None);
+        Blocker(lock.clone()).handle_event(&Event::grab_suspended());
+        assert_eq!(guard.wait(), LockdownEnd::GrabLost);
+    }
+
+    #[test]
+    fn test_empty_chord_is_rejected() {
+        assert!(matches!(
+            engage(&[], LockdownOptions::new()),
+            Err(Error::Other(_))
+        ));
+    }
+}
@@ -0,0 +1,84 @@
+//! Media key shortcuts.
+//!
+//! Each function taps the corresponding media key through
+//! [`key_tap`](crate::key_tap), so it goes through the simulation rate limit.
+//! The backends send these the way a keyboard's own media keys arrive:
+//! system-defined events on macOS, extended virtual keys on Windows (with a
+//! `WM_APPCOMMAND` fallback), `XF86Audio*` keysyms on X11 and
+//! `KEY_VOLUMEUP`/`KEY_PLAYPAUSE`/... on evdev.
+//!
+//! # Example
+//!
+//! ```no_run
+//! monio::media::volume_up().unwrap();
+//! monio::media::play_pause().unwrap();
+//! ```
+
+use crate::error::Result;
+use crate::keycode::Key;
+use crate::simulation::key_tap;
+
+/// Raise the system volume one step.
+pub fn volume_up() -> Result<()> {
+    key_tap(Key::VolumeUp)
+}
+
+/// Lower the system volume one step.
+pub fn volume_down() -> Result<()> {
+    key_tap(Key::VolumeDown)
+}
+
+/// Toggle mute.
+pub fn mute_toggle() -> Result<()> {
+    key_tap(Key::VolumeMute)
+}
+
+/// Toggle playback in the active media player.
+pub fn play_pause() -> Result<()> {
+    key_tap(Key::MediaPlayPause)
+}
+
+/// Skip to the next track.
+pub fn next_track() -> Result<()> {
+    key_tap(Key::MediaNext)
+}
+
+/// Go back to the previous track.
+pub fn previous_track() -> Result<()> {
+    key_tap(Key::MediaPrevious)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::listen_channel;
+    use crate::event::EventType;
+    use std::time::{Duration, Instant};
+
+    /// Toggles mute twice, so the system ends up where it started.
+    ///
+    /// Needs a real session with input access (and Accessibility permission
+    /// on macOS). Run with `cargo test mute_toggle -- --ignored`.
+    #[test]
+    #[ignore = "needs a display and input permissions"]
+    fn test_mute_toggle_is_observed() {
+        let (handle, rx) = listen_channel(256).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        mute_toggle().unwrap();
+        mute_toggle().unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut presses = 0;
+        while presses < 2 && Instant::now() < deadline {
+            if let Ok(event) = rx.recv_timeout(Duration::from_millis(100))
+                && event.event_type == EventType::KeyPressed
+                && event.keyboard.as_ref().map(|kb| kb.key) == Some(Key::VolumeMute)
+            {
+                presses += 1;
+            }
+        }
+        drop(handle);
+        assert_eq!(presses, 2);
+    }
+}
@@ -0,0 +1,442 @@
+//! Delivering events in timestamp order, see
+//! [`Hook::strict_ordering`](crate::Hook::strict_ordering).
+//!
+//! On Windows, X11 and evdev, a modifier and the key pressed with it are
+//! separate callbacks, possibly from separate hooks or devices, and don't
+//! always arrive in the order they happened. A consumer then sees "K
+//! pressed" with a [`mask`](crate::Event::mask) that doesn't include the
+//! Ctrl pressed a moment before.
+//!
+//! With strict ordering, listen hooks hold each event back for a couple of
+//! milliseconds ([`WINDOW`]) and deliver events in order of their native
+//! timestamps instead of arrival. Events that were overtaken get their mask
+//! corrected: it includes the modifiers and buttons pressed (and excludes
+//! those released) by events with earlier timestamps, and no longer
+//! reflects changes by events with later ones. Events with equal timestamps
+//! keep their arrival order, and events overtaken by more than the window
+//! can't be fixed.
+//!
+//! The cost is latency: every event is delivered on a worker thread, up to
+//! the window late. Grab hooks have to decide about each event as it
+//! arrives, so they deliver in arrival order regardless.
+
+use crate::event::{Button, Event, EventType};
+use crate::hook::EventHandler;
+use crate::keycode::Key;
+use crate::state::{
+    MASK_ALT, MASK_BUTTON1, MASK_BUTTON2, MASK_BUTTON3, MASK_BUTTON4, MASK_BUTTON5, MASK_CTRL,
+    MASK_META, MASK_SHIFT,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long events are held back to let earlier ones catch up.
+pub const WINDOW: Duration = Duration::from_millis(2);
+
+/// How long the worker sleeps without events before checking for shutdown.
+const IDLE: Duration = Duration::from_millis(100);
+
+/// The mask bits an event sets (`true`) or clears (`false`).
+pub(crate) fn mask_change(event: &Event) -> Option<(u32, bool)> {
+    let pressed = match event.event_type {
+        EventType::KeyPressed | EventType::MousePressed => true,
+        EventType::KeyReleased | EventType::MouseReleased => false,
+        _ => return None,
+    };
+    let bits = if let Some(kb) = &event.keyboard {
+        match kb.key {
+            Key::ShiftLeft | Key::ShiftRight => MASK_SHIFT,
+            Key::ControlLeft | Key::ControlRight => MASK_CTRL,
+            Key::AltLeft | Key::AltRight => MASK_ALT,
+            Key::MetaLeft | Key::MetaRight => MASK_META,
+            _ => return None,
+        }
+    } else {
+        match event.mouse.as_ref()?.button? {
+            Button::Left => MASK_BUTTON1,
+            Button::Right => MASK_BUTTON2,
+            Button::Middle => MASK_BUTTON3,
+            Button::Button4 => MASK_BUTTON4,
+            Button::Button5 => MASK_BUTTON5,
+            Button::Unknown(_) => return None,
+        }
+    };
+    Some((bits, pressed))
+}
+
+fn apply(mask: &mut u32, (bits, set): (u32, bool)) {
+    if set {
+        *mask |= bits;
+    } else {
+        *mask &= !bits;
+    }
+}
+
+struct Pending {
+    event: Event,
+    arrived: Instant,
+    sequence: u64,
+}
+
+/// Events waiting to be delivered in timestamp order.
+pub(crate) struct ReorderBuffer {
+    window: Duration,
+    pending: Vec<Pending>,
+    next_sequence: u64,
+}
+
+impl ReorderBuffer {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: Vec::new(),
+            next_sequence: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, event: &Event, now: Instant) {
+        self.pending.push(Pending {
+            event: event.clone(),
+            arrived: now,
+            sequence: self.next_sequence,
+        });
+        self.next_sequence += 1;
+    }
+
+    /// When the next event is due.
+    pub(crate) fn next_due(&self) -> Option<Instant> {
+        self.pending.iter().map(|p| p.arrived + self.window).min()
+    }
+
+    /// Take the events due at `now`, in timestamp order: those held for the
+    /// window, and all events with earlier timestamps.
+    pub(crate) fn release(&mut self, now: Instant) -> Vec<Event> {
+        let Some(cutoff) = self
+            .pending
+            .iter()
+            .filter(|p| p.arrived + self.window <= now)
+            .map(|p| p.event.time)
+            .max()
+        else {
+            return Vec::new();
+        };
+        let mut released = Vec::new();
+        while let Some(event) = self.pop_earliest(|p| p.event.time <= cutoff) {
+            released.push(event);
+        }
+        released
+    }
+
+    /// Take every event, in timestamp order.
+    pub(crate) fn drain(&mut self) -> Vec<Event> {
+        let mut released = Vec::new();
+        while let Some(event) = self.pop_earliest(|_| true) {
+            released.push(event);
+        }
+        released
+    }
+
+    /// Remove the earliest event if it passes `filter`, and correct the
+    /// masks of it and the events it was swapped with.
+    fn pop_earliest(&mut self, filter: impl Fn(&Pending) -> bool) -> Option<Event> {
+        let index = (0..self.pending.len())
+            .min_by_key(|&i| (self.pending[i].event.time, self.pending[i].sequence))
+            .filter(|&i| filter(&self.pending[i]))?;
+        let Pending {
+            mut event,
+            sequence,
+            ..
+        } = self.pending.remove(index);
+
+        // Whatever is left and arrived earlier happened later
+        for later in self.pending.iter_mut().filter(|p| p.sequence < sequence) {
+            // Its change is already in this event's mask, but shouldn't be
+            if let Some((bits, set)) = mask_change(&later.event) {
+                apply(&mut event.mask, (bits, !set));
+            }
+            // This event's change isn't in its mask yet, but should be
+            if let Some(change) = mask_change(&event) {
+                apply(&mut later.event.mask, change);
+            }
+        }
+        Some(event)
+    }
+}
+
+struct Shared<H> {
+    buffer: Mutex<ReorderBuffer>,
+    arrived: Condvar,
+    /// Held while delivering, so deliveries from the worker and the hook
+    /// thread don't interleave.
+    delivering: Mutex<()>,
+    stop: AtomicBool,
+    handler: H,
+}
+
+impl<H: EventHandler> Shared<H> {
+    fn deliver_due(&self) {
+        let Ok(_delivering) = self.delivering.lock() else {
+            return;
+        };
+        let events = match self.buffer.lock() {
+            Ok(mut buffer) => buffer.release(Instant::now()),
+            Err(_) => return,
+        };
+        for event in &events {
+            self.handler.handle_event(event);
+        }
+    }
+
+    /// Deliver everything held, then `last`.
+    fn flush(&self, last: Option<&Event>) {
+        let Ok(_delivering) = self.delivering.lock() else {
+            return;
+        };
+        let events = match self.buffer.lock() {
+            Ok(mut buffer) => buffer.drain(),
+            Err(_) => return,
+        };
+        for event in events.iter().chain(last) {
+            self.handler.handle_event(event);
+        }
+    }
+
+    fn run(&self) {
+        while !self.stop.load(Ordering::SeqCst) {
+            let Ok(buffer) = self.buffer.lock() else {
+                return;
+            };
+            let now = Instant::now();
+            let wait = buffer
+                .next_due()
+                .map_or(IDLE, |due| due.saturating_duration_since(now));
+            if !wait.is_zero() {
+                let _ = self.arrived.wait_timeout(buffer, wait);
+                continue;
+            }
+            drop(buffer);
+            self.deliver_due();
+        }
+    }
+}
+
+/// The worker delivering held events.
+pub(crate) struct Reorderer<H: EventHandler> {
+    shared: Arc<Shared<H>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<H: EventHandler> Drop for Reorderer<H> {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        self.shared.arrived.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        // Normally already flushed by `HookDisabled`
+        self.shared.flush(None);
+    }
+}
+
+/// Listen handler delivering events to `H` in timestamp order, or directly
+/// without strict ordering.
+pub(crate) enum Ordered<H: EventHandler> {
+    Direct(H),
+    Reordered(Reorderer<H>),
+}
+
+impl<H: EventHandler + 'static> Ordered<H> {
+    pub(crate) fn new(handler: H, strict: bool) -> Self {
+        if !strict {
+            return Self::Direct(handler);
+        }
+        let shared = Arc::new(Shared {
+            buffer: Mutex::new(ReorderBuffer::new(WINDOW)),
+            arrived: Condvar::new(),
+            delivering: Mutex::new(()),
+            stop: AtomicBool::new(false),
+            handler,
+        });
+        let worker = {
+            let shared = shared.clone();
+            thread::spawn(move || shared.run())
+        };
+        Self::Reordered(Reorderer {
+            shared,
+            worker: Some(worker),
+        })
+    }
+}
+
+impl<H: EventHandler> EventHandler for Ordered<H> {
+    fn handle_event(&self, event: &Event) {
+        let shared = match self {
+            Self::Direct(handler) => return handler.handle_event(event),
+            Self::Reordered(reorderer) => &reorderer.shared,
+        };
+        if event.event_type == EventType::HookDisabled {
+            shared.flush(Some(event));
+            return;
+        }
+        if let Ok(mut buffer) = shared.buffer.lock() {
+            buffer.push(event, Instant::now());
+        }
+        shared.arrived.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    /// An event that happened at `ms`, with `mask` as the platform built it.
+    fn at(ms: u64, mask: u32, mut event: Event) -> Event {
+        event.time = SystemTime::UNIX_EPOCH + Duration::from_millis(ms);
+        event.mask = mask;
+        event
+    }
+
+    fn summary(events: &[Event]) -> Vec<(EventType, u32)> {
+        events.iter().map(|e| (e.event_type, e.mask)).collect()
+    }
+
+    #[test]
+    fn test_overtaken_modifier_press_joins_the_mask() {
+        let start = Instant::now();
+        let mut buffer = ReorderBuffer::new(WINDOW);
+        // K arrives before the Ctrl pressed just before it
+        buffer.push(&at(11, 0, Event::key_pressed(Key::KeyK, 37)), start);
+        buffer.push(
+            &at(10, MASK_CTRL, Event::key_pressed(Key::ControlLeft, 29)),
+            start + MS / 2,
+        );
+        assert!(buffer.release(start + MS).is_empty());
+
+        let events = buffer.release(start + WINDOW);
+        let keys: Vec<_> = events
+            .iter()
+            .map(|e| e.keyboard.as_ref().unwrap().key)
+            .collect();
+        assert_eq!(keys, [Key::ControlLeft, Key::KeyK]);
+        assert_eq!(events[1].mask, MASK_CTRL);
+        assert_eq!(buffer.next_due(), None);
+    }
+
+    #[test]
+    fn test_overtaken_modifier_release_leaves_the_mask() {
+        let start = Instant::now();
+        let mut buffer = ReorderBuffer::new(WINDOW);
+        // K was pressed with Shift held, but arrives after Shift's release
+        // and so without it
+        buffer.push(&at(20, 0, Event::key_released(Key::ShiftLeft, 42)), start);
+        buffer.push(&at(19, 0, Event::key_pressed(Key::KeyK, 37)), start);
+        buffer.push(&at(21, 0, Event::key_released(Key::KeyK, 37)), start);
+
+        let events = buffer.drain();
+        assert_eq!(
+            summary(&events),
+            [
+                (EventType::KeyPressed, MASK_SHIFT),
+                (EventType::KeyReleased, 0),
+                (EventType::KeyReleased, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_in_order_events_are_untouched() {
+        let start = Instant::now();
+        let mut buffer = ReorderBuffer::new(WINDOW);
+        let events = [
+            at(1, MASK_ALT, Event::key_pressed(Key::AltLeft, 56)),
+            at(2, MASK_ALT, Event::key_pressed(Key::Tab, 15)),
+            at(3, MASK_ALT, Event::key_released(Key::Tab, 15)),
+            at(4, 0, Event::key_released(Key::AltLeft, 56)),
+        ];
+        for (i, event) in events.iter().enumerate() {
+            buffer.push(event, start + i as u32 * MS);
+        }
+        // Only what has been held for the window is due
+        assert_eq!(buffer.release(start + WINDOW + MS).len(), 2);
+        assert_eq!(buffer.next_due(), Some(start + 2 * MS + WINDOW));
+        let mut released = events[..2].to_vec();
+        released.extend(buffer.drain());
+        assert_eq!(summary(&released), summary(&events));
+    }
+
+    #[test]
+    fn test_button_press_overtaken_by_motion() {
+        let start = Instant::now();
+        let mut buffer = ReorderBuffer::new(WINDOW);
+        buffer.push(&at(31, 0, Event::mouse_moved(5.0, 5.0)), start);
+        buffer.push(
+            &at(
+                30,
+                MASK_BUTTON1,
+                Event::mouse_pressed(Button::Left, 0.0, 0.0),
+            ),
+            start,
+        );
+        // Equal timestamps keep arrival order
+        buffer.push(&at(31, MASK_BUTTON1, Event::mouse_moved(6.0, 6.0)), start);
+
+        let events = buffer.release(start + WINDOW);
+        assert_eq!(
+            summary(&events),
+            [
+                (EventType::MousePressed, MASK_BUTTON1),
+                (EventType::MouseMoved, MASK_BUTTON1),
+                (EventType::MouseMoved, MASK_BUTTON1),
+            ]
+        );
+        assert_eq!(events[1].mouse.as_ref().unwrap().x, 5.0);
+    }
+
+    #[test]
+    fn test_reordered_handler_delivers_in_timestamp_order() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let seen = seen.clone();
+            move |event: &Event| seen.lock().unwrap().push(event.clone())
+        };
+        let ordered = Ordered::new(handler, true);
+        ordered.handle_event(&at(2, 0, Event::key_pressed(Key::KeyK, 37)));
+        ordered.handle_event(&at(1, MASK_CTRL, Event::key_pressed(Key::ControlLeft, 29)));
+
+        // The worker delivers once the window has passed
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while seen.lock().unwrap().len() < 2 && Instant::now() < deadline {
+            thread::sleep(MS);
+        }
+        assert_eq!(
+            summary(&seen.lock().unwrap()),
+            [
+                (EventType::KeyPressed, MASK_CTRL),
+                (EventType::KeyPressed, MASK_CTRL)
+            ]
+        );
+
+        // HookDisabled flushes what is held and comes last
+        ordered.handle_event(&at(4, MASK_CTRL, Event::key_released(Key::KeyK, 37)));
+        ordered.handle_event(&at(3, MASK_CTRL, Event::key_pressed(Key::KeyJ, 36)));
+        ordered.handle_event(&Event::hook_disabled());
+        let keys: Vec<_> = seen.lock().unwrap()[2..]
+            .iter()
+            .map(|e| (e.event_type, e.keyboard.as_ref().map(|kb| kb.key)))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                (EventType::KeyPressed, Some(Key::KeyJ)),
+                (EventType::KeyReleased, Some(Key::KeyK)),
+                (EventType::HookDisabled, None),
+            ]
+        );
+        drop(ordered);
+    }
+}
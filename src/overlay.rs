@@ -0,0 +1,840 @@
+//! Input overlay for streaming software (OBS browser sources and similar).
+//!
+//! [`overlay_server`] listens on `127.0.0.1:<port>` and serves:
+//!
+//! - `/`: a small built-in page showing recent keys and the mouse state. Add
+//!   it to OBS as a browser source.
+//! - `/ws`: a WebSocket feed of JSON messages, which the page consumes and
+//!   other tools can use too.
+//!
+//! The page is configured with query parameters:
+//!
+//! | Parameter | Default | Meaning |
+//! |-----------|---------|---------|
+//! | `mouse`   | `1`     | `0` hides the mouse panel |
+//! | `keys`    | `8`     | Most recent keys shown |
+//! | `fade`    | `2000`  | Milliseconds before a key fades out (`0` keeps keys) |
+//! | `theme`   | `dark`  | `dark`, `light` or `transparent` |
+//!
+//! For example `http://127.0.0.1:9000/?keys=5&fade=1500&theme=transparent`.
+//!
+//! # Messages
+//!
+//! Each WebSocket text frame is one JSON object with a `type` field:
+//!
+//! ```json
+//! {"type":"key","label":"C","modifiers":["ctrl"],"chord":"Ctrl+C","pressed":true}
+//! {"type":"mouse","x":812.0,"y":430.5}
+//! {"type":"button","button":"left","pressed":false}
+//! {"type":"wheel","direction":"down","chord":"Shift+Wheel Down"}
+//! ```
+//!
+//! `chord` is the key or wheel direction with the held modifiers, written in
+//! the platform's style by [`format_chord`](crate::format::format_chord).
+//!
+//! Every client has its own bounded queue, so a slow client never holds up
+//! the hook or other clients: consecutive mouse moves are coalesced into the
+//! latest position, and once a client falls [`CLIENT_QUEUE_CAPACITY`]
+//! messages behind, its oldest messages are dropped.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::overlay::overlay_server;
+//!
+//! let server = overlay_server(9000)?;
+//! println!("Add http://{}/ as a browser source", server.local_addr());
+//! std::thread::park();
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::event::{Button, Event, EventType, ScrollDirection};
+use crate::format::{ChordStyle, format_chord};
+use crate::hook::Hook;
+use crate::keycode::Key;
+use crate::state::Modifiers;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Messages a WebSocket client may fall behind before the oldest are dropped.
+pub const CLIENT_QUEUE_CAPACITY: usize = 256;
+
+/// The built-in overlay page.
+const PAGE: &str = include_str!("overlay/page.html");
+
+/// GUID appended to the client key in the WebSocket handshake (RFC 6455).
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest request head or client frame accepted.
+const MAX_REQUEST_SIZE: usize = 16 * 1024;
+
+/// How long a connection may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+
+/// One entry of the overlay feed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Key {
+        label: String,
+        modifiers: Vec<&'static str>,
+        chord: String,
+        pressed: bool,
+    },
+    Mouse {
+        x: f64,
+        y: f64,
+    },
+    Button {
+        button: &'static str,
+        pressed: bool,
+    },
+    Wheel {
+        direction: &'static str,
+        chord: String,
+    },
+}
+
+impl Message {
+    fn from_event(event: &Event) -> Option<Self> {
+        match event.event_type {
+            EventType::KeyPressed | EventType::KeyReleased => {
+                let kb = event.keyboard.as_ref()?;
+                let mods = Modifiers::from_mask(event.mask);
+                let modifiers = [
+                    (mods.ctrl, "ctrl"),
+                    (mods.alt, "alt"),
+                    (mods.shift, "shift"),
+                    (mods.meta, "meta"),
+                ]
+                .into_iter()
+                .filter_map(|(held, name)| held.then_some(name))
+                .collect();
+                Some(Message::Key {
+                    label: key_label(kb.key),
+                    modifiers,
+                    chord: format_chord(&mods, kb.key, ChordStyle::native()),
+                    pressed: event.event_type == EventType::KeyPressed,
+                })
+            }
+            EventType::MouseMoved | EventType::MouseDragged => {
+                let mouse = event.mouse.as_ref()?;
+                Some(Message::Mouse {
+                    x: mouse.x,
+                    y: mouse.y,
+                })
+            }
+            EventType::MousePressed | EventType::MouseReleased => {
+                let button = event.mouse.as_ref()?.button?;
+                Some(Message::Button {
+                    button: button_name(button),
+                    pressed: event.event_type == EventType::MousePressed,
+                })
+            }
+            EventType::MouseWheel => {
+                let wheel = event.wheel.as_ref()?;
+                let direction = match wheel.direction {
+                    ScrollDirection::Up => "up",
+                    ScrollDirection::Down => "down",
+                    ScrollDirection::Left => "left",
+                    ScrollDirection::Right => "right",
+                };
+                Some(Message::Wheel {
+                    direction,
+                    chord: format_chord(
+                        &Modifiers::from_mask(event.mask),
+                        wheel.direction,
+                        ChordStyle::native(),
+                    ),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn is_motion(&self) -> bool {
+        matches!(self, Message::Mouse { .. })
+    }
+}
+
+/// How a key is shown on the overlay.
+fn key_label(key: Key) -> String {
+    match key {
+        Key::Space => "Space".into(),
+        Key::Escape => "Esc".into(),
+        Key::Unknown(code) => format!("#{code}"),
+        key => match key.as_char() {
+            Some(ch) => ch.to_uppercase().collect(),
+            None => format!("{key:?}"),
+        },
+    }
+}
+
+fn button_name(button: Button) -> &'static str {
+    match button {
+        Button::Left => "left",
+        Button::Right => "right",
+        Button::Middle => "middle",
+        Button::Button4 => "back",
+        Button::Button5 => "forward",
+        Button::Unknown(_) => "other",
+    }
+}
+
+/// A message waiting to be sent to one client.
+#[derive(Debug)]
+struct Queued {
+    text: Arc<str>,
+    is_motion: bool,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    messages: VecDeque<Queued>,
+    closed: bool,
+    dropped: u64,
+}
+
+/// Per-client outgoing queue, drained by the client's writer thread.
+#[derive(Debug, Default)]
+struct ClientQueue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+impl ClientQueue {
+    /// Queue a message, returning `false` once the client is gone.
+    fn push(&self, text: Arc<str>, is_motion: bool) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        if state.closed {
+            return false;
+        }
+        // Only the latest position matters
+        if is_motion
+            && let Some(last) = state.messages.back_mut()
+            && last.is_motion
+        {
+            last.text = text;
+        } else {
+            if state.messages.len() >= CLIENT_QUEUE_CAPACITY {
+                state.messages.pop_front();
+                state.dropped += 1;
+            }
+            state.messages.push_back(Queued { text, is_motion });
+        }
+        self.ready.notify_one();
+        true
+    }
+
+    /// Wait for the next message, or `None` once closed.
+    fn pop(&self) -> Option<Arc<str>> {
+        let mut state = self.state.lock().ok()?;
+        loop {
+            if state.closed {
+                return None;
+            }
+            if let Some(queued) = state.messages.pop_front() {
+                return Some(queued.text);
+            }
+            state = self.ready.wait(state).ok()?;
+        }
+    }
+
+    fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.closed = true;
+            if state.dropped > 0 {
+                log::debug!("Overlay client dropped {} messages", state.dropped);
+            }
+        }
+        self.ready.notify_all();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().map_or(true, |state| state.closed)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    clients: Mutex<Vec<Arc<ClientQueue>>>,
+    running: AtomicBool,
+}
+
+impl Shared {
+    fn publish(&self, event: &Event) {
+        let Some(message) = Message::from_event(event) else {
+            return;
+        };
+        let text: Arc<str> = match serde_json::to_string(&message) {
+            Ok(text) => text.into(),
+            Err(e) => {
+                log::debug!("Failed to serialize overlay message: {}", e);
+                return;
+            }
+        };
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.retain(|client| client.push(text.clone(), message.is_motion()));
+        }
+    }
+
+    fn client_count(&self) -> usize {
+        self.clients.lock().map_or(0, |clients| {
+            clients.iter().filter(|client| !client.is_closed()).count()
+        })
+    }
+
+    fn close_all(&self) {
+        if let Ok(mut clients) = self.clients.lock() {
+            for client in clients.drain(..) {
+                client.close();
+            }
+        }
+    }
+}
+
+/// A running overlay server. Stops when dropped.
+pub struct OverlayServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    accept_thread: Option<JoinHandle<()>>,
+    hook: Option<Hook>,
+}
+
+/// Serve the overlay page and feed on `127.0.0.1:port`, fed by a new hook.
+///
+/// Pass port `0` to let the OS pick one (see [`OverlayServer::local_addr`]).
+pub fn overlay_server(port: u16) -> Result<OverlayServer> {
+    let mut server = OverlayServer::bind(SocketAddr::from(([127, 0, 0, 1], port)))?;
+    let shared = server.shared.clone();
+    let hook = Hook::new();
+    hook.run_async(move |event: &Event| shared.publish(event))?;
+    server.hook = Some(hook);
+    Ok(server)
+}
+
+impl OverlayServer {
+    /// Start serving on `addr` without a hook.
+    fn bind(addr: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| Error::Other(format!("failed to bind overlay server: {}", e)))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| Error::Other(format!("failed to bind overlay server: {}", e)))?;
+
+        let shared = Arc::new(Shared::default());
+        shared.running.store(true, Ordering::SeqCst);
+        let accept_shared = shared.clone();
+        let accept_thread = thread::Builder::new()
+            .name("monio-overlay".into())
+            .spawn(move || accept_loop(listener, accept_shared))
+            .map_err(|e| Error::ThreadError(e.to_string()))?;
+
+        Ok(Self {
+            addr,
+            shared,
+            accept_thread: Some(accept_thread),
+            hook: None,
+        })
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Number of connected WebSocket clients.
+    pub fn client_count(&self) -> usize {
+        self.shared.client_count()
+    }
+
+    /// Stop the hook and server, disconnecting all clients.
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        let result = match self.hook.take() {
+            Some(hook) => hook.stop(),
+            None => Ok(()),
+        };
+        if self.shared.running.swap(false, Ordering::SeqCst) {
+            // Wake the accept loop
+            let _ = TcpStream::connect(self.addr);
+        }
+        if let Some(thread) = self.accept_thread.take() {
+            let _ = thread.join();
+        }
+        self.shared.close_all();
+        result
+    }
+}
+
+impl Drop for OverlayServer {
+    fn drop(&mut self) {
+        if let Err(e) = self.shutdown() {
+            log::debug!("Failed to stop overlay server: {}", e);
+        }
+    }
+}
+
+fn accept_loop(listener: TcpListener, shared: Arc<Shared>) {
+    for stream in listener.incoming() {
+        if !shared.running.load(Ordering::SeqCst) {
+            break;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::debug!("Overlay accept failed: {}", e);
+                continue;
+            }
+        };
+        let shared = shared.clone();
+        let spawned = thread::Builder::new()
+            .name("monio-overlay-conn".into())
+            .spawn(move || {
+                if let Err(e) = handle_connection(stream, &shared) {
+                    log::debug!("Overlay connection failed: {}", e);
+                }
+            });
+        if let Err(e) = spawned {
+            log::debug!("Failed to spawn overlay connection thread: {}", e);
+        }
+    }
+}
+
+/// The parts of an HTTP request the server looks at.
+#[derive(Debug, Default, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    websocket_key: Option<String>,
+    upgrade: bool,
+}
+
+/// Read and parse a request head.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let mut request = Request::default();
+    let mut size = 0;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+        size += read;
+        if read == 0 || size > MAX_REQUEST_SIZE {
+            return Err(invalid("incomplete or oversized request"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if request.method.is_empty() {
+            let mut parts = line.split_whitespace();
+            request.method = parts.next().unwrap_or_default().to_string();
+            let target = parts
+                .next()
+                .ok_or_else(|| invalid("missing request target"))?;
+            request.path = target.split('?').next().unwrap_or("/").to_string();
+            continue;
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "upgrade" => request.upgrade = value.eq_ignore_ascii_case("websocket"),
+            "sec-websocket-key" => request.websocket_key = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    Ok(request)
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn handle_connection(mut stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = read_request(&mut reader)?;
+
+    if request.method != "GET" {
+        write_response(&mut stream, "405 Method Not Allowed", "text/plain", "");
+        return Ok(());
+    }
+    match request.path.as_str() {
+        "/" | "/index.html" => {
+            write_response(&mut stream, "200 OK", "text/html; charset=utf-8", PAGE);
+            Ok(())
+        }
+        "/ws" => match (&request.websocket_key, request.upgrade) {
+            (Some(key), true) => serve_websocket(stream, reader, key, shared),
+            _ => {
+                write_response(&mut stream, "400 Bad Request", "text/plain", "");
+                Ok(())
+            }
+        },
+        _ => {
+            write_response(&mut stream, "404 Not Found", "text/plain", "");
+            Ok(())
+        }
+    }
+}
+
+fn serve_websocket(
+    mut stream: TcpStream,
+    mut reader: BufReader<TcpStream>,
+    key: &str,
+    shared: &Shared,
+) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    );
+    stream.write_all(response.as_bytes())?;
+    stream.set_read_timeout(None)?;
+    stream.set_nodelay(true)?;
+
+    let queue = Arc::new(ClientQueue::default());
+    if let Ok(mut clients) = shared.clients.lock() {
+        clients.push(queue.clone());
+    }
+
+    let writer_queue = queue.clone();
+    let mut writer = stream.try_clone()?;
+    thread::Builder::new()
+        .name("monio-overlay-ws".into())
+        .spawn(move || {
+            while let Some(text) = writer_queue.pop() {
+                if writer
+                    .write_all(&encode_frame(OPCODE_TEXT, text.as_bytes()))
+                    .is_err()
+                {
+                    writer_queue.close();
+                    return;
+                }
+            }
+            let _ = writer.write_all(&encode_frame(OPCODE_CLOSE, &[]));
+            let _ = writer.shutdown(Shutdown::Both);
+        })?;
+
+    // Clients only send control frames; watch for them closing
+    loop {
+        match read_frame(&mut reader) {
+            Ok((OPCODE_CLOSE, _)) | Err(_) => break,
+            Ok(_) => {}
+        }
+    }
+    queue.close();
+    Ok(())
+}
+
+/// Encode an unmasked (server-to-client) frame.
+fn encode_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Read one client frame, returning its opcode and unmasked payload.
+fn read_frame(reader: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head)?;
+    let opcode = head[0] & 0x0f;
+    let masked = head[1] & 0x80 != 0;
+
+    let len = match head[1] & 0x7f {
+        126 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if len > MAX_REQUEST_SIZE as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "websocket frame too large",
+        ));
+    }
+
+    let mut mask = [0u8; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    if masked {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+    Ok((opcode, payload))
+}
+
+/// `Sec-WebSocket-Accept` for a client's `Sec-WebSocket-Key`.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()))
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MASK_CTRL;
+
+    #[test]
+    fn test_websocket_handshake_key() {
+        let hex: String = sha1(b"abc").iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(hex, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        // Example from RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        for len in [0, 125, 126, 0xffff, 0x10000] {
+            let frame = encode_frame(OPCODE_TEXT, &vec![b'x'; len]);
+            let header = match len {
+                0..=125 => 2,
+                126..=0xffff => 4,
+                _ => 10,
+            };
+            assert_eq!(frame.len(), header + len);
+            assert_eq!(frame[0], 0x81);
+        }
+
+        // A masked client frame
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x81, 0x80 | 5];
+        frame.extend_from_slice(&mask);
+        frame.extend(b"hello".iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        let (opcode, payload) = read_frame(&mut frame.as_slice()).unwrap();
+        assert_eq!((opcode, payload.as_slice()), (OPCODE_TEXT, &b"hello"[..]));
+    }
+
+    #[test]
+    fn test_queue_coalesces_motion_and_drops_oldest() {
+        let queue = ClientQueue::default();
+        assert!(queue.push("a".into(), false));
+        assert!(queue.push("m1".into(), true));
+        assert!(queue.push("m2".into(), true));
+        assert!(queue.push("b".into(), false));
+        assert_eq!(queue.pop().as_deref(), Some("a"));
+        assert_eq!(queue.pop().as_deref(), Some("m2"));
+        assert_eq!(queue.pop().as_deref(), Some("b"));
+
+        for i in 0..CLIENT_QUEUE_CAPACITY + 10 {
+            queue.push(i.to_string().into(), false);
+        }
+        assert_eq!(queue.pop().as_deref(), Some("10"));
+        assert_eq!(queue.state.lock().unwrap().dropped, 10);
+
+        queue.close();
+        assert_eq!(queue.pop(), None);
+        assert!(!queue.push("late".into(), false));
+    }
+
+    #[test]
+    fn test_messages() {
+        let mut ctrl_c = Event::key_pressed(Key::KeyC, 0);
+        ctrl_c.mask = MASK_CTRL;
+        let chord = format_chord(
+            &Modifiers::from_mask(MASK_CTRL),
+            Key::KeyC,
+            ChordStyle::native(),
+        );
+        assert_eq!(
+            serde_json::to_string(&Message::from_event(&ctrl_c).unwrap()).unwrap(),
+            format!(
+                r#"{{"type":"key","label":"C","modifiers":["ctrl"],"chord":"{chord}","pressed":true}}"#
+            )
+        );
+        assert_eq!(
+            Message::from_event(&Event::mouse_released(Button::Left, 1.0, 2.0)),
+            Some(Message::Button {
+                button: "left",
+                pressed: false
+            })
+        );
+        assert_eq!(
+            Message::from_event(&Event::mouse_wheel(0.0, 0.0, ScrollDirection::Down, 1.0)),
+            Some(Message::Wheel {
+                direction: "down",
+                chord: format_chord(
+                    &Modifiers::default(),
+                    ScrollDirection::Down,
+                    ChordStyle::native()
+                ),
+            })
+        );
+        assert_eq!(
+            Message::from_event(&Event::new(EventType::HookEnabled)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_server_end_to_end() {
+        let server = OverlayServer::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let addr = server.local_addr();
+
+        let mut page = TcpStream::connect(addr).unwrap();
+        page.write_all(b"GET /?keys=3 HTTP/1.1\r\nHost: x\r\n\r\n")
+            .unwrap();
+        let mut body = String::new();
+        page.read_to_string(&mut body).unwrap();
+        assert!(body.starts_with("HTTP/1.1 200 OK"));
+        assert!(body.contains("<html"));
+
+        let mut ws = TcpStream::connect(addr).unwrap();
+        ws.write_all(
+            b"GET /ws HTTP/1.1\r\nHost: x\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+              Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        )
+        .unwrap();
+        let mut reader = BufReader::new(ws.try_clone().unwrap());
+        let mut status = String::new();
+        reader.read_line(&mut status).unwrap();
+        assert!(status.starts_with("HTTP/1.1 101"));
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+
+        // The client is registered once the handshake has been sent
+        while server.client_count() == 0 {
+            thread::sleep(Duration::from_millis(5));
+        }
+        server.shared.publish(&Event::key_pressed(Key::Escape, 0));
+        let (opcode, payload) = read_frame(&mut reader).unwrap();
+        assert_eq!(opcode, OPCODE_TEXT);
+        let chord = format_chord(&Modifiers::default(), Key::Escape, ChordStyle::native());
+        assert_eq!(
+            String::from_utf8(payload).unwrap(),
+            format!(
+                r#"{{"type":"key","label":"Esc","modifiers":[],"chord":"{chord}","pressed":true}}"#
+            )
+        );
+
+        server.stop().unwrap();
+        let (opcode, _) = read_frame(&mut reader).unwrap();
+        assert_eq!(opcode, OPCODE_CLOSE);
+    }
+}
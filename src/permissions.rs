@@ -0,0 +1,185 @@
+//! Guiding users through the macOS Accessibility permission.
+//!
+//! Listening, grabbing and simulating all need the app to be trusted for
+//! Accessibility (System Settings → Privacy & Security → Accessibility).
+//! Until it is, hooks fail with [`Error::PermissionDenied`]. The typical
+//! onboarding is three calls: check [`accessibility_status`], send the user
+//! to the settings with [`open_accessibility_settings`], and wait for the
+//! grant with [`wait_for_accessibility`] (or [`on_granted`] without
+//! blocking).
+//!
+//! A grant is tied to the app's code signature and location. After the app
+//! is re-signed (every rebuild, for unsigned development builds) or moved,
+//! the settings can still show it as allowed while event taps fail; that is
+//! reported as [`AccessibilityStatus::StaleGrant`], and the user has to
+//! remove the app from the list and add it again.
+//!
+//! Other platforms have no such permission: the status is always
+//! [`Granted`](AccessibilityStatus::Granted), and there are no settings to
+//! open.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::permissions::{self, AccessibilityStatus};
+//! use std::time::Duration;
+//!
+//! if permissions::accessibility_status() != AccessibilityStatus::Granted {
+//!     permissions::open_accessibility_settings()?;
+//!     permissions::wait_for_accessibility(Duration::from_secs(120), Duration::from_millis(500))?;
+//! }
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::platform;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often [`on_granted`] checks the permission.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Whether the app may observe and post input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessibilityStatus {
+    /// Hooks and simulation work.
+    Granted,
+    /// The app isn't trusted for Accessibility.
+    Denied,
+    /// The app is trusted, but event taps still fail: the grant belongs to
+    /// an earlier signature or location of the app.
+    StaleGrant,
+}
+
+/// Check the Accessibility permission.
+///
+/// When the app is trusted, a throwaway listen-only event tap is created to
+/// tell a working grant from a stale one.
+pub fn accessibility_status() -> AccessibilityStatus {
+    platform::accessibility_status()
+}
+
+/// Ask the system to add the app to the Accessibility list, showing its
+/// prompt if the app isn't trusted yet. Returns whether it already is.
+///
+/// Apps only show up in the settings once they asked, so call this before
+/// [`open_accessibility_settings`].
+pub fn request_accessibility() -> bool {
+    platform::request_accessibility()
+}
+
+/// Open System Settings at the Accessibility list.
+///
+/// Fails with [`Error::NotSupported`] on platforms without the permission.
+pub fn open_accessibility_settings() -> Result<()> {
+    platform::open_accessibility_settings()
+}
+
+/// Block until the Accessibility permission is granted, checking every
+/// `poll_interval`, for at most `timeout`.
+///
+/// Fails with [`Error::PermissionDenied`] when the time runs out, saying
+/// whether the grant is missing or stale.
+pub fn wait_for_accessibility(timeout: Duration, poll_interval: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let status = accessibility_status();
+        if status == AccessibilityStatus::Granted {
+            return Ok(());
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(Error::PermissionDenied(match status {
+                AccessibilityStatus::StaleGrant => "Accessibility permission is stale: remove \
+                     the app from the Accessibility list and add it again."
+                    .into(),
+                _ => format!("Accessibility permission not granted within {timeout:?}"),
+            }));
+        }
+        thread::sleep(poll_interval.min(deadline - now));
+    }
+}
+
+/// Call `callback` once the Accessibility permission is granted, checking
+/// in the background.
+///
+/// If the permission is already granted, `callback` runs right away on the
+/// watcher thread. Dropping the returned watcher stops watching.
+pub fn on_granted(callback: impl FnOnce() + Send + 'static) -> Result<AccessibilityWatcher> {
+    let (stop, stopped) = mpsc::channel::<()>();
+    thread::Builder::new()
+        .name("monio-permissions".into())
+        .spawn(move || {
+            loop {
+                if accessibility_status() == AccessibilityStatus::Granted {
+                    callback();
+                    return;
+                }
+                match stopped.recv_timeout(WATCH_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => return,
+                }
+            }
+        })
+        .map_err(|e| Error::ThreadError(e.to_string()))?;
+    Ok(AccessibilityWatcher { _stop: stop })
+}
+
+/// Watches for the Accessibility permission, see [`on_granted`].
+///
+/// Dropping it stops watching.
+#[derive(Debug)]
+#[must_use = "watching stops as soon as the watcher is dropped"]
+pub struct AccessibilityWatcher {
+    /// Disconnects when dropped, stopping the thread.
+    _stop: Sender<()>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_granted_without_the_permission() {
+        assert_eq!(accessibility_status(), AccessibilityStatus::Granted);
+        assert!(request_accessibility());
+        assert!(matches!(
+            open_accessibility_settings(),
+            Err(Error::NotSupported(_))
+        ));
+        wait_for_accessibility(Duration::ZERO, Duration::ZERO).unwrap();
+
+        let granted = Arc::new(AtomicBool::new(false));
+        let flag = granted.clone();
+        let _watcher = on_granted(move || flag.store(true, Ordering::SeqCst)).unwrap();
+        let start = Instant::now();
+        while !granted.load(Ordering::SeqCst) && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(granted.load(Ordering::SeqCst));
+    }
+
+    /// Whether the test runner is trusted depends on the machine.
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_wait_times_out_unless_granted() {
+        let result = wait_for_accessibility(Duration::from_millis(50), Duration::from_millis(10));
+        match accessibility_status() {
+            AccessibilityStatus::Granted => assert!(result.is_ok()),
+            _ => assert!(matches!(result, Err(Error::PermissionDenied(_)))),
+        }
+
+        // Dropping the watcher stops it without calling back
+        let called = Arc::new(AtomicBool::new(false));
+        let flag = called.clone();
+        if accessibility_status() != AccessibilityStatus::Granted {
+            drop(on_granted(move || flag.store(true, Ordering::SeqCst)).unwrap());
+            thread::sleep(WATCH_INTERVAL * 2);
+            assert!(!called.load(Ordering::SeqCst));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! The hook a backend is running, for backends that can run one at a time.
+//!
+//! A backend's callbacks find the running hook's handler through state of
+//! that run (a user data pointer, a thread-local, a closure), never through
+//! a slot a second hook could overwrite. What the OS only offers once (an
+//! XRecord context to disable, a run loop or thread to stop) is claimed
+//! here, so a second hook fails with [`Error::AlreadyRunning`] instead.
+
+use crate::error::{Error, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The running flag of the hook a backend is running, if any.
+pub(crate) struct ActiveHook(Mutex<Option<Arc<AtomicBool>>>);
+
+impl ActiveHook {
+    pub(crate) const fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    fn slot(&self) -> MutexGuard<'_, Option<Arc<AtomicBool>>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Claim the backend for the hook of `running` until the claim drops.
+    ///
+    /// Fails with [`Error::AlreadyRunning`] while another hook holds it.
+    pub(crate) fn claim(&self, running: &Arc<AtomicBool>) -> Result<Claim<'_>> {
+        let mut slot = self.slot();
+        if slot.is_some() {
+            return Err(Error::AlreadyRunning);
+        }
+        *slot = Some(running.clone());
+        Ok(Claim(self))
+    }
+
+    /// Whether a hook is running and has been asked to stop.
+    ///
+    /// Stops are requested by clearing the hook's flag first, so a stop
+    /// meant for a hook that never got the backend leaves this one alone.
+    #[cfg_attr(all(target_os = "linux", not(feature = "x11")), allow(dead_code))]
+    pub(crate) fn stopping(&self) -> bool {
+        self.slot()
+            .as_ref()
+            .is_some_and(|running| !running.load(Ordering::SeqCst))
+    }
+}
+
+/// A hook's hold on an [`ActiveHook`], released on drop.
+pub(crate) struct Claim<'a>(&'a ActiveHook);
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        *self.0.slot() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_claim_is_refused() {
+        let active = ActiveHook::new();
+        let first = Arc::new(AtomicBool::new(true));
+        let second = Arc::new(AtomicBool::new(true));
+
+        let _claim = active.claim(&first).unwrap();
+        assert!(matches!(active.claim(&second), Err(Error::AlreadyRunning)));
+        // Not even the running hook claims it twice
+        assert!(matches!(active.claim(&first), Err(Error::AlreadyRunning)));
+    }
+
+    #[test]
+    fn test_claim_again_after_drop() {
+        let active = ActiveHook::new();
+        let first = Arc::new(AtomicBool::new(true));
+        let second = Arc::new(AtomicBool::new(true));
+
+        drop(active.claim(&first).unwrap());
+        let claim = active.claim(&second).unwrap();
+        drop(claim);
+        drop(active.claim(&first).unwrap());
+    }
+
+    #[test]
+    fn test_stopping_follows_the_claimed_flag() {
+        let active = ActiveHook::new();
+        let first = Arc::new(AtomicBool::new(true));
+        let second = Arc::new(AtomicBool::new(true));
+        assert!(!active.stopping());
+
+        let claim = active.claim(&first).unwrap();
+        assert!(active.claim(&second).is_err());
+        // Stopping the refused hook doesn't stop the running one
+        second.store(false, Ordering::SeqCst);
+        assert!(!active.stopping());
+        first.store(false, Ordering::SeqCst);
+        assert!(active.stopping());
+        // Nor does a stop after the hook returned
+        drop(claim);
+        assert!(!active.stopping());
+    }
+}
@@ -0,0 +1,188 @@
+//! Absolute axes (touchscreens, tablets) and their mapping to the desktop.
+//!
+//! Positions are normalized from the axis ranges, run through the device's
+//! [`InputTransform`], and scaled to the desktop: the bounding box of all
+//! displays.
+
+use crate::calibration::InputTransform;
+use crate::display::Rect;
+use evdev::{AbsoluteAxisType, raw_stream::RawDevice};
+
+/// The range of an absolute axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct AxisRange {
+    pub(super) min: i32,
+    pub(super) max: i32,
+}
+
+impl AxisRange {
+    /// Position of `value` within the range, from 0.0 to 1.0.
+    pub(super) fn normalize(&self, value: i32) -> f64 {
+        if self.max <= self.min {
+            return 0.0;
+        }
+        // In f64: the differences can overflow i32 on extreme ranges
+        let (value, min, max) = (f64::from(value), f64::from(self.min), f64::from(self.max));
+        ((value - min) / (max - min)).clamp(0.0, 1.0)
+    }
+
+    /// The range of `axis` on `device`, if it reports it.
+    pub(super) fn of(device: &RawDevice, axis: AbsoluteAxisType) -> Option<Self> {
+        if !device.supported_absolute_axes()?.contains(axis) {
+            return None;
+        }
+        let info = device.get_abs_state().ok()?[axis.0 as usize];
+        Some(Self {
+            min: info.minimum,
+            max: info.maximum,
+        })
+    }
+}
+
+/// Maps an absolute X/Y axis pair to screen coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct AbsMapping {
+    pub(super) x: AxisRange,
+    pub(super) y: AxisRange,
+    /// Where the axes map to; without a display, raw axis values are used.
+    pub(super) screen: Option<Rect>,
+    /// Calibration, applied in normalized coordinates.
+    pub(super) transform: InputTransform,
+}
+
+impl AbsMapping {
+    /// Screen position of the raw axis values.
+    pub(super) fn map(&self, x: i32, y: i32) -> (f64, f64) {
+        let Some(screen) = self.screen else {
+            return (x as f64, y as f64);
+        };
+        let (x, y) = self
+            .transform
+            .apply(self.x.normalize(x), self.y.normalize(y));
+        (
+            screen.x + x.clamp(0.0, 1.0) * screen.width,
+            screen.y + y.clamp(0.0, 1.0) * screen.height,
+        )
+    }
+}
+
+/// Pointer position from a device's `ABS_X`/`ABS_Y`.
+///
+/// A transform can mix the axes, so the last value of both is kept and each
+/// update maps the pair.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct AbsPointer {
+    pub(super) mapping: AbsMapping,
+    x: i32,
+    y: i32,
+}
+
+impl AbsPointer {
+    pub(super) fn new(mapping: AbsMapping) -> Self {
+        Self {
+            mapping,
+            x: mapping.x.min,
+            y: mapping.y.min,
+        }
+    }
+
+    /// A pointer for `device`'s `ABS_X`/`ABS_Y`, if it has them.
+    pub(super) fn for_device(
+        device: &RawDevice,
+        screen: Option<Rect>,
+        transform: InputTransform,
+    ) -> Option<Self> {
+        let mut pointer = Self::new(AbsMapping {
+            x: AxisRange::of(device, AbsoluteAxisType::ABS_X)?,
+            y: AxisRange::of(device, AbsoluteAxisType::ABS_Y)?,
+            screen,
+            transform,
+        });
+        if let Ok(abs) = device.get_abs_state() {
+            pointer.x = abs[AbsoluteAxisType::ABS_X.0 as usize].value;
+            pointer.y = abs[AbsoluteAxisType::ABS_Y.0 as usize].value;
+        }
+        Some(pointer)
+    }
+
+    /// Record an axis value, returning the new screen position if `axis` is
+    /// `ABS_X` or `ABS_Y`.
+    pub(super) fn update(&mut self, axis: AbsoluteAxisType, value: i32) -> Option<(f64, f64)> {
+        match axis {
+            AbsoluteAxisType::ABS_X => self.x = value,
+            AbsoluteAxisType::ABS_Y => self.y = value,
+            _ => return None,
+        }
+        Some(self.mapping.map(self.x, self.y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(transform: InputTransform) -> AbsMapping {
+        AbsMapping {
+            x: AxisRange { min: 0, max: 4000 },
+            y: AxisRange { min: 0, max: 2000 },
+            screen: Some(Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 1000.0,
+                height: 500.0,
+            }),
+            transform,
+        }
+    }
+
+    #[test]
+    fn test_normalize_extreme_range() {
+        let range = AxisRange {
+            min: i32::MIN,
+            max: i32::MAX,
+        };
+        assert_eq!(range.normalize(i32::MIN), 0.0);
+        assert_eq!(range.normalize(i32::MAX), 1.0);
+    }
+
+    #[test]
+    fn test_map() {
+        let plain = mapping(InputTransform::IDENTITY);
+        assert_eq!(plain.map(2000, 500), (500.0, 125.0));
+
+        // Out-of-range values are clamped to the screen
+        assert_eq!(plain.map(5000, -5), (1000.0, 0.0));
+
+        // Without a display, raw values are used
+        let raw = AbsMapping {
+            screen: None,
+            ..plain
+        };
+        assert_eq!(raw.map(1234, -5), (1234.0, -5.0));
+    }
+
+    #[test]
+    fn test_map_with_transform() {
+        let flipped = mapping(InputTransform::flip_x());
+        assert_eq!(flipped.map(1000, 500), (750.0, 125.0));
+
+        // The panel's top-left corner is the screen's top-right corner
+        let rotated = mapping(InputTransform::rotate_90());
+        assert_eq!(rotated.map(0, 0), (1000.0, 0.0));
+        assert_eq!(rotated.map(0, 2000), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_pointer_mixes_axes() {
+        let mut pointer = AbsPointer::new(mapping(InputTransform::swap_axes()));
+        assert_eq!(
+            pointer.update(AbsoluteAxisType::ABS_X, 4000),
+            Some((0.0, 500.0))
+        );
+        assert_eq!(
+            pointer.update(AbsoluteAxisType::ABS_Y, 1000),
+            Some((500.0, 500.0))
+        );
+        assert_eq!(pointer.update(AbsoluteAxisType::ABS_PRESSURE, 10), None);
+    }
+}
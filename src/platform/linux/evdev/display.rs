@@ -1,26 +1,41 @@
-//! evdev backend has no display/system settings access.
+//! evdev backend display queries, from the kernel's DRM connectors.
+//!
+//! There is no window system to ask, so outputs are read from
+//! `/sys/class/drm/card*-*`: each connector directory has a `status`
+//! (`connected`/`disconnected`), an `enabled` flag and the supported `modes`,
+//! preferred mode first. This works on the console and in kiosk sessions,
+//! but positions and scaling are guessed (see [`DisplaySource::Drm`]).
 
 #![allow(dead_code)]
 
-use crate::display::{DisplayInfo, SystemSettings};
+use crate::display::{DisplayInfo, DisplaySource, Rect, SystemSettings};
 use crate::error::{Error, Result};
+use std::fs;
+use std::path::Path;
+
+const DRM_CLASS_DIR: &str = "/sys/class/drm";
 
 pub fn displays() -> Result<Vec<DisplayInfo>> {
-    Err(Error::NotSupported(
-        "Display information not available for evdev backend".into(),
-    ))
+    let displays = drm_displays(Path::new(DRM_CLASS_DIR))?;
+    if displays.is_empty() {
+        return Err(Error::NotSupported(
+            "No connected DRM outputs found for evdev backend".into(),
+        ));
+    }
+    Ok(displays)
 }
 
 pub fn primary_display() -> Result<DisplayInfo> {
-    Err(Error::NotSupported(
-        "Display information not available for evdev backend".into(),
-    ))
+    displays()?
+        .into_iter()
+        .find(|display| display.is_primary)
+        .ok_or_else(|| Error::Platform("No primary display found".into()))
 }
 
-pub fn display_at_point(_x: f64, _y: f64) -> Result<Option<DisplayInfo>> {
-    Err(Error::NotSupported(
-        "Display information not available for evdev backend".into(),
-    ))
+pub fn display_at_point(x: f64, y: f64) -> Result<Option<DisplayInfo>> {
+    Ok(displays()?
+        .into_iter()
+        .find(|display| display.bounds.contains(x, y)))
 }
 
 pub fn system_settings() -> Result<SystemSettings> {
@@ -28,3 +43,193 @@ pub fn system_settings() -> Result<SystemSettings> {
         "System settings not available for evdev backend".into(),
     ))
 }
+
+/// A connected DRM output and its preferred resolution.
+#[derive(Debug, PartialEq)]
+struct Connector {
+    /// Directory name, e.g. `card0-eDP-1`.
+    name: String,
+    width: u32,
+    height: u32,
+}
+
+impl Connector {
+    /// Built-in panels, which make the best guess for the primary display.
+    fn is_internal(&self) -> bool {
+        let kind = self.name.split_once('-').map_or("", |(_, kind)| kind);
+        ["eDP", "LVDS", "DSI"]
+            .iter()
+            .any(|internal| kind.starts_with(internal))
+    }
+}
+
+/// Connected outputs under `class_dir`, laid out left to right.
+fn drm_displays(class_dir: &Path) -> Result<Vec<DisplayInfo>> {
+    let entries = fs::read_dir(class_dir).map_err(|e| {
+        Error::NotSupported(format!(
+            "Cannot read {} for evdev backend: {}",
+            class_dir.display(),
+            e
+        ))
+    })?;
+
+    let mut connectors: Vec<Connector> = entries
+        .flatten()
+        .filter_map(|entry| read_connector(&entry.path()))
+        .collect();
+    connectors.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let primary = connectors
+        .iter()
+        .position(Connector::is_internal)
+        .unwrap_or(0);
+
+    let mut x = 0.0;
+    Ok(connectors
+        .iter()
+        .enumerate()
+        .map(|(index, connector)| {
+            let bounds = Rect {
+                x,
+                y: 0.0,
+                width: connector.width as f64,
+                height: connector.height as f64,
+            };
+            x += bounds.width;
+            DisplayInfo {
+                id: index as u32 + 1,
+                bounds,
+                scale_factor: 1.0,
+                refresh_rate: None,
+                is_primary: index == primary,
+                source: DisplaySource::Drm,
+            }
+        })
+        .collect())
+}
+
+/// Read a connector directory (`card<N>-<connector>`), if it is a connected,
+/// enabled output with at least one mode.
+fn read_connector(dir: &Path) -> Option<Connector> {
+    let name = dir.file_name()?.to_str()?.to_string();
+    let (card, _) = name.split_once('-')?;
+    if !card.starts_with("card") {
+        return None;
+    }
+
+    let read = |file: &str| fs::read_to_string(dir.join(file)).ok();
+    if read("status")?.trim() != "connected" {
+        return None;
+    }
+    if read("enabled").is_some_and(|enabled| enabled.trim() == "disabled") {
+        return None;
+    }
+    let (width, height) = read("modes")?.lines().find_map(parse_mode)?;
+
+    Some(Connector {
+        name,
+        width,
+        height,
+    })
+}
+
+/// Parse a mode line like `1920x1080` (interlaced modes end in `i`).
+fn parse_mode(line: &str) -> Option<(u32, u32)> {
+    let (width, height) = line.trim().split_once('x')?;
+    let height = height.trim_end_matches('i');
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A fake `/sys/class/drm` with the given connectors.
+    fn fake_drm(name: &str, connectors: &[(&str, &str, &str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("monio_test_drm_{}", name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("card0")).unwrap();
+        fs::write(root.join("version"), "drm 1.1.0\n").unwrap();
+        for (connector, status, enabled, modes) in connectors {
+            let dir = root.join(connector);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("status"), format!("{status}\n")).unwrap();
+            fs::write(dir.join("enabled"), format!("{enabled}\n")).unwrap();
+            fs::write(dir.join("modes"), modes).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("1920x1080\n"), Some((1920, 1080)));
+        assert_eq!(parse_mode("1920x1080i"), Some((1920, 1080)));
+        assert_eq!(parse_mode("garbage"), None);
+    }
+
+    #[test]
+    fn test_drm_displays() {
+        let root = fake_drm(
+            "layout",
+            &[
+                (
+                    "card0-HDMI-A-1",
+                    "connected",
+                    "enabled",
+                    "1920x1080\n1280x720\n",
+                ),
+                ("card0-eDP-1", "connected", "enabled", "2560x1600\n"),
+                ("card0-DP-1", "disconnected", "disabled", ""),
+                ("card0-DP-2", "connected", "disabled", "1920x1080\n"),
+                ("card0-DP-3", "connected", "enabled", ""),
+            ],
+        );
+
+        let displays = drm_displays(&root).unwrap();
+        assert_eq!(displays.len(), 2);
+
+        // Sorted by connector name: HDMI-A-1, then eDP-1 to its right
+        assert_eq!(
+            displays[0].bounds,
+            Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 1920.0,
+                height: 1080.0
+            }
+        );
+        assert_eq!(displays[1].bounds.x, 1920.0);
+        assert_eq!(displays[1].bounds.width, 2560.0);
+
+        // The built-in panel is the primary display
+        assert!(!displays[0].is_primary);
+        assert!(displays[1].is_primary);
+        assert!(
+            displays
+                .iter()
+                .all(|d| d.source == DisplaySource::Drm && d.scale_factor == 1.0)
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_drm_first_output_is_primary_without_panel() {
+        let root = fake_drm(
+            "external",
+            &[
+                ("card1-HDMI-A-1", "connected", "enabled", "1280x800\n"),
+                ("card0-DP-1", "connected", "enabled", "3840x2160\n"),
+            ],
+        );
+
+        let displays = drm_displays(&root).unwrap();
+        assert_eq!(displays.len(), 2);
+        assert_eq!(displays[0].bounds.width, 3840.0);
+        assert!(displays[0].is_primary);
+        assert!(!displays[1].is_primary);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
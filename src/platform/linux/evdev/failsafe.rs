@@ -0,0 +1,58 @@
+//! Key state for the failsafe, read with `EVIOCGKEY`.
+//!
+//! The kernel keeps a device's key state whoever reads or grabs it, so
+//! polling it sees the chord even while monio holds the devices.
+
+use super::listen::enumerate_devices;
+use crate::error::{Error, Result};
+use crate::keycode::Key;
+use crate::platform::linux::keycodes::key_to_evdev_keycode;
+use evdev::raw_stream::RawDevice;
+
+/// Reads whether keys are held on any keyboard.
+pub(crate) struct KeyProbe {
+    codes: Vec<u16>,
+    devices: Vec<RawDevice>,
+}
+
+impl KeyProbe {
+    /// Open the devices that have any of `keys`.
+    pub(crate) fn open(keys: &[Key]) -> Result<Self> {
+        let codes: Vec<u16> = keys.iter().map(|&key| key_to_evdev_keycode(key)).collect();
+        if codes.contains(&0) {
+            return Err(Error::NotSupported(format!(
+                "No evdev key code for a key of {:?}",
+                keys
+            )));
+        }
+        let devices: Vec<RawDevice> = enumerate_devices()?
+            .into_iter()
+            .map(|(_, device)| device)
+            .filter(|device| {
+                device.supported_keys().is_some_and(|supported| {
+                    codes
+                        .iter()
+                        .any(|&code| supported.contains(evdev::Key::new(code)))
+                })
+            })
+            .collect();
+        if devices.is_empty() {
+            return Err(Error::PermissionDenied(
+                "No keyboard accessible for the failsafe".into(),
+            ));
+        }
+        Ok(Self { codes, devices })
+    }
+
+    /// Whether all the keys are held, across devices.
+    pub(crate) fn all_held(&mut self) -> bool {
+        let held: Vec<_> = self
+            .devices
+            .iter()
+            .filter_map(|device| device.get_key_state().ok())
+            .collect();
+        self.codes
+            .iter()
+            .all(|&code| held.iter().any(|keys| keys.contains(evdev::Key::new(code))))
+    }
+}
@@ -4,72 +4,469 @@
 //! Works on both X11 and Wayland.
 
 #![allow(dead_code)]
+#![warn(clippy::undocumented_unsafe_blocks)]
 
+use crate::calibration::{DeviceTransform, InputTransform};
+use crate::convert;
+use crate::debug;
+use crate::display::Rect;
 use crate::error::{Error, Result};
-use crate::event::{Button, Event, ScrollDirection};
-use crate::hook::{EventHandler, GrabHandler};
-use crate::platform::linux::evdev::simulate::emit_event;
+use crate::event::{Backend, Button, Event, ScrollDirection};
+use crate::hook::{EventHandler, EventMask, GrabHandler};
+use crate::keycode::Key;
+use crate::platform::ActiveHook;
+use crate::platform::linux::evdev::abs::AbsPointer;
+use crate::platform::linux::evdev::simulate::{VIRTUAL_DEVICE_NAME, emit_event};
+use crate::platform::linux::evdev::touch::{TouchStep, TouchTracker};
 use crate::platform::linux::keycodes::evdev_keycode_to_key;
-use crate::state::{
-    self, MASK_ALT, MASK_BUTTON1, MASK_BUTTON2, MASK_BUTTON3, MASK_BUTTON4, MASK_BUTTON5,
-    MASK_CTRL, MASK_META, MASK_SHIFT,
+use crate::state::{self, MASK_ALT, MASK_CTRL, MASK_META, MASK_SHIFT, button_to_mask};
+use evdev::{
+    BusType, EventType as EvdevEventType, InputEvent, InputEventKind, Synchronization,
+    raw_stream::RawDevice,
 };
-use evdev::{Device, EventType as EvdevEventType, InputEventKind};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::os::unix::io::AsRawFd;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
-/// Flag to signal stopping
-static STOP_FLAG: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+/// The running grab. Listeners each read the devices on their own, but
+/// grabs share the statics below, so they run one at a time.
+static GRAB: ActiveHook = ActiveHook::new();
 
 /// Current mouse position (evdev gives relative motion, we need to track absolute)
 static MOUSE_POS: Mutex<(f64, f64)> = Mutex::new((0.0, 0.0));
 
-/// Update modifier mask from keycode
-fn update_key_modifier(code: u16, pressed: bool) {
-    let mask = match code {
-        42 | 54 => MASK_SHIFT,  // KEY_LEFTSHIFT, KEY_RIGHTSHIFT
-        29 | 97 => MASK_CTRL,   // KEY_LEFTCTRL, KEY_RIGHTCTRL
-        56 | 100 => MASK_ALT,   // KEY_LEFTALT, KEY_RIGHTALT
-        125 | 126 => MASK_META, // KEY_LEFTMETA, KEY_RIGHTMETA
-        _ => return,
-    };
+/// Number of `SYN_DROPPED` frames recovered from since the process started.
+static DROPPED_FRAMES: AtomicU64 = AtomicU64::new(0);
+
+/// Number of times the kernel reported dropped events (`SYN_DROPPED`).
+///
+/// Each drop is followed by a re-sync with the device's key state; a
+/// steadily growing count means the handler can't keep up with the input.
+pub fn dropped_frame_count() -> u64 {
+    DROPPED_FRAMES.load(Ordering::Relaxed)
+}
+
+/// Devices currently held by the grab loop.
+static GRABBED: Mutex<Vec<GrabbedDevice>> = Mutex::new(Vec::new());
+
+/// Set to ask the grab loop to release its devices.
+static FORCE_UNGRAB: AtomicBool = AtomicBool::new(false);
+
+/// How many grabbed devices [`emergency_release`] can release.
+const GRABBED_FD_SLOTS: usize = 64;
+
+/// File descriptors of the grabbed devices, -1 in unused slots. Atomics, so
+/// [`emergency_release`] doesn't wait for a lock the grab loop might hold.
+static GRABBED_FDS: [AtomicI32; GRABBED_FD_SLOTS] =
+    [const { AtomicI32::new(-1) }; GRABBED_FD_SLOTS];
+
+/// `EVIOCGRAB`, `_IOW('E', 0x90, int)`.
+const EVIOCGRAB: u32 = 0x4004_4590;
+
+/// Whether the grab loop should hold its devices, see [`set_grab_enabled`].
+static GRAB_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Whether touchscreens without multitouch drive the mouse, see
+/// [`set_touch_as_mouse`].
+static TOUCH_AS_MOUSE: AtomicBool = AtomicBool::new(true);
+
+/// Categories whose devices are opened, see [`set_event_mask`].
+static EVENT_MASK: AtomicU32 = AtomicU32::new(EventMask::ALL.bits());
+
+/// Calibration for absolute devices, see [`set_input_transforms`].
+static INPUT_TRANSFORMS: Mutex<Vec<DeviceTransform>> = Mutex::new(Vec::new());
+
+/// An input device held exclusively by grab mode, see [`grab_status`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrabbedDevice {
+    /// Device node, e.g. `/dev/input/event3`.
+    pub path: PathBuf,
+    /// Name the device reports.
+    pub name: String,
+    /// When the device was grabbed.
+    pub grabbed_at: SystemTime,
+}
+
+/// Devices the grab loop currently holds exclusively.
+///
+/// Empty when no grab hook is running, or after [`force_ungrab_all`].
+pub fn grab_status() -> Vec<GrabbedDevice> {
+    GRABBED.lock().map(|g| g.clone()).unwrap_or_default()
+}
+
+/// Release every grabbed device, from any thread.
+///
+/// The grab loop ungrabs its devices on its next iteration (within about
+/// 100ms) and keeps running in listen mode: the handler still sees events,
+/// but they reach other applications whatever it returns. An
+/// [`EventType::GrabSuspended`](crate::EventType::GrabSuspended) event is
+/// dispatched once the devices are released. Does nothing if no devices
+/// are grabbed.
+pub fn force_ungrab_all() {
+    let grabbed = GRABBED.lock().map(|g| !g.is_empty()).unwrap_or(true);
+    if grabbed {
+        FORCE_UNGRAB.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Ungrab every grabbed device right away, from any thread, even while the
+/// grab loop is stuck in a handler.
+///
+/// Calls `EVIOCGRAB` on the loop's file descriptors directly, then tells
+/// the loop to carry on in listen mode like [`force_ungrab_all`], once it
+/// runs again.
+pub(crate) fn emergency_release() {
+    FORCE_UNGRAB.store(true, Ordering::SeqCst);
+    for slot in &GRABBED_FDS {
+        let fd = slot.load(Ordering::SeqCst);
+        if fd >= 0 {
+            // SAFETY: EVIOCGRAB takes an int by value. At worst the fd was
+            // just closed and reused, and the ioctl fails.
+            let result = unsafe { libc::ioctl(fd, EVIOCGRAB as _, 0 as libc::c_int) };
+            if result < 0 {
+                log::debug!(
+                    "Failed to ungrab fd {}: {}",
+                    fd,
+                    std::io::Error::last_os_error()
+                );
+            }
+        }
+    }
+    log::warn!("Failsafe released all grabbed input devices");
+}
+
+/// Publish the grabbed devices' fds for [`emergency_release`], or clear them.
+fn publish_grabbed_fds(devices: &[RawDevice]) {
+    if devices.len() > GRABBED_FD_SLOTS {
+        log::warn!(
+            "Only the first {} of {} grabbed devices can be released by the failsafe",
+            GRABBED_FD_SLOTS,
+            devices.len()
+        );
+    }
+    for (i, slot) in GRABBED_FDS.iter().enumerate() {
+        let fd = devices.get(i).map_or(-1, |device| device.as_raw_fd());
+        slot.store(fd, Ordering::SeqCst);
+    }
+}
+
+/// Release the grabbed devices while the grab loop keeps reading them, or
+/// grab them again.
+///
+/// Takes effect on the loop's next iteration. Has no effect after
+/// [`force_ungrab_all`].
+pub(crate) fn set_grab_enabled(enabled: bool) {
+    GRAB_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Whether single-touch axes (`ABS_X`/`ABS_Y`, `BTN_TOUCH`) of touchscreens
+/// move the mouse, or are reported as touches.
+///
+/// Multitouch contacts are reported as touches either way. Takes effect
+/// immediately, also for running hooks.
+pub(crate) fn set_touch_as_mouse(enabled: bool) {
+    TOUCH_AS_MOUSE.store(enabled, Ordering::SeqCst);
+}
 
-    if pressed {
-        state::set_mask(mask);
-    } else {
-        state::unset_mask(mask);
+/// Calibration transforms for absolute devices, matched by device name or
+/// node. The first match wins.
+///
+/// Takes effect for devices opened afterwards, i.e. when a hook starts.
+pub(crate) fn set_input_transforms(transforms: Vec<DeviceTransform>) {
+    if let Ok(mut current) = INPUT_TRANSFORMS.lock() {
+        *current = transforms;
+    }
+}
+
+/// Open only devices reporting events in `mask`.
+///
+/// Takes effect for devices opened afterwards, i.e. when a hook starts.
+pub(crate) fn set_event_mask(mask: EventMask) {
+    EVENT_MASK.store(mask.bits(), Ordering::SeqCst);
+}
+
+/// Whether a device may report events in `events`.
+///
+/// Keys below `BTN_0` make a keyboard; pointer axes or buttons make a
+/// mouse or touch device. Devices with both, like keyboards with a
+/// trackpoint, are kept for either.
+fn reports(device: &RawDevice, events: EventMask) -> bool {
+    let supported = device.supported_events();
+    let (mut keys, mut buttons) = (false, false);
+    if let Some(codes) = device.supported_keys() {
+        for key in codes.iter() {
+            if key.code() < evdev::Key::BTN_0.code() {
+                keys = true;
+            } else {
+                buttons = true;
+            }
+        }
+    }
+    let pointer = buttons
+        || supported.contains(EvdevEventType::RELATIVE)
+        || supported.contains(EvdevEventType::ABSOLUTE);
+    (keys && events.contains(EventMask::KEYBOARD))
+        || (pointer && events.intersects(EventMask::MOUSE | EventMask::TOUCH))
+}
+
+/// The configured transform for a device.
+fn input_transform(device: &RawDevice, path: &Path) -> Option<InputTransform> {
+    let name = device.name().unwrap_or_default();
+    let path = path.to_string_lossy();
+    INPUT_TRANSFORMS
+        .lock()
+        .ok()?
+        .iter()
+        .find(|t| t.matches(name, &path))
+        .map(|t| t.transform)
+}
+
+/// Per-device state kept by the event loops.
+struct DeviceState {
+    sync: SyncState,
+    /// Set for touchscreens.
+    touch: Option<TouchTracker>,
+    /// Set for touchscreens and calibrated devices, whose `ABS_X`/`ABS_Y`
+    /// are mapped to the desktop.
+    pointer: Option<AbsPointer>,
+}
+
+impl DeviceState {
+    fn new(device: &RawDevice, path: &Path, screen: Option<Rect>) -> Self {
+        let transform = input_transform(device, path);
+        let calibration = transform.unwrap_or_default();
+        let touch = TouchTracker::for_device(device, screen, calibration);
+        let pointer = (touch.is_some() || transform.is_some())
+            .then(|| AbsPointer::for_device(device, screen, calibration))
+            .flatten();
+        Self {
+            sync: SyncState::default(),
+            touch,
+            pointer,
+        }
+    }
+}
+
+/// Where absolute devices map to: the bounding box of all displays, if they
+/// can be found.
+fn desktop_bounds() -> Option<Rect> {
+    let displays = super::display::displays()
+        .map_err(|e| log::debug!("No display for absolute device mapping: {}", e))
+        .ok()?;
+    displays
+        .iter()
+        .map(|display| display.bounds)
+        .reduce(|a, b| {
+            let x = a.x.min(b.x);
+            let y = a.y.min(b.y);
+            Rect {
+                x,
+                y,
+                width: (a.x + a.width).max(b.x + b.width) - x,
+                height: (a.y + a.height).max(b.y + b.height) - y,
+            }
+        })
+}
+
+/// Read a device's pending events and convert them, calling `deliver` with
+/// each raw event and the events it produced.
+///
+/// Touch events are produced at the end of their frame, with all the raw
+/// events of the frame.
+fn read_converted(
+    device: &mut RawDevice,
+    state: &mut DeviceState,
+    mut deliver: impl FnMut(&[InputEvent], Vec<Event>),
+) {
+    let injected = is_virtual_device(device);
+    let touch_as_mouse = TOUCH_AS_MOUSE.load(Ordering::SeqCst);
+    let DeviceState {
+        sync,
+        touch,
+        pointer,
+    } = state;
+    read_device(device, sync, |ev| {
+        let step = touch
+            .as_mut()
+            .map_or(TouchStep::NotTouch, |t| t.process(ev, touch_as_mouse));
+        let (raw, mut events) = match step {
+            TouchStep::Pending => return,
+            TouchStep::Frame { events, raw } => (raw, events),
+            TouchStep::NotTouch => (
+                vec![*ev],
+                convert_event(ev, pointer.as_mut()).into_iter().collect(),
+            ),
+        };
+        for event in &mut events {
+            event.injected = Some(injected);
+        }
+        deliver(&raw, events);
+    });
+}
+
+/// Grab or ungrab every device, logging failures.
+fn set_devices_grabbed(devices: &mut [RawDevice], grab: bool) -> Vec<bool> {
+    devices
+        .iter_mut()
+        .map(|device| {
+            let result = if grab { device.grab() } else { device.ungrab() };
+            if let Err(e) = &result {
+                log::warn!(
+                    "Failed to {} device {}: {}",
+                    if grab { "grab" } else { "ungrab" },
+                    device.name().unwrap_or("unknown"),
+                    e
+                );
+            }
+            result.is_ok()
+        })
+        .collect()
+}
+
+/// What to do with a raw event, see [`SyncState::filter`].
+#[derive(Debug, PartialEq, Eq)]
+enum Step {
+    /// Handle the event normally.
+    Deliver,
+    /// Discard the event (part of a dropped frame).
+    Skip,
+    /// The dropped frame ended; re-read the device state.
+    Resync,
+}
+
+/// Per-device state for recovering from `SYN_DROPPED`.
+#[derive(Debug, Default)]
+struct SyncState {
+    /// Discarding events until the `SYN_REPORT` ending the dropped frame.
+    dropping: bool,
+    /// Key and button codes reported as down.
+    held: HashSet<u16>,
+}
+
+impl SyncState {
+    fn filter(&mut self, ev: &InputEvent) -> Step {
+        match ev.kind() {
+            InputEventKind::Synchronization(Synchronization::SYN_DROPPED) => {
+                self.dropping = true;
+                Step::Skip
+            }
+            InputEventKind::Synchronization(Synchronization::SYN_REPORT) if self.dropping => {
+                self.dropping = false;
+                Step::Resync
+            }
+            _ if self.dropping => Step::Skip,
+            InputEventKind::Key(key) => {
+                match ev.value() {
+                    0 => {
+                        self.held.remove(&key.code());
+                    }
+                    1 => {
+                        self.held.insert(key.code());
+                    }
+                    _ => {}
+                }
+                Step::Deliver
+            }
+            _ => Step::Deliver,
+        }
+    }
+
+    /// Bring `held` in line with the keys the kernel reports `down`.
+    ///
+    /// Returns corrective events: releases for keys that went up during the
+    /// drop, then presses for keys that went down, each in code order.
+    fn reconcile(&mut self, down: &HashSet<u16>) -> Vec<InputEvent> {
+        let mut released: Vec<u16> = self.held.difference(down).copied().collect();
+        let mut pressed: Vec<u16> = down.difference(&self.held).copied().collect();
+        released.sort_unstable();
+        pressed.sort_unstable();
+        self.held.clone_from(down);
+
+        released
+            .into_iter()
+            .map(|code| InputEvent::new(EvdevEventType::KEY, code, 0))
+            .chain(
+                pressed
+                    .into_iter()
+                    .map(|code| InputEvent::new(EvdevEventType::KEY, code, 1)),
+            )
+            .collect()
+    }
+}
+
+/// Read a device's pending events, recovering from `SYN_DROPPED`.
+///
+/// Events of a dropped frame are discarded; once it ends, the device's key
+/// state (`EVIOCGKEY`) is read back and corrective events are delivered in
+/// their place.
+fn read_device(device: &mut RawDevice, sync: &mut SyncState, mut deliver: impl FnMut(&InputEvent)) {
+    let Ok(events) = device.fetch_events() else {
+        return;
+    };
+    let events: Vec<InputEvent> = events.collect();
+
+    for ev in &events {
+        match sync.filter(ev) {
+            Step::Deliver => deliver(ev),
+            Step::Skip => {
+                if ev.kind() == InputEventKind::Synchronization(Synchronization::SYN_DROPPED) {
+                    let count = DROPPED_FRAMES.fetch_add(1, Ordering::Relaxed) + 1;
+                    log::warn!(
+                        "{}: kernel dropped input events (SYN_DROPPED, {} so far), re-syncing",
+                        device.name().unwrap_or("unknown device"),
+                        count
+                    );
+                }
+            }
+            Step::Resync => match device.get_key_state() {
+                Ok(keys) => {
+                    let down: HashSet<u16> = keys.iter().map(|key| key.code()).collect();
+                    for correction in sync.reconcile(&down) {
+                        deliver(&correction);
+                    }
+                }
+                Err(e) => log::warn!("Failed to read key state after SYN_DROPPED: {}", e),
+            },
+        }
     }
 }
 
 /// Convert evdev button code to Button enum
 fn code_to_button(code: u16) -> Option<Button> {
-    match code {
-        0x110 => Some(Button::Left),    // BTN_LEFT
-        0x111 => Some(Button::Right),   // BTN_RIGHT
-        0x112 => Some(Button::Middle),  // BTN_MIDDLE
-        0x113 => Some(Button::Button4), // BTN_SIDE
-        0x114 => Some(Button::Button5), // BTN_EXTRA
-        _ => None,
-    }
+    Button::from_platform_code(Backend::Evdev, code.into())
 }
 
 /// Get button mask for code
 fn code_to_mask(code: u16) -> u32 {
-    match code {
-        0x110 => MASK_BUTTON1,
-        0x111 => MASK_BUTTON2,
-        0x112 => MASK_BUTTON3,
-        0x113 => MASK_BUTTON4,
-        0x114 => MASK_BUTTON5,
-        _ => 0,
+    code_to_button(code).map_or(0, |button| button_to_mask(button.number()))
+}
+
+/// Mouse buttons held on any device (`EVIOCGKEY`), as a `MASK_BUTTON*` mask.
+pub(crate) fn held_buttons() -> Option<u32> {
+    let devices = enumerate_devices().ok()?;
+    let mut held = 0;
+    for (_, device) in devices {
+        let has_buttons = device
+            .supported_keys()
+            .is_some_and(|keys| keys.contains(evdev::Key::BTN_LEFT));
+        if !has_buttons {
+            continue;
+        }
+        if let Ok(keys) = device.get_key_state() {
+            for key in keys.iter() {
+                held |= code_to_mask(key.code());
+            }
+        }
     }
+    Some(held)
 }
 
-/// Enumerate all input devices
-fn enumerate_devices() -> Result<Vec<Device>> {
+/// Enumerate all input devices, with their device nodes
+pub(super) fn enumerate_devices() -> Result<Vec<(PathBuf, RawDevice)>> {
     let mut devices = Vec::new();
 
     let dir = fs::read_dir("/dev/input").map_err(|e| {
@@ -84,14 +481,14 @@ fn enumerate_devices() -> Result<Vec<Device>> {
         if let Some(name) = path.file_name() {
             let name = name.to_string_lossy();
             if name.starts_with("event") {
-                match Device::open(&path) {
+                match RawDevice::open(&path) {
                     Ok(device) => {
                         // Only include devices that have key or relative events
                         let supported = device.supported_events();
                         if supported.contains(EvdevEventType::KEY)
                             || supported.contains(EvdevEventType::RELATIVE)
                         {
-                            devices.push(device);
+                            devices.push((path, device));
                         }
                     }
                     Err(e) => {
@@ -136,31 +533,31 @@ impl<H: GrabHandler> GrabHandlerWrapper<H> {
     }
 }
 
-/// Run the event hook (blocking).
-pub fn run_hook<H: EventHandler + 'static>(running: &Arc<AtomicBool>, handler: H) -> Result<()> {
-    // Store stop flag
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = Some(running.clone());
+/// Devices grabbed for exclusive access, ungrabbed on drop.
+///
+/// The kernel drops a grab when its fd closes, but ungrabbing explicitly
+/// releases the devices even if something still holds the fds.
+struct GrabbedDevices(Vec<RawDevice>);
+
+impl Drop for GrabbedDevices {
+    fn drop(&mut self) {
+        publish_grabbed_fds(&[]);
+        for device in &mut self.0 {
+            let _ = device.ungrab();
+        }
+        if let Ok(mut grabbed) = GRABBED.lock() {
+            grabbed.clear();
+        }
     }
+}
 
+/// Run the event hook (blocking).
+pub fn run_hook<H: EventHandler + 'static>(running: &Arc<AtomicBool>, handler: H) -> Result<()> {
     let wrapper = ListenHandler { handler };
     run_event_loop(running, |event| {
         wrapper.handle(event);
         true // Always pass through in listen mode
-    })?;
-
-    // Cleanup
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = None;
-    }
-
-    Ok(())
+    })
 }
 
 /// Run the event hook with grab capability (blocking).
@@ -168,24 +565,31 @@ pub fn run_grab_hook<H: GrabHandler + 'static>(
     running: &Arc<AtomicBool>,
     handler: H,
 ) -> Result<()> {
-    // Store stop flag
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = Some(running.clone());
-    }
-
+    let _claim = GRAB.claim(running)?;
     let wrapper = GrabHandlerWrapper { handler };
 
     // For grab mode, we need to grab the devices
     let devices = enumerate_devices()?;
-    let mut grabbed_devices = Vec::new();
-
-    for mut device in devices {
+    let events = EventMask::from_bits_truncate(EVENT_MASK.load(Ordering::SeqCst));
+    let mut grabbed_devices = GrabbedDevices(Vec::new());
+    let mut status = Vec::new();
+    let mut paths = Vec::new();
+
+    for (path, mut device) in devices {
+        // Our own virtual device carries pass-through and replayed events,
+        // and devices without wanted events are left to pass through
+        if device.name() == Some(VIRTUAL_DEVICE_NAME) || !reports(&device, events) {
+            continue;
+        }
         // Try to grab the device (exclusive access)
         if device.grab().is_ok() {
-            grabbed_devices.push(device);
+            status.push(GrabbedDevice {
+                path: path.clone(),
+                name: device.name().unwrap_or("unknown").to_string(),
+                grabbed_at: SystemTime::now(),
+            });
+            grabbed_devices.0.push(device);
+            paths.push(path);
         } else {
             log::warn!(
                 "Failed to grab device: {}",
@@ -194,33 +598,32 @@ pub fn run_grab_hook<H: GrabHandler + 'static>(
         }
     }
 
-    if grabbed_devices.is_empty() {
+    if grabbed_devices.0.is_empty() {
         return Err(Error::PermissionDenied(
             "Could not grab any input devices. Make sure you're in the 'input' group.".into(),
         ));
     }
 
+    FORCE_UNGRAB.store(false, Ordering::SeqCst);
+    if let Ok(mut grabbed) = GRABBED.lock() {
+        *grabbed = status;
+    }
+    publish_grabbed_fds(&grabbed_devices.0);
+
     // Send hook enabled event
     let _ = wrapper.handle(&Event::hook_enabled());
 
     // Event loop with grabbed devices
-    run_grabbed_event_loop(running, &mut grabbed_devices, |event| wrapper.handle(event))?;
-
-    // Send hook disabled event
-    let _ = wrapper.handle(&Event::hook_disabled());
+    let result = run_grabbed_event_loop(running, &mut grabbed_devices.0, &paths, |event| {
+        wrapper.handle(event)
+    });
 
     // Ungrab devices
-    for mut device in grabbed_devices {
-        let _ = device.ungrab();
-    }
+    drop(grabbed_devices);
+    result?;
 
-    // Cleanup
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = None;
-    }
+    // Send hook disabled event
+    let _ = wrapper.handle(&Event::hook_disabled());
 
     Ok(())
 }
@@ -230,7 +633,11 @@ fn run_event_loop<F>(running: &Arc<AtomicBool>, mut callback: F) -> Result<()>
 where
     F: FnMut(&Event) -> bool,
 {
-    let devices = enumerate_devices()?;
+    let events = EventMask::from_bits_truncate(EVENT_MASK.load(Ordering::SeqCst));
+    let devices: Vec<_> = enumerate_devices()?
+        .into_iter()
+        .filter(|(_, device)| reports(device, events))
+        .collect();
 
     // Send hook enabled event
     callback(&Event::hook_enabled());
@@ -238,7 +645,7 @@ where
     // Create poll fds
     let mut poll_fds: Vec<libc::pollfd> = devices
         .iter()
-        .map(|d| libc::pollfd {
+        .map(|(_, d)| libc::pollfd {
             fd: d.as_raw_fd(),
             events: libc::POLLIN,
             revents: 0,
@@ -246,11 +653,19 @@ where
         .collect();
 
     // Store devices in a map for easy lookup
-    let mut device_map: HashMap<i32, Device> =
-        devices.into_iter().map(|d| (d.as_raw_fd(), d)).collect();
+    let screen = desktop_bounds();
+    let mut device_map: HashMap<i32, (RawDevice, DeviceState)> = devices
+        .into_iter()
+        .map(|(path, d)| {
+            let state = DeviceState::new(&d, &path, screen);
+            (d.as_raw_fd(), (d, state))
+        })
+        .collect();
 
     while running.load(Ordering::SeqCst) {
         // Poll with timeout
+        // SAFETY: the pointer and length describe `poll_fds`, which poll
+        // only writes `revents` of
         let ret = unsafe { libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as _, 100) };
 
         if ret < 0 {
@@ -276,14 +691,12 @@ where
         }
 
         // Simplified approach: iterate and fetch events
-        for device in device_map.values_mut() {
-            if let Ok(events) = device.fetch_events() {
-                for ev in events {
-                    if let Some(event) = convert_event(&ev) {
-                        callback(&event);
-                    }
+        for (device, state) in device_map.values_mut() {
+            read_converted(device, state, |_, events| {
+                for event in &events {
+                    callback(event);
                 }
-            }
+            });
         }
     }
 
@@ -296,7 +709,8 @@ where
 /// Event loop for grab mode (with device grabbing)
 fn run_grabbed_event_loop<F>(
     running: &Arc<AtomicBool>,
-    devices: &mut [Device],
+    devices: &mut [RawDevice],
+    paths: &[PathBuf],
     mut callback: F,
 ) -> Result<()>
 where
@@ -311,9 +725,61 @@ where
             revents: 0,
         })
         .collect();
+    let screen = desktop_bounds();
+    let mut states: Vec<DeviceState> = devices
+        .iter()
+        .zip(paths)
+        .map(|(d, path)| DeviceState::new(d, path, screen))
+        .collect();
+    // Once suspended (or released), events already reach other applications
+    // directly
+    let mut suspended = false;
+    // Devices released by `set_grab_enabled(false)`, with their grab status
+    let mut released: Option<Vec<GrabbedDevice>> = None;
 
     while running.load(Ordering::SeqCst) {
+        if FORCE_UNGRAB.swap(false, Ordering::SeqCst) && !suspended {
+            set_devices_grabbed(devices, false);
+            if let Ok(mut grabbed) = GRABBED.lock() {
+                grabbed.clear();
+            }
+            suspended = true;
+            released = None;
+            log::info!("Input devices released, grab hook continues in listen mode");
+            callback(&Event::grab_suspended());
+        }
+
+        let grab_enabled = GRAB_ENABLED.load(Ordering::SeqCst);
+        if !suspended && grab_enabled == released.is_some() {
+            if let Some(status) = released.take() {
+                let now = SystemTime::now();
+                let grabbed: Vec<GrabbedDevice> = set_devices_grabbed(devices, true)
+                    .into_iter()
+                    .zip(status)
+                    .filter(|(ok, _)| *ok)
+                    .map(|(_, device)| GrabbedDevice {
+                        grabbed_at: now,
+                        ..device
+                    })
+                    .collect();
+                if let Ok(mut status) = GRABBED.lock() {
+                    *status = grabbed;
+                }
+            } else {
+                set_devices_grabbed(devices, false);
+                released = Some(
+                    GRABBED
+                        .lock()
+                        .map(|mut g| std::mem::take(&mut *g))
+                        .unwrap_or_default(),
+                );
+            }
+        }
+        let grabbed = !suspended && released.is_none();
+
         // Poll with timeout
+        // SAFETY: the pointer and length describe `poll_fds`, which poll
+        // only writes `revents` of
         let ret = unsafe { libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as _, 100) };
 
         if ret < 0 {
@@ -332,23 +798,25 @@ where
         for (i, pfd) in poll_fds.iter().enumerate() {
             if pfd.revents & libc::POLLIN != 0
                 && let Some(device) = devices.get_mut(i)
-                && let Ok(events) = device.fetch_events()
+                && let Some(state) = states.get_mut(i)
             {
-                for ev in events {
-                    let pass_through = if let Some(event) = convert_event(&ev) {
-                        callback(&event)
-                    } else {
-                        // Unknown event type - pass through
-                        true
-                    };
-
-                    if pass_through {
-                        // Re-inject the original event via uinput
-                        if let Err(e) = emit_event(&ev) {
-                            log::debug!("Failed to re-inject event: {}", e);
+                read_converted(device, state, |raw, events| {
+                    // Unknown events pass through; a touch frame is consumed
+                    // if the handler consumes any of its touches
+                    let mut pass_through = true;
+                    for event in &events {
+                        pass_through &= callback(event);
+                    }
+
+                    if pass_through && grabbed {
+                        // Re-inject the original events via uinput
+                        for ev in raw {
+                            if let Err(e) = emit_event(ev) {
+                                log::debug!("Failed to re-inject event: {}", e);
+                            }
                         }
                     }
-                }
+                });
             }
         }
     }
@@ -356,116 +824,140 @@ where
     Ok(())
 }
 
+/// Whether a device is software-backed (uinput), so its events are injected.
+fn is_virtual_device(device: &RawDevice) -> bool {
+    device.input_id().bus_type() == BusType::BUS_VIRTUAL
+        || device.name() == Some(VIRTUAL_DEVICE_NAME)
+}
+
 /// Convert evdev InputEvent to our Event type
-fn convert_event(ev: &evdev::InputEvent) -> Option<Event> {
-    match ev.kind() {
-        InputEventKind::Key(key) => {
-            let code = key.code();
-            let pressed = ev.value() == 1;
-
-            // Check if it's a mouse button
-            if (0x110..=0x117).contains(&code) {
-                let button = code_to_button(code)?;
-                let mask = code_to_mask(code);
-
-                if pressed {
-                    state::set_mask(mask);
-                    let (x, y) = *MOUSE_POS.lock().ok()?;
-                    Some(Event::mouse_pressed(button, x, y))
-                } else {
-                    state::unset_mask(mask);
-                    let (x, y) = *MOUSE_POS.lock().ok()?;
-                    Some(Event::mouse_released(button, x, y))
-                }
-            } else {
-                // Keyboard key
-                update_key_modifier(code, pressed);
-                let key = evdev_keycode_to_key(code);
-
-                if pressed {
-                    Some(Event::key_pressed(key, code as u32))
-                } else {
-                    Some(Event::key_released(key, code as u32))
-                }
-            }
-        }
+///
+/// `abs` maps absolute positions to the desktop (for touchscreens and
+/// calibrated devices); without it they are used as-is.
+fn convert_event(ev: &evdev::InputEvent, abs: Option<&mut AbsPointer>) -> Option<Event> {
+    let raw = convert::evdev::RawEvent {
+        type_: ev.event_type().0,
+        code: ev.code(),
+        value: ev.value(),
+    };
+    let mut position = MOUSE_POS.lock().ok()?;
+    convert::with_global_mask(|mask| {
+        convert::evdev::convert(
+            &raw,
+            mask,
+            &mut position,
+            |position, axis, value| match abs {
+                Some(abs) => abs.update(evdev::AbsoluteAxisType(axis), value),
+                None => convert::evdev::unmapped(position, axis, value),
+            },
+            resolve_key,
+        )
+    })
+}
 
-        InputEventKind::RelAxis(axis) => {
-            use evdev::RelativeAxisType;
+/// The key for a key code, reporting codes without one.
+fn resolve_key(code: u16) -> Key {
+    let key = evdev_keycode_to_key(code);
+    if let Key::Unknown(_) = key {
+        debug::unknown_key("evdev", code as u32, |report| {
+            report.name = Some(format!("{:?}", evdev::Key::new(code)));
+        });
+    }
+    key
+}
 
-            let mut pos = MOUSE_POS.lock().ok()?;
-            let value = ev.value() as f64;
+/// Stop the event hook.
+pub fn stop_hook() -> Result<()> {
+    // The stop is signaled via the running atomic
+    Ok(())
+}
 
-            match axis {
-                RelativeAxisType::REL_X => {
-                    pos.0 += value;
-                    if state::is_button_held() {
-                        Some(Event::mouse_dragged(pos.0, pos.1))
-                    } else {
-                        Some(Event::mouse_moved(pos.0, pos.1))
-                    }
-                }
-                RelativeAxisType::REL_Y => {
-                    pos.1 += value;
-                    if state::is_button_held() {
-                        Some(Event::mouse_dragged(pos.0, pos.1))
-                    } else {
-                        Some(Event::mouse_moved(pos.0, pos.1))
-                    }
-                }
-                RelativeAxisType::REL_WHEEL => {
-                    let direction = if value > 0.0 {
-                        ScrollDirection::Up
-                    } else {
-                        ScrollDirection::Down
-                    };
-                    Some(Event::mouse_wheel(pos.0, pos.1, direction, value.abs()))
-                }
-                RelativeAxisType::REL_HWHEEL => {
-                    let direction = if value > 0.0 {
-                        ScrollDirection::Right
-                    } else {
-                        ScrollDirection::Left
-                    };
-                    Some(Event::mouse_wheel(pos.0, pos.1, direction, value.abs()))
-                }
-                _ => None,
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evdev::Key as EvdevKey;
 
-        InputEventKind::AbsAxis(axis) => {
-            use evdev::AbsoluteAxisType;
+    fn key(key: EvdevKey, value: i32) -> InputEvent {
+        InputEvent::new(EvdevEventType::KEY, key.code(), value)
+    }
 
-            let mut pos = MOUSE_POS.lock().ok()?;
-            let value = ev.value() as f64;
+    fn syn(sync: Synchronization) -> InputEvent {
+        InputEvent::new(EvdevEventType::SYNCHRONIZATION, sync.0, 0)
+    }
 
-            match axis {
-                AbsoluteAxisType::ABS_X => {
-                    pos.0 = value;
-                    if state::is_button_held() {
-                        Some(Event::mouse_dragged(pos.0, pos.1))
-                    } else {
-                        Some(Event::mouse_moved(pos.0, pos.1))
-                    }
-                }
-                AbsoluteAxisType::ABS_Y => {
-                    pos.1 = value;
-                    if state::is_button_held() {
-                        Some(Event::mouse_dragged(pos.0, pos.1))
-                    } else {
-                        Some(Event::mouse_moved(pos.0, pos.1))
-                    }
-                }
-                _ => None,
-            }
+    #[test]
+    fn test_dropped_frame_is_skipped_until_report() {
+        let mut sync = SyncState::default();
+        assert_eq!(sync.filter(&key(EvdevKey::KEY_A, 1)), Step::Deliver);
+        assert_eq!(
+            sync.filter(&syn(Synchronization::SYN_REPORT)),
+            Step::Deliver
+        );
+
+        assert_eq!(sync.filter(&syn(Synchronization::SYN_DROPPED)), Step::Skip);
+        assert_eq!(sync.filter(&key(EvdevKey::KEY_A, 0)), Step::Skip);
+        assert_eq!(sync.filter(&key(EvdevKey::KEY_B, 1)), Step::Skip);
+        assert_eq!(sync.filter(&syn(Synchronization::SYN_REPORT)), Step::Resync);
+
+        // Skipped events don't touch the tracked state
+        assert_eq!(sync.held, HashSet::from([EvdevKey::KEY_A.code()]));
+        assert_eq!(sync.filter(&key(EvdevKey::KEY_C, 1)), Step::Deliver);
+    }
+
+    #[test]
+    fn test_reconcile_against_snapshot() {
+        let mut sync = SyncState::default();
+        for ev in [
+            key(EvdevKey::KEY_LEFTSHIFT, 1),
+            key(EvdevKey::KEY_A, 1),
+            key(EvdevKey::KEY_A, 2),
+        ] {
+            sync.filter(&ev);
         }
 
-        _ => None,
+        // During the drop, A was released and B and BTN_LEFT pressed
+        let down = HashSet::from([
+            EvdevKey::KEY_LEFTSHIFT.code(),
+            EvdevKey::KEY_B.code(),
+            EvdevKey::BTN_LEFT.code(),
+        ]);
+        let corrections: Vec<(u16, i32)> = sync
+            .reconcile(&down)
+            .iter()
+            .map(|ev| (ev.code(), ev.value()))
+            .collect();
+        assert_eq!(
+            corrections,
+            vec![
+                (EvdevKey::KEY_A.code(), 0),
+                (EvdevKey::KEY_B.code(), 1),
+                (EvdevKey::BTN_LEFT.code(), 1),
+            ]
+        );
+        assert_eq!(sync.held, down);
+
+        // Already in sync: nothing to correct
+        assert!(sync.reconcile(&down).is_empty());
     }
-}
 
-/// Stop the event hook.
-pub fn stop_hook() -> Result<()> {
-    // The stop is signaled via the running atomic
-    Ok(())
+    #[test]
+    fn test_captured_events_reinject_as_captured() {
+        use crate::platform::linux::evdev::simulate::reinjected_key;
+
+        // Keys without a name of their own keep their code
+        for code in [
+            EvdevKey::KEY_KPENTER,
+            EvdevKey::KEY_F13,
+            EvdevKey::new(0xF0),
+        ] {
+            for (value, pressed) in [(1, true), (0, false)] {
+                let event = convert_event(&key(code, value), None).unwrap();
+                assert_eq!(reinjected_key(&event), Some((code, pressed)));
+            }
+        }
+
+        let event = Event::mouse_released(Button::Middle, 10.0, 20.0);
+        assert_eq!(reinjected_key(&event), Some((EvdevKey::BTN_MIDDLE, false)));
+        assert_eq!(reinjected_key(&Event::mouse_moved(1.0, 2.0)), None);
+    }
 }
@@ -32,13 +32,131 @@
 
 #![allow(unused_imports)]
 
+mod abs;
 mod display;
+#[cfg(all(feature = "failsafe", not(feature = "x11")))]
+mod failsafe;
 mod listen;
 mod simulate;
+mod touch;
+#[cfg(feature = "test-util")]
+mod virtual_input;
 
 pub use display::{display_at_point, displays, primary_display, system_settings};
-pub use listen::{run_grab_hook, run_hook, stop_hook};
+#[cfg(all(feature = "failsafe", not(feature = "x11")))]
+pub(crate) use failsafe::KeyProbe;
+#[cfg(feature = "failsafe")]
+pub(crate) use listen::emergency_release;
+pub use listen::{
+    GrabbedDevice, dropped_frame_count, force_ungrab_all, grab_status, run_grab_hook, run_hook,
+    stop_hook,
+};
+pub(crate) use listen::{
+    held_buttons, set_event_mask, set_grab_enabled, set_input_transforms, set_touch_as_mouse,
+};
+pub(crate) use simulate::{inject_synthetic, lock_states, reinject};
 pub use simulate::{
     key_press, key_release, key_tap, mouse_click, mouse_move, mouse_position, mouse_press,
     mouse_release, simulate,
 };
+#[cfg(feature = "test-util")]
+pub use virtual_input::VirtualInput;
+
+/// Cursor control needs the compositor, which evdev can't talk to.
+#[cfg(not(feature = "x11"))]
+pub(crate) fn confine_cursor(_rect: crate::display::Rect) -> crate::error::Result<()> {
+    Err(crate::error::Error::NotSupported(
+        "Cursor confinement is not supported on Wayland/evdev".into(),
+    ))
+}
+
+#[cfg(not(feature = "x11"))]
+pub(crate) fn release_cursor() -> crate::error::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(feature = "x11"))]
+pub(crate) fn hide_cursor() -> crate::error::Result<()> {
+    Err(crate::error::Error::NotSupported(
+        "Cursor hiding is not supported on Wayland/evdev".into(),
+    ))
+}
+
+#[cfg(not(feature = "x11"))]
+pub(crate) fn show_cursor() -> crate::error::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(feature = "x11"))]
+pub(crate) fn warp_cursor(_x: f64, _y: f64) -> crate::error::Result<()> {
+    Err(crate::error::Error::NotSupported(
+        "Cursor warping is not supported on Wayland/evdev".into(),
+    ))
+}
+
+/// Key codes the kernel can report (`KEY_MAX`).
+#[cfg(not(feature = "x11"))]
+pub(crate) const KEYCODES: std::ops::RangeInclusive<u32> = 0..=0x2ff;
+
+/// The key an evdev key code maps to.
+#[cfg(not(feature = "x11"))]
+pub(crate) fn key_for_code(code: u32) -> crate::keycode::Key {
+    match u16::try_from(code) {
+        Ok(code) => crate::platform::linux::keycodes::evdev_keycode_to_key(code),
+        Err(_) => crate::keycode::Key::Unknown(code),
+    }
+}
+
+/// Input methods run above the kernel, in the compositor or the app.
+#[cfg(not(feature = "x11"))]
+pub(crate) fn ime_composing() -> Option<bool> {
+    None
+}
+
+/// evdev codes are positional and already map to US-layout keys.
+#[cfg(not(feature = "x11"))]
+pub(crate) fn physical_key(_code: u32, key: crate::keycode::Key) -> crate::keycode::Key {
+    key
+}
+
+/// Without access to the compositor's keymap, the layout is unknown.
+#[cfg(not(feature = "x11"))]
+pub(crate) fn logical_key(_code: u32, key: crate::keycode::Key) -> crate::keycode::Key {
+    key
+}
+
+/// Whether grab mode can keep events from reaching other applications.
+// Items like this are only read when evdev is the backend, not next to x11
+#[cfg(not(feature = "x11"))]
+pub(crate) const GRAB_SUPPRESSES: bool = true;
+
+/// Whether events carry the OS's timestamps rather than the time monio saw
+/// them, which makes their delivery latency measurable.
+#[cfg(not(feature = "x11"))]
+pub(crate) const NATIVE_TIMESTAMPS: bool = false;
+
+/// Name of the input backend, recorded in recording metadata.
+#[cfg_attr(not(feature = "recorder"), allow(dead_code))]
+#[cfg(not(feature = "x11"))]
+pub(crate) const BACKEND: &str = "evdev";
+
+/// Whether pointer positions are absolute screen coordinates.
+#[cfg(not(feature = "x11"))]
+pub(crate) const ABSOLUTE_POSITION: bool = false;
+
+/// Whether pointer positions are in points rather than physical pixels.
+#[cfg(not(feature = "x11"))]
+pub(crate) const POSITIONS_IN_POINTS: bool = false;
+
+/// Whether each input device is read on its own.
+#[cfg(not(feature = "x11"))]
+pub(crate) const PER_DEVICE: bool = true;
+
+/// Whether `Key::Char` keys the layout has no key for can be simulated.
+#[cfg(not(feature = "x11"))]
+pub(crate) const SIMULATES_UNICODE: bool = false;
+
+/// Where display information comes from.
+#[cfg(not(feature = "x11"))]
+pub(crate) const DISPLAY_SOURCE: Option<crate::display::DisplaySource> =
+    Some(crate::display::DisplaySource::Drm);
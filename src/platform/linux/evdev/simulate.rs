@@ -5,13 +5,15 @@
 #![allow(dead_code)]
 
 use crate::error::{Error, Result};
-use crate::event::{Button, Event, EventType};
+use crate::event::{Backend, Button, Event, EventType};
 use crate::keycode::Key;
 use crate::platform::linux::keycodes::key_to_evdev_keycode;
+use crate::snapshot::LockStates;
 use evdev::{
     AttributeSet, EventType as EvdevEventType, InputEvent, Key as EvdevKey, RelativeAxisType,
     uinput::{VirtualDevice, VirtualDeviceBuilder},
 };
+use std::path::Path;
 use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
@@ -19,6 +21,9 @@ use std::time::Duration;
 /// Lazy-initialized virtual device for simulation
 static VIRTUAL_DEVICE: Mutex<Option<VirtualDevice>> = Mutex::new(None);
 
+/// Name of the uinput device used for simulation and grab pass-through.
+pub(crate) const VIRTUAL_DEVICE_NAME: &str = "monio grab passthrough";
+
 /// Emit raw input events directly (for grab mode re-injection).
 /// This is an internal function used by the grab mode to pass through events.
 pub(crate) fn emit_event(ev: &InputEvent) -> Result<()> {
@@ -66,6 +71,9 @@ fn get_virtual_device() -> Result<std::sync::MutexGuard<'static, Option<VirtualD
         keys.insert(EvdevKey::BTN_MIDDLE);
         keys.insert(EvdevKey::BTN_SIDE);
         keys.insert(EvdevKey::BTN_EXTRA);
+        keys.insert(EvdevKey::BTN_FORWARD);
+        keys.insert(EvdevKey::BTN_BACK);
+        keys.insert(EvdevKey::BTN_TASK);
 
         let mut rel_axes = AttributeSet::<RelativeAxisType>::new();
         rel_axes.insert(RelativeAxisType::REL_X);
@@ -77,7 +85,7 @@ fn get_virtual_device() -> Result<std::sync::MutexGuard<'static, Option<VirtualD
             .map_err(|e| {
                 Error::SimulateFailed(format!("Failed to create virtual device builder: {}", e))
             })?
-            .name("monio grab passthrough")
+            .name(VIRTUAL_DEVICE_NAME)
             .with_keys(&keys)
             .map_err(|e| Error::SimulateFailed(format!("Failed to add keys: {}", e)))?
             .with_relative_axes(&rel_axes)
@@ -98,15 +106,12 @@ fn get_virtual_device() -> Result<std::sync::MutexGuard<'static, Option<VirtualD
 }
 
 /// Convert Button to evdev key code
-fn button_to_evdev_key(button: Button) -> EvdevKey {
-    match button {
-        Button::Left => EvdevKey::BTN_LEFT,
-        Button::Right => EvdevKey::BTN_RIGHT,
-        Button::Middle => EvdevKey::BTN_MIDDLE,
-        Button::Button4 => EvdevKey::BTN_SIDE,
-        Button::Button5 => EvdevKey::BTN_EXTRA,
-        Button::Unknown(_) => EvdevKey::BTN_LEFT, // Fallback
-    }
+pub(super) fn button_to_evdev_key(button: Button) -> Result<EvdevKey> {
+    let code = button
+        .platform_code(Backend::Evdev)
+        .and_then(|code| u16::try_from(code).ok())
+        .ok_or_else(|| Error::SimulateFailed(format!("evdev has no button for {:?}", button)))?;
+    Ok(EvdevKey::new(code))
 }
 
 /// Emit a key event
@@ -149,6 +154,44 @@ fn emit_relative(axis: RelativeAxisType, value: i32) -> Result<()> {
     Ok(())
 }
 
+/// Inject an event that the grab hook won't see again.
+///
+/// Events come from the virtual device, which grab mode never reads, and
+/// share its queue with passed-through events, so they stay in write order.
+pub(crate) fn inject_synthetic(event: &Event) -> Result<()> {
+    simulate(event)
+}
+
+/// Re-post a captured event as the grab saw it.
+///
+/// Keys are sent by their original evdev code. The virtual pointer only
+/// moves relatively, so button events happen wherever the cursor is now.
+pub(crate) fn reinject(event: &Event) -> Result<()> {
+    match reinjected_key(event) {
+        Some((key, pressed)) => emit_key(key, pressed),
+        None => simulate(event),
+    }
+}
+
+/// The key or button a captured event is re-posted as, and whether it's
+/// pressed. `None` for other events.
+pub(super) fn reinjected_key(event: &Event) -> Option<(EvdevKey, bool)> {
+    match event.event_type {
+        EventType::KeyPressed | EventType::KeyReleased => {
+            let code = u16::try_from(event.keyboard.as_ref()?.raw_code).ok()?;
+            Some((
+                EvdevKey::new(code),
+                event.event_type == EventType::KeyPressed,
+            ))
+        }
+        EventType::MousePressed | EventType::MouseReleased => Some((
+            button_to_evdev_key(event.mouse.as_ref()?.button?).ok()?,
+            event.event_type == EventType::MousePressed,
+        )),
+        _ => None,
+    }
+}
+
 /// Simulate an event.
 pub fn simulate(event: &Event) -> Result<()> {
     match event.event_type {
@@ -181,21 +224,48 @@ pub fn simulate(event: &Event) -> Result<()> {
                 mouse_move(mouse.x, mouse.y)?;
             }
         }
+        EventType::MouseWheel => {
+            if let Some(wheel) = &event.wheel {
+                let (vertical, horizontal) = wheel.steps();
+                if vertical != 0 {
+                    emit_relative(RelativeAxisType::REL_WHEEL, vertical)?;
+                }
+                if horizontal != 0 {
+                    emit_relative(RelativeAxisType::REL_HWHEEL, horizontal)?;
+                }
+            }
+        }
         _ => {}
     }
     Ok(())
 }
 
+/// Convert a key to an evdev keycode.
+///
+/// evdev has no notion of the keyboard layout, so `Key::Char` can't be mapped.
+fn evdev_keycode(key: Key) -> Result<u16> {
+    match key {
+        Key::Char(_) => Err(Error::SimulateFailed(format!(
+            "Unsupported key on evdev (layout unknown): {:?}",
+            key
+        ))),
+        key => match key_to_evdev_keycode(key) {
+            0 => Err(Error::SimulateFailed(format!("Unsupported key: {:?}", key))),
+            code => Ok(code),
+        },
+    }
+}
+
 /// Press a key.
 pub fn key_press(key: Key) -> Result<()> {
-    let code = key_to_evdev_keycode(key);
+    let code = evdev_keycode(key)?;
     let evdev_key = EvdevKey::new(code);
     emit_key(evdev_key, true)
 }
 
 /// Release a key.
 pub fn key_release(key: Key) -> Result<()> {
-    let code = key_to_evdev_keycode(key);
+    let code = evdev_keycode(key)?;
     let evdev_key = EvdevKey::new(code);
     emit_key(evdev_key, false)
 }
@@ -209,13 +279,13 @@ pub fn key_tap(key: Key) -> Result<()> {
 
 /// Press a mouse button.
 pub fn mouse_press(button: Button) -> Result<()> {
-    let evdev_key = button_to_evdev_key(button);
+    let evdev_key = button_to_evdev_key(button)?;
     emit_key(evdev_key, true)
 }
 
 /// Release a mouse button.
 pub fn mouse_release(button: Button) -> Result<()> {
-    let evdev_key = button_to_evdev_key(button);
+    let evdev_key = button_to_evdev_key(button)?;
     emit_key(evdev_key, false)
 }
 
@@ -238,6 +308,38 @@ pub fn mouse_position() -> Result<(f64, f64)> {
     ))
 }
 
+/// Lock key states, from the keyboard LEDs in `/sys/class/leds`.
+pub(crate) fn lock_states() -> LockStates {
+    leds_in(Path::new(LEDS_CLASS_DIR))
+}
+
+const LEDS_CLASS_DIR: &str = "/sys/class/leds";
+
+/// Lock states from the LEDs under `class_dir`, named `<input>::capslock`
+/// etc. A lock is on if any keyboard shows it.
+fn leds_in(class_dir: &Path) -> LockStates {
+    let mut locks = LockStates::default();
+    let Ok(entries) = std::fs::read_dir(class_dir) else {
+        return locks;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some((_, led)) = name.to_str().and_then(|name| name.rsplit_once("::")) else {
+            continue;
+        };
+        let lock = match led {
+            "capslock" => &mut locks.caps_lock,
+            "numlock" => &mut locks.num_lock,
+            "scrolllock" => &mut locks.scroll_lock,
+            _ => continue,
+        };
+        let on = std::fs::read_to_string(entry.path().join("brightness"))
+            .is_ok_and(|brightness| brightness.trim().parse::<u32>().is_ok_and(|b| b > 0));
+        *lock |= on;
+    }
+    locks
+}
+
 /// Note: evdev uses relative motion, so we move by the delta.
 /// For absolute positioning, the cursor needs to already be at (0,0)
 /// or we need to track current position (which is complex).
@@ -248,3 +350,36 @@ pub fn mouse_move(x: f64, y: f64) -> Result<()> {
     emit_relative(RelativeAxisType::REL_Y, y as i32)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leds_in() {
+        let root = std::env::temp_dir().join("monio_test_leds");
+        let _ = std::fs::remove_dir_all(&root);
+        for (led, brightness) in [
+            ("input3::capslock", "1\n"),
+            ("input3::numlock", "0\n"),
+            ("input7::numlock", "1\n"),
+            ("input3::scrolllock", "0\n"),
+            ("tpacpi::power", "1\n"),
+        ] {
+            let dir = root.join(led);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("brightness"), brightness).unwrap();
+        }
+
+        assert_eq!(
+            leds_in(&root),
+            LockStates {
+                caps_lock: true,
+                num_lock: true,
+                scroll_lock: false,
+            }
+        );
+        assert_eq!(leds_in(&root.join("missing")), LockStates::default());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
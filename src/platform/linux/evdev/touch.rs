@@ -0,0 +1,512 @@
+//! Touchscreen support.
+//!
+//! Multitouch screens use the kernel's slot protocol (type B): each finger
+//! has a slot, `ABS_MT_SLOT` picks the slot the following axis values belong
+//! to, and `ABS_MT_TRACKING_ID` is set when a finger lands in the slot and to
+//! -1 when it lifts. A frame of changes is complete at `SYN_REPORT`.
+//! Single-touch screens only report `ABS_X`/`ABS_Y` and `BTN_TOUCH`.
+//!
+//! Positions are mapped to the desktop like other absolute axes (see
+//! [`AbsMapping`]).
+
+use super::abs::{AbsMapping, AxisRange};
+use crate::calibration::InputTransform;
+use crate::display::Rect;
+use crate::event::{Event, EventType};
+use evdev::{
+    AbsoluteAxisType, InputEvent, InputEventKind, Key, PropType, Synchronization,
+    raw_stream::RawDevice,
+};
+
+/// Slots beyond this are ignored, in case a device reports a silly range.
+const MAX_SLOTS: usize = 32;
+
+/// The axes a touchscreen reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct TouchAxes {
+    /// `ABS_X`/`ABS_Y`: the only position on single-touch screens, and the
+    /// first finger on multitouch ones.
+    pub(super) single: AbsMapping,
+    pub(super) pressure: Option<AxisRange>,
+    /// `ABS_MT_POSITION_X/Y` and the number of slots, on multitouch screens.
+    pub(super) multi: Option<(AbsMapping, usize)>,
+    pub(super) mt_pressure: Option<AxisRange>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Slot {
+    /// Tracking ID of the finger in the slot, as of the current frame.
+    tracking_id: Option<i32>,
+    /// Tracking ID last reported to the handler.
+    reported: Option<i32>,
+    x: i32,
+    y: i32,
+    pressure: Option<i32>,
+    moved: bool,
+}
+
+/// What a raw event meant to the touch tracker.
+#[derive(Debug)]
+pub(super) enum TouchStep {
+    /// Not part of a touch; convert it as usual.
+    NotTouch,
+    /// Recorded; it is reported with the rest of its frame.
+    Pending,
+    /// The frame ended: the touch events it produced, and the raw events it
+    /// was made of (ending with the `SYN_REPORT`), for re-injection.
+    Frame {
+        events: Vec<Event>,
+        raw: Vec<InputEvent>,
+    },
+}
+
+/// Per-device touch state.
+#[derive(Debug)]
+pub(super) struct TouchTracker {
+    axes: TouchAxes,
+    slot: usize,
+    slots: Vec<Slot>,
+    /// The slot used by single-touch reporting.
+    single: Slot,
+    raw: Vec<InputEvent>,
+}
+
+impl TouchTracker {
+    pub(super) fn new(axes: TouchAxes) -> Self {
+        let slots = axes.multi.map_or(0, |(_, slots)| slots);
+        Self {
+            axes,
+            slot: 0,
+            slots: vec![Slot::default(); slots],
+            single: Slot::default(),
+            raw: Vec::new(),
+        }
+    }
+
+    /// A tracker for `device` if it is a touchscreen, mapping onto `screen`.
+    pub(super) fn for_device(
+        device: &RawDevice,
+        screen: Option<Rect>,
+        transform: InputTransform,
+    ) -> Option<Self> {
+        if !device.properties().contains(PropType::DIRECT) {
+            return None;
+        }
+        let supported = device.supported_absolute_axes()?;
+        let touch = device
+            .supported_keys()
+            .is_some_and(|keys| keys.contains(Key::BTN_TOUCH));
+        let abs = device.get_abs_state().ok()?;
+        let range = |axis: AbsoluteAxisType| {
+            supported.contains(axis).then(|| {
+                let info = abs[axis.0 as usize];
+                AxisRange {
+                    min: info.minimum,
+                    max: info.maximum,
+                }
+            })
+        };
+
+        let multi = match (
+            range(AbsoluteAxisType::ABS_MT_SLOT),
+            range(AbsoluteAxisType::ABS_MT_POSITION_X),
+            range(AbsoluteAxisType::ABS_MT_POSITION_Y),
+        ) {
+            (Some(slots), Some(x), Some(y)) => Some((
+                AbsMapping {
+                    x,
+                    y,
+                    screen,
+                    transform,
+                },
+                ((slots.max + 1).max(1) as usize).min(MAX_SLOTS),
+            )),
+            _ => None,
+        };
+        let single = match (
+            range(AbsoluteAxisType::ABS_X),
+            range(AbsoluteAxisType::ABS_Y),
+        ) {
+            (Some(x), Some(y)) if touch || multi.is_some() => AbsMapping {
+                x,
+                y,
+                screen,
+                transform,
+            },
+            _ => multi?.0,
+        };
+
+        let mut tracker = Self::new(TouchAxes {
+            single,
+            pressure: range(AbsoluteAxisType::ABS_PRESSURE),
+            multi,
+            mt_pressure: range(AbsoluteAxisType::ABS_MT_PRESSURE),
+        });
+        tracker.slot = abs[AbsoluteAxisType::ABS_MT_SLOT.0 as usize].value.max(0) as usize;
+        Some(tracker)
+    }
+
+    /// Feed a raw event.
+    ///
+    /// Multitouch events always become touch events. The single-touch axes
+    /// (which multitouch screens also send, for the first finger) are left
+    /// to mouse emulation if `touch_as_mouse`, and otherwise become touches
+    /// on single-touch screens and are dropped on multitouch ones.
+    pub(super) fn process(&mut self, ev: &InputEvent, touch_as_mouse: bool) -> TouchStep {
+        let multi = self.axes.multi.is_some();
+        match ev.kind() {
+            InputEventKind::Synchronization(Synchronization::SYN_REPORT) => {
+                if self.raw.is_empty() {
+                    return TouchStep::NotTouch;
+                }
+                let mut raw = std::mem::take(&mut self.raw);
+                raw.push(*ev);
+                TouchStep::Frame {
+                    events: self.frame(),
+                    raw,
+                }
+            }
+            InputEventKind::AbsAxis(axis) if multi && self.process_multi(axis, ev.value()) => {
+                self.raw.push(*ev);
+                TouchStep::Pending
+            }
+            _ if touch_as_mouse => TouchStep::NotTouch,
+            InputEventKind::AbsAxis(axis) if self.process_single(axis, ev.value()) => {
+                self.raw.push(*ev);
+                TouchStep::Pending
+            }
+            InputEventKind::Key(Key::BTN_TOUCH) => {
+                if !multi {
+                    self.single.tracking_id = (ev.value() != 0).then_some(0);
+                }
+                self.raw.push(*ev);
+                TouchStep::Pending
+            }
+            // Tool buttons (finger count, pen) on multitouch screens
+            InputEventKind::Key(key) if multi && (0x140..=0x14f).contains(&key.code()) => {
+                self.raw.push(*ev);
+                TouchStep::Pending
+            }
+            _ => TouchStep::NotTouch,
+        }
+    }
+
+    fn process_multi(&mut self, axis: AbsoluteAxisType, value: i32) -> bool {
+        if axis == AbsoluteAxisType::ABS_MT_SLOT {
+            self.slot = value.max(0) as usize;
+            return true;
+        }
+        let is_mt = axis.0 >= AbsoluteAxisType::ABS_MT_SLOT.0;
+        let Some(slot) = self.slots.get_mut(self.slot) else {
+            // A slot we don't track; still part of the touch frame
+            return is_mt;
+        };
+        match axis {
+            AbsoluteAxisType::ABS_MT_TRACKING_ID => {
+                slot.tracking_id = (value >= 0).then_some(value)
+            }
+            AbsoluteAxisType::ABS_MT_POSITION_X => {
+                slot.x = value;
+                slot.moved = true;
+            }
+            AbsoluteAxisType::ABS_MT_POSITION_Y => {
+                slot.y = value;
+                slot.moved = true;
+            }
+            AbsoluteAxisType::ABS_MT_PRESSURE => {
+                slot.pressure = Some(value);
+                slot.moved = true;
+            }
+            // Other MT axes (touch size, orientation, ...) aren't reported
+            _ => return is_mt,
+        }
+        true
+    }
+
+    /// Whether `axis` is a single-touch axis. On multitouch screens these
+    /// duplicate the first finger; they are recorded but not reported.
+    fn process_single(&mut self, axis: AbsoluteAxisType, value: i32) -> bool {
+        let slot = &mut self.single;
+        match axis {
+            AbsoluteAxisType::ABS_X => slot.x = value,
+            AbsoluteAxisType::ABS_Y => slot.y = value,
+            AbsoluteAxisType::ABS_PRESSURE => slot.pressure = Some(value),
+            _ => return false,
+        }
+        slot.moved = true;
+        true
+    }
+
+    /// The touch events for the frame that just ended.
+    fn frame(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        match self.axes.multi {
+            Some((mapping, _)) => {
+                for slot in &mut self.slots {
+                    report(slot, &mapping, self.axes.mt_pressure, &mut events);
+                }
+            }
+            None => report(
+                &mut self.single,
+                &self.axes.single,
+                self.axes.pressure,
+                &mut events,
+            ),
+        }
+        events
+    }
+}
+
+/// Append the events for a slot's changes in the last frame.
+fn report(
+    slot: &mut Slot,
+    mapping: &AbsMapping,
+    pressure: Option<AxisRange>,
+    events: &mut Vec<Event>,
+) {
+    let (x, y) = mapping.map(slot.x, slot.y);
+    let touch = |event_type, id: i32| {
+        Event::touch(
+            event_type,
+            id as u32,
+            x,
+            y,
+            pressure
+                .zip(slot.pressure)
+                .map(|(range, value)| range.normalize(value) as f32),
+        )
+    };
+
+    match (slot.reported, slot.tracking_id) {
+        (None, Some(id)) => events.push(touch(EventType::TouchBegan, id)),
+        (Some(old), Some(id)) if old != id => {
+            events.push(touch(EventType::TouchEnded, old));
+            events.push(touch(EventType::TouchBegan, id));
+        }
+        (Some(_), Some(id)) if slot.moved => events.push(touch(EventType::TouchMoved, id)),
+        (Some(old), None) => events.push(touch(EventType::TouchEnded, old)),
+        _ => {}
+    }
+    slot.reported = slot.tracking_id;
+    slot.moved = false;
+    if slot.tracking_id.is_none() {
+        slot.pressure = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evdev::EventType as EvdevEventType;
+
+    const SCREEN: Rect = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: 1000.0,
+        height: 500.0,
+    };
+
+    fn mapping() -> AbsMapping {
+        AbsMapping {
+            x: AxisRange { min: 0, max: 4000 },
+            y: AxisRange { min: 0, max: 2000 },
+            screen: Some(SCREEN),
+            transform: InputTransform::IDENTITY,
+        }
+    }
+
+    fn multitouch() -> TouchTracker {
+        TouchTracker::new(TouchAxes {
+            single: mapping(),
+            pressure: None,
+            multi: Some((mapping(), 10)),
+            mt_pressure: Some(AxisRange { min: 0, max: 255 }),
+        })
+    }
+
+    fn single_touch() -> TouchTracker {
+        TouchTracker::new(TouchAxes {
+            single: mapping(),
+            pressure: None,
+            multi: None,
+            mt_pressure: None,
+        })
+    }
+
+    fn abs(axis: AbsoluteAxisType, value: i32) -> InputEvent {
+        InputEvent::new(EvdevEventType::ABSOLUTE, axis.0, value)
+    }
+
+    fn btn_touch(value: i32) -> InputEvent {
+        InputEvent::new(EvdevEventType::KEY, Key::BTN_TOUCH.code(), value)
+    }
+
+    fn syn() -> InputEvent {
+        InputEvent::new(
+            EvdevEventType::SYNCHRONIZATION,
+            Synchronization::SYN_REPORT.0,
+            0,
+        )
+    }
+
+    /// Feed a frame (a `SYN_REPORT` is appended) and return its touches as
+    /// `(type, id, x, y)`.
+    fn frame(
+        tracker: &mut TouchTracker,
+        events: &[InputEvent],
+        touch_as_mouse: bool,
+    ) -> Vec<(EventType, u32, f64, f64)> {
+        for ev in events {
+            let step = tracker.process(ev, touch_as_mouse);
+            assert!(!matches!(step, TouchStep::NotTouch), "{:?}", ev);
+        }
+        match tracker.process(&syn(), touch_as_mouse) {
+            TouchStep::Frame {
+                events: touches,
+                raw,
+            } => {
+                assert_eq!(raw.len(), events.len() + 1);
+                touches
+                    .iter()
+                    .map(|e| {
+                        let t = e.touch.as_ref().unwrap();
+                        (e.event_type, t.id, t.x, t.y)
+                    })
+                    .collect()
+            }
+            other => panic!("expected a frame, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_two_fingers() {
+        use AbsoluteAxisType as A;
+        let mut tracker = multitouch();
+
+        // First finger lands
+        let touches = frame(
+            &mut tracker,
+            &[
+                abs(A::ABS_MT_TRACKING_ID, 7),
+                abs(A::ABS_MT_POSITION_X, 2000),
+                abs(A::ABS_MT_POSITION_Y, 1000),
+                btn_touch(1),
+            ],
+            false,
+        );
+        assert_eq!(touches, vec![(EventType::TouchBegan, 7, 500.0, 250.0)]);
+
+        // Second finger lands in slot 1 while the first moves
+        let touches = frame(
+            &mut tracker,
+            &[
+                abs(A::ABS_MT_POSITION_X, 2400),
+                abs(A::ABS_MT_SLOT, 1),
+                abs(A::ABS_MT_TRACKING_ID, 8),
+                abs(A::ABS_MT_POSITION_X, 4000),
+                abs(A::ABS_MT_POSITION_Y, 0),
+            ],
+            false,
+        );
+        assert_eq!(
+            touches,
+            vec![
+                (EventType::TouchMoved, 7, 600.0, 250.0),
+                (EventType::TouchBegan, 8, 1000.0, 0.0),
+            ]
+        );
+
+        // First finger lifts; the second doesn't move and isn't reported
+        let touches = frame(
+            &mut tracker,
+            &[abs(A::ABS_MT_SLOT, 0), abs(A::ABS_MT_TRACKING_ID, -1)],
+            false,
+        );
+        assert_eq!(touches, vec![(EventType::TouchEnded, 7, 600.0, 250.0)]);
+
+        // Second finger lifts and a new one lands in slot 1 within one frame
+        let touches = frame(
+            &mut tracker,
+            &[
+                abs(A::ABS_MT_SLOT, 1),
+                abs(A::ABS_MT_TRACKING_ID, 9),
+                abs(A::ABS_MT_POSITION_X, 0),
+            ],
+            false,
+        );
+        assert_eq!(
+            touches,
+            vec![
+                (EventType::TouchEnded, 8, 0.0, 0.0),
+                (EventType::TouchBegan, 9, 0.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pressure_is_normalized() {
+        use AbsoluteAxisType as A;
+        let mut tracker = multitouch();
+        for ev in [abs(A::ABS_MT_TRACKING_ID, 1), abs(A::ABS_MT_PRESSURE, 51)] {
+            tracker.process(&ev, true);
+        }
+        let TouchStep::Frame { events, .. } = tracker.process(&syn(), true) else {
+            panic!("expected a frame");
+        };
+        assert_eq!(events[0].touch.as_ref().unwrap().pressure, Some(0.2));
+    }
+
+    #[test]
+    fn test_multitouch_legacy_axes() {
+        use AbsoluteAxisType as A;
+        let mut tracker = multitouch();
+
+        // Left to mouse emulation...
+        assert!(matches!(
+            tracker.process(&abs(A::ABS_X, 100), true),
+            TouchStep::NotTouch
+        ));
+        assert!(matches!(
+            tracker.process(&btn_touch(1), true),
+            TouchStep::NotTouch
+        ));
+
+        // ...or dropped, without producing touches of their own
+        let touches = frame(
+            &mut tracker,
+            &[abs(A::ABS_X, 100), abs(A::ABS_Y, 100), btn_touch(1)],
+            false,
+        );
+        assert!(touches.is_empty());
+    }
+
+    #[test]
+    fn test_single_touch() {
+        use AbsoluteAxisType as A;
+        let mut tracker = single_touch();
+
+        // Mouse emulation unless disabled
+        assert!(matches!(
+            tracker.process(&btn_touch(1), true),
+            TouchStep::NotTouch
+        ));
+        assert!(matches!(
+            tracker.process(&abs(A::ABS_X, 100), true),
+            TouchStep::NotTouch
+        ));
+        assert!(matches!(tracker.process(&syn(), true), TouchStep::NotTouch));
+
+        let touches = frame(
+            &mut tracker,
+            &[btn_touch(1), abs(A::ABS_X, 4000), abs(A::ABS_Y, 2000)],
+            false,
+        );
+        assert_eq!(touches, vec![(EventType::TouchBegan, 0, 1000.0, 500.0)]);
+
+        let touches = frame(&mut tracker, &[abs(A::ABS_Y, 0)], false);
+        assert_eq!(touches, vec![(EventType::TouchMoved, 0, 1000.0, 0.0)]);
+
+        let touches = frame(&mut tracker, &[btn_touch(0)], false);
+        assert_eq!(touches, vec![(EventType::TouchEnded, 0, 1000.0, 0.0)]);
+    }
+}
@@ -0,0 +1,203 @@
+//! A uinput device for driving the evdev backend from tests.
+
+use super::simulate::button_to_evdev_key;
+use crate::error::{Error, Result};
+use crate::event::{Button, ScrollDirection};
+use crate::keycode::Key;
+use crate::platform::linux::keycodes::key_to_evdev_keycode;
+use evdev::{
+    AttributeSet, Device as RawDevice, EventType as EvdevEventType, InputEvent, Key as EvdevKey,
+    RelativeAxisType,
+    uinput::{VirtualDevice, VirtualDeviceBuilder},
+};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Name of the device, as listed by `evtest` and in `/proc/bus/input/devices`.
+const DEVICE_NAME: &str = "monio virtual input";
+
+/// How long [`VirtualInput::new`] waits for udev to publish the device node.
+const NODE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A virtual keyboard and mouse, created through `/dev/uinput`.
+///
+/// Its events reach the evdev backend like those of a real device, so a
+/// test can start a hook, drive the device and check what the hook reports.
+/// Create it before starting the hook: devices are enumerated when the hook
+/// starts, and [`new`](Self::new) returns once the device node can be
+/// opened. The device goes away when dropped.
+///
+/// Needs write access to `/dev/uinput`; check
+/// [`is_available`](Self::is_available) to skip tests where it's missing.
+///
+/// # Example
+///
+/// ```no_run
+/// use monio::channel::listen_channel;
+/// use monio::testsupport::VirtualInput;
+/// use monio::{EventType, Key};
+///
+/// let mut input = VirtualInput::new()?;
+/// let (_handle, rx) = listen_channel(64)?;
+/// input.key_tap(Key::KeyA)?;
+/// assert!(rx.iter().any(|e| e.event_type == EventType::KeyReleased));
+/// # Ok::<(), monio::Error>(())
+/// ```
+pub struct VirtualInput {
+    device: VirtualDevice,
+    path: PathBuf,
+}
+
+impl std::fmt::Debug for VirtualInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualInput")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VirtualInput {
+    /// Whether `/dev/uinput` can be opened for writing.
+    pub fn is_available() -> bool {
+        OpenOptions::new().write(true).open("/dev/uinput").is_ok()
+    }
+
+    /// Create the device with every key, the mouse buttons, motion and both
+    /// wheels, and wait until its node under `/dev/input` can be opened.
+    pub fn new() -> Result<Self> {
+        let mut keys = AttributeSet::<EvdevKey>::new();
+        for code in 1..256 {
+            keys.insert(EvdevKey::new(code));
+        }
+        for button in [
+            Button::Left,
+            Button::Right,
+            Button::Middle,
+            Button::Button4,
+            Button::Button5,
+            Button::Unknown(6),
+            Button::Unknown(7),
+            Button::Unknown(8),
+        ] {
+            keys.insert(button_to_evdev_key(button)?);
+        }
+
+        let mut axes = AttributeSet::<RelativeAxisType>::new();
+        axes.insert(RelativeAxisType::REL_X);
+        axes.insert(RelativeAxisType::REL_Y);
+        axes.insert(RelativeAxisType::REL_WHEEL);
+        axes.insert(RelativeAxisType::REL_HWHEEL);
+
+        let mut device = VirtualDeviceBuilder::new()
+            .and_then(|builder| builder.name(DEVICE_NAME).with_keys(&keys))
+            .and_then(|builder| builder.with_relative_axes(&axes))
+            .and_then(|builder| builder.build())
+            .map_err(|e| {
+                Error::PermissionDenied(format!(
+                    "Failed to create virtual device: {}. Make sure /dev/uinput is writable.",
+                    e
+                ))
+            })?;
+
+        let path = wait_for_node(&mut device)?;
+        Ok(Self { device, path })
+    }
+
+    /// The device node, e.g. `/dev/input/event7`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Emit one raw event, e.g. `(EV_KEY, KEY_A, 1)`, followed by a
+    /// `SYN_REPORT`. Types and codes are those of `linux/input-event-codes.h`.
+    pub fn emit_raw(&mut self, kind: u16, code: u16, value: i32) -> Result<()> {
+        self.device
+            .emit(&[InputEvent::new(EvdevEventType(kind), code, value)])
+            .map_err(|e| Error::SimulateFailed(format!("Failed to emit event: {}", e)))
+    }
+
+    fn key(&mut self, key: Key, value: i32) -> Result<()> {
+        let code = key_to_evdev_keycode(key);
+        if code == 0 {
+            return Err(Error::NotSupported(format!("No evdev code for {key:?}")));
+        }
+        self.emit_raw(EvdevEventType::KEY.0, code, value)
+    }
+
+    /// Press a key.
+    pub fn key_press(&mut self, key: Key) -> Result<()> {
+        self.key(key, 1)
+    }
+
+    /// Release a key.
+    pub fn key_release(&mut self, key: Key) -> Result<()> {
+        self.key(key, 0)
+    }
+
+    /// Press and release a key.
+    pub fn key_tap(&mut self, key: Key) -> Result<()> {
+        self.key_press(key)?;
+        self.key_release(key)
+    }
+
+    /// Press a mouse button.
+    pub fn button_press(&mut self, button: Button) -> Result<()> {
+        let code = button_to_evdev_key(button)?.code();
+        self.emit_raw(EvdevEventType::KEY.0, code, 1)
+    }
+
+    /// Release a mouse button.
+    pub fn button_release(&mut self, button: Button) -> Result<()> {
+        let code = button_to_evdev_key(button)?.code();
+        self.emit_raw(EvdevEventType::KEY.0, code, 0)
+    }
+
+    /// Move the pointer by a relative amount, one axis at a time.
+    pub fn move_by(&mut self, dx: i32, dy: i32) -> Result<()> {
+        if dx != 0 {
+            self.emit_raw(EvdevEventType::RELATIVE.0, RelativeAxisType::REL_X.0, dx)?;
+        }
+        if dy != 0 {
+            self.emit_raw(EvdevEventType::RELATIVE.0, RelativeAxisType::REL_Y.0, dy)?;
+        }
+        Ok(())
+    }
+
+    /// Turn a wheel by `clicks` notches.
+    pub fn scroll(&mut self, direction: ScrollDirection, clicks: u32) -> Result<()> {
+        let clicks = clicks as i32;
+        let (axis, value) = match direction {
+            ScrollDirection::Up => (RelativeAxisType::REL_WHEEL, clicks),
+            ScrollDirection::Down => (RelativeAxisType::REL_WHEEL, -clicks),
+            ScrollDirection::Right => (RelativeAxisType::REL_HWHEEL, clicks),
+            ScrollDirection::Left => (RelativeAxisType::REL_HWHEEL, -clicks),
+        };
+        self.emit_raw(EvdevEventType::RELATIVE.0, axis.0, value)
+    }
+}
+
+/// Find the device's `/dev/input/event*` node and wait until udev has set
+/// it up, so a hook started afterwards enumerates it.
+fn wait_for_node(device: &mut VirtualDevice) -> Result<PathBuf> {
+    let deadline = Instant::now() + NODE_TIMEOUT;
+    loop {
+        let node = device
+            .enumerate_dev_nodes_blocking()
+            .map_err(|e| Error::Platform(format!("Failed to find virtual device node: {}", e)))?
+            .flatten()
+            .next();
+        if let Some(path) = node
+            && RawDevice::open(&path).is_ok()
+        {
+            return Ok(path);
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::Platform(format!(
+                "Virtual device node didn't appear within {NODE_TIMEOUT:?}"
+            )));
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
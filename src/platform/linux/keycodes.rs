@@ -134,6 +134,15 @@ pub fn keycode_to_key(code: u32) -> Key {
         106 => Key::NumpadDivide,
         104 => Key::NumpadEnter,
 
+        // Media keys
+        121 => Key::VolumeMute,
+        122 => Key::VolumeDown,
+        123 => Key::VolumeUp,
+        171 => Key::MediaNext,
+        172 => Key::MediaPlayPause,
+        173 => Key::MediaPrevious,
+        174 => Key::MediaStop,
+
         _ => Key::Unknown(code),
     }
 }
@@ -261,6 +270,15 @@ pub fn key_to_keycode(key: Key) -> Option<u32> {
         Key::NumpadDivide => 106,
         Key::NumpadEnter => 104,
 
+        // Media keys
+        Key::VolumeMute => 121,
+        Key::VolumeDown => 122,
+        Key::VolumeUp => 123,
+        Key::MediaNext => 171,
+        Key::MediaPlayPause => 172,
+        Key::MediaPrevious => 173,
+        Key::MediaStop => 174,
+
         Key::Unknown(code) => code,
         _ => return None,
     })
@@ -285,3 +303,30 @@ pub fn key_to_evdev_keycode(key: Key) -> u16 {
         .map(|x11_code| x11_code.wrapping_sub(X11_EVDEV_OFFSET) as u16)
         .unwrap_or(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_keys_roundtrip() {
+        for key in [
+            Key::VolumeUp,
+            Key::VolumeDown,
+            Key::VolumeMute,
+            Key::MediaPlayPause,
+            Key::MediaStop,
+            Key::MediaNext,
+            Key::MediaPrevious,
+        ] {
+            let code = key_to_keycode(key).unwrap();
+            assert_eq!(keycode_to_key(code), key);
+        }
+        // KEY_VOLUMEUP and KEY_PLAYPAUSE from linux/input-event-codes.h
+        assert_eq!(key_to_keycode(Key::VolumeUp), Some(115 + X11_EVDEV_OFFSET));
+        assert_eq!(
+            key_to_keycode(Key::MediaPlayPause),
+            Some(164 + X11_EVDEV_OFFSET)
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! X11 keysym to Key mapping.
+//!
+//! Used as a fallback when a keycode is not in the fixed (US-layout) keycode
+//! table, so that keys on non-US layouts still map to something meaningful.
+//! Only the X11 backend reads keysyms.
+
+use crate::keycode::Key;
+use std::collections::HashMap;
+
+/// Offset of the Unicode keysym range (`0x01000000 + codepoint`).
+const UNICODE_KEYSYM_OFFSET: u32 = 0x0100_0000;
+
+/// Map a keysym to a Key.
+///
+/// ASCII letters, digits and punctuation map to their named variants, other
+/// printable Latin-1 / Unicode keysyms and dead keys map to [`Key::Char`].
+/// Returns `None` for keysyms with no reasonable mapping.
+pub fn keysym_to_key(keysym: u32) -> Option<Key> {
+    if let Some(key) = media_keysym_to_key(keysym) {
+        return Some(key);
+    }
+    let ch = match keysym {
+        // Printable ASCII and Latin-1 (keysym == codepoint)
+        0x20..=0x7e | 0xa1..=0xff => char::from_u32(keysym)?,
+
+        // Dead keys, represented by their spacing accent
+        0xfe50..=0xfe5f => dead_key_char(keysym)?,
+
+        // Direct Unicode keysyms
+        k if k > UNICODE_KEYSYM_OFFSET => {
+            let ch = char::from_u32(k - UNICODE_KEYSYM_OFFSET)?;
+            if ch.is_control() || ch.is_whitespace() {
+                return None;
+            }
+            ch
+        }
+
+        _ => return None,
+    };
+    Some(Key::from_char(ch))
+}
+
+/// `XF86Audio*` keysyms for the media keys.
+const MEDIA_KEYSYMS: &[(u32, Key)] = &[
+    (0x1008_ff11, Key::VolumeDown),     // XF86AudioLowerVolume
+    (0x1008_ff12, Key::VolumeMute),     // XF86AudioMute
+    (0x1008_ff13, Key::VolumeUp),       // XF86AudioRaiseVolume
+    (0x1008_ff14, Key::MediaPlayPause), // XF86AudioPlay
+    (0x1008_ff15, Key::MediaStop),      // XF86AudioStop
+    (0x1008_ff16, Key::MediaPrevious),  // XF86AudioPrev
+    (0x1008_ff17, Key::MediaNext),      // XF86AudioNext
+];
+
+fn media_keysym_to_key(keysym: u32) -> Option<Key> {
+    MEDIA_KEYSYMS
+        .iter()
+        .find(|&&(sym, _)| sym == keysym)
+        .map(|&(_, key)| key)
+}
+
+/// The `XF86Audio*` keysym for a media key.
+#[cfg_attr(not(feature = "x11"), allow(dead_code))]
+pub fn media_key_keysym(key: Key) -> Option<u32> {
+    MEDIA_KEYSYMS
+        .iter()
+        .find(|&&(_, k)| k == key)
+        .map(|&(sym, _)| sym)
+}
+
+/// The keysym that types a character.
+#[cfg_attr(not(feature = "x11"), allow(dead_code))]
+pub fn char_to_keysym(ch: char) -> u32 {
+    match ch as u32 {
+        cp @ (0x20..=0x7e | 0xa0..=0xff) => cp,
+        cp => UNICODE_KEYSYM_OFFSET + cp,
+    }
+}
+
+/// The dead-key keysym whose spacing accent is `ch`, if any.
+#[cfg_attr(not(feature = "x11"), allow(dead_code))]
+pub fn char_to_dead_keysym(ch: char) -> Option<u32> {
+    (0xfe50..=0xfe5f).find(|&keysym| dead_key_char(keysym) == Some(ch))
+}
+
+/// The spacing character for a dead-key keysym (`XK_dead_*`).
+fn dead_key_char(keysym: u32) -> Option<char> {
+    Some(match keysym {
+        0xfe50 => '`',       // dead_grave
+        0xfe51 => '\u{b4}',  // dead_acute
+        0xfe52 => '^',       // dead_circumflex
+        0xfe53 => '~',       // dead_tilde
+        0xfe54 => '\u{af}',  // dead_macron
+        0xfe55 => '\u{2d8}', // dead_breve
+        0xfe56 => '\u{2d9}', // dead_abovedot
+        0xfe57 => '\u{a8}',  // dead_diaeresis
+        0xfe58 => '\u{2da}', // dead_abovering
+        0xfe59 => '\u{2dd}', // dead_doubleacute
+        0xfe5a => '\u{2c7}', // dead_caron
+        0xfe5b => '\u{b8}',  // dead_cedilla
+        0xfe5c => '\u{2db}', // dead_ogonek
+        _ => return None,
+    })
+}
+
+/// Cache of resolved fallback keys, keyed by (layout group, keycode).
+///
+/// Must be cleared when the keyboard mapping changes (`MappingNotify`).
+#[cfg_attr(not(feature = "x11"), allow(dead_code))]
+#[derive(Debug, Default)]
+pub struct KeysymCache {
+    keys: HashMap<(u8, u32), Key>,
+}
+
+#[cfg_attr(not(feature = "x11"), allow(dead_code))]
+impl KeysymCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self {
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Resolve an unmapped keycode, querying its keysym via `lookup` on a miss.
+    ///
+    /// Falls back to `Key::Unknown(code)` if the keysym has no mapping.
+    pub fn resolve(&mut self, group: u8, code: u32, lookup: impl FnOnce() -> u32) -> Key {
+        *self
+            .keys
+            .entry((group, code))
+            .or_insert_with(|| keysym_to_key(lookup()).unwrap_or(Key::Unknown(code)))
+    }
+
+    /// Forget all cached mappings.
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Level-0 keysyms for a German (de) layout, as reported by
+    /// `XkbKeycodeToKeysym(dpy, code, 0, 0)`.
+    const DE_KEYMAP: &[(u32, u32)] = &[
+        (20, 0x00df),  // ssharp
+        (21, 0xfe51),  // dead_acute
+        (29, 0x007a),  // z
+        (34, 0x00fc),  // udiaeresis
+        (35, 0x002b),  // plus
+        (47, 0x00f6),  // odiaeresis
+        (48, 0x00e4),  // adiaeresis
+        (49, 0xfe52),  // dead_circumflex
+        (51, 0x0023),  // numbersign
+        (52, 0x0079),  // y
+        (61, 0x002d),  // minus
+        (94, 0x003c),  // less
+        (135, 0xff67), // Menu
+    ];
+
+    fn keysym(code: u32) -> u32 {
+        DE_KEYMAP
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, sym)| *sym)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_de_keymap_fixture() {
+        let mut cache = KeysymCache::new();
+        let mut resolve = |code| cache.resolve(0, code, || keysym(code));
+
+        assert_eq!(resolve(20), Key::Char('ß'));
+        assert_eq!(resolve(21), Key::Char('\u{b4}'));
+        assert_eq!(resolve(29), Key::KeyZ);
+        assert_eq!(resolve(34), Key::Char('ü'));
+        assert_eq!(resolve(35), Key::Char('+'));
+        assert_eq!(resolve(47), Key::Char('ö'));
+        assert_eq!(resolve(48), Key::Char('ä'));
+        assert_eq!(resolve(49), Key::Char('^'));
+        assert_eq!(resolve(51), Key::Char('#'));
+        assert_eq!(resolve(52), Key::KeyY);
+        assert_eq!(resolve(61), Key::Minus);
+        assert_eq!(resolve(94), Key::Char('<'));
+        assert_eq!(resolve(135), Key::Unknown(135));
+    }
+
+    #[test]
+    fn test_cache_invalidation() {
+        let mut cache = KeysymCache::new();
+        assert_eq!(cache.resolve(0, 47, || 0x00f6), Key::Char('ö'));
+        // Cached: lookup is not consulted again
+        assert_eq!(cache.resolve(0, 47, || 0x003b), Key::Char('ö'));
+        // Different group is a separate entry
+        assert_eq!(cache.resolve(1, 47, || 0x003b), Key::Semicolon);
+
+        cache.clear();
+        assert_eq!(cache.resolve(0, 47, || 0x003b), Key::Semicolon);
+    }
+
+    #[test]
+    fn test_char_to_keysym() {
+        assert_eq!(char_to_keysym('a'), 0x61);
+        assert_eq!(char_to_keysym('§'), 0xa7);
+        assert_eq!(char_to_keysym('€'), 0x0100_20ac);
+        assert_eq!(char_to_dead_keysym('^'), Some(0xfe52));
+        assert_eq!(char_to_dead_keysym('a'), None);
+
+        for ch in ['ö', '§', '€', '<'] {
+            assert_eq!(keysym_to_key(char_to_keysym(ch)), Some(Key::Char(ch)));
+        }
+    }
+
+    #[test]
+    fn test_unicode_keysyms() {
+        assert_eq!(keysym_to_key(0x0100_20ac), Some(Key::Char('€')));
+        assert_eq!(keysym_to_key(0x00c4), Some(Key::Char('ä')));
+        assert_eq!(keysym_to_key(0x0100_0020), None);
+    }
+
+    #[test]
+    fn test_media_keysyms() {
+        assert_eq!(keysym_to_key(0x1008_ff13), Some(Key::VolumeUp));
+        assert_eq!(keysym_to_key(0x1008_ff14), Some(Key::MediaPlayPause));
+        assert_eq!(media_key_keysym(Key::MediaNext), Some(0x1008_ff17));
+        assert_eq!(media_key_keysym(Key::KeyA), None);
+        // Other XF86 keysyms (XF86MonBrightnessUp) stay unmapped
+        assert_eq!(keysym_to_key(0x1008_ff02), None);
+    }
+}
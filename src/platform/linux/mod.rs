@@ -45,6 +45,7 @@
 //! grab only for consuming/blocking events rather than selective pass-through.
 
 mod keycodes;
+mod keysym;
 
 #[cfg(feature = "x11")]
 mod x11;
@@ -60,10 +61,14 @@ pub use x11::*;
 #[cfg(all(feature = "evdev", not(feature = "x11")))]
 pub use evdev::*;
 
+// Tests drive the evdev backend, whichever backend is the default
+#[cfg(all(feature = "evdev", feature = "test-util"))]
+pub use evdev::VirtualInput;
+
 // If neither X11 nor evdev features are enabled, provide stub implementations
 #[cfg(not(any(feature = "x11", feature = "evdev")))]
 mod stub {
-    use crate::display::{DisplayInfo, SystemSettings};
+    use crate::display::{DisplayInfo, Rect, SystemSettings};
     use crate::error::{Error, Result};
     use crate::event::{Button, Event};
     use crate::hook::{EventHandler, GrabHandler};
@@ -93,6 +98,62 @@ mod stub {
         Ok(())
     }
 
+    pub(crate) fn set_grab_enabled(_enabled: bool) {}
+
+    #[cfg(feature = "failsafe")]
+    pub(crate) struct KeyProbe;
+
+    #[cfg(feature = "failsafe")]
+    impl KeyProbe {
+        pub(crate) fn open(_keys: &[Key]) -> Result<Self> {
+            Err(Error::NotSupported(
+                "No Linux backend enabled. Enable 'x11' or 'evdev' feature.".into(),
+            ))
+        }
+
+        pub(crate) fn all_held(&mut self) -> bool {
+            false
+        }
+    }
+
+    #[cfg(feature = "failsafe")]
+    pub(crate) fn emergency_release() {}
+
+    pub(crate) fn set_touch_as_mouse(_enabled: bool) {}
+
+    pub(crate) fn set_input_transforms(_transforms: Vec<crate::calibration::DeviceTransform>) {}
+
+    pub(crate) fn set_event_mask(_mask: crate::hook::EventMask) {}
+
+    pub(crate) const GRAB_SUPPRESSES: bool = false;
+
+    pub(crate) const NATIVE_TIMESTAMPS: bool = false;
+
+    #[cfg_attr(not(feature = "recorder"), allow(dead_code))]
+    pub(crate) const BACKEND: &str = "none";
+
+    pub(crate) const ABSOLUTE_POSITION: bool = false;
+
+    pub(crate) const POSITIONS_IN_POINTS: bool = false;
+
+    pub(crate) const PER_DEVICE: bool = false;
+
+    pub(crate) const SIMULATES_UNICODE: bool = false;
+
+    pub(crate) const DISPLAY_SOURCE: Option<crate::display::DisplaySource> = None;
+
+    pub(crate) fn inject_synthetic(_event: &Event) -> Result<()> {
+        Err(Error::NotSupported(
+            "No Linux backend enabled. Enable 'x11' or 'evdev' feature.".into(),
+        ))
+    }
+
+    pub(crate) fn reinject(_event: &Event) -> Result<()> {
+        Err(Error::NotSupported(
+            "No Linux backend enabled. Enable 'x11' or 'evdev' feature.".into(),
+        ))
+    }
+
     pub fn simulate(_event: &Event) -> Result<()> {
         Err(Error::NotSupported(
             "No Linux backend enabled. Enable 'x11' or 'evdev' feature.".into(),
@@ -141,12 +202,65 @@ mod stub {
         ))
     }
 
+    pub(crate) fn lock_states() -> crate::snapshot::LockStates {
+        crate::snapshot::LockStates::default()
+    }
+
+    pub(crate) fn held_buttons() -> Option<u32> {
+        None
+    }
+
     pub fn mouse_move(_x: f64, _y: f64) -> Result<()> {
         Err(Error::NotSupported(
             "No Linux backend enabled. Enable 'x11' or 'evdev' feature.".into(),
         ))
     }
 
+    pub(crate) fn confine_cursor(_rect: Rect) -> Result<()> {
+        Err(Error::NotSupported(
+            "No Linux backend enabled. Enable 'x11' or 'evdev' feature.".into(),
+        ))
+    }
+
+    pub(crate) fn release_cursor() -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(clippy::reversed_empty_ranges)]
+    pub(crate) const KEYCODES: std::ops::RangeInclusive<u32> = 1..=0;
+
+    pub(crate) fn key_for_code(code: u32) -> Key {
+        Key::Unknown(code)
+    }
+
+    pub(crate) fn ime_composing() -> Option<bool> {
+        None
+    }
+
+    pub(crate) fn physical_key(_code: u32, key: Key) -> Key {
+        key
+    }
+
+    pub(crate) fn logical_key(_code: u32, key: Key) -> Key {
+        key
+    }
+
+    pub(crate) fn hide_cursor() -> Result<()> {
+        Err(Error::NotSupported(
+            "No Linux backend enabled. Enable 'x11' or 'evdev' feature.".into(),
+        ))
+    }
+
+    pub(crate) fn show_cursor() -> Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn warp_cursor(_x: f64, _y: f64) -> Result<()> {
+        Err(Error::NotSupported(
+            "No Linux backend enabled. Enable 'x11' or 'evdev' feature.".into(),
+        ))
+    }
+
     pub fn displays() -> Result<Vec<DisplayInfo>> {
         Err(Error::NotSupported(
             "No Linux backend enabled. Enable 'x11' or 'evdev' feature.".into(),
@@ -174,3 +288,23 @@ mod stub {
 
 #[cfg(not(any(feature = "x11", feature = "evdev")))]
 pub use stub::*;
+
+/// Scroll-only grabs are applied by the handler wrapper; the backends read
+/// every event either way.
+pub(crate) fn set_scroll_only(_scroll_only: bool) {}
+
+/// There is no Accessibility permission to grant.
+pub(crate) fn accessibility_status() -> crate::permissions::AccessibilityStatus {
+    crate::permissions::AccessibilityStatus::Granted
+}
+
+/// There is no Accessibility permission to request.
+pub(crate) fn request_accessibility() -> bool {
+    true
+}
+
+pub(crate) fn open_accessibility_settings() -> crate::error::Result<()> {
+    Err(crate::error::Error::NotSupported(
+        "Accessibility settings only exist on macOS".into(),
+    ))
+}
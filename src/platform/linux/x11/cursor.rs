@@ -0,0 +1,194 @@
+//! Cursor confinement (XFixes pointer barriers), visibility (XFixes) and
+//! warping (XWarpPointer).
+
+use crate::display::Rect;
+use crate::error::{Error, Result};
+use std::os::raw::c_int;
+use std::ptr::{null, null_mut};
+use std::sync::Mutex;
+use x11::{xfixes, xlib};
+
+// Barrier directions from <X11/extensions/xfixeswire.h>: the directions in
+// which the pointer may cross the barrier.
+const BARRIER_POSITIVE_X: c_int = 1 << 0;
+const BARRIER_POSITIVE_Y: c_int = 1 << 1;
+const BARRIER_NEGATIVE_X: c_int = 1 << 2;
+const BARRIER_NEGATIVE_Y: c_int = 1 << 3;
+
+/// Barriers live as long as the connection that created them.
+struct Barriers {
+    display: *mut xlib::Display,
+    barriers: Vec<xfixes::PointerBarrier>,
+}
+
+// The connection is only used under the `ACTIVE` lock.
+unsafe impl Send for Barriers {}
+
+impl Drop for Barriers {
+    fn drop(&mut self) {
+        unsafe {
+            for &barrier in &self.barriers {
+                xfixes::XFixesDestroyPointerBarrier(self.display, barrier);
+            }
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+static ACTIVE: Mutex<Option<Barriers>> = Mutex::new(None);
+
+pub(crate) fn confine_cursor(rect: Rect) -> Result<()> {
+    let mut active = ACTIVE
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    active.take();
+
+    let display = unsafe { xlib::XOpenDisplay(null()) };
+    if display.is_null() {
+        return Err(Error::Platform("Failed to open X display".into()));
+    }
+    let mut barriers = Barriers {
+        display,
+        barriers: Vec::with_capacity(4),
+    };
+
+    let (mut event_base, mut error_base) = (0, 0);
+    let (mut major, mut minor) = (5, 0);
+    unsafe {
+        if xfixes::XFixesQueryExtension(display, &mut event_base, &mut error_base) == 0
+            || xfixes::XFixesQueryVersion(
+                display,
+                &mut major,
+                // Written by Xlib despite the binding's `*const`
+                std::ptr::addr_of_mut!(minor),
+            ) == 0
+            || major < 5
+        {
+            return Err(Error::NotSupported(
+                "Cursor confinement requires the XFixes 5 extension".into(),
+            ));
+        }
+    }
+
+    let left = rect.x.floor() as c_int;
+    let top = rect.y.floor() as c_int;
+    let right = (rect.x + rect.width).ceil() as c_int - 1;
+    let bottom = (rect.y + rect.height).ceil() as c_int - 1;
+    let edges = [
+        (left, top, left, bottom, BARRIER_POSITIVE_X),
+        (right, top, right, bottom, BARRIER_NEGATIVE_X),
+        (left, top, right, top, BARRIER_POSITIVE_Y),
+        (left, bottom, right, bottom, BARRIER_NEGATIVE_Y),
+    ];
+
+    unsafe {
+        let root = xlib::XDefaultRootWindow(display);
+
+        // Barriers only block crossings, so move the cursor inside first
+        let (x, y) = super::mouse_position()?;
+        if !rect.contains(x, y) {
+            let (x, y) = rect.clamp(x, y);
+            xlib::XWarpPointer(display, 0, root, 0, 0, 0, 0, x as c_int, y as c_int);
+        }
+
+        for (x1, y1, x2, y2, directions) in edges {
+            let barrier = xfixes::XFixesCreatePointerBarrier(
+                display,
+                root,
+                x1,
+                y1,
+                x2,
+                y2,
+                directions,
+                0,
+                null_mut(),
+            );
+            if barrier == 0 {
+                return Err(Error::Platform("XFixesCreatePointerBarrier failed".into()));
+            }
+            barriers.barriers.push(barrier);
+        }
+        xlib::XSync(display, xlib::False);
+    }
+
+    *active = Some(barriers);
+    Ok(())
+}
+
+pub(crate) fn release_cursor() -> Result<()> {
+    let mut active = ACTIVE
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    active.take();
+    Ok(())
+}
+
+/// Connection keeping the cursor hidden; closing it shows the cursor again.
+struct Hidden(*mut xlib::Display);
+
+// The connection is only used under the `HIDDEN` lock.
+unsafe impl Send for Hidden {}
+
+impl Drop for Hidden {
+    fn drop(&mut self) {
+        unsafe {
+            xfixes::XFixesShowCursor(self.0, xlib::XDefaultRootWindow(self.0));
+            xlib::XCloseDisplay(self.0);
+        }
+    }
+}
+
+static HIDDEN: Mutex<Option<Hidden>> = Mutex::new(None);
+
+pub(crate) fn hide_cursor() -> Result<()> {
+    let mut hidden = HIDDEN
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    if hidden.is_some() {
+        return Ok(());
+    }
+    let display = unsafe { xlib::XOpenDisplay(null()) };
+    if display.is_null() {
+        return Err(Error::Platform("Failed to open X display".into()));
+    }
+    unsafe {
+        xfixes::XFixesHideCursor(display, xlib::XDefaultRootWindow(display));
+        xlib::XFlush(display);
+    }
+    *hidden = Some(Hidden(display));
+    Ok(())
+}
+
+pub(crate) fn show_cursor() -> Result<()> {
+    let mut hidden = HIDDEN
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    hidden.take();
+    Ok(())
+}
+
+/// The server generates motion events for warps, so XRecord listeners
+/// report them as moves.
+pub(crate) fn warp_cursor(x: f64, y: f64) -> Result<()> {
+    let display = unsafe { xlib::XOpenDisplay(null()) };
+    if display.is_null() {
+        return Err(Error::Platform("Failed to open X display".into()));
+    }
+    unsafe {
+        let root = xlib::XDefaultRootWindow(display);
+        xlib::XWarpPointer(
+            display,
+            0,
+            root,
+            0,
+            0,
+            0,
+            0,
+            x.round() as c_int,
+            y.round() as c_int,
+        );
+        xlib::XFlush(display);
+        xlib::XCloseDisplay(display);
+    }
+    Ok(())
+}
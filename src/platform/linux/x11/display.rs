@@ -1,8 +1,8 @@
 //! X11 display and system property queries.
 
-use crate::display::{DisplayInfo, Rect, SystemSettings};
+use crate::display::{DisplayInfo, DisplaySource, Rect, SystemSettings};
 use crate::error::{Error, Result};
-use std::ptr::null;
+use std::ptr::{null, null_mut};
 use x11::xlib;
 
 pub fn displays() -> Result<Vec<DisplayInfo>> {
@@ -22,6 +22,7 @@ pub fn displays() -> Result<Vec<DisplayInfo>> {
             scale_factor: 1.0,
             refresh_rate: None,
             is_primary: true,
+            source: DisplaySource::WindowSystem,
         }])
     })
 }
@@ -41,7 +42,7 @@ pub fn display_at_point(x: f64, y: f64) -> Result<Option<DisplayInfo>> {
 }
 
 pub fn system_settings() -> Result<SystemSettings> {
-    let (mouse_sensitivity, mouse_acceleration, mouse_acceleration_threshold) =
+    let (mouse_sensitivity, mouse_acceleration, mouse_acceleration_threshold, keyboard_layout) =
         with_display(|display| unsafe {
             let mut accel_numerator: i32 = 0;
             let mut accel_denominator: i32 = 0;
@@ -57,6 +58,7 @@ pub fn system_settings() -> Result<SystemSettings> {
                 Some(accel_numerator as f64),
                 Some(accel_denominator as f64),
                 Some(threshold as f64),
+                xkb_layout(display),
             ))
         })?;
 
@@ -66,11 +68,68 @@ pub fn system_settings() -> Result<SystemSettings> {
         mouse_sensitivity,
         mouse_acceleration,
         mouse_acceleration_threshold,
+        mouse_acceleration_threshold2: None,
         double_click_time: None,
-        keyboard_layout: None,
+        drag_threshold: None,
+        keyboard_layout,
+        keyboard_layout_name: None,
+        natural_scrolling: None,
     })
 }
 
+/// The first XKB layout and variant, e.g. `"us"` or `"us(intl)"`, from the
+/// `_XKB_RULES_NAMES` root window property.
+unsafe fn xkb_layout(display: *mut xlib::Display) -> Option<String> {
+    let data = unsafe {
+        let atom = xlib::XInternAtom(display, c"_XKB_RULES_NAMES".as_ptr(), xlib::True);
+        if atom == 0 {
+            return None;
+        }
+        let mut actual_type = 0;
+        let mut actual_format = 0;
+        let mut items = 0;
+        let mut remaining = 0;
+        let mut data: *mut u8 = null_mut();
+        let status = xlib::XGetWindowProperty(
+            display,
+            xlib::XDefaultRootWindow(display),
+            atom,
+            0,
+            1024,
+            xlib::False,
+            xlib::XA_STRING,
+            &mut actual_type,
+            &mut actual_format,
+            &mut items,
+            &mut remaining,
+            &mut data,
+        );
+        if status != xlib::Success as i32 || data.is_null() {
+            return None;
+        }
+        let bytes = std::slice::from_raw_parts(data, items as usize).to_vec();
+        xlib::XFree(data.cast());
+        if actual_format != 8 {
+            return None;
+        }
+        bytes
+    };
+    parse_xkb_rules_names(&data)
+}
+
+/// `_XKB_RULES_NAMES` holds NUL-separated rules, model, layouts, variants
+/// and options; layouts and variants are comma-separated per group.
+fn parse_xkb_rules_names(data: &[u8]) -> Option<String> {
+    let mut fields = data.split(|&b| b == 0).map(String::from_utf8_lossy);
+    let layouts = fields.nth(2)?;
+    let variants = fields.next().unwrap_or_default();
+    let layout = layouts.split(',').next().filter(|l| !l.is_empty())?;
+    match variants.split(',').next().filter(|v| !v.is_empty()) {
+        Some(variant) => Some(format!("{layout}({variant})")),
+        None => Some(layout.to_string()),
+    }
+}
+
 /// RAII wrapper to ensure display is always closed.
 struct DisplayGuard(*mut xlib::Display);
 
@@ -104,3 +163,31 @@ fn with_display<T>(f: impl FnOnce(*mut xlib::Display) -> Result<T>) -> Result<T>
         f(guard.as_ptr())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_xkb_rules_names() {
+        assert_eq!(
+            parse_xkb_rules_names(b"evdev\0pc105\0us,de\0,\0grp:alt_shift_toggle\0").as_deref(),
+            Some("us")
+        );
+        assert_eq!(
+            parse_xkb_rules_names(b"evdev\0pc105\0us\0intl\0\0").as_deref(),
+            Some("us(intl)")
+        );
+        assert_eq!(parse_xkb_rules_names(b"evdev\0pc105\0\0\0\0"), None);
+        assert_eq!(parse_xkb_rules_names(b""), None);
+    }
+
+    /// Needs an X server. Run with `cargo test system_settings -- --ignored`.
+    #[test]
+    #[ignore = "needs an X11 display"]
+    fn test_system_settings_filled_in() {
+        let settings = system_settings().unwrap();
+        assert!(settings.mouse_acceleration.is_some());
+        assert!(settings.keyboard_layout.is_some());
+    }
+}
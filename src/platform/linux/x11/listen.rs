@@ -1,329 +1,382 @@
 //! X11 input listening using XRecord.
 
+#![warn(clippy::undocumented_unsafe_blocks)]
+
+use crate::convert;
+use crate::debug;
 use crate::error::{Error, Result};
-use crate::event::{Button, Event, ScrollDirection};
-use crate::hook::{EventHandler, GrabHandler};
-use crate::state::{
-    self, MASK_ALT, MASK_BUTTON1, MASK_BUTTON2, MASK_BUTTON3, MASK_CTRL, MASK_META, MASK_SHIFT,
-};
-use std::os::raw::{c_char, c_int, c_uchar, c_ulong};
+use crate::event::Event;
+use crate::hook::{EventHandler, EventMask, GrabHandler};
+use std::os::raw::{c_char, c_int, c_uchar, c_uint, c_ulong};
 use std::ptr::null;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use x11::xlib;
 use x11::xrecord;
 
+use crate::keycode::Key;
+use crate::platform::ActiveHook;
 use crate::platform::linux::keycodes::keycode_to_key;
+use crate::platform::linux::keysym::KeysymCache;
 
-/// Stored handler for the callback
-static HANDLER: Mutex<Option<Box<dyn EventHandler>>> = Mutex::new(None);
-
-/// Flag to signal stopping
-static STOP_FLAG: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+/// The running hook. [`stop_hook`] disables the one context in
+/// [`CONTEXT`], so hooks run one at a time.
+static ACTIVE: ActiveHook = ActiveHook::new();
 
 /// XRecord context for stopping the hook
 static CONTEXT: Mutex<Option<xrecord::XRecordContext>> = Mutex::new(None);
 
+/// Categories to record, see [`set_event_mask`].
+static EVENT_MASK: AtomicU32 = AtomicU32::new(EventMask::ALL.bits());
+
+/// Keysym fallback for keycodes missing from the fixed table
+static KEYSYM_LOOKUP: Mutex<Option<KeysymLookup>> = Mutex::new(None);
+
 const FALSE: c_int = 0;
 
-/// XRecord data structure for events
-#[repr(C)]
-struct XRecordDatum {
-    type_: u8,
-    code: u8,
-    _rest: u64,
-    _1: bool,
-    _2: bool,
-    _3: bool,
-    root_x: i16,
-    root_y: i16,
-    _event_x: i16,
-    _event_y: i16,
-    _state: u16,
+/// `XkbUseCoreKbd` device spec (not exported by the x11 crate)
+const XKB_USE_CORE_KBD: c_uint = 0x0100;
+
+/// Display connection used for keysym queries, plus the resolved-key cache.
+///
+/// The XRecord data connection can't be used for requests while the record
+/// loop is running, so lookups go through a separate connection.
+struct KeysymLookup {
+    display: DisplayConnection,
+    cache: KeysymCache,
 }
 
-/// Update modifier mask from keyboard event
-fn update_key_modifier(code: u32, pressed: bool) {
-    let mask = match code {
-        50 | 62 => MASK_SHIFT,  // Shift L/R
-        37 | 105 => MASK_CTRL,  // Control L/R
-        64 | 108 => MASK_ALT,   // Alt L/R
-        133 | 134 => MASK_META, // Super L/R
-        _ => return,
-    };
+/// An X display connection, closed on drop.
+struct DisplayConnection(*mut xlib::Display);
+
+// SAFETY: Xlib connections may move between threads; each is only used by
+// one thread at a time: the hook thread, or whoever holds the KEYSYM_LOOKUP
+// mutex.
+unsafe impl Send for DisplayConnection {}
+
+impl DisplayConnection {
+    /// The active layout group of the core keyboard.
+    fn group(&self) -> u8 {
+        // SAFETY: the display is open, and the state plain data Xkb fills
+        // in; zeroed, it reads as group 0 if the request fails
+        unsafe {
+            let mut state: xlib::XkbStateRec = std::mem::zeroed();
+            xlib::XkbGetState(self.0, XKB_USE_CORE_KBD, &mut state);
+            state.group
+        }
+    }
 
-    if pressed {
-        state::set_mask(mask);
-    } else {
-        state::unset_mask(mask);
+    fn open() -> Option<Self> {
+        // SAFETY: a null name opens `$DISPLAY`; failure returns null
+        let display = unsafe { xlib::XOpenDisplay(null()) };
+        (!display.is_null()).then_some(Self(display))
     }
 }
 
-/// Convert X11 event to our Event type
-fn convert_event(type_: c_int, code: u8, x: f64, y: f64) -> Option<Event> {
-    match type_ {
-        t if t == xlib::KeyPress => {
-            let code32 = code as u32;
-            update_key_modifier(code32, true);
-            let key = keycode_to_key(code32);
-            Some(Event::key_pressed(key, code32))
-        }
+impl Drop for DisplayConnection {
+    fn drop(&mut self) {
+        // SAFETY: the display was opened by `open`, and is closed once
+        unsafe { xlib::XCloseDisplay(self.0) };
+    }
+}
 
-        t if t == xlib::KeyRelease => {
-            let code32 = code as u32;
-            update_key_modifier(code32, false);
-            let key = keycode_to_key(code32);
-            Some(Event::key_released(key, code32))
-        }
+/// An XRecord context, disabled and freed on drop (before its display is
+/// closed).
+struct RecordContext<'a> {
+    display: &'a DisplayConnection,
+    context: xrecord::XRecordContext,
+}
 
-        t if t == xlib::ButtonPress => {
-            match code {
-                1 => {
-                    state::set_mask(MASK_BUTTON1);
-                    Some(Event::mouse_pressed(Button::Left, x, y))
-                }
-                2 => {
-                    state::set_mask(MASK_BUTTON3);
-                    Some(Event::mouse_pressed(Button::Middle, x, y))
-                }
-                3 => {
-                    state::set_mask(MASK_BUTTON2);
-                    Some(Event::mouse_pressed(Button::Right, x, y))
-                }
-                // Scroll wheel events in X11
-                4 => Some(Event::mouse_wheel(x, y, ScrollDirection::Up, 1.0)),
-                5 => Some(Event::mouse_wheel(x, y, ScrollDirection::Down, 1.0)),
-                6 => Some(Event::mouse_wheel(x, y, ScrollDirection::Left, 1.0)),
-                7 => Some(Event::mouse_wheel(x, y, ScrollDirection::Right, 1.0)),
-                c => Some(Event::mouse_pressed(Button::Unknown(c), x, y)),
-            }
+impl Drop for RecordContext<'_> {
+    fn drop(&mut self) {
+        // SAFETY: the context was created on the display, which the borrow
+        // keeps open, and is freed once
+        unsafe {
+            xrecord::XRecordDisableContext(self.display.0, self.context);
+            xrecord::XRecordFreeContext(self.display.0, self.context);
         }
+    }
+}
 
-        t if t == xlib::ButtonRelease => {
-            match code {
-                1 => {
-                    state::unset_mask(MASK_BUTTON1);
-                    Some(Event::mouse_released(Button::Left, x, y))
-                }
-                2 => {
-                    state::unset_mask(MASK_BUTTON3);
-                    Some(Event::mouse_released(Button::Middle, x, y))
-                }
-                3 => {
-                    state::unset_mask(MASK_BUTTON2);
-                    Some(Event::mouse_released(Button::Right, x, y))
-                }
-                4..=7 => None, // Wheel "release" - ignored
-                c => Some(Event::mouse_released(Button::Unknown(c), x, y)),
-            }
-        }
+/// Clears the hook's statics when `run_hook` returns, on every path.
+///
+/// Dropping the keysym lookup closes its display connection.
+struct HookStatics;
 
-        t if t == xlib::MotionNotify => {
-            // THE KEY FIX: Check button state for drag detection
-            if state::is_button_held() {
-                Some(Event::mouse_dragged(x, y))
-            } else {
-                Some(Event::mouse_moved(x, y))
-            }
+impl Drop for HookStatics {
+    fn drop(&mut self) {
+        if let Ok(mut c) = CONTEXT.lock() {
+            *c = None;
+        }
+        if let Ok(mut guard) = KEYSYM_LOOKUP.lock() {
+            *guard = None;
         }
-
-        _ => None,
     }
 }
 
-/// XRecord callback
-unsafe extern "C" fn record_callback(
-    _null: *mut c_char,
-    raw_data: *mut xrecord::XRecordInterceptData,
-) {
-    unsafe {
-        let data = match raw_data.as_ref() {
-            Some(d) => d,
-            None => return,
-        };
-
-        if data.category != xrecord::XRecordFromServer {
-            xrecord::XRecordFreeData(raw_data);
-            return;
-        }
+/// What a run of the hook hands the record callback, through its closure
+/// pointer.
+struct RecordState<'a> {
+    running: &'a AtomicBool,
+    handler: &'a dyn EventHandler,
+}
 
-        // Check stop flag
-        if let Ok(guard) = STOP_FLAG.lock()
-            && let Some(ref flag) = *guard
-            && !flag.load(Ordering::SeqCst)
-        {
-            xrecord::XRecordFreeData(raw_data);
-            return;
-        }
+impl KeysymLookup {
+    /// Resolve a keycode through its keysym in the current layout group.
+    fn resolve(&mut self, code: u8) -> Key {
+        let display = self.display.0;
+        let group = self.display.group();
+        self.cache.resolve(group, code as u32, || {
+            // SAFETY: the display is open while `self` is
+            unsafe { xlib::XkbKeycodeToKeysym(display, code, group as c_int, 0) as u32 }
+        })
+    }
 
-        // Parse the event data
-        #[allow(clippy::cast_ptr_alignment)]
-        let xdatum = match (data.data as *const XRecordDatum).as_ref() {
-            Some(d) => d,
-            None => {
-                xrecord::XRecordFreeData(raw_data);
-                return;
-            }
-        };
-
-        let type_ = xdatum.type_ as c_int;
-        let code = xdatum.code;
-        let x = xdatum.root_x as f64;
-        let y = xdatum.root_y as f64;
-
-        if let Some(event) = convert_event(type_, code, x, y)
-            && let Ok(guard) = HANDLER.lock()
-            && let Some(ref handler) = *guard
-        {
-            handler.handle_event(&event);
+    /// The name of a keycode's keysym in the current layout group, e.g.
+    /// `XF86Tools (0x1008ff81)`.
+    fn keysym_name(&self, code: u8) -> String {
+        let group = self.display.group();
+        // SAFETY: the display is open while `self` is
+        let keysym = unsafe { xlib::XkbKeycodeToKeysym(self.display.0, code, group as c_int, 0) };
+        // SAFETY: returns a static string, or null for unnamed keysyms
+        let name = unsafe { xlib::XKeysymToString(keysym) };
+        if name.is_null() {
+            format!("NoSymbol ({keysym:#x})")
+        } else {
+            // SAFETY: non-null, and a NUL-terminated string Xlib keeps
+            let name = unsafe { std::ffi::CStr::from_ptr(name) }.to_string_lossy();
+            format!("{name} ({keysym:#x})")
         }
-
-        xrecord::XRecordFreeData(raw_data);
     }
 }
 
-/// Run the event hook (blocking).
-pub fn run_hook<H: EventHandler + 'static>(running: &Arc<AtomicBool>, handler: H) -> Result<()> {
-    // Store handler and stop flag
-    {
-        let mut h = HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = Some(Box::new(handler));
+/// Convert a keycode, falling back to the active layout for unknown keys.
+fn resolve_key(code: u8) -> Key {
+    let key = keycode_to_key(code as u32);
+    if !matches!(key, Key::Unknown(_)) {
+        return key;
     }
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = Some(running.clone());
+
+    let Ok(mut guard) = KEYSYM_LOOKUP.lock() else {
+        return key;
+    };
+    let Some(lookup) = guard.as_mut() else {
+        return key;
+    };
+    let key = lookup.resolve(code);
+    if matches!(key, Key::Unknown(_)) {
+        debug::unknown_key("x11", code as u32, |report| {
+            report.name = Some(lookup.keysym_name(code));
+        });
     }
+    key
+}
 
-    unsafe {
-        // Open display
-        let dpy_control = xlib::XOpenDisplay(null());
-        if dpy_control.is_null() {
-            return Err(Error::HookStartFailed("Failed to open X display".into()));
-        }
+/// Intercept data handed to the record callback, freed on drop.
+struct InterceptData(*mut xrecord::XRecordInterceptData);
 
-        // Check for RECORD extension
-        let extension_name = c"RECORD";
-        let extension = xlib::XInitExtension(dpy_control, extension_name.as_ptr());
-        if extension.is_null() {
-            xlib::XCloseDisplay(dpy_control);
-            return Err(Error::HookStartFailed(
-                "XRecord extension not available".into(),
-            ));
-        }
+impl Drop for InterceptData {
+    fn drop(&mut self) {
+        // SAFETY: XRecord allocated the data for the callback to free, once
+        unsafe { xrecord::XRecordFreeData(self.0) };
+    }
+}
 
-        // Prepare record range
-        let mut record_range: xrecord::XRecordRange = *xrecord::XRecordAllocRange();
-        record_range.device_events.first = xlib::KeyPress as c_uchar;
-        record_range.device_events.last = xlib::MotionNotify as c_uchar;
+/// XRecord callback, with the [`RecordState`] of its run as `closure`.
+unsafe extern "C" fn record_callback(
+    closure: *mut c_char,
+    raw_data: *mut xrecord::XRecordInterceptData,
+) {
+    if raw_data.is_null() {
+        return;
+    }
+    let intercepted = InterceptData(raw_data);
+    // SAFETY: non-null, and XRecord's until `intercepted` frees it below
+    let data = unsafe { &*intercepted.0 };
+    // SAFETY: `run_hook` passes its `RecordState` as the closure, and it
+    // outlives the record loop that calls back
+    let Some(state) = (unsafe { closure.cast::<RecordState>().as_ref() }) else {
+        return;
+    };
 
-        // Create context
-        let mut record_all_clients: c_ulong = xrecord::XRecordAllClients;
-        let context = xrecord::XRecordCreateContext(
-            dpy_control,
-            0,
-            &mut record_all_clients,
-            1,
-            &mut &mut record_range as *mut &mut xrecord::XRecordRange
-                as *mut *mut xrecord::XRecordRange,
-            1,
-        );
+    if data.category != xrecord::XRecordFromServer {
+        return;
+    }
 
-        if context == 0 {
-            xlib::XCloseDisplay(dpy_control);
-            return Err(Error::HookStartFailed(
-                "Failed to create XRecord context".into(),
-            ));
-        }
+    // Check stop flag
+    if !state.running.load(Ordering::SeqCst) {
+        return;
+    }
 
-        xlib::XSync(dpy_control, FALSE);
+    // Parse the event data (`data_len` counts 4-byte units)
+    let Ok(units) = usize::try_from(data.data_len) else {
+        return;
+    };
+    // SAFETY: the server data is `data_len` units at `data`, which stay
+    // valid and unchanged until `intercepted` is freed, after its last use
+    let bytes = unsafe { convert::x11::datum(data.data, units) };
+    let Some(raw) = bytes.and_then(convert::x11::RawEvent::parse) else {
+        return;
+    };
 
-        // Store context for stop_hook to use
+    // Keyboard mapping changed: cached keysyms are stale
+    if raw.type_ == convert::x11::MAPPING_NOTIFY {
+        if let Ok(mut guard) = KEYSYM_LOOKUP.lock()
+            && let Some(lookup) = guard.as_mut()
         {
-            let mut c = CONTEXT
-                .lock()
-                .map_err(|_| Error::ThreadError("context mutex poisoned".into()))?;
-            *c = Some(context);
+            lookup.cache.clear();
         }
+        return;
+    }
 
-        // Send hook enabled event
-        if let Ok(guard) = HANDLER.lock()
-            && let Some(ref handler) = *guard
-        {
-            handler.handle_event(&Event::hook_enabled());
-        }
+    if let Some(event) =
+        convert::with_global_mask(|mask| convert::x11::convert(&raw, mask, resolve_key))
+    {
+        state.handler.handle_event(&event);
+    }
+}
 
-        // Run the record loop
-        let result =
-            xrecord::XRecordEnableContext(dpy_control, context, Some(record_callback), &mut 0);
+/// Record only the events in `mask` from the next hook on.
+pub(crate) fn set_event_mask(mask: EventMask) {
+    EVENT_MASK.store(mask.bits(), Ordering::SeqCst);
+}
 
-        // Send hook disabled event
-        if let Ok(guard) = HANDLER.lock()
-            && let Some(ref handler) = *guard
-        {
-            handler.handle_event(&Event::hook_disabled());
-        }
+/// Run the event hook (blocking).
+///
+/// Fails with [`Error::AlreadyRunning`] while another hook runs.
+pub fn run_hook<H: EventHandler + 'static>(running: &Arc<AtomicBool>, handler: H) -> Result<()> {
+    let _claim = ACTIVE.claim(running)?;
+    let _statics = HookStatics;
+    let state = RecordState {
+        running,
+        handler: &handler,
+    };
 
-        // Clean up
-        xrecord::XRecordDisableContext(dpy_control, context);
-        xrecord::XRecordFreeContext(dpy_control, context);
-        xlib::XCloseDisplay(dpy_control);
+    // Open display
+    let dpy_control = DisplayConnection::open()
+        .ok_or_else(|| Error::HookStartFailed("Failed to open X display".into()))?;
+
+    // Check for RECORD extension
+    // SAFETY: the display is open, and the name a NUL-terminated string
+    let extension = unsafe { xlib::XInitExtension(dpy_control.0, c"RECORD".as_ptr()) };
+    if extension.is_null() {
+        return Err(Error::HookStartFailed(
+            "XRecord extension not available".into(),
+        ));
+    }
 
-        if result == 0 {
-            return Err(Error::HookStartFailed(
-                "Failed to enable XRecord context".into(),
-            ));
-        }
+    // Prepare record range
+    // SAFETY: no preconditions; the range is zeroed, or null on failure
+    let range = unsafe { xrecord::XRecordAllocRange() };
+    if range.is_null() {
+        return Err(Error::HookStartFailed(
+            "Failed to allocate XRecord range".into(),
+        ));
     }
+    // SAFETY: `range` is a valid range that nothing else uses; it is copied
+    // out before Xlib frees it
+    let mut record_range: xrecord::XRecordRange = unsafe {
+        let copy = *range;
+        xlib::XFree(range.cast());
+        copy
+    };
+    let events = EventMask::from_bits_truncate(EVENT_MASK.load(Ordering::SeqCst));
+    let (first, last) = convert::x11::record_range(events).unwrap_or((0, 0));
+    record_range.device_events.first = first;
+    record_range.device_events.last = last;
+    record_range.delivered_events.first = xlib::MappingNotify as c_uchar;
+    record_range.delivered_events.last = xlib::MappingNotify as c_uchar;
+
+    // Create context
+    let mut record_all_clients: c_ulong = xrecord::XRecordAllClients;
+    let mut ranges = [&raw mut record_range];
+    // SAFETY: the display is open, and the client and range arrays hold the
+    // one element each they are said to; Xlib copies them
+    let context = unsafe {
+        xrecord::XRecordCreateContext(
+            dpy_control.0,
+            0,
+            &mut record_all_clients,
+            1,
+            ranges.as_mut_ptr(),
+            1,
+        )
+    };
 
-    // Clean up handler and statics
-    {
-        let mut h = HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = None;
+    if context == 0 {
+        return Err(Error::HookStartFailed(
+            "Failed to create XRecord context".into(),
+        ));
     }
+    let context = RecordContext {
+        display: &dpy_control,
+        context,
+    };
+
+    // SAFETY: the display is open
+    unsafe { xlib::XSync(dpy_control.0, FALSE) };
+
+    // Open a connection for keysym lookups; without it unknown keys stay Unknown
+    if let Some(display) = DisplayConnection::open()
+        && let Ok(mut guard) = KEYSYM_LOOKUP.lock()
     {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = None;
+        *guard = Some(KeysymLookup {
+            display,
+            cache: KeysymCache::new(),
+        });
     }
+
+    // Store context for stop_hook to use
     {
         let mut c = CONTEXT
             .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *c = None;
+            .map_err(|_| Error::ThreadError("context mutex poisoned".into()))?;
+        *c = Some(context.context);
+    }
+
+    // Send hook enabled event
+    handler.handle_event(&Event::hook_enabled());
+
+    // Run the record loop
+    // SAFETY: the display and context are open until after the loop
+    // returns, and `record_callback` only reads `state`, which outlives it
+    let result = unsafe {
+        xrecord::XRecordEnableContext(
+            dpy_control.0,
+            context.context,
+            Some(record_callback),
+            &state as *const RecordState as *mut c_char,
+        )
+    };
+
+    // Send hook disabled event
+    handler.handle_event(&Event::hook_disabled());
+
+    if result == 0 {
+        return Err(Error::HookStartFailed(
+            "Failed to enable XRecord context".into(),
+        ));
     }
 
     Ok(())
 }
 
-/// Stop the event hook.
+/// Stop the event hook, once its running flag has been cleared.
 pub fn stop_hook() -> Result<()> {
-    // Signal the stop flag to tell the XRecord loop to exit
-    if let Ok(guard) = STOP_FLAG.lock()
-        && let Some(ref flag) = *guard
-    {
-        flag.store(false, Ordering::SeqCst);
+    // A stop for a hook that never got the backend leaves the running one be
+    if !ACTIVE.stopping() {
+        return Ok(());
     }
 
     // XRecordDisableContext needs to be called from a separate control display
     // connection to unblock XRecordEnableContext on the data connection
-    unsafe {
-        if let Ok(ctx_guard) = CONTEXT.lock()
-            && let Some(ctx) = *ctx_guard
-        {
-            // Open a new display connection for the control channel
-            let dpy_control = xlib::XOpenDisplay(null());
-            if !dpy_control.is_null() {
-                xrecord::XRecordDisableContext(dpy_control, ctx);
-                xlib::XCloseDisplay(dpy_control);
-            }
-        }
+    if let Ok(ctx_guard) = CONTEXT.lock()
+        && let Some(ctx) = *ctx_guard
+        && let Some(dpy_control) = DisplayConnection::open()
+    {
+        // SAFETY: the display is open; `CONTEXT` holds the context while the
+        // hook runs, and holding its lock keeps the hook from freeing it
+        unsafe { xrecord::XRecordDisableContext(dpy_control.0, ctx) };
     }
 
     Ok(())
@@ -361,3 +414,43 @@ pub fn run_grab_hook<H: GrabHandler + 'static>(
     let adapter = GrabToListenAdapter(handler);
     run_hook(running, adapter)
 }
+
+/// Keycodes the X server can report.
+pub(crate) const KEYCODES: std::ops::RangeInclusive<u32> = 8..=255;
+
+/// The key a keycode maps to, without consulting the layout.
+pub(crate) fn key_for_code(code: u32) -> Key {
+    keycode_to_key(code)
+}
+
+/// Input methods talk to apps over XIM or D-Bus, which XRecord doesn't see.
+pub(crate) fn ime_composing() -> Option<bool> {
+    None
+}
+
+/// Keys are named by position already.
+pub(crate) fn physical_key(_code: u32, key: Key) -> Key {
+    key
+}
+
+/// The key this keycode types on the active layout.
+///
+/// Needs the keysym lookup connection, so falls back to the physical key
+/// while no listener is running.
+pub(crate) fn logical_key(code: u32, key: Key) -> Key {
+    // Only character keys move between layouts, and only keys a listener
+    // reported for this code (not events built by hand) can be resolved
+    if key.as_char().is_none() || keycode_to_key(code) != key {
+        return key;
+    }
+    let Ok(code) = u8::try_from(code) else {
+        return key;
+    };
+    let Ok(mut guard) = KEYSYM_LOOKUP.lock() else {
+        return key;
+    };
+    match guard.as_mut().map(|lookup| lookup.resolve(code)) {
+        Some(Key::Unknown(_)) | None => key,
+        Some(logical) => logical,
+    }
+}
@@ -1,12 +1,74 @@
 //! X11 implementation using XRecord.
 
+mod cursor;
 mod display;
 mod listen;
 mod simulate;
 
+pub(crate) use cursor::{confine_cursor, hide_cursor, release_cursor, show_cursor, warp_cursor};
 pub use display::{display_at_point, displays, primary_display, system_settings};
+pub(crate) use listen::{
+    KEYCODES, ime_composing, key_for_code, logical_key, physical_key, set_event_mask,
+};
 pub use listen::{run_grab_hook, run_hook, stop_hook};
+pub(crate) use simulate::{held_buttons, inject_synthetic, lock_states, reinject};
 pub use simulate::{
     key_press, key_release, key_tap, mouse_click, mouse_move, mouse_position, mouse_press,
     mouse_release, simulate,
 };
+
+/// Grab mode falls back to listening on X11, so there is nothing to release.
+pub(crate) fn set_grab_enabled(_enabled: bool) {}
+
+/// Grabs can't block input on X11, so the failsafe has nothing to guard.
+#[cfg(feature = "failsafe")]
+pub(crate) struct KeyProbe;
+
+#[cfg(feature = "failsafe")]
+impl KeyProbe {
+    pub(crate) fn open(_keys: &[crate::keycode::Key]) -> crate::error::Result<Self> {
+        Err(crate::error::Error::NotSupported(
+            "The failsafe isn't needed on X11, where grabs can't block input".into(),
+        ))
+    }
+
+    pub(crate) fn all_held(&mut self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "failsafe")]
+pub(crate) fn emergency_release() {}
+
+/// Touchscreens arrive as mouse events through XRecord.
+pub(crate) fn set_touch_as_mouse(_enabled: bool) {}
+
+/// Absolute devices are calibrated by the X server (libinput/evdev driver).
+pub(crate) fn set_input_transforms(_transforms: Vec<crate::calibration::DeviceTransform>) {}
+
+/// Whether grab mode can keep events from reaching other applications.
+pub(crate) const GRAB_SUPPRESSES: bool = false;
+
+/// Whether events carry the OS's timestamps rather than the time monio saw
+/// them, which makes their delivery latency measurable.
+pub(crate) const NATIVE_TIMESTAMPS: bool = false;
+
+/// Name of the input backend, recorded in recording metadata.
+#[cfg_attr(not(feature = "recorder"), allow(dead_code))]
+pub(crate) const BACKEND: &str = "x11";
+
+/// Whether pointer positions are absolute screen coordinates.
+pub(crate) const ABSOLUTE_POSITION: bool = true;
+
+/// Whether pointer positions are in points rather than physical pixels.
+pub(crate) const POSITIONS_IN_POINTS: bool = false;
+
+/// Whether each input device is read on its own.
+pub(crate) const PER_DEVICE: bool = false;
+
+/// Whether `Key::Char` keys the layout has no key for can be simulated.
+pub(crate) const SIMULATES_UNICODE: bool = true;
+
+/// Where display information comes from.
+pub(crate) const DISPLAY_SOURCE: Option<crate::display::DisplaySource> =
+    Some(crate::display::DisplaySource::WindowSystem);
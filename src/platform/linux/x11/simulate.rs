@@ -1,14 +1,21 @@
 //! X11 event simulation using XTest.
 
 use crate::error::{Error, Result};
-use crate::event::{Button, Event, EventType};
+use crate::event::{Backend, Button, Event, EventType};
 use crate::keycode::Key;
-use std::os::raw::c_int;
+use crate::snapshot::LockStates;
+use crate::state;
+use std::os::raw::{c_int, c_uint, c_ulong};
 use std::ptr::null;
+use std::sync::Mutex;
 use x11::xlib;
 use x11::xtest;
 
 use crate::platform::linux::keycodes::key_to_keycode;
+use crate::platform::linux::keysym::{char_to_dead_keysym, char_to_keysym, media_key_keysym};
+
+/// Spare keycodes temporarily bound to characters missing from the layout
+static REMAPPED: Mutex<Vec<(char, c_uint)>> = Mutex::new(Vec::new());
 
 const TRUE: c_int = 1;
 const FALSE: c_int = 0;
@@ -50,6 +57,76 @@ pub fn mouse_position() -> Result<(f64, f64)> {
     }
 }
 
+/// Mouse buttons the server reports held, as a `MASK_BUTTON*` mask.
+pub(crate) fn held_buttons() -> Option<u32> {
+    let display = open_display().ok()?;
+    let screen = unsafe { xlib::XDefaultScreen(display) };
+    let root = unsafe { xlib::XRootWindow(display, screen) };
+
+    let mut root_return = 0u64;
+    let mut child_return = 0u64;
+    let mut root_x: c_int = 0;
+    let mut root_y: c_int = 0;
+    let mut win_x: c_int = 0;
+    let mut win_y: c_int = 0;
+    let mut mask: u32 = 0;
+
+    let result = unsafe {
+        xlib::XQueryPointer(
+            display,
+            root,
+            &mut root_return,
+            &mut child_return,
+            &mut root_x,
+            &mut root_y,
+            &mut win_x,
+            &mut win_y,
+            &mut mask,
+        )
+    };
+    unsafe { xlib::XCloseDisplay(display) };
+
+    if result == FALSE {
+        return None;
+    }
+    // X11 numbers the middle button 2 and the right button 3
+    let mut held = 0;
+    for (x_mask, monio_mask) in [
+        (xlib::Button1Mask, state::MASK_BUTTON1),
+        (xlib::Button2Mask, state::MASK_BUTTON3),
+        (xlib::Button3Mask, state::MASK_BUTTON2),
+    ] {
+        if mask & x_mask != 0 {
+            held |= monio_mask;
+        }
+    }
+    Some(held)
+}
+
+/// Lock key states, from the keyboard indicators.
+///
+/// Uses the conventional indicator order (Caps Lock, Num Lock, Scroll Lock).
+pub(crate) fn lock_states() -> LockStates {
+    /// `XkbUseCoreKbd`
+    const USE_CORE_KBD: c_uint = 0x0100;
+
+    let Ok(display) = open_display() else {
+        return LockStates::default();
+    };
+    let mut state: c_uint = 0;
+    let status = unsafe { xlib::XkbGetIndicatorState(display, USE_CORE_KBD, &mut state) };
+    unsafe { xlib::XCloseDisplay(display) };
+
+    if status != 0 {
+        return LockStates::default();
+    }
+    LockStates {
+        caps_lock: state & 1 != 0,
+        num_lock: state & 2 != 0,
+        scroll_lock: state & 4 != 0,
+    }
+}
+
 /// Open a display connection
 fn open_display() -> Result<*mut xlib::Display> {
     let display = unsafe { xlib::XOpenDisplay(null()) };
@@ -60,6 +137,143 @@ fn open_display() -> Result<*mut xlib::Display> {
     }
 }
 
+/// Find a keycode with no keysyms bound that isn't already remapped.
+fn find_spare_keycode(display: *mut xlib::Display, remapped: &[(char, c_uint)]) -> Option<c_uint> {
+    unsafe {
+        let mut min: c_int = 0;
+        let mut max: c_int = 0;
+        xlib::XDisplayKeycodes(display, &mut min, &mut max);
+
+        let mut per_keycode: c_int = 0;
+        let mapping =
+            xlib::XGetKeyboardMapping(display, min as u8, max - min + 1, &mut per_keycode);
+        if mapping.is_null() {
+            return None;
+        }
+
+        let per_keycode = per_keycode as usize;
+        let keysyms = std::slice::from_raw_parts(mapping, (max - min + 1) as usize * per_keycode);
+        // Search from the top, where keycodes are least likely to be in use
+        let spare = (min..=max).rev().map(|code| code as c_uint).find(|&code| {
+            let offset = (code as usize - min as usize) * per_keycode;
+            keysyms[offset..offset + per_keycode]
+                .iter()
+                .all(|&sym| sym == 0)
+                && !remapped.iter().any(|&(_, c)| c == code)
+        });
+
+        xlib::XFree(mapping.cast());
+        spare
+    }
+}
+
+/// Resolve the keycode for a key.
+///
+/// A `Key::Char` is looked up on the active layout; if no key produces it, a
+/// spare keycode is bound to it until the key is released. Media keys are
+/// looked up by their `XF86Audio*` keysym, falling back to the evdev keymap.
+fn resolve_keycode(display: *mut xlib::Display, key: Key) -> Result<c_uint> {
+    if let Some(keysym) = media_key_keysym(key) {
+        let code = unsafe { xlib::XKeysymToKeycode(display, keysym as c_ulong) };
+        if code != 0 {
+            return Ok(code as c_uint);
+        }
+    }
+    let Key::Char(ch) = key else {
+        return key_to_keycode(key)
+            .ok_or_else(|| Error::SimulateFailed(format!("Unsupported key: {:?}", key)));
+    };
+
+    let mut remapped = REMAPPED
+        .lock()
+        .map_err(|_| Error::SimulateFailed("mutex poisoned".into()))?;
+    if let Some(&(_, code)) = remapped.iter().find(|(c, _)| *c == ch) {
+        return Ok(code);
+    }
+
+    for keysym in [Some(char_to_keysym(ch)), char_to_dead_keysym(ch)]
+        .into_iter()
+        .flatten()
+    {
+        let code = unsafe { xlib::XKeysymToKeycode(display, keysym as c_ulong) };
+        if code != 0 {
+            return Ok(code as c_uint);
+        }
+    }
+
+    let code = find_spare_keycode(display, &remapped)
+        .ok_or_else(|| Error::SimulateFailed(format!("No spare keycode for {:?}", key)))?;
+    let mut keysym = char_to_keysym(ch) as c_ulong;
+    unsafe {
+        xlib::XChangeKeyboardMapping(display, code as c_int, 1, &mut keysym, 1);
+        xlib::XSync(display, FALSE);
+    }
+    remapped.push((ch, code));
+    Ok(code)
+}
+
+/// Unbind a spare keycode remapped for `key`, if any.
+fn restore_keycode(display: *mut xlib::Display, key: Key) {
+    let Key::Char(ch) = key else {
+        return;
+    };
+    let Ok(mut remapped) = REMAPPED.lock() else {
+        return;
+    };
+    if let Some(index) = remapped.iter().position(|(c, _)| *c == ch) {
+        let (_, code) = remapped.remove(index);
+        let mut no_symbol: c_ulong = 0;
+        unsafe {
+            xlib::XChangeKeyboardMapping(display, code as c_int, 1, &mut no_symbol, 1);
+            xlib::XSync(display, FALSE);
+        }
+    }
+}
+
+/// Inject an event on behalf of the grab hook.
+///
+/// Grab mode falls back to listening on X11, so there is no grab to bypass.
+pub(crate) fn inject_synthetic(event: &Event) -> Result<()> {
+    simulate(event)
+}
+
+/// Re-post a captured event as the hook saw it.
+///
+/// Keys are sent by their original keycode; button events first move the
+/// pointer back to where they happened. Modifiers are server state on X11,
+/// so the ones held when the event is posted apply.
+pub(crate) fn reinject(event: &Event) -> Result<()> {
+    match (event.event_type, &event.keyboard, &event.mouse) {
+        (EventType::KeyPressed | EventType::KeyReleased, Some(kb), _) => {
+            fake_keycode(kb.raw_code, event.event_type == EventType::KeyPressed)
+        }
+        (EventType::MousePressed | EventType::MouseReleased, _, Some(mouse)) => {
+            mouse_move(mouse.x, mouse.y)?;
+            simulate(event)
+        }
+        _ => simulate(event),
+    }
+}
+
+/// Press or release a raw keycode.
+fn fake_keycode(keycode: u32, pressed: bool) -> Result<()> {
+    let display = open_display()?;
+    let is_press = if pressed { TRUE } else { FALSE };
+    let result = unsafe { xtest::XTestFakeKeyEvent(display, keycode, is_press, 0) };
+
+    unsafe {
+        xlib::XFlush(display);
+        xlib::XSync(display, 0);
+        xlib::XCloseDisplay(display);
+    }
+
+    if result == 0 {
+        Err(Error::SimulateFailed("XTestFakeKeyEvent failed".into()))
+    } else {
+        Ok(())
+    }
+}
+
 /// Simulate an event.
 pub fn simulate(event: &Event) -> Result<()> {
     match event.event_type {
@@ -94,7 +308,8 @@ pub fn simulate(event: &Event) -> Result<()> {
         }
         EventType::MouseWheel => {
             if let Some(wheel) = &event.wheel {
-                mouse_scroll(wheel.delta as i32, 0)?;
+                let (vertical, horizontal) = wheel.steps();
+                mouse_scroll(vertical, horizontal)?;
             }
         }
         _ => {}
@@ -104,10 +319,14 @@ pub fn simulate(event: &Event) -> Result<()> {
 
 /// Press a key.
 pub fn key_press(key: Key) -> Result<()> {
-    let keycode = key_to_keycode(key)
-        .ok_or_else(|| Error::SimulateFailed(format!("Unsupported key: {:?}", key)))?;
-
     let display = open_display()?;
+    let keycode = match resolve_keycode(display, key) {
+        Ok(keycode) => keycode,
+        Err(e) => {
+            unsafe { xlib::XCloseDisplay(display) };
+            return Err(e);
+        }
+    };
     let result = unsafe { xtest::XTestFakeKeyEvent(display, keycode, TRUE, 0) };
 
     unsafe {
@@ -125,17 +344,22 @@ pub fn key_press(key: Key) -> Result<()> {
 
 /// Release a key.
 pub fn key_release(key: Key) -> Result<()> {
-    let keycode = key_to_keycode(key)
-        .ok_or_else(|| Error::SimulateFailed(format!("Unsupported key: {:?}", key)))?;
-
     let display = open_display()?;
+    let keycode = match resolve_keycode(display, key) {
+        Ok(keycode) => keycode,
+        Err(e) => {
+            unsafe { xlib::XCloseDisplay(display) };
+            return Err(e);
+        }
+    };
     let result = unsafe { xtest::XTestFakeKeyEvent(display, keycode, FALSE, 0) };
 
     unsafe {
         xlib::XFlush(display);
         xlib::XSync(display, 0);
-        xlib::XCloseDisplay(display);
     }
+    restore_keycode(display, key);
+    unsafe { xlib::XCloseDisplay(display) };
 
     if result == 0 {
         Err(Error::SimulateFailed("XTestFakeKeyEvent failed".into()))
@@ -152,20 +376,15 @@ pub fn key_tap(key: Key) -> Result<()> {
 }
 
 /// Get X11 button code
-fn button_to_code(button: Button) -> u32 {
-    match button {
-        Button::Left => 1,
-        Button::Middle => 2,
-        Button::Right => 3,
-        Button::Button4 => 8,
-        Button::Button5 => 9,
-        Button::Unknown(code) => code as u32,
-    }
+fn button_to_code(button: Button) -> Result<u32> {
+    button
+        .platform_code(Backend::X11)
+        .ok_or_else(|| Error::SimulateFailed(format!("X11 has no button for {:?}", button)))
 }
 
 /// Press a mouse button.
 pub fn mouse_press(button: Button) -> Result<()> {
-    let code = button_to_code(button);
+    let code = button_to_code(button)?;
     let display = open_display()?;
     let result = unsafe { xtest::XTestFakeButtonEvent(display, code, TRUE, 0) };
 
@@ -184,7 +403,7 @@ pub fn mouse_press(button: Button) -> Result<()> {
 
 /// Release a mouse button.
 pub fn mouse_release(button: Button) -> Result<()> {
-    let code = button_to_code(button);
+    let code = button_to_code(button)?;
     let display = open_display()?;
     let result = unsafe { xtest::XTestFakeButtonEvent(display, code, FALSE, 0) };
 
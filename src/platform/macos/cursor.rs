@@ -0,0 +1,119 @@
+//! Cursor confinement, visibility and warping.
+//!
+//! There is no system-wide clip rectangle on macOS, so confinement is done
+//! by a background thread that polls the cursor and warps it back whenever
+//! it has left.
+
+use crate::display::Rect;
+use crate::error::{Error, Result};
+use objc2_core_foundation::CGPoint;
+use objc2_core_graphics::{
+    CGAssociateMouseAndMouseCursorPosition, CGDisplayHideCursor, CGDisplayShowCursor, CGError,
+    CGMainDisplayID, CGWarpMouseCursorPosition,
+};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often the cursor position is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+struct Clamp {
+    rect: Option<Rect>,
+    /// Whether the polling thread is running.
+    running: bool,
+}
+
+static CLAMP: Mutex<Clamp> = Mutex::new(Clamp {
+    rect: None,
+    running: false,
+});
+
+/// Warp the cursor into `rect` if it is outside.
+fn clamp(rect: Rect) -> Result<()> {
+    let (x, y) = super::mouse_position()?;
+    if rect.contains(x, y) {
+        return Ok(());
+    }
+    let (x, y) = rect.clamp(x, y);
+    warp_cursor(x, y)
+}
+
+/// Warped cursors don't produce mouse events.
+pub(crate) fn warp_cursor(x: f64, y: f64) -> Result<()> {
+    let status = CGWarpMouseCursorPosition(CGPoint { x, y });
+    if status != CGError::Success {
+        return Err(Error::Platform(format!(
+            "CGWarpMouseCursorPosition failed: {:?}",
+            status
+        )));
+    }
+    // Warping suppresses mouse input briefly unless re-associated
+    CGAssociateMouseAndMouseCursorPosition(true);
+    Ok(())
+}
+
+/// Only takes effect while this process is the active application.
+pub(crate) fn hide_cursor() -> Result<()> {
+    let status = CGDisplayHideCursor(CGMainDisplayID());
+    if status != CGError::Success {
+        return Err(Error::Platform(format!(
+            "CGDisplayHideCursor failed: {:?}",
+            status
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn show_cursor() -> Result<()> {
+    let status = CGDisplayShowCursor(CGMainDisplayID());
+    if status != CGError::Success {
+        return Err(Error::Platform(format!(
+            "CGDisplayShowCursor failed: {:?}",
+            status
+        )));
+    }
+    Ok(())
+}
+
+fn poll() {
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let rect = match CLAMP.lock() {
+            Ok(mut state) => match state.rect {
+                Some(rect) => rect,
+                None => {
+                    state.running = false;
+                    return;
+                }
+            },
+            Err(_) => return,
+        };
+        if let Err(e) = clamp(rect) {
+            log::debug!("Failed to clamp cursor: {}", e);
+        }
+    }
+}
+
+pub(crate) fn confine_cursor(rect: Rect) -> Result<()> {
+    let mut state = CLAMP
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    clamp(rect)?;
+    if !state.running {
+        std::thread::Builder::new()
+            .name("monio-cursor-clamp".into())
+            .spawn(poll)
+            .map_err(|e| Error::ThreadError(e.to_string()))?;
+        state.running = true;
+    }
+    state.rect = Some(rect);
+    Ok(())
+}
+
+pub(crate) fn release_cursor() -> Result<()> {
+    let mut state = CLAMP
+        .lock()
+        .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+    state.rect = None;
+    Ok(())
+}
@@ -1,10 +1,13 @@
 //! macOS display and system property queries.
 
-use crate::display::{DisplayInfo, Rect, SystemSettings};
+use crate::display::{DisplayInfo, DisplaySource, Rect, SystemSettings};
 use crate::error::{Error, Result};
+use core::ffi::c_void;
+use core::ptr::NonNull;
+use objc2::{class, msg_send};
 use objc2_core_foundation::{
-    CFNumber, CFNumberType, CFPreferencesCopyValue, CFString, kCFPreferencesAnyApplication,
-    kCFPreferencesAnyHost, kCFPreferencesCurrentUser,
+    CFBoolean, CFNumber, CFNumberType, CFPreferencesCopyValue, CFRetained, CFString, CFType,
+    kCFPreferencesAnyApplication, kCFPreferencesAnyHost, kCFPreferencesCurrentUser,
 };
 use objc2_core_graphics::{
     CGDirectDisplayID, CGDisplayBounds, CGDisplayCopyDisplayMode, CGDisplayMode,
@@ -44,6 +47,7 @@ fn display_info(display_id: CGDirectDisplayID, index: u32) -> DisplayInfo {
         scale_factor,
         refresh_rate,
         is_primary: display_id == CGMainDisplayID(),
+        source: DisplaySource::WindowSystem,
     }
 }
 
@@ -92,20 +96,104 @@ pub fn system_settings() -> Result<SystemSettings> {
         pref_number_i64("InitialKeyRepeat").map(|value| (value * 15) as u32);
 
     let mouse_sensitivity = pref_number_f64("com.apple.mouse.scaling");
+    // A scaling of -1, or the "linear" switch added in macOS 14, turns the
+    // acceleration curve off
+    let mouse_acceleration = if pref_bool("com.apple.mouse.linear") == Some(true) {
+        Some(0.0)
+    } else {
+        mouse_sensitivity.map(|scaling| scaling.max(0.0))
+    };
+    let (keyboard_layout, keyboard_layout_name) = keyboard_layout();
+    // The preference is only written once the user changes the setting
     let double_click_time = pref_number_f64("com.apple.mouse.doubleClickThreshold")
-        .map(|seconds| (seconds * 1000.0) as u32);
+        .or_else(|| Some(unsafe { msg_send![class!(NSEvent), doubleClickInterval] }))
+        .map(|seconds: f64| (seconds * 1000.0) as u32);
 
     Ok(SystemSettings {
         keyboard_repeat_rate,
         keyboard_repeat_delay,
         mouse_sensitivity,
-        mouse_acceleration: None,
+        mouse_acceleration,
         mouse_acceleration_threshold: None,
+        mouse_acceleration_threshold2: None,
         double_click_time,
-        keyboard_layout: None,
+        drag_threshold: None,
+        keyboard_layout,
+        keyboard_layout_name,
+        // Natural scrolling is on unless the user turned it off
+        natural_scrolling: Some(pref_bool("com.apple.swipescrolldirection").unwrap_or(true)),
     })
 }
 
+#[link(name = "Carbon", kind = "framework")]
+unsafe extern "C" {
+    static kTISPropertyInputSourceID: *const c_void;
+    static kTISPropertyLocalizedName: *const c_void;
+    fn TISCopyCurrentKeyboardInputSource() -> *mut c_void;
+    fn TISGetInputSourceProperty(source: *mut c_void, key: *const c_void) -> *const c_void;
+}
+
+unsafe extern "C" {
+    fn pthread_main_np() -> i32;
+}
+
+/// The current input source's ID (e.g. `com.apple.keylayout.US`) and
+/// localized name.
+///
+/// Text Input Sources must be used from the main thread (newer macOS
+/// versions abort otherwise), so other threads read the ID from the
+/// HIToolbox preferences and name the layout after its last component.
+fn keyboard_layout() -> (Option<String>, Option<String>) {
+    if unsafe { pthread_main_np() } != 0 {
+        return unsafe { current_input_source() };
+    }
+    let id = pref_string_in(
+        "com.apple.HIToolbox",
+        "AppleCurrentKeyboardLayoutInputSourceID",
+    );
+    let name = id
+        .as_deref()
+        .and_then(|id| id.rsplit('.').next())
+        .map(str::to_string);
+    (id, name)
+}
+
+/// Must be called on the main thread.
+unsafe fn current_input_source() -> (Option<String>, Option<String>) {
+    let Some(source) = NonNull::new(unsafe { TISCopyCurrentKeyboardInputSource() }) else {
+        return (None, None);
+    };
+    // SAFETY: the source was returned by a Copy function, so we own it
+    let source = unsafe { CFRetained::<CFType>::from_raw(source.cast()) };
+    let property = |key: *const c_void| {
+        let value =
+            unsafe { TISGetInputSourceProperty(CFRetained::as_ptr(&source).as_ptr().cast(), key) };
+        // SAFETY: both properties are CFStrings owned by the source
+        unsafe { value.cast::<CFString>().as_ref() }.map(|s| s.to_string())
+    };
+    unsafe {
+        (
+            property(kTISPropertyInputSourceID),
+            property(kTISPropertyLocalizedName),
+        )
+    }
+}
+
+fn pref_string_in(application: &str, key: &str) -> Option<String> {
+    let application = CFString::from_str(application);
+    let key = CFString::from_str(key);
+    let value = unsafe {
+        CFPreferencesCopyValue(
+            &key,
+            &application,
+            kCFPreferencesCurrentUser,
+            kCFPreferencesAnyHost,
+        )
+    }?;
+    let string = value.downcast::<CFString>().ok()?;
+    Some(string.to_string())
+}
+
 fn pref_number_i64(key: &str) -> Option<i64> {
     let key = CFString::from_str(key);
     let value = unsafe {
@@ -123,6 +211,20 @@ fn pref_number_i64(key: &str) -> Option<i64> {
     if ok { Some(out) } else { None }
 }
 
+fn pref_bool(key: &str) -> Option<bool> {
+    let key = CFString::from_str(key);
+    let value = unsafe {
+        CFPreferencesCopyValue(
+            &key,
+            kCFPreferencesAnyApplication,
+            kCFPreferencesCurrentUser,
+            kCFPreferencesAnyHost,
+        )
+    }?;
+    let boolean = value.downcast::<CFBoolean>().ok()?;
+    Some(boolean.as_bool())
+}
+
 fn pref_number_f64(key: &str) -> Option<f64> {
     let key = CFString::from_str(key);
     let value = unsafe {
@@ -139,3 +241,17 @@ fn pref_number_f64(key: &str) -> Option<f64> {
     let ok = unsafe { number.value(CFNumberType::Float64Type, &mut out as *mut _ as *mut _) };
     if ok { Some(out) } else { None }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_settings_filled_in() {
+        let settings = system_settings().unwrap();
+        assert!(settings.double_click_time.is_some());
+        assert!(settings.natural_scrolling.is_some());
+        assert!(settings.keyboard_layout.is_some());
+        assert!(settings.keyboard_layout_name.is_some());
+    }
+}
@@ -0,0 +1,41 @@
+//! Key state for the failsafe, from the HID system state.
+//!
+//! The HID system state follows the hardware, before any event tap, so it
+//! sees the chord whatever a stuck tap is doing.
+
+use super::keycodes::key_to_keycode;
+use crate::error::{Error, Result};
+use crate::keycode::Key;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventSourceKeyState(state_id: i32, key: u16) -> bool;
+}
+
+/// `kCGEventSourceStateHIDSystemState`
+const HID_SYSTEM_STATE: i32 = 1;
+
+/// Reads whether keys are held.
+pub(crate) struct KeyProbe {
+    codes: Vec<u16>,
+}
+
+impl KeyProbe {
+    pub(crate) fn open(keys: &[Key]) -> Result<Self> {
+        let codes = keys
+            .iter()
+            .map(|&key| key_to_keycode(key))
+            .collect::<Option<Vec<u16>>>()
+            .ok_or_else(|| {
+                Error::NotSupported(format!("No virtual key code for a key of {:?}", keys))
+            })?;
+        Ok(Self { codes })
+    }
+
+    /// Whether all the keys are held.
+    pub(crate) fn all_held(&mut self) -> bool {
+        self.codes
+            .iter()
+            .all(|&code| unsafe { CGEventSourceKeyState(HID_SYSTEM_STATE, code) })
+    }
+}
@@ -0,0 +1,102 @@
+//! Layout-aware key resolution using the active keyboard layout.
+
+use crate::debug;
+use crate::keycode::Key;
+use objc2_core_graphics::{CGEvent, CGEventFlags};
+
+use super::keycodes::{key_to_keycode, keycode_to_key};
+
+/// Highest virtual keycode on Apple keyboards.
+const MAX_KEYCODE: u16 = 127;
+
+/// The text a keycode produces on the active layout without modifiers.
+///
+/// Translates a synthetic key-down event, which goes through the current
+/// input source without touching the Text Input Sources API.
+fn text_for_keycode(code: u16) -> Option<String> {
+    unsafe {
+        let event = CGEvent::new_keyboard_event(None, code, true)?;
+        CGEvent::set_flags(Some(&event), CGEventFlags(0));
+
+        let mut buf = [0u16; 4];
+        let mut len = 0;
+        CGEvent::keyboard_get_unicode_string(
+            Some(&event),
+            buf.len() as _,
+            &mut len,
+            buf.as_mut_ptr(),
+        );
+
+        String::from_utf16(&buf[..(len as usize).min(buf.len())])
+            .ok()
+            .filter(|text| !text.is_empty())
+    }
+}
+
+/// The character a keycode produces on the active layout without modifiers.
+fn char_for_keycode(code: u16) -> Option<char> {
+    let text = text_for_keycode(code)?;
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) if !ch.is_control() && !ch.is_whitespace() => Some(ch),
+        _ => None,
+    }
+}
+
+/// Convert a keycode, falling back to the active layout for unknown keys.
+pub fn resolve_key(code: u16) -> Key {
+    match keycode_to_key(code) {
+        Key::Unknown(_) => char_for_keycode(code)
+            .map(Key::from_char)
+            .unwrap_or_else(|| {
+                debug::unknown_key("macos", code as u32, |report| {
+                    report.text = text_for_keycode(code);
+                });
+                Key::Unknown(code as u32)
+            }),
+        key => key,
+    }
+}
+
+/// Convert a key to a keycode, searching the active layout for `Key::Char`.
+pub fn resolve_keycode(key: Key) -> Option<u16> {
+    match key {
+        Key::Char(_) => {
+            (0..=MAX_KEYCODE).find(|&code| char_for_keycode(code).map(Key::from_char) == Some(key))
+        }
+        key => key_to_keycode(key),
+    }
+}
+
+/// Virtual keycodes.
+pub(crate) const KEYCODES: std::ops::RangeInclusive<u32> = 0..=MAX_KEYCODE as u32;
+
+/// The key a keycode maps to, without consulting the layout.
+pub(crate) fn key_for_code(code: u32) -> Key {
+    u16::try_from(code).map_or(Key::Unknown(code), keycode_to_key)
+}
+
+/// Composition happens in each app's text input context, out of reach of
+/// the event tap.
+pub(crate) fn ime_composing() -> Option<bool> {
+    None
+}
+
+/// Keys are named by position already.
+pub(crate) fn physical_key(_code: u32, key: Key) -> Key {
+    key
+}
+
+/// The key this keycode types on the active layout.
+pub(crate) fn logical_key(code: u32, key: Key) -> Key {
+    // Only character keys move between layouts, and only keys a listener
+    // reported for this code (not events built by hand) can be resolved
+    if key.as_char().is_none() || u16::try_from(code).map(keycode_to_key) != Ok(key) {
+        return key;
+    }
+    u16::try_from(code)
+        .ok()
+        .and_then(char_for_keycode)
+        .map(Key::from_char)
+        .unwrap_or(key)
+}
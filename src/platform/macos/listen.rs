@@ -2,53 +2,69 @@
 
 #![allow(improper_ctypes_definitions)]
 #![allow(unsafe_op_in_unsafe_fn)]
+#![warn(clippy::undocumented_unsafe_blocks)]
 
+use crate::convert;
 use crate::error::{Error, Result};
-use crate::event::{Button, Event, ScrollDirection};
-use crate::hook::{EventHandler, GrabHandler};
-use crate::state::{
-    self, MASK_ALT, MASK_BUTTON1, MASK_BUTTON2, MASK_BUTTON3, MASK_BUTTON4, MASK_BUTTON5,
-    MASK_CTRL, MASK_META, MASK_SHIFT,
-};
+use crate::event::Event;
+use crate::hook::{EventHandler, EventMask, GrabHandler};
 use core::ptr::NonNull;
-use objc2_core_foundation::{CFMachPort, CFRunLoop, kCFRunLoopCommonModes};
+use objc2_core_foundation::{
+    CFMachPort, CFRetained, CFRunLoop, CFRunLoopSource, kCFRunLoopCommonModes,
+};
 use objc2_core_graphics::{
-    CGEvent, CGEventField, CGEventFlags, CGEventTapCallBack, CGEventTapLocation, CGEventTapOptions,
+    CGEvent, CGEventField, CGEventTapCallBack, CGEventTapLocation, CGEventTapOptions,
     CGEventTapPlacement, CGEventTapProxy, CGEventType, kCGEventMaskForAllEvents,
 };
 use objc2_foundation::NSAutoreleasePool;
 use std::ffi::c_void;
 use std::ptr::null_mut;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 
 use super::keycodes::keycode_to_key;
+use super::layout::resolve_key;
+use super::media::{SYSTEM_DEFINED, convert_system_defined};
+use super::simulate::{SIMULATED_TAG, SYNTHETIC_TAG};
+use crate::platform::ActiveHook;
 
-/// Stored handler for the callback (listen mode)
-static HANDLER: Mutex<Option<Box<dyn EventHandler>>> = Mutex::new(None);
+/// The running hook. The tap and run loop below are the running hook's,
+/// so hooks run one at a time.
+static ACTIVE: ActiveHook = ActiveHook::new();
 
-/// Stored handler for the callback (grab mode)
-static GRAB_HANDLER: Mutex<Option<Box<dyn GrabHandler>>> = Mutex::new(None);
-
-/// Flag to signal the run loop to stop
-static STOP_FLAG: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+/// Categories to tap, see [`set_event_mask`].
+static EVENT_MASK: AtomicU32 = AtomicU32::new(EventMask::ALL.bits());
 
 /// Last seen flags for detecting modifier key press/release
-static LAST_FLAGS: Mutex<CGEventFlags> = Mutex::new(CGEventFlags(0));
+static LAST_FLAGS: Mutex<u64> = Mutex::new(0);
+
+/// A retained reference to the installed tap, so it can be stored in a static.
+struct TapHandle(CFRetained<CFMachPort>);
 
-/// Wrapper for raw pointer to CFMachPort that implements Send + Sync
-/// Safety: The pointer is only accessed from the callback which runs on the same thread
-struct TapPointer(*const CFMachPort);
-unsafe impl Send for TapPointer {}
-unsafe impl Sync for TapPointer {}
+// SAFETY: CoreFoundation objects may be retained and released from any
+// thread, and the tap is only enabled through it, which CGEventTapEnable
+// allows from any thread.
+unsafe impl Send for TapHandle {}
+// SAFETY: as for `Send`; the handle hands out no interior mutability.
+unsafe impl Sync for TapHandle {}
 
 /// Stored event tap for timeout recovery
-static EVENT_TAP: Mutex<Option<TapPointer>> = Mutex::new(None);
+static EVENT_TAP: Mutex<Option<TapHandle>> = Mutex::new(None);
+
+/// The installed tap again, for [`emergency_release`], which can't wait for
+/// the lock above.
+static EVENT_TAP_RAW: AtomicPtr<c_void> = AtomicPtr::new(null_mut());
+
+/// Set once [`emergency_release`] disabled the tap, so it isn't re-enabled.
+static EMERGENCY_RELEASED: AtomicBool = AtomicBool::new(false);
 
 /// Wrapper for raw CFRunLoop pointer that implements Send + Sync.
-/// Safety: CFRunLoopStop() is documented as thread-safe by Apple.
 struct RunLoopRef(*const CFRunLoop);
+
+// SAFETY: the pointer is only used for CFRunLoopStop, which Apple documents
+// as thread-safe, and is cleared before the run loop it points to is released.
 unsafe impl Send for RunLoopRef {}
+// SAFETY: as for `Send`.
 unsafe impl Sync for RunLoopRef {}
 
 /// Stored reference to the hook thread's CFRunLoop, so `stop_hook()` can
@@ -61,76 +77,72 @@ static GRAB_MODE: AtomicBool = AtomicBool::new(false);
 #[link(name = "Cocoa", kind = "framework")]
 unsafe extern "C" {}
 
-/// Convert CGEventFlags to our modifier mask
-fn flags_to_mask(flags: CGEventFlags) -> u32 {
-    let mut mask = 0u32;
-
-    if flags.contains(CGEventFlags::MaskShift) {
-        mask |= MASK_SHIFT;
-    }
-    if flags.contains(CGEventFlags::MaskControl) {
-        mask |= MASK_CTRL;
-    }
-    if flags.contains(CGEventFlags::MaskAlternate) {
-        mask |= MASK_ALT;
-    }
-    if flags.contains(CGEventFlags::MaskCommand) {
-        mask |= MASK_META;
-    }
-
-    mask
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    static kCFRunLoopDefaultMode: *const c_void;
+    fn CFRunLoopRunInMode(
+        mode: *const c_void,
+        seconds: f64,
+        return_after_source_handled: u8,
+    ) -> i32;
 }
 
-/// Update modifier mask from event flags
-fn update_modifiers(flags: CGEventFlags) {
-    let new_mods = flags_to_mask(flags);
-    let current = state::get_mask();
+/// How long each run loop slice lasts before the stop flag is re-checked.
+const RUN_LOOP_SLICE_SECS: f64 = 0.25;
 
-    // Update only modifier bits, preserve button bits
-    let buttons = current & state::MASK_ALL_BUTTONS;
-    let new_mask = new_mods | buttons;
+/// Run the current thread's run loop until the hook is stopped.
+///
+/// `CFRunLoopStop` only affects a run loop that is already running, so a stop
+/// landing just before the loop starts would be lost. Running in short slices
+/// and re-checking the stop flag makes that case terminate too.
+unsafe fn run_until_stopped(running: &AtomicBool) {
+    while running.load(Ordering::SeqCst) {
+        CFRunLoopRunInMode(kCFRunLoopDefaultMode, RUN_LOOP_SLICE_SECS, 0);
+    }
+}
 
-    // Clear all and set new
-    state::reset_mask();
-    state::set_mask(new_mask);
+/// The handler of a run of the hook, for listen or grab mode.
+enum TapHandler<'a> {
+    Listen(&'a dyn EventHandler),
+    Grab(&'a dyn GrabHandler),
 }
 
-/// Get button mask for a button number
-fn button_to_mask(button: i64) -> u32 {
-    match button {
-        0 => MASK_BUTTON1,
-        1 => MASK_BUTTON2,
-        2 => MASK_BUTTON3,
-        3 => MASK_BUTTON4,
-        4 => MASK_BUTTON5,
-        _ => 0,
-    }
+/// What a run of the hook hands the tap callback, through its user info
+/// pointer.
+struct TapState<'a> {
+    running: &'a AtomicBool,
+    handler: TapHandler<'a>,
 }
 
-/// Convert button number to Button enum
-fn number_to_button(button: i64) -> Button {
-    match button {
-        0 => Button::Left,
-        1 => Button::Right,
-        2 => Button::Middle,
-        3 => Button::Button4,
-        4 => Button::Button5,
-        n => Button::Unknown(n as u8),
+impl TapState<'_> {
+    /// Deliver an event, returning whether the handler consumed it.
+    fn deliver(&self, event: &Event) -> bool {
+        match self.handler {
+            TapHandler::Listen(handler) => {
+                handler.handle_event(event);
+                false
+            }
+            // Nothing is consumed once the failsafe released the grab
+            TapHandler::Grab(handler) => {
+                handler.handle_event(event).is_none() && GRAB_MODE.load(Ordering::SeqCst)
+            }
+        }
     }
 }
 
-/// The CGEventTap callback
+/// The CGEventTap callback, with the [`TapState`] of its run as `user_info`.
 unsafe extern "C-unwind" fn event_callback(
     _proxy: CGEventTapProxy,
     event_type: CGEventType,
     cg_event: NonNull<CGEvent>,
-    _user_info: *mut c_void,
+    user_info: *mut c_void,
 ) -> *mut CGEvent {
+    let Some(state) = user_info.cast::<TapState>().as_ref() else {
+        return cg_event.as_ptr();
+    };
+
     // Check if we should stop
-    if let Ok(guard) = STOP_FLAG.lock()
-        && let Some(ref flag) = *guard
-        && !flag.load(Ordering::SeqCst)
-    {
+    if !state.running.load(Ordering::SeqCst) {
         if let Some(run_loop) = CFRunLoop::current() {
             run_loop.stop();
         }
@@ -142,261 +154,126 @@ unsafe extern "C-unwind" fn event_callback(
     if event_type == CGEventType::TapDisabledByTimeout
         || event_type == CGEventType::TapDisabledByUserInput
     {
+        if EMERGENCY_RELEASED.load(Ordering::SeqCst) {
+            return cg_event.as_ptr();
+        }
         if let Ok(guard) = EVENT_TAP.lock()
-            && let Some(ref tap_ptr) = *guard
+            && let Some(ref tap) = *guard
         {
             log::warn!("Event tap was disabled (timeout or user input), re-enabling...");
-            if !tap_ptr.0.is_null() {
-                CGEvent::tap_enable(&*tap_ptr.0, true);
-            }
+            CGEvent::tap_enable(&tap.0, true);
         }
         return cg_event.as_ptr();
     }
 
-    // Get event flags and update modifier state
-    let flags = CGEvent::flags(Some(cg_event.as_ref()));
-    update_modifiers(flags);
+    // Replayed events already went through the grab handler
+    let user_data =
+        CGEvent::integer_value_field(Some(cg_event.as_ref()), CGEventField::EventSourceUserData);
+    if GRAB_MODE.load(Ordering::SeqCst) && user_data == SYNTHETIC_TAG {
+        return cg_event.as_ptr();
+    }
 
-    let event = convert_event(event_type, cg_event);
+    let event = convert_event(event_type, cg_event).map(|mut event| {
+        event.injected = Some(is_injected(cg_event, user_data));
+        event.target_pid = target_pid(cg_event);
+        event
+    });
 
-    // Check if we're in grab mode
-    if GRAB_MODE.load(Ordering::SeqCst) {
-        // Grab mode: handler decides whether to consume event
-        if let Some(evt) = event
-            && let Ok(guard) = GRAB_HANDLER.lock()
-            && let Some(ref handler) = *guard
-            && handler.handle_event(&evt).is_none()
-        {
-            // Handler returned None - consume the event
-            return null_mut();
-        }
-    } else {
-        // Listen mode: just dispatch, always pass through
-        if let Some(evt) = event
-            && let Ok(guard) = HANDLER.lock()
-            && let Some(ref handler) = *guard
-        {
-            handler.handle_event(&evt);
-        }
+    if let Some(evt) = event
+        && state.deliver(&evt)
+    {
+        // Handler returned None - consume the event
+        return null_mut();
     }
 
     cg_event.as_ptr()
 }
 
-/// Convert a CGEvent to our Event type
-unsafe fn convert_event(event_type: CGEventType, cg_event: NonNull<CGEvent>) -> Option<Event> {
-    match event_type {
-        CGEventType::KeyDown => {
-            let code = CGEvent::integer_value_field(
-                Some(cg_event.as_ref()),
-                CGEventField::KeyboardEventKeycode,
-            );
-            let key = keycode_to_key(code as u16);
-            Some(Event::key_pressed(key, code as u32))
-        }
-
-        CGEventType::KeyUp => {
-            let code = CGEvent::integer_value_field(
-                Some(cg_event.as_ref()),
-                CGEventField::KeyboardEventKeycode,
-            );
-            let key = keycode_to_key(code as u16);
-            Some(Event::key_released(key, code as u32))
-        }
-
-        CGEventType::FlagsChanged => {
-            let code = CGEvent::integer_value_field(
-                Some(cg_event.as_ref()),
-                CGEventField::KeyboardEventKeycode,
-            );
-            let key = keycode_to_key(code as u16);
-            let flags = CGEvent::flags(Some(cg_event.as_ref()));
-
-            // Determine if this is a press or release based on flag changes
-            let mut last_flags = LAST_FLAGS.lock().ok()?;
-            let is_press = if flags.contains(CGEventFlags::MaskShift)
-                && !last_flags.contains(CGEventFlags::MaskShift)
-            {
-                *last_flags = flags;
-                true
-            } else if !flags.contains(CGEventFlags::MaskShift)
-                && last_flags.contains(CGEventFlags::MaskShift)
-            {
-                *last_flags = flags;
-                false
-            } else if flags.contains(CGEventFlags::MaskControl)
-                && !last_flags.contains(CGEventFlags::MaskControl)
-            {
-                *last_flags = flags;
-                true
-            } else if !flags.contains(CGEventFlags::MaskControl)
-                && last_flags.contains(CGEventFlags::MaskControl)
-            {
-                *last_flags = flags;
-                false
-            } else if flags.contains(CGEventFlags::MaskAlternate)
-                && !last_flags.contains(CGEventFlags::MaskAlternate)
-            {
-                *last_flags = flags;
-                true
-            } else if !flags.contains(CGEventFlags::MaskAlternate)
-                && last_flags.contains(CGEventFlags::MaskAlternate)
-            {
-                *last_flags = flags;
-                false
-            } else if flags.contains(CGEventFlags::MaskCommand)
-                && !last_flags.contains(CGEventFlags::MaskCommand)
-            {
-                *last_flags = flags;
-                true
-            } else if !flags.contains(CGEventFlags::MaskCommand)
-                && last_flags.contains(CGEventFlags::MaskCommand)
-            {
-                *last_flags = flags;
-                false
-            } else {
-                return None;
-            };
-
-            if is_press {
-                Some(Event::key_pressed(key, code as u32))
-            } else {
-                Some(Event::key_released(key, code as u32))
-            }
-        }
-
-        CGEventType::LeftMouseDown => {
-            state::set_mask(MASK_BUTTON1);
-            let point = CGEvent::location(Some(cg_event.as_ref()));
-            Some(Event::mouse_pressed(Button::Left, point.x, point.y))
-        }
-
-        CGEventType::LeftMouseUp => {
-            state::unset_mask(MASK_BUTTON1);
-            let point = CGEvent::location(Some(cg_event.as_ref()));
-            Some(Event::mouse_released(Button::Left, point.x, point.y))
-        }
-
-        CGEventType::RightMouseDown => {
-            state::set_mask(MASK_BUTTON2);
-            let point = CGEvent::location(Some(cg_event.as_ref()));
-            Some(Event::mouse_pressed(Button::Right, point.x, point.y))
-        }
-
-        CGEventType::RightMouseUp => {
-            state::unset_mask(MASK_BUTTON2);
-            let point = CGEvent::location(Some(cg_event.as_ref()));
-            Some(Event::mouse_released(Button::Right, point.x, point.y))
-        }
-
-        CGEventType::OtherMouseDown => {
-            let button_num = CGEvent::integer_value_field(
-                Some(cg_event.as_ref()),
-                CGEventField::MouseEventButtonNumber,
-            );
-            let mask = button_to_mask(button_num);
-            if mask != 0 {
-                state::set_mask(mask);
-            }
-            let button = number_to_button(button_num);
-            let point = CGEvent::location(Some(cg_event.as_ref()));
-            Some(Event::mouse_pressed(button, point.x, point.y))
-        }
-
-        CGEventType::OtherMouseUp => {
-            let button_num = CGEvent::integer_value_field(
-                Some(cg_event.as_ref()),
-                CGEventField::MouseEventButtonNumber,
-            );
-            let mask = button_to_mask(button_num);
-            if mask != 0 {
-                state::unset_mask(mask);
-            }
-            let button = number_to_button(button_num);
-            let point = CGEvent::location(Some(cg_event.as_ref()));
-            Some(Event::mouse_released(button, point.x, point.y))
-        }
-
-        CGEventType::MouseMoved => {
-            let point = CGEvent::location(Some(cg_event.as_ref()));
-            // THE KEY FIX: Check button state for drag detection
-            if state::is_button_held() {
-                Some(Event::mouse_dragged(point.x, point.y))
-            } else {
-                Some(Event::mouse_moved(point.x, point.y))
-            }
-        }
-
-        CGEventType::LeftMouseDragged
-        | CGEventType::RightMouseDragged
-        | CGEventType::OtherMouseDragged => {
-            let point = CGEvent::location(Some(cg_event.as_ref()));
-            Some(Event::mouse_dragged(point.x, point.y))
-        }
+/// Whether an event was generated by software.
+///
+/// Hardware events come from the HID system event source; events posted by
+/// other tools usually use another source state. Our own simulated events
+/// use the HID source too, so they are recognized by their marker instead.
+unsafe fn is_injected(cg_event: NonNull<CGEvent>, user_data: i64) -> bool {
+    /// `kCGEventSourceStateHIDSystemState`
+    const HID_SYSTEM_STATE: i64 = 1;
+
+    let source_state =
+        CGEvent::integer_value_field(Some(cg_event.as_ref()), CGEventField::EventSourceStateID);
+    source_state != HID_SYSTEM_STATE || user_data == SIMULATED_TAG || user_data == SYNTHETIC_TAG
+}
 
-        CGEventType::ScrollWheel => {
-            let point = CGEvent::location(Some(cg_event.as_ref()));
-            let delta_y = CGEvent::integer_value_field(
-                Some(cg_event.as_ref()),
-                CGEventField::ScrollWheelEventDeltaAxis1,
-            );
-            let delta_x = CGEvent::integer_value_field(
-                Some(cg_event.as_ref()),
-                CGEventField::ScrollWheelEventDeltaAxis2,
-            );
-
-            let (direction, delta) = if delta_y.abs() > delta_x.abs() {
-                if delta_y > 0 {
-                    (ScrollDirection::Up, delta_y as f64)
-                } else {
-                    (ScrollDirection::Down, -delta_y as f64)
-                }
-            } else if delta_x > 0 {
-                (ScrollDirection::Left, delta_x as f64)
-            } else {
-                (ScrollDirection::Right, -delta_x as f64)
-            };
-
-            Some(Event::mouse_wheel(point.x, point.y, direction, delta))
-        }
+/// The process the event is delivered to, if the window server says.
+unsafe fn target_pid(cg_event: NonNull<CGEvent>) -> Option<i32> {
+    let pid = CGEvent::integer_value_field(
+        Some(cg_event.as_ref()),
+        CGEventField::EventTargetUnixProcessID,
+    );
+    i32::try_from(pid).ok().filter(|&pid| pid > 0)
+}
 
-        _ => None,
+/// Convert a CGEvent to our Event type, updating the modifier and button state
+unsafe fn convert_event(event_type: CGEventType, cg_event: NonNull<CGEvent>) -> Option<Event> {
+    let event = Some(cg_event.as_ref());
+    let field = |field| CGEvent::integer_value_field(event, field);
+    let point = CGEvent::location(event);
+    let raw = convert::macos::RawEvent {
+        event_type: event_type.0,
+        flags: CGEvent::flags(event).0,
+        keycode: field(CGEventField::KeyboardEventKeycode),
+        button_number: field(CGEventField::MouseEventButtonNumber),
+        x: point.x,
+        y: point.y,
+        scroll_delta: (
+            field(CGEventField::ScrollWheelEventDeltaAxis1),
+            field(CGEventField::ScrollWheelEventDeltaAxis2),
+        ),
+    };
+
+    let mut last_flags = LAST_FLAGS.lock().ok()?;
+    let converted = convert::with_global_mask(|mask| {
+        convert::macos::convert(&raw, mask, &mut last_flags, resolve_key, keycode_to_key)
+    });
+    if event_type == SYSTEM_DEFINED {
+        return convert_system_defined(cg_event);
     }
+    converted
 }
 
-/// Run the event hook (blocking).
-pub fn run_hook<H: EventHandler + 'static>(running: &Arc<AtomicBool>, handler: H) -> Result<()> {
-    // Store handler and stop flag
-    {
-        let mut h = HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = Some(Box::new(handler));
-    }
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = Some(running.clone());
-    }
-    {
-        let mut f = LAST_FLAGS
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *f = CGEventFlags(0);
-    }
+/// Tap only the events in `mask` from the next hook on.
+pub(crate) fn set_event_mask(mask: EventMask) {
+    EVENT_MASK.store(mask.bits(), Ordering::SeqCst);
+}
 
-    unsafe {
-        let _pool = NSAutoreleasePool::new();
+/// An enabled event tap on the hook thread's run loop, torn down on drop.
+///
+/// Dropping the tap alone isn't enough: the run loop source keeps the mach
+/// port alive, so the source is removed and the port invalidated.
+struct InstalledTap {
+    tap: CFRetained<CFMachPort>,
+    source: CFRetained<CFRunLoopSource>,
+    run_loop: CFRetained<CFRunLoop>,
+}
 
+impl InstalledTap {
+    /// Create an event tap, add it to the current run loop and enable it.
+    ///
+    /// The tap hands `state` to the callback, so it must outlive the tap.
+    unsafe fn install(options: CGEventTapOptions, state: &TapState) -> Result<Self> {
         let callback: CGEventTapCallBack = Some(event_callback);
+        let events = EventMask::from_bits_truncate(EVENT_MASK.load(Ordering::SeqCst));
+        let mask = if events == EventMask::ALL {
+            kCGEventMaskForAllEvents.into()
+        } else {
+            convert::macos::tap_mask(events)
+        };
         let tap = CGEvent::tap_create(
             CGEventTapLocation::HIDEventTap,
             CGEventTapPlacement::HeadInsertEventTap,
-            CGEventTapOptions::ListenOnly,
-            kCGEventMaskForAllEvents.into(),
+            options,
+            mask,
             callback,
-            null_mut(),
+            state as *const TapState as *mut c_void,
         )
         .ok_or_else(|| {
             Error::PermissionDenied(
@@ -405,82 +282,108 @@ pub fn run_hook<H: EventHandler + 'static>(running: &Arc<AtomicBool>, handler: H
             )
         })?;
 
+        let source = CFMachPort::new_run_loop_source(None, Some(&tap), 0)
+            .ok_or_else(|| Error::HookStartFailed("Failed to create run loop source".into()))?;
+
+        let run_loop = CFRunLoop::current()
+            .ok_or_else(|| Error::HookStartFailed("Failed to get current run loop".into()))?;
+
+        let installed = Self {
+            tap,
+            source,
+            run_loop,
+        };
+
         // Store the tap reference for timeout recovery
         {
             let mut tap_guard = EVENT_TAP
                 .lock()
                 .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-            *tap_guard = Some(TapPointer(&*tap as *const CFMachPort));
+            *tap_guard = Some(TapHandle(installed.tap.clone()));
         }
-
-        let source = CFMachPort::new_run_loop_source(None, Some(&tap), 0)
-            .ok_or_else(|| Error::HookStartFailed("Failed to create run loop source".into()))?;
-
-        let current_loop = CFRunLoop::current()
-            .ok_or_else(|| Error::HookStartFailed("Failed to get current run loop".into()))?;
+        EMERGENCY_RELEASED.store(false, Ordering::SeqCst);
+        EVENT_TAP_RAW.store(
+            &*installed.tap as *const CFMachPort as *mut c_void,
+            Ordering::SeqCst,
+        );
 
         // Store run loop reference so stop_hook() can stop the correct run loop
         {
             let mut rl = HOOK_RUN_LOOP
                 .lock()
                 .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-            *rl = Some(RunLoopRef(&*current_loop as *const CFRunLoop));
+            *rl = Some(RunLoopRef(&*installed.run_loop as *const CFRunLoop));
         }
 
-        current_loop.add_source(Some(&source), kCFRunLoopCommonModes);
+        installed
+            .run_loop
+            .add_source(Some(&installed.source), kCFRunLoopCommonModes);
 
         // Enable the tap
-        CGEvent::tap_enable(&tap, true);
+        CGEvent::tap_enable(&installed.tap, true);
 
-        // Send hook enabled event
-        {
-            if let Ok(guard) = HANDLER.lock()
-                && let Some(ref handler) = *guard
-            {
-                handler.handle_event(&Event::hook_enabled());
-            }
-        }
-
-        // Run the loop
-        CFRunLoop::run();
+        Ok(installed)
+    }
+}
 
-        // Send hook disabled event
-        {
-            if let Ok(guard) = HANDLER.lock()
-                && let Some(ref handler) = *guard
-            {
-                handler.handle_event(&Event::hook_disabled());
-            }
+impl Drop for InstalledTap {
+    fn drop(&mut self) {
+        // Clear the pointers first, they are about to dangle, and drop the
+        // stored handle so the tap can't be re-enabled once invalidated
+        EVENT_TAP_RAW.store(null_mut(), Ordering::SeqCst);
+        if let Ok(mut rl) = HOOK_RUN_LOOP.lock() {
+            *rl = None;
+        }
+        if let Ok(mut t) = EVENT_TAP.lock() {
+            *t = None;
+        }
+        // SAFETY: the tap, source and run loop are retained by `self`, and
+        // this runs on the hook thread whose run loop the source was added to
+        unsafe {
+            CGEvent::tap_enable(&self.tap, false);
+            self.run_loop
+                .remove_source(Some(&self.source), kCFRunLoopCommonModes);
+            self.tap.invalidate();
         }
     }
+}
 
-    // Clean up
-    {
-        let mut rl = HOOK_RUN_LOOP
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *rl = None;
-    }
-    {
-        let mut h = HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = None;
-    }
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = None;
+#[cfg(feature = "failsafe")]
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventTapEnable(tap: *mut c_void, enable: bool);
+}
+
+/// Disable monio's event tap right away, from any thread, even while the
+/// hook thread is stuck in a handler, and keep it from being re-enabled.
+/// Input then bypasses monio; the hook keeps running until stopped.
+#[cfg(feature = "failsafe")]
+pub(crate) fn emergency_release() {
+    EMERGENCY_RELEASED.store(true, Ordering::SeqCst);
+    GRAB_MODE.store(false, Ordering::SeqCst);
+    let tap = EVENT_TAP_RAW.swap(null_mut(), Ordering::SeqCst);
+    if !tap.is_null() {
+        // SAFETY: the tap is valid until `InstalledTap` is dropped, which
+        // clears the pointer first
+        unsafe { CGEventTapEnable(tap, false) };
     }
-    {
-        let mut t = EVENT_TAP
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *t = None;
+    log::warn!("Failsafe disabled monio's event tap");
+}
+
+/// Clears the hook's statics when it returns, on every path.
+struct HookStatics;
+
+impl Drop for HookStatics {
+    fn drop(&mut self) {
+        GRAB_MODE.store(false, Ordering::SeqCst);
     }
+}
 
-    Ok(())
+/// Run the event hook (blocking).
+///
+/// Fails with [`Error::AlreadyRunning`] while another hook runs.
+pub fn run_hook<H: EventHandler + 'static>(running: &Arc<AtomicBool>, handler: H) -> Result<()> {
+    run(running, TapHandler::Listen(&handler))
 }
 
 /// Run the event hook with grab capability (blocking).
@@ -490,123 +393,44 @@ pub fn run_grab_hook<H: GrabHandler + 'static>(
     running: &Arc<AtomicBool>,
     handler: H,
 ) -> Result<()> {
-    // Store handler and stop flag
-    {
-        let mut h = GRAB_HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = Some(Box::new(handler));
-    }
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = Some(running.clone());
-    }
+    run(running, TapHandler::Grab(&handler))
+}
+
+/// Install the event tap and run the run loop until stopped.
+fn run(running: &Arc<AtomicBool>, handler: TapHandler) -> Result<()> {
+    let _claim = ACTIVE.claim(running)?;
+    let _statics = HookStatics;
     {
         let mut f = LAST_FLAGS
             .lock()
             .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *f = CGEventFlags(0);
+        *f = 0;
     }
 
-    // Enable grab mode
-    GRAB_MODE.store(true, Ordering::SeqCst);
+    // Grabs use Default (not ListenOnly) to allow consuming events
+    let grab = matches!(handler, TapHandler::Grab(_));
+    let options = if grab {
+        CGEventTapOptions::Default
+    } else {
+        CGEventTapOptions::ListenOnly
+    };
+    GRAB_MODE.store(grab, Ordering::SeqCst);
+    let state = TapState { running, handler };
 
+    // SAFETY: `state` outlives the tap, which is dropped at the end of this
+    // block, and the run loop runs on this thread, which installed the tap
     unsafe {
         let _pool = NSAutoreleasePool::new();
-
-        let callback: CGEventTapCallBack = Some(event_callback);
-        // Use Default (not ListenOnly) to allow consuming events
-        let tap = CGEvent::tap_create(
-            CGEventTapLocation::HIDEventTap,
-            CGEventTapPlacement::HeadInsertEventTap,
-            CGEventTapOptions::Default, // Allows modification/consumption
-            kCGEventMaskForAllEvents.into(),
-            callback,
-            null_mut(),
-        )
-        .ok_or_else(|| {
-            Error::PermissionDenied(
-                "Failed to create event tap. Make sure Accessibility permissions are granted."
-                    .into(),
-            )
-        })?;
-
-        // Store the tap reference for timeout recovery
-        {
-            let mut tap_guard = EVENT_TAP
-                .lock()
-                .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-            *tap_guard = Some(TapPointer(&*tap as *const CFMachPort));
-        }
-
-        let source = CFMachPort::new_run_loop_source(None, Some(&tap), 0)
-            .ok_or_else(|| Error::HookStartFailed("Failed to create run loop source".into()))?;
-
-        let current_loop = CFRunLoop::current()
-            .ok_or_else(|| Error::HookStartFailed("Failed to get current run loop".into()))?;
-
-        // Store run loop reference so stop_hook() can stop the correct run loop
-        {
-            let mut rl = HOOK_RUN_LOOP
-                .lock()
-                .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-            *rl = Some(RunLoopRef(&*current_loop as *const CFRunLoop));
-        }
-
-        current_loop.add_source(Some(&source), kCFRunLoopCommonModes);
-
-        // Enable the tap
-        CGEvent::tap_enable(&tap, true);
+        let _tap = InstalledTap::install(options, &state)?;
 
         // Send hook enabled event
-        {
-            if let Ok(guard) = GRAB_HANDLER.lock()
-                && let Some(ref handler) = *guard
-            {
-                let _ = handler.handle_event(&Event::hook_enabled());
-            }
-        }
+        state.deliver(&Event::hook_enabled());
 
         // Run the loop
-        CFRunLoop::run();
+        run_until_stopped(running);
 
         // Send hook disabled event
-        {
-            if let Ok(guard) = GRAB_HANDLER.lock()
-                && let Some(ref handler) = *guard
-            {
-                let _ = handler.handle_event(&Event::hook_disabled());
-            }
-        }
-    }
-
-    // Clean up
-    GRAB_MODE.store(false, Ordering::SeqCst);
-    {
-        let mut rl = HOOK_RUN_LOOP
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *rl = None;
-    }
-    {
-        let mut h = GRAB_HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = None;
-    }
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = None;
-    }
-    {
-        let mut t = EVENT_TAP
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *t = None;
+        state.deliver(&Event::hook_disabled());
     }
 
     Ok(())
@@ -618,16 +442,19 @@ pub fn run_grab_hook<H: GrabHandler + 'static>(
 /// main thread's run loop — not the background hook thread's. In Electron,
 /// this would attempt to stop Chromium's main run loop.
 pub fn stop_hook() -> Result<()> {
-    if let Ok(guard) = HOOK_RUN_LOOP.lock() {
-        if let Some(ref rl) = *guard {
-            if !rl.0.is_null() {
-                // Safety: CFRunLoopStop is thread-safe per Apple docs.
-                // The pointer is valid because the hook thread's run loop is
-                // still alive (Hook::stop sets the flag, calls us, then joins).
-                unsafe {
-                    (&*rl.0).stop();
-                }
-            }
+    // A stop for a hook that never got the backend leaves the running one be
+    if !ACTIVE.stopping() {
+        return Ok(());
+    }
+    if let Ok(guard) = HOOK_RUN_LOOP.lock()
+        && let Some(ref rl) = *guard
+        && !rl.0.is_null()
+    {
+        // SAFETY: CFRunLoopStop is thread-safe per Apple docs. The pointer
+        // is valid while the lock is held: `InstalledTap` clears it under
+        // the lock before releasing the run loop.
+        unsafe {
+            (&*rl.0).stop();
         }
     }
     Ok(())
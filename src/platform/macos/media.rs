@@ -0,0 +1,179 @@
+//! Media keys, which macOS delivers as system-defined events.
+//!
+//! Volume and playback keys (and the F-row when the Fn setting makes it send
+//! media functions) don't produce `KeyDown`/`KeyUp`. They arrive as
+//! `NSSystemDefined` events of subtype 8, with the key in `data1`:
+//!
+//! ```text
+//! data1 = key type << 16 | key state << 8 | repeat flag
+//! ```
+//!
+//! where the key type is an `NX_KEYTYPE_*` constant and the state is `0xA`
+//! for down, `0xB` for up. The key type is reported as the event's
+//! `raw_code`.
+
+use crate::error::{Error, Result};
+use crate::event::Event;
+use crate::keycode::Key;
+use core::ptr::NonNull;
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send};
+use objc2_core_foundation::CGPoint;
+use objc2_core_graphics::{CGEvent, CGEventType};
+
+/// `kCGEventSystemDefined` / `NSEventTypeSystemDefined`.
+pub(super) const SYSTEM_DEFINED: CGEventType = CGEventType(crate::convert::macos::SYSTEM_DEFINED);
+
+/// `NX_SUBTYPE_AUX_CONTROL_BUTTONS`: media and other auxiliary keys.
+const AUX_CONTROL_BUTTONS: i16 = 8;
+
+const KEY_STATE_DOWN: isize = 0xA;
+const KEY_STATE_UP: isize = 0xB;
+
+// `NX_KEYTYPE_*` from IOKit/hidsystem/ev_keymap.h
+const NX_KEYTYPE_SOUND_UP: u32 = 0;
+const NX_KEYTYPE_SOUND_DOWN: u32 = 1;
+const NX_KEYTYPE_MUTE: u32 = 7;
+const NX_KEYTYPE_PLAY: u32 = 16;
+const NX_KEYTYPE_NEXT: u32 = 17;
+const NX_KEYTYPE_PREVIOUS: u32 = 18;
+const NX_KEYTYPE_FAST: u32 = 19;
+const NX_KEYTYPE_REWIND: u32 = 20;
+
+/// The key for an `NX_KEYTYPE_*` value, if it has one.
+fn nx_key_to_key(key_type: u32) -> Option<Key> {
+    match key_type {
+        NX_KEYTYPE_SOUND_UP => Some(Key::VolumeUp),
+        NX_KEYTYPE_SOUND_DOWN => Some(Key::VolumeDown),
+        NX_KEYTYPE_MUTE => Some(Key::VolumeMute),
+        NX_KEYTYPE_PLAY => Some(Key::MediaPlayPause),
+        NX_KEYTYPE_NEXT | NX_KEYTYPE_FAST => Some(Key::MediaNext),
+        NX_KEYTYPE_PREVIOUS | NX_KEYTYPE_REWIND => Some(Key::MediaPrevious),
+        _ => None,
+    }
+}
+
+/// The `NX_KEYTYPE_*` value to post for a media key.
+pub(super) fn key_to_nx_key(key: Key) -> Option<u32> {
+    match key {
+        Key::VolumeUp => Some(NX_KEYTYPE_SOUND_UP),
+        Key::VolumeDown => Some(NX_KEYTYPE_SOUND_DOWN),
+        Key::VolumeMute => Some(NX_KEYTYPE_MUTE),
+        Key::MediaPlayPause => Some(NX_KEYTYPE_PLAY),
+        Key::MediaNext => Some(NX_KEYTYPE_NEXT),
+        Key::MediaPrevious => Some(NX_KEYTYPE_PREVIOUS),
+        _ => None,
+    }
+}
+
+/// Split `data1` into the key type and whether the key went down.
+fn parse_data1(data1: isize) -> Option<(u32, bool)> {
+    let key_type = ((data1 >> 16) & 0xFFFF) as u32;
+    match (data1 >> 8) & 0xFF {
+        KEY_STATE_DOWN => Some((key_type, true)),
+        KEY_STATE_UP => Some((key_type, false)),
+        _ => None,
+    }
+}
+
+fn make_data1(key_type: u32, pressed: bool) -> isize {
+    let state = if pressed {
+        KEY_STATE_DOWN
+    } else {
+        KEY_STATE_UP
+    };
+    ((key_type as isize) << 16) | (state << 8)
+}
+
+/// Convert a system-defined event into a key event, if it is a media key.
+///
+/// Other system-defined events (brightness, eject, ...) return `None` and
+/// pass through untouched.
+pub(super) unsafe fn convert_system_defined(cg_event: NonNull<CGEvent>) -> Option<Event> {
+    let (subtype, data1) = objc2::rc::autoreleasepool(|_| unsafe {
+        let ns_event: *mut AnyObject =
+            msg_send![class!(NSEvent), eventWithCGEvent: cg_event.as_ptr()];
+        if ns_event.is_null() {
+            return None;
+        }
+        let subtype: i16 = msg_send![ns_event, subtype];
+        let data1: isize = msg_send![ns_event, data1];
+        Some((subtype, data1))
+    })?;
+    if subtype != AUX_CONTROL_BUTTONS {
+        return None;
+    }
+
+    let (key_type, pressed) = parse_data1(data1)?;
+    let key = nx_key_to_key(key_type)?;
+    Some(if pressed {
+        Event::key_pressed(key, key_type)
+    } else {
+        Event::key_released(key, key_type)
+    })
+}
+
+/// Post a media key as a system-defined event, through `post`.
+pub(super) fn post_media_key(key_type: u32, pressed: bool, post: fn(&CGEvent)) -> Result<()> {
+    let modifier_flags: usize = if pressed { 0xA00 } else { 0xB00 };
+    objc2::rc::autoreleasepool(|_| unsafe {
+        let ns_event: *mut AnyObject = msg_send![
+            class!(NSEvent),
+            otherEventWithType: 14usize,
+            location: CGPoint { x: 0.0, y: 0.0 },
+            modifierFlags: modifier_flags,
+            timestamp: 0.0f64,
+            windowNumber: 0isize,
+            context: std::ptr::null_mut::<AnyObject>(),
+            subtype: AUX_CONTROL_BUTTONS,
+            data1: make_data1(key_type, pressed),
+            data2: -1isize
+        ];
+        if ns_event.is_null() {
+            return Err(Error::SimulateFailed(
+                "Failed to create media key event".into(),
+            ));
+        }
+        // Owned by the NSEvent, which outlives this pool
+        let cg_event: *mut CGEvent = msg_send![ns_event, CGEvent];
+        let cg_event = cg_event
+            .as_ref()
+            .ok_or_else(|| Error::SimulateFailed("Failed to convert media key event".into()))?;
+        post(cg_event);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data1_roundtrip() {
+        for key in [
+            Key::VolumeUp,
+            Key::VolumeDown,
+            Key::VolumeMute,
+            Key::MediaPlayPause,
+            Key::MediaNext,
+            Key::MediaPrevious,
+        ] {
+            let key_type = key_to_nx_key(key).unwrap();
+            for pressed in [true, false] {
+                let (parsed, down) = parse_data1(make_data1(key_type, pressed)).unwrap();
+                assert_eq!(nx_key_to_key(parsed), Some(key));
+                assert_eq!(down, pressed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_repeat_and_unknown_state() {
+        // Volume up held down, with the repeat flag set
+        assert_eq!(parse_data1(0x000A01), Some((NX_KEYTYPE_SOUND_UP, true)));
+        assert_eq!(parse_data1(0x10_0B00), Some((NX_KEYTYPE_PLAY, false)));
+        assert_eq!(parse_data1(0x10_0000), None);
+        // Brightness up has no key
+        assert_eq!(nx_key_to_key(2), None);
+    }
+}
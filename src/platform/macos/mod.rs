@@ -1,13 +1,77 @@
 //! macOS platform implementation using CGEventTap.
 
+mod cursor;
 mod display;
+#[cfg(feature = "failsafe")]
+mod failsafe;
 mod keycodes;
+mod layout;
 mod listen;
+mod media;
+mod permissions;
 mod simulate;
+#[cfg(feature = "macos-targeted")]
+mod target;
 
+pub(crate) use cursor::{confine_cursor, hide_cursor, release_cursor, show_cursor, warp_cursor};
 pub use display::{display_at_point, displays, primary_display, system_settings};
+#[cfg(feature = "failsafe")]
+pub(crate) use failsafe::KeyProbe;
+pub(crate) use layout::{KEYCODES, ime_composing, key_for_code, logical_key, physical_key};
+#[cfg(feature = "failsafe")]
+pub(crate) use listen::emergency_release;
+pub(crate) use listen::set_event_mask;
 pub use listen::{run_grab_hook, run_hook, stop_hook};
+pub(crate) use permissions::{
+    accessibility_status, open_accessibility_settings, request_accessibility,
+};
+pub(crate) use simulate::{held_buttons, inject_synthetic, lock_states, reinject};
 pub use simulate::{
     key_press, key_release, key_tap, mouse_click, mouse_move, mouse_position, mouse_press,
     mouse_release, simulate,
 };
+#[cfg(all(feature = "macos-targeted", feature = "recorder"))]
+pub(crate) use target::resolve_pid;
+#[cfg(feature = "macos-targeted")]
+pub use target::{AppInfo, AppTarget, process_name, running_apps, send_to_app};
+
+/// Grab hooks stay installed while grabbing is disabled; the handler wrapper
+/// passes every event through.
+pub(crate) fn set_grab_enabled(_enabled: bool) {}
+
+/// Scroll-only grabs are applied by the handler wrapper; the event tap sees
+/// every event either way.
+pub(crate) fn set_scroll_only(_scroll_only: bool) {}
+
+/// Touch input (trackpads) is reported as mouse events.
+pub(crate) fn set_touch_as_mouse(_enabled: bool) {}
+
+/// Pointer positions arrive already mapped to the screen.
+pub(crate) fn set_input_transforms(_transforms: Vec<crate::calibration::DeviceTransform>) {}
+
+/// Whether grab mode can keep events from reaching other applications.
+pub(crate) const GRAB_SUPPRESSES: bool = true;
+
+/// Whether events carry the OS's timestamps rather than the time monio saw
+/// them, which makes their delivery latency measurable.
+pub(crate) const NATIVE_TIMESTAMPS: bool = false;
+
+/// Name of the input backend, recorded in recording metadata.
+#[cfg_attr(not(feature = "recorder"), allow(dead_code))]
+pub(crate) const BACKEND: &str = "cgeventtap";
+
+/// Whether pointer positions are absolute screen coordinates.
+pub(crate) const ABSOLUTE_POSITION: bool = true;
+
+/// Whether pointer positions are in points rather than physical pixels.
+pub(crate) const POSITIONS_IN_POINTS: bool = true;
+
+/// Whether each input device is read on its own.
+pub(crate) const PER_DEVICE: bool = false;
+
+/// Whether `Key::Char` keys the layout has no key for can be simulated.
+pub(crate) const SIMULATES_UNICODE: bool = true;
+
+/// Where display information comes from.
+pub(crate) const DISPLAY_SOURCE: Option<crate::display::DisplaySource> =
+    Some(crate::display::DisplaySource::WindowSystem);
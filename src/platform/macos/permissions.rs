@@ -0,0 +1,92 @@
+//! The Accessibility permission, see [`crate::permissions`].
+
+use crate::error::{Error, Result};
+use crate::permissions::AccessibilityStatus;
+use core::ptr::NonNull;
+use objc2::rc::autoreleasepool;
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send};
+use objc2_core_graphics::{
+    CGEvent, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement, CGEventTapProxy,
+    CGEventType, kCGEventMaskForAllEvents,
+};
+use objc2_foundation::NSString;
+use std::ffi::c_void;
+use std::ptr::null_mut;
+
+/// The Privacy & Security → Accessibility pane.
+const ACCESSIBILITY_SETTINGS_URL: &str =
+    "x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility";
+
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+    fn AXIsProcessTrustedWithOptions(options: *const c_void) -> bool;
+}
+
+/// Passes everything on; the probe tap is never enabled anyway.
+unsafe extern "C-unwind" fn probe_callback(
+    _proxy: CGEventTapProxy,
+    _event_type: CGEventType,
+    cg_event: NonNull<CGEvent>,
+    _user_info: *mut c_void,
+) -> *mut CGEvent {
+    cg_event.as_ptr()
+}
+
+/// Whether a listen-only event tap can be created right now.
+fn can_create_tap() -> bool {
+    let tap = unsafe {
+        CGEvent::tap_create(
+            CGEventTapLocation::HIDEventTap,
+            CGEventTapPlacement::TailAppendEventTap,
+            CGEventTapOptions::ListenOnly,
+            kCGEventMaskForAllEvents.into(),
+            Some(probe_callback),
+            null_mut(),
+        )
+    };
+    match tap {
+        Some(tap) => {
+            tap.invalidate();
+            true
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn accessibility_status() -> AccessibilityStatus {
+    if !unsafe { AXIsProcessTrusted() } {
+        AccessibilityStatus::Denied
+    } else if can_create_tap() {
+        AccessibilityStatus::Granted
+    } else {
+        AccessibilityStatus::StaleGrant
+    }
+}
+
+pub(crate) fn request_accessibility() -> bool {
+    autoreleasepool(|_| unsafe {
+        // `kAXTrustedCheckOptionPrompt`
+        let key = NSString::from_str("AXTrustedCheckOptionPrompt");
+        let yes: *mut AnyObject = msg_send![class!(NSNumber), numberWithBool: true];
+        let options: *mut AnyObject =
+            msg_send![class!(NSDictionary), dictionaryWithObject: yes, forKey: &*key];
+        AXIsProcessTrustedWithOptions(options as *const c_void)
+    })
+}
+
+pub(crate) fn open_accessibility_settings() -> Result<()> {
+    let status = std::process::Command::new("/usr/bin/open")
+        .arg(ACCESSIBILITY_SETTINGS_URL)
+        .status()
+        .map_err(|e| Error::Platform(format!("Failed to open System Settings: {}", e)))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Platform(format!(
+            "Failed to open System Settings: open exited with {}",
+            status
+        )))
+    }
+}
@@ -3,26 +3,236 @@
 #![allow(unused_unsafe)]
 
 use crate::error::{Error, Result};
-use crate::event::{Button, Event, EventType};
+use crate::event::{Backend, Button, Event, EventType};
 use crate::keycode::Key;
+use crate::snapshot::LockStates;
+use crate::state::{MASK_ALT, MASK_CAPS_LOCK, MASK_CTRL, MASK_META, MASK_SHIFT};
 use objc2_core_foundation::CGPoint;
 use objc2_core_graphics::{
     CGEvent, CGEventField, CGEventFlags, CGEventSource, CGEventSourceStateID, CGEventTapLocation,
     CGEventType, CGMouseButton, CGScrollEventUnit,
 };
+use std::cell::Cell;
 use std::sync::Mutex;
 
-use super::keycodes::key_to_keycode;
+use super::layout::resolve_keycode;
+use super::media::{key_to_nx_key, post_media_key};
 
 /// Track the current modifier flags for simulation
 static SIM_FLAGS: Mutex<CGEventFlags> = Mutex::new(CGEventFlags(0));
 
+/// `EventSourceUserData` marker for events posted by [`simulate`] and friends.
+pub(crate) const SIMULATED_TAG: i64 = 0x6D6F_6E73; // "mons"
+
+/// `EventSourceUserData` marker for events injected by [`inject_synthetic`].
+pub(crate) const SYNTHETIC_TAG: i64 = 0x6D6F_6E69; // "moni"
+
+thread_local! {
+    /// `EventSourceUserData` stamped on events posted from this thread.
+    static USER_DATA: Cell<i64> = const { Cell::new(SIMULATED_TAG) };
+
+    /// Process that events posted from this thread go to, instead of the
+    /// HID tap location.
+    static TARGET_PID: Cell<Option<i32>> = const { Cell::new(None) };
+}
+
+/// Post an event at the HID tap location, or to the target process.
+fn post(event: &CGEvent) {
+    CGEvent::set_integer_value_field(
+        Some(event),
+        CGEventField::EventSourceUserData,
+        USER_DATA.get(),
+    );
+    match TARGET_PID.get() {
+        Some(pid) => unsafe { CGEventPostToPid(pid, event) },
+        None => CGEvent::post(CGEventTapLocation::HIDEventTap, Some(event)),
+    }
+}
+
+/// Inject an event tagged as synthetic, so the grab tap passes it through
+/// without consulting the handler.
+pub(crate) fn inject_synthetic(event: &Event) -> Result<()> {
+    USER_DATA.set(SYNTHETIC_TAG);
+    let result = simulate(event);
+    USER_DATA.set(SIMULATED_TAG);
+    result
+}
+
+/// Re-post a captured event as the tap saw it, tagged as synthetic.
+///
+/// Keys are posted with their original keycode and the modifier flags from
+/// the event's mask; button events at their original location. Media keys
+/// and other events are simulated as usual.
+pub(crate) fn reinject(event: &Event) -> Result<()> {
+    USER_DATA.set(SYNTHETIC_TAG);
+    let result = repost(event);
+    USER_DATA.set(SIMULATED_TAG);
+    result
+}
+
+/// Post a captured event to process `pid` as it was captured, like
+/// [`reinject`]. Typed characters are posted as key events carrying the text.
+#[cfg(feature = "macos-targeted")]
+pub(super) fn post_to_pid(pid: i32, event: &Event) -> Result<()> {
+    TARGET_PID.set(Some(pid));
+    let result = match (event.event_type, &event.keyboard) {
+        (EventType::KeyTyped, Some(kb)) => match kb.char {
+            Some(ch) => post_text(ch, mask_to_flags(event.mask)),
+            None => Ok(()),
+        },
+        _ => repost(event),
+    };
+    TARGET_PID.set(None);
+    result
+}
+
+/// Post a press and release typing `ch`, whatever key would produce it.
+#[cfg(feature = "macos-targeted")]
+fn post_text(ch: char, flags: CGEventFlags) -> Result<()> {
+    let mut units = [0u16; 2];
+    let units = ch.encode_utf16(&mut units);
+    for pressed in [true, false] {
+        unsafe {
+            let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+                .ok_or_else(|| Error::SimulateFailed("Failed to create event source".into()))?;
+            let event = CGEvent::new_keyboard_event(Some(&source), 0, pressed)
+                .ok_or_else(|| Error::SimulateFailed("Failed to create keyboard event".into()))?;
+            CGEventKeyboardSetUnicodeString(&event, units.len(), units.as_ptr());
+            CGEvent::set_flags(Some(&event), flags);
+            post(&event);
+        }
+    }
+    Ok(())
+}
+
+fn repost(event: &Event) -> Result<()> {
+    let pressed = matches!(
+        event.event_type,
+        EventType::KeyPressed | EventType::MousePressed
+    );
+    match (event.event_type, &event.keyboard, &event.mouse) {
+        (EventType::KeyPressed | EventType::KeyReleased, Some(kb), _)
+            if key_to_nx_key(kb.key).is_none() =>
+        {
+            post_keycode(
+                kb.raw_code as u16,
+                kb.key,
+                pressed,
+                mask_to_flags(event.mask),
+            )
+        }
+        (EventType::MousePressed | EventType::MouseReleased, _, Some(mouse)) => {
+            match mouse.button {
+                Some(button) => post_button(
+                    button,
+                    CGPoint {
+                        x: mouse.x,
+                        y: mouse.y,
+                    },
+                    pressed,
+                    Some(mask_to_flags(event.mask)),
+                ),
+                None => Ok(()),
+            }
+        }
+        _ => simulate(event),
+    }
+}
+
+/// Event flags for the modifier bits of a `MASK_*` mask.
+fn mask_to_flags(mask: u32) -> CGEventFlags {
+    let mut flags = CGEventFlags(0);
+    for (bit, flag) in [
+        (MASK_SHIFT, CGEventFlags::MaskShift),
+        (MASK_CTRL, CGEventFlags::MaskControl),
+        (MASK_ALT, CGEventFlags::MaskAlternate),
+        (MASK_META, CGEventFlags::MaskCommand),
+        (MASK_CAPS_LOCK, CGEventFlags::MaskAlphaShift),
+    ] {
+        if mask & bit != 0 {
+            flags.insert(flag);
+        }
+    }
+    flags
+}
+
+/// Post a key event for a raw keycode with exactly `flags`.
+///
+/// Modifier keys are posted as `FlagsChanged`, like the keyboard's own.
+fn post_keycode(keycode: u16, key: Key, pressed: bool, flags: CGEventFlags) -> Result<()> {
+    unsafe {
+        let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+            .ok_or_else(|| Error::SimulateFailed("Failed to create event source".into()))?;
+        let event = if is_modifier_key(key) {
+            let event = CGEvent::new(Some(&source))
+                .ok_or_else(|| Error::SimulateFailed("Failed to create event".into()))?;
+            CGEvent::set_type(Some(&event), CGEventType::FlagsChanged);
+            CGEvent::set_integer_value_field(
+                Some(&event),
+                CGEventField::KeyboardEventKeycode,
+                keycode as i64,
+            );
+            event
+        } else {
+            CGEvent::new_keyboard_event(Some(&source), keycode, pressed)
+                .ok_or_else(|| Error::SimulateFailed("Failed to create keyboard event".into()))?
+        };
+        CGEvent::set_flags(Some(&event), flags);
+        post(&event);
+    }
+    Ok(())
+}
+
 /// Get current mouse position as (x, y) coordinates.
 pub fn mouse_position() -> Result<(f64, f64)> {
     let point = get_current_mouse_location()?;
     Ok((point.x, point.y))
 }
 
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventSourceFlagsState(state_id: i32) -> u64;
+    fn CGEventSourceButtonState(state_id: i32, button: u32) -> bool;
+    fn CGEventPostToPid(pid: i32, event: &CGEvent);
+    #[cfg(feature = "macos-targeted")]
+    fn CGEventKeyboardSetUnicodeString(event: &CGEvent, length: usize, string: *const u16);
+}
+
+/// Lock key states. Macs only have Caps Lock.
+pub(crate) fn lock_states() -> LockStates {
+    /// `kCGEventSourceStateCombinedSessionState`
+    const COMBINED_SESSION_STATE: i32 = 0;
+    /// `kCGEventFlagMaskAlphaShift`
+    const ALPHA_SHIFT: u64 = 0x0001_0000;
+
+    let flags = unsafe { CGEventSourceFlagsState(COMBINED_SESSION_STATE) };
+    LockStates {
+        caps_lock: flags & ALPHA_SHIFT != 0,
+        ..LockStates::default()
+    }
+}
+
+/// Mouse buttons held, as a `MASK_BUTTON*` mask.
+pub(crate) fn held_buttons() -> Option<u32> {
+    /// `kCGEventSourceStateCombinedSessionState`
+    const COMBINED_SESSION_STATE: i32 = 0;
+
+    // Quartz numbers buttons from 0: left, right, center, then others
+    let mut held = 0;
+    for (button, mask) in [
+        (0, crate::state::MASK_BUTTON1),
+        (1, crate::state::MASK_BUTTON2),
+        (2, crate::state::MASK_BUTTON3),
+        (3, crate::state::MASK_BUTTON4),
+        (4, crate::state::MASK_BUTTON5),
+    ] {
+        if unsafe { CGEventSourceButtonState(COMBINED_SESSION_STATE, button) } {
+            held |= mask;
+        }
+    }
+    Some(held)
+}
+
 /// Get current mouse location
 fn get_current_mouse_location() -> Result<CGPoint> {
     unsafe {
@@ -83,7 +293,9 @@ pub fn simulate(event: &Event) -> Result<()> {
         }
         EventType::MouseWheel => {
             if let Some(wheel) = &event.wheel {
-                mouse_scroll(wheel.delta as i32, 0)?;
+                // Positive horizontal deltas scroll left on macOS
+                let (vertical, horizontal) = wheel.steps();
+                mouse_scroll(vertical, -horizontal)?;
             }
         }
         _ => {}
@@ -92,8 +304,13 @@ pub fn simulate(event: &Event) -> Result<()> {
 }
 
 /// Press a key.
+///
+/// Media keys are posted as system-defined events, like the keyboard's own.
 pub fn key_press(key: Key) -> Result<()> {
-    let keycode = key_to_keycode(key)
+    if let Some(key_type) = key_to_nx_key(key) {
+        return post_media_key(key_type, true, post);
+    }
+    let keycode = resolve_keycode(key)
         .ok_or_else(|| Error::SimulateFailed(format!("Unsupported key: {:?}", key)))?;
 
     unsafe {
@@ -131,7 +348,7 @@ pub fn key_press(key: Key) -> Result<()> {
                 _ => {}
             }
             CGEvent::set_flags(Some(&event), *flags);
-            CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&event));
+            post(&event);
         } else {
             // For regular keys, use keyboard event
             let event = CGEvent::new_keyboard_event(Some(&source), keycode, true)
@@ -140,7 +357,7 @@ pub fn key_press(key: Key) -> Result<()> {
                 .lock()
                 .map_err(|_| Error::SimulateFailed("mutex poisoned".into()))?;
             CGEvent::set_flags(Some(&event), *flags);
-            CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&event));
+            post(&event);
         }
     }
     Ok(())
@@ -148,7 +365,10 @@ pub fn key_press(key: Key) -> Result<()> {
 
 /// Release a key.
 pub fn key_release(key: Key) -> Result<()> {
-    let keycode = key_to_keycode(key)
+    if let Some(key_type) = key_to_nx_key(key) {
+        return post_media_key(key_type, false, post);
+    }
+    let keycode = resolve_keycode(key)
         .ok_or_else(|| Error::SimulateFailed(format!("Unsupported key: {:?}", key)))?;
 
     unsafe {
@@ -186,7 +406,7 @@ pub fn key_release(key: Key) -> Result<()> {
                 _ => {}
             }
             CGEvent::set_flags(Some(&event), *flags);
-            CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&event));
+            post(&event);
         } else {
             // For regular keys, use keyboard event
             let event = CGEvent::new_keyboard_event(Some(&source), keycode, false)
@@ -195,7 +415,7 @@ pub fn key_release(key: Key) -> Result<()> {
                 .lock()
                 .map_err(|_| Error::SimulateFailed("mutex poisoned".into()))?;
             CGEvent::set_flags(Some(&event), *flags);
-            CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&event));
+            post(&event);
         }
     }
     Ok(())
@@ -220,44 +440,35 @@ fn button_to_cg_button(button: Button) -> CGMouseButton {
 
 /// Press a mouse button.
 pub fn mouse_press(button: Button) -> Result<()> {
-    let point = get_current_mouse_location()?;
-    let cg_button = button_to_cg_button(button);
-
-    let event_type = match button {
-        Button::Left => CGEventType::LeftMouseDown,
-        Button::Right => CGEventType::RightMouseDown,
-        _ => CGEventType::OtherMouseDown,
-    };
-
-    unsafe {
-        let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
-            .ok_or_else(|| Error::SimulateFailed("Failed to create event source".into()))?;
-        let event = CGEvent::new_mouse_event(Some(&source), event_type, point, cg_button)
-            .ok_or_else(|| Error::SimulateFailed("Failed to create mouse event".into()))?;
-
-        // Set button number for other mouse buttons
-        if let Button::Button4 | Button::Button5 | Button::Middle | Button::Unknown(_) = button {
-            CGEvent::set_integer_value_field(
-                Some(&event),
-                CGEventField::MouseEventButtonNumber,
-                (button.number() - 1) as i64,
-            );
-        }
-
-        CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&event));
-    }
-    Ok(())
+    post_button(button, get_current_mouse_location()?, true, None)
 }
 
 /// Release a mouse button.
 pub fn mouse_release(button: Button) -> Result<()> {
-    let point = get_current_mouse_location()?;
+    post_button(button, get_current_mouse_location()?, false, None)
+}
+
+/// Post a button press or release at `point`, with `flags` if given.
+fn post_button(
+    button: Button,
+    point: CGPoint,
+    pressed: bool,
+    flags: Option<CGEventFlags>,
+) -> Result<()> {
+    // Unknown(1..=5) are the named buttons
+    let button = Button::from_number(button.number());
+    let number = button
+        .platform_code(Backend::MacOS)
+        .ok_or_else(|| Error::SimulateFailed(format!("macOS has no button for {:?}", button)))?;
     let cg_button = button_to_cg_button(button);
 
-    let event_type = match button {
-        Button::Left => CGEventType::LeftMouseUp,
-        Button::Right => CGEventType::RightMouseUp,
-        _ => CGEventType::OtherMouseUp,
+    let event_type = match (button, pressed) {
+        (Button::Left, true) => CGEventType::LeftMouseDown,
+        (Button::Left, false) => CGEventType::LeftMouseUp,
+        (Button::Right, true) => CGEventType::RightMouseDown,
+        (Button::Right, false) => CGEventType::RightMouseUp,
+        (_, true) => CGEventType::OtherMouseDown,
+        (_, false) => CGEventType::OtherMouseUp,
     };
 
     unsafe {
@@ -271,11 +482,14 @@ pub fn mouse_release(button: Button) -> Result<()> {
             CGEvent::set_integer_value_field(
                 Some(&event),
                 CGEventField::MouseEventButtonNumber,
-                (button.number() - 1) as i64,
+                number as i64,
             );
         }
+        if let Some(flags) = flags {
+            CGEvent::set_flags(Some(&event), flags);
+        }
 
-        CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&event));
+        post(&event);
     }
     Ok(())
 }
@@ -302,7 +516,7 @@ pub fn mouse_move(x: f64, y: f64) -> Result<()> {
         )
         .ok_or_else(|| Error::SimulateFailed("Failed to create mouse event".into()))?;
 
-        CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&event));
+        post(&event);
     }
     Ok(())
 }
@@ -322,7 +536,7 @@ pub fn mouse_scroll(delta_y: i32, delta_x: i32) -> Result<()> {
         )
         .ok_or_else(|| Error::SimulateFailed("Failed to create scroll event".into()))?;
 
-        CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&event));
+        post(&event);
     }
     Ok(())
 }
@@ -0,0 +1,199 @@
+//! Sending input to a specific app with `CGEventPostToPid`.
+
+use crate::error::{Error, Result};
+use crate::event::Event;
+use objc2::rc::autoreleasepool;
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send};
+use objc2_foundation::NSString;
+
+use super::media::key_to_nx_key;
+use super::simulate::post_to_pid;
+
+/// A running app, see [`running_apps`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppInfo {
+    /// The app's process ID.
+    pub pid: i32,
+    /// The app's localized name.
+    pub name: String,
+    /// The bundle identifier, e.g. `com.apple.TextEdit`; `None` for
+    /// processes without a bundle.
+    pub bundle_id: Option<String>,
+    /// Whether the app is frontmost.
+    pub active: bool,
+}
+
+/// Where [`send_to_app`](crate::simulation::send_to_app) posts events.
+///
+/// Events posted to a process skip the window server's routing, so the app
+/// gets them whether or not it is frontmost. How far that goes depends on
+/// the event:
+///
+/// - Key presses and releases reach the app's key window, with the modifier
+///   flags from the event's mask. Other apps' shortcuts and the system's
+///   keyboard state are unaffected.
+/// - [`KeyTyped`](crate::EventType::KeyTyped) events are posted as key
+///   events carrying the character, so text arrives whatever the keyboard
+///   layout.
+/// - Mouse events keep their screen position. Apps deliver them to the window
+///   under that point, and many ignore clicks while inactive, so they are
+///   only dependable when the app's window is at the position.
+/// - Media keys are handled by the system, not apps, and are refused.
+///
+/// Posting to other processes needs the same Accessibility permission as
+/// simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppTarget {
+    /// A process ID.
+    Pid(i32),
+    /// The first running app with this bundle identifier.
+    BundleId(String),
+}
+
+impl From<&AppInfo> for AppTarget {
+    fn from(app: &AppInfo) -> Self {
+        AppTarget::Pid(app.pid)
+    }
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGPreflightPostEventAccess() -> bool;
+}
+
+unsafe extern "C" {
+    /// From libproc, part of libSystem.
+    fn proc_pidpath(pid: i32, buffer: *mut std::ffi::c_void, buffersize: u32) -> i32;
+}
+
+/// `PROC_PIDPATHINFO_MAXSIZE`
+const PROC_PIDPATH_MAX: usize = 4 * 1024;
+
+/// The executable name of a process, e.g. for an event's
+/// [`target_pid`](crate::Event::target_pid).
+///
+/// Read from the executable's path with `proc_pidpath`, so it works for
+/// processes without an app bundle too. `None` if the process is gone or
+/// belongs to another user.
+pub fn process_name(pid: i32) -> Option<String> {
+    if pid <= 0 {
+        return None;
+    }
+    let mut buffer = vec![0u8; PROC_PIDPATH_MAX];
+    let len = unsafe { proc_pidpath(pid, buffer.as_mut_ptr().cast(), buffer.len() as u32) };
+    let len = usize::try_from(len).ok().filter(|&len| len > 0)?;
+    let path = std::path::Path::new(std::str::from_utf8(&buffer[..len]).ok()?);
+    Some(path.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Apps running in the current session, including background-only ones.
+pub fn running_apps() -> Vec<AppInfo> {
+    autoreleasepool(|_| unsafe {
+        let workspace: *mut AnyObject = msg_send![class!(NSWorkspace), sharedWorkspace];
+        if workspace.is_null() {
+            return Vec::new();
+        }
+        let apps: *mut AnyObject = msg_send![workspace, runningApplications];
+        if apps.is_null() {
+            return Vec::new();
+        }
+        let count: usize = msg_send![apps, count];
+        (0..count)
+            .filter_map(|i| {
+                let app: *mut AnyObject = msg_send![apps, objectAtIndex: i];
+                app_info(app)
+            })
+            .collect()
+    })
+}
+
+/// Read an `NSRunningApplication`.
+unsafe fn app_info(app: *mut AnyObject) -> Option<AppInfo> {
+    if app.is_null() {
+        return None;
+    }
+    unsafe {
+        let pid: i32 = msg_send![app, processIdentifier];
+        if pid <= 0 {
+            return None;
+        }
+        let name: *mut NSString = msg_send![app, localizedName];
+        let bundle_id: *mut NSString = msg_send![app, bundleIdentifier];
+        let active: bool = msg_send![app, isActive];
+        Some(AppInfo {
+            pid,
+            name: name.as_ref().map(|s| s.to_string()).unwrap_or_default(),
+            bundle_id: bundle_id.as_ref().map(|s| s.to_string()),
+            active,
+        })
+    }
+}
+
+/// Post an event to an app.
+///
+/// Key and button events carry the modifier flags from the event's mask.
+/// Events other than keys, typed characters, buttons, moves and wheel are
+/// ignored.
+pub fn send_to_app(target: &AppTarget, event: &Event) -> Result<()> {
+    if !unsafe { CGPreflightPostEventAccess() } {
+        return Err(Error::PermissionDenied(
+            "Posting events to apps requires Accessibility permissions.".into(),
+        ));
+    }
+    let pid = resolve_pid(target)?;
+    if let Some(kb) = &event.keyboard
+        && event.is_keyboard()
+        && key_to_nx_key(kb.key).is_some()
+    {
+        return Err(Error::NotSupported(format!(
+            "Media keys can't be sent to an app: {:?}",
+            kb.key
+        )));
+    }
+    post_to_pid(pid, event)
+}
+
+/// The process a target refers to.
+pub(crate) fn resolve_pid(target: &AppTarget) -> Result<i32> {
+    match target {
+        AppTarget::Pid(pid) if *pid > 0 => Ok(*pid),
+        AppTarget::Pid(pid) => Err(Error::Platform(format!("Invalid process ID {}", pid))),
+        AppTarget::BundleId(bundle_id) => running_apps()
+            .into_iter()
+            .find(|app| app.bundle_id.as_deref() == Some(bundle_id.as_str()))
+            .map(|app| app.pid)
+            .ok_or_else(|| Error::Platform(format!("No running app with bundle ID {}", bundle_id))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_running_apps() {
+        // The Finder and Dock run in every session
+        let apps = running_apps();
+        assert!(!apps.is_empty());
+        assert!(apps.iter().all(|app| app.pid > 0));
+    }
+
+    #[test]
+    fn test_process_name() {
+        let name = process_name(std::process::id() as i32).unwrap();
+        assert!(name.starts_with("monio"), "{name}");
+        assert_eq!(process_name(0), None);
+        assert_eq!(process_name(-1), None);
+    }
+
+    #[test]
+    fn test_resolve_pid() {
+        assert_eq!(resolve_pid(&AppTarget::Pid(42)).unwrap(), 42);
+        assert!(resolve_pid(&AppTarget::Pid(0)).is_err());
+        assert!(matches!(
+            resolve_pid(&AppTarget::BundleId("com.example.monio.missing".into())),
+            Err(Error::Platform(_))
+        ));
+    }
+}
@@ -1,5 +1,17 @@
 //! Platform-specific implementations.
 
+// Without a backend, only tests use it
+#[cfg_attr(
+    all(target_os = "linux", not(any(feature = "x11", feature = "evdev"))),
+    allow(dead_code)
+)]
+mod active;
+#[cfg_attr(
+    all(target_os = "linux", not(any(feature = "x11", feature = "evdev"))),
+    allow(unused_imports)
+)]
+pub(crate) use active::ActiveHook;
+
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
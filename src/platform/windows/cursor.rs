@@ -0,0 +1,41 @@
+//! Cursor confinement (ClipCursor), visibility (ShowCursor) and warping
+//! (SetCursorPos).
+
+use crate::display::Rect;
+use crate::error::{Error, Result};
+use windows::Win32::Foundation::RECT;
+use windows::Win32::UI::WindowsAndMessaging::{ClipCursor, SetCursorPos, ShowCursor};
+
+pub(crate) fn confine_cursor(rect: Rect) -> Result<()> {
+    let clip = RECT {
+        left: rect.x.floor() as i32,
+        top: rect.y.floor() as i32,
+        right: (rect.x + rect.width).ceil() as i32,
+        bottom: (rect.y + rect.height).ceil() as i32,
+    };
+    unsafe { ClipCursor(Some(&clip as *const RECT)) }
+        .map_err(|e| Error::Platform(format!("ClipCursor failed: {}", e)))
+}
+
+pub(crate) fn release_cursor() -> Result<()> {
+    unsafe { ClipCursor(None) }.map_err(|e| Error::Platform(format!("ClipCursor failed: {}", e)))
+}
+
+/// `ShowCursor` keeps a per-thread display counter and only affects this
+/// process's windows.
+pub(crate) fn hide_cursor() -> Result<()> {
+    unsafe { ShowCursor(false) };
+    Ok(())
+}
+
+pub(crate) fn show_cursor() -> Result<()> {
+    unsafe { ShowCursor(true) };
+    Ok(())
+}
+
+/// `SetCursorPos` doesn't pass through low-level mouse hooks, so listeners
+/// don't see a move.
+pub(crate) fn warp_cursor(x: f64, y: f64) -> Result<()> {
+    unsafe { SetCursorPos(x.round() as i32, y.round() as i32) }
+        .map_err(|e| Error::Platform(format!("SetCursorPos failed: {}", e)))
+}
@@ -1,21 +1,22 @@
 //! Windows display and system property queries.
 
-use crate::display::{DisplayInfo, Rect, SystemSettings};
+use crate::display::{DisplayInfo, DisplaySource, Rect, SystemSettings};
 use crate::error::{Error, Result};
-use std::mem::size_of;
-use windows::Win32::Foundation::{BOOL, LPARAM, RECT};
+use std::mem::{size_of, size_of_val};
+use windows::Win32::Foundation::{BOOL, ERROR_SUCCESS, LPARAM, RECT};
 use windows::Win32::Graphics::Gdi::{
     ENUM_CURRENT_SETTINGS, EnumDisplayMonitors, EnumDisplaySettingsW, GetMonitorInfoW, HDC,
     HMONITOR, MONITORINFO, MONITORINFOEXW,
 };
+use windows::Win32::System::Registry::{HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ, RegGetValueW};
 use windows::Win32::UI::HiDpi::{GetDpiForMonitor, GetDpiForSystem, MDT_EFFECTIVE_DPI};
 use windows::Win32::UI::Input::KeyboardAndMouse::GetKeyboardLayoutNameW;
 use windows::Win32::UI::WindowsAndMessaging::{
-    MONITORINFOF_PRIMARY, SPI_GETKEYBOARDDELAY, SPI_GETKEYBOARDSPEED, SPI_GETMOUSE,
-    SPI_GETMOUSESPEED, SYSTEM_PARAMETERS_INFO_ACTION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
-    SystemParametersInfoW,
+    GetSystemMetrics, MONITORINFOF_PRIMARY, SM_CXDRAG, SM_CYDRAG, SPI_GETKEYBOARDDELAY,
+    SPI_GETKEYBOARDSPEED, SPI_GETMOUSE, SPI_GETMOUSESPEED, SYSTEM_PARAMETERS_INFO_ACTION,
+    SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, SystemParametersInfoW,
 };
-use windows::core::PCWSTR;
+use windows::core::{PCWSTR, w};
 
 pub fn displays() -> Result<Vec<DisplayInfo>> {
     let mut context = MonitorContext {
@@ -58,9 +59,13 @@ pub fn system_settings() -> Result<SystemSettings> {
     let keyboard_repeat_rate = system_param_u32(SPI_GETKEYBOARDSPEED);
     let keyboard_repeat_delay = system_param_u32(SPI_GETKEYBOARDDELAY);
     let mouse_sensitivity = system_param_u32(SPI_GETMOUSESPEED).map(|v| v as f64);
-    let (mouse_acceleration_threshold, mouse_acceleration) = get_mouse_accel();
-    let double_click_time = None; // GetDoubleClickTime not available in windows 0.59
+    let (mouse_acceleration_threshold, mouse_acceleration_threshold2, mouse_acceleration) =
+        get_mouse_accel();
+    let double_click_time = Some(unsafe { GetDoubleClickTime() });
+    let drag_threshold =
+        Some(unsafe { GetSystemMetrics(SM_CXDRAG).max(GetSystemMetrics(SM_CYDRAG)) } as f64);
     let keyboard_layout = get_keyboard_layout_name();
+    let keyboard_layout_name = keyboard_layout.as_deref().and_then(layout_display_name);
 
     Ok(SystemSettings {
         keyboard_repeat_rate,
@@ -68,8 +73,12 @@ pub fn system_settings() -> Result<SystemSettings> {
         mouse_sensitivity,
         mouse_acceleration,
         mouse_acceleration_threshold,
+        mouse_acceleration_threshold2,
         double_click_time,
+        drag_threshold,
         keyboard_layout,
+        keyboard_layout_name,
+        natural_scrolling: None,
     })
 }
 
@@ -112,10 +121,7 @@ fn display_from_info(hmonitor: HMONITOR, info: &MONITORINFOEXW, id: u32) -> Disp
     let height = (rect.bottom - rect.top) as f64;
     let is_primary = (info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY) != 0;
 
-    let scale_factor = match monitor_dpi_scale(hmonitor) {
-        Some(scale) => scale,
-        None => 1.0,
-    };
+    let scale_factor = monitor_dpi_scale(hmonitor).unwrap_or(1.0);
 
     let refresh_rate = monitor_refresh_rate(info);
 
@@ -130,6 +136,7 @@ fn display_from_info(hmonitor: HMONITOR, info: &MONITORINFOEXW, id: u32) -> Disp
         scale_factor,
         refresh_rate,
         is_primary,
+        source: DisplaySource::WindowSystem,
     }
 }
 
@@ -163,6 +170,12 @@ fn monitor_refresh_rate(info: &MONITORINFOEXW) -> Option<u32> {
     }
 }
 
+// Linked directly so it doesn't depend on where the windows crate exposes it
+#[link(name = "user32")]
+unsafe extern "system" {
+    fn GetDoubleClickTime() -> u32;
+}
+
 fn system_param_u32(action: SYSTEM_PARAMETERS_INFO_ACTION) -> Option<u32> {
     let mut value: u32 = 0;
     let ok = unsafe {
@@ -176,7 +189,9 @@ fn system_param_u32(action: SYSTEM_PARAMETERS_INFO_ACTION) -> Option<u32> {
     if ok.is_ok() { Some(value) } else { None }
 }
 
-fn get_mouse_accel() -> (Option<f64>, Option<f64>) {
+/// The `SPI_GETMOUSE` triplet: both acceleration thresholds and the
+/// acceleration level (0 = off, 1 or 2 = "enhance pointer precision").
+fn get_mouse_accel() -> (Option<f64>, Option<f64>, Option<f64>) {
     let mut mouse = [0i32; 3];
     let result = unsafe {
         SystemParametersInfoW(
@@ -187,11 +202,10 @@ fn get_mouse_accel() -> (Option<f64>, Option<f64>) {
         )
     };
     if result.is_ok() {
-        let threshold = ((mouse[0] + mouse[1]) as f64) / 2.0;
-        let speed = mouse[2] as f64;
-        (Some(threshold), Some(speed))
+        let [threshold1, threshold2, level] = mouse.map(|value| Some(value as f64));
+        (threshold1, threshold2, level)
     } else {
-        (None, None)
+        (None, None, None)
     }
 }
 
@@ -205,3 +219,55 @@ fn get_keyboard_layout_name() -> Option<String> {
         None
     }
 }
+
+/// Look up the `Layout Text` of a keyboard layout ID (KLID) in the registry,
+/// e.g. `"00000409"` -> `"US"`.
+fn layout_display_name(klid: &str) -> Option<String> {
+    let subkey: Vec<u16> = format!("SYSTEM\\CurrentControlSet\\Control\\Keyboard Layouts\\{klid}")
+        .encode_utf16()
+        .chain(Some(0))
+        .collect();
+    let mut buffer = [0u16; 256];
+    let mut size = size_of_val(&buffer) as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(subkey.as_ptr()),
+            w!("Layout Text"),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr().cast()),
+            Some(&mut size),
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return None;
+    }
+    let len = (size as usize / 2).min(buffer.len());
+    let name = String::from_utf16_lossy(&buffer[..len]);
+    let name = name.trim_end_matches('\0');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_settings_filled_in() {
+        let settings = system_settings().unwrap();
+        assert!(settings.double_click_time.is_some_and(|ms| ms > 0));
+        assert!(settings.mouse_acceleration.is_some());
+        assert!(settings.mouse_acceleration_threshold.is_some());
+        assert!(settings.mouse_acceleration_threshold2.is_some());
+        assert!(settings.keyboard_layout.is_some());
+        assert!(settings.keyboard_layout_name.is_some());
+    }
+
+    #[test]
+    fn test_layout_display_name() {
+        // US English ships with every Windows install
+        assert_eq!(layout_display_name("00000409").as_deref(), Some("US"));
+        assert_eq!(layout_display_name("not-a-klid"), None);
+    }
+}
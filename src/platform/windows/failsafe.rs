@@ -0,0 +1,119 @@
+//! Key state for the failsafe, from a low-level keyboard hook of its own.
+//!
+//! `GetAsyncKeyState` doesn't see keys a grab hook consumed, so the failsafe
+//! installs its own hook on its own thread and keeps the held keys in
+//! atomics. See [`crate::failsafe`] for how it interacts with a stuck hook.
+
+use super::keycodes::key_to_keycode;
+use crate::error::{Error, Result};
+use crate::keycode::Key;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, GetMessageW, HC_ACTION, KBDLLHOOKSTRUCT, PostThreadMessageW, SetWindowsHookExW,
+    UnhookWindowsHookEx, WH_KEYBOARD_LL, WM_KEYDOWN, WM_KEYUP, WM_QUIT, WM_SYSKEYDOWN, WM_SYSKEYUP,
+};
+
+/// Held virtual keys, one bit per VK code.
+static HELD: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+
+fn set_held(vk: u32, held: bool) {
+    let (word, bit) = ((vk as usize / 64) % HELD.len(), vk % 64);
+    if held {
+        HELD[word].fetch_or(1 << bit, Ordering::SeqCst);
+    } else {
+        HELD[word].fetch_and(!(1 << bit), Ordering::SeqCst);
+    }
+}
+
+fn is_held(vk: u16) -> bool {
+    let (word, bit) = ((vk as usize / 64) % HELD.len(), vk % 64);
+    HELD[word].load(Ordering::SeqCst) & (1 << bit) != 0
+}
+
+unsafe extern "system" fn keyboard_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        // SAFETY: lparam points to a KBDLLHOOKSTRUCT for HC_ACTION
+        let kb = unsafe { &*(lparam.0 as *const KBDLLHOOKSTRUCT) };
+        match wparam.0 as u32 {
+            WM_KEYDOWN | WM_SYSKEYDOWN => set_held(kb.vkCode, true),
+            WM_KEYUP | WM_SYSKEYUP => set_held(kb.vkCode, false),
+            _ => {}
+        }
+    }
+    unsafe { CallNextHookEx(None, code, wparam, lparam) }
+}
+
+/// Reads whether keys are held, through the failsafe's hook.
+pub(crate) struct KeyProbe {
+    codes: Vec<u16>,
+    thread_id: u32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl KeyProbe {
+    /// Install the failsafe's keyboard hook on a thread of its own.
+    pub(crate) fn open(keys: &[Key]) -> Result<Self> {
+        let codes = keys
+            .iter()
+            .map(|&key| key_to_keycode(key))
+            .collect::<Option<Vec<u16>>>()
+            .ok_or_else(|| {
+                Error::NotSupported(format!("No virtual key code for a key of {:?}", keys))
+            })?;
+        for word in &HELD {
+            word.store(0, Ordering::SeqCst);
+        }
+
+        let (ready, installed) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("monio-failsafe-hook".into())
+            .spawn(move || {
+                let hook =
+                    unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_proc), None, 0) };
+                let hook = match hook {
+                    Ok(hook) => hook,
+                    Err(e) => {
+                        let _ = ready.send(Err(e.to_string()));
+                        return;
+                    }
+                };
+                let _ = ready.send(Ok(unsafe { GetCurrentThreadId() }));
+                let mut msg = Default::default();
+                unsafe {
+                    while GetMessageW(&mut msg, None, 0, 0).as_bool() {}
+                    let _ = UnhookWindowsHookEx(hook);
+                }
+            })
+            .map_err(|e| Error::ThreadError(format!("failed to spawn failsafe hook: {}", e)))?;
+
+        let thread_id = installed
+            .recv()
+            .map_err(|_| Error::ThreadError("failsafe hook thread exited".into()))?
+            .map_err(|e| Error::HookStartFailed(format!("failsafe keyboard hook: {}", e)))?;
+        Ok(Self {
+            codes,
+            thread_id,
+            thread: Some(thread),
+        })
+    }
+
+    /// Whether all the keys are held.
+    pub(crate) fn all_held(&mut self) -> bool {
+        self.codes.iter().all(|&vk| is_held(vk))
+    }
+}
+
+impl Drop for KeyProbe {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
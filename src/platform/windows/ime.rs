@@ -0,0 +1,55 @@
+//! Input method state of the foreground window, through IMM.
+//!
+//! Another process's input context can't be opened, but its default IME
+//! window answers `WM_IME_CONTROL` queries from any process.
+
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+use windows::Win32::UI::Input::Ime::ImmGetDefaultIMEWnd;
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, SMTO_ABORTIFHUNG, SendMessageTimeoutW, WM_IME_CONTROL,
+};
+
+/// `WM_IME_CONTROL` commands (`imm.h`).
+const IMC_GETCONVERSIONMODE: usize = 0x0001;
+const IMC_GETOPENSTATUS: usize = 0x0005;
+
+/// Conversion mode bit for native (kana, hangul, Chinese) input.
+const IME_CMODE_NATIVE: usize = 0x0001;
+
+/// How long a hung foreground window may keep a query waiting.
+const QUERY_TIMEOUT_MS: u32 = 20;
+
+/// Whether the foreground window's input method composes keys: open, and
+/// in native rather than alphanumeric mode.
+///
+/// `None` when there is no foreground window or it doesn't answer.
+pub(crate) fn ime_composing() -> Option<bool> {
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_invalid() {
+            return None;
+        }
+        let ime = ImmGetDefaultIMEWnd(hwnd);
+        if ime.is_invalid() {
+            // No input method for this window
+            return Some(false);
+        }
+        let query = |command: usize| {
+            let mut result = 0usize;
+            let sent = SendMessageTimeoutW(
+                ime,
+                WM_IME_CONTROL,
+                WPARAM(command),
+                LPARAM(0),
+                SMTO_ABORTIFHUNG,
+                QUERY_TIMEOUT_MS,
+                Some(&mut result),
+            );
+            (sent.0 != 0).then_some(result)
+        };
+        if query(IMC_GETOPENSTATUS)? == 0 {
+            return Some(false);
+        }
+        Some(query(IMC_GETCONVERSIONMODE)? & IME_CMODE_NATIVE != 0)
+    }
+}
@@ -320,3 +320,75 @@ pub fn key_to_keycode(key: Key) -> Option<u16> {
         _ => return None,
     })
 }
+
+/// Convert a set-1 scan code in the character block to the key at that
+/// position on a US layout.
+///
+/// Other keys are in the same place on every layout and return `None`.
+pub fn scancode_to_key(scan: u32) -> Option<Key> {
+    const NUMBER_ROW: [Key; 12] = [
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+        Key::Num0,
+        Key::Minus,
+        Key::Equal,
+    ];
+    const TOP_ROW: [Key; 12] = [
+        Key::KeyQ,
+        Key::KeyW,
+        Key::KeyE,
+        Key::KeyR,
+        Key::KeyT,
+        Key::KeyY,
+        Key::KeyU,
+        Key::KeyI,
+        Key::KeyO,
+        Key::KeyP,
+        Key::BracketLeft,
+        Key::BracketRight,
+    ];
+    const HOME_ROW: [Key; 12] = [
+        Key::KeyA,
+        Key::KeyS,
+        Key::KeyD,
+        Key::KeyF,
+        Key::KeyG,
+        Key::KeyH,
+        Key::KeyJ,
+        Key::KeyK,
+        Key::KeyL,
+        Key::Semicolon,
+        Key::Quote,
+        Key::Grave,
+    ];
+    const BOTTOM_ROW: [Key; 10] = [
+        Key::KeyZ,
+        Key::KeyX,
+        Key::KeyC,
+        Key::KeyV,
+        Key::KeyB,
+        Key::KeyN,
+        Key::KeyM,
+        Key::Comma,
+        Key::Period,
+        Key::Slash,
+    ];
+
+    let scan = scan as usize;
+    match scan {
+        0x02..=0x0d => Some(NUMBER_ROW[scan - 0x02]),
+        0x10..=0x1b => Some(TOP_ROW[scan - 0x10]),
+        0x1e..=0x29 => Some(HOME_ROW[scan - 0x1e]),
+        0x2b => Some(Key::Backslash),
+        0x2c..=0x35 => Some(BOTTOM_ROW[scan - 0x2c]),
+        0x56 => Some(Key::IntlBackslash),
+        _ => None,
+    }
+}
@@ -0,0 +1,74 @@
+//! Layout-aware key resolution using the active keyboard layout.
+
+use crate::debug;
+use crate::keycode::Key;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetKeyboardLayout, MAPVK_VK_TO_CHAR, MAPVK_VK_TO_VSC, MapVirtualKeyW, VkKeyScanExW,
+};
+
+use super::keycodes::{key_to_keycode, keycode_to_key, scancode_to_key};
+
+/// Set by `MapVirtualKeyW` for dead keys.
+const DEAD_KEY_FLAG: u32 = 0x8000_0000;
+
+/// Convert a virtual key, falling back to the active layout for unknown keys.
+///
+/// `scan_code` is only used to report keys that stay unknown.
+pub fn resolve_key(code: u16, scan_code: u32) -> Key {
+    match keycode_to_key(code) {
+        Key::Unknown(_) => {
+            // Unshifted character in the low word (uppercase for letters)
+            let mapped = unsafe { MapVirtualKeyW(code as u32, MAPVK_VK_TO_CHAR) } & !DEAD_KEY_FLAG;
+            let ch = char::from_u32(mapped).filter(|_| mapped != 0);
+            match ch.filter(|ch| !ch.is_control() && !ch.is_whitespace()) {
+                Some(ch) => Key::from_char(ch),
+                None => {
+                    debug::unknown_key("windows", code as u32, |report| {
+                        report.scan_code = Some(scan_code);
+                        report.text = ch.map(String::from);
+                    });
+                    Key::Unknown(code as u32)
+                }
+            }
+        }
+        key => key,
+    }
+}
+
+/// Convert a key to a virtual key, searching the active layout for `Key::Char`.
+pub fn resolve_keycode(key: Key) -> Option<u16> {
+    match key {
+        Key::Char(ch) => {
+            let unit = u16::try_from(ch as u32).ok()?;
+            let scan = unsafe { VkKeyScanExW(unit, GetKeyboardLayout(0)) };
+            // Low byte is the virtual key, high byte the shift state; -1 if absent
+            (scan != -1).then_some(scan as u16 & 0xff)
+        }
+        key => key_to_keycode(key),
+    }
+}
+
+/// Virtual key codes.
+pub(crate) const KEYCODES: std::ops::RangeInclusive<u32> = 0..=0xfe;
+
+/// The key a virtual key maps to.
+pub(crate) fn key_for_code(code: u32) -> Key {
+    u16::try_from(code).map_or(Key::Unknown(code), keycode_to_key)
+}
+
+/// The key at this virtual key's position on a US layout.
+///
+/// Virtual keys follow the layout, so this goes through the scan code.
+pub(crate) fn physical_key(code: u32, key: Key) -> Key {
+    // Events built by hand may carry an unrelated code
+    if u16::try_from(code).map(keycode_to_key) != Ok(key) {
+        return key;
+    }
+    let scan = unsafe { MapVirtualKeyW(code, MAPVK_VK_TO_VSC) };
+    scancode_to_key(scan).unwrap_or(key)
+}
+
+/// Virtual keys already follow the active layout.
+pub(crate) fn logical_key(_code: u32, key: Key) -> Key {
+    key
+}
@@ -1,14 +1,17 @@
 //! Windows input listening using SetWindowsHookEx.
 
+#![warn(clippy::undocumented_unsafe_blocks)]
+
+use crate::convert;
 use crate::error::{Error, Result};
-use crate::event::{Button, Event, ScrollDirection};
-use crate::hook::{EventHandler, GrabHandler};
-use crate::state::{
-    self, MASK_ALT, MASK_BUTTON1, MASK_BUTTON2, MASK_BUTTON3, MASK_BUTTON4, MASK_BUTTON5,
-    MASK_CTRL, MASK_META, MASK_SHIFT,
-};
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::event::Event;
+use crate::hook::{EventHandler, EventMask, GrabHandler};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 // Wrapper for HHOOK to make it Send + Sync
 #[derive(Clone, Copy)]
@@ -17,530 +20,847 @@ struct SendableHHOOK(HHOOK);
 // SAFETY: HHOOK is just a handle/pointer that the Windows API owns.
 // It's safe to send between threads because Windows handles are thread-safe.
 unsafe impl Send for SendableHHOOK {}
+// SAFETY: as above; the handle is only passed to thread-safe hook functions
 unsafe impl Sync for SendableHHOOK {}
 use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
 use windows::Win32::System::Threading::GetCurrentThreadId;
 use windows::Win32::UI::WindowsAndMessaging::{
-    CallNextHookEx, GetMessageW, HC_ACTION, HHOOK, KBDLLHOOKSTRUCT, MSLLHOOKSTRUCT,
-    PostThreadMessageW, SetWindowsHookExW, UnhookWindowsHookEx, WH_KEYBOARD_LL, WH_MOUSE_LL,
-    WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP,
-    WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_QUIT, WM_RBUTTONDOWN, WM_RBUTTONUP,
-    WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP,
+    CallNextHookEx, GetMessageW, HC_ACTION, HHOOK, HOOKPROC, KBDLLHOOKSTRUCT, LLKHF_EXTENDED,
+    LLKHF_INJECTED, LLMHF_INJECTED, MSLLHOOKSTRUCT, PostThreadMessageW, SetWindowsHookExW,
+    UnhookWindowsHookEx, WH_KEYBOARD_LL, WH_MOUSE_LL, WINDOWS_HOOK_ID, WM_MOUSEHWHEEL,
+    WM_MOUSEWHEEL, WM_QUIT,
 };
+#[cfg(feature = "pointer")]
+use windows::Win32::UI::WindowsAndMessaging::{DispatchMessageW, WM_INPUT};
 
-use super::keycodes::keycode_to_key;
+use super::layout::resolve_key;
+use super::simulate::SYNTHETIC_TAG;
+use crate::platform::ActiveHook;
+
+/// The running hook's handler, for listen or grab mode.
+///
+/// Both hooks deliver through it, via [`dispatch`].
+enum ActiveHandler {
+    Listen(Box<dyn EventHandler>),
+    Grab(Box<dyn GrabHandler>),
+}
 
-// Constants
-const WHEEL_DELTA: i16 = 120;
+impl ActiveHandler {
+    /// Deliver an event, returning whether the handler consumed it.
+    fn deliver(&self, event: &Event) -> bool {
+        match self {
+            ActiveHandler::Listen(handler) => {
+                handler.handle_event(event);
+                false
+            }
+            ActiveHandler::Grab(handler) => handler.handle_event(event).is_none(),
+        }
+    }
+}
 
-/// Stored handler for the callback (listen mode)
-static HANDLER: Mutex<Option<Box<dyn EventHandler>>> = Mutex::new(None);
+/// How far native event time may drift from the wall clock before rebasing.
+const MAX_CLOCK_DRIFT: Duration = Duration::from_secs(1);
 
-/// Stored handler for the callback (grab mode)
-static GRAB_HANDLER: Mutex<Option<Box<dyn GrabHandler>>> = Mutex::new(None);
+/// Maps the hooks' native timestamps (`GetTickCount` milliseconds, shared by
+/// keyboard and mouse) onto wall-clock time, never going backwards.
+struct EventClock {
+    base: Option<(SystemTime, u32)>,
+    last: SystemTime,
+}
 
-/// Flag to signal stopping
-static STOP_FLAG: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+impl EventClock {
+    const fn new() -> Self {
+        Self {
+            base: None,
+            last: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    /// The wall-clock time for native time `tick`, observed at `now`.
+    fn stamp(&mut self, tick: u32, now: SystemTime) -> SystemTime {
+        let time = self.base.and_then(|(base_time, base_tick)| {
+            // Signed, so slightly older ticks and the 49-day wrap both work
+            let offset = tick.wrapping_sub(base_tick) as i32;
+            let time = if offset >= 0 {
+                base_time + Duration::from_millis(offset as u64)
+            } else {
+                base_time - Duration::from_millis(offset.unsigned_abs() as u64)
+            };
+            let drift = now.duration_since(time).unwrap_or_else(|e| e.duration());
+            (drift <= MAX_CLOCK_DRIFT).then_some(time)
+        });
+        let time = time.unwrap_or_else(|| {
+            self.base = Some((now, tick));
+            now
+        });
+
+        self.last = time.max(self.last);
+        self.last
+    }
+}
+
+thread_local! {
+    /// Handler of the hook running on this thread. Low-level hooks are
+    /// called on the thread that installed them.
+    static HANDLER: RefCell<Option<ActiveHandler>> = const { RefCell::new(None) };
+
+    /// Clock for events dispatched on this (the hook) thread.
+    static CLOCK: RefCell<EventClock> = const { RefCell::new(EventClock::new()) };
+
+    /// Whether the handler is running on this thread.
+    static DISPATCHING: Cell<bool> = const { Cell::new(false) };
+
+    /// Events from hook calls that re-entered while the handler was running.
+    static PENDING: RefCell<VecDeque<Event>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// The running hook. The hook handles and thread below are the running
+/// hook's, so hooks run one at a time.
+static ACTIVE: ActiveHook = ActiveHook::new();
 
 /// Hook handles
 static KEYBOARD_HOOK: Mutex<Option<SendableHHOOK>> = Mutex::new(None);
 static MOUSE_HOOK: Mutex<Option<SendableHHOOK>> = Mutex::new(None);
 
+/// Raw hook handles, for [`emergency_release`], which can't wait for the
+/// locks above.
+static KEYBOARD_HOOK_RAW: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static MOUSE_HOOK_RAW: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
 /// Thread ID for message posting
 static THREAD_ID: Mutex<u32> = Mutex::new(0);
 
-/// Flag indicating whether we're in grab mode
+/// Flag indicating whether we're in grab mode (read before locking the handler)
 static GRAB_MODE: AtomicBool = AtomicBool::new(false);
 
-/// Update modifier mask from keyboard event
-fn update_key_modifier(code: u32, pressed: bool) {
-    let mask = match code {
-        0xA0 | 0xA1 => MASK_SHIFT, // VK_LSHIFT, VK_RSHIFT
-        0xA2 | 0xA3 => MASK_CTRL,  // VK_LCONTROL, VK_RCONTROL
-        0xA4 | 0xA5 => MASK_ALT,   // VK_LMENU, VK_RMENU
-        0x5B | 0x5C => MASK_META,  // VK_LWIN, VK_RWIN
-        _ => return,
-    };
+/// Whether grabs only intercept the wheel, see
+/// [`GrabOptions::scroll_only`](crate::GrabOptions::scroll_only).
+static SCROLL_ONLY: AtomicBool = AtomicBool::new(false);
 
-    if pressed {
-        state::set_mask(mask);
-    } else {
-        state::unset_mask(mask);
-    }
+/// Set whether the next grab hook only intercepts the wheel.
+pub(crate) fn set_scroll_only(scroll_only: bool) {
+    SCROLL_ONLY.store(scroll_only, Ordering::SeqCst);
 }
 
-/// Get VK code from KBDLLHOOKSTRUCT
-unsafe fn get_vk_code(lpdata: LPARAM) -> u32 {
-    let kb = unsafe { *(lpdata.0 as *const KBDLLHOOKSTRUCT) };
-    kb.vkCode
-}
+/// Categories to hook, see [`set_event_mask`].
+static EVENT_MASK: AtomicU32 = AtomicU32::new(EventMask::ALL.bits());
 
-/// Get point from MSLLHOOKSTRUCT
-unsafe fn get_mouse_point(lpdata: LPARAM) -> (i32, i32) {
-    let mouse = unsafe { *(lpdata.0 as *const MSLLHOOKSTRUCT) };
-    (mouse.pt.x, mouse.pt.y)
+/// Hook only the events in `mask` from the next hook on.
+pub(crate) fn set_event_mask(mask: EventMask) {
+    EVENT_MASK.store(mask.bits(), Ordering::SeqCst);
 }
 
-/// Get wheel delta from MSLLHOOKSTRUCT
-unsafe fn get_wheel_delta(lpdata: LPARAM) -> i16 {
-    let mouse = unsafe { *(lpdata.0 as *const MSLLHOOKSTRUCT) };
-    ((mouse.mouseData >> 16) & 0xFFFF) as i16
-}
+// The structs low-level hooks pass, as `winuser.h` lays them out: 32-bit
+// fields, then the pointer-sized extra info, aligned
+const _: () = assert!(size_of::<KBDLLHOOKSTRUCT>() == 4 * 4 + size_of::<usize>());
+const _: () = assert!(
+    size_of::<MSLLHOOKSTRUCT>()
+        == (5 * 4usize).next_multiple_of(size_of::<usize>()) + size_of::<usize>()
+);
 
-/// Get X button code from MSLLHOOKSTRUCT
-unsafe fn get_xbutton_code(lpdata: LPARAM) -> u8 {
-    let mouse = unsafe { *(lpdata.0 as *const MSLLHOOKSTRUCT) };
-    ((mouse.mouseData >> 16) & 0xFFFF) as u8
+/// Copy the struct a low-level hook passes in `lparam`, or `None` if the
+/// pointer is null or misaligned for it.
+///
+/// # Safety
+///
+/// Unless null, `lparam` must point to a `T` for the duration of the call,
+/// as a hook's `lparam` does while its callback runs.
+unsafe fn read_hook_struct<T: Copy>(lparam: LPARAM) -> Option<T> {
+    let ptr = lparam.0 as *const T;
+    if ptr.is_null() || !ptr.is_aligned() {
+        return None;
+    }
+    // SAFETY: non-null and aligned, and the caller vouches that it points
+    // to a `T`; the struct is plain data, copied out
+    Some(unsafe { ptr.read() })
 }
 
-/// Convert Windows message to our Event type
-unsafe fn convert_event(wparam: WPARAM, lparam: LPARAM) -> Option<Event> {
-    let msg = wparam.0 as u32;
-
-    match msg {
-        WM_KEYDOWN | WM_SYSKEYDOWN => {
-            let code = unsafe { get_vk_code(lparam) };
-            update_key_modifier(code, true);
-            let key = keycode_to_key(code as u16);
-            Some(Event::key_pressed(key, code))
-        }
+/// What both hooks' structs carry.
+trait HookStruct {
+    /// Native timestamp, `GetTickCount` milliseconds.
+    fn time(&self) -> u32;
+    /// The extra info the sender attached.
+    fn extra_info(&self) -> usize;
+    /// Whether software generated the event.
+    fn injected(&self) -> bool;
+
+    /// Whether the event was injected by `inject_synthetic`.
+    fn is_synthetic(&self) -> bool {
+        self.extra_info() == SYNTHETIC_TAG
+    }
+}
 
-        WM_KEYUP | WM_SYSKEYUP => {
-            let code = unsafe { get_vk_code(lparam) };
-            update_key_modifier(code, false);
-            let key = keycode_to_key(code as u16);
-            Some(Event::key_released(key, code))
-        }
+impl HookStruct for KBDLLHOOKSTRUCT {
+    fn time(&self) -> u32 {
+        self.time
+    }
 
-        WM_LBUTTONDOWN => {
-            state::set_mask(MASK_BUTTON1);
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            Some(Event::mouse_pressed(Button::Left, x as f64, y as f64))
-        }
+    fn extra_info(&self) -> usize {
+        self.dwExtraInfo
+    }
 
-        WM_LBUTTONUP => {
-            state::unset_mask(MASK_BUTTON1);
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            Some(Event::mouse_released(Button::Left, x as f64, y as f64))
-        }
+    fn injected(&self) -> bool {
+        self.flags.contains(LLKHF_INJECTED)
+    }
+}
 
-        WM_RBUTTONDOWN => {
-            state::set_mask(MASK_BUTTON2);
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            Some(Event::mouse_pressed(Button::Right, x as f64, y as f64))
-        }
+impl HookStruct for MSLLHOOKSTRUCT {
+    fn time(&self) -> u32 {
+        self.time
+    }
 
-        WM_RBUTTONUP => {
-            state::unset_mask(MASK_BUTTON2);
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            Some(Event::mouse_released(Button::Right, x as f64, y as f64))
-        }
+    fn extra_info(&self) -> usize {
+        self.dwExtraInfo
+    }
 
-        WM_MBUTTONDOWN => {
-            state::set_mask(MASK_BUTTON3);
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            Some(Event::mouse_pressed(Button::Middle, x as f64, y as f64))
-        }
+    fn injected(&self) -> bool {
+        self.flags & LLMHF_INJECTED != 0
+    }
+}
 
-        WM_MBUTTONUP => {
-            state::unset_mask(MASK_BUTTON3);
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            Some(Event::mouse_released(Button::Middle, x as f64, y as f64))
-        }
+/// The fields of a keyboard hook event that are converted.
+fn keyboard_fields(wparam: WPARAM, kb: &KBDLLHOOKSTRUCT) -> convert::windows::RawKeyboard {
+    convert::windows::RawKeyboard {
+        message: wparam.0 as u32,
+        vk_code: kb.vkCode,
+        scan_code: kb.scanCode,
+        extended: kb.flags.contains(LLKHF_EXTENDED),
+    }
+}
 
-        WM_XBUTTONDOWN => {
-            let xbutton = unsafe { get_xbutton_code(lparam) };
-            let (button, mask) = match xbutton {
-                1 => (Button::Button4, MASK_BUTTON4),
-                2 => (Button::Button5, MASK_BUTTON5),
-                _ => (Button::Unknown(xbutton), 0),
-            };
-            if mask != 0 {
-                state::set_mask(mask);
-            }
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            Some(Event::mouse_pressed(button, x as f64, y as f64))
-        }
+/// The fields of a mouse hook event that are converted.
+fn mouse_fields(wparam: WPARAM, mouse: &MSLLHOOKSTRUCT) -> convert::windows::RawMouse {
+    convert::windows::RawMouse {
+        message: wparam.0 as u32,
+        x: mouse.pt.x,
+        y: mouse.pt.y,
+        mouse_data: mouse.mouseData,
+    }
+}
 
-        WM_XBUTTONUP => {
-            let xbutton = unsafe { get_xbutton_code(lparam) };
-            let (button, mask) = match xbutton {
-                1 => (Button::Button4, MASK_BUTTON4),
-                2 => (Button::Button5, MASK_BUTTON5),
-                _ => (Button::Unknown(xbutton), 0),
-            };
-            if mask != 0 {
-                state::unset_mask(mask);
-            }
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            Some(Event::mouse_released(button, x as f64, y as f64))
-        }
+/// Deliver an event to this thread's handler, if any.
+fn deliver(event: &Event) -> bool {
+    HANDLER.with_borrow(|handler| {
+        handler
+            .as_ref()
+            .is_some_and(|handler| handler.deliver(event))
+    })
+}
 
-        WM_MOUSEMOVE => {
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            // THE KEY FIX: Check button state for drag detection
-            if state::is_button_held() {
-                Some(Event::mouse_dragged(x as f64, y as f64))
-            } else {
-                Some(Event::mouse_moved(x as f64, y as f64))
-            }
-        }
+/// Stamp events with their native time and hand them to the handler.
+///
+/// Both hooks run on the hook thread and come through here, so the handler
+/// sees keyboard and mouse events in the order the system delivered them. A
+/// handler that pumps messages (a modal dialog, `SendMessage`) can re-enter
+/// either hook; those events are queued behind the current one instead of
+/// overtaking it, and can't be consumed since the system needs an answer
+/// before the handler returns.
+///
+/// Returns whether the handler consumed any of `events`.
+fn dispatch(native_time: u32, events: Vec<Event>) -> bool {
+    let time = CLOCK.with_borrow_mut(|clock| clock.stamp(native_time, SystemTime::now()));
+    let events = events.into_iter().map(|mut event| {
+        event.time = time;
+        event
+    });
+
+    if DISPATCHING.get() {
+        PENDING.with_borrow_mut(|pending| pending.extend(events));
+        return false;
+    }
 
-        WM_MOUSEWHEEL => {
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            let delta = unsafe { get_wheel_delta(lparam) };
-            let delta_units = delta as f64 / WHEEL_DELTA as f64;
-            let (direction, abs_delta) = if delta > 0 {
-                (ScrollDirection::Up, delta_units)
-            } else {
-                (ScrollDirection::Down, -delta_units)
-            };
-            Some(Event::mouse_wheel(x as f64, y as f64, direction, abs_delta))
-        }
+    DISPATCHING.set(true);
+    // One message can carry several events (both X buttons); consuming any
+    // of them consumes the message
+    let consumed = events.fold(false, |consumed, event| deliver(&event) | consumed);
+    while let Some(event) = PENDING.with_borrow_mut(VecDeque::pop_front) {
+        deliver(&event);
+    }
+    DISPATCHING.set(false);
+    consumed
+}
 
-        WM_MOUSEHWHEEL => {
-            let (x, y) = unsafe { get_mouse_point(lparam) };
-            let delta = unsafe { get_wheel_delta(lparam) };
-            let delta_units = delta as f64 / WHEEL_DELTA as f64;
-            let (direction, abs_delta) = if delta > 0 {
-                (ScrollDirection::Right, delta_units)
-            } else {
-                (ScrollDirection::Left, -delta_units)
-            };
-            Some(Event::mouse_wheel(x as f64, y as f64, direction, abs_delta))
-        }
+/// Mouse hook callback for scroll-only grabs: everything but the wheel
+/// goes straight to the next hook, unconverted and unseen by handlers.
+unsafe extern "system" fn wheel_callback(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 && matches!(wparam.0 as u32, WM_MOUSEWHEEL | WM_MOUSEHWHEEL) {
+        // SAFETY: the same arguments, from the mouse hook
+        return unsafe { mouse_callback(code, wparam, lparam) };
+    }
+    let hook = MOUSE_HOOK.lock().ok().and_then(|g| g.map(|h| h.0));
+    // SAFETY: passes on what the system passed the hook, unchanged
+    unsafe { CallNextHookEx(hook, code, wparam, lparam) }
+}
 
-        _ => None,
+/// Tell the hook thread to quit its message loop, if its hook was stopped.
+fn quit_if_stopping() {
+    if ACTIVE.stopping()
+        && let Ok(thread_id) = THREAD_ID.lock()
+    {
+        // SAFETY: plain message posting; fails harmlessly for a stale id
+        let _ = unsafe { PostThreadMessageW(*thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) };
     }
 }
 
 /// Keyboard hook callback
 unsafe extern "system" fn keyboard_callback(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
-    if code == HC_ACTION as i32 {
-        // Check stop flag
-        if let Ok(guard) = STOP_FLAG.lock() {
-            if let Some(ref flag) = *guard {
-                if !flag.load(Ordering::SeqCst) {
-                    // Stop requested
-                    if let Ok(thread_id) = THREAD_ID.lock() {
-                        let _ = unsafe {
-                            PostThreadMessageW(*thread_id, WM_QUIT, WPARAM(0), LPARAM(0))
-                        };
-                    }
-                }
-            }
-        }
-
-        if let Some(event) = unsafe { convert_event(wparam, lparam) } {
-            // Check if we're in grab mode
-            if GRAB_MODE.load(Ordering::SeqCst) {
-                if let Ok(guard) = GRAB_HANDLER.lock() {
-                    if let Some(ref handler) = *guard {
-                        if handler.handle_event(&event).is_none() {
-                            // Handler returned None - consume the event
-                            return LRESULT(1);
-                        }
-                    }
-                }
-            } else {
-                // Listen mode: just dispatch
-                if let Ok(guard) = HANDLER.lock() {
-                    if let Some(ref handler) = *guard {
-                        handler.handle_event(&event);
-                    }
-                }
+    let kb = if code == HC_ACTION as i32 {
+        // SAFETY: for `HC_ACTION`, the keyboard hook's `lparam` points to a
+        // `KBDLLHOOKSTRUCT` while the callback runs
+        unsafe { read_hook_struct::<KBDLLHOOKSTRUCT>(lparam) }
+    } else {
+        None
+    };
+    if let Some(kb) = kb {
+        quit_if_stopping();
+
+        // Replayed events already went through the grab handler
+        let replayed = GRAB_MODE.load(Ordering::SeqCst) && kb.is_synthetic();
+
+        let raw = keyboard_fields(wparam, &kb);
+        if !replayed
+            && let Some(mut event) = convert::with_global_mask(|mask| {
+                convert::windows::convert_keyboard(&raw, mask, resolve_key)
+            })
+        {
+            event.injected = Some(kb.injected());
+
+            if dispatch(kb.time(), vec![event]) {
+                // Handler returned None - consume the event
+                return LRESULT(1);
             }
         }
     }
 
     let hook = KEYBOARD_HOOK.lock().ok().and_then(|g| g.map(|h| h.0));
+    // SAFETY: passes on what the system passed the hook, unchanged
     unsafe { CallNextHookEx(hook, code, wparam, lparam) }
 }
 
 /// Mouse hook callback
 unsafe extern "system" fn mouse_callback(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
-    if code == HC_ACTION as i32 {
-        // Check stop flag (same as keyboard callback)
-        if let Ok(guard) = STOP_FLAG.lock() {
-            if let Some(ref flag) = *guard {
-                if !flag.load(Ordering::SeqCst) {
-                    // Stop requested
-                    if let Ok(thread_id) = THREAD_ID.lock() {
-                        let _ = unsafe {
-                            PostThreadMessageW(*thread_id, WM_QUIT, WPARAM(0), LPARAM(0))
-                        };
-                    }
-                }
-            }
-        }
-
-        if let Some(event) = unsafe { convert_event(wparam, lparam) } {
-            // Check if we're in grab mode
-            if GRAB_MODE.load(Ordering::SeqCst) {
-                if let Ok(guard) = GRAB_HANDLER.lock() {
-                    if let Some(ref handler) = *guard {
-                        if handler.handle_event(&event).is_none() {
-                            // Handler returned None - consume the event
-                            return LRESULT(1);
-                        }
-                    }
-                }
-            } else {
-                // Listen mode: just dispatch
-                if let Ok(guard) = HANDLER.lock() {
-                    if let Some(ref handler) = *guard {
-                        handler.handle_event(&event);
-                    }
-                }
+    let mouse = if code == HC_ACTION as i32 {
+        // SAFETY: for `HC_ACTION`, the mouse hook's `lparam` points to an
+        // `MSLLHOOKSTRUCT` while the callback runs
+        unsafe { read_hook_struct::<MSLLHOOKSTRUCT>(lparam) }
+    } else {
+        None
+    };
+    if let Some(mouse) = mouse {
+        quit_if_stopping();
+
+        // Replayed events already went through the grab handler
+        let replayed = GRAB_MODE.load(Ordering::SeqCst) && mouse.is_synthetic();
+
+        let events = match wparam.0 as u32 {
+            _ if replayed => Vec::new(),
+            // Already reported with pixel deltas from the touchpad
+            #[cfg(feature = "pointer")]
+            WM_MOUSEWHEEL | WM_MOUSEHWHEEL if super::precision::covers(mouse.time()) => Vec::new(),
+            _ => {
+                let raw = mouse_fields(wparam, &mouse);
+                convert::with_global_mask(|mask| convert::windows::convert_mouse(&raw, mask))
             }
+        };
+        let injected = mouse.injected();
+        let events: Vec<Event> = events
+            .into_iter()
+            .map(|mut event| {
+                event.injected = Some(injected);
+                event
+            })
+            .collect();
+
+        if !events.is_empty() && dispatch(mouse.time(), events) {
+            // Handler returned None - consume the event
+            return LRESULT(1);
         }
     }
 
     let hook = MOUSE_HOOK.lock().ok().and_then(|g| g.map(|h| h.0));
+    // SAFETY: passes on what the system passed the hook, unchanged
     unsafe { CallNextHookEx(hook, code, wparam, lparam) }
 }
 
-/// Run the event hook (blocking).
-pub fn run_hook<H: EventHandler + 'static>(running: &Arc<AtomicBool>, handler: H) -> Result<()> {
-    // Store handler and stop flag
-    {
-        let mut h = HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = Some(Box::new(handler));
+/// An installed low-level hook, removed on drop.
+struct InstalledHook {
+    hook: SendableHHOOK,
+    slot: &'static Mutex<Option<SendableHHOOK>>,
+    raw: &'static AtomicPtr<c_void>,
+}
+
+impl InstalledHook {
+    /// Install a hook and publish it in `slot` for `CallNextHookEx`, and in
+    /// `raw` for [`emergency_release`].
+    fn install(
+        id: WINDOWS_HOOK_ID,
+        callback: HOOKPROC,
+        slot: &'static Mutex<Option<SendableHHOOK>>,
+        raw: &'static AtomicPtr<c_void>,
+    ) -> windows::core::Result<Self> {
+        // SAFETY: a low-level hook with a callback of this module, on the
+        // calling thread (no module handle or thread id needed)
+        let hook = SendableHHOOK(unsafe { SetWindowsHookExW(id, callback, None, 0)? });
+        if let Ok(mut h) = slot.lock() {
+            *h = Some(hook);
+        }
+        raw.store(hook.0.0, Ordering::SeqCst);
+        Ok(Self { hook, slot, raw })
     }
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = Some(running.clone());
+}
+
+impl Drop for InstalledHook {
+    fn drop(&mut self) {
+        self.raw.store(std::ptr::null_mut(), Ordering::SeqCst);
+        if let Ok(mut h) = self.slot.lock() {
+            *h = None;
+        }
+        // SAFETY: the handle was returned by `SetWindowsHookExW`; removing it
+        // again fails harmlessly if the failsafe already removed it
+        unsafe {
+            let _ = UnhookWindowsHookEx(self.hook.0);
+        }
     }
+}
+
+/// Clears the hook's statics when it returns, on every path.
+struct HookStatics;
+
+impl Drop for HookStatics {
+    fn drop(&mut self) {
+        GRAB_MODE.store(false, Ordering::SeqCst);
+        if let Ok(mut tid) = THREAD_ID.lock() {
+            *tid = 0;
+        }
+        HANDLER.set(None);
+    }
+}
+
+/// Run the event hook (blocking).
+///
+/// Fails with [`Error::AlreadyRunning`] while another hook runs.
+pub fn run_hook<H: EventHandler + 'static>(running: &Arc<AtomicBool>, handler: H) -> Result<()> {
+    run(running, ActiveHandler::Listen(Box::new(handler)))
+}
+
+/// Run the event hook with grab capability (blocking).
+///
+/// Similar to `run_hook`, but allows the handler to consume events by returning `None`.
+pub fn run_grab_hook<H: GrabHandler + 'static>(
+    running: &Arc<AtomicBool>,
+    handler: H,
+) -> Result<()> {
+    run(running, ActiveHandler::Grab(Box::new(handler)))
+}
+
+/// Install the keyboard and mouse hooks and pump messages until stopped.
+fn run(running: &Arc<AtomicBool>, handler: ActiveHandler) -> Result<()> {
+    let _claim = ACTIVE.claim(running)?;
+    let _statics = HookStatics;
+
+    GRAB_MODE.store(matches!(handler, ActiveHandler::Grab(_)), Ordering::SeqCst);
+    HANDLER.set(Some(handler));
 
     // Store current thread ID for stopping
     {
         let mut tid = THREAD_ID
             .lock()
             .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
+        // SAFETY: no preconditions
         *tid = unsafe { GetCurrentThreadId() };
     }
 
-    // Set up keyboard hook
-    let keyboard_hook = unsafe {
-        SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_callback), None, 0)
-            .map_err(|e| Error::HookStartFailed(format!("Failed to set keyboard hook: {}", e)))?
+    // Set up keyboard and mouse hooks, removed again however we return.
+    // Scroll-only grabs leave the keyboard alone entirely, and neither hook
+    // is installed for events the mask leaves out
+    let scroll_only = GRAB_MODE.load(Ordering::SeqCst) && SCROLL_ONLY.load(Ordering::SeqCst);
+    let events = EventMask::from_bits_truncate(EVENT_MASK.load(Ordering::SeqCst));
+    let _keyboard_hook = if scroll_only || !events.contains(EventMask::KEYBOARD) {
+        None
+    } else {
+        Some(
+            InstalledHook::install(
+                WH_KEYBOARD_LL,
+                Some(keyboard_callback),
+                &KEYBOARD_HOOK,
+                &KEYBOARD_HOOK_RAW,
+            )
+            .map_err(|e| Error::HookStartFailed(format!("Failed to set keyboard hook: {}", e)))?,
+        )
+    };
+    let mouse_proc: HOOKPROC = if scroll_only {
+        Some(wheel_callback)
+    } else {
+        Some(mouse_callback)
+    };
+    let _mouse_hook = if events.intersects(EventMask::MOUSE) {
+        Some(
+            InstalledHook::install(WH_MOUSE_LL, mouse_proc, &MOUSE_HOOK, &MOUSE_HOOK_RAW)
+                .map_err(|e| Error::HookStartFailed(format!("Failed to set mouse hook: {}", e)))?,
+        )
+    } else {
+        None
     };
-    {
-        let mut kh = KEYBOARD_HOOK
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *kh = Some(SendableHHOOK(keyboard_hook));
-    }
 
-    // Set up mouse hook
-    let mouse_hook = unsafe {
-        SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_callback), None, 0)
-            .map_err(|e| Error::HookStartFailed(format!("Failed to set mouse hook: {}", e)))?
+    // Precision touchpads are only followed for listening; a failure to
+    // register leaves scrolling to the wheel ticks
+    #[cfg(feature = "pointer")]
+    let _touchpads = if GRAB_MODE.load(Ordering::SeqCst) {
+        None
+    } else {
+        super::precision::register().ok()
     };
-    {
-        let mut mh = MOUSE_HOOK
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *mh = Some(SendableHHOOK(mouse_hook));
-    }
 
     // Send hook enabled event
-    {
-        if let Ok(guard) = HANDLER.lock() {
-            if let Some(ref handler) = *guard {
-                handler.handle_event(&Event::hook_enabled());
-            }
-        }
-    }
+    deliver(&Event::hook_enabled());
 
     // Message loop
     let mut msg = windows::Win32::UI::WindowsAndMessaging::MSG::default();
-    unsafe {
-        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
-            // Check stop flag
-            if let Ok(guard) = STOP_FLAG.lock() {
-                if let Some(ref flag) = *guard {
-                    if !flag.load(Ordering::SeqCst) {
-                        break;
-                    }
-                }
+    // SAFETY: `msg` is a valid `MSG` to fill in. `WM_QUIT` returns 0 and an
+    // error -1, both ending the loop
+    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.0 > 0 {
+        #[cfg(feature = "pointer")]
+        if msg.message == WM_INPUT {
+            let events = super::precision::handle_input(msg.lParam, msg.time);
+            if !events.is_empty() {
+                dispatch(msg.time, events);
             }
+            // SAFETY: `msg` was just filled in by `GetMessageW`
+            unsafe { DispatchMessageW(&msg) };
+            continue;
+        }
+        // Check stop flag
+        if !running.load(Ordering::SeqCst) {
+            break;
         }
     }
 
     // Send hook disabled event
-    {
-        if let Ok(guard) = HANDLER.lock() {
-            if let Some(ref handler) = *guard {
-                handler.handle_event(&Event::hook_disabled());
-            }
-        }
-    }
+    deliver(&Event::hook_disabled());
 
-    // Clean up hooks
-    unsafe {
-        if let Ok(mut kh) = KEYBOARD_HOOK.lock() {
-            if let Some(hook) = kh.take() {
-                let _ = UnhookWindowsHookEx(hook.0);
-            }
-        }
-        if let Ok(mut mh) = MOUSE_HOOK.lock() {
-            if let Some(hook) = mh.take() {
-                let _ = UnhookWindowsHookEx(hook.0);
+    Ok(())
+}
+
+/// Remove monio's hooks right away, from any thread, even while the hook
+/// thread is stuck in a handler. Input then bypasses monio; the hook thread
+/// keeps running until stopped.
+#[cfg(feature = "failsafe")]
+pub(crate) fn emergency_release() {
+    GRAB_MODE.store(false, Ordering::SeqCst);
+    for raw in [&KEYBOARD_HOOK_RAW, &MOUSE_HOOK_RAW] {
+        let hook = raw.swap(std::ptr::null_mut(), Ordering::SeqCst);
+        if !hook.is_null() {
+            // SAFETY: the handle was published by `InstalledHook`, and taking
+            // it out of `raw` keeps anyone else from removing it again
+            unsafe {
+                let _ = UnhookWindowsHookEx(HHOOK(hook));
             }
         }
     }
+    log::warn!("Failsafe removed monio's input hooks");
+}
 
-    // Clean up handler
-    {
-        let mut h = HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = None;
+/// Stop the event hook, once its running flag has been cleared.
+pub fn stop_hook() -> Result<()> {
+    // A stop for a hook that never got the backend leaves the running one be
+    if !ACTIVE.stopping() {
+        return Ok(());
     }
+    if let Ok(thread_id) = THREAD_ID.lock()
+        && *thread_id != 0
     {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = None;
+        // SAFETY: plain message posting; fails harmlessly for a stale id
+        unsafe {
+            let _ = PostThreadMessageW(*thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        }
     }
-
     Ok(())
 }
 
-/// Run the event hook with grab capability (blocking).
-///
-/// Similar to `run_hook`, but allows the handler to consume events by returning `None`.
-pub fn run_grab_hook<H: GrabHandler + 'static>(
-    running: &Arc<AtomicBool>,
-    handler: H,
-) -> Result<()> {
-    // Store handler and stop flag
-    {
-        let mut h = GRAB_HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = Some(Box::new(handler));
-    }
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = Some(running.clone());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventType;
+    use windows::Win32::Foundation::POINT;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        WM_KEYDOWN, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE, WM_RBUTTONDOWN, WM_SYSKEYUP,
+        WM_XBUTTONDOWN,
+    };
+
+    fn key_down(vk: u32, time: u32) {
+        let kb = KBDLLHOOKSTRUCT {
+            vkCode: vk,
+            time,
+            ..Default::default()
+        };
+        // SAFETY: `lparam` points to a keyboard hook struct for the call
+        unsafe {
+            keyboard_callback(
+                HC_ACTION as i32,
+                WPARAM(WM_KEYDOWN as usize),
+                LPARAM(&kb as *const _ as isize),
+            );
+        }
     }
 
-    // Enable grab mode
-    GRAB_MODE.store(true, Ordering::SeqCst);
+    // Wheel rather than move events, which depend on the shared button mask
+    fn mouse_wheel(x: i32, time: u32) {
+        let mouse = MSLLHOOKSTRUCT {
+            pt: windows::Win32::Foundation::POINT { x, y: 0 },
+            mouseData: (convert::windows::WHEEL_DELTA as u32) << 16,
+            time,
+            ..Default::default()
+        };
+        // SAFETY: `lparam` points to a mouse hook struct for the call
+        unsafe {
+            mouse_callback(
+                HC_ACTION as i32,
+                WPARAM(WM_MOUSEWHEEL as usize),
+                LPARAM(&mouse as *const _ as isize),
+            );
+        }
+    }
 
-    // Store current thread ID for stopping
-    {
-        let mut tid = THREAD_ID
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *tid = unsafe { GetCurrentThreadId() };
+    #[test]
+    fn test_hook_structs_are_read_through_checked_pointers() {
+        let kb = KBDLLHOOKSTRUCT {
+            vkCode: 0x41,
+            time: 7,
+            flags: LLKHF_INJECTED,
+            dwExtraInfo: SYNTHETIC_TAG,
+            ..Default::default()
+        };
+        // SAFETY: `lparam` points to `kb`
+        let read = unsafe { read_hook_struct::<KBDLLHOOKSTRUCT>(LPARAM(&kb as *const _ as isize)) };
+        let read = read.unwrap();
+        assert_eq!((read.vkCode, read.time()), (0x41, 7));
+        assert!(read.injected() && read.is_synthetic());
+
+        let mouse = MSLLHOOKSTRUCT {
+            flags: LLMHF_INJECTED,
+            ..Default::default()
+        };
+        // SAFETY: `lparam` points to `mouse`
+        let read =
+            unsafe { read_hook_struct::<MSLLHOOKSTRUCT>(LPARAM(&mouse as *const _ as isize)) };
+        let read = read.unwrap();
+        assert!(read.injected() && !read.is_synthetic());
+
+        // Null and misaligned pointers are turned down before they are read
+        // SAFETY: null is never read
+        assert!(unsafe { read_hook_struct::<MSLLHOOKSTRUCT>(LPARAM(0)) }.is_none());
+        let words = [0u64; 5];
+        let misaligned = words.as_ptr().cast::<u8>().wrapping_add(1);
+        // SAFETY: a misaligned pointer is never read
+        let read = unsafe { read_hook_struct::<MSLLHOOKSTRUCT>(LPARAM(misaligned as isize)) };
+        assert!(read.is_none());
     }
 
-    // Set up keyboard hook
-    let keyboard_hook = unsafe {
-        SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_callback), None, 0)
-            .map_err(|e| Error::HookStartFailed(format!("Failed to set keyboard hook: {}", e)))?
-    };
-    {
-        let mut kh = KEYBOARD_HOOK
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *kh = Some(SendableHHOOK(keyboard_hook));
+    #[test]
+    fn test_misaligned_keyboard_struct_is_not_read() {
+        let words = [0u64; 4];
+        let misaligned = words.as_ptr().cast::<u8>().wrapping_add(2);
+        // SAFETY: a misaligned pointer is never read
+        let read = unsafe { read_hook_struct::<KBDLLHOOKSTRUCT>(LPARAM(misaligned as isize)) };
+        assert!(read.is_none());
+        // SAFETY: null is never read
+        assert!(unsafe { read_hook_struct::<KBDLLHOOKSTRUCT>(LPARAM(0)) }.is_none());
     }
 
-    // Set up mouse hook
-    let mouse_hook = unsafe {
-        SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_callback), None, 0)
-            .map_err(|e| Error::HookStartFailed(format!("Failed to set mouse hook: {}", e)))?
-    };
-    {
-        let mut mh = MOUSE_HOOK
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *mh = Some(SendableHHOOK(mouse_hook));
+    /// The keyboard fields as the callbacks read them before the checked
+    /// reads, straight through the pointer.
+    ///
+    /// # Safety
+    ///
+    /// `lparam` must point to a `KBDLLHOOKSTRUCT`.
+    unsafe fn unchecked_keyboard(wparam: WPARAM, lparam: LPARAM) -> convert::windows::RawKeyboard {
+        // SAFETY: the caller passes a keyboard hook struct
+        let kb = unsafe { *(lparam.0 as *const KBDLLHOOKSTRUCT) };
+        convert::windows::RawKeyboard {
+            message: wparam.0 as u32,
+            vk_code: kb.vkCode,
+            scan_code: kb.scanCode,
+            extended: kb.flags.contains(LLKHF_EXTENDED),
+        }
     }
 
-    // Send hook enabled event
-    {
-        if let Ok(guard) = GRAB_HANDLER.lock() {
-            if let Some(ref handler) = *guard {
-                let _ = handler.handle_event(&Event::hook_enabled());
-            }
+    /// The mouse fields as the callbacks read them before the checked reads.
+    ///
+    /// # Safety
+    ///
+    /// `lparam` must point to an `MSLLHOOKSTRUCT`.
+    unsafe fn unchecked_mouse(wparam: WPARAM, lparam: LPARAM) -> convert::windows::RawMouse {
+        // SAFETY: the caller passes a mouse hook struct
+        let mouse = unsafe { *(lparam.0 as *const MSLLHOOKSTRUCT) };
+        convert::windows::RawMouse {
+            message: wparam.0 as u32,
+            x: mouse.pt.x,
+            y: mouse.pt.y,
+            mouse_data: mouse.mouseData,
         }
     }
 
-    // Message loop
-    let mut msg = windows::Win32::UI::WindowsAndMessaging::MSG::default();
-    unsafe {
-        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
-            // Check stop flag
-            if let Ok(guard) = STOP_FLAG.lock() {
-                if let Some(ref flag) = *guard {
-                    if !flag.load(Ordering::SeqCst) {
-                        break;
-                    }
+    #[test]
+    fn test_fields_are_unchanged_by_checked_reads() {
+        let keys = [
+            (WM_KEYDOWN, 0x41, 0x1e, Default::default()),
+            (WM_SYSKEYUP, 0x12, 0x38, LLKHF_EXTENDED),
+            (WM_KEYDOWN, 0xff, 0xffff, LLKHF_INJECTED),
+        ];
+        for (message, vk_code, scan_code, flags) in keys {
+            let kb = KBDLLHOOKSTRUCT {
+                vkCode: vk_code,
+                scanCode: scan_code,
+                flags,
+                time: 42,
+                ..Default::default()
+            };
+            let (wparam, lparam) = (WPARAM(message as usize), LPARAM(&kb as *const _ as isize));
+            // SAFETY: `lparam` points to `kb`
+            let read = unsafe { read_hook_struct::<KBDLLHOOKSTRUCT>(lparam) }.unwrap();
+            let fields = keyboard_fields(wparam, &read);
+            // SAFETY: `lparam` points to `kb`
+            assert_eq!(fields, unsafe { unchecked_keyboard(wparam, lparam) });
+            assert_eq!(
+                fields,
+                convert::windows::RawKeyboard {
+                    message,
+                    vk_code,
+                    scan_code,
+                    extended: flags == LLKHF_EXTENDED,
                 }
-            }
+            );
         }
-    }
 
-    // Send hook disabled event
-    {
-        if let Ok(guard) = GRAB_HANDLER.lock() {
-            if let Some(ref handler) = *guard {
-                let _ = handler.handle_event(&Event::hook_disabled());
-            }
+        let moves = [
+            (WM_MOUSEMOVE, POINT { x: -1920, y: 1080 }, 0),
+            (WM_MOUSEWHEEL, POINT { x: 5, y: 6 }, 0xff88_0000),
+            (WM_XBUTTONDOWN, POINT { x: 0, y: 0 }, 0x0002_0000),
+        ];
+        for (message, pt, mouse_data) in moves {
+            let mouse = MSLLHOOKSTRUCT {
+                pt,
+                mouseData: mouse_data,
+                flags: LLMHF_INJECTED,
+                ..Default::default()
+            };
+            let (wparam, lparam) = (
+                WPARAM(message as usize),
+                LPARAM(&mouse as *const _ as isize),
+            );
+            // SAFETY: `lparam` points to `mouse`
+            let read = unsafe { read_hook_struct::<MSLLHOOKSTRUCT>(lparam) }.unwrap();
+            let fields = mouse_fields(wparam, &read);
+            // SAFETY: `lparam` points to `mouse`
+            assert_eq!(fields, unsafe { unchecked_mouse(wparam, lparam) });
+            assert_eq!(
+                fields,
+                convert::windows::RawMouse {
+                    message,
+                    x: pt.x,
+                    y: pt.y,
+                    mouse_data,
+                }
+            );
         }
     }
 
-    // Clean up hooks
-    unsafe {
-        if let Ok(mut kh) = KEYBOARD_HOOK.lock() {
-            if let Some(hook) = kh.take() {
-                let _ = UnhookWindowsHookEx(hook.0);
-            }
-        }
-        if let Ok(mut mh) = MOUSE_HOOK.lock() {
-            if let Some(hook) = mh.take() {
-                let _ = UnhookWindowsHookEx(hook.0);
+    #[test]
+    fn test_interleaved_hooks_deliver_in_native_order() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        HANDLER.set(Some(ActiveHandler::Listen(Box::new(
+            move |event: &Event| {
+                recorder
+                    .lock()
+                    .unwrap()
+                    .push((event.event_type, event.time));
+                // A handler pumping messages re-enters the other hook
+                if event.keyboard.as_ref().map(|kb| kb.raw_code) == Some(0x5A) {
+                    mouse_wheel(-1, 10_000);
+                }
+            },
+        ))));
+
+        // Ticks close to the wrap, interleaving keyboard and mouse
+        let start = u32::MAX - 20;
+        for i in 0..20u32 {
+            let tick = start.wrapping_add(i * 3);
+            if i % 2 == 0 {
+                key_down(0x41, tick);
+            } else {
+                mouse_wheel(i as i32, tick);
             }
         }
+        key_down(0x5A, start.wrapping_add(60));
+        key_down(0x41, start.wrapping_add(61));
+        HANDLER.set(None);
+
+        let seen = seen.lock().unwrap();
+        let types: Vec<EventType> = seen.iter().map(|(t, _)| *t).collect();
+        let mut expected: Vec<EventType> = (0..20)
+            .map(|i| {
+                if i % 2 == 0 {
+                    EventType::KeyPressed
+                } else {
+                    EventType::MouseWheel
+                }
+            })
+            .collect();
+        // The re-entrant wheel event arrives after the key that triggered it
+        expected.extend([
+            EventType::KeyPressed,
+            EventType::MouseWheel,
+            EventType::KeyPressed,
+        ]);
+        assert_eq!(types, expected);
+
+        assert!(seen.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        let span = seen[19].1.duration_since(seen[0].1).unwrap();
+        assert_eq!(span, Duration::from_millis(57));
     }
 
-    // Clean up
-    GRAB_MODE.store(false, Ordering::SeqCst);
-    {
-        let mut h = GRAB_HANDLER
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *h = None;
-    }
-    {
-        let mut s = STOP_FLAG
-            .lock()
-            .map_err(|_| Error::ThreadError("mutex poisoned".into()))?;
-        *s = None;
+    #[test]
+    fn test_event_clock_rebases_on_drift() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut clock = EventClock::new();
+        assert_eq!(clock.stamp(500, now), now);
+        assert_eq!(clock.stamp(520, now), now + Duration::from_millis(20));
+        // Slightly older native time doesn't go backwards
+        assert_eq!(clock.stamp(510, now), now + Duration::from_millis(20));
+
+        // After a long gap (e.g. sleep), follow the wall clock again
+        let later = now + Duration::from_secs(60);
+        assert_eq!(clock.stamp(600, later), later);
     }
 
-    Ok(())
-}
-
-/// Stop the event hook.
-pub fn stop_hook() -> Result<()> {
-    if let Ok(thread_id) = THREAD_ID.lock() {
-        if *thread_id != 0 {
-            unsafe {
-                let _ = PostThreadMessageW(*thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
-            }
+    #[test]
+    fn test_wheel_callback_only_delivers_wheel() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        HANDLER.set(Some(ActiveHandler::Grab(Box::new(move |event: &Event| {
+            recorder.lock().unwrap().push(event.event_type);
+            None
+        }))));
+
+        let mouse = MSLLHOOKSTRUCT {
+            mouseData: (convert::windows::WHEEL_DELTA as u32) << 16,
+            ..Default::default()
+        };
+        // SAFETY: `lparam` points to a mouse hook struct for each call
+        let call = |msg: u32| unsafe {
+            wheel_callback(
+                HC_ACTION as i32,
+                WPARAM(msg as usize),
+                LPARAM(&mouse as *const _ as isize),
+            )
+        };
+        for msg in [WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE, WM_RBUTTONDOWN] {
+            assert_eq!(call(msg), LRESULT(0), "passed on");
         }
+        assert_eq!(call(WM_MOUSEWHEEL), LRESULT(1), "consumed");
+        HANDLER.set(None);
+
+        assert_eq!(*seen.lock().unwrap(), [EventType::MouseWheel]);
     }
-    Ok(())
 }
@@ -1,13 +1,86 @@
 //! Windows platform implementation using SetWindowsHookEx.
 
+mod cursor;
 mod display;
+#[cfg(feature = "failsafe")]
+mod failsafe;
+mod ime;
 mod keycodes;
+mod layout;
 mod listen;
+#[cfg(feature = "pointer")]
+mod precision;
 mod simulate;
+#[cfg(feature = "windows-targeted")]
+mod target;
 
+pub(crate) use cursor::{confine_cursor, hide_cursor, release_cursor, show_cursor, warp_cursor};
 pub use display::{display_at_point, displays, primary_display, system_settings};
+#[cfg(feature = "failsafe")]
+pub(crate) use failsafe::KeyProbe;
+pub(crate) use ime::ime_composing;
+pub(crate) use layout::{KEYCODES, key_for_code, logical_key, physical_key};
+#[cfg(feature = "failsafe")]
+pub(crate) use listen::emergency_release;
 pub use listen::{run_grab_hook, run_hook, stop_hook};
+pub(crate) use listen::{set_event_mask, set_scroll_only};
+pub(crate) use simulate::{held_buttons, inject_synthetic, lock_states, reinject};
 pub use simulate::{
     key_press, key_release, key_tap, mouse_click, mouse_move, mouse_position, mouse_press,
     mouse_release, simulate,
 };
+#[cfg(feature = "windows-targeted")]
+pub use target::{WindowHandle, WindowTarget, find_window, send_to_window};
+
+/// Grab hooks stay installed while grabbing is disabled; the handler wrapper
+/// passes every event through.
+pub(crate) fn set_grab_enabled(_enabled: bool) {}
+
+/// Touch input reaches the low-level hooks as mouse events.
+pub(crate) fn set_touch_as_mouse(_enabled: bool) {}
+
+/// Digitizers are calibrated by the system (Tablet PC settings).
+pub(crate) fn set_input_transforms(_transforms: Vec<crate::calibration::DeviceTransform>) {}
+
+/// Whether grab mode can keep events from reaching other applications.
+pub(crate) const GRAB_SUPPRESSES: bool = true;
+
+/// Whether events carry the OS's timestamps rather than the time monio saw
+/// them, which makes their delivery latency measurable.
+pub(crate) const NATIVE_TIMESTAMPS: bool = true;
+
+/// Name of the input backend, recorded in recording metadata.
+#[cfg_attr(not(feature = "recorder"), allow(dead_code))]
+pub(crate) const BACKEND: &str = "windows-hooks";
+
+/// Whether pointer positions are absolute screen coordinates.
+pub(crate) const ABSOLUTE_POSITION: bool = true;
+
+/// Whether pointer positions are in points rather than physical pixels.
+pub(crate) const POSITIONS_IN_POINTS: bool = false;
+
+/// Whether each input device is read on its own.
+pub(crate) const PER_DEVICE: bool = false;
+
+/// Whether `Key::Char` keys the layout has no key for can be simulated.
+pub(crate) const SIMULATES_UNICODE: bool = false;
+
+/// Where display information comes from.
+pub(crate) const DISPLAY_SOURCE: Option<crate::display::DisplaySource> =
+    Some(crate::display::DisplaySource::WindowSystem);
+
+/// There is no Accessibility permission to grant.
+pub(crate) fn accessibility_status() -> crate::permissions::AccessibilityStatus {
+    crate::permissions::AccessibilityStatus::Granted
+}
+
+/// There is no Accessibility permission to request.
+pub(crate) fn request_accessibility() -> bool {
+    true
+}
+
+pub(crate) fn open_accessibility_settings() -> crate::error::Result<()> {
+    Err(crate::error::Error::NotSupported(
+        "Accessibility settings only exist on macOS".into(),
+    ))
+}
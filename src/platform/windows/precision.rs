@@ -0,0 +1,555 @@
+//! Precision touchpad scrolling through raw input (`pointer` feature).
+//!
+//! The low-level mouse hook only sees touchpad scrolling as coarse
+//! `WM_MOUSEWHEEL` ticks. Listen hooks additionally register a message-only
+//! window for raw input from precision touchpads (HID digitizer usage page
+//! `0x0D`, usage `0x05`), follow two-finger pans from the contacts, and
+//! report them as wheel events with [`WheelData::pixel_delta`] and
+//! [`WheelData::phase`]. While a pan is reported that way, and briefly after
+//! it, the hook's wheel ticks for the same gesture are dropped. Inertia
+//! after the fingers lift only comes as ticks, and is reported as usual.
+//!
+//! Precision touchpads need Windows 8.1 or later. Touchpads with legacy
+//! drivers don't report contacts through raw input; their scrolling keeps
+//! arriving as ticks only. Pans follow the system default of content moving
+//! with the fingers: fingers moving down scroll up.
+//!
+//! [`WheelData::pixel_delta`]: crate::event::WheelData::pixel_delta
+//! [`WheelData::phase`]: crate::event::WheelData::phase
+
+use crate::event::{Event, ScrollDirection, ScrollPhase};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    HIDP_CAPS, HIDP_STATUS_SUCCESS, HIDP_VALUE_CAPS, HidP_GetCaps, HidP_GetUsageValue,
+    HidP_GetUsages, HidP_GetValueCaps, HidP_Input, PHIDP_PREPARSED_DATA,
+};
+use windows::Win32::Foundation::{HANDLE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::UI::Input::{
+    GetRawInputData, GetRawInputDeviceInfoW, HRAWINPUT, RAWINPUT, RAWINPUTDEVICE, RAWINPUTHEADER,
+    RID_INPUT, RIDEV_INPUTSINK, RIDEV_REMOVE, RIDI_PREPARSEDDATA, RIM_TYPEHID,
+    RegisterRawInputDevices,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, HWND_MESSAGE, RegisterClassW, WINDOW_EX_STYLE,
+    WINDOW_STYLE, WNDCLASSW,
+};
+use windows::core::w;
+
+/// Pixels per millimetre of finger travel: 1:1 at 96 DPI.
+const PIXELS_PER_MM: f64 = 96.0 / 25.4;
+
+/// Pixels per wheel notch (three 16-pixel lines), for [`WheelData::delta`].
+///
+/// [`WheelData::delta`]: crate::event::WheelData::delta
+const PIXELS_PER_NOTCH: f64 = 48.0;
+
+/// How long after a pan (in `GetTickCount` milliseconds) hook wheel ticks
+/// are still attributed to it.
+const TICK_GRACE_MS: u32 = 150;
+
+const PAGE_GENERIC_DESKTOP: u16 = 0x01;
+const PAGE_DIGITIZER: u16 = 0x0D;
+const USAGE_X: u16 = 0x30;
+const USAGE_Y: u16 = 0x31;
+const USAGE_TOUCHPAD: u16 = 0x05;
+const USAGE_TIP_SWITCH: u16 = 0x42;
+const USAGE_CONTACT_COUNT: u16 = 0x54;
+
+/// HID unit codes for lengths in the SI and English systems.
+const UNIT_CENTIMETER: u32 = 0x11;
+const UNIT_INCH: u32 = 0x13;
+
+/// Size of a HID axis, to turn positions into millimetres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Axis {
+    logical_min: i32,
+    /// Millimetres per logical unit.
+    mm_per_unit: f64,
+}
+
+impl Axis {
+    /// From a value's logical and physical ranges. `exponent` is the HID
+    /// unit exponent, a 4-bit two's complement number.
+    fn new(logical: (i32, i32), physical: (i32, i32), unit: u32, exponent: u32) -> Option<Self> {
+        let units = f64::from(logical.1 - logical.0);
+        if units <= 0.0 {
+            return None;
+        }
+        let exponent = ((exponent as i32 & 0xF) ^ 0x8) - 0x8;
+        let mm_per_physical = match unit {
+            UNIT_CENTIMETER => 10.0,
+            UNIT_INCH => 25.4,
+            _ => return None,
+        } * 10f64.powi(exponent);
+        let size = f64::from(physical.1 - physical.0) * mm_per_physical;
+        (size > 0.0).then(|| Self {
+            logical_min: logical.0,
+            mm_per_unit: size / units,
+        })
+    }
+
+    fn to_mm(self, value: i32) -> f64 {
+        f64::from(value - self.logical_min) * self.mm_per_unit
+    }
+}
+
+/// Turns the fingers on a touchpad into scroll events.
+#[derive(Debug, Default)]
+pub(crate) struct PanTracker {
+    /// Midpoint of the two fingers, in millimetres.
+    midpoint: Option<(f64, f64)>,
+    panning: bool,
+    /// Direction of the last event, repeated when the pan ends.
+    direction: Option<ScrollDirection>,
+    /// When the pan last moved or ended.
+    last_tick: Option<u32>,
+}
+
+impl PanTracker {
+    /// Feed the positions of the fingers down, in millimetres, at native
+    /// time `tick`. Returns wheel events at the cursor position `(x, y)`.
+    pub(crate) fn update(
+        &mut self,
+        fingers: &[(f64, f64)],
+        tick: u32,
+        (x, y): (f64, f64),
+    ) -> Vec<Event> {
+        let [a, b] = fingers else {
+            self.midpoint = None;
+            if !std::mem::take(&mut self.panning) {
+                return Vec::new();
+            }
+            self.last_tick = Some(tick);
+            let direction = self.direction.unwrap_or(ScrollDirection::Up);
+            return vec![pan_event(x, y, direction, 0.0, ScrollPhase::Ended)];
+        };
+        let midpoint = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+        let Some(last) = self.midpoint.replace(midpoint) else {
+            return Vec::new();
+        };
+
+        // Content follows the fingers
+        let dy = (midpoint.1 - last.1) * PIXELS_PER_MM;
+        let dx = (midpoint.0 - last.0) * PIXELS_PER_MM;
+        let mut events = Vec::new();
+        for (pixels, positive, negative) in [
+            (dy, ScrollDirection::Up, ScrollDirection::Down),
+            (dx, ScrollDirection::Left, ScrollDirection::Right),
+        ] {
+            if pixels == 0.0 {
+                continue;
+            }
+            let direction = if pixels > 0.0 { positive } else { negative };
+            let phase = if std::mem::replace(&mut self.panning, true) {
+                ScrollPhase::Changed
+            } else {
+                ScrollPhase::Began
+            };
+            self.direction = Some(direction);
+            self.last_tick = Some(tick);
+            events.push(pan_event(x, y, direction, pixels.abs(), phase));
+        }
+        events
+    }
+
+    /// Whether hook wheel ticks at native time `tick` belong to a pan
+    /// already reported.
+    pub(crate) fn covers(&self, tick: u32) -> bool {
+        self.panning
+            || self
+                .last_tick
+                .is_some_and(|last| tick.wrapping_sub(last) <= TICK_GRACE_MS)
+    }
+}
+
+fn pan_event(x: f64, y: f64, direction: ScrollDirection, pixels: f64, phase: ScrollPhase) -> Event {
+    let mut event = Event::mouse_wheel(x, y, direction, pixels / PIXELS_PER_NOTCH);
+    if let Some(wheel) = &mut event.wheel {
+        wheel.pixel_delta = Some(pixels);
+        wheel.phase = Some(phase);
+    }
+    event
+}
+
+/// A touchpad's report layout.
+struct Device {
+    preparsed: Vec<u8>,
+    /// Link collections of the fingers, with their axes.
+    fingers: Vec<(u16, Axis, Axis)>,
+}
+
+impl Device {
+    fn preparsed(&self) -> PHIDP_PREPARSED_DATA {
+        PHIDP_PREPARSED_DATA(self.preparsed.as_ptr() as isize)
+    }
+
+    /// Read the layout of the device behind a raw input handle.
+    fn open(handle: HANDLE) -> Option<Self> {
+        let mut size = 0u32;
+        unsafe { GetRawInputDeviceInfoW(Some(handle), RIDI_PREPARSEDDATA, None, &mut size) };
+        let mut preparsed = vec![0u8; size as usize];
+        let read = unsafe {
+            GetRawInputDeviceInfoW(
+                Some(handle),
+                RIDI_PREPARSEDDATA,
+                Some(preparsed.as_mut_ptr().cast()),
+                &mut size,
+            )
+        };
+        if read == u32::MAX || read == 0 {
+            return None;
+        }
+        let data = PHIDP_PREPARSED_DATA(preparsed.as_ptr() as isize);
+
+        let mut caps = HIDP_CAPS::default();
+        if unsafe { HidP_GetCaps(data, &mut caps) } != HIDP_STATUS_SUCCESS {
+            return None;
+        }
+        let mut count = caps.NumberInputValueCaps;
+        let mut values = vec![HIDP_VALUE_CAPS::default(); count as usize];
+        if unsafe { HidP_GetValueCaps(HidP_Input, values.as_mut_ptr(), &mut count, data) }
+            != HIDP_STATUS_SUCCESS
+        {
+            return None;
+        }
+        values.truncate(count as usize);
+
+        let axis = |value: &HIDP_VALUE_CAPS| {
+            Axis::new(
+                (value.LogicalMin, value.LogicalMax),
+                (value.PhysicalMin, value.PhysicalMax),
+                value.Units,
+                value.UnitsExp,
+            )
+        };
+        let usage_of = |value: &HIDP_VALUE_CAPS| {
+            // Both union members are plain data, so either reads fine
+            (!value.IsRange && value.UsagePage == PAGE_GENERIC_DESKTOP)
+                .then_some(unsafe { value.Anonymous.NotRange.Usage })
+        };
+        let mut fingers = Vec::new();
+        for x in values.iter().filter(|v| usage_of(v) == Some(USAGE_X)) {
+            let y = values
+                .iter()
+                .find(|v| v.LinkCollection == x.LinkCollection && usage_of(v) == Some(USAGE_Y));
+            if let (Some(x_axis), Some(y_axis)) = (axis(x), y.and_then(axis)) {
+                fingers.push((x.LinkCollection, x_axis, y_axis));
+            }
+        }
+        (!fingers.is_empty()).then_some(Self { preparsed, fingers })
+    }
+
+    fn value(&self, report: &[u8], page: u16, collection: u16, usage: u16) -> Option<u32> {
+        let mut value = 0u32;
+        let status = unsafe {
+            HidP_GetUsageValue(
+                HidP_Input,
+                page,
+                Some(collection),
+                usage,
+                &mut value,
+                self.preparsed(),
+                report,
+            )
+        };
+        (status == HIDP_STATUS_SUCCESS).then_some(value)
+    }
+
+    fn tip_down(&self, report: &mut [u8], collection: u16) -> bool {
+        let mut usages = [0u16; 16];
+        let mut count = usages.len() as u32;
+        let status = unsafe {
+            HidP_GetUsages(
+                HidP_Input,
+                PAGE_DIGITIZER,
+                Some(collection),
+                usages.as_mut_ptr(),
+                &mut count,
+                self.preparsed(),
+                report,
+            )
+        };
+        status == HIDP_STATUS_SUCCESS && usages[..count as usize].contains(&USAGE_TIP_SWITCH)
+    }
+}
+
+/// Contacts of one frame, which hybrid-mode touchpads spread over several
+/// reports.
+#[derive(Default)]
+struct Frame {
+    expected: usize,
+    seen: usize,
+    fingers: Vec<(f64, f64)>,
+}
+
+/// Raw input state of the hook thread.
+struct Touchpads {
+    devices: HashMap<isize, Option<Device>>,
+    frame: Frame,
+    pan: PanTracker,
+}
+
+thread_local! {
+    static TOUCHPADS: RefCell<Option<Touchpads>> = const { RefCell::new(None) };
+}
+
+/// Raw input registration for the hook thread, removed on drop.
+pub(crate) struct Registration {
+    hwnd: HWND,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let device = RAWINPUTDEVICE {
+            usUsagePage: PAGE_DIGITIZER,
+            usUsage: USAGE_TOUCHPAD,
+            dwFlags: RIDEV_REMOVE,
+            hwndTarget: HWND::default(),
+        };
+        unsafe {
+            let _ = RegisterRawInputDevices(&[device], size_of::<RAWINPUTDEVICE>() as u32);
+            let _ = DestroyWindow(self.hwnd);
+        }
+        TOUCHPADS.set(None);
+    }
+}
+
+/// Window procedure of the message-only window: `WM_INPUT` is taken from
+/// the hook thread's message loop, everything else gets the default.
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+}
+
+/// Start receiving touchpad contacts on this thread, as `WM_INPUT` messages
+/// for [`handle_input`].
+pub(crate) fn register() -> windows::core::Result<Registration> {
+    let class = WNDCLASSW {
+        lpfnWndProc: Some(window_proc),
+        lpszClassName: w!("monio_touchpad"),
+        ..Default::default()
+    };
+    // Fails harmlessly once the class exists
+    unsafe { RegisterClassW(&class) };
+    let hwnd = unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE(0),
+            w!("monio_touchpad"),
+            w!(""),
+            WINDOW_STYLE(0),
+            0,
+            0,
+            0,
+            0,
+            Some(HWND_MESSAGE),
+            None,
+            None,
+            None,
+        )
+    }?;
+    let device = RAWINPUTDEVICE {
+        usUsagePage: PAGE_DIGITIZER,
+        usUsage: USAGE_TOUCHPAD,
+        dwFlags: RIDEV_INPUTSINK,
+        hwndTarget: hwnd,
+    };
+    let registration = Registration { hwnd };
+    unsafe { RegisterRawInputDevices(&[device], size_of::<RAWINPUTDEVICE>() as u32) }?;
+    TOUCHPADS.set(Some(Touchpads {
+        devices: HashMap::new(),
+        frame: Frame::default(),
+        pan: PanTracker::default(),
+    }));
+    Ok(registration)
+}
+
+/// Whether hook wheel ticks at native time `tick` duplicate a pan.
+pub(crate) fn covers(tick: u32) -> bool {
+    TOUCHPADS.with_borrow(|touchpads| {
+        touchpads
+            .as_ref()
+            .is_some_and(|touchpads| touchpads.pan.covers(tick))
+    })
+}
+
+/// Convert a `WM_INPUT` message into wheel events.
+pub(crate) fn handle_input(lparam: LPARAM, tick: u32) -> Vec<Event> {
+    let Some(mut data) = read_input(lparam) else {
+        return Vec::new();
+    };
+    TOUCHPADS.with_borrow_mut(|touchpads| {
+        let Some(touchpads) = touchpads else {
+            return Vec::new();
+        };
+        // SAFETY: `read_input` checked the buffer holds a HID RAWINPUT
+        let raw = unsafe { &*(data.as_ptr() as *const RAWINPUT) };
+        let handle = raw.header.hDevice;
+        let hid = unsafe { raw.data.hid };
+        let device = touchpads
+            .devices
+            .entry(handle.0 as isize)
+            .or_insert_with(|| Device::open(handle));
+        let Some(device) = device else {
+            return Vec::new();
+        };
+
+        let offset = size_of::<RAWINPUTHEADER>() + 2 * size_of::<u32>();
+        let size = hid.dwSizeHid as usize;
+        let cursor = super::simulate::mouse_position().unwrap_or_default();
+        let mut events = Vec::new();
+        for i in 0..hid.dwCount as usize {
+            let start = offset + i * size;
+            let Some(report) = data.get_mut(start..start + size) else {
+                break;
+            };
+            let frame = &mut touchpads.frame;
+            if let Some(count) = device.value(report, PAGE_DIGITIZER, 0, USAGE_CONTACT_COUNT)
+                && count > 0
+            {
+                *frame = Frame {
+                    expected: count as usize,
+                    ..Frame::default()
+                };
+            }
+            for &(collection, x_axis, y_axis) in &device.fingers {
+                if frame.seen >= frame.expected {
+                    break;
+                }
+                frame.seen += 1;
+                if !device.tip_down(report, collection) {
+                    continue;
+                }
+                let x = device.value(report, PAGE_GENERIC_DESKTOP, collection, USAGE_X);
+                let y = device.value(report, PAGE_GENERIC_DESKTOP, collection, USAGE_Y);
+                if let (Some(x), Some(y)) = (x, y) {
+                    frame
+                        .fingers
+                        .push((x_axis.to_mm(x as i32), y_axis.to_mm(y as i32)));
+                }
+            }
+            if frame.expected > 0 && frame.seen >= frame.expected {
+                let fingers = std::mem::take(frame).fingers;
+                events.extend(touchpads.pan.update(&fingers, tick, cursor));
+            }
+        }
+        events
+    })
+}
+
+/// The `RAWINPUT` of a `WM_INPUT` message, if it came from a HID device.
+fn read_input(lparam: LPARAM) -> Option<Vec<u8>> {
+    let input = HRAWINPUT(lparam.0 as _);
+    let header = size_of::<RAWINPUTHEADER>() as u32;
+    let mut size = 0u32;
+    unsafe { GetRawInputData(input, RID_INPUT, None, &mut size, header) };
+    let mut data = vec![0u8; size as usize];
+    let read = unsafe {
+        GetRawInputData(
+            input,
+            RID_INPUT,
+            Some(data.as_mut_ptr().cast()),
+            &mut size,
+            header,
+        )
+    };
+    if read == u32::MAX || (read as usize) < size_of::<RAWINPUTHEADER>() {
+        return None;
+    }
+    // SAFETY: the buffer holds at least a header
+    let kind = unsafe { (*(data.as_ptr() as *const RAWINPUTHEADER)).dwType };
+    (kind == RIM_TYPEHID.0).then_some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventType;
+
+    fn wheels(events: &[Event]) -> Vec<(ScrollDirection, f64, ScrollPhase)> {
+        events
+            .iter()
+            .map(|e| {
+                assert_eq!(e.event_type, EventType::MouseWheel);
+                let wheel = e.wheel.as_ref().unwrap();
+                let pixels = (wheel.pixel_delta.unwrap() * 100.0).round() / 100.0;
+                (wheel.direction, pixels, wheel.phase.unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_axis_units() {
+        // 10.5 cm over 0..=1050, exponent -2 (0xE)
+        let axis = Axis::new((0, 1050), (0, 1050), UNIT_CENTIMETER, 0xE).unwrap();
+        assert!((axis.mm_per_unit - 0.1).abs() < 1e-9);
+        assert!((axis.to_mm(500) - 50.0).abs() < 1e-9);
+
+        // 4 inches over 0..=4000, exponent -3 (0xD)
+        let axis = Axis::new((0, 4000), (0, 4000), UNIT_INCH, 0xD).unwrap();
+        assert!((axis.to_mm(1000) - 25.4).abs() < 1e-9);
+
+        assert_eq!(Axis::new((0, 0), (0, 10), UNIT_CENTIMETER, 0), None);
+        assert_eq!(Axis::new((0, 100), (0, 10), 0x12, 0), None);
+    }
+
+    #[test]
+    fn test_two_finger_pan_phases() {
+        let mut pan = PanTracker::default();
+        let at = (10.0, 20.0);
+        // One finger moves the cursor, not the wheel
+        assert!(pan.update(&[(10.0, 10.0)], 0, at).is_empty());
+        assert!(pan.update(&[(10.0, 10.0), (30.0, 10.0)], 10, at).is_empty());
+        assert!(!pan.covers(10));
+
+        // Fingers move down 2.54 mm: 9.6 pixels up
+        let began = pan.update(&[(10.0, 12.54), (30.0, 12.54)], 20, at);
+        assert_eq!(
+            wheels(&began),
+            [(ScrollDirection::Up, 9.6, ScrollPhase::Began)]
+        );
+        assert_eq!(
+            began[0].wheel.as_ref().unwrap().delta,
+            9.6 / PIXELS_PER_NOTCH
+        );
+        assert_eq!(began[0].wheel.as_ref().unwrap().x, 10.0);
+
+        // Then up and to the right
+        let changed = pan.update(&[(12.54, 10.0), (32.54, 10.0)], 30, at);
+        assert_eq!(
+            wheels(&changed),
+            [
+                (ScrollDirection::Down, 9.6, ScrollPhase::Changed),
+                (ScrollDirection::Left, 9.6, ScrollPhase::Changed),
+            ]
+        );
+        assert!(pan.covers(30));
+
+        let ended = pan.update(&[], 40, at);
+        assert_eq!(
+            wheels(&ended),
+            [(ScrollDirection::Left, 0.0, ScrollPhase::Ended)]
+        );
+        assert!(pan.update(&[], 50, at).is_empty());
+    }
+
+    #[test]
+    fn test_ticks_deduplicated_around_pans() {
+        let mut pan = PanTracker::default();
+        let at = (0.0, 0.0);
+        pan.update(&[(0.0, 0.0), (10.0, 0.0)], 1000, at);
+        pan.update(&[(0.0, 1.0), (10.0, 1.0)], 1010, at);
+        assert!(pan.covers(1015));
+        pan.update(&[], 1100, at);
+        // Ticks trailing the lift still belong to the pan, inertia doesn't
+        assert!(pan.covers(1100 + TICK_GRACE_MS));
+        assert!(!pan.covers(1100 + TICK_GRACE_MS + 1));
+
+        // A fresh tracker claims nothing
+        assert!(!PanTracker::default().covers(0));
+    }
+}
@@ -3,23 +3,39 @@
 use crate::error::{Error, Result};
 use crate::event::{Button, Event, EventType};
 use crate::keycode::Key;
+use crate::snapshot::LockStates;
+use std::cell::Cell;
 use std::mem::size_of;
-use windows::Win32::Foundation::POINT;
+use windows::Win32::Foundation::{LPARAM, POINT, WPARAM};
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT, KEYEVENTF_KEYUP, MOUSE_EVENT_FLAGS,
+    GetAsyncKeyState, GetKeyState, VK_CAPITAL, VK_LBUTTON, VK_MBUTTON, VK_NUMLOCK, VK_RBUTTON,
+    VK_SCROLL, VK_XBUTTON1, VK_XBUTTON2,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBD_EVENT_FLAGS, KEYBDINPUT,
+    KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, MAPVK_VK_TO_VSC, MOUSE_EVENT_FLAGS,
     MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
     MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN,
     MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN,
-    MOUSEEVENTF_XUP, MOUSEINPUT, SendInput, VIRTUAL_KEY,
+    MOUSEEVENTF_XUP, MOUSEINPUT, MapVirtualKeyW, SendInput, VIRTUAL_KEY,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
-    GetCursorPos, GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
+    GetCursorPos, GetForegroundWindow, GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
+    SM_SWAPBUTTON, SendMessageW, WM_APPCOMMAND,
 };
 
-use super::keycodes::key_to_keycode;
+use super::layout::resolve_keycode;
 
 const WHEEL_DELTA: u32 = 120;
 
+/// `dwExtraInfo` marker for events injected by [`inject_synthetic`].
+pub(crate) const SYNTHETIC_TAG: usize = 0x6D6F_6E69; // "moni"
+
+thread_local! {
+    /// `dwExtraInfo` stamped on inputs sent from this thread.
+    static EXTRA_INFO: Cell<usize> = const { Cell::new(0) };
+}
+
 /// Get current mouse position as (x, y) coordinates.
 pub fn mouse_position() -> Result<(f64, f64)> {
     let mut point = POINT { x: 0, y: 0 };
@@ -30,6 +46,40 @@ pub fn mouse_position() -> Result<(f64, f64)> {
     Ok((point.x as f64, point.y as f64))
 }
 
+/// Lock key states, from the toggle bit of the keys' state.
+pub(crate) fn lock_states() -> LockStates {
+    let toggled = |key: VIRTUAL_KEY| unsafe { GetKeyState(key.0 as i32) } & 1 != 0;
+    LockStates {
+        caps_lock: toggled(VK_CAPITAL),
+        num_lock: toggled(VK_NUMLOCK),
+        scroll_lock: toggled(VK_SCROLL),
+    }
+}
+
+/// Mouse buttons held, as a `MASK_BUTTON*` mask.
+pub(crate) fn held_buttons() -> Option<u32> {
+    let down = |key: VIRTUAL_KEY| unsafe { GetAsyncKeyState(key.0 as i32) } < 0;
+    // GetAsyncKeyState reports physical buttons, hooks report logical ones
+    let (left, right) = if unsafe { GetSystemMetrics(SM_SWAPBUTTON) } != 0 {
+        (VK_RBUTTON, VK_LBUTTON)
+    } else {
+        (VK_LBUTTON, VK_RBUTTON)
+    };
+    let mut held = 0;
+    for (key, mask) in [
+        (left, crate::state::MASK_BUTTON1),
+        (right, crate::state::MASK_BUTTON2),
+        (VK_MBUTTON, crate::state::MASK_BUTTON3),
+        (VK_XBUTTON1, crate::state::MASK_BUTTON4),
+        (VK_XBUTTON2, crate::state::MASK_BUTTON5),
+    ] {
+        if down(key) {
+            held |= mask;
+        }
+    }
+    Some(held)
+}
+
 /// Send a mouse event
 fn sim_mouse_event(flags: MOUSE_EVENT_FLAGS, data: u32, dx: i32, dy: i32) -> Result<()> {
     let input = INPUT {
@@ -41,7 +91,7 @@ fn sim_mouse_event(flags: MOUSE_EVENT_FLAGS, data: u32, dx: i32, dy: i32) -> Res
                 mouseData: data,
                 dwFlags: flags,
                 time: 0,
-                dwExtraInfo: 0,
+                dwExtraInfo: EXTRA_INFO.get(),
             },
         },
     };
@@ -60,9 +110,34 @@ fn sim_mouse_event(flags: MOUSE_EVENT_FLAGS, data: u32, dx: i32, dy: i32) -> Res
 
 /// Send a keyboard event
 fn sim_keyboard_event(vk: u16, flags: u32) -> Result<()> {
-    let mut dwflags = windows::Win32::UI::Input::KeyboardAndMouse::KEYBD_EVENT_FLAGS(0);
-    if flags != 0 {
-        dwflags = KEYEVENTF_KEYUP;
+    send_key(vk, 0, flags != 0, is_extended_vk(vk))
+}
+
+/// Whether a virtual key is sent with the extended-key flag by a standard
+/// keyboard. Without the flag, arrows and friends turn into their numpad
+/// twins and some players ignore media keys.
+fn is_extended_vk(vk: u16) -> bool {
+    matches!(
+        vk,
+        0x21..=0x28 // Page Up/Down, End, Home, arrows
+            | 0x2C..=0x2E // Print Screen, Insert, Delete
+            | 0x5B..=0x5D // Windows keys, Apps
+            | 0x6F // Numpad Divide
+            | 0x90 // Num Lock
+            | 0xA3 // Right Ctrl
+            | 0xA5 // Right Alt
+            | 0xA6..=0xB7 // Browser, volume, media and launch keys
+    )
+}
+
+/// Send a keyboard event with an explicit scan code and extended-key flag
+fn send_key(vk: u16, scan: u16, up: bool, extended: bool) -> Result<()> {
+    let mut dwflags = KEYBD_EVENT_FLAGS(0);
+    if up {
+        dwflags |= KEYEVENTF_KEYUP;
+    }
+    if extended {
+        dwflags |= KEYEVENTF_EXTENDEDKEY;
     }
 
     let input = INPUT {
@@ -70,10 +145,10 @@ fn sim_keyboard_event(vk: u16, flags: u32) -> Result<()> {
         Anonymous: INPUT_0 {
             ki: KEYBDINPUT {
                 wVk: VIRTUAL_KEY(vk),
-                wScan: 0,
+                wScan: scan,
                 dwFlags: dwflags,
                 time: 0,
-                dwExtraInfo: 0,
+                dwExtraInfo: EXTRA_INFO.get(),
             },
         },
     };
@@ -90,6 +165,82 @@ fn sim_keyboard_event(vk: u16, flags: u32) -> Result<()> {
     }
 }
 
+/// The `APPCOMMAND_*` value for a media key.
+fn media_app_command(key: Key) -> Option<u32> {
+    Some(match key {
+        Key::VolumeMute => 8,
+        Key::VolumeDown => 9,
+        Key::VolumeUp => 10,
+        Key::MediaNext => 11,
+        Key::MediaPrevious => 12,
+        Key::MediaStop => 13,
+        Key::MediaPlayPause => 14,
+        _ => return None,
+    })
+}
+
+/// Send a media key as `WM_APPCOMMAND` to the foreground window.
+///
+/// Used when `SendInput` is blocked, e.g. by UIPI. Windows passes unhandled
+/// app commands on to the shell, which adjusts the volume or the player.
+fn send_app_command(command: u32) -> Result<()> {
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_invalid() {
+            return Err(Error::SimulateFailed(
+                "No foreground window for WM_APPCOMMAND".into(),
+            ));
+        }
+        SendMessageW(
+            hwnd,
+            WM_APPCOMMAND,
+            Some(WPARAM(hwnd.0 as usize)),
+            Some(LPARAM((command << 16) as isize)),
+        );
+    }
+    Ok(())
+}
+
+/// Inject an event tagged as synthetic, so the grab hook passes it through
+/// without consulting the handler.
+pub(crate) fn inject_synthetic(event: &Event) -> Result<()> {
+    EXTRA_INFO.set(SYNTHETIC_TAG);
+    let result = simulate(event);
+    EXTRA_INFO.set(0);
+    result
+}
+
+/// Re-post a captured event as the hook saw it, tagged as synthetic.
+///
+/// Keys keep their virtual key and extended-key flag and get the matching
+/// scan code; button events first move the cursor back to where they
+/// happened.
+pub(crate) fn reinject(event: &Event) -> Result<()> {
+    EXTRA_INFO.set(SYNTHETIC_TAG);
+    let result = repost(event);
+    EXTRA_INFO.set(0);
+    result
+}
+
+fn repost(event: &Event) -> Result<()> {
+    match (event.event_type, &event.keyboard, &event.mouse) {
+        (EventType::KeyPressed | EventType::KeyReleased, Some(kb), _) => {
+            let scan = unsafe { MapVirtualKeyW(kb.raw_code, MAPVK_VK_TO_VSC) };
+            send_key(
+                kb.raw_code as u16,
+                scan as u16,
+                event.event_type == EventType::KeyReleased,
+                kb.extended,
+            )
+        }
+        (EventType::MousePressed | EventType::MouseReleased, _, Some(mouse)) => {
+            mouse_move(mouse.x, mouse.y)?;
+            simulate(event)
+        }
+        _ => simulate(event),
+    }
+}
+
 /// Simulate an event.
 pub fn simulate(event: &Event) -> Result<()> {
     match event.event_type {
@@ -104,17 +255,13 @@ pub fn simulate(event: &Event) -> Result<()> {
             }
         }
         EventType::MousePressed => {
-            if let Some(mouse) = &event.mouse {
-                if let Some(button) = mouse.button {
-                    mouse_press(button)?;
-                }
+            if let Some(button) = event.mouse.as_ref().and_then(|m| m.button) {
+                mouse_press(button)?;
             }
         }
         EventType::MouseReleased => {
-            if let Some(mouse) = &event.mouse {
-                if let Some(button) = mouse.button {
-                    mouse_release(button)?;
-                }
+            if let Some(button) = event.mouse.as_ref().and_then(|m| m.button) {
+                mouse_release(button)?;
             }
         }
         EventType::MouseMoved | EventType::MouseDragged => {
@@ -124,7 +271,8 @@ pub fn simulate(event: &Event) -> Result<()> {
         }
         EventType::MouseWheel => {
             if let Some(wheel) = &event.wheel {
-                mouse_scroll(wheel.delta as i32, 0)?;
+                let (vertical, horizontal) = wheel.steps();
+                mouse_scroll(vertical, horizontal)?;
             }
         }
         _ => {}
@@ -133,17 +281,27 @@ pub fn simulate(event: &Event) -> Result<()> {
 }
 
 /// Press a key.
+///
+/// If `SendInput` fails for a media key, the key is sent as `WM_APPCOMMAND`
+/// instead.
 pub fn key_press(key: Key) -> Result<()> {
-    let keycode = key_to_keycode(key)
+    let keycode = resolve_keycode(key)
         .ok_or_else(|| Error::SimulateFailed(format!("Unsupported key: {:?}", key)))?;
-    sim_keyboard_event(keycode, 0)
+    match (sim_keyboard_event(keycode, 0), media_app_command(key)) {
+        (Err(_), Some(command)) => send_app_command(command),
+        (result, _) => result,
+    }
 }
 
 /// Release a key.
 pub fn key_release(key: Key) -> Result<()> {
-    let keycode = key_to_keycode(key)
+    let keycode = resolve_keycode(key)
         .ok_or_else(|| Error::SimulateFailed(format!("Unsupported key: {:?}", key)))?;
-    sim_keyboard_event(keycode, 1)
+    match sim_keyboard_event(keycode, 1) {
+        // An app command fallback already acted on the press
+        Err(_) if media_app_command(key).is_some() => Ok(()),
+        result => result,
+    }
 }
 
 /// Press and release a key.
@@ -155,25 +313,33 @@ pub fn key_tap(key: Key) -> Result<()> {
 
 /// Press a mouse button.
 pub fn mouse_press(button: Button) -> Result<()> {
-    match button {
+    // Unknown(1..=5) are the named buttons
+    match Button::from_number(button.number()) {
         Button::Left => sim_mouse_event(MOUSEEVENTF_LEFTDOWN, 0, 0, 0),
         Button::Right => sim_mouse_event(MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
         Button::Middle => sim_mouse_event(MOUSEEVENTF_MIDDLEDOWN, 0, 0, 0),
         Button::Button4 => sim_mouse_event(MOUSEEVENTF_XDOWN, 1, 0, 0),
         Button::Button5 => sim_mouse_event(MOUSEEVENTF_XDOWN, 2, 0, 0),
-        Button::Unknown(code) => sim_mouse_event(MOUSEEVENTF_XDOWN, code as u32, 0, 0),
+        Button::Unknown(_) => Err(Error::SimulateFailed(format!(
+            "Windows has no button for {:?}",
+            button
+        ))),
     }
 }
 
 /// Release a mouse button.
 pub fn mouse_release(button: Button) -> Result<()> {
-    match button {
+    // Unknown(1..=5) are the named buttons
+    match Button::from_number(button.number()) {
         Button::Left => sim_mouse_event(MOUSEEVENTF_LEFTUP, 0, 0, 0),
         Button::Right => sim_mouse_event(MOUSEEVENTF_RIGHTUP, 0, 0, 0),
         Button::Middle => sim_mouse_event(MOUSEEVENTF_MIDDLEUP, 0, 0, 0),
         Button::Button4 => sim_mouse_event(MOUSEEVENTF_XUP, 1, 0, 0),
         Button::Button5 => sim_mouse_event(MOUSEEVENTF_XUP, 2, 0, 0),
-        Button::Unknown(code) => sim_mouse_event(MOUSEEVENTF_XUP, code as u32, 0, 0),
+        Button::Unknown(_) => Err(Error::SimulateFailed(format!(
+            "Windows has no button for {:?}",
+            button
+        ))),
     }
 }
 
@@ -209,7 +375,7 @@ pub fn mouse_scroll(delta_y: i32, delta_x: i32) -> Result<()> {
     if delta_y != 0 {
         sim_mouse_event(
             MOUSEEVENTF_WHEEL,
-            (delta_y as i32).wrapping_mul(WHEEL_DELTA as i32) as u32,
+            delta_y.wrapping_mul(WHEEL_DELTA as i32) as u32,
             0,
             0,
         )?;
@@ -217,7 +383,7 @@ pub fn mouse_scroll(delta_y: i32, delta_x: i32) -> Result<()> {
     if delta_x != 0 {
         sim_mouse_event(
             MOUSEEVENTF_HWHEEL,
-            (delta_x as i32).wrapping_mul(WHEEL_DELTA as i32) as u32,
+            delta_x.wrapping_mul(WHEEL_DELTA as i32) as u32,
             0,
             0,
         )?;
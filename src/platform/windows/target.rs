@@ -0,0 +1,456 @@
+//! Sending input to a specific window with `PostMessage`.
+
+use crate::error::{Error, Result};
+use crate::event::{Button, Event, EventType, MouseData, ScrollDirection, WheelData};
+use crate::state::{
+    MASK_ALT, MASK_BUTTON1, MASK_BUTTON2, MASK_BUTTON3, MASK_BUTTON4, MASK_BUTTON5, MASK_CTRL,
+    MASK_SHIFT,
+};
+use std::ffi::c_void;
+use std::mem::size_of;
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, POINT, WPARAM};
+use windows::Win32::Graphics::Gdi::ScreenToClient;
+use windows::Win32::UI::Input::KeyboardAndMouse::{MAPVK_VK_TO_VSC, MapVirtualKeyW};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GUITHREADINFO, GW_OWNER, GetGUIThreadInfo, GetWindow, GetWindowTextLengthW,
+    GetWindowTextW, GetWindowThreadProcessId, IsChild, IsWindowVisible, PostMessageW, WM_CHAR,
+    WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP,
+    WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN,
+    WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP,
+};
+
+use super::layout::resolve_keycode;
+
+const WHEEL_DELTA: f64 = 120.0;
+
+/// A top-level window, see [`find_window`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowHandle {
+    /// The window handle (`HWND`) as an integer.
+    pub hwnd: isize,
+    /// The process owning the window.
+    pub pid: u32,
+    /// The window title when it was found.
+    pub title: String,
+}
+
+/// Where [`send_to_window`](crate::simulation::send_to_window) posts events.
+///
+/// Posted messages skip the system input queue, so the window gets them
+/// whether or not it has focus, even minimized. That is also their limit:
+///
+/// - Low-level hooks, raw input and DirectInput never see them, so games and
+///   apps reading input that way ignore them.
+/// - The target's keyboard state isn't updated. Apps that check modifiers
+///   with `GetKeyState` (most shortcuts, e.g. Ctrl+S) see them as released.
+///   Alt is carried in the message itself, so menu accelerators work.
+/// - Characters come from the target's own `TranslateMessage`, using its
+///   keyboard layout and state; [`KeyTyped`](crate::EventType::KeyTyped)
+///   events are posted as `WM_CHAR` directly.
+/// - Windows of processes running at a higher integrity level (e.g. elevated)
+///   refuse messages from lower ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowTarget {
+    /// A window handle (`HWND`) as an integer.
+    Window(isize),
+    /// The main window of a process: its first visible, unowned top-level
+    /// window.
+    Process(u32),
+}
+
+impl From<&WindowHandle> for WindowTarget {
+    fn from(handle: &WindowHandle) -> Self {
+        WindowTarget::Window(handle.hwnd)
+    }
+}
+
+/// Top-level windows whose title contains `title`, ignoring case.
+///
+/// Hidden windows are included; minimized windows count as visible.
+pub fn find_window(title: &str) -> Vec<WindowHandle> {
+    let needle = title.to_lowercase();
+    top_level_windows()
+        .into_iter()
+        .filter_map(|hwnd| {
+            let title = window_title(hwnd);
+            if title.is_empty() || !title.to_lowercase().contains(&needle) {
+                return None;
+            }
+            Some(WindowHandle {
+                hwnd: hwnd.0 as isize,
+                pid: window_pid(hwnd),
+                title,
+            })
+        })
+        .collect()
+}
+
+/// Post an event to a window.
+///
+/// Keys go to the window's focused control, if it has one; mouse events to
+/// the window itself, at the event's screen position translated to the
+/// window. Events other than keys, buttons, moves and wheel are ignored.
+pub fn send_to_window(target: WindowTarget, event: &Event) -> Result<()> {
+    let window = resolve_window(target)?;
+    match event.event_type {
+        EventType::KeyPressed | EventType::KeyReleased => {
+            let Some(kb) = &event.keyboard else {
+                return Ok(());
+            };
+            let vk = match resolve_keycode(kb.key) {
+                Some(vk) => u32::from(vk),
+                None if kb.raw_code != 0 => kb.raw_code,
+                None => {
+                    return Err(Error::SimulateFailed(format!(
+                        "Unsupported key: {:?}",
+                        kb.key
+                    )));
+                }
+            };
+            let up = event.event_type == EventType::KeyReleased;
+            let alt = event.mask & MASK_ALT != 0;
+            let msg = match (alt, up) {
+                (false, false) => WM_KEYDOWN,
+                (false, true) => WM_KEYUP,
+                (true, false) => WM_SYSKEYDOWN,
+                (true, true) => WM_SYSKEYUP,
+            };
+            let scan = unsafe { MapVirtualKeyW(vk, MAPVK_VK_TO_VSC) };
+            post(
+                focus_of(window),
+                msg,
+                WPARAM(vk as usize),
+                key_lparam(scan, kb.extended, alt, up),
+            )
+        }
+        EventType::KeyTyped => {
+            let Some(ch) = event.keyboard.as_ref().and_then(|kb| kb.char) else {
+                return Ok(());
+            };
+            let focus = focus_of(window);
+            for unit in ch.encode_utf16(&mut [0; 2]) {
+                post(focus, WM_CHAR, WPARAM(*unit as usize), LPARAM(1))?;
+            }
+            Ok(())
+        }
+        EventType::MousePressed
+        | EventType::MouseReleased
+        | EventType::MouseMoved
+        | EventType::MouseDragged => {
+            let Some(mouse) = &event.mouse else {
+                return Ok(());
+            };
+            let Some((msg, button_bits)) = mouse_message(event.event_type, mouse) else {
+                return Ok(());
+            };
+            let wparam = (u32::from(button_bits) << 16) | mouse_key_state(event.mask);
+            post(
+                window,
+                msg,
+                WPARAM(wparam as usize),
+                client_lparam(window, mouse.x, mouse.y),
+            )
+        }
+        EventType::MouseWheel => {
+            let Some(wheel) = &event.wheel else {
+                return Ok(());
+            };
+            let (msg, delta) = wheel_message(wheel);
+            let wparam = (u32::from(delta as u16) << 16) | mouse_key_state(event.mask);
+            // Wheel messages carry screen coordinates
+            post(
+                window,
+                msg,
+                WPARAM(wparam as usize),
+                point_lparam(wheel.x.round() as i32, wheel.y.round() as i32),
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+fn post(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> Result<()> {
+    unsafe { PostMessageW(Some(hwnd), msg, wparam, lparam) }
+        .map_err(|e| Error::SimulateFailed(format!("PostMessageW failed: {}", e)))
+}
+
+/// The window a target refers to.
+fn resolve_window(target: WindowTarget) -> Result<HWND> {
+    let hwnd = match target {
+        WindowTarget::Window(hwnd) => HWND(hwnd as *mut c_void),
+        WindowTarget::Process(pid) => main_window(pid)
+            .ok_or_else(|| Error::Platform(format!("No window found for process {}", pid)))?,
+    };
+    if window_pid(hwnd) == 0 {
+        return Err(Error::Platform(format!(
+            "Invalid window handle {:?}",
+            hwnd.0
+        )));
+    }
+    Ok(hwnd)
+}
+
+/// The focused control inside `window`, or `window` itself.
+fn focus_of(window: HWND) -> HWND {
+    let thread = unsafe { GetWindowThreadProcessId(window, None) };
+    let mut info = GUITHREADINFO {
+        cbSize: size_of::<GUITHREADINFO>() as u32,
+        ..Default::default()
+    };
+    if unsafe { GetGUIThreadInfo(thread, &mut info) }.is_ok()
+        && !info.hwndFocus.is_invalid()
+        && (info.hwndFocus == window || unsafe { IsChild(window, info.hwndFocus) }.as_bool())
+    {
+        info.hwndFocus
+    } else {
+        window
+    }
+}
+
+/// The first visible, unowned top-level window of `pid`, or any of its
+/// top-level windows.
+fn main_window(pid: u32) -> Option<HWND> {
+    let windows: Vec<HWND> = top_level_windows()
+        .into_iter()
+        .filter(|&hwnd| window_pid(hwnd) == pid)
+        .collect();
+    windows
+        .iter()
+        .copied()
+        .find(|&hwnd| unsafe {
+            IsWindowVisible(hwnd).as_bool()
+                && !GetWindow(hwnd, GW_OWNER).is_ok_and(|owner| !owner.is_invalid())
+        })
+        .or_else(|| windows.first().copied())
+}
+
+fn top_level_windows() -> Vec<HWND> {
+    let mut windows: Vec<HWND> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(
+            Some(collect_window),
+            LPARAM(&mut windows as *mut Vec<HWND> as isize),
+        );
+    }
+    windows
+}
+
+unsafe extern "system" fn collect_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let windows = unsafe { &mut *(lparam.0 as *mut Vec<HWND>) };
+    windows.push(hwnd);
+    BOOL(1)
+}
+
+fn window_pid(hwnd: HWND) -> u32 {
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid as *mut u32)) };
+    pid
+}
+
+fn window_title(hwnd: HWND) -> String {
+    let len = unsafe { GetWindowTextLengthW(hwnd) };
+    if len <= 0 {
+        return String::new();
+    }
+    let mut buf = vec![0u16; len as usize + 1];
+    let copied = unsafe { GetWindowTextW(hwnd, &mut buf) };
+    String::from_utf16_lossy(&buf[..copied.max(0) as usize])
+}
+
+/// `lParam` of a key message: repeat count 1, scan code, extended, context
+/// (Alt held) and transition bits.
+fn key_lparam(scan: u32, extended: bool, alt: bool, up: bool) -> LPARAM {
+    let mut lparam = 1 | ((scan & 0xFF) << 16);
+    if extended {
+        lparam |= 1 << 24;
+    }
+    if alt {
+        lparam |= 1 << 29;
+    }
+    if up {
+        // Previous key state and transition state
+        lparam |= (1 << 30) | (1 << 31);
+    }
+    LPARAM(lparam as isize)
+}
+
+/// The message for a button or move, and the X button bits of its `wParam`.
+fn mouse_message(event_type: EventType, mouse: &MouseData) -> Option<(u32, u16)> {
+    let pressed = event_type == EventType::MousePressed;
+    let message = match (event_type, mouse.button) {
+        (EventType::MouseMoved | EventType::MouseDragged, _) => (WM_MOUSEMOVE, 0),
+        (_, Some(Button::Left)) => (
+            if pressed {
+                WM_LBUTTONDOWN
+            } else {
+                WM_LBUTTONUP
+            },
+            0,
+        ),
+        (_, Some(Button::Right)) => (
+            if pressed {
+                WM_RBUTTONDOWN
+            } else {
+                WM_RBUTTONUP
+            },
+            0,
+        ),
+        (_, Some(Button::Middle)) => (
+            if pressed {
+                WM_MBUTTONDOWN
+            } else {
+                WM_MBUTTONUP
+            },
+            0,
+        ),
+        (_, Some(Button::Button4)) => (
+            if pressed {
+                WM_XBUTTONDOWN
+            } else {
+                WM_XBUTTONUP
+            },
+            1,
+        ),
+        (_, Some(Button::Button5)) => (
+            if pressed {
+                WM_XBUTTONDOWN
+            } else {
+                WM_XBUTTONUP
+            },
+            2,
+        ),
+        _ => return None,
+    };
+    Some(message)
+}
+
+/// The `MK_*` flags for held buttons and modifiers in `mask`.
+fn mouse_key_state(mask: u32) -> u32 {
+    [
+        (MASK_BUTTON1, 0x0001), // MK_LBUTTON
+        (MASK_BUTTON2, 0x0002), // MK_RBUTTON
+        (MASK_SHIFT, 0x0004),   // MK_SHIFT
+        (MASK_CTRL, 0x0008),    // MK_CONTROL
+        (MASK_BUTTON3, 0x0010), // MK_MBUTTON
+        (MASK_BUTTON4, 0x0020), // MK_XBUTTON1
+        (MASK_BUTTON5, 0x0040), // MK_XBUTTON2
+    ]
+    .iter()
+    .filter(|(bit, _)| mask & bit != 0)
+    .fold(0, |flags, (_, flag)| flags | flag)
+}
+
+/// The wheel message and its signed delta in `WHEEL_DELTA` units.
+fn wheel_message(wheel: &WheelData) -> (u32, i16) {
+    let delta = (wheel.delta.abs() * WHEEL_DELTA)
+        .round()
+        .min(i16::MAX as f64) as i16;
+    match wheel.direction {
+        ScrollDirection::Up => (WM_MOUSEWHEEL, delta),
+        ScrollDirection::Down => (WM_MOUSEWHEEL, -delta),
+        ScrollDirection::Left => (WM_MOUSEHWHEEL, -delta),
+        ScrollDirection::Right => (WM_MOUSEHWHEEL, delta),
+    }
+}
+
+/// Screen coordinates as `lParam` relative to `hwnd`'s client area.
+fn client_lparam(hwnd: HWND, x: f64, y: f64) -> LPARAM {
+    let mut point = POINT {
+        x: x.round() as i32,
+        y: y.round() as i32,
+    };
+    let _ = unsafe { ScreenToClient(hwnd, &mut point) };
+    point_lparam(point.x, point.y)
+}
+
+fn point_lparam(x: i32, y: i32) -> LPARAM {
+    LPARAM(((x as u16 as u32) | ((y as u16 as u32) << 16)) as isize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycode::Key;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DestroyWindow, DispatchMessageW, MSG, PM_REMOVE, PeekMessageW,
+        TranslateMessage, WINDOW_EX_STYLE, WS_OVERLAPPEDWINDOW,
+    };
+    use windows::core::{HSTRING, w};
+
+    #[test]
+    fn test_key_lparam() {
+        assert_eq!(key_lparam(0x1E, false, false, false).0, 0x001E_0001);
+        assert_eq!(key_lparam(0x1C, true, false, true).0 as u32, 0xC11C_0001);
+        assert_eq!(key_lparam(0x3E, false, true, false).0, 0x203E_0001);
+    }
+
+    #[test]
+    fn test_mouse_wparam() {
+        assert_eq!(mouse_key_state(MASK_BUTTON1 | MASK_CTRL), 0x0009);
+        let mouse = MouseData {
+            x: 0.0,
+            y: 0.0,
+            button: Some(Button::Button5),
+            clicks: 1,
+        };
+        assert_eq!(
+            mouse_message(EventType::MouseReleased, &mouse),
+            Some((WM_XBUTTONUP, 2))
+        );
+        let wheel = WheelData {
+            x: 0.0,
+            y: 0.0,
+            direction: ScrollDirection::Down,
+            delta: 1.5,
+            pixel_delta: None,
+            phase: None,
+        };
+        assert_eq!(wheel_message(&wheel), (WM_MOUSEWHEEL, -180));
+    }
+
+    /// Types into an edit control this test owns, found by its title.
+    #[test]
+    fn test_send_to_own_window() {
+        let title = format!("monio_test_target {}", std::process::id());
+        let hwnd = unsafe {
+            CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                w!("EDIT"),
+                &HSTRING::from(title.as_str()),
+                WS_OVERLAPPEDWINDOW,
+                0,
+                0,
+                200,
+                100,
+                None,
+                None,
+                None,
+                None,
+            )
+        }
+        .unwrap();
+
+        let found = find_window(&title.to_uppercase());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].hwnd, hwnd.0 as isize);
+        assert_eq!(found[0].pid, std::process::id());
+
+        let target = WindowTarget::from(&found[0]);
+        for key in [Key::KeyH, Key::KeyI] {
+            send_to_window(target, &Event::key_pressed(key, 0)).unwrap();
+            send_to_window(target, &Event::key_released(key, 0)).unwrap();
+        }
+        send_to_window(target, &Event::key_typed(Key::Unknown(0), 0, '!')).unwrap();
+
+        unsafe {
+            let mut msg = MSG::default();
+            while PeekMessageW(&mut msg, Some(hwnd), 0, 0, PM_REMOVE).as_bool() {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        let text = window_title(hwnd);
+        unsafe { DestroyWindow(hwnd) }.unwrap();
+        assert!(text.contains("hi!"), "{text:?}");
+    }
+}
@@ -0,0 +1,367 @@
+//! Process-wide switches for turning off grabbing and simulation.
+//!
+//! An escape hatch for deployments of apps embedding monio:
+//!
+//! - With `MONIO_DISABLE_GRAB=1` in the environment, or after
+//!   `set_global_grab_policy(Policy::Deny)`, every grab hook
+//!   ([`Hook::grab`](crate::Hook::grab), [`grab_channel`](crate::channel::grab_channel),
+//!   ...) runs as a listener instead, like on X11: the handler still sees
+//!   every event, but what it returns is ignored and nothing is consumed. A
+//!   warning is logged, and [`Hook::is_grab_denied`](crate::Hook::is_grab_denied)
+//!   tells the app.
+//! - With `MONIO_DISABLE_SIMULATION=1`, or after
+//!   `set_global_simulation_policy(Policy::Deny)`, the simulation functions
+//!   ([`simulate`](crate::simulate), [`key_tap`](crate::key_tap), ...,
+//!   [`Event::reinject`](crate::Event::reinject)) do nothing and return
+//!   [`Error::DisabledByPolicy`].
+//!
+//! # Precedence
+//!
+//! A policy set through the API wins over the environment variable;
+//! clearing it defers to the environment again. Without either, everything
+//! is allowed. The environment is read whenever a hook starts or an event is
+//! simulated. `1`, `true`, `yes` and `on` (in any case) deny; any other
+//! value allows.
+//!
+//! Policies apply when a grab hook starts; changing them doesn't affect
+//! hooks already running.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::policy::{Policy, set_global_grab_policy};
+//! use monio::{Event, Hook};
+//!
+//! set_global_grab_policy(Policy::Deny);
+//!
+//! let hook = Hook::new();
+//! hook.grab_async(|_: &Event| None).unwrap();
+//! assert!(hook.is_grab_denied()); // Input still reaches other apps
+//! ```
+
+use crate::error::{Error, Result};
+use crate::event::{Event, EventType};
+use crate::hook::{EventHandler, GrabHandler};
+use crate::intercept::GrabCapabilities;
+use crate::source;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// Environment variable that denies grabbing when set to a true value.
+pub const DISABLE_GRAB_ENV: &str = "MONIO_DISABLE_GRAB";
+
+/// Environment variable that denies simulation when set to a true value.
+pub const DISABLE_SIMULATION_ENV: &str = "MONIO_DISABLE_SIMULATION";
+
+/// Whether an operation is allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// The operation works normally.
+    Allow,
+    /// Grabs degrade to listening; simulation fails with
+    /// [`Error::DisabledByPolicy`].
+    Deny,
+}
+
+/// No policy set through the API.
+const UNSET: u8 = 0;
+const ALLOW: u8 = 1;
+const DENY: u8 = 2;
+
+static GRAB_POLICY: AtomicU8 = AtomicU8::new(UNSET);
+static SIMULATION_POLICY: AtomicU8 = AtomicU8::new(UNSET);
+
+impl Policy {
+    fn encode(self) -> u8 {
+        match self {
+            Policy::Allow => ALLOW,
+            Policy::Deny => DENY,
+        }
+    }
+}
+
+/// Where an effective policy came from, for messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Api,
+    Env(&'static str),
+    Default,
+}
+
+/// The effective policy: the API's if set, else the environment's.
+fn resolve(set: &AtomicU8, env: &'static str) -> (Policy, Source) {
+    match set.load(Ordering::SeqCst) {
+        ALLOW => (Policy::Allow, Source::Api),
+        DENY => (Policy::Deny, Source::Api),
+        _ if env_denies(env) => (Policy::Deny, Source::Env(env)),
+        _ => (Policy::Allow, Source::Default),
+    }
+}
+
+/// Whether environment variable `name` is set to a true value.
+fn env_denies(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|value| {
+        matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    })
+}
+
+fn describe(what: &str, source: Source) -> String {
+    match source {
+        Source::Env(name) => format!("{what} disabled by {name}"),
+        _ => format!("{what} disabled by the global policy"),
+    }
+}
+
+/// Allow or deny grabbing for the whole process, overriding
+/// `MONIO_DISABLE_GRAB`.
+pub fn set_global_grab_policy(policy: Policy) {
+    GRAB_POLICY.store(policy.encode(), Ordering::SeqCst);
+}
+
+/// Forget the policy set with [`set_global_grab_policy`], deferring to
+/// `MONIO_DISABLE_GRAB` again.
+pub fn clear_global_grab_policy() {
+    GRAB_POLICY.store(UNSET, Ordering::SeqCst);
+}
+
+/// Whether grab hooks started now would grab.
+pub fn grab_policy() -> Policy {
+    resolve(&GRAB_POLICY, DISABLE_GRAB_ENV).0
+}
+
+/// Allow or deny simulation for the whole process, overriding
+/// `MONIO_DISABLE_SIMULATION`.
+pub fn set_global_simulation_policy(policy: Policy) {
+    SIMULATION_POLICY.store(policy.encode(), Ordering::SeqCst);
+}
+
+/// Forget the policy set with [`set_global_simulation_policy`], deferring
+/// to `MONIO_DISABLE_SIMULATION` again.
+pub fn clear_global_simulation_policy() {
+    SIMULATION_POLICY.store(UNSET, Ordering::SeqCst);
+}
+
+/// Whether events can be simulated now.
+pub fn simulation_policy() -> Policy {
+    resolve(&SIMULATION_POLICY, DISABLE_SIMULATION_ENV).0
+}
+
+/// Fail with [`Error::DisabledByPolicy`] if simulation is denied.
+pub(crate) fn check_simulation() -> Result<()> {
+    match resolve(&SIMULATION_POLICY, DISABLE_SIMULATION_ENV) {
+        (Policy::Allow, _) => Ok(()),
+        (Policy::Deny, source) => Err(Error::DisabledByPolicy(describe("simulation", source))),
+    }
+}
+
+/// Whether a grab hook starting now must listen instead, logging why.
+pub(crate) fn grab_denied() -> bool {
+    match resolve(&GRAB_POLICY, DISABLE_GRAB_ENV) {
+        (Policy::Allow, _) => false,
+        (Policy::Deny, source) => {
+            log::warn!("{}; listening instead", describe("grabbing", source));
+            true
+        }
+    }
+}
+
+/// Run a grab hook, or a listen hook calling `handler` if `denied`.
+///
+/// Grabs that can't consume events report
+/// [`EventType::GrabDegraded`](crate::EventType::GrabDegraded).
+pub(crate) fn run_grab_hook<H: GrabHandler + 'static>(
+    running: &Arc<AtomicBool>,
+    handler: H,
+    denied: bool,
+) -> Result<()> {
+    if denied {
+        source::run_hook(running, ListenOnly(Degraded::new(handler)))
+    } else if !GrabCapabilities::new(false).can_consume {
+        source::run_grab_hook(running, Degraded::new(handler))
+    } else {
+        source::run_grab_hook(running, handler)
+    }
+}
+
+/// Grab handler that can't consume, told so after `HookEnabled`.
+struct Degraded<H> {
+    handler: H,
+    reported: AtomicBool,
+}
+
+impl<H> Degraded<H> {
+    fn new(handler: H) -> Self {
+        Self {
+            handler,
+            reported: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<H: GrabHandler> GrabHandler for Degraded<H> {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        let verdict = self.handler.handle_event(event);
+        if event.event_type == EventType::HookEnabled && !self.reported.swap(true, Ordering::SeqCst)
+        {
+            let _ = self.handler.handle_event(&Event::grab_degraded());
+        }
+        verdict
+    }
+}
+
+/// Grab handler run by a listen hook; its verdicts are ignored.
+pub(crate) struct ListenOnly<H>(pub(crate) H);
+
+impl<H: GrabHandler> EventHandler for ListenOnly<H> {
+    fn handle_event(&self, event: &Event) {
+        let _ = self.0.handle_event(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Tests share the process-wide policies and environment.
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn set_env(name: &str, value: Option<&str>) {
+        // SAFETY: tests touching these variables are serialized, and nothing
+        // else in the crate writes the environment.
+        unsafe {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_grab_precedence() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        clear_global_grab_policy();
+
+        set_env(DISABLE_GRAB_ENV, None);
+        assert_eq!(grab_policy(), Policy::Allow);
+        assert!(!grab_denied());
+
+        for value in ["1", "true", "YES", " on "] {
+            set_env(DISABLE_GRAB_ENV, Some(value));
+            assert_eq!(grab_policy(), Policy::Deny, "{value:?}");
+        }
+        assert!(grab_denied());
+        for value in ["0", "false", ""] {
+            set_env(DISABLE_GRAB_ENV, Some(value));
+            assert_eq!(grab_policy(), Policy::Allow, "{value:?}");
+        }
+
+        // The API wins over the environment either way
+        set_env(DISABLE_GRAB_ENV, Some("1"));
+        set_global_grab_policy(Policy::Allow);
+        assert_eq!(grab_policy(), Policy::Allow);
+        set_env(DISABLE_GRAB_ENV, None);
+        set_global_grab_policy(Policy::Deny);
+        assert_eq!(grab_policy(), Policy::Deny);
+
+        clear_global_grab_policy();
+        assert_eq!(grab_policy(), Policy::Allow);
+    }
+
+    #[test]
+    fn test_simulation_denied() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        clear_global_simulation_policy();
+
+        set_env(DISABLE_SIMULATION_ENV, Some("1"));
+        let err = crate::key_tap(crate::Key::KeyA).unwrap_err();
+        assert!(
+            matches!(&err, Error::DisabledByPolicy(msg) if msg.contains(DISABLE_SIMULATION_ENV)),
+            "{err}"
+        );
+        assert!(matches!(
+            Event::key_pressed(crate::Key::KeyA, 0).reinject(),
+            Err(Error::DisabledByPolicy(_))
+        ));
+
+        set_global_simulation_policy(Policy::Allow);
+        assert!(check_simulation().is_ok());
+        set_env(DISABLE_SIMULATION_ENV, None);
+        set_global_simulation_policy(Policy::Deny);
+        assert!(matches!(
+            crate::simulate(&Event::mouse_moved(1.0, 1.0)),
+            Err(Error::DisabledByPolicy(msg)) if msg.contains("global policy")
+        ));
+
+        clear_global_simulation_policy();
+        assert!(check_simulation().is_ok());
+    }
+
+    #[test]
+    fn test_listen_only_ignores_verdicts() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let seen = seen.clone();
+            ListenOnly(move |event: &Event| {
+                seen.lock().unwrap().push(event.event_type);
+                None
+            })
+        };
+        EventHandler::handle_event(&handler, &Event::mouse_moved(1.0, 2.0));
+        assert_eq!(*seen.lock().unwrap(), vec![crate::EventType::MouseMoved]);
+    }
+
+    #[test]
+    fn test_degraded_reported_once_after_enabled() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let seen = seen.clone();
+            Degraded::new(move |event: &Event| {
+                seen.lock().unwrap().push(event.event_type);
+                None
+            })
+        };
+        for event in [
+            Event::hook_enabled(),
+            Event::mouse_moved(1.0, 2.0),
+            Event::hook_enabled(),
+        ] {
+            assert_eq!(GrabHandler::handle_event(&handler, &event), None);
+        }
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                EventType::HookEnabled,
+                EventType::GrabDegraded,
+                EventType::MouseMoved,
+                EventType::HookEnabled,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_require_consumption_fails_when_denied() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let hook = crate::Hook::new();
+        assert_eq!(
+            hook.grab_capabilities(),
+            GrabCapabilities::new(false),
+            "before the first grab"
+        );
+
+        set_global_grab_policy(Policy::Deny);
+        let hook = hook.grab_options(crate::GrabOptions::default().require_consumption(true));
+        let result = hook.grab_async(|_: &Event| None);
+        clear_global_grab_policy();
+
+        assert!(
+            matches!(&result, Err(Error::NotSupported(msg)) if msg.contains("policy")),
+            "{result:?}"
+        );
+        assert!(!hook.is_running());
+        assert!(!hook.is_grab_denied());
+    }
+}
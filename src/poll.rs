@@ -0,0 +1,406 @@
+//! Frame-synchronized event polling.
+//!
+//! Game loops usually want input once per frame rather than through
+//! callbacks. A [`PolledHook`] queues events from the hook thread in a
+//! fixed-size ring buffer; each frame, [`PolledHook::poll_events`] drains
+//! everything queued since the last poll without blocking, and
+//! [`PolledHook::poll_state`] reads the current mouse position, held buttons
+//! and held keys.
+//!
+//! If the queue fills up between polls, new events are dropped (see
+//! [`PolledHook::dropped_count`]), while the polled state stays current.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::poll::PolledHook;
+//! use monio::{EventType, Key};
+//! use std::time::Duration;
+//!
+//! let mut input = PolledHook::start()?;
+//! let mut events = Vec::new();
+//! loop {
+//!     events.clear();
+//!     input.poll_events_into(&mut events);
+//!     for event in &events {
+//!         if event.event_type == EventType::KeyPressed {
+//!             println!("{:?}", event.keyboard.as_ref().map(|kb| kb.key));
+//!         }
+//!     }
+//!     if input.poll_state().is_key_down(Key::Escape) {
+//!         break;
+//!     }
+//!     std::thread::sleep(Duration::from_millis(16));
+//! }
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::Result;
+use crate::event::{Button, Event};
+use crate::hook::Hook;
+use crate::keycode::Key;
+use crate::snapshot::{InputSnapshot, StateTracker, TrackedState};
+use crate::state::{
+    MASK_BUTTON1, MASK_BUTTON2, MASK_BUTTON3, MASK_BUTTON4, MASK_BUTTON5, Modifiers,
+};
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Default number of events queued between polls.
+pub const DEFAULT_POLL_CAPACITY: usize = 4096;
+
+/// Fixed-size single-producer, single-consumer event queue.
+///
+/// Each end takes its own lock, so pushing and draining never wait on each
+/// other; the locks only keep a second producer or consumer out.
+struct EventRing {
+    slots: Box<[UnsafeCell<MaybeUninit<Event>>]>,
+    /// Next slot to read. Only advanced by the consumer.
+    head: AtomicUsize,
+    /// Next slot to write. Only advanced by the producer.
+    tail: AtomicUsize,
+    producer: Mutex<()>,
+    consumer: Mutex<()>,
+    dropped: AtomicU64,
+}
+
+// Slots are only accessed under the ownership protocol documented on
+// `push` and `drain_into`, and `Event` is `Send`.
+unsafe impl Sync for EventRing {}
+
+impl EventRing {
+    fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(2).next_power_of_two();
+        Self {
+            slots: (0..capacity)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            producer: Mutex::new(()),
+            consumer: Mutex::new(()),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn slot(&self, index: usize) -> *mut MaybeUninit<Event> {
+        self.slots[index & (self.slots.len() - 1)].get()
+    }
+
+    /// Queue a copy of `event`, or count it as dropped if the ring is full.
+    fn push(&self, event: &Event) -> bool {
+        let _producer = self.producer.lock().unwrap_or_else(|e| e.into_inner());
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == self.slots.len() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        // The slot at `tail` is outside `head..tail`, so the consumer won't
+        // touch it until the store below publishes it.
+        unsafe { (*self.slot(tail)).write(event.clone()) };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Move every queued event into `out`, returning how many there were.
+    fn drain_into(&self, out: &mut Vec<Event>) -> usize {
+        let _consumer = self.consumer.lock().unwrap_or_else(|e| e.into_inner());
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        let count = tail.wrapping_sub(head);
+        out.reserve(count);
+        for i in 0..count {
+            // Slots in `head..tail` were initialized by `push`, and the
+            // producer won't reuse them until `head` moves past them.
+            out.push(unsafe { (*self.slot(head.wrapping_add(i))).assume_init_read() });
+        }
+        self.head.store(tail, Ordering::Release);
+        count
+    }
+}
+
+impl Drop for EventRing {
+    fn drop(&mut self) {
+        self.drain_into(&mut Vec::new());
+    }
+}
+
+/// Number of bits in a [`KeySet`].
+const KEY_SET_BITS: usize = 256;
+
+/// Compact set of held keys.
+///
+/// [`Key::Unknown`] keys are not tracked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct KeySet {
+    bits: [u64; KEY_SET_BITS / 64],
+}
+
+impl KeySet {
+    /// Whether `key` is in the set.
+    pub fn contains(&self, key: Key) -> bool {
+        key_index(key).is_some_and(|i| self.bits[i / 64] & (1 << (i % 64)) != 0)
+    }
+
+    /// Number of keys in the set.
+    pub fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&word| word == 0)
+    }
+}
+
+/// Bit index of `key` in a [`KeySet`].
+fn key_index(key: Key) -> Option<usize> {
+    if matches!(key, Key::Unknown(_)) {
+        return None;
+    }
+    // `Key` is `#[repr(u16)]`, so it starts with its u16 discriminant.
+    let discriminant = unsafe { *(&key as *const Key as *const u16) };
+    Some(discriminant as usize).filter(|&i| i < KEY_SET_BITS)
+}
+
+/// Snapshot of the input state, see [`PolledHook::poll_state`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputState {
+    /// Last known mouse position.
+    pub mouse_position: (f64, f64),
+    /// Held mouse buttons: bit 0 is button 1 ([`Button::Left`]) through
+    /// bit 4 for button 5.
+    pub buttons: u8,
+    /// Held keyboard modifiers.
+    pub modifiers: Modifiers,
+    /// Held keys.
+    pub keys: KeySet,
+}
+
+impl InputState {
+    /// Whether `button` is held.
+    pub fn is_button_down(&self, button: Button) -> bool {
+        matches!(button.number(), 1..=5) && self.buttons & (1 << (button.number() - 1)) != 0
+    }
+
+    /// Whether `key` is held.
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Decode the state a [`StateTracker`] read.
+    fn from_tracked(state: &TrackedState) -> Self {
+        let mut keys = KeySet::default();
+        for i in state.pressed_keys.iter().filter_map(|&key| key_index(key)) {
+            keys.bits[i / 64] |= 1 << (i % 64);
+        }
+        let buttons = [
+            MASK_BUTTON1,
+            MASK_BUTTON2,
+            MASK_BUTTON3,
+            MASK_BUTTON4,
+            MASK_BUTTON5,
+        ]
+        .iter()
+        .enumerate()
+        .filter(|&(_, &button)| state.mask & button != 0)
+        .fold(0, |bits, (i, _)| bits | 1 << i);
+
+        InputState {
+            mouse_position: state.mouse_position.unwrap_or_default(),
+            buttons,
+            modifiers: Modifiers::from_mask(state.mask),
+            keys,
+        }
+    }
+}
+
+/// A hook whose events are polled, e.g. once per frame.
+///
+/// The hook stops when this is dropped.
+pub struct PolledHook {
+    hook: Hook,
+    ring: Arc<EventRing>,
+    state: Arc<StateTracker>,
+}
+
+impl PolledHook {
+    /// Start a hook queuing up to [`DEFAULT_POLL_CAPACITY`] events between polls.
+    pub fn start() -> Result<Self> {
+        Self::with_capacity(DEFAULT_POLL_CAPACITY)
+    }
+
+    /// Start a hook queuing up to `capacity` events between polls.
+    ///
+    /// `capacity` is rounded up to a power of two.
+    pub fn with_capacity(capacity: usize) -> Result<Self> {
+        let polled = Self {
+            hook: Hook::new(),
+            ring: Arc::new(EventRing::with_capacity(capacity)),
+            state: Arc::new(StateTracker::new()),
+        };
+        let ring = polled.ring.clone();
+        let state = polled.state.clone();
+        polled.hook.run_async(move |event: &Event| {
+            state.update(event);
+            ring.push(event);
+        })?;
+        Ok(polled)
+    }
+
+    /// Take every event queued since the last poll, oldest first.
+    ///
+    /// Never blocks; returns an empty vector if nothing happened.
+    pub fn poll_events(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        self.ring.drain_into(&mut events);
+        events
+    }
+
+    /// Like [`poll_events`](Self::poll_events), appending to `events` so
+    /// its allocation can be reused between frames. Returns how many
+    /// events were added.
+    pub fn poll_events_into(&mut self, events: &mut Vec<Event>) -> usize {
+        self.ring.drain_into(events)
+    }
+
+    /// The current input state.
+    ///
+    /// This reflects every event the hook has seen, including ones not
+    /// polled yet. All fields are from the same instant.
+    pub fn poll_state(&self) -> InputState {
+        InputState::from_tracked(&self.state.read())
+    }
+
+    /// The full input state, including lock keys and the exact keys held;
+    /// see [`input_snapshot`](crate::input_snapshot).
+    pub fn snapshot(&self) -> InputSnapshot {
+        self.state.snapshot()
+    }
+
+    /// Number of events dropped because the queue was full.
+    pub fn dropped_count(&self) -> u64 {
+        self.ring.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stop the hook.
+    pub fn stop(self) -> Result<()> {
+        self.hook.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_ring_drains_in_order_and_drops_when_full() {
+        let ring = EventRing::with_capacity(3);
+        assert_eq!(ring.slots.len(), 4);
+        for x in 0..6 {
+            ring.push(&Event::mouse_moved(x as f64, 0.0));
+        }
+        assert_eq!(ring.dropped.load(Ordering::Relaxed), 2);
+
+        let mut events = Vec::new();
+        assert_eq!(ring.drain_into(&mut events), 4);
+        let xs: Vec<f64> = events.iter().map(|e| e.mouse.as_ref().unwrap().x).collect();
+        assert_eq!(xs, vec![0.0, 1.0, 2.0, 3.0]);
+
+        // Wraps around once drained
+        ring.push(&Event::mouse_moved(9.0, 0.0));
+        events.clear();
+        assert_eq!(ring.drain_into(&mut events), 1);
+        assert_eq!(events[0].mouse.as_ref().unwrap().x, 9.0);
+        assert_eq!(ring.drain_into(&mut events), 0);
+    }
+
+    #[test]
+    fn test_ring_across_threads() {
+        let ring = Arc::new(EventRing::with_capacity(64));
+        let producer = {
+            let ring = ring.clone();
+            std::thread::spawn(move || {
+                for x in 0..10_000 {
+                    while !ring.push(&Event::mouse_moved(x as f64, 0.0)) {
+                        std::thread::yield_now();
+                    }
+                }
+            })
+        };
+
+        let mut events = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while events.len() < 10_000 && Instant::now() < deadline {
+            ring.drain_into(&mut events);
+        }
+        producer.join().unwrap();
+        assert!(
+            events
+                .iter()
+                .enumerate()
+                .all(|(i, e)| e.mouse.as_ref().unwrap().x == i as f64)
+        );
+        assert_eq!(events.len(), 10_000);
+    }
+
+    #[test]
+    fn test_state_tracks_keys_buttons_and_position() {
+        let state = StateTracker::new();
+        let mut shift = Event::key_pressed(Key::ShiftLeft, 0);
+        shift.mask = crate::state::MASK_SHIFT;
+        state.update(&shift);
+        state.update(&Event::key_pressed(Key::KeyW, 0));
+        state.update(&Event::key_pressed(Key::Unknown(999), 999));
+        let mut press = Event::mouse_pressed(Button::Right, 10.0, 20.0);
+        press.mask = crate::state::MASK_SHIFT | MASK_BUTTON2;
+        state.update(&press);
+
+        let snapshot = InputState::from_tracked(&state.read());
+        assert_eq!(snapshot.mouse_position, (10.0, 20.0));
+        assert!(snapshot.is_button_down(Button::Right));
+        assert!(!snapshot.is_button_down(Button::Left));
+        assert_eq!(snapshot.buttons, 0b10);
+        assert!(snapshot.modifiers.shift);
+        assert!(snapshot.is_key_down(Key::KeyW));
+        assert!(snapshot.is_key_down(Key::ShiftLeft));
+        assert!(!snapshot.is_key_down(Key::Unknown(999)));
+        assert_eq!(snapshot.keys.len(), 2);
+
+        state.update(&Event::key_released(Key::KeyW, 0));
+        assert!(!InputState::from_tracked(&state.read()).is_key_down(Key::KeyW));
+    }
+
+    /// Per-frame drain cost with 1000 queued events.
+    ///
+    /// Run with `cargo test --release bench_drain -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_drain_1000_events() {
+        const FRAMES: u32 = 1000;
+        let ring = EventRing::with_capacity(1024);
+        let mut events = Vec::with_capacity(1000);
+        let mut total = Duration::ZERO;
+        for _ in 0..FRAMES {
+            for x in 0..1000 {
+                ring.push(&Event::mouse_moved(x as f64, 0.0));
+            }
+            events.clear();
+            let start = Instant::now();
+            ring.drain_into(&mut events);
+            total += start.elapsed();
+            assert_eq!(events.len(), 1000);
+        }
+        println!(
+            "drain of 1000 events: {:.1}µs per frame",
+            total.as_secs_f64() * 1e6 / FRAMES as f64
+        );
+    }
+}
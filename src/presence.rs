@@ -0,0 +1,358 @@
+//! Away and return detection.
+//!
+//! [`watch`] reports [`Presence::Away`] once there has been no input for a
+//! while, and [`Presence::Returned`] when input resumes, without collecting
+//! statistics. It runs its own listen hook, ignoring injected input so
+//! automation doesn't keep the user present.
+//!
+//! Returning is debounced: a key or button press counts right away, but
+//! motion (a nudged mouse, a brushed touchpad) has to go on for
+//! [`RETURN_DEBOUNCE`] before the user counts as back.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::presence::{self, Presence};
+//! use std::time::Duration;
+//!
+//! let (_watcher, rx) = presence::watch_channel(Duration::from_secs(300))?;
+//! for presence in rx {
+//!     match presence {
+//!         Presence::Away { since } => println!("away since {since:?}"),
+//!         Presence::Returned { away_for } => println!("back after {away_for:?}"),
+//!     }
+//! }
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::event::{Event, EventType};
+use crate::hook::Hook;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long motion has to go on, with no pause as long, to end an absence.
+pub const RETURN_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// A change in whether the user is at the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Presence {
+    /// No input since `since`, for at least the idle threshold.
+    Away {
+        /// When the last input came in.
+        since: SystemTime,
+    },
+    /// Input resumed after an absence.
+    Returned {
+        /// How long there was no input, from the last input before going
+        /// away to the first one of the return.
+        away_for: Duration,
+    },
+}
+
+/// Input as far as presence goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Activity {
+    /// A key or button press, which ends an absence right away.
+    Press,
+    /// Anything else, which has to go on to end an absence.
+    Motion,
+}
+
+impl Activity {
+    fn of(event: &Event) -> Option<Self> {
+        match event.event_type {
+            EventType::KeyPressed | EventType::MousePressed => Some(Self::Press),
+            // Follows its key press
+            EventType::KeyTyped | EventType::HookEnabled | EventType::HookDisabled => None,
+            _ => Some(Self::Motion),
+        }
+    }
+}
+
+/// The presence state machine, driven by explicit instants.
+#[derive(Debug)]
+struct Tracker {
+    threshold: Duration,
+    /// An instant and its wall-clock time, for [`Presence::Away`].
+    anchor: (Instant, SystemTime),
+    last_activity: Instant,
+    away: bool,
+    /// Start and latest instant of motion seen while away.
+    burst: Option<(Instant, Instant)>,
+}
+
+impl Tracker {
+    fn new(threshold: Duration, now: Instant, wall: SystemTime) -> Self {
+        Self {
+            threshold,
+            anchor: (now, wall),
+            last_activity: now,
+            away: false,
+            burst: None,
+        }
+    }
+
+    fn wall_time(&self, instant: Instant) -> SystemTime {
+        let (anchor, wall) = self.anchor;
+        wall + instant.saturating_duration_since(anchor)
+    }
+
+    /// When [`tick`](Self::tick) next has something to report.
+    fn deadline(&self) -> Option<Instant> {
+        (!self.away).then(|| self.last_activity + self.threshold)
+    }
+
+    /// Check for an absence at `now`.
+    fn tick(&mut self, now: Instant) -> Option<Presence> {
+        if self.away || now < self.last_activity + self.threshold {
+            return None;
+        }
+        self.away = true;
+        self.burst = None;
+        Some(Presence::Away {
+            since: self.wall_time(self.last_activity),
+        })
+    }
+
+    /// Record input at `now`.
+    fn activity(&mut self, activity: Activity, now: Instant) -> Option<Presence> {
+        if !self.away {
+            self.last_activity = self.last_activity.max(now);
+            return None;
+        }
+        let start = match (activity, self.burst) {
+            (Activity::Press, _) => now,
+            (Activity::Motion, Some((start, latest)))
+                if now.saturating_duration_since(latest) <= RETURN_DEBOUNCE =>
+            {
+                start
+            }
+            (Activity::Motion, _) => now,
+        };
+        self.burst = Some((start, now));
+        if activity == Activity::Motion && now.saturating_duration_since(start) < RETURN_DEBOUNCE {
+            return None;
+        }
+        self.away = false;
+        self.burst = None;
+        let away_for = start.saturating_duration_since(self.last_activity);
+        self.last_activity = now;
+        Some(Presence::Returned { away_for })
+    }
+}
+
+enum Message {
+    Activity(Activity, Instant),
+    Stop,
+}
+
+/// A running presence watch, stopped when dropped.
+///
+/// Created by [`watch`] and [`watch_channel`].
+pub struct PresenceWatcher {
+    hook: Hook,
+    control: Sender<Message>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl PresenceWatcher {
+    /// Stop watching and wait for the last callback to return.
+    pub fn stop(mut self) -> Result<()> {
+        self.stop_inner()
+    }
+
+    fn stop_inner(&mut self) -> Result<()> {
+        let stopped = match self.hook.stop() {
+            Ok(()) | Err(Error::NotRunning) => Ok(()),
+            Err(e) => Err(e),
+        };
+        let _ = self.control.send(Message::Stop);
+        if let Some(worker) = self.worker.take() {
+            worker
+                .join()
+                .map_err(|_| Error::ThreadError("presence callback panicked".into()))?;
+        }
+        stopped
+    }
+}
+
+impl Drop for PresenceWatcher {
+    fn drop(&mut self) {
+        let _ = self.stop_inner();
+    }
+}
+
+impl std::fmt::Debug for PresenceWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PresenceWatcher")
+            .field("running", &self.hook.is_running())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Call `callback` whenever the user goes away for `idle_threshold` or
+/// comes back.
+///
+/// The user counts as present when watching starts. The callback runs on a
+/// thread of its own, so it may block without holding up input.
+///
+/// # Example
+///
+/// ```no_run
+/// use monio::presence::{self, Presence};
+/// use std::time::Duration;
+///
+/// let watcher = presence::watch(Duration::from_secs(60), |presence| {
+///     if let Presence::Returned { away_for } = presence {
+///         println!("Welcome back! You were away for {}s", away_for.as_secs());
+///     }
+/// })?;
+/// # drop(watcher);
+/// # Ok::<(), monio::Error>(())
+/// ```
+pub fn watch<F>(idle_threshold: Duration, mut callback: F) -> Result<PresenceWatcher>
+where
+    F: FnMut(Presence) + Send + 'static,
+{
+    let (control, messages) = mpsc::channel();
+    let mut tracker = Tracker::new(idle_threshold, Instant::now(), SystemTime::now());
+    let worker = thread::Builder::new()
+        .name("monio-presence".into())
+        .spawn(move || {
+            loop {
+                let message = match tracker.deadline() {
+                    Some(deadline) => {
+                        let wait = deadline.saturating_duration_since(Instant::now());
+                        messages.recv_timeout(wait)
+                    }
+                    None => messages.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                let presence = match message {
+                    Ok(Message::Activity(activity, at)) => tracker.activity(activity, at),
+                    Err(RecvTimeoutError::Timeout) => tracker.tick(Instant::now()),
+                    Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                };
+                if let Some(presence) = presence {
+                    callback(presence);
+                }
+            }
+        })
+        .map_err(|e| Error::ThreadError(format!("failed to spawn presence thread: {}", e)))?;
+
+    let hook = Hook::new().ignore_injected(true);
+    let activity = control.clone();
+    let watcher = PresenceWatcher {
+        hook,
+        control,
+        worker: Some(worker),
+    };
+    watcher.hook.run_async(move |event: &Event| {
+        if let Some(kind) = Activity::of(event) {
+            let _ = activity.send(Message::Activity(kind, Instant::now()));
+        }
+    })?;
+    Ok(watcher)
+}
+
+/// Like [`watch`], but sends the changes to a channel.
+///
+/// The channel disconnects once the watcher is stopped.
+pub fn watch_channel(idle_threshold: Duration) -> Result<(PresenceWatcher, Receiver<Presence>)> {
+    let (sender, receiver) = mpsc::channel();
+    let watcher = watch(idle_threshold, move |presence| {
+        let _ = sender.send(presence);
+    })?;
+    Ok((watcher, receiver))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLD: Duration = Duration::from_secs(60);
+
+    struct Clock {
+        start: Instant,
+        wall: SystemTime,
+    }
+
+    impl Clock {
+        fn new() -> Self {
+            Self {
+                start: Instant::now(),
+                wall: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000),
+            }
+        }
+
+        fn at(&self, ms: u64) -> Instant {
+            self.start + Duration::from_millis(ms)
+        }
+
+        fn tracker(&self) -> Tracker {
+            Tracker::new(THRESHOLD, self.start, self.wall)
+        }
+    }
+
+    #[test]
+    fn test_away_after_threshold() {
+        let clock = Clock::new();
+        let mut tracker = clock.tracker();
+        assert_eq!(tracker.deadline(), Some(clock.at(60_000)));
+        assert_eq!(tracker.tick(clock.at(59_999)), None);
+
+        // Input pushes the deadline back
+        assert_eq!(tracker.activity(Activity::Motion, clock.at(10_000)), None);
+        assert_eq!(tracker.tick(clock.at(60_000)), None);
+        assert_eq!(tracker.deadline(), Some(clock.at(70_000)));
+
+        assert_eq!(
+            tracker.tick(clock.at(70_000)),
+            Some(Presence::Away {
+                since: clock.wall + Duration::from_secs(10)
+            })
+        );
+        // Reported once
+        assert_eq!(tracker.deadline(), None);
+        assert_eq!(tracker.tick(clock.at(80_000)), None);
+    }
+
+    #[test]
+    fn test_press_returns_immediately() {
+        let clock = Clock::new();
+        let mut tracker = clock.tracker();
+        tracker.tick(clock.at(60_000)).unwrap();
+        assert_eq!(
+            tracker.activity(Activity::Press, clock.at(90_000)),
+            Some(Presence::Returned {
+                away_for: Duration::from_secs(90)
+            })
+        );
+        assert_eq!(tracker.deadline(), Some(clock.at(150_000)));
+    }
+
+    #[test]
+    fn test_twitch_does_not_return() {
+        let clock = Clock::new();
+        let mut tracker = clock.tracker();
+        tracker.tick(clock.at(60_000)).unwrap();
+
+        // A bumped mouse: a few moves within 100 ms
+        for ms in [100_000, 100_030, 100_060, 100_100] {
+            assert_eq!(tracker.activity(Activity::Motion, clock.at(ms)), None);
+        }
+        // Another bump after a pause starts over
+        assert_eq!(tracker.activity(Activity::Motion, clock.at(100_700)), None);
+        assert_eq!(tracker.activity(Activity::Motion, clock.at(101_000)), None);
+        assert_eq!(tracker.tick(clock.at(200_000)), None);
+
+        // Steady motion does return, timed from its start
+        assert_eq!(
+            tracker.activity(Activity::Motion, clock.at(101_200)),
+            Some(Presence::Returned {
+                away_for: Duration::from_millis(100_700)
+            })
+        );
+    }
+}
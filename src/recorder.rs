@@ -26,26 +26,180 @@
 //! recording.playback().unwrap();
 //! ```
 
+mod diff;
+mod flight;
+mod playback;
+mod redact;
+mod repeat;
+mod script;
+
+pub use diff::{DiffTolerance, RecordingDiff, SequenceChange, TimingDifference};
+pub use flight::{FlightOptions, FlightRecorder};
+pub use playback::{CatchUp, PlaybackHandle, PlaybackOptions, TimingStats, VerificationFailure};
+pub use redact::{KeyRedaction, PositionRedaction, RedactionOptions, TextRedaction};
+pub use repeat::RepeatedSegment;
+pub use script::{ScriptImport, UnsupportedLine};
+
 use crate::Hook;
+use crate::display::DisplayInfo;
 use crate::error::{Error, Result};
-use crate::event::{Event, EventType};
+use crate::event::{Event, EventType, FORMAT_VERSION};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime};
 
-/// A recorded event with its timestamp relative to recording start.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A recorded event with its timestamp relative to the first recorded event.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RecordedEvent {
-    /// Time elapsed since recording start.
+    /// Time elapsed since the first recorded event.
+    ///
+    /// Derived from the event's own timestamp rather than the time the
+    /// recorder saw it, so intervals don't pick up dispatch jitter. Never
+    /// decreases, even if the wall clock is adjusted while recording.
     pub elapsed: Duration,
+    /// Wall-clock time of the event, for correlating with external logs.
+    ///
+    /// `None` in recordings made before this field existed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wall_time: Option<SystemTime>,
     /// The event that occurred.
     pub event: Event,
 }
 
+/// Turns event timestamps into monotonic offsets from the first event.
+#[derive(Debug, Default)]
+pub(crate) struct Timeline {
+    /// Timestamp of the first event, and when it was seen.
+    anchor: Option<(SystemTime, Instant)>,
+    last: Duration,
+}
+
+impl Timeline {
+    pub(crate) fn record(&mut self, event: &Event) -> RecordedEvent {
+        let elapsed = match self.anchor {
+            None => {
+                self.anchor = Some((event.time, Instant::now()));
+                Duration::ZERO
+            }
+            // If the wall clock stepped backwards, fall back to when we saw it
+            Some((time, seen)) => event
+                .time
+                .duration_since(time)
+                .unwrap_or_else(|_| seen.elapsed()),
+        };
+        self.last = self.last.max(elapsed);
+
+        RecordedEvent {
+            elapsed: self.last,
+            wall_time: Some(event.time),
+            event: event.clone(),
+        }
+    }
+
+    /// Time elapsed on this timeline, `None` before the first event.
+    pub(crate) fn now(&self) -> Option<Duration> {
+        self.anchor.map(|(_, seen)| self.last.max(seen.elapsed()))
+    }
+}
+
+/// The environment a recording was made in.
+///
+/// Captured by [`EventRecorder::start_recording`] on a best-effort basis:
+/// whatever the platform can't report is left empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct RecordingMetadata {
+    /// Format the recording is in, see [`FORMAT_VERSION`]. `0` for
+    /// recordings saved before formats were versioned.
+    pub format_version: u32,
+    /// monio version that made the recording.
+    pub monio_version: String,
+    /// Operating system (`std::env::consts::OS`).
+    pub os: String,
+    /// Input backend, e.g. `"x11"` or `"evdev"`.
+    pub backend: String,
+    /// Displays connected while recording.
+    pub displays: Vec<DisplayInfo>,
+    /// Keyboard layout identifier.
+    pub keyboard_layout: Option<String>,
+    /// Whether "natural" scrolling was enabled.
+    pub natural_scrolling: Option<bool>,
+    /// Fields written by newer versions, kept so they survive a re-save.
+    #[cfg(feature = "serde")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl RecordingMetadata {
+    /// Capture the current environment.
+    pub fn capture() -> Self {
+        let settings = crate::display::system_settings().ok();
+        Self {
+            format_version: FORMAT_VERSION,
+            monio_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            backend: crate::platform::BACKEND.to_string(),
+            displays: crate::display::displays().unwrap_or_default(),
+            keyboard_layout: settings.as_ref().and_then(|s| s.keyboard_layout.clone()),
+            natural_scrolling: settings.and_then(|s| s.natural_scrolling),
+            #[cfg(feature = "serde")]
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    /// Ways `current` differs from this environment that may affect playback.
+    ///
+    /// Only differences both sides know about are reported.
+    pub fn differences(&self, current: &RecordingMetadata) -> Vec<String> {
+        let mut differences = Vec::new();
+        if !self.os.is_empty() && !current.os.is_empty() && self.os != current.os {
+            differences.push(format!(
+                "recorded on {}, playing back on {}",
+                self.os, current.os
+            ));
+        }
+        if !self.displays.is_empty()
+            && !current.displays.is_empty()
+            && self.displays.len() != current.displays.len()
+        {
+            differences.push(format!(
+                "recorded with {} display(s), {} connected now",
+                self.displays.len(),
+                current.displays.len()
+            ));
+        }
+        if let (Some(recorded), Some(now)) = (&self.keyboard_layout, &current.keyboard_layout)
+            && recorded != now
+        {
+            differences.push(format!(
+                "recorded with keyboard layout {}, current layout is {}",
+                recorded, now
+            ));
+        }
+        if let (Some(recorded), Some(now)) = (self.natural_scrolling, current.natural_scrolling)
+            && recorded != now
+        {
+            differences.push("natural scrolling setting differs".to_string());
+        }
+        differences
+    }
+}
+
+/// Upgrades from each format version to the next, indexed by the version
+/// they upgrade from.
+const MIGRATIONS: [fn(&mut Recording) -> Result<()>; FORMAT_VERSION as usize] = [
+    // 0 -> 1: fields added until then load with their defaults
+    |_| Ok(()),
+];
+
 /// A complete recording of user input events.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Recording {
     /// Recorded events with timestamps.
     pub events: Vec<RecordedEvent>,
@@ -53,6 +207,15 @@ pub struct Recording {
     pub created_at: SystemTime,
     /// Optional description.
     pub description: Option<String>,
+    /// The environment the recording was made in.
+    ///
+    /// Empty in recordings made before this field existed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: RecordingMetadata,
+    /// Fields written by newer versions, kept so they survive a re-save.
+    #[cfg(feature = "serde")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Recording {
@@ -62,6 +225,12 @@ impl Recording {
             events: Vec::new(),
             created_at: SystemTime::now(),
             description: None,
+            metadata: RecordingMetadata {
+                format_version: FORMAT_VERSION,
+                ..RecordingMetadata::default()
+            },
+            #[cfg(feature = "serde")]
+            extra: serde_json::Map::new(),
         }
     }
 
@@ -85,6 +254,7 @@ impl Recording {
     }
 
     /// Save the recording to a file (JSON format).
+    #[cfg(feature = "serde")]
     pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
         let json = serde_json::to_string_pretty(self)
             .map_err(|e| Error::Other(format!("Failed to serialize recording: {}", e)))?;
@@ -94,17 +264,113 @@ impl Recording {
     }
 
     /// Load a recording from a file (JSON format).
+    ///
+    /// Recordings in older formats are upgraded, see
+    /// [`upgrade_in_place`](Self::upgrade_in_place).
+    #[cfg(feature = "serde")]
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let json = std::fs::read_to_string(path)
             .map_err(|e| Error::Other(format!("Failed to read recording file: {}", e)))?;
-        let recording: Recording = serde_json::from_str(&json)
+        let mut recording: Recording = serde_json::from_str(&json)
             .map_err(|e| Error::Other(format!("Failed to deserialize recording: {}", e)))?;
+        recording.upgrade_in_place()?;
         Ok(recording)
     }
 
+    /// Bring a recording in an older format up to [`FORMAT_VERSION`].
+    ///
+    /// Runs the migration of each version in turn and updates
+    /// [`RecordingMetadata::format_version`]. Recordings from newer versions
+    /// of monio are left alone; fields they added are kept in `extra`.
+    pub fn upgrade_in_place(&mut self) -> Result<()> {
+        while let Some(migrate) = MIGRATIONS.get(self.metadata.format_version as usize) {
+            migrate(self)?;
+            self.metadata.format_version += 1;
+        }
+        Ok(())
+    }
+
+    /// Check the current environment against the recording's metadata.
+    ///
+    /// Returns a description of each material difference (display count,
+    /// keyboard layout, ...) that may make playback go wrong. Playback logs
+    /// these as warnings but carries on.
+    pub fn validate(&self) -> Vec<String> {
+        self.metadata.differences(&RecordingMetadata::capture())
+    }
+
+    fn warn_about_environment(&self) {
+        for difference in self.validate() {
+            log::warn!("Recording environment differs: {}", difference);
+        }
+    }
+
+    /// Summarize the recording and its metadata for humans.
+    pub fn describe(&self) -> String {
+        let meta = &self.metadata;
+        let unknown = |value: &str| {
+            if value.is_empty() {
+                "unknown".to_string()
+            } else {
+                value.to_string()
+            }
+        };
+        let created = self
+            .created_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let mut out = String::new();
+        if let Some(description) = &self.description {
+            out.push_str(&format!("{}\n", description));
+        }
+        out.push_str(&format!(
+            "{} events over {:.3}s, created at {} (unix time)\n",
+            self.event_count(),
+            self.duration().as_secs_f64(),
+            created
+        ));
+        out.push_str(&format!(
+            "monio {} on {} ({} backend)\n",
+            unknown(&meta.monio_version),
+            unknown(&meta.os),
+            unknown(&meta.backend)
+        ));
+        out.push_str(&format!("Displays: {}\n", meta.displays.len()));
+        for display in &meta.displays {
+            let b = display.bounds;
+            out.push_str(&format!(
+                "  #{}: {}x{} at ({}, {}), scale {}{}\n",
+                display.id,
+                b.width,
+                b.height,
+                b.x,
+                b.y,
+                display.scale_factor,
+                if display.is_primary { ", primary" } else { "" }
+            ));
+        }
+        out.push_str(&format!(
+            "Keyboard layout: {}\n",
+            meta.keyboard_layout.as_deref().unwrap_or("unknown")
+        ));
+        out.push_str(&format!(
+            "Natural scrolling: {}",
+            match meta.natural_scrolling {
+                Some(true) => "on",
+                Some(false) => "off",
+                None => "unknown",
+            }
+        ));
+        out
+    }
+
     /// Playback this recording, simulating all recorded events.
     ///
-    /// Events are replayed with their original timing intervals.
+    /// Events are replayed with their original timing intervals; see
+    /// [`playback_with_options`](Self::playback_with_options) for what
+    /// happens when playback falls behind.
     pub fn playback(&self) -> Result<()> {
         self.playback_with_speed(1.0)
     }
@@ -125,51 +391,100 @@ impl Recording {
     /// recording.playback_with_speed(2.0).unwrap();
     /// ```
     pub fn playback_with_speed(&self, speed: f64) -> Result<()> {
-        if speed <= 0.0 {
-            return Err(Error::Other("Playback speed must be positive".into()));
-        }
-
-        if self.events.is_empty() {
-            return Ok(());
-        }
-
-        let start = Instant::now();
-        let mut _last_elapsed = Duration::ZERO;
+        self.playback_with_options(PlaybackOptions::new().speed(speed))
+            .map(drop)
+    }
 
-        for recorded in &self.events {
-            // Skip HookEnabled/HookDisabled events during playback
-            match recorded.event.event_type {
-                EventType::HookEnabled | EventType::HookDisabled => continue,
-                _ => {}
-            }
+    /// Playback this recording into one window, posting events to it
+    /// instead of simulating them for the focused one.
+    ///
+    /// The window doesn't need focus, so the recording can replay into a
+    /// background app while the user keeps working. Subject to the
+    /// limitations of [`send_to_window`](crate::simulation::send_to_window).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::recorder::Recording;
+    /// use monio::simulation::find_window;
+    ///
+    /// let recording = Recording::load("macro.json").unwrap();
+    /// let window = &find_window("Notepad")[0];
+    /// recording.playback_to_window(window, 1.0).unwrap();
+    /// ```
+    #[cfg(all(target_os = "windows", feature = "windows-targeted"))]
+    pub fn playback_to_window(
+        &self,
+        target: impl Into<crate::simulation::WindowTarget>,
+        speed: f64,
+    ) -> Result<()> {
+        let target = target.into();
+        self.play(&PlaybackOptions::new().speed(speed), |_, event| {
+            crate::simulation::send_to_window(target, event)
+        })
+        .map(drop)
+    }
 
-            // Calculate target time with speed adjustment
-            let target_elapsed = recorded.elapsed.as_secs_f64() / speed;
-            let target_duration = Duration::from_secs_f64(target_elapsed);
+    /// Playback this recording into one app, posting events to it instead
+    /// of simulating them for the frontmost one.
+    ///
+    /// A bundle ID is resolved to a process once, before playback starts.
+    /// Subject to the limitations of
+    /// [`send_to_app`](crate::simulation::send_to_app).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::recorder::Recording;
+    /// use monio::simulation::AppTarget;
+    ///
+    /// let recording = Recording::load("macro.json").unwrap();
+    /// let editor = AppTarget::BundleId("com.apple.TextEdit".into());
+    /// recording.playback_to_app(editor, 1.0).unwrap();
+    /// ```
+    #[cfg(all(target_os = "macos", feature = "macos-targeted"))]
+    pub fn playback_to_app(
+        &self,
+        target: impl Into<crate::simulation::AppTarget>,
+        speed: f64,
+    ) -> Result<()> {
+        let target =
+            crate::simulation::AppTarget::Pid(crate::platform::resolve_pid(&target.into())?);
+        self.play(&PlaybackOptions::new().speed(speed), |_, event| {
+            crate::simulation::send_to_app(target.clone(), event)
+        })
+        .map(drop)
+    }
 
-            // Wait until it's time for this event
-            let elapsed = start.elapsed();
-            if target_duration > elapsed {
-                std::thread::sleep(target_duration - elapsed);
+    /// Playback this recording `times` times, waiting `gap` between the end
+    /// of one playback and the start of the next.
+    ///
+    /// Pairs with [`extract`](Self::extract) to replay a detected loop.
+    pub fn playback_repeated(&self, times: usize, gap: Duration) -> Result<()> {
+        for i in 0..times {
+            if i > 0 {
+                std::thread::sleep(gap);
             }
-
-            // Simulate the event
-            crate::platform::simulate(&recorded.event)?;
-
-            _last_elapsed = recorded.elapsed;
+            self.playback()?;
         }
-
         Ok(())
     }
 
     /// Playback without timing (as fast as possible).
     pub fn playback_fast(&self) -> Result<()> {
+        self.warn_about_environment();
         for recorded in &self.events {
             match recorded.event.event_type {
-                EventType::HookEnabled | EventType::HookDisabled => continue,
+                EventType::HookEnabled
+                | EventType::HookDisabled
+                | EventType::GrabSuspended
+                | EventType::GrabDegraded
+                | EventType::GlobalSuspendChanged => {
+                    continue;
+                }
                 _ => {}
             }
-            crate::platform::simulate(&recorded.event)?;
+            crate::simulate(&recorded.event)?;
         }
         Ok(())
     }
@@ -184,9 +499,10 @@ impl Default for Recording {
 /// Records user input events for later playback.
 pub struct EventRecorder {
     recording: Arc<Mutex<Option<Recording>>>,
-    start_time: Arc<Mutex<Option<Instant>>>,
+    timeline: Arc<Mutex<Timeline>>,
     hook: Option<Hook>,
     running: Arc<AtomicBool>,
+    motion_min_distance: f64,
 }
 
 impl EventRecorder {
@@ -194,12 +510,22 @@ impl EventRecorder {
     pub fn new() -> Self {
         Self {
             recording: Arc::new(Mutex::new(None)),
-            start_time: Arc::new(Mutex::new(None)),
+            timeline: Arc::new(Mutex::new(Timeline::default())),
             hook: None,
             running: Arc::new(AtomicBool::new(false)),
+            motion_min_distance: 0.0,
         }
     }
 
+    /// Only record mouse moves at least `distance` screen points apart.
+    ///
+    /// See [`Hook::motion_min_distance`]; button and wheel events are still
+    /// recorded at the exact cursor position.
+    pub fn with_motion_min_distance(mut self, distance: f64) -> Self {
+        self.motion_min_distance = distance;
+        self
+    }
+
     /// Start recording events.
     ///
     /// This starts a background hook that captures all input events.
@@ -210,7 +536,7 @@ impl EventRecorder {
         }
 
         let recording = self.recording.clone();
-        let start_time = self.start_time.clone();
+        let timeline = self.timeline.clone();
         let running = self.running.clone();
 
         // Initialize recording
@@ -218,17 +544,20 @@ impl EventRecorder {
             let mut rec = recording
                 .lock()
                 .map_err(|_| Error::ThreadError("recording mutex poisoned".into()))?;
-            *rec = Some(Recording::new());
+            *rec = Some(Recording {
+                metadata: RecordingMetadata::capture(),
+                ..Recording::new()
+            });
         }
         {
-            let mut time = start_time
+            let mut timeline = timeline
                 .lock()
-                .map_err(|_| Error::ThreadError("time mutex poisoned".into()))?;
-            *time = Some(Instant::now());
+                .map_err(|_| Error::ThreadError("timeline mutex poisoned".into()))?;
+            *timeline = Timeline::default();
         }
 
         // Create hook
-        let hook = Hook::new();
+        let hook = Hook::new().motion_min_distance(self.motion_min_distance);
 
         // Start recording in background
         hook.run_async(move |event: &Event| {
@@ -238,21 +567,19 @@ impl EventRecorder {
 
             // Skip hook lifecycle events in recording
             match event.event_type {
-                EventType::HookEnabled | EventType::HookDisabled => return,
+                EventType::HookEnabled
+                | EventType::HookDisabled
+                | EventType::GrabSuspended
+                | EventType::GrabDegraded
+                | EventType::GlobalSuspendChanged => {
+                    return;
+                }
                 _ => {}
             }
 
-            let elapsed = {
-                let time = start_time.lock();
-                match time {
-                    Ok(t) => t.map(|instant| instant.elapsed()).unwrap_or(Duration::ZERO),
-                    Err(_) => return, // Mutex poisoned, skip this event
-                }
-            };
-
-            let recorded = RecordedEvent {
-                elapsed,
-                event: event.clone(),
+            let recorded = match timeline.lock() {
+                Ok(mut timeline) => timeline.record(event),
+                Err(_) => return, // Mutex poisoned, skip this event
             };
 
             if let Ok(ref mut r) = recording.lock()
@@ -334,18 +661,26 @@ mod tests {
         let mut recording = Recording::new();
         recording.events.push(RecordedEvent {
             elapsed: Duration::from_secs(5),
+            wall_time: None,
             event: Event::new(EventType::KeyPressed),
         });
         assert_eq!(recording.duration(), Duration::from_secs(5));
     }
 
+    #[cfg(feature = "serde")]
     #[test]
     fn test_save_load_roundtrip() {
         let mut recording = Recording::new().with_description("Test");
         recording.events.push(RecordedEvent {
             elapsed: Duration::from_millis(100),
+            wall_time: None,
             event: Event::key_pressed(crate::Key::KeyA, 30),
         });
+        recording.events.push(RecordedEvent {
+            elapsed: Duration::from_millis(200),
+            wall_time: None,
+            event: Event::key_pressed(crate::Key::Char('§'), 49),
+        });
 
         let temp_path = std::env::temp_dir().join("monio_test_recording.json");
         recording.save(&temp_path).unwrap();
@@ -353,7 +688,159 @@ mod tests {
         let loaded = Recording::load(&temp_path).unwrap();
         assert_eq!(loaded.description, recording.description);
         assert_eq!(loaded.event_count(), recording.event_count());
+        assert_eq!(
+            loaded.events[1].event.keyboard.as_ref().map(|kb| kb.key),
+            Some(crate::Key::Char('§'))
+        );
 
         std::fs::remove_file(&temp_path).unwrap();
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_key_serde_representation() {
+        // Recordings on disk depend on this staying stable
+        assert_eq!(
+            serde_json::to_string(&crate::Key::KeyA).unwrap(),
+            r#""KeyA""#
+        );
+        assert_eq!(
+            serde_json::to_string(&crate::Key::Char('§')).unwrap(),
+            r#"{"Char":"§"}"#
+        );
+        let key: crate::Key = serde_json::from_str(r#"{"Char":"ö"}"#).unwrap();
+        assert_eq!(key, crate::Key::Char('ö'));
+    }
+
+    #[test]
+    fn test_intervals_come_from_event_timestamps() {
+        let base = SystemTime::now();
+        let mut timeline = Timeline::default();
+        let mut recorded = Vec::new();
+
+        // Precisely spaced events, delivered with uneven delays
+        for (i, delay) in [0, 3, 0, 9, 1].into_iter().enumerate() {
+            std::thread::sleep(Duration::from_millis(delay));
+            let mut event = Event::key_pressed(crate::Key::KeyA, 30);
+            event.time = base + Duration::from_millis(20 * i as u64);
+            recorded.push(timeline.record(&event));
+        }
+
+        let elapsed: Vec<Duration> = recorded.iter().map(|r| r.elapsed).collect();
+        let expected: Vec<Duration> = (0..5).map(|i| Duration::from_millis(20 * i)).collect();
+        assert_eq!(elapsed, expected);
+        assert_eq!(
+            recorded[2].wall_time,
+            Some(base + Duration::from_millis(40))
+        );
+
+        // A wall-clock step backwards doesn't reorder the timeline
+        let mut event = Event::key_released(crate::Key::KeyA, 30);
+        event.time = base - Duration::from_secs(60);
+        assert_eq!(timeline.record(&event).elapsed, Duration::from_millis(80));
+    }
+
+    #[test]
+    fn test_upgrade_in_place() {
+        let mut recording = Recording::new();
+        assert_eq!(recording.metadata.format_version, FORMAT_VERSION);
+
+        recording.metadata.format_version = 0;
+        recording.upgrade_in_place().unwrap();
+        assert_eq!(recording.metadata.format_version, FORMAT_VERSION);
+
+        // Newer formats are left as they are
+        recording.metadata.format_version = FORMAT_VERSION + 1;
+        recording.upgrade_in_place().unwrap();
+        assert_eq!(recording.metadata.format_version, FORMAT_VERSION + 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_recording_without_wall_time() {
+        let mut json = serde_json::to_value(Recording::new()).unwrap();
+        let event = serde_json::to_value(Event::key_pressed(crate::Key::KeyA, 30)).unwrap();
+        json["events"] = serde_json::json!([{
+            "elapsed": { "secs": 1, "nanos": 0 },
+            "event": event,
+        }]);
+
+        let recording: Recording = serde_json::from_value(json).unwrap();
+        assert_eq!(recording.events[0].elapsed, Duration::from_secs(1));
+        assert_eq!(recording.events[0].wall_time, None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_metadata_roundtrip_and_unknown_fields() {
+        let mut recording = Recording::new();
+        recording.metadata = RecordingMetadata {
+            monio_version: "0.1.0".into(),
+            os: "linux".into(),
+            backend: "x11".into(),
+            keyboard_layout: Some("us".into()),
+            ..RecordingMetadata::default()
+        };
+
+        let mut json = serde_json::to_value(&recording).unwrap();
+        json["from_the_future"] = serde_json::json!([1, 2]);
+        json["metadata"]["pointer_speed"] = serde_json::json!(3);
+
+        let loaded: Recording = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.metadata.keyboard_layout.as_deref(), Some("us"));
+        assert_eq!(loaded.extra["from_the_future"], serde_json::json!([1, 2]));
+        assert_eq!(loaded.metadata.extra["pointer_speed"], serde_json::json!(3));
+
+        // Written back out unchanged
+        let again = serde_json::to_value(&loaded).unwrap();
+        assert_eq!(again["from_the_future"], serde_json::json!([1, 2]));
+        assert_eq!(again["metadata"]["pointer_speed"], serde_json::json!(3));
+
+        let description = loaded.describe();
+        assert!(description.contains("monio 0.1.0 on linux (x11 backend)"));
+        assert!(description.contains("Keyboard layout: us"));
+    }
+
+    #[test]
+    fn test_metadata_differences() {
+        let display = |id| DisplayInfo {
+            id,
+            bounds: crate::display::Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 100.0,
+                height: 100.0,
+            },
+            scale_factor: 1.0,
+            refresh_rate: None,
+            is_primary: false,
+            source: crate::display::DisplaySource::WindowSystem,
+        };
+        let recorded = RecordingMetadata {
+            os: "windows".into(),
+            displays: vec![display(1), display(2)],
+            keyboard_layout: Some("00000409".into()),
+            ..RecordingMetadata::default()
+        };
+
+        let same = RecordingMetadata {
+            displays: vec![display(3), display(4)],
+            ..recorded.clone()
+        };
+        assert!(recorded.differences(&same).is_empty());
+
+        let changed = RecordingMetadata {
+            displays: vec![display(1)],
+            keyboard_layout: Some("00000407".into()),
+            ..recorded.clone()
+        };
+        assert_eq!(recorded.differences(&changed).len(), 2);
+
+        // Old recordings without metadata never warn
+        assert!(
+            RecordingMetadata::default()
+                .differences(&changed)
+                .is_empty()
+        );
+    }
 }
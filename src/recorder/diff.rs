@@ -0,0 +1,463 @@
+//! Comparing recordings, see [`Recording::diff`].
+
+use super::{RecordedEvent, Recording};
+use crate::event::{Button, EventType, ScrollDirection};
+use crate::keycode::Key;
+use std::time::Duration;
+
+/// How far two recordings may differ and still count as equivalent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffTolerance {
+    /// Largest allowed difference in when matching events happen.
+    pub timing: Duration,
+    /// Largest allowed average distance between the mouse paths, in pixels.
+    pub distance: f64,
+}
+
+impl Default for DiffTolerance {
+    fn default() -> Self {
+        Self {
+            timing: Duration::from_millis(50),
+            distance: 5.0,
+        }
+    }
+}
+
+/// An event found in only one of the two recordings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceChange {
+    /// The event at `index` in the original recording has no counterpart.
+    Removed {
+        /// Index into the original recording's events.
+        index: usize,
+        /// Type of the event.
+        event_type: EventType,
+    },
+    /// The event at `index` in the other recording has no counterpart.
+    Added {
+        /// Index into the other recording's events.
+        index: usize,
+        /// Type of the event.
+        event_type: EventType,
+    },
+}
+
+/// A pair of matching events further apart in time than the tolerance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingDifference {
+    /// Index into the original recording's events.
+    pub index: usize,
+    /// Index into the other recording's events.
+    pub other_index: usize,
+    /// When the event happened in the original recording.
+    pub elapsed: Duration,
+    /// When the event happened in the other recording.
+    pub other_elapsed: Duration,
+}
+
+/// Differences between two recordings, see [`Recording::diff`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordingDiff {
+    /// Key, button and wheel events without a counterpart, in order.
+    pub sequence: Vec<SequenceChange>,
+    /// Matching events whose timing differs by more than the tolerance.
+    pub timing: Vec<TimingDifference>,
+    /// Largest timing difference between any two matching events.
+    pub max_timing_delta: Duration,
+    /// Average distance between the mouse paths, in pixels.
+    ///
+    /// `None` unless both recordings contain mouse movement.
+    pub mouse_path_deviation: Option<f64>,
+}
+
+impl RecordingDiff {
+    /// Whether the recordings match within `tolerance`.
+    ///
+    /// The same key, button and wheel events must occur in the same order,
+    /// each within `tolerance.timing` of its counterpart, and the mouse
+    /// paths must deviate by at most `tolerance.distance` on average.
+    pub fn is_equivalent(&self, tolerance: DiffTolerance) -> bool {
+        self.sequence.is_empty()
+            && self.max_timing_delta <= tolerance.timing
+            && self
+                .mouse_path_deviation
+                .is_none_or(|deviation| deviation <= tolerance.distance)
+    }
+}
+
+impl Recording {
+    /// Compare this recording with `other`.
+    ///
+    /// Key, button and wheel events are aligned by longest common
+    /// subsequence, matching on event type, key, character, button and
+    /// scroll direction. Mouse moves are compared as paths instead, since
+    /// playback rarely reproduces them one for one: both paths are
+    /// interpolated at each other's timestamps and the distances averaged.
+    ///
+    /// Alignment takes time and memory proportional to the product of the
+    /// two event counts, after skipping any common prefix and suffix.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::recorder::{DiffTolerance, Recording};
+    ///
+    /// let source = Recording::load("macro.json").unwrap();
+    /// let replayed = Recording::load("replayed.json").unwrap();
+    /// let diff = source.diff(&replayed, DiffTolerance::default());
+    /// assert!(diff.is_equivalent(DiffTolerance::default()), "{:?}", diff);
+    /// ```
+    pub fn diff(&self, other: &Recording, tolerance: DiffTolerance) -> RecordingDiff {
+        let (indices, signatures) = discrete_events(&self.events);
+        let (other_indices, other_signatures) = discrete_events(&other.events);
+
+        let mut diff = RecordingDiff {
+            mouse_path_deviation: path_deviation(
+                &mouse_path(&self.events),
+                &mouse_path(&other.events),
+            ),
+            ..RecordingDiff::default()
+        };
+
+        for step in align(&signatures, &other_signatures) {
+            match step {
+                Step::Match(a, b) => {
+                    let (index, other_index) = (indices[a], other_indices[b]);
+                    let elapsed = self.events[index].elapsed;
+                    let other_elapsed = other.events[other_index].elapsed;
+                    let delta = elapsed.abs_diff(other_elapsed);
+                    diff.max_timing_delta = diff.max_timing_delta.max(delta);
+                    if delta > tolerance.timing {
+                        diff.timing.push(TimingDifference {
+                            index,
+                            other_index,
+                            elapsed,
+                            other_elapsed,
+                        });
+                    }
+                }
+                Step::Removed(a) => diff.sequence.push(SequenceChange::Removed {
+                    index: indices[a],
+                    event_type: signatures[a].event_type,
+                }),
+                Step::Added(b) => diff.sequence.push(SequenceChange::Added {
+                    index: other_indices[b],
+                    event_type: other_signatures[b].event_type,
+                }),
+            }
+        }
+        diff
+    }
+}
+
+/// What has to match for two events to count as the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Signature {
+    event_type: EventType,
+    key: Option<Key>,
+    char: Option<char>,
+    button: Option<Button>,
+    direction: Option<ScrollDirection>,
+}
+
+/// Key, button and wheel events, with their indices.
+pub(super) fn discrete_events(events: &[RecordedEvent]) -> (Vec<usize>, Vec<Signature>) {
+    events
+        .iter()
+        .enumerate()
+        .filter(|(_, recorded)| {
+            matches!(
+                recorded.event.event_type,
+                EventType::KeyPressed
+                    | EventType::KeyReleased
+                    | EventType::KeyTyped
+                    | EventType::MousePressed
+                    | EventType::MouseReleased
+                    | EventType::MouseClicked
+                    | EventType::MouseWheel
+            )
+        })
+        .map(|(index, recorded)| {
+            let event = &recorded.event;
+            let signature = Signature {
+                event_type: event.event_type,
+                key: event.keyboard.as_ref().map(|kb| kb.key),
+                char: event.keyboard.as_ref().and_then(|kb| kb.char),
+                button: event.mouse.as_ref().and_then(|m| m.button),
+                direction: event.wheel.as_ref().map(|w| w.direction),
+            };
+            (index, signature)
+        })
+        .unzip()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Match(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Longest-common-subsequence alignment of `a` and `b`.
+fn align<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Step> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (mid_a, mid_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    // lengths[i * width + j]: LCS length of mid_a[i..] and mid_b[j..]
+    let width = mid_b.len() + 1;
+    let mut lengths = vec![0u32; (mid_a.len() + 1) * width];
+    for i in (0..mid_a.len()).rev() {
+        for j in (0..mid_b.len()).rev() {
+            lengths[i * width + j] = if mid_a[i] == mid_b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut steps: Vec<Step> = (0..prefix).map(|i| Step::Match(i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < mid_a.len() || j < mid_b.len() {
+        if i < mid_a.len() && j < mid_b.len() && mid_a[i] == mid_b[j] {
+            steps.push(Step::Match(prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if j == mid_b.len()
+            || (i < mid_a.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+        {
+            steps.push(Step::Removed(prefix + i));
+            i += 1;
+        } else {
+            steps.push(Step::Added(prefix + j));
+            j += 1;
+        }
+    }
+    steps.extend((0..suffix).map(|k| Step::Match(a.len() - suffix + k, b.len() - suffix + k)));
+    steps
+}
+
+/// Mouse positions over time (seconds since the start of the recording).
+fn mouse_path(events: &[RecordedEvent]) -> Vec<(f64, f64, f64)> {
+    events
+        .iter()
+        .filter(|recorded| {
+            matches!(
+                recorded.event.event_type,
+                EventType::MouseMoved | EventType::MouseDragged
+            )
+        })
+        .filter_map(|recorded| {
+            let mouse = recorded.event.mouse.as_ref()?;
+            Some((recorded.elapsed.as_secs_f64(), mouse.x, mouse.y))
+        })
+        .collect()
+}
+
+/// Position along `path` at time `t`, holding still outside its time span.
+fn position_at(path: &[(f64, f64, f64)], t: f64) -> (f64, f64) {
+    let next = path.partition_point(|&(time, _, _)| time <= t);
+    if next == 0 {
+        return (path[0].1, path[0].2);
+    }
+    let (t0, x0, y0) = path[next - 1];
+    let Some(&(t1, x1, y1)) = path.get(next) else {
+        return (x0, y0);
+    };
+    let f = (t - t0) / (t1 - t0);
+    (x0 + (x1 - x0) * f, y0 + (y1 - y0) * f)
+}
+
+/// Average distance between two paths, sampled at both paths' timestamps.
+fn path_deviation(a: &[(f64, f64, f64)], b: &[(f64, f64, f64)]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let distances = |from: &[(f64, f64, f64)], to: &[(f64, f64, f64)]| {
+        from.iter()
+            .map(|&(t, x, y)| {
+                let (px, py) = position_at(to, t);
+                (x - px).hypot(y - py)
+            })
+            .sum::<f64>()
+    };
+    Some((distances(a, b) + distances(b, a)) / (a.len() + b.len()) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+
+    /// Build a recording from `(milliseconds, event)` pairs.
+    fn fixture(events: Vec<(u64, Event)>) -> Recording {
+        let mut recording = Recording::new();
+        recording.events = events
+            .into_iter()
+            .map(|(ms, event)| RecordedEvent {
+                elapsed: Duration::from_millis(ms),
+                wall_time: None,
+                event,
+            })
+            .collect();
+        recording
+    }
+
+    /// Typing "hi", then dragging diagonally from (0, 0) to (100, 100).
+    fn typing_and_drag() -> Recording {
+        let mut events = vec![
+            (0, Event::key_pressed(Key::KeyH, 35)),
+            (40, Event::key_released(Key::KeyH, 35)),
+            (80, Event::key_pressed(Key::KeyI, 23)),
+            (120, Event::key_released(Key::KeyI, 23)),
+            (200, Event::mouse_pressed(Button::Left, 0.0, 0.0)),
+        ];
+        for step in 1..=10 {
+            let p = step as f64 * 10.0;
+            events.push((200 + step * 20, Event::mouse_dragged(p, p)));
+        }
+        events.push((420, Event::mouse_released(Button::Left, 100.0, 100.0)));
+        fixture(events)
+    }
+
+    #[test]
+    fn test_identical_recordings_are_equivalent() {
+        let recording = typing_and_drag();
+        let diff = recording.diff(&recording, DiffTolerance::default());
+        assert!(diff.sequence.is_empty());
+        assert!(diff.timing.is_empty());
+        assert_eq!(diff.max_timing_delta, Duration::ZERO);
+        assert_eq!(diff.mouse_path_deviation, Some(0.0));
+        assert!(diff.is_equivalent(DiffTolerance::default()));
+    }
+
+    #[test]
+    fn test_sequence_changes_are_reported_in_order() {
+        let source = typing_and_drag();
+        // "i" went missing and a wheel event appeared before the drag
+        let mut events: Vec<(u64, Event)> = source
+            .events
+            .iter()
+            .filter(|e| {
+                e.event
+                    .keyboard
+                    .as_ref()
+                    .is_none_or(|kb| kb.key != Key::KeyI)
+            })
+            .map(|e| (e.elapsed.as_millis() as u64, e.event.clone()))
+            .collect();
+        events.insert(
+            2,
+            (
+                150,
+                Event::mouse_wheel(0.0, 0.0, ScrollDirection::Down, 1.0),
+            ),
+        );
+        let other = fixture(events);
+
+        let diff = source.diff(&other, DiffTolerance::default());
+        assert_eq!(
+            diff.sequence,
+            vec![
+                SequenceChange::Removed {
+                    index: 2,
+                    event_type: EventType::KeyPressed
+                },
+                SequenceChange::Removed {
+                    index: 3,
+                    event_type: EventType::KeyReleased
+                },
+                SequenceChange::Added {
+                    index: 2,
+                    event_type: EventType::MouseWheel
+                },
+            ]
+        );
+        assert!(!diff.is_equivalent(DiffTolerance::default()));
+    }
+
+    #[test]
+    fn test_timing_beyond_tolerance() {
+        let source = typing_and_drag();
+        let mut slow = source.clone();
+        // The release of "h" comes 80ms late
+        slow.events[1].elapsed += Duration::from_millis(80);
+
+        let diff = source.diff(&slow, DiffTolerance::default());
+        assert!(diff.sequence.is_empty());
+        assert_eq!(
+            diff.timing,
+            vec![TimingDifference {
+                index: 1,
+                other_index: 1,
+                elapsed: Duration::from_millis(40),
+                other_elapsed: Duration::from_millis(120),
+            }]
+        );
+        assert_eq!(diff.max_timing_delta, Duration::from_millis(80));
+        assert!(!diff.is_equivalent(DiffTolerance::default()));
+
+        let lenient = DiffTolerance {
+            timing: Duration::from_millis(100),
+            ..DiffTolerance::default()
+        };
+        assert!(source.diff(&slow, lenient).is_equivalent(lenient));
+    }
+
+    #[test]
+    fn test_mouse_path_deviation() {
+        let source = typing_and_drag();
+
+        // Same line, sampled half as often
+        let mut coarse: Vec<(u64, Event)> =
+            vec![(200, Event::mouse_pressed(Button::Left, 0.0, 0.0))];
+        for step in [1, 3, 5, 7, 9, 10] {
+            let p = step as f64 * 10.0;
+            coarse.push((200 + step * 20, Event::mouse_dragged(p, p)));
+        }
+        let coarse = fixture(coarse);
+        let deviation = source.diff(&coarse, DiffTolerance::default());
+        assert!(deviation.mouse_path_deviation.unwrap() < 1e-9);
+
+        // The whole path shifted by (3, 4)
+        let mut shifted = source.clone();
+        for recorded in &mut shifted.events {
+            if recorded.event.event_type == EventType::MouseDragged
+                && let Some(mouse) = recorded.event.mouse.as_mut()
+            {
+                mouse.x += 3.0;
+                mouse.y += 4.0;
+            }
+        }
+        let diff = source.diff(&shifted, DiffTolerance::default());
+        assert!((diff.mouse_path_deviation.unwrap() - 5.0).abs() < 1e-9);
+        assert!(diff.is_equivalent(DiffTolerance::default()));
+        assert!(!diff.is_equivalent(DiffTolerance {
+            distance: 4.0,
+            ..DiffTolerance::default()
+        }));
+    }
+
+    #[test]
+    fn test_align_trims_common_ends() {
+        let steps = align(&[1, 2, 3, 4], &[1, 5, 3, 4]);
+        assert_eq!(
+            steps,
+            vec![
+                Step::Match(0, 0),
+                Step::Removed(1),
+                Step::Added(1),
+                Step::Match(2, 2),
+                Step::Match(3, 3),
+            ]
+        );
+        assert_eq!(align::<u8>(&[], &[]), vec![]);
+        assert_eq!(align(&[], &[7]), vec![Step::Added(0)]);
+    }
+}
@@ -0,0 +1,399 @@
+//! Always-on capture of the last few seconds of input, see
+//! [`FlightRecorder`].
+
+use super::{RecordedEvent, Recording, RecordingMetadata, RedactionOptions, Timeline};
+use crate::event::{Event, EventType};
+use crate::hook::Hook;
+use crate::subscribe::Subscription;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// How much a [`FlightRecorder`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlightOptions {
+    window: Duration,
+    max_events: usize,
+    motion_interval: Option<Duration>,
+    redaction: Option<RedactionOptions>,
+}
+
+impl FlightOptions {
+    /// Keep the events of the last `window`, up to 10 000 of them.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            max_events: 10_000,
+            motion_interval: None,
+            redaction: None,
+        }
+    }
+
+    /// Never keep more than `max_events`, dropping the oldest first.
+    ///
+    /// Bounds memory however fast events arrive.
+    pub fn max_events(mut self, max_events: usize) -> Self {
+        self.max_events = max_events.max(1);
+        self
+    }
+
+    /// Keep at most one mouse move per `interval`.
+    ///
+    /// Moves arriving sooner replace the last one kept, so the cursor's
+    /// latest position survives. Button, wheel and key events are never
+    /// down-sampled.
+    pub fn motion_interval(mut self, interval: Duration) -> Self {
+        self.motion_interval = Some(interval);
+        self
+    }
+
+    /// Redact dumps with `options`, see [`Recording::redact`].
+    pub fn redact(mut self, options: RedactionOptions) -> Self {
+        self.redaction = Some(options);
+        self
+    }
+}
+
+/// The recent events, oldest first.
+#[derive(Debug)]
+struct Ring {
+    options: FlightOptions,
+    events: VecDeque<RecordedEvent>,
+    /// When the last mouse move kept was.
+    last_motion: Option<Duration>,
+}
+
+impl Ring {
+    fn new(options: FlightOptions) -> Self {
+        Self {
+            options,
+            events: VecDeque::new(),
+            last_motion: None,
+        }
+    }
+
+    fn push(&mut self, recorded: RecordedEvent) {
+        let elapsed = recorded.elapsed;
+        if is_motion(&recorded.event)
+            && let Some(interval) = self.options.motion_interval
+        {
+            let recent = self
+                .last_motion
+                .is_some_and(|last| elapsed.saturating_sub(last) < interval);
+            if recent && self.events.back().is_some_and(|e| is_motion(&e.event)) {
+                if let Some(back) = self.events.back_mut() {
+                    *back = recorded;
+                }
+                return;
+            }
+            if !recent {
+                self.last_motion = Some(elapsed);
+            }
+        }
+
+        self.events.push_back(recorded);
+        if self.events.len() > self.options.max_events {
+            self.events.pop_front();
+        }
+        self.evict(elapsed);
+    }
+
+    /// Drop events more than the window older than `now`.
+    fn evict(&mut self, now: Duration) {
+        let cutoff = now.saturating_sub(self.options.window);
+        while self.events.front().is_some_and(|e| e.elapsed < cutoff) {
+            self.events.pop_front();
+        }
+    }
+
+    /// The events in the window, timed from the first of them.
+    fn snapshot(&self) -> Vec<RecordedEvent> {
+        let start = self.events.front().map_or(Duration::ZERO, |e| e.elapsed);
+        self.events
+            .iter()
+            .map(|recorded| RecordedEvent {
+                elapsed: recorded.elapsed - start,
+                ..recorded.clone()
+            })
+            .collect()
+    }
+}
+
+fn is_motion(event: &Event) -> bool {
+    matches!(
+        event.event_type,
+        EventType::MouseMoved | EventType::MouseDragged
+    )
+}
+
+struct Inner {
+    ring: Mutex<Ring>,
+    timeline: Mutex<Timeline>,
+}
+
+impl Inner {
+    fn record(&self, event: &Event) {
+        match event.event_type {
+            EventType::HookEnabled
+            | EventType::HookDisabled
+            | EventType::GrabSuspended
+            | EventType::GrabDegraded
+            | EventType::GlobalSuspendChanged => return,
+            _ => {}
+        }
+        let Ok(recorded) = self.timeline.lock().map(|mut t| t.record(event)) else {
+            return;
+        };
+        if let Ok(mut ring) = self.ring.lock() {
+            ring.push(recorded);
+        }
+    }
+}
+
+/// Keeps the last few seconds of a hook's input, to inspect after
+/// something went wrong without having recorded on purpose.
+///
+/// Events are offered to the recorder through a
+/// [subscription](Hook::subscribe), so it shares the hook with the app's
+/// own handler. Memory is bounded by the window and by
+/// [`FlightOptions::max_events`]. Cloning gives another handle to the same
+/// recorder; it stops recording when the last one is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use monio::recorder::FlightRecorder;
+/// use monio::{Event, Hook, Key};
+/// use std::time::Duration;
+///
+/// let hook = Hook::new();
+/// let recorder = FlightRecorder::attach(&hook, Duration::from_secs(10));
+///
+/// // Save what just happened when F12 is pressed
+/// let on_f12 = recorder.clone();
+/// hook.on_key_pressed(Key::F12, move || {
+///     let _ = on_f12.dump().save("last-10s.json");
+/// })
+/// .detach();
+///
+/// hook.run(|_: &Event| {}).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct FlightRecorder {
+    inner: Arc<Inner>,
+    _subscription: Arc<Subscription>,
+}
+
+impl FlightRecorder {
+    /// Start keeping the last `window` of `hook`'s events.
+    pub fn attach(hook: &Hook, window: Duration) -> Self {
+        Self::attach_with(hook, FlightOptions::new(window))
+    }
+
+    /// Start keeping `hook`'s events as `options` say.
+    pub fn attach_with(hook: &Hook, options: FlightOptions) -> Self {
+        let inner = Arc::new(Inner {
+            ring: Mutex::new(Ring::new(options)),
+            timeline: Mutex::new(Timeline::default()),
+        });
+        let recording = inner.clone();
+        let subscription = hook.subscribe(move |event: &Event| recording.record(event));
+        Self {
+            inner,
+            _subscription: Arc::new(subscription),
+        }
+    }
+
+    /// The events of the last window, as a recording starting at the
+    /// oldest of them. Redacted if the options ask for it.
+    pub fn dump(&self) -> Recording {
+        let now = self.inner.timeline.lock().ok().and_then(|t| t.now());
+        let (events, redaction) = match self.inner.ring.lock() {
+            Ok(mut ring) => {
+                if let Some(now) = now {
+                    ring.evict(now);
+                }
+                (ring.snapshot(), ring.options.redaction)
+            }
+            Err(_) => (Vec::new(), None),
+        };
+
+        let recording = Recording {
+            events,
+            created_at: SystemTime::now(),
+            description: Some("Flight recorder dump".to_string()),
+            metadata: RecordingMetadata::capture(),
+            #[cfg(feature = "serde")]
+            extra: serde_json::Map::new(),
+        };
+        match redaction {
+            Some(options) => recording.redact(options),
+            None => recording,
+        }
+    }
+
+    /// [`dump`](Self::dump) as an event log: a
+    /// [`LogHeader`](crate::session::LogHeader) line, then one JSON
+    /// [`Event`] per line, as written by
+    /// [`CaptureSessionBuilder::with_jsonl`](crate::session::CaptureSessionBuilder::with_jsonl).
+    #[cfg(feature = "serde")]
+    pub fn dump_jsonl(&self) -> crate::Result<String> {
+        fn line(out: &mut String, value: &impl serde::Serialize) -> crate::Result<()> {
+            let json = serde_json::to_string(value).map_err(|e| {
+                crate::Error::Other(format!("Failed to serialize flight recording: {}", e))
+            })?;
+            out.push_str(&json);
+            out.push('\n');
+            Ok(())
+        }
+
+        let mut out = String::new();
+        line(&mut out, &crate::session::LogHeader::current())?;
+        for recorded in self.dump().events {
+            line(&mut out, &recorded.event)?;
+        }
+        Ok(out)
+    }
+
+    /// Number of events currently kept.
+    pub fn len(&self) -> usize {
+        self.inner.ring.lock().map(|r| r.events.len()).unwrap_or(0)
+    }
+
+    /// Whether no events are kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget the events kept so far.
+    pub fn clear(&self) {
+        if let Ok(mut ring) = self.inner.ring.lock() {
+            ring.events.clear();
+            ring.last_motion = None;
+        }
+    }
+}
+
+impl std::fmt::Debug for FlightRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FlightRecorder")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64, event: Event) -> RecordedEvent {
+        RecordedEvent {
+            elapsed: Duration::from_millis(ms),
+            wall_time: None,
+            event,
+        }
+    }
+
+    fn key(ms: u64) -> RecordedEvent {
+        at(ms, Event::key_pressed(crate::Key::KeyA, 30))
+    }
+
+    fn moved(ms: u64, x: f64) -> RecordedEvent {
+        at(ms, Event::mouse_moved(x, 0.0))
+    }
+
+    fn times(ring: &Ring) -> Vec<u64> {
+        ring.events
+            .iter()
+            .map(|e| e.elapsed.as_millis() as u64)
+            .collect()
+    }
+
+    #[test]
+    fn test_evicts_events_older_than_window() {
+        let mut ring = Ring::new(FlightOptions::new(Duration::from_millis(100)));
+        for ms in [0, 40, 80, 120, 170] {
+            ring.push(key(ms));
+        }
+        assert_eq!(times(&ring), [80, 120, 170]);
+
+        // Eviction also follows the clock with no new events
+        ring.evict(Duration::from_millis(250));
+        assert_eq!(times(&ring), [170]);
+        ring.evict(Duration::from_millis(1000));
+        assert!(ring.events.is_empty());
+    }
+
+    #[test]
+    fn test_caps_number_of_events() {
+        let mut ring = Ring::new(FlightOptions::new(Duration::from_secs(60)).max_events(3));
+        for ms in 0..10 {
+            ring.push(key(ms));
+        }
+        assert_eq!(times(&ring), [7, 8, 9]);
+
+        // A cap of zero still keeps the latest event
+        let mut ring = Ring::new(FlightOptions::new(Duration::from_secs(60)).max_events(0));
+        ring.push(key(1));
+        ring.push(key(2));
+        assert_eq!(times(&ring), [2]);
+    }
+
+    #[test]
+    fn test_downsamples_motion() {
+        let options =
+            FlightOptions::new(Duration::from_secs(60)).motion_interval(Duration::from_millis(50));
+        let mut ring = Ring::new(options);
+        ring.push(moved(0, 0.0));
+        ring.push(moved(10, 1.0));
+        ring.push(moved(20, 2.0));
+        ring.push(key(30));
+        ring.push(moved(40, 4.0));
+        ring.push(moved(60, 6.0));
+
+        assert_eq!(times(&ring), [20, 30, 40, 60]);
+        // The latest position replaced the earlier moves
+        assert_eq!(ring.events[0].event.mouse.as_ref().unwrap().x, 2.0);
+
+        // Without an interval every move is kept
+        let mut ring = Ring::new(FlightOptions::new(Duration::from_secs(60)));
+        for ms in 0..5 {
+            ring.push(moved(ms, ms as f64));
+        }
+        assert_eq!(ring.events.len(), 5);
+    }
+
+    #[test]
+    fn test_snapshot_starts_at_zero() {
+        let mut ring = Ring::new(FlightOptions::new(Duration::from_millis(100)));
+        for ms in [0, 500, 520, 560] {
+            ring.push(key(ms));
+        }
+        let elapsed: Vec<Duration> = ring.snapshot().iter().map(|e| e.elapsed).collect();
+        assert_eq!(elapsed, [0, 20, 60].map(Duration::from_millis).to_vec());
+    }
+
+    #[test]
+    fn test_recorder_skips_lifecycle_events_and_redacts() {
+        let hook = Hook::new();
+        let recorder = FlightRecorder::attach_with(
+            &hook,
+            FlightOptions::new(Duration::from_secs(60)).redact(RedactionOptions::default()),
+        );
+        recorder.inner.record(&Event::new(EventType::HookEnabled));
+        recorder
+            .inner
+            .record(&Event::key_pressed(crate::Key::KeyA, 30));
+        assert_eq!(recorder.len(), 1);
+
+        let dump = recorder.dump();
+        assert_eq!(dump.event_count(), 1);
+        assert_eq!(
+            dump.events[0].event.keyboard.as_ref().map(|kb| kb.key),
+            Some(crate::Key::Unknown(1))
+        );
+
+        recorder.clear();
+        assert!(recorder.is_empty());
+    }
+}
@@ -0,0 +1,982 @@
+//! Playback scheduling, see [`Recording::playback_with_options`].
+//!
+//! Every event gets an absolute deadline, its offset in the recording
+//! scaled by the speed, measured from the instant playback started. Waiting
+//! for a deadline sleeps through long gaps and spins for the last
+//! millisecond, so sleep overshoot doesn't add up over the recording and
+//! tight intervals stay tight. When the schedule slips anyway (a loaded
+//! machine, a slow simulator), [`CatchUp`] decides what happens to the
+//! events that are already due.
+//!
+//! [`PlaybackOptions::verify_position`] adds checks that catch a recording
+//! going off the rails, such as a simulated move the OS swallowed: the
+//! cursor is compared with the recorded position before every press, and
+//! nothing may be left pressed after the last event.
+
+use super::{RecordedEvent, Recording};
+use crate::error::{Error, Result};
+use crate::event::{Button, Event, EventType};
+use crate::keycode::Key;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How close to a deadline waiting switches from parking to spinning.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// What playback does with events that are already late.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CatchUp {
+    /// Skip late mouse moves that a later, also due, move supersedes; other
+    /// events are played late. The cursor jumps rather than replaying a
+    /// burst of stale motion.
+    SkipLate,
+    /// Play every event, late ones as fast as possible until playback is
+    /// back on schedule.
+    #[default]
+    PlayLate,
+    /// Play the late event, then shift the rest of the schedule by how late
+    /// it was, keeping the original intervals between later events.
+    Resync,
+}
+
+/// A check of [`PlaybackOptions::verify_position`] that failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationFailure {
+    /// The cursor wasn't where the recording was about to press a button.
+    CursorMismatch {
+        /// Index of the press in [`Recording::events`].
+        index: usize,
+        /// Where the recording pressed.
+        expected: (f64, f64),
+        /// Where the cursor was.
+        actual: (f64, f64),
+    },
+    /// Keys or buttons were still pressed after the last event.
+    StillPressed {
+        /// Index of the last event in [`Recording::events`].
+        index: usize,
+        /// Keys pressed and not released, in press order.
+        keys: Vec<Key>,
+        /// Buttons pressed and not released, in press order.
+        buttons: Vec<Button>,
+    },
+}
+
+impl VerificationFailure {
+    /// Index in [`Recording::events`] of the event the check failed at.
+    pub fn index(&self) -> usize {
+        match self {
+            Self::CursorMismatch { index, .. } | Self::StillPressed { index, .. } => *index,
+        }
+    }
+}
+
+impl fmt::Display for VerificationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CursorMismatch {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "event {}: cursor at ({}, {}) instead of ({}, {})",
+                index, actual.0, actual.1, expected.0, expected.1
+            ),
+            Self::StillPressed {
+                index,
+                keys,
+                buttons,
+            } => write!(
+                f,
+                "event {}: still pressed after the last event: keys {:?}, buttons {:?}",
+                index, keys, buttons
+            ),
+        }
+    }
+}
+
+/// Callback of [`PlaybackOptions::on_verification_failure`].
+#[derive(Clone)]
+struct FailureCallback(Arc<dyn Fn(&VerificationFailure) -> bool + Send + Sync>);
+
+impl fmt::Debug for FailureCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FailureCallback")
+    }
+}
+
+impl PartialEq for FailureCallback {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Options for [`Recording::playback_with_options`] and
+/// [`Recording::start_playback`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackOptions {
+    speed: f64,
+    catch_up: CatchUp,
+    late_threshold: Duration,
+    verify_tolerance: Option<f64>,
+    on_failure: Option<FailureCallback>,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            catch_up: CatchUp::default(),
+            late_threshold: Duration::from_millis(10),
+            verify_tolerance: None,
+            on_failure: None,
+        }
+    }
+}
+
+impl PlaybackOptions {
+    /// Original speed, playing late events late.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Speed multiplier (1.0 = normal speed, 2.0 = double speed, 0.5 = half
+    /// speed).
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// What to do when the schedule slips.
+    pub fn catch_up(mut self, catch_up: CatchUp) -> Self {
+        self.catch_up = catch_up;
+        self
+    }
+
+    /// How far behind its deadline an event must be to count as late.
+    /// Defaults to 10ms.
+    pub fn late_threshold(mut self, threshold: Duration) -> Self {
+        self.late_threshold = threshold;
+        self
+    }
+
+    /// Before each button press, check that the cursor is within
+    /// `tolerance_px` of where the recording pressed, and after the last
+    /// event, that the recording left nothing pressed.
+    ///
+    /// A failed check aborts playback with [`Error::VerificationFailed`],
+    /// unless [`on_verification_failure`](Self::on_verification_failure)
+    /// says otherwise. Reads the cursor with
+    /// [`mouse_position`](crate::mouse_position), so playback fails where
+    /// that isn't supported.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::Error;
+    /// use monio::recorder::{PlaybackOptions, Recording};
+    ///
+    /// let recording = Recording::load("macro.json").unwrap();
+    /// let options = PlaybackOptions::new().verify_position(5.0);
+    /// match recording.playback_with_options(options) {
+    ///     Err(Error::VerificationFailed(failure)) => {
+    ///         eprintln!("macro went off track at event {}", failure.index());
+    ///     }
+    ///     result => {
+    ///         result.unwrap();
+    ///     }
+    /// }
+    /// ```
+    pub fn verify_position(mut self, tolerance_px: f64) -> Self {
+        self.verify_tolerance = Some(tolerance_px);
+        self
+    }
+
+    /// Call `callback` when a check of
+    /// [`verify_position`](Self::verify_position) fails, instead of
+    /// aborting. Playback waits for the callback, so it can pause for the
+    /// user to fix things; it returns whether to carry on.
+    pub fn on_verification_failure<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&VerificationFailure) -> bool + Send + Sync + 'static,
+    {
+        self.on_failure = Some(FailureCallback(Arc::new(callback)));
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !(self.speed > 0.0 && self.speed.is_finite()) {
+            return Err(Error::Other("Playback speed must be positive".into()));
+        }
+        if self
+            .verify_tolerance
+            .is_some_and(|tolerance| !(tolerance >= 0.0 && tolerance.is_finite()))
+        {
+            return Err(Error::Other(
+                "Position tolerance must be a non-negative number of pixels".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// How closely playback kept to its schedule.
+///
+/// The timing error of an event is how long after its deadline it was
+/// handed to the simulator.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimingStats {
+    /// Events played.
+    pub played: u64,
+    /// Late mouse moves skipped by [`CatchUp::SkipLate`].
+    pub skipped: u64,
+    /// Events played later than the late threshold.
+    pub late: u64,
+    /// Times [`CatchUp::Resync`] shifted the schedule.
+    pub resyncs: u64,
+    /// The largest timing error.
+    pub max_error: Duration,
+    total_error: Duration,
+}
+
+impl TimingStats {
+    /// Average timing error over the events played.
+    pub fn mean_error(&self) -> Duration {
+        match u32::try_from(self.played) {
+            Ok(0) => Duration::ZERO,
+            Ok(played) => self.total_error / played,
+            Err(_) => self.total_error.div_f64(self.played as f64),
+        }
+    }
+
+    fn record(&mut self, error: Duration, late: bool) {
+        self.played += 1;
+        self.late += u64::from(late);
+        self.max_error = self.max_error.max(error);
+        self.total_error += error;
+    }
+}
+
+/// Time since playback started.
+pub(crate) trait Clock {
+    fn now(&self) -> Duration;
+
+    /// Wait until `deadline`, or until `stop` is set.
+    fn wait_until(&self, deadline: Duration, stop: &AtomicBool);
+}
+
+/// The clock used for real playback.
+pub(crate) struct MonotonicClock {
+    start: Instant,
+}
+
+impl MonotonicClock {
+    pub(crate) fn start() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn wait_until(&self, deadline: Duration, stop: &AtomicBool) {
+        loop {
+            if stop.load(Ordering::SeqCst) {
+                return;
+            }
+            let Some(left) = deadline.checked_sub(self.now()) else {
+                return;
+            };
+            if left > SPIN_THRESHOLD {
+                // Parked rather than asleep, so stopping wakes it
+                thread::park_timeout(left - SPIN_THRESHOLD);
+            } else if left.is_zero() {
+                return;
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+}
+
+fn is_lifecycle(event: &Event) -> bool {
+    matches!(
+        event.event_type,
+        EventType::HookEnabled
+            | EventType::HookDisabled
+            | EventType::GrabSuspended
+            | EventType::GrabDegraded
+            | EventType::GlobalSuspendChanged
+    )
+}
+
+fn is_motion(event: &Event) -> bool {
+    matches!(
+        event.event_type,
+        EventType::MouseMoved | EventType::MouseDragged
+    )
+}
+
+/// Play `events` on `clock`'s schedule, handing each to `send` with its
+/// index, until done or `stop` is set.
+pub(crate) fn schedule(
+    events: &[RecordedEvent],
+    options: &PlaybackOptions,
+    clock: &impl Clock,
+    stop: &AtomicBool,
+    stats: &Mutex<TimingStats>,
+    mut send: impl FnMut(usize, &Event) -> Result<()>,
+) -> Result<()> {
+    options.validate()?;
+    let events: Vec<(usize, &RecordedEvent)> = events
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| !is_lifecycle(&r.event))
+        .collect();
+    // How far Resync has pushed the schedule back
+    let mut shift = Duration::ZERO;
+    let deadline =
+        |recorded: &RecordedEvent, shift| recorded.elapsed.div_f64(options.speed) + shift;
+
+    for (i, &(index, recorded)) in events.iter().enumerate() {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let mut due = deadline(recorded, shift);
+        clock.wait_until(due, stop);
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let now = clock.now();
+        let lateness = now.saturating_sub(due);
+        let late = lateness > options.late_threshold;
+        if late {
+            match options.catch_up {
+                CatchUp::PlayLate => {}
+                CatchUp::SkipLate => {
+                    let superseded = events.get(i + 1).is_some_and(|(_, next)| {
+                        is_motion(&next.event) && deadline(next, shift) <= now
+                    });
+                    if is_motion(&recorded.event) && superseded {
+                        if let Ok(mut stats) = stats.lock() {
+                            stats.skipped += 1;
+                        }
+                        continue;
+                    }
+                }
+                CatchUp::Resync => {
+                    shift += lateness;
+                    due = now;
+                    if let Ok(mut stats) = stats.lock() {
+                        stats.resyncs += 1;
+                    }
+                }
+            }
+        }
+
+        let error = clock.now().saturating_sub(due);
+        send(index, &recorded.event)?;
+        if let Ok(mut stats) = stats.lock() {
+            stats.record(error, late);
+        }
+    }
+    Ok(())
+}
+
+/// Keys and buttons pressed by playback and not yet released.
+#[derive(Default)]
+struct Held {
+    keys: Vec<Key>,
+    buttons: Vec<Button>,
+}
+
+impl Held {
+    fn track(&mut self, event: &Event) {
+        let key = event.keyboard.as_ref().map(|kb| kb.key);
+        let button = event.mouse.as_ref().and_then(|m| m.button);
+        match (event.event_type, key, button) {
+            (EventType::KeyPressed, Some(key), _) if !self.keys.contains(&key) => {
+                self.keys.push(key);
+            }
+            (EventType::KeyReleased, Some(key), _) => self.keys.retain(|&k| k != key),
+            (EventType::MousePressed, _, Some(button)) if !self.buttons.contains(&button) => {
+                self.buttons.push(button);
+            }
+            (EventType::MouseReleased, _, Some(button)) => self.buttons.retain(|&b| b != button),
+            _ => {}
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.buttons.is_empty()
+    }
+
+    fn release(self) {
+        for key in self.keys.into_iter().rev() {
+            let _ = crate::simulation::key_release(key);
+        }
+        for button in self.buttons.into_iter().rev() {
+            let _ = crate::simulation::mouse_release(button);
+        }
+    }
+}
+
+/// Simulates played events, keeping track of what they hold and making the
+/// checks of [`PlaybackOptions::verify_position`].
+struct Player<S, P> {
+    simulate: S,
+    /// Reads the cursor position.
+    position: P,
+    held: Held,
+    tolerance: Option<f64>,
+    on_failure: Option<FailureCallback>,
+    last_index: Option<usize>,
+}
+
+impl<S, P> Player<S, P>
+where
+    S: FnMut(&Event) -> Result<()>,
+    P: FnMut() -> Result<(f64, f64)>,
+{
+    fn new(options: &PlaybackOptions, simulate: S, position: P) -> Self {
+        Self {
+            simulate,
+            position,
+            held: Held::default(),
+            tolerance: options.verify_tolerance,
+            on_failure: options.on_failure.clone(),
+            last_index: None,
+        }
+    }
+
+    fn send(&mut self, index: usize, event: &Event) -> Result<()> {
+        if let (Some(tolerance), EventType::MousePressed, Some(mouse)) =
+            (self.tolerance, event.event_type, &event.mouse)
+        {
+            let actual = (self.position)()?;
+            let expected = (mouse.x, mouse.y);
+            if (actual.0 - expected.0).hypot(actual.1 - expected.1) > tolerance {
+                self.fail(VerificationFailure::CursorMismatch {
+                    index,
+                    expected,
+                    actual,
+                })?;
+            }
+        }
+        (self.simulate)(event)?;
+        self.held.track(event);
+        self.last_index = Some(index);
+        Ok(())
+    }
+
+    /// Check the end of a playback that ran to completion.
+    fn finish(&mut self) -> Result<()> {
+        match self.last_index {
+            Some(index) if self.tolerance.is_some() && !self.held.is_empty() => {
+                self.fail(VerificationFailure::StillPressed {
+                    index,
+                    keys: self.held.keys.clone(),
+                    buttons: self.held.buttons.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn fail(&self, failure: VerificationFailure) -> Result<()> {
+        log::warn!("Playback verification failed: {}", failure);
+        match &self.on_failure {
+            Some(callback) if (callback.0)(&failure) => Ok(()),
+            _ => Err(Error::VerificationFailed(failure)),
+        }
+    }
+}
+
+/// A playback running in the background, see
+/// [`Recording::start_playback`].
+///
+/// Dropping the handle stops playback.
+pub struct PlaybackHandle {
+    thread: Option<JoinHandle<Result<()>>>,
+    stop: Arc<AtomicBool>,
+    stats: Arc<Mutex<TimingStats>>,
+}
+
+impl PlaybackHandle {
+    /// Timing statistics so far.
+    pub fn stats(&self) -> TimingStats {
+        self.stats
+            .lock()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether playback has finished, stopped or failed.
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(|t| t.is_finished())
+    }
+
+    /// Wait for playback to finish.
+    ///
+    /// Fails with [`Error::VerificationFailed`] if a check of
+    /// [`PlaybackOptions::verify_position`] aborted it.
+    pub fn wait(mut self) -> Result<TimingStats> {
+        self.join()?;
+        Ok(self.stats())
+    }
+
+    /// Stop playback, releasing any keys and buttons it left pressed.
+    pub fn stop(mut self) -> Result<TimingStats> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.join()?;
+        Ok(self.stats())
+    }
+
+    fn join(&mut self) -> Result<()> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        thread.thread().unpark();
+        thread
+            .join()
+            .map_err(|_| Error::ThreadError("playback thread panicked".into()))?
+    }
+}
+
+impl Drop for PlaybackHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.join();
+    }
+}
+
+impl Recording {
+    /// Playback this recording with the given options, returning how
+    /// closely it kept to the original timing.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::recorder::{CatchUp, PlaybackOptions, Recording};
+    ///
+    /// let recording = Recording::load("macro.json").unwrap();
+    /// let options = PlaybackOptions::new().catch_up(CatchUp::SkipLate);
+    /// let stats = recording.playback_with_options(options).unwrap();
+    /// println!("mean error {:?}, max {:?}", stats.mean_error(), stats.max_error);
+    /// ```
+    pub fn playback_with_options(&self, options: PlaybackOptions) -> Result<TimingStats> {
+        let mut player = Player::new(&options, crate::simulate, crate::mouse_position);
+        let stats = self.play(&options, |index, event| player.send(index, event))?;
+        player.finish()?;
+        Ok(stats)
+    }
+
+    /// Playback this recording on a background thread.
+    ///
+    /// Stopping the playback, or dropping the handle, releases any keys and
+    /// buttons the recording pressed but hadn't released yet.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::recorder::{PlaybackOptions, Recording};
+    /// use std::time::Duration;
+    ///
+    /// let recording = Recording::load("macro.json").unwrap();
+    /// let playback = recording.start_playback(PlaybackOptions::new()).unwrap();
+    /// std::thread::sleep(Duration::from_secs(5));
+    /// let stats = playback.stop().unwrap();
+    /// println!("played {} events", stats.played);
+    /// ```
+    pub fn start_playback(&self, options: PlaybackOptions) -> Result<PlaybackHandle> {
+        options.validate()?;
+        self.warn_about_environment();
+
+        let events = self.events.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stats = Arc::new(Mutex::new(TimingStats::default()));
+        let thread = {
+            let (stop, stats) = (stop.clone(), stats.clone());
+            thread::Builder::new()
+                .name("monio-playback".into())
+                .spawn(move || {
+                    let mut player = Player::new(&options, crate::simulate, crate::mouse_position);
+                    let clock = MonotonicClock::start();
+                    let mut result = schedule(&events, &options, &clock, &stop, &stats, |i, e| {
+                        player.send(i, e)
+                    });
+                    if result.is_ok() && !stop.load(Ordering::SeqCst) {
+                        result = player.finish();
+                    }
+                    player.held.release();
+                    result
+                })
+                .map_err(|e| Error::ThreadError(e.to_string()))?
+        };
+
+        Ok(PlaybackHandle {
+            thread: Some(thread),
+            stop,
+            stats,
+        })
+    }
+
+    /// Replay the events on schedule, handing each to `send` with its index.
+    pub(super) fn play(
+        &self,
+        options: &PlaybackOptions,
+        send: impl FnMut(usize, &Event) -> Result<()>,
+    ) -> Result<TimingStats> {
+        options.validate()?;
+        if self.events.is_empty() {
+            return Ok(TimingStats::default());
+        }
+        self.warn_about_environment();
+
+        let stats = Mutex::new(TimingStats::default());
+        let clock = MonotonicClock::start();
+        schedule(
+            &self.events,
+            options,
+            &clock,
+            &AtomicBool::new(false),
+            &stats,
+            send,
+        )?;
+        stats
+            .into_inner()
+            .map_err(|_| Error::ThreadError("playback stats mutex poisoned".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+
+    /// A clock that only moves when waited on, overshooting each wait by a
+    /// fixed amount.
+    struct FakeClock {
+        now: Cell<Duration>,
+        overshoot: Duration,
+    }
+
+    impl FakeClock {
+        fn new(overshoot: Duration) -> Self {
+            Self {
+                now: Cell::new(Duration::ZERO),
+                overshoot,
+            }
+        }
+
+        fn advance(&self, by: Duration) {
+            self.now.set(self.now.get() + by);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            self.now.get()
+        }
+
+        fn wait_until(&self, deadline: Duration, _stop: &AtomicBool) {
+            if deadline > self.now.get() {
+                self.now.set(deadline + self.overshoot);
+            }
+        }
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn recorded(at: u64, event: Event) -> RecordedEvent {
+        RecordedEvent {
+            elapsed: ms(at),
+            wall_time: None,
+            event,
+        }
+    }
+
+    /// Plays `events`, with the simulator taking `cost` per event (more for
+    /// `slow` key events), returning when each event was sent.
+    fn play(
+        events: &[RecordedEvent],
+        options: PlaybackOptions,
+        clock: &FakeClock,
+        cost: impl Fn(&Event) -> Duration,
+    ) -> (Vec<(u64, EventType)>, TimingStats) {
+        let sent = RefCell::new(Vec::new());
+        let stats = Mutex::new(TimingStats::default());
+        schedule(
+            events,
+            &options,
+            clock,
+            &AtomicBool::new(false),
+            &stats,
+            |_, event| {
+                sent.borrow_mut()
+                    .push((clock.now().as_millis() as u64, event.event_type));
+                clock.advance(cost(event));
+                Ok(())
+            },
+        )
+        .unwrap();
+        (sent.into_inner(), stats.into_inner().unwrap())
+    }
+
+    #[test]
+    fn test_deadlines_are_absolute() {
+        // Every wait overshoots by 2ms, but deadlines don't drift
+        let clock = FakeClock::new(ms(2));
+        let events: Vec<_> = (0..5)
+            .map(|i| recorded(i * 10, Event::mouse_moved(i as f64, 0.0)))
+            .collect();
+        let (sent, stats) = play(&events, PlaybackOptions::new(), &clock, |_| ms(0));
+        let times: Vec<u64> = sent.iter().map(|&(t, _)| t).collect();
+        assert_eq!(times, [0, 12, 22, 32, 42]);
+        assert_eq!(stats.played, 5);
+        assert_eq!(stats.max_error, ms(2));
+        assert_eq!(stats.late, 0);
+
+        // Speed scales the deadlines
+        let clock = FakeClock::new(ms(0));
+        let (sent, _) = play(&events, PlaybackOptions::new().speed(2.0), &clock, |_| {
+            ms(0)
+        });
+        let times: Vec<u64> = sent.iter().map(|&(t, _)| t).collect();
+        assert_eq!(times, [0, 5, 10, 15, 20]);
+    }
+
+    /// A key press the simulator takes 50ms over, then a burst of moves
+    /// and a key release.
+    fn stall() -> Vec<RecordedEvent> {
+        vec![
+            recorded(0, Event::key_pressed(Key::KeyA, 0)),
+            recorded(10, Event::mouse_moved(1.0, 0.0)),
+            recorded(20, Event::mouse_moved(2.0, 0.0)),
+            recorded(30, Event::mouse_moved(3.0, 0.0)),
+            recorded(40, Event::key_released(Key::KeyA, 0)),
+            recorded(100, Event::mouse_moved(4.0, 0.0)),
+        ]
+    }
+
+    fn stall_cost(event: &Event) -> Duration {
+        if event.event_type == EventType::KeyPressed {
+            ms(50)
+        } else {
+            ms(1)
+        }
+    }
+
+    #[test]
+    fn test_play_late_plays_everything() {
+        let clock = FakeClock::new(ms(0));
+        let (sent, stats) = play(&stall(), PlaybackOptions::new(), &clock, stall_cost);
+        let times: Vec<u64> = sent.iter().map(|&(t, _)| t).collect();
+        assert_eq!(times, [0, 50, 51, 52, 53, 100]);
+        assert_eq!(stats.late, 4);
+        assert_eq!(stats.max_error, ms(40));
+        assert_eq!(stats.mean_error(), (ms(40) + ms(31) + ms(22) + ms(13)) / 6);
+    }
+
+    #[test]
+    fn test_skip_late_drops_superseded_moves_only() {
+        let clock = FakeClock::new(ms(0));
+        let options = PlaybackOptions::new().catch_up(CatchUp::SkipLate);
+        let (sent, stats) = play(&stall(), options, &clock, stall_cost);
+        // The moves at 10 and 20 are superseded by the one at 30, which
+        // lands the cursor before the key release
+        assert_eq!(
+            sent,
+            [
+                (0, EventType::KeyPressed),
+                (50, EventType::MouseMoved),
+                (51, EventType::KeyReleased),
+                (100, EventType::MouseMoved),
+            ]
+        );
+        assert_eq!(stats.skipped, 2);
+        assert_eq!(stats.played, 4);
+    }
+
+    #[test]
+    fn test_resync_shifts_the_schedule() {
+        let clock = FakeClock::new(ms(0));
+        let options = PlaybackOptions::new().catch_up(CatchUp::Resync);
+        let (sent, stats) = play(&stall(), options, &clock, stall_cost);
+        let times: Vec<u64> = sent.iter().map(|&(t, _)| t).collect();
+        // 40ms late at the first move, then the original intervals
+        assert_eq!(times, [0, 50, 60, 70, 80, 140]);
+        assert_eq!(stats.resyncs, 1);
+        assert_eq!(stats.late, 1);
+        assert_eq!(stats.max_error, ms(0));
+    }
+
+    #[test]
+    fn test_stop_and_invalid_speed() {
+        let stats = Mutex::new(TimingStats::default());
+        let clock = FakeClock::new(ms(0));
+        let stopped = AtomicBool::new(true);
+        schedule(
+            &stall(),
+            &PlaybackOptions::new(),
+            &clock,
+            &stopped,
+            &stats,
+            |_, _| panic!("nothing plays once stopped"),
+        )
+        .unwrap();
+
+        for speed in [0.0, -1.0, f64::NAN] {
+            assert!(
+                schedule(
+                    &stall(),
+                    &PlaybackOptions::new().speed(speed),
+                    &clock,
+                    &AtomicBool::new(false),
+                    &stats,
+                    |_, _| Ok(()),
+                )
+                .is_err()
+            );
+        }
+    }
+
+    /// Plays `events` through a player whose simulator loses the moves to
+    /// `lost`, returning the result and the events simulated.
+    fn play_verified(
+        events: &[RecordedEvent],
+        options: PlaybackOptions,
+        lost: (f64, f64),
+    ) -> (Result<()>, Vec<EventType>) {
+        let cursor = Cell::new((0.0, 0.0));
+        let simulated = RefCell::new(Vec::new());
+        let mut player = Player::new(
+            &options,
+            |event: &Event| {
+                if let Some(mouse) = &event.mouse
+                    && (mouse.x, mouse.y) != lost
+                {
+                    cursor.set((mouse.x, mouse.y));
+                }
+                simulated.borrow_mut().push(event.event_type);
+                Ok(())
+            },
+            || Ok(cursor.get()),
+        );
+        let clock = FakeClock::new(ms(0));
+        let stats = Mutex::new(TimingStats::default());
+        let result = schedule(
+            events,
+            &options,
+            &clock,
+            &AtomicBool::new(false),
+            &stats,
+            |i, e| player.send(i, e),
+        )
+        .and_then(|()| player.finish());
+        drop(player);
+        (result, simulated.into_inner())
+    }
+
+    /// Moves to a button and clicks it, after a lifecycle event.
+    fn click_at(x: f64, y: f64) -> Vec<RecordedEvent> {
+        vec![
+            recorded(0, Event::hook_enabled()),
+            recorded(0, Event::mouse_moved(x, y)),
+            recorded(10, Event::mouse_pressed(Button::Left, x, y)),
+            recorded(20, Event::mouse_released(Button::Left, x, y)),
+        ]
+    }
+
+    #[test]
+    fn test_verification_catches_lost_move() {
+        let options = PlaybackOptions::new().verify_position(2.0);
+        let (result, simulated) =
+            play_verified(&click_at(100.0, 50.0), options.clone(), (-1.0, -1.0));
+        result.unwrap();
+        assert_eq!(simulated.len(), 3);
+
+        // The simulator swallows the move, so the press would miss
+        let (result, simulated) = play_verified(&click_at(100.0, 50.0), options, (100.0, 50.0));
+        match result {
+            Err(Error::VerificationFailed(failure)) => assert_eq!(
+                failure,
+                VerificationFailure::CursorMismatch {
+                    index: 2,
+                    expected: (100.0, 50.0),
+                    actual: (0.0, 0.0),
+                }
+            ),
+            other => panic!("expected a verification failure, got {:?}", other),
+        }
+        // Aborted before the press
+        assert_eq!(simulated, [EventType::MouseMoved]);
+
+        // Within tolerance
+        let options = PlaybackOptions::new().verify_position(200.0);
+        let (result, _) = play_verified(&click_at(100.0, 50.0), options, (100.0, 50.0));
+        result.unwrap();
+    }
+
+    #[test]
+    fn test_verification_callback_decides() {
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let options = PlaybackOptions::new()
+            .verify_position(0.0)
+            .on_verification_failure({
+                let failures = failures.clone();
+                move |failure| {
+                    failures.lock().unwrap().push(failure.index());
+                    true
+                }
+            });
+        let (result, simulated) = play_verified(&click_at(10.0, 10.0), options, (10.0, 10.0));
+        result.unwrap();
+        assert_eq!(simulated.len(), 3);
+        assert_eq!(*failures.lock().unwrap(), [2]);
+    }
+
+    #[test]
+    fn test_verification_of_held_input() {
+        let events = vec![
+            recorded(0, Event::key_pressed(Key::ShiftLeft, 0)),
+            recorded(10, Event::key_pressed(Key::KeyA, 0)),
+            recorded(20, Event::key_released(Key::KeyA, 0)),
+            recorded(30, Event::hook_disabled()),
+        ];
+        let (result, _) = play_verified(&events, PlaybackOptions::new(), (-1.0, -1.0));
+        result.unwrap();
+
+        let options = PlaybackOptions::new().verify_position(1.0);
+        let (result, _) = play_verified(&events, options, (-1.0, -1.0));
+        match result {
+            Err(Error::VerificationFailed(failure)) => {
+                assert_eq!(failure.index(), 2);
+                assert!(matches!(
+                    failure,
+                    VerificationFailure::StillPressed { keys, buttons, .. }
+                        if keys == [Key::ShiftLeft] && buttons.is_empty()
+                ));
+            }
+            other => panic!("expected a verification failure, got {:?}", other),
+        }
+
+        assert!(
+            PlaybackOptions::new()
+                .verify_position(-1.0)
+                .validate()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Stripping sensitive input from recordings, see [`Recording::redact`].
+
+use super::{RecordedEvent, Recording};
+use crate::event::{EventType, KeyboardData};
+use crate::keycode::Key;
+use std::collections::HashMap;
+
+/// What happens to key identities, see [`RedactionOptions`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyRedaction {
+    /// Keep keys as recorded.
+    Keep,
+    /// Replace each distinct key with its own placeholder,
+    /// `Key::Unknown(1)`, `Key::Unknown(2)` and so on in order of first
+    /// appearance. Presses still pair with their releases, and rollover
+    /// stays visible, but not which keys were typed.
+    #[default]
+    ReplaceWithPlaceholder,
+    /// Replace every key with `Key::Unknown(0)`, so not even repeated keys
+    /// can be told apart.
+    DropKeyIdentity,
+    /// Remove keyboard events altogether.
+    Remove,
+}
+
+/// What happens to pointer positions, see [`RedactionOptions`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionRedaction {
+    /// Keep positions as recorded.
+    #[default]
+    Keep,
+    /// Snap positions down to a grid this many screen points wide, e.g. to
+    /// hide what was clicked in a dialog. `Quantize(0)` and `Quantize(1)`
+    /// keep whole points.
+    Quantize(u32),
+}
+
+/// What happens to the text typed, see [`RedactionOptions`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextRedaction {
+    /// Keep characters as recorded.
+    Keep,
+    /// Remove characters from keyboard events, and the character keys of
+    /// [`KeyTyped`](EventType::KeyTyped) events.
+    #[default]
+    Remove,
+}
+
+/// What [`Recording::redact`] strips.
+///
+/// The default replaces keys with placeholders, removes text and keeps
+/// positions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RedactionOptions {
+    /// Key identities, including raw key codes.
+    pub keyboard: KeyRedaction,
+    /// Positions of mouse, wheel and touch events.
+    pub mouse_positions: PositionRedaction,
+    /// Characters typed.
+    pub text: TextRedaction,
+}
+
+/// Hands out the placeholder for each key.
+#[derive(Default)]
+struct Placeholders {
+    keys: HashMap<Key, u32>,
+}
+
+impl Placeholders {
+    fn get(&mut self, key: Key) -> u32 {
+        let next = self.keys.len() as u32 + 1;
+        *self.keys.entry(key).or_insert(next)
+    }
+}
+
+fn quantize(value: f64, grid: u32) -> f64 {
+    let grid = f64::from(grid.max(1));
+    (value / grid).floor() * grid
+}
+
+impl Recording {
+    /// A copy of this recording with sensitive input stripped, e.g. before
+    /// attaching it to a bug report.
+    ///
+    /// Event types, timing and the number of events are kept, except for
+    /// keyboard events removed with [`KeyRedaction::Remove`], so timing
+    /// bugs still reproduce. With anything but [`KeyRedaction::Keep`], raw
+    /// key codes are cleared along with the keys. Fields written by newer
+    /// versions of monio are dropped, since they can't be vetted.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::recorder::{PositionRedaction, RedactionOptions, Recording};
+    ///
+    /// let recording = Recording::load("bug.json").unwrap();
+    /// let shareable = recording.redact(RedactionOptions {
+    ///     mouse_positions: PositionRedaction::Quantize(50),
+    ///     ..Default::default()
+    /// });
+    /// shareable.save("bug-redacted.json").unwrap();
+    /// ```
+    pub fn redact(&self, options: RedactionOptions) -> Recording {
+        let mut placeholders = Placeholders::default();
+        let events = self
+            .events
+            .iter()
+            .filter(|recorded| {
+                options.keyboard != KeyRedaction::Remove || recorded.event.keyboard.is_none()
+            })
+            .map(|recorded| {
+                let mut recorded = recorded.clone();
+                redact_event(&mut recorded, &options, &mut placeholders);
+                recorded
+            })
+            .collect();
+
+        Recording {
+            events,
+            created_at: self.created_at,
+            description: self.description.clone(),
+            metadata: super::RecordingMetadata {
+                #[cfg(feature = "serde")]
+                extra: Default::default(),
+                ..self.metadata.clone()
+            },
+            #[cfg(feature = "serde")]
+            extra: Default::default(),
+        }
+    }
+}
+
+fn redact_event(
+    recorded: &mut RecordedEvent,
+    options: &RedactionOptions,
+    placeholders: &mut Placeholders,
+) {
+    let event = &mut recorded.event;
+    let typed = event.event_type == EventType::KeyTyped;
+    if let Some(kb) = &mut event.keyboard {
+        match options.keyboard {
+            KeyRedaction::Keep | KeyRedaction::Remove => {}
+            KeyRedaction::ReplaceWithPlaceholder => {
+                let placeholder = placeholders.get(kb.key);
+                replace_key(kb, placeholder);
+            }
+            KeyRedaction::DropKeyIdentity => replace_key(kb, 0),
+        }
+        if options.text == TextRedaction::Remove {
+            kb.char = None;
+            if typed && matches!(kb.key, Key::Char(_)) {
+                replace_key(kb, 0);
+            }
+        }
+    }
+
+    if let PositionRedaction::Quantize(grid) = options.mouse_positions {
+        if let Some(mouse) = &mut event.mouse {
+            mouse.x = quantize(mouse.x, grid);
+            mouse.y = quantize(mouse.y, grid);
+        }
+        if let Some(wheel) = &mut event.wheel {
+            wheel.x = quantize(wheel.x, grid);
+            wheel.y = quantize(wheel.y, grid);
+        }
+        if let Some(touch) = &mut event.touch {
+            touch.x = quantize(touch.x, grid);
+            touch.y = quantize(touch.y, grid);
+        }
+    }
+}
+
+fn replace_key(kb: &mut KeyboardData, placeholder: u32) {
+    kb.key = Key::Unknown(placeholder);
+    kb.raw_code = placeholder;
+    kb.extended = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{Button, Event};
+    use std::time::Duration;
+
+    fn recording() -> Recording {
+        let mut recording = Recording::new();
+        let events = [
+            Event::key_pressed(Key::ShiftLeft, 42),
+            Event::key_pressed(Key::KeyP, 25),
+            Event::key_typed(Key::Char('P'), 25, 'P'),
+            Event::key_released(Key::KeyP, 25),
+            Event::key_released(Key::ShiftLeft, 42),
+            Event::mouse_moved(123.0, 456.0),
+            Event::mouse_pressed(Button::Left, 123.0, 456.0),
+            Event::key_pressed(Key::KeyW, 17),
+            Event::key_released(Key::KeyW, 17),
+        ];
+        for (i, event) in events.into_iter().enumerate() {
+            recording.events.push(RecordedEvent {
+                elapsed: Duration::from_millis(i as u64 * 10),
+                wall_time: None,
+                event,
+            });
+        }
+        recording
+    }
+
+    fn keys(recording: &Recording) -> Vec<(Key, u32, Option<char>)> {
+        recording
+            .events
+            .iter()
+            .filter_map(|r| r.event.keyboard.as_ref())
+            .map(|kb| (kb.key, kb.raw_code, kb.char))
+            .collect()
+    }
+
+    fn structure(recording: &Recording) -> Vec<(EventType, Duration)> {
+        recording
+            .events
+            .iter()
+            .map(|r| (r.event.event_type, r.elapsed))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_key_identity_survives() {
+        let original = recording();
+        let originals = keys(&original);
+        for keyboard in [
+            KeyRedaction::ReplaceWithPlaceholder,
+            KeyRedaction::DropKeyIdentity,
+            KeyRedaction::Remove,
+        ] {
+            let redacted = original.redact(RedactionOptions {
+                keyboard,
+                ..Default::default()
+            });
+            for (key, raw_code, ch) in keys(&redacted) {
+                assert!(matches!(key, Key::Unknown(_)), "{keyboard:?}: {key:?}");
+                assert!(ch.is_none());
+                assert!(
+                    originals
+                        .iter()
+                        .all(|&(k, code, _)| k != key && code != raw_code),
+                    "{keyboard:?}: {key:?} {raw_code}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_structure_survives() {
+        let original = recording();
+        let redacted = original.redact(RedactionOptions::default());
+        assert_eq!(structure(&redacted), structure(&original));
+        // Presses still pair with their releases
+        assert_eq!(
+            keys(&redacted),
+            [
+                (Key::Unknown(1), 1, None),
+                (Key::Unknown(2), 2, None),
+                (Key::Unknown(3), 3, None),
+                (Key::Unknown(2), 2, None),
+                (Key::Unknown(1), 1, None),
+                (Key::Unknown(4), 4, None),
+                (Key::Unknown(4), 4, None),
+            ]
+        );
+
+        let dropped = original.redact(RedactionOptions {
+            keyboard: KeyRedaction::DropKeyIdentity,
+            ..Default::default()
+        });
+        assert_eq!(structure(&dropped), structure(&original));
+        assert!(
+            keys(&dropped)
+                .iter()
+                .all(|&k| k == (Key::Unknown(0), 0, None))
+        );
+
+        let removed = original.redact(RedactionOptions {
+            keyboard: KeyRedaction::Remove,
+            ..Default::default()
+        });
+        assert_eq!(
+            structure(&removed),
+            [
+                (EventType::MouseMoved, Duration::from_millis(50)),
+                (EventType::MousePressed, Duration::from_millis(60)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_and_positions() {
+        let original = recording();
+        let redacted = original.redact(RedactionOptions {
+            keyboard: KeyRedaction::Keep,
+            mouse_positions: PositionRedaction::Quantize(100),
+            text: TextRedaction::Remove,
+        });
+        let keys = keys(&redacted);
+        assert_eq!(keys[1], (Key::KeyP, 25, None));
+        // The typed character is gone, key included
+        assert_eq!(keys[2], (Key::Unknown(0), 0, None));
+
+        let mouse = redacted.events[5].event.mouse.as_ref().unwrap();
+        assert_eq!((mouse.x, mouse.y), (100.0, 400.0));
+
+        let kept = original.redact(RedactionOptions {
+            keyboard: KeyRedaction::Keep,
+            mouse_positions: PositionRedaction::Keep,
+            text: TextRedaction::Keep,
+        });
+        assert_eq!(kept.events.len(), original.events.len());
+        assert!(
+            kept.events
+                .iter()
+                .zip(&original.events)
+                .all(|(a, b)| a.event == b.event)
+        );
+    }
+}
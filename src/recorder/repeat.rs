@@ -0,0 +1,275 @@
+//! Finding loops in recordings, see [`Recording::detect_repetitions`].
+
+use super::diff::discrete_events;
+use super::{RecordedEvent, Recording};
+use std::ops::Range;
+use std::time::Duration;
+
+/// A sequence of events performed several times in a row, see
+/// [`Recording::detect_repetitions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepeatedSegment {
+    /// The events of each repetition, as index ranges into the recording's
+    /// events, in order.
+    ///
+    /// A repetition starts at its first key, button or wheel event and runs
+    /// up to the next repetition, so mouse moves in between belong to the
+    /// repetition before them. The last one ends with its last key, button
+    /// or wheel event.
+    pub iterations: Vec<Range<usize>>,
+    /// Key, button and wheel events per repetition.
+    pub length: usize,
+    /// Average time from the start of one repetition to the next.
+    pub period: Duration,
+}
+
+impl RepeatedSegment {
+    /// How many times the sequence was performed.
+    pub fn count(&self) -> usize {
+        self.iterations.len()
+    }
+}
+
+impl Recording {
+    /// Find sequences of at least `min_len` key, button and wheel events
+    /// performed at least `min_count` times in a row.
+    ///
+    /// Events are matched on event type, key, character, button and scroll
+    /// direction; timing and mouse moves are ignored. Overlapping candidates
+    /// are resolved in favour of the one covering the most events, so a
+    /// sequence repeated six times is reported once rather than also as a
+    /// twice-as-long sequence repeated three times. Results are in recording
+    /// order.
+    ///
+    /// Takes time proportional to the square of the number of key, button
+    /// and wheel events divided by `min_count`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::recorder::Recording;
+    ///
+    /// let recording = Recording::load("session.json").unwrap();
+    /// if let Some(segment) = recording.detect_repetitions(4, 3).first() {
+    ///     println!("Repeated {} times", segment.count());
+    ///     let iteration = recording.extract(segment);
+    ///     let gap = segment.period.saturating_sub(iteration.duration());
+    ///     iteration.playback_repeated(segment.count(), gap).unwrap();
+    /// }
+    /// ```
+    pub fn detect_repetitions(&self, min_len: usize, min_count: usize) -> Vec<RepeatedSegment> {
+        let (indices, signatures) = discrete_events(&self.events);
+        let (min_len, min_count) = (min_len.max(1), min_count.max(2));
+
+        // (start, length, count) in discrete events
+        let mut candidates = Vec::new();
+        for length in min_len..=signatures.len() / min_count {
+            // A run of `run` matches at distance `length` starting at `start`
+            // means the block at `start` repeats (run + length) / length times
+            let mut start = 0;
+            while start + length < signatures.len() {
+                let run = (start..signatures.len() - length)
+                    .take_while(|&i| signatures[i] == signatures[i + length])
+                    .count();
+                let count = (run + length) / length;
+                if count >= min_count {
+                    candidates.push((start, length, count));
+                }
+                start += run.max(1);
+            }
+        }
+
+        // Greedily keep the candidates covering the most events, preferring
+        // shorter sequences and then earlier ones
+        candidates.sort_by_key(|&(start, length, count)| {
+            (std::cmp::Reverse(length * count), length, start)
+        });
+        let mut chosen: Vec<(usize, usize, usize)> = Vec::new();
+        for candidate in candidates {
+            let (start, length, count) = candidate;
+            let end = start + length * count;
+            if chosen
+                .iter()
+                .all(|&(s, l, c)| end <= s || start >= s + l * c)
+            {
+                chosen.push(candidate);
+            }
+        }
+        chosen.sort_by_key(|&(start, _, _)| start);
+
+        chosen
+            .into_iter()
+            .map(|(start, length, count)| {
+                let iterations: Vec<Range<usize>> = (0..count)
+                    .map(|k| {
+                        let first = indices[start + k * length];
+                        let end = match indices.get(start + (k + 1) * length) {
+                            Some(&next) if k + 1 < count => next,
+                            _ => indices[start + (k + 1) * length - 1] + 1,
+                        };
+                        first..end
+                    })
+                    .collect();
+                let first = self.events[iterations[0].start].elapsed;
+                let last = self.events[iterations[count - 1].start].elapsed;
+                RepeatedSegment {
+                    period: last.saturating_sub(first) / (count as u32 - 1),
+                    iterations,
+                    length,
+                }
+            })
+            .collect()
+    }
+
+    /// A recording of the first repetition of `segment`, starting at zero.
+    ///
+    /// The description and metadata are kept. Events outside the recording
+    /// are ignored.
+    pub fn extract(&self, segment: &RepeatedSegment) -> Recording {
+        let Some(range) = segment.iterations.first() else {
+            return self.clone_with_events(Vec::new());
+        };
+        let events = self
+            .events
+            .get(range.start.min(self.events.len())..range.end.min(self.events.len()))
+            .unwrap_or_default();
+        let offset = events.first().map_or(Duration::ZERO, |e| e.elapsed);
+        self.clone_with_events(
+            events
+                .iter()
+                .map(|recorded| RecordedEvent {
+                    elapsed: recorded.elapsed.saturating_sub(offset),
+                    ..recorded.clone()
+                })
+                .collect(),
+        )
+    }
+
+    fn clone_with_events(&self, events: Vec<RecordedEvent>) -> Recording {
+        Recording {
+            events,
+            created_at: self.created_at,
+            description: self.description.clone(),
+            metadata: self.metadata.clone(),
+            #[cfg(feature = "serde")]
+            extra: self.extra.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{Button, Event};
+    use crate::keycode::Key;
+
+    /// A recording of `(milliseconds, event)` pairs.
+    fn fixture(events: Vec<(u64, Event)>) -> Recording {
+        let mut recording = Recording::new();
+        recording.events = events
+            .into_iter()
+            .map(|(ms, event)| RecordedEvent {
+                elapsed: Duration::from_millis(ms),
+                wall_time: None,
+                event,
+            })
+            .collect();
+        recording
+    }
+
+    /// Press and release of `key`, at `ms`.
+    fn tap(ms: u64, key: Key) -> [(u64, Event); 2] {
+        [
+            (ms, Event::key_pressed(key, 0)),
+            (ms + 10, Event::key_released(key, 0)),
+        ]
+    }
+
+    /// Type "ab", move to a button and click it, `count` times one second
+    /// apart, after typing "x".
+    fn form_filling(count: u64) -> Recording {
+        let mut events = tap(0, Key::KeyX).to_vec();
+        for i in 0..count {
+            let t = 100 + i * 1000;
+            events.extend(tap(t, Key::KeyA));
+            events.extend(tap(t + 50, Key::KeyB));
+            events.push((t + 100, Event::mouse_moved(10.0 * i as f64, 20.0)));
+            events.push((t + 200, Event::mouse_pressed(Button::Left, 50.0, 20.0)));
+            events.push((t + 250, Event::mouse_released(Button::Left, 50.0, 20.0)));
+        }
+        fixture(events)
+    }
+
+    #[test]
+    fn test_detects_loop() {
+        let recording = form_filling(5);
+        let segments = recording.detect_repetitions(3, 3);
+        assert_eq!(segments.len(), 1);
+
+        let segment = &segments[0];
+        assert_eq!(segment.count(), 5);
+        assert_eq!(segment.length, 6);
+        assert_eq!(segment.period, Duration::from_secs(1));
+        // Each iteration is 7 events including the move, after the "x" tap
+        assert_eq!(segment.iterations[0], 2..9);
+        assert_eq!(segment.iterations[1], 9..16);
+        assert_eq!(segment.iterations[4], 30..37);
+    }
+
+    #[test]
+    fn test_prefers_shortest_period() {
+        // "ab" six times is not also reported as "abab" three times
+        let mut events = Vec::new();
+        for i in 0..6 {
+            events.extend(tap(i * 100, Key::KeyA));
+            events.extend(tap(i * 100 + 50, Key::KeyB));
+        }
+        let segments = fixture(events).detect_repetitions(2, 2);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].length, 4);
+        assert_eq!(segments[0].count(), 6);
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let recording = form_filling(2);
+        assert!(recording.detect_repetitions(3, 3).is_empty());
+        assert_eq!(recording.detect_repetitions(3, 2).len(), 1);
+        assert!(recording.detect_repetitions(7, 2).is_empty());
+        assert!(Recording::new().detect_repetitions(1, 2).is_empty());
+    }
+
+    #[test]
+    fn test_separate_loops() {
+        let mut events = Vec::new();
+        for i in 0..3 {
+            events.extend(tap(i * 100, Key::KeyA));
+        }
+        events.extend(tap(1000, Key::KeyZ));
+        for i in 0..4 {
+            events.extend(tap(2000 + i * 100, Key::KeyB));
+        }
+        let segments = fixture(events).detect_repetitions(2, 3);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].iterations[0], 0..2);
+        assert_eq!(segments[0].count(), 3);
+        assert_eq!(segments[1].iterations[0], 8..10);
+        assert_eq!(segments[1].count(), 4);
+    }
+
+    #[test]
+    fn test_extract_first_iteration() {
+        let recording = form_filling(3).with_description("Forms");
+        let segment = &recording.detect_repetitions(3, 3)[0];
+        let iteration = recording.extract(segment);
+
+        assert_eq!(iteration.description.as_deref(), Some("Forms"));
+        assert_eq!(iteration.event_count(), 7);
+        assert_eq!(iteration.events[0].elapsed, Duration::ZERO);
+        assert_eq!(iteration.duration(), Duration::from_millis(250));
+        assert_eq!(
+            iteration.events[0].event.keyboard.as_ref().map(|kb| kb.key),
+            Some(Key::KeyA)
+        );
+    }
+}
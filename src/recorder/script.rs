@@ -0,0 +1,1109 @@
+//! Converting recordings to and from xdotool and AutoHotkey scripts, see
+//! [`Recording::to_xdotool_script`] and [`Recording::to_autohotkey_script`].
+
+use super::{RecordedEvent, Recording};
+use crate::event::{Button, Event, EventType, ScrollDirection};
+use crate::keycode::{Key, us_keystroke};
+use monio_core::keycode::names::{ahk_name, from_ahk_name, from_xdotool_name, xdotool_name};
+use std::time::Duration;
+
+/// A script converted into a recording, see
+/// [`Recording::from_xdotool_script`].
+#[derive(Debug, Clone)]
+pub struct ScriptImport {
+    /// The events of every line that could be converted.
+    pub recording: Recording,
+    /// Lines that couldn't be converted, in order. Nothing of them is in
+    /// the recording.
+    pub unsupported: Vec<UnsupportedLine>,
+}
+
+/// A script line [`ScriptImport`] left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedLine {
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// The line, trimmed.
+    pub text: String,
+    /// What couldn't be converted.
+    pub reason: String,
+}
+
+/// What a script line does, in recording terms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Sleep(Duration),
+    Press(Key),
+    Release(Key),
+    Move(f64, f64),
+    ButtonDown(Button),
+    ButtonUp(Button),
+    Wheel(ScrollDirection, u32),
+}
+
+/// How an event is written in a script.
+enum Converted {
+    Lines(Vec<String>),
+    /// Not input, or covered by other events (typed characters, clicks).
+    Skip,
+    Unsupported(String),
+}
+
+impl Recording {
+    /// This recording as a shell script of `xdotool` commands, for X11.
+    ///
+    /// Keys are written as `keydown`/`keyup` with their X keysym names,
+    /// buttons as `mousedown`/`mouseup`, the wheel as `click 4` to `click 7`
+    /// and pauses as `sleep`. Typed characters and clicks are left out, since
+    /// the presses and releases they come from are in the script. Events
+    /// xdotool can't express, such as touches or keys without a keysym, are
+    /// written as comments so nothing is lost silently.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use monio::recorder::Recording;
+    ///
+    /// let recording = Recording::load("macro.json").unwrap();
+    /// std::fs::write("macro.sh", recording.to_xdotool_script()).unwrap();
+    /// ```
+    pub fn to_xdotool_script(&self) -> String {
+        let mut script = String::from("#!/bin/sh\n# Exported from a monio recording\n");
+        self.export(&mut script, "#", xdotool_sleep, xdotool_lines);
+        script
+    }
+
+    /// This recording as an AutoHotkey v2 script, for Windows.
+    ///
+    /// Keys are written as `Send "{key down}"`, buttons and the wheel as
+    /// `Click` at the event's screen position, moves as `MouseMove` and
+    /// pauses as `Sleep`. What's left out or commented follows
+    /// [`to_xdotool_script`](Self::to_xdotool_script).
+    pub fn to_autohotkey_script(&self) -> String {
+        let mut script = String::from(
+            "; Exported from a monio recording\n\
+             #Requires AutoHotkey v2.0\n\
+             CoordMode \"Mouse\", \"Screen\"\n",
+        );
+        self.export(&mut script, ";", ahk_sleep, ahk_lines);
+        script
+    }
+
+    /// Convert a simple xdotool script into a recording.
+    ///
+    /// Understands one command per line, with or without the `xdotool`
+    /// prefix: `key`, `keydown`, `keyup` (including `ctrl+c` chords), `type`,
+    /// `mousemove`, `click`, `mousedown`, `mouseup` and `sleep`. Comments and
+    /// blank lines are skipped. Anything else, like window commands or
+    /// chained commands, is listed in [`ScriptImport::unsupported`].
+    ///
+    /// `type` assumes a US layout for the keys behind shifted symbols.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use monio::recorder::Recording;
+    ///
+    /// let import = Recording::from_xdotool_script(
+    ///     "xdotool mousemove 100 200\nxdotool click 1\nxdotool search --name Firefox",
+    /// );
+    /// assert_eq!(import.recording.events.len(), 3);
+    /// assert_eq!(import.unsupported[0].line, 3);
+    /// ```
+    pub fn from_xdotool_script(script: &str) -> ScriptImport {
+        import(script, |line| {
+            if line.starts_with('#') {
+                return Ok(None);
+            }
+            xdotool_actions(line).map(Some)
+        })
+    }
+
+    /// Convert a simple AutoHotkey script into a recording.
+    ///
+    /// Understands `Send` (and `SendInput`/`SendEvent`) with key names in
+    /// braces, `{key down}`/`{key up}`, `^!+#` modifiers and plain text,
+    /// `Click`, `MouseMove` and `Sleep`, in v1 or v2 syntax. Comments,
+    /// directives and delay settings are skipped. Anything else, such as
+    /// hotkeys, variables or control flow, is listed in
+    /// [`ScriptImport::unsupported`].
+    ///
+    /// Coordinates are taken as screen coordinates, and plain text assumes a
+    /// US layout for the keys behind shifted symbols.
+    pub fn from_autohotkey_script(script: &str) -> ScriptImport {
+        import(script, |line| {
+            if line.starts_with(';') || line.starts_with('#') {
+                return Ok(None);
+            }
+            ahk_actions(line).map(Some)
+        })
+    }
+
+    /// Write each playable event with `convert`, preceded by the pause
+    /// since the previous one.
+    fn export(
+        &self,
+        script: &mut String,
+        comment: &str,
+        sleep: fn(Duration) -> String,
+        convert: fn(&Event) -> Converted,
+    ) {
+        // Pauses are rounded to milliseconds without drifting
+        let mut slept = Duration::ZERO;
+        for recorded in &self.events {
+            let lines = match convert(&recorded.event) {
+                Converted::Skip => continue,
+                Converted::Unsupported(reason) => {
+                    vec![format!("{comment} unsupported: {reason}")]
+                }
+                Converted::Lines(lines) => lines,
+            };
+            let at = Duration::from_millis(recorded.elapsed.as_millis() as u64);
+            if at > slept {
+                script.push_str(&sleep(at - slept));
+                script.push('\n');
+                slept = at;
+            }
+            for line in lines {
+                script.push_str(&line);
+                script.push('\n');
+            }
+        }
+    }
+}
+
+/// Whether an event is left out of scripts without comment.
+fn skipped(event: &Event) -> bool {
+    matches!(
+        event.event_type,
+        EventType::HookEnabled
+            | EventType::HookDisabled
+            | EventType::GrabSuspended
+            | EventType::GrabDegraded
+            | EventType::GlobalSuspendChanged
+            | EventType::KeyTyped
+            | EventType::MouseClicked
+            | EventType::DisplayChanged
+    )
+}
+
+fn xdotool_sleep(pause: Duration) -> String {
+    format!("sleep {:.3}", pause.as_secs_f64())
+}
+
+fn xdotool_lines(event: &Event) -> Converted {
+    if skipped(event) {
+        return Converted::Skip;
+    }
+    let line = match (
+        event.event_type,
+        &event.keyboard,
+        &event.mouse,
+        &event.wheel,
+    ) {
+        (EventType::KeyPressed | EventType::KeyReleased, Some(kb), _, _) => {
+            let Some(name) = xdotool_name(kb.key) else {
+                return Converted::Unsupported(format!("key {:?} has no keysym", kb.key));
+            };
+            let command = if event.event_type == EventType::KeyPressed {
+                "keydown"
+            } else {
+                "keyup"
+            };
+            format!("xdotool {command} {name}")
+        }
+        (EventType::MouseMoved | EventType::MouseDragged, _, Some(mouse), _) => {
+            format!(
+                "xdotool mousemove {} {}",
+                mouse.x.round() as i64,
+                mouse.y.round() as i64
+            )
+        }
+        (EventType::MousePressed | EventType::MouseReleased, _, Some(mouse), _) => {
+            let Some(number) = mouse.button.and_then(x_button) else {
+                return Converted::Unsupported(format!("button {:?}", mouse.button));
+            };
+            let command = if event.event_type == EventType::MousePressed {
+                "mousedown"
+            } else {
+                "mouseup"
+            };
+            format!("xdotool {command} {number}")
+        }
+        (EventType::MouseWheel, _, _, Some(wheel)) => {
+            let (vertical, horizontal) = wheel.steps();
+            let button = match wheel.direction {
+                ScrollDirection::Up => 4,
+                ScrollDirection::Down => 5,
+                ScrollDirection::Left => 6,
+                ScrollDirection::Right => 7,
+            };
+            let clicks = vertical.unsigned_abs().max(horizontal.unsigned_abs());
+            return Converted::Lines(vec![format!("xdotool click {button}"); clicks as usize]);
+        }
+        _ => return Converted::Unsupported(format!("{:?} event", event.event_type)),
+    };
+    Converted::Lines(vec![line])
+}
+
+/// X button number of a button.
+fn x_button(button: Button) -> Option<u8> {
+    match button {
+        Button::Left => Some(1),
+        Button::Middle => Some(2),
+        Button::Right => Some(3),
+        Button::Button4 => Some(8),
+        Button::Button5 => Some(9),
+        Button::Unknown(_) => None,
+    }
+}
+
+fn ahk_sleep(pause: Duration) -> String {
+    format!("Sleep {}", pause.as_millis())
+}
+
+fn ahk_lines(event: &Event) -> Converted {
+    if skipped(event) {
+        return Converted::Skip;
+    }
+    let line = match (
+        event.event_type,
+        &event.keyboard,
+        &event.mouse,
+        &event.wheel,
+    ) {
+        (EventType::KeyPressed | EventType::KeyReleased, Some(kb), _, _) => {
+            let Some(name) = ahk_name(kb.key) else {
+                return Converted::Unsupported(format!("key {:?} has no AutoHotkey name", kb.key));
+            };
+            let state = if event.event_type == EventType::KeyPressed {
+                "down"
+            } else {
+                "up"
+            };
+            format!("Send \"{{{} {state}}}\"", ahk_escape(&name))
+        }
+        (EventType::MouseMoved | EventType::MouseDragged, _, Some(mouse), _) => {
+            format!(
+                "MouseMove {}, {}, 0",
+                mouse.x.round() as i64,
+                mouse.y.round() as i64
+            )
+        }
+        (EventType::MousePressed | EventType::MouseReleased, _, Some(mouse), _) => {
+            let Some(name) = mouse.button.and_then(ahk_button) else {
+                return Converted::Unsupported(format!("button {:?}", mouse.button));
+            };
+            let state = if event.event_type == EventType::MousePressed {
+                "Down"
+            } else {
+                "Up"
+            };
+            format!(
+                "Click \"{} {} {name} {state}\"",
+                mouse.x.round() as i64,
+                mouse.y.round() as i64
+            )
+        }
+        (EventType::MouseWheel, _, _, Some(wheel)) => {
+            let (vertical, horizontal) = wheel.steps();
+            let name = match wheel.direction {
+                ScrollDirection::Up => "WheelUp",
+                ScrollDirection::Down => "WheelDown",
+                ScrollDirection::Left => "WheelLeft",
+                ScrollDirection::Right => "WheelRight",
+            };
+            format!(
+                "Click \"{} {} {name} {}\"",
+                wheel.x.round() as i64,
+                wheel.y.round() as i64,
+                vertical.unsigned_abs().max(horizontal.unsigned_abs())
+            )
+        }
+        _ => return Converted::Unsupported(format!("{:?} event", event.event_type)),
+    };
+    Converted::Lines(vec![line])
+}
+
+fn ahk_button(button: Button) -> Option<&'static str> {
+    match button {
+        Button::Left => Some("Left"),
+        Button::Right => Some("Right"),
+        Button::Middle => Some("Middle"),
+        Button::Button4 => Some("X1"),
+        Button::Button5 => Some("X2"),
+        Button::Unknown(_) => None,
+    }
+}
+
+/// Escape text for a double-quoted AutoHotkey v2 string.
+fn ahk_escape(text: &str) -> String {
+    text.replace('`', "``").replace('"', "`\"")
+}
+
+/// Run each non-blank line through `parse` and replay the actions.
+///
+/// `parse` returns `Ok(None)` for lines to skip, such as comments.
+fn import(
+    script: &str,
+    parse: impl Fn(&str) -> Result<Option<Vec<Action>>, String>,
+) -> ScriptImport {
+    let mut builder = Builder::default();
+    let mut unsupported = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parse(line) {
+            Ok(Some(actions)) => actions.into_iter().for_each(|a| builder.apply(a)),
+            Ok(None) => {}
+            Err(reason) => unsupported.push(UnsupportedLine {
+                line: index + 1,
+                text: line.to_string(),
+                reason,
+            }),
+        }
+    }
+    ScriptImport {
+        recording: builder.recording,
+        unsupported,
+    }
+}
+
+/// Turns actions into timed events.
+#[derive(Default)]
+struct Builder {
+    recording: Recording,
+    elapsed: Duration,
+    /// Where the mouse was last moved to.
+    position: Option<(f64, f64)>,
+    buttons_down: usize,
+}
+
+impl Builder {
+    fn apply(&mut self, action: Action) {
+        let (x, y) = self.position.unwrap_or_default();
+        let event = match action {
+            Action::Sleep(pause) => {
+                self.elapsed += pause;
+                return;
+            }
+            Action::Press(key) => Event::key_pressed(key, 0),
+            Action::Release(key) => Event::key_released(key, 0),
+            // Clicks at a position move there first, often where the
+            // mouse already is
+            Action::Move(x, y) if self.position == Some((x, y)) => return,
+            Action::Move(x, y) => {
+                self.position = Some((x, y));
+                if self.buttons_down > 0 {
+                    Event::mouse_dragged(x, y)
+                } else {
+                    Event::mouse_moved(x, y)
+                }
+            }
+            Action::ButtonDown(button) => {
+                self.buttons_down += 1;
+                Event::mouse_pressed(button, x, y)
+            }
+            Action::ButtonUp(button) => {
+                self.buttons_down = self.buttons_down.saturating_sub(1);
+                Event::mouse_released(button, x, y)
+            }
+            Action::Wheel(direction, steps) => Event::mouse_wheel(x, y, direction, steps as f64),
+        };
+        self.recording.events.push(RecordedEvent {
+            elapsed: self.elapsed,
+            wall_time: None,
+            event: Event {
+                time: self.recording.created_at + self.elapsed,
+                ..event
+            },
+        });
+    }
+}
+
+/// Actions typing `text`, with Shift for capitals and shifted symbols.
+fn type_text(text: &str, actions: &mut Vec<Action>) {
+    for ch in text.chars() {
+        let (key, shift) = us_keystroke(ch);
+        if shift {
+            actions.push(Action::Press(Key::ShiftLeft));
+        }
+        actions.extend([Action::Press(key), Action::Release(key)]);
+        if shift {
+            actions.push(Action::Release(Key::ShiftLeft));
+        }
+    }
+}
+
+fn xdotool_actions(line: &str) -> Result<Vec<Action>, String> {
+    let words = shell_words(line)?;
+    let mut words = words.iter().map(String::as_str).peekable();
+    if words.peek() == Some(&"xdotool") {
+        words.next();
+    }
+    let command = words.next().ok_or("missing command")?;
+    let known = [
+        "sleep",
+        "key",
+        "keydown",
+        "keyup",
+        "type",
+        "mousemove",
+        "click",
+        "mousedown",
+        "mouseup",
+    ];
+    if !known.contains(&command) {
+        return Err(format!("command {command}"));
+    }
+
+    // Options of the supported commands; those taking a value consume it
+    let mut repeat = 1;
+    let mut args = Vec::new();
+    while let Some(word) = words.next() {
+        match word {
+            "--repeat" => {
+                repeat = words
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--repeat needs a count")?;
+            }
+            "--delay" => {
+                words.next();
+            }
+            "--sync" | "--clearmodifiers" => {}
+            _ if word.starts_with("--") => return Err(format!("option {word}")),
+            _ => args.push(word),
+        }
+    }
+
+    let mut actions = Vec::new();
+    match command {
+        "sleep" => {
+            let seconds: f64 = match args.as_slice() {
+                [seconds] => seconds.parse().map_err(|_| "invalid duration")?,
+                _ => return Err("sleep takes one duration".into()),
+            };
+            let micros = (seconds.max(0.0) * 1_000_000.0).round() as u64;
+            actions.push(Action::Sleep(Duration::from_micros(micros)));
+        }
+        "key" | "keydown" | "keyup" => {
+            if args.is_empty() {
+                return Err(format!("{command} needs a key"));
+            }
+            for _ in 0..repeat {
+                for chord in &args {
+                    let keys = chord
+                        .split('+')
+                        .map(|name| from_xdotool_name(name).ok_or(format!("unknown key {name}")))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if command != "keyup" {
+                        actions.extend(keys.iter().map(|key| Action::Press(*key)));
+                    }
+                    if command != "keydown" {
+                        actions.extend(keys.iter().rev().map(|key| Action::Release(*key)));
+                    }
+                }
+            }
+        }
+        "type" => {
+            for _ in 0..repeat {
+                type_text(&args.join(" "), &mut actions);
+            }
+        }
+        "mousemove" => match args.as_slice() {
+            [x, y] => {
+                let x = x.parse().map_err(|_| "invalid x coordinate")?;
+                let y = y.parse().map_err(|_| "invalid y coordinate")?;
+                actions.push(Action::Move(x, y));
+            }
+            _ => return Err("mousemove takes x and y".into()),
+        },
+        "click" | "mousedown" | "mouseup" => {
+            let number: u8 = match args.as_slice() {
+                [number] => number.parse().map_err(|_| "invalid button")?,
+                _ => return Err(format!("{command} takes one button")),
+            };
+            let wheel = match number {
+                4 => Some(ScrollDirection::Up),
+                5 => Some(ScrollDirection::Down),
+                6 => Some(ScrollDirection::Left),
+                7 => Some(ScrollDirection::Right),
+                _ => None,
+            };
+            let button = match number {
+                1 => Button::Left,
+                2 => Button::Middle,
+                3 => Button::Right,
+                8 => Button::Button4,
+                9 => Button::Button5,
+                _ if wheel.is_some() => Button::Unknown(number),
+                _ => return Err(format!("button {number}")),
+            };
+            for _ in 0..repeat {
+                match (wheel, command) {
+                    (Some(direction), "click") => actions.push(Action::Wheel(direction, 1)),
+                    (Some(_), _) => return Err(format!("{command} on a wheel button")),
+                    (None, "mousedown") => actions.push(Action::ButtonDown(button)),
+                    (None, "mouseup") => actions.push(Action::ButtonUp(button)),
+                    (None, _) => {
+                        actions.extend([Action::ButtonDown(button), Action::ButtonUp(button)])
+                    }
+                }
+            }
+        }
+        _ => unreachable!("checked above"),
+    }
+    Ok(actions)
+}
+
+/// Split a line into words like a shell, honouring quotes and backslashes.
+fn shell_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ' ' | '\t' => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => word.push(ch),
+                        None => return Err("unterminated quote".into()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => word.extend(chars.next()),
+                        Some(ch) => word.push(ch),
+                        None => return Err("unterminated quote".into()),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            '#' if word.is_none() => break,
+            _ => word.get_or_insert_with(String::new).push(ch),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn ahk_actions(line: &str) -> Result<Vec<Action>, String> {
+    let split = line
+        .find(|ch: char| !ch.is_ascii_alphanumeric())
+        .unwrap_or(line.len());
+    let (command, rest) = line.split_at(split);
+    let mut args = rest.trim();
+    if let Some(stripped) = args.strip_prefix(',') {
+        args = stripped.trim();
+    } else if let Some(stripped) = args.strip_prefix('(').and_then(|a| a.strip_suffix(')')) {
+        args = stripped.trim();
+    }
+    let args = strip_ahk_comment(args);
+
+    let mut actions = Vec::new();
+    match command.to_ascii_lowercase().as_str() {
+        "sleep" => {
+            let ms: u64 = args.parse().map_err(|_| "invalid delay")?;
+            actions.push(Action::Sleep(Duration::from_millis(ms)));
+        }
+        "send" | "sendinput" | "sendevent" | "sendplay" => {
+            send_actions(&ahk_string(args)?, &mut actions)?;
+        }
+        "click" => click_actions(&ahk_string(args)?, &mut actions)?,
+        "mousemove" => {
+            let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+            let relative = parts
+                .get(3)
+                .is_some_and(|mode| mode.trim_matches('"').eq_ignore_ascii_case("R"));
+            match parts.as_slice() {
+                _ if relative => return Err("relative moves".into()),
+                [x, y, ..] => {
+                    let x = x.parse().map_err(|_| "invalid x coordinate")?;
+                    let y = y.parse().map_err(|_| "invalid y coordinate")?;
+                    actions.push(Action::Move(x, y));
+                }
+                _ => return Err("MouseMove takes x and y".into()),
+            }
+        }
+        "coordmode" => {
+            let parts: Vec<String> = args
+                .split(',')
+                .map(|part| part.trim().trim_matches('"').to_ascii_lowercase())
+                .collect();
+            if !matches!(parts.as_slice(), [target, mode] if target == "mouse" && mode == "screen")
+            {
+                return Err("coordinates other than mouse screen coordinates".into());
+            }
+        }
+        // Delays and send modes don't change what is sent
+        "setkeydelay" | "setmousedelay" | "setdefaultmousespeed" | "sendmode" => {}
+        _ => return Err(format!("command {command}")),
+    }
+    Ok(actions)
+}
+
+/// Cut a trailing `;` comment, which must follow whitespace.
+fn strip_ahk_comment(args: &str) -> &str {
+    let mut in_string = false;
+    let mut previous = ' ';
+    for (index, ch) in args.char_indices() {
+        match ch {
+            '"' if previous != '`' => in_string = !in_string,
+            ';' if !in_string && previous.is_whitespace() => return args[..index].trim_end(),
+            _ => {}
+        }
+        previous = ch;
+    }
+    args
+}
+
+/// The text of an argument: a v2 quoted string, unescaped, or v1 raw text.
+fn ahk_string(arg: &str) -> Result<String, String> {
+    let Some(quoted) = arg.strip_prefix('"') else {
+        return Ok(arg.to_string());
+    };
+    let quoted = quoted.strip_suffix('"').ok_or("unterminated string")?;
+    let mut text = String::new();
+    let mut chars = quoted.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '`' {
+            text.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some('r') => text.push('\r'),
+            Some(other) => text.push(other),
+            None => return Err("dangling escape".into()),
+        }
+    }
+    Ok(text)
+}
+
+/// Actions for the keys of a `Send` string.
+fn send_actions(keys: &str, actions: &mut Vec<Action>) -> Result<(), String> {
+    let mut modifiers: Vec<Key> = Vec::new();
+    let mut chars = keys.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        let modifier = match ch {
+            '^' => Some(Key::ControlLeft),
+            '!' => Some(Key::AltLeft),
+            '+' => Some(Key::ShiftLeft),
+            '#' => Some(Key::MetaLeft),
+            _ => None,
+        };
+        if let Some(modifier) = modifier {
+            modifiers.push(modifier);
+            continue;
+        }
+
+        let mut tap = Vec::new();
+        if ch == '{' {
+            // `{{}` and `{}}` name the braces themselves
+            let body_start = start + 1;
+            let close = keys[body_start + 1..]
+                .find('}')
+                .map(|i| body_start + 1 + i)
+                .ok_or("unterminated {")?;
+            let body = &keys[body_start..close];
+            while chars.peek().is_some_and(|(i, _)| *i <= close) {
+                chars.next();
+            }
+            let (name, suffix) = match body.rsplit_once(' ') {
+                Some((name, suffix)) => (name, suffix.to_ascii_lowercase()),
+                None => (body, String::new()),
+            };
+            match name.to_ascii_lowercase().as_str() {
+                "text" | "raw" if suffix.is_empty() => {
+                    let text: String = chars.by_ref().map(|(_, ch)| ch).collect();
+                    type_text(&text, actions);
+                    break;
+                }
+                "blind" if suffix.is_empty() => continue,
+                _ => {}
+            }
+            let key = if name.chars().count() == 1 && "{}".contains(name) {
+                None
+            } else {
+                Some(from_ahk_name(name).ok_or(format!("unknown key {name}"))?)
+            };
+            match (key, suffix.as_str()) {
+                (Some(key), "down") => actions.push(Action::Press(key)),
+                (Some(key), "up") => actions.push(Action::Release(key)),
+                (_, suffix) => {
+                    let count: usize = if suffix.is_empty() {
+                        1
+                    } else {
+                        suffix.parse().map_err(|_| format!("invalid key {body}"))?
+                    };
+                    for _ in 0..count {
+                        match key {
+                            Some(key) => tap.extend([Action::Press(key), Action::Release(key)]),
+                            None => type_text(name, &mut tap),
+                        }
+                    }
+                }
+            }
+        } else {
+            type_text(&ch.to_string(), &mut tap);
+        }
+
+        // Modifiers apply to the next key only
+        actions.extend(modifiers.iter().map(|key| Action::Press(*key)));
+        actions.append(&mut tap);
+        actions.extend(modifiers.drain(..).rev().map(Action::Release));
+    }
+    if !modifiers.is_empty() {
+        return Err("modifier without a key".into());
+    }
+    Ok(())
+}
+
+/// Actions for the options of a `Click`.
+fn click_actions(options: &str, actions: &mut Vec<Action>) -> Result<(), String> {
+    let mut numbers = Vec::new();
+    let mut button = Button::Left;
+    let mut wheel = None;
+    let mut state = None;
+    for word in options
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .filter(|word| !word.is_empty())
+    {
+        if let Ok(number) = word.parse::<f64>() {
+            numbers.push(number);
+            continue;
+        }
+        match word.to_ascii_lowercase().as_str() {
+            "left" | "l" => button = Button::Left,
+            "right" | "r" => button = Button::Right,
+            "middle" | "m" => button = Button::Middle,
+            "x1" => button = Button::Button4,
+            "x2" => button = Button::Button5,
+            "wheelup" | "wu" => wheel = Some(ScrollDirection::Up),
+            "wheeldown" | "wd" => wheel = Some(ScrollDirection::Down),
+            "wheelleft" | "wl" => wheel = Some(ScrollDirection::Left),
+            "wheelright" | "wr" => wheel = Some(ScrollDirection::Right),
+            "down" | "d" => state = Some(true),
+            "up" | "u" => state = Some(false),
+            _ => return Err(format!("click option {word}")),
+        }
+    }
+
+    let count = match numbers.as_slice() {
+        [] => 1.0,
+        [count] => *count,
+        [x, y] => {
+            actions.push(Action::Move(*x, *y));
+            1.0
+        }
+        [x, y, count] => {
+            actions.push(Action::Move(*x, *y));
+            *count
+        }
+        _ => return Err("too many numbers".into()),
+    };
+    let count = count.max(0.0) as u32;
+    match (wheel, state) {
+        (Some(direction), None) => actions.push(Action::Wheel(direction, count)),
+        (Some(_), Some(_)) => return Err("wheel with down or up".into()),
+        (None, Some(true)) => actions.push(Action::ButtonDown(button)),
+        (None, Some(false)) => actions.push(Action::ButtonUp(button)),
+        (None, None) => {
+            for _ in 0..count {
+                actions.extend([Action::ButtonDown(button), Action::ButtonUp(button)]);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(recording: &Recording) -> Vec<(u128, EventType, String)> {
+        recording
+            .events
+            .iter()
+            .map(|recorded| {
+                let event = &recorded.event;
+                let detail = match (&event.keyboard, &event.mouse, &event.wheel) {
+                    (Some(kb), _, _) => format!("{:?}", kb.key),
+                    (_, Some(mouse), _) => format!("{:?} {} {}", mouse.button, mouse.x, mouse.y),
+                    (_, _, Some(wheel)) => format!("{:?} {}", wheel.direction, wheel.delta),
+                    _ => String::new(),
+                };
+                (recorded.elapsed.as_millis(), event.event_type, detail)
+            })
+            .collect()
+    }
+
+    fn recording(events: Vec<(u64, Event)>) -> Recording {
+        let mut recording = Recording::new();
+        for (ms, event) in events {
+            recording.events.push(RecordedEvent {
+                elapsed: Duration::from_millis(ms),
+                wall_time: None,
+                event,
+            });
+        }
+        recording
+    }
+
+    /// Ctrl+Shift+A, a drag, a scroll and a typed character.
+    fn sample() -> Recording {
+        recording(vec![
+            (0, Event::hook_enabled()),
+            (0, Event::key_pressed(Key::ControlLeft, 0)),
+            (10, Event::key_pressed(Key::ShiftLeft, 0)),
+            (20, Event::key_pressed(Key::KeyA, 0)),
+            (20, Event::key_typed(Key::KeyA, 0, 'A')),
+            (90, Event::key_released(Key::KeyA, 0)),
+            (95, Event::key_released(Key::ShiftLeft, 0)),
+            (95, Event::key_released(Key::ControlLeft, 0)),
+            (500, Event::mouse_moved(100.0, 200.0)),
+            (650, Event::mouse_pressed(Button::Left, 100.0, 200.0)),
+            (700, Event::mouse_dragged(300.0, 250.0)),
+            (800, Event::mouse_released(Button::Left, 300.0, 250.0)),
+            (800, Event::mouse_clicked(Button::Left, 300.0, 250.0, 1)),
+            (
+                1500,
+                Event::mouse_wheel(300.0, 250.0, ScrollDirection::Down, 1.0),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_xdotool_export() {
+        let script = sample().to_xdotool_script();
+        let body: Vec<&str> = script.lines().skip(2).collect();
+        assert_eq!(
+            body,
+            [
+                "xdotool keydown Control_L",
+                "sleep 0.010",
+                "xdotool keydown Shift_L",
+                "sleep 0.010",
+                "xdotool keydown a",
+                "sleep 0.070",
+                "xdotool keyup a",
+                "sleep 0.005",
+                "xdotool keyup Shift_L",
+                "xdotool keyup Control_L",
+                "sleep 0.405",
+                "xdotool mousemove 100 200",
+                "sleep 0.150",
+                "xdotool mousedown 1",
+                "sleep 0.050",
+                "xdotool mousemove 300 250",
+                "sleep 0.100",
+                "xdotool mouseup 1",
+                "sleep 0.700",
+                "xdotool click 5",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xdotool_round_trip() {
+        let original = sample();
+        let import = Recording::from_xdotool_script(&original.to_xdotool_script());
+        assert!(import.unsupported.is_empty(), "{:?}", import.unsupported);
+        assert_eq!(
+            import.recording.to_xdotool_script(),
+            original.to_xdotool_script()
+        );
+
+        let mut expected = original.clone();
+        expected.events.retain(|recorded| !skipped(&recorded.event));
+        assert_eq!(summary(&import.recording), summary(&expected));
+    }
+
+    #[test]
+    fn test_autohotkey_export() {
+        let script = sample().to_autohotkey_script();
+        let lines: Vec<&str> = script.lines().collect();
+        assert_eq!(lines[1], "#Requires AutoHotkey v2.0");
+        assert_eq!(
+            lines[3..8],
+            [
+                "Send \"{LCtrl down}\"",
+                "Sleep 10",
+                "Send \"{LShift down}\"",
+                "Sleep 10",
+                "Send \"{a down}\"",
+            ]
+        );
+        assert!(lines.contains(&"Click \"100 200 Left Down\""));
+        assert!(lines.contains(&"MouseMove 300, 250, 0"));
+        assert_eq!(lines.last(), Some(&"Click \"300 250 WheelDown 1\""));
+    }
+
+    #[test]
+    fn test_autohotkey_round_trip() {
+        let original = sample();
+        let import = Recording::from_autohotkey_script(&original.to_autohotkey_script());
+        assert!(import.unsupported.is_empty(), "{:?}", import.unsupported);
+        assert_eq!(
+            import.recording.to_autohotkey_script(),
+            original.to_autohotkey_script()
+        );
+    }
+
+    #[test]
+    fn test_unsupported_events_are_commented() {
+        let recording = recording(vec![
+            (0, Event::key_pressed(Key::Unknown(300), 0)),
+            (0, Event::touch(EventType::TouchBegan, 1, 0.0, 0.0, None)),
+            (0, Event::key_pressed(Key::Char('"'), 0)),
+        ]);
+        let xdotool = recording.to_xdotool_script();
+        assert!(xdotool.contains("# unsupported: key Unknown(300) has no keysym"));
+        assert!(xdotool.contains("# unsupported: TouchBegan event"));
+        assert!(xdotool.contains("xdotool keydown U0022"));
+
+        let ahk = recording.to_autohotkey_script();
+        assert!(ahk.contains("; unsupported: key Unknown(300) has no AutoHotkey name"));
+        assert!(ahk.contains("Send \"{`\" down}\""));
+        let import = Recording::from_autohotkey_script(&ahk);
+        assert!(import.unsupported.is_empty(), "{:?}", import.unsupported);
+        assert_eq!(
+            summary(&import.recording),
+            vec![(0, EventType::KeyPressed, "Char('\"')".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_xdotool_import() {
+        let script = "\
+#!/bin/bash
+# Log in
+xdotool mousemove --sync 640 400 click --repeat 2 1
+xdotool type 'Hi!'
+xdotool key --delay 50 ctrl+s Return
+sleep 0.5
+xdotool search --name Firefox windowactivate
+xdotool click 4
+";
+        let import = Recording::from_xdotool_script(script);
+        let unsupported: Vec<_> = import
+            .unsupported
+            .iter()
+            .map(|line| (line.line, line.reason.as_str()))
+            .collect();
+        assert_eq!(
+            unsupported,
+            [(3, "mousemove takes x and y"), (7, "command search")]
+        );
+
+        let keys: Vec<(EventType, Key)> = import
+            .recording
+            .events
+            .iter()
+            .filter_map(|r| {
+                r.event
+                    .keyboard
+                    .as_ref()
+                    .map(|kb| (r.event.event_type, kb.key))
+            })
+            .collect();
+        use EventType::{KeyPressed as P, KeyReleased as R};
+        assert_eq!(
+            keys,
+            [
+                (P, Key::ShiftLeft),
+                (P, Key::KeyH),
+                (R, Key::KeyH),
+                (R, Key::ShiftLeft),
+                (P, Key::KeyI),
+                (R, Key::KeyI),
+                (P, Key::ShiftLeft),
+                (P, Key::Num1),
+                (R, Key::Num1),
+                (R, Key::ShiftLeft),
+                (P, Key::ControlLeft),
+                (P, Key::KeyS),
+                (R, Key::KeyS),
+                (R, Key::ControlLeft),
+                (P, Key::Enter),
+                (R, Key::Enter),
+            ]
+        );
+        let last = import.recording.events.last().unwrap();
+        assert_eq!(last.event.event_type, EventType::MouseWheel);
+        assert_eq!(last.elapsed, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_autohotkey_import() {
+        let script = "\
+#SingleInstance Force
+SetKeyDelay 20 ; irrelevant
+Send, ^c
+Sleep, 100
+Send \"{Shift down}ab{Shift up}{Enter 2}\"
+Click 100, 200
+Click \"R\"
+MouseMove(10, 20)
+MsgBox \"done\"
+Send {Bogus}
+Click \"50 60 WheelUp 3\"
+";
+        let import = Recording::from_autohotkey_script(script);
+        let unsupported: Vec<_> = import
+            .unsupported
+            .iter()
+            .map(|line| (line.line, line.reason.as_str()))
+            .collect();
+        assert_eq!(
+            unsupported,
+            [(9, "command MsgBox"), (10, "unknown key Bogus")]
+        );
+
+        let summary = summary(&import.recording);
+        let expected = [
+            (0, EventType::KeyPressed, "ControlLeft"),
+            (0, EventType::KeyPressed, "KeyC"),
+            (0, EventType::KeyReleased, "KeyC"),
+            (0, EventType::KeyReleased, "ControlLeft"),
+            (100, EventType::KeyPressed, "ShiftLeft"),
+            (100, EventType::KeyPressed, "KeyA"),
+            (100, EventType::KeyReleased, "KeyA"),
+            (100, EventType::KeyPressed, "KeyB"),
+            (100, EventType::KeyReleased, "KeyB"),
+            (100, EventType::KeyReleased, "ShiftLeft"),
+            (100, EventType::KeyPressed, "Enter"),
+            (100, EventType::KeyReleased, "Enter"),
+            (100, EventType::KeyPressed, "Enter"),
+            (100, EventType::KeyReleased, "Enter"),
+            (100, EventType::MouseMoved, "None 100 200"),
+            (100, EventType::MousePressed, "Some(Left) 100 200"),
+            (100, EventType::MouseReleased, "Some(Left) 100 200"),
+            (100, EventType::MousePressed, "Some(Right) 100 200"),
+            (100, EventType::MouseReleased, "Some(Right) 100 200"),
+            (100, EventType::MouseMoved, "None 10 20"),
+            (100, EventType::MouseMoved, "None 50 60"),
+            (100, EventType::MouseWheel, "Up 3"),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(ms, event_type, detail)| (*ms, *event_type, detail.to_string()))
+            .collect();
+        assert_eq!(summary, expected);
+    }
+}
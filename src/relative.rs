@@ -0,0 +1,241 @@
+//! Relative pointer mode ("pointer lock").
+//!
+//! [`relative_pointer_mode`] hides the cursor, pins it to the centre of the
+//! display it is on and reports how far the mouse moved, which is what games
+//! and 3D viewports want. Everything is restored when the returned
+//! [`RelativeModeGuard`] is dropped, including while unwinding from a panic.
+//!
+//! After every move the cursor is warped back to the centre, so each
+//! reported position is a delta from the centre. Deltas are pointer deltas
+//! after the system's acceleration, not raw device counts. Platform notes:
+//!
+//! - **macOS**: warps don't produce events. The cursor is hidden only while
+//!   this process is the active application.
+//! - **Windows**: warps don't reach the low-level hook. The cursor is hidden
+//!   only over this process's windows.
+//! - **X11**: the server reports each warp as a move back to the centre,
+//!   which is recognised and skipped.
+//! - **evdev**: positions are accumulated from `REL_X`/`REL_Y` without
+//!   clamping, so deltas are the device's relative events. The cursor can't
+//!   be hidden or pinned; the compositor keeps moving it.
+//!
+//! A move that happened before the previous warp took effect is measured
+//! from the centre too, so under heavy load some distance can be counted
+//! twice.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::relative::relative_pointer_mode;
+//!
+//! let guard = relative_pointer_mode(|delta| {
+//!     println!("dx={:+.0} dy={:+.0}", delta.dx, delta.dy);
+//! })?;
+//! std::thread::sleep(std::time::Duration::from_secs(5));
+//! drop(guard);
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::confine::{ConfineGuard, confine_cursor};
+use crate::cursor::{HiddenCursor, hide_cursor, warp_cursor};
+use crate::display::{display_at_point, primary_display};
+use crate::error::{Error, Result};
+use crate::event::{Event, EventType};
+use crate::hook::Hook;
+use crate::snapshot::input_snapshot;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver};
+use std::time::SystemTime;
+
+/// Mouse movement reported in relative pointer mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseDelta {
+    /// Horizontal movement in screen points (positive is right).
+    pub dx: f64,
+    /// Vertical movement in screen points (positive is down).
+    pub dy: f64,
+    /// When the movement happened.
+    pub time: SystemTime,
+}
+
+/// Turns cursor positions into deltas.
+#[derive(Debug)]
+struct DeltaTracker {
+    /// Where the cursor is warped back to, if it is pinned.
+    center: Option<(f64, f64)>,
+    last: Option<(f64, f64)>,
+}
+
+impl DeltaTracker {
+    fn pinned(center: (f64, f64)) -> Self {
+        Self {
+            center: Some(center),
+            last: None,
+        }
+    }
+
+    fn unpinned() -> Self {
+        Self {
+            center: None,
+            last: None,
+        }
+    }
+
+    /// The movement that brought the cursor to `(x, y)`, if any.
+    fn update(&mut self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let origin = match self.center {
+            Some(center) => center,
+            None => self.last.replace((x, y))?,
+        };
+        let delta = (x - origin.0, y - origin.1);
+        (delta != (0.0, 0.0)).then_some(delta)
+    }
+}
+
+/// Keeps relative pointer mode active until dropped.
+///
+/// Dropping stops the hook, releases the cursor, shows it again and puts it
+/// back where it was when relative mode started.
+#[must_use = "relative mode ends as soon as the guard is dropped"]
+pub struct RelativeModeGuard {
+    hook: Hook,
+    confined: Option<ConfineGuard>,
+    hidden: Option<HiddenCursor>,
+    restore_position: Option<(f64, f64)>,
+}
+
+impl Drop for RelativeModeGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.hook.stop() {
+            log::debug!("Failed to stop relative mode hook: {}", e);
+        }
+        self.confined.take();
+        self.hidden.take();
+        if let Some((x, y)) = self.restore_position
+            && let Err(e) = warp_cursor(x, y)
+        {
+            log::debug!("Failed to restore cursor position: {}", e);
+        }
+    }
+}
+
+/// Treat `NotSupported` as "do without", keeping other errors.
+fn optional<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::NotSupported(reason)) => {
+            log::debug!("Relative mode without cursor control: {}", reason);
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Enter relative pointer mode, calling `callback` with each movement.
+///
+/// The callback runs on the hook thread; see the [module docs](self) for
+/// per-platform behaviour.
+pub fn relative_pointer_mode<F>(callback: F) -> Result<RelativeModeGuard>
+where
+    F: Fn(MouseDelta) + Send + Sync + 'static,
+{
+    let position = input_snapshot().mouse_position;
+    let display = match position {
+        Some((x, y)) => display_at_point(x, y).ok().flatten(),
+        None => None,
+    }
+    .or_else(|| primary_display().ok());
+
+    // Pin only where the cursor can actually be warped
+    let pinned = match (&display, position) {
+        (Some(display), Some(_)) => {
+            let bounds = display.bounds;
+            let center = (
+                (bounds.x + bounds.width / 2.0).round(),
+                (bounds.y + bounds.height / 2.0).round(),
+            );
+            optional(warp_cursor(center.0, center.1))?.map(|_| (center, bounds))
+        }
+        _ => None,
+    };
+    let confined = match pinned {
+        Some((_, bounds)) => optional(confine_cursor(bounds))?,
+        None => None,
+    };
+    let hidden = optional(hide_cursor())?;
+
+    let tracker = Mutex::new(match pinned {
+        Some((center, _)) => DeltaTracker::pinned(center),
+        None => DeltaTracker::unpinned(),
+    });
+    let center = pinned.map(|(center, _)| center);
+
+    // Built first so that a failed start restores everything too
+    let guard = RelativeModeGuard {
+        hook: Hook::new(),
+        confined,
+        hidden,
+        restore_position: pinned.and(position),
+    };
+    guard.hook.run_async(move |event: &Event| {
+        if !matches!(
+            event.event_type,
+            EventType::MouseMoved | EventType::MouseDragged
+        ) {
+            return;
+        }
+        let Some(mouse) = &event.mouse else {
+            return;
+        };
+        let Some((dx, dy)) = tracker
+            .lock()
+            .ok()
+            .and_then(|mut t| t.update(mouse.x, mouse.y))
+        else {
+            return;
+        };
+        if let Some((x, y)) = center
+            && let Err(e) = warp_cursor(x, y)
+        {
+            log::debug!("Failed to re-center cursor: {}", e);
+        }
+        callback(MouseDelta {
+            dx,
+            dy,
+            time: event.time,
+        });
+    })?;
+    Ok(guard)
+}
+
+/// Enter relative pointer mode, receiving movements on a channel.
+pub fn relative_pointer_channel() -> Result<(RelativeModeGuard, Receiver<MouseDelta>)> {
+    let (tx, rx) = mpsc::channel();
+    let guard = relative_pointer_mode(move |delta| {
+        let _ = tx.send(delta);
+    })?;
+    Ok((guard, rx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_deltas_are_relative_to_center() {
+        let mut tracker = DeltaTracker::pinned((500.0, 400.0));
+        assert_eq!(tracker.update(505.0, 398.0), Some((5.0, -2.0)));
+        // The warp back to the centre, as reported on X11
+        assert_eq!(tracker.update(500.0, 400.0), None);
+        assert_eq!(tracker.update(490.0, 400.0), Some((-10.0, 0.0)));
+    }
+
+    #[test]
+    fn test_unpinned_deltas_follow_positions() {
+        let mut tracker = DeltaTracker::unpinned();
+        assert_eq!(tracker.update(10.0, 10.0), None);
+        assert_eq!(tracker.update(13.0, 10.0), Some((3.0, 0.0)));
+        assert_eq!(tracker.update(13.0, 6.0), Some((0.0, -4.0)));
+        assert_eq!(tracker.update(13.0, 6.0), None);
+    }
+}
@@ -0,0 +1,307 @@
+//! Key rollover (NKRO) and ghosting diagnostics.
+//!
+//! [`RolloverTester`] consumes a stream of keyboard events and tracks how many
+//! keys were held at once, which keys appear stuck (held for a long time with
+//! no autorepeat, usually a missed release), and which prompted combinations
+//! never fully registered (ghosting / limited rollover).
+//!
+//! The tester is decoupled from the hook so it can be driven by
+//! [`listen_channel`](crate::channel::listen_channel), a recording, or
+//! synthetic events in tests.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::channel::listen_channel;
+//! use monio::rollover::RolloverTester;
+//! use std::time::Duration;
+//!
+//! let (handle, rx) = listen_channel(256).unwrap();
+//! let mut tester = RolloverTester::with_script(RolloverTester::default_script());
+//!
+//! while let Some(prompt) = tester.current_prompt().map(<[_]>::to_vec) {
+//!     println!("Hold: {:?}", prompt);
+//!     while tester.current_prompt() == Some(&prompt[..]) {
+//!         match rx.recv_timeout(Duration::from_secs(5)) {
+//!             Ok(event) => tester.process_event(&event),
+//!             Err(_) => tester.skip_prompt(),
+//!         }
+//!     }
+//! }
+//!
+//! println!("Max simultaneous keys: {}", tester.max_simultaneous_keys());
+//! println!("Ghosted: {:?}", tester.suspected_ghosted_combinations());
+//! handle.stop().unwrap();
+//! ```
+
+use crate::event::{Event, EventType};
+use crate::keycode::Key;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
+
+/// How long a key may stay held without autorepeat before it is suspected stuck.
+pub const DEFAULT_STUCK_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// A prompted combination that never registered in full.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GhostedCombination {
+    /// The keys the user was asked to hold together.
+    pub expected: Vec<Key>,
+    /// Keys from `expected` that were never seen held alongside the others.
+    pub missing: Vec<Key>,
+}
+
+/// Tracks key rollover and detects ghosting from a stream of events.
+#[derive(Debug, Clone)]
+pub struct RolloverTester {
+    /// Held keys, mapped to the time of their last press (or autorepeat).
+    held: HashMap<Key, SystemTime>,
+    max_simultaneous: usize,
+    max_combination: Vec<Key>,
+    stuck_threshold: Duration,
+
+    script: Vec<Vec<Key>>,
+    step: usize,
+    /// Largest subset of the current prompt seen held at once.
+    best_observed: HashSet<Key>,
+    ghosted: Vec<GhostedCombination>,
+}
+
+impl Default for RolloverTester {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RolloverTester {
+    /// Create a tester with no prompt script.
+    pub fn new() -> Self {
+        Self::with_script(Vec::new())
+    }
+
+    /// Create a tester that walks the user through a list of key combinations.
+    pub fn with_script(script: Vec<Vec<Key>>) -> Self {
+        Self {
+            held: HashMap::new(),
+            max_simultaneous: 0,
+            max_combination: Vec::new(),
+            stuck_threshold: DEFAULT_STUCK_THRESHOLD,
+            script,
+            step: 0,
+            best_observed: HashSet::new(),
+            ghosted: Vec::new(),
+        }
+    }
+
+    /// Set how long a key may be held without autorepeat before it is reported stuck.
+    pub fn with_stuck_threshold(mut self, threshold: Duration) -> Self {
+        self.stuck_threshold = threshold;
+        self
+    }
+
+    /// A script of combinations that commonly expose ghosting on 2KRO/6KRO boards.
+    pub fn default_script() -> Vec<Vec<Key>> {
+        vec![
+            vec![Key::KeyW, Key::KeyA, Key::KeyS, Key::KeyD],
+            vec![Key::ShiftLeft, Key::KeyW, Key::KeyA, Key::Space],
+            vec![
+                Key::KeyQ,
+                Key::KeyW,
+                Key::KeyE,
+                Key::KeyR,
+                Key::KeyT,
+                Key::KeyY,
+            ],
+            vec![
+                Key::KeyA,
+                Key::KeyS,
+                Key::KeyD,
+                Key::KeyF,
+                Key::KeyJ,
+                Key::KeyK,
+                Key::KeyL,
+            ],
+            vec![Key::ControlLeft, Key::ShiftLeft, Key::AltLeft, Key::KeyZ],
+        ]
+    }
+
+    /// Process an input event. Non-keyboard events are ignored.
+    pub fn process_event(&mut self, event: &Event) {
+        let Some(kb) = &event.keyboard else {
+            return;
+        };
+
+        match event.event_type {
+            EventType::KeyPressed => {
+                // Autorepeat re-sends KeyPressed; refresh the timestamp.
+                self.held.insert(kb.key, event.time);
+
+                if self.held.len() > self.max_simultaneous {
+                    self.max_simultaneous = self.held.len();
+                    self.max_combination = self.held.keys().copied().collect();
+                }
+
+                self.observe_prompt();
+            }
+            EventType::KeyReleased => {
+                self.held.remove(&kb.key);
+            }
+            _ => {}
+        }
+    }
+
+    fn observe_prompt(&mut self) {
+        let Some(prompt) = self.script.get(self.step) else {
+            return;
+        };
+
+        let observed: HashSet<Key> = prompt
+            .iter()
+            .filter(|key| self.held.contains_key(key))
+            .copied()
+            .collect();
+
+        if observed.len() == prompt.len() {
+            self.step += 1;
+            self.best_observed.clear();
+        } else if observed.len() > self.best_observed.len() {
+            self.best_observed = observed;
+        }
+    }
+
+    /// Maximum number of keys seen held at the same time.
+    pub fn max_simultaneous_keys(&self) -> usize {
+        self.max_simultaneous
+    }
+
+    /// The set of keys held when the maximum was reached.
+    pub fn max_combination(&self) -> &[Key] {
+        &self.max_combination
+    }
+
+    /// Keys currently considered held.
+    pub fn held_keys(&self) -> Vec<Key> {
+        self.held.keys().copied().collect()
+    }
+
+    /// Keys held longer than the stuck threshold (as of `now`) without autorepeat.
+    ///
+    /// These most likely had their release event lost.
+    pub fn suspected_stuck_keys(&self, now: SystemTime) -> Vec<Key> {
+        self.held
+            .iter()
+            .filter(|(_, pressed)| {
+                now.duration_since(**pressed)
+                    .is_ok_and(|held| held > self.stuck_threshold)
+            })
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    /// Forget held keys, e.g. after acknowledging stuck keys.
+    pub fn clear_held(&mut self) {
+        self.held.clear();
+    }
+
+    /// The combination the user should currently hold, if the script isn't finished.
+    pub fn current_prompt(&self) -> Option<&[Key]> {
+        self.script.get(self.step).map(Vec::as_slice)
+    }
+
+    /// Give up on the current prompt, recording it as ghosted.
+    pub fn skip_prompt(&mut self) {
+        let Some(prompt) = self.script.get(self.step) else {
+            return;
+        };
+
+        let missing = prompt
+            .iter()
+            .filter(|key| !self.best_observed.contains(key))
+            .copied()
+            .collect();
+        self.ghosted.push(GhostedCombination {
+            expected: prompt.clone(),
+            missing,
+        });
+
+        self.step += 1;
+        self.best_observed.clear();
+    }
+
+    /// Prompted combinations that never registered in full.
+    pub fn suspected_ghosted_combinations(&self) -> &[GhostedCombination] {
+        &self.ghosted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: Key) -> Event {
+        Event::key_pressed(key, 0)
+    }
+
+    fn release(key: Key) -> Event {
+        Event::key_released(key, 0)
+    }
+
+    #[test]
+    fn test_max_simultaneous() {
+        let mut tester = RolloverTester::new();
+        tester.process_event(&press(Key::KeyA));
+        tester.process_event(&press(Key::KeyS));
+        tester.process_event(&press(Key::KeyA)); // autorepeat
+        tester.process_event(&release(Key::KeyA));
+        tester.process_event(&press(Key::KeyD));
+        tester.process_event(&press(Key::KeyF));
+
+        assert_eq!(tester.max_simultaneous_keys(), 3);
+        assert_eq!(tester.held_keys().len(), 3);
+    }
+
+    #[test]
+    fn test_stuck_keys() {
+        let mut tester = RolloverTester::new().with_stuck_threshold(Duration::from_secs(1));
+        let mut event = press(Key::KeyA);
+        event.time = SystemTime::now() - Duration::from_secs(5);
+        tester.process_event(&event);
+        tester.process_event(&press(Key::KeyB));
+
+        assert_eq!(
+            tester.suspected_stuck_keys(SystemTime::now()),
+            vec![Key::KeyA]
+        );
+    }
+
+    #[test]
+    fn test_script_ghosting() {
+        let mut tester = RolloverTester::with_script(vec![
+            vec![Key::KeyA, Key::KeyB],
+            vec![Key::KeyQ, Key::KeyW, Key::KeyE],
+        ]);
+
+        tester.process_event(&press(Key::KeyA));
+        tester.process_event(&press(Key::KeyB));
+        assert_eq!(
+            tester.current_prompt(),
+            Some(&[Key::KeyQ, Key::KeyW, Key::KeyE][..])
+        );
+        tester.process_event(&release(Key::KeyA));
+        tester.process_event(&release(Key::KeyB));
+
+        // KeyE never registers while Q and W are held
+        tester.process_event(&press(Key::KeyQ));
+        tester.process_event(&press(Key::KeyW));
+        tester.skip_prompt();
+
+        assert!(tester.current_prompt().is_none());
+        assert_eq!(
+            tester.suspected_ghosted_combinations(),
+            &[GhostedCombination {
+                expected: vec![Key::KeyQ, Key::KeyW, Key::KeyE],
+                missing: vec![Key::KeyE],
+            }]
+        );
+    }
+}
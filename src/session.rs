@@ -0,0 +1,600 @@
+//! Capture sessions: statistics, recording and logging from one hook.
+//!
+//! A [`CaptureSession`] runs a single hook and feeds every event to the
+//! consumers it was built with. Consumers are isolated from each other: one
+//! that fails (or panics) is detached and its error reported in
+//! [`CaptureResult::errors`], while the others keep going.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::session::CaptureSession;
+//! use monio::EventType;
+//!
+//! let session = CaptureSession::builder()
+//!     .with_statistics()
+//!     .with_recording()
+//!     .with_jsonl("events.jsonl")
+//!     .with_filter(|event: &monio::Event| event.event_type != EventType::MouseMoved)
+//!     .start()
+//!     .unwrap();
+//!
+//! std::thread::sleep(std::time::Duration::from_secs(10));
+//!
+//! let result = session.stop().unwrap();
+//! if let Some(stats) = &result.statistics {
+//!     println!("{}", stats.summary());
+//! }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::event::Event;
+use crate::filter::EventFilter;
+use crate::hook::Hook;
+use crate::shutdown::{Registration, ShutdownToken};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "recorder")]
+use crate::event::EventType;
+#[cfg(feature = "recorder")]
+use crate::recorder::{Recording, RecordingMetadata, Timeline};
+#[cfg(feature = "statistics")]
+use crate::statistics::EventStatistics;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::fs::File;
+#[cfg(feature = "serde")]
+use std::io::{BufWriter, Write};
+#[cfg(feature = "serde")]
+use std::path::PathBuf;
+#[cfg(feature = "statistics")]
+use std::time::Instant;
+
+type Filter = Box<dyn Fn(&Event) -> bool + Send + Sync>;
+
+/// Something fed with the session's events.
+trait Consumer: Send {
+    /// Name used in error messages.
+    fn name(&self) -> &'static str;
+
+    fn consume(&mut self, event: &Event) -> Result<()>;
+
+    /// Contribute to the result when the session stops.
+    fn finish(self: Box<Self>, result: &mut CaptureResult) -> Result<()>;
+}
+
+#[cfg(feature = "statistics")]
+struct StatisticsConsumer(EventStatistics);
+
+#[cfg(feature = "statistics")]
+impl Consumer for StatisticsConsumer {
+    fn name(&self) -> &'static str {
+        "statistics"
+    }
+
+    fn consume(&mut self, event: &Event) -> Result<()> {
+        self.0.record_event(event);
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>, result: &mut CaptureResult) -> Result<()> {
+        self.0.end_time = Some(Instant::now());
+        result.statistics = Some(self.0);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "recorder")]
+struct RecordingConsumer {
+    recording: Recording,
+    timeline: Timeline,
+}
+
+#[cfg(feature = "recorder")]
+impl Consumer for RecordingConsumer {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn consume(&mut self, event: &Event) -> Result<()> {
+        // Skip hook lifecycle events in recording
+        if !matches!(
+            event.event_type,
+            EventType::HookEnabled
+                | EventType::HookDisabled
+                | EventType::GrabSuspended
+                | EventType::GrabDegraded
+                | EventType::GlobalSuspendChanged
+        ) {
+            let recorded = self.timeline.record(event);
+            self.recording.events.push(recorded);
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>, result: &mut CaptureResult) -> Result<()> {
+        result.recording = Some(self.recording);
+        Ok(())
+    }
+}
+
+/// Writes one JSON event per line.
+/// The first line of an event log, see
+/// [`CaptureSessionBuilder::with_jsonl`]. Every line after it is an
+/// [`Event`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogHeader {
+    /// Format of the events, see [`FORMAT_VERSION`](crate::FORMAT_VERSION).
+    pub format_version: u32,
+    /// monio version that wrote the log.
+    pub monio_version: String,
+}
+
+#[cfg(feature = "serde")]
+impl LogHeader {
+    pub(crate) fn current() -> Self {
+        Self {
+            format_version: crate::event::FORMAT_VERSION,
+            monio_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+struct JsonlConsumer {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+#[cfg(feature = "serde")]
+impl Consumer for JsonlConsumer {
+    fn name(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn consume(&mut self, event: &Event) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, event)
+            .map_err(|e| Error::Other(format!("Failed to write event log: {}", e)))?;
+        self.writer
+            .write_all(b"\n")
+            .map_err(|e| Error::Other(format!("Failed to write event log: {}", e)))
+    }
+
+    fn finish(mut self: Box<Self>, result: &mut CaptureResult) -> Result<()> {
+        result.log_path = Some(self.path.clone());
+        self.writer
+            .flush()
+            .map_err(|e| Error::Other(format!("Failed to flush event log: {}", e)))
+    }
+}
+
+/// What a stopped [`CaptureSession`] collected.
+#[derive(Debug, Default)]
+pub struct CaptureResult {
+    /// Statistics, if the session was built with them.
+    #[cfg(feature = "statistics")]
+    pub statistics: Option<crate::statistics::EventStatistics>,
+    /// The recording, if the session was built with one.
+    #[cfg(feature = "recorder")]
+    pub recording: Option<crate::recorder::Recording>,
+    /// Where events were logged, if the session was built with a log.
+    #[cfg(feature = "serde")]
+    pub log_path: Option<std::path::PathBuf>,
+    /// Errors from consumers that failed along the way.
+    ///
+    /// A failed consumer stops receiving events, but still contributes what
+    /// it collected up to that point.
+    pub errors: Vec<Error>,
+}
+
+struct Slot {
+    consumer: Box<dyn Consumer>,
+    failed: bool,
+}
+
+/// State shared between the session handle and the hook thread.
+struct SessionCore {
+    filter: Option<Filter>,
+    paused: AtomicBool,
+    slots: Mutex<Vec<Slot>>,
+    errors: Mutex<Vec<Error>>,
+}
+
+impl SessionCore {
+    fn handle(&self, event: &Event) {
+        if self.paused.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Some(filter) = &self.filter
+            && !filter(event)
+        {
+            return;
+        }
+        let Ok(mut slots) = self.slots.lock() else {
+            return;
+        };
+
+        for slot in slots.iter_mut().filter(|slot| !slot.failed) {
+            let error = match catch_unwind(AssertUnwindSafe(|| slot.consumer.consume(event))) {
+                Ok(Ok(())) => continue,
+                Ok(Err(e)) => e,
+                Err(_) => Error::Other(format!("{} consumer panicked", slot.consumer.name())),
+            };
+            log::warn!(
+                "Capture session: detaching {} consumer: {}",
+                slot.consumer.name(),
+                error
+            );
+            slot.failed = true;
+            if let Ok(mut errors) = self.errors.lock() {
+                errors.push(error);
+            }
+        }
+    }
+
+    fn finish(&self) -> Result<CaptureResult> {
+        let slots = std::mem::take(
+            &mut *self
+                .slots
+                .lock()
+                .map_err(|_| Error::ThreadError("mutex poisoned".into()))?,
+        );
+        let mut result = CaptureResult {
+            errors: std::mem::take(
+                &mut *self
+                    .errors
+                    .lock()
+                    .map_err(|_| Error::ThreadError("mutex poisoned".into()))?,
+            ),
+            ..CaptureResult::default()
+        };
+
+        for slot in slots {
+            let name = slot.consumer.name();
+            match catch_unwind(AssertUnwindSafe(|| slot.consumer.finish(&mut result))) {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => result.errors.push(e),
+                Err(_) => result
+                    .errors
+                    .push(Error::Other(format!("{} consumer panicked", name))),
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Builder for [`CaptureSession`].
+#[derive(Default)]
+pub struct CaptureSessionBuilder {
+    filter: Option<Filter>,
+    #[cfg(feature = "statistics")]
+    statistics: bool,
+    #[cfg(feature = "recorder")]
+    recording: bool,
+    #[cfg(feature = "serde")]
+    jsonl: Option<PathBuf>,
+    shutdown: Option<ShutdownToken>,
+    #[cfg(test)]
+    consumers: Vec<Box<dyn Consumer>>,
+}
+
+impl CaptureSessionBuilder {
+    /// Collect [`EventStatistics`](crate::statistics::EventStatistics).
+    #[cfg(feature = "statistics")]
+    pub fn with_statistics(mut self) -> Self {
+        self.statistics = true;
+        self
+    }
+
+    /// Record a [`Recording`](crate::recorder::Recording).
+    #[cfg(feature = "recorder")]
+    pub fn with_recording(mut self) -> Self {
+        self.recording = true;
+        self
+    }
+
+    /// Log every event as a line of JSON to `path`, replacing the file.
+    ///
+    /// The first line is a [`LogHeader`] naming the format of the events.
+    #[cfg(feature = "serde")]
+    pub fn with_jsonl(mut self, path: impl Into<PathBuf>) -> Self {
+        self.jsonl = Some(path.into());
+        self
+    }
+
+    /// Only pass on events for which `filter` returns true.
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Event) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Only pass on events matching the filter expression `source`, see
+    /// [`filter`](crate::filter).
+    ///
+    /// Fails with [`Error::InvalidFilter`] if `source` doesn't parse.
+    pub fn with_filter_expr(self, source: &str) -> Result<Self> {
+        let filter = EventFilter::parse(source)?;
+        Ok(self.with_filter(move |event: &Event| filter.matches(event)))
+    }
+
+    /// Stop the session's hook when `token` is cancelled.
+    ///
+    /// The consumers keep what they have; [`CaptureSession::stop`] still
+    /// collects the results.
+    pub fn with_shutdown_token(mut self, token: &ShutdownToken) -> Self {
+        self.shutdown = Some(token.clone());
+        self
+    }
+
+    #[cfg(test)]
+    fn with_consumer(mut self, consumer: impl Consumer + 'static) -> Self {
+        self.consumers.push(Box::new(consumer));
+        self
+    }
+
+    /// Set up the consumers; fails if one can't be created.
+    fn build(&mut self) -> Result<SessionCore> {
+        #[allow(unused_mut)]
+        let mut consumers: Vec<Box<dyn Consumer>> = Vec::new();
+
+        #[cfg(feature = "statistics")]
+        if self.statistics {
+            let mut stats = EventStatistics::new();
+            stats.start_time = Some(Instant::now());
+            consumers.push(Box::new(StatisticsConsumer(stats)));
+        }
+        #[cfg(feature = "recorder")]
+        if self.recording {
+            consumers.push(Box::new(RecordingConsumer {
+                recording: Recording {
+                    metadata: RecordingMetadata::capture(),
+                    ..Recording::new()
+                },
+                timeline: Timeline::default(),
+            }));
+        }
+        #[cfg(feature = "serde")]
+        if let Some(path) = self.jsonl.take() {
+            let file = File::create(&path).map_err(|e| {
+                Error::Other(format!(
+                    "Failed to create event log {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            let mut writer = BufWriter::new(file);
+            serde_json::to_writer(&mut writer, &LogHeader::current())
+                .map_err(|e| Error::Other(format!("Failed to write event log: {}", e)))?;
+            writer
+                .write_all(b"\n")
+                .map_err(|e| Error::Other(format!("Failed to write event log: {}", e)))?;
+            consumers.push(Box::new(JsonlConsumer { path, writer }));
+        }
+        #[cfg(test)]
+        consumers.extend(std::mem::take(&mut self.consumers));
+
+        Ok(SessionCore {
+            filter: self.filter.take(),
+            paused: AtomicBool::new(false),
+            slots: Mutex::new(
+                consumers
+                    .into_iter()
+                    .map(|consumer| Slot {
+                        consumer,
+                        failed: false,
+                    })
+                    .collect(),
+            ),
+            errors: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Start the session's hook in the background.
+    pub fn start(mut self) -> Result<CaptureSession> {
+        let core = Arc::new(self.build()?);
+        let hook = Hook::new();
+        let handler_core = core.clone();
+        hook.run_async(move |event: &Event| handler_core.handle(event))?;
+        let shutdown = self.shutdown.map(|token| {
+            let registration = hook.stop_on(&token);
+            (token, registration)
+        });
+        Ok(CaptureSession {
+            hook,
+            core,
+            shutdown,
+        })
+    }
+}
+
+/// A running capture session; see the [module docs](self).
+pub struct CaptureSession {
+    hook: Hook,
+    core: Arc<SessionCore>,
+    shutdown: Option<(ShutdownToken, Registration)>,
+}
+
+impl CaptureSession {
+    /// Start building a session.
+    pub fn builder() -> CaptureSessionBuilder {
+        CaptureSessionBuilder::default()
+    }
+
+    /// Stop passing events to the consumers until [`resume`](Self::resume).
+    pub fn pause(&self) {
+        self.core.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resume passing events to the consumers.
+    pub fn resume(&self) {
+        self.core.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether the session is paused.
+    pub fn is_paused(&self) -> bool {
+        self.core.paused.load(Ordering::SeqCst)
+    }
+
+    /// Stop the hook and collect the consumers' results.
+    pub fn stop(self) -> Result<CaptureResult> {
+        let stopped = self.hook.stop();
+        let mut result = self.core.finish()?;
+        let cancelled = self
+            .shutdown
+            .as_ref()
+            .is_some_and(|(token, _)| token.is_cancelled());
+        match stopped {
+            Ok(()) => {}
+            // Already stopped by the shutdown token
+            Err(Error::NotRunning) if cancelled => {}
+            Err(e) => result.errors.push(e),
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycode::Key;
+
+    /// Fails on the given key: with an error, or by panicking.
+    struct Flaky {
+        key: Key,
+        panic: bool,
+        seen: Arc<Mutex<usize>>,
+    }
+
+    impl Consumer for Flaky {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn consume(&mut self, event: &Event) -> Result<()> {
+            if event.keyboard.as_ref().is_some_and(|kb| kb.key == self.key) {
+                if self.panic {
+                    panic!("flaky consumer");
+                }
+                return Err(Error::Other("flaky consumer".into()));
+            }
+            *self.seen.lock().unwrap() += 1;
+            Ok(())
+        }
+
+        fn finish(self: Box<Self>, _result: &mut CaptureResult) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failing_consumer_is_detached() {
+        let erroring = Arc::new(Mutex::new(0));
+        let panicking = Arc::new(Mutex::new(0));
+        let core = CaptureSession::builder()
+            .with_consumer(Flaky {
+                key: Key::KeyB,
+                panic: false,
+                seen: erroring.clone(),
+            })
+            .with_consumer(Flaky {
+                key: Key::KeyC,
+                panic: true,
+                seen: panicking.clone(),
+            })
+            .build()
+            .unwrap();
+
+        for key in [Key::KeyA, Key::KeyB, Key::KeyC, Key::KeyD] {
+            core.handle(&Event::key_pressed(key, 0));
+        }
+
+        // Each stopped at its failure; the other carried on until its own
+        assert_eq!(*erroring.lock().unwrap(), 1);
+        assert_eq!(*panicking.lock().unwrap(), 2);
+        assert_eq!(core.finish().unwrap().errors.len(), 2);
+    }
+
+    #[test]
+    fn test_filter_and_pause() {
+        let seen = Arc::new(Mutex::new(0));
+        let core = CaptureSession::builder()
+            .with_filter(|event: &Event| event.keyboard.is_some())
+            .with_consumer(Flaky {
+                key: Key::Escape,
+                panic: false,
+                seen: seen.clone(),
+            })
+            .build()
+            .unwrap();
+
+        core.handle(&Event::key_pressed(Key::KeyA, 0));
+        core.handle(&Event::mouse_moved(1.0, 1.0));
+        core.paused.store(true, Ordering::SeqCst);
+        core.handle(&Event::key_pressed(Key::KeyA, 0));
+        assert_eq!(*seen.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_filter_expression() {
+        let seen = Arc::new(Mutex::new(0));
+        let core = CaptureSession::builder()
+            .with_filter_expr("keyboard && key != KeyB")
+            .unwrap()
+            .with_consumer(Flaky {
+                key: Key::Escape,
+                panic: false,
+                seen: seen.clone(),
+            })
+            .build()
+            .unwrap();
+
+        core.handle(&Event::key_pressed(Key::KeyA, 0));
+        core.handle(&Event::key_pressed(Key::KeyB, 0));
+        core.handle(&Event::mouse_moved(1.0, 1.0));
+        assert_eq!(*seen.lock().unwrap(), 1);
+
+        let invalid = CaptureSession::builder().with_filter_expr("keyboard &&");
+        assert!(matches!(invalid, Err(Error::InvalidFilter(_))));
+    }
+
+    #[cfg(all(feature = "statistics", feature = "recorder", feature = "serde"))]
+    #[test]
+    fn test_all_consumers_share_events() {
+        let path = std::env::temp_dir().join("monio_test_session.jsonl");
+        let core = CaptureSession::builder()
+            .with_statistics()
+            .with_recording()
+            .with_jsonl(&path)
+            .build()
+            .unwrap();
+
+        core.handle(&Event::hook_enabled());
+        core.handle(&Event::key_pressed(Key::KeyA, 30));
+        core.handle(&Event::key_released(Key::KeyA, 30));
+        let result = core.finish().unwrap();
+
+        assert!(result.errors.is_empty());
+        assert_eq!(result.statistics.unwrap().key_press_count, 1);
+        // Lifecycle events aren't recorded
+        assert_eq!(result.recording.unwrap().event_count(), 2);
+
+        let log = std::fs::read_to_string(result.log_path.as_ref().unwrap()).unwrap();
+        let mut lines = log.lines();
+        let header: LogHeader = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(header.format_version, crate::FORMAT_VERSION);
+        let logged: Vec<Event> = lines
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(logged.len(), 3);
+        assert_eq!(logged[1].keyboard.as_ref().unwrap().key, Key::KeyA);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+//! Stopping hooks from a shared shutdown signal.
+//!
+//! A [`ShutdownToken`] is a cheap, cloneable flag: hand clones to the parts
+//! of a service that run hooks, and [`cancel`](ShutdownToken::cancel) it once
+//! to stop them all, the same way [`Hook::stop`](crate::Hook::stop) would.
+//!
+//! - [`Hook::run_with_token`](crate::Hook::run_with_token) and
+//!   [`Hook::grab_with_token`](crate::Hook::grab_with_token) block until the
+//!   token is cancelled (or the hook stops otherwise).
+//! - [`ChannelHookHandle::stop_on`](crate::channel::ChannelHookHandle::stop_on)
+//!   stops a channel hook, closing its channel.
+//! - `CaptureSessionBuilder::with_shutdown_token` stops a capture session's
+//!   hook; `stop()` still collects the results.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::{Event, Hook, ShutdownToken};
+//!
+//! let token = ShutdownToken::new();
+//! let worker = {
+//!     let token = token.clone();
+//!     std::thread::spawn(move || {
+//!         Hook::new().run_with_token(|event: &Event| println!("{:?}", event.event_type), &token)
+//!     })
+//! };
+//!
+//! // ... on shutdown:
+//! token.cancel();
+//! worker.join().unwrap().unwrap();
+//! ```
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    callbacks: Mutex<Vec<(u64, Callback)>>,
+    next_id: AtomicU64,
+}
+
+/// A cloneable signal that stops the hooks watching it.
+///
+/// All clones share one state: cancelling any of them cancels all. A token
+/// can't be reset; use a new one for the next run.
+#[derive(Clone, Default)]
+pub struct ShutdownToken {
+    inner: Arc<Inner>,
+}
+
+impl ShutdownToken {
+    /// Create a token that isn't cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop every hook watching this token.
+    ///
+    /// Can be called from any thread, including a hook's handler, and more
+    /// than once.
+    pub fn cancel(&self) {
+        if self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let callbacks = match self.inner.callbacks.lock() {
+            Ok(mut callbacks) => std::mem::take(&mut *callbacks),
+            Err(_) => return,
+        };
+        for (_, callback) in callbacks {
+            callback();
+        }
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Call `callback` when the token is cancelled, or now if it already is.
+    ///
+    /// Dropping the returned registration forgets the callback.
+    pub(crate) fn on_cancel(&self, callback: impl FnOnce() + Send + 'static) -> Registration {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut callbacks) = self.inner.callbacks.lock() {
+            // Checked under the lock, so `cancel` either sees the callback
+            // or has already set the flag
+            if !self.is_cancelled() {
+                callbacks.push((id, Box::new(callback)));
+                return Registration {
+                    id,
+                    inner: Arc::downgrade(&self.inner),
+                };
+            }
+        }
+        callback();
+        Registration {
+            id,
+            inner: Weak::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ShutdownToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// A callback registered with [`ShutdownToken::on_cancel`]; dropping it
+/// unregisters the callback.
+pub(crate) struct Registration {
+    id: u64,
+    inner: Weak<Inner>,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.upgrade()
+            && let Ok(mut callbacks) = inner.callbacks.lock()
+        {
+            callbacks.retain(|(id, _)| *id != self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_runs_registered_callbacks_once() {
+        let token = ShutdownToken::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let counter = count.clone();
+        let _kept = token.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let counter = count.clone();
+        drop(token.on_cancel(move || {
+            counter.fetch_add(10, Ordering::SeqCst);
+        }));
+
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        clone.cancel();
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // Registering after cancelling runs the callback right away
+        let counter = count.clone();
+        let _late = token.on_cancel(move || {
+            counter.fetch_add(100, Ordering::SeqCst);
+        });
+        assert_eq!(count.load(Ordering::SeqCst), 101);
+    }
+}
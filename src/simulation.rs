@@ -0,0 +1,421 @@
+//! Rate-limited event simulation.
+//!
+//! Every simulation function exported at the crate root goes through a
+//! global token-bucket limiter. Injecting faster than the OS can take events
+//! gives nondeterministic results (Windows silently drops `SendInput` calls,
+//! macOS coalesces events), so bursts beyond the configured rate are smoothed
+//! out by sleeping instead of failing.
+//!
+//! Simulation can be turned off for the whole process, see
+//! [`policy`](crate::policy).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::simulation::{SimulationConfig, set_simulation_config, simulation_stats};
+//! use monio::{Event, Key, simulate_batch};
+//!
+//! set_simulation_config(SimulationConfig::default().with_max_events_per_second(500));
+//!
+//! let events: Vec<Event> = (0..100)
+//!     .flat_map(|_| [Event::key_pressed(Key::KeyA, 0), Event::key_released(Key::KeyA, 0)])
+//!     .collect();
+//! simulate_batch(&events).unwrap();
+//!
+//! println!("{} events were delayed", simulation_stats().delayed_events);
+//! ```
+
+use crate::error::Result;
+use crate::event::{Button, Event};
+use crate::keycode::Key;
+use crate::platform;
+use crate::policy;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[cfg(all(target_os = "macos", feature = "macos-targeted"))]
+pub use crate::platform::{AppInfo, AppTarget, running_apps};
+#[cfg(all(target_os = "windows", feature = "windows-targeted"))]
+pub use crate::platform::{WindowHandle, WindowTarget, find_window};
+
+/// Default for [`SimulationConfig::max_events_per_second`].
+pub const DEFAULT_MAX_EVENTS_PER_SECOND: u32 = 2000;
+
+/// Default for [`SimulationConfig::burst`].
+pub const DEFAULT_BURST: u32 = 50;
+
+/// Simulation rate limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulationConfig {
+    /// Sustained rate, or `None` to simulate as fast as possible.
+    pub max_events_per_second: Option<u32>,
+    /// How many events may be simulated back to back before the rate applies.
+    pub burst: u32,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            max_events_per_second: Some(DEFAULT_MAX_EVENTS_PER_SECOND),
+            burst: DEFAULT_BURST,
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// Don't limit the simulation rate.
+    pub fn unlimited() -> Self {
+        Self {
+            max_events_per_second: None,
+            ..Self::default()
+        }
+    }
+
+    /// Set the sustained rate (at least 1).
+    pub fn with_max_events_per_second(mut self, rate: u32) -> Self {
+        self.max_events_per_second = Some(rate.max(1));
+        self
+    }
+
+    /// Set the burst size (at least 1).
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+/// Rate limiter diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulationStats {
+    /// Events simulated through the limiter.
+    pub simulated_events: u64,
+    /// Events that had to wait for the limiter.
+    pub delayed_events: u64,
+    /// Total time spent waiting.
+    pub total_delay: Duration,
+}
+
+/// Token bucket; tokens may go negative to reserve future slots.
+#[derive(Debug)]
+struct RateLimiter {
+    config: SimulationConfig,
+    tokens: f64,
+    last: Option<Instant>,
+    stats: SimulationStats,
+}
+
+impl RateLimiter {
+    const fn new() -> Self {
+        Self {
+            config: SimulationConfig {
+                max_events_per_second: Some(DEFAULT_MAX_EVENTS_PER_SECOND),
+                burst: DEFAULT_BURST,
+            },
+            tokens: DEFAULT_BURST as f64,
+            last: None,
+            stats: SimulationStats {
+                simulated_events: 0,
+                delayed_events: 0,
+                total_delay: Duration::ZERO,
+            },
+        }
+    }
+
+    fn configure(&mut self, config: SimulationConfig) {
+        self.config = config;
+        self.tokens = config.burst as f64;
+        self.last = None;
+    }
+
+    /// Reserve `count` events at `now`, returning how long to wait first.
+    fn reserve(&mut self, count: u32, now: Instant) -> Duration {
+        self.stats.simulated_events += u64::from(count);
+        let Some(rate) = self.config.max_events_per_second else {
+            return Duration::ZERO;
+        };
+        let rate = f64::from(rate.max(1));
+        let burst = f64::from(self.config.burst.max(1));
+
+        if let Some(last) = self.last {
+            let refill = now.saturating_duration_since(last).as_secs_f64() * rate;
+            self.tokens = (self.tokens + refill).min(burst);
+        }
+        self.last = Some(now);
+        self.tokens -= f64::from(count);
+
+        if self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        let wait = Duration::from_secs_f64(-self.tokens / rate);
+        self.stats.delayed_events += u64::from(count);
+        self.stats.total_delay += wait;
+        wait
+    }
+}
+
+static LIMITER: Mutex<RateLimiter> = Mutex::new(RateLimiter::new());
+
+/// Source of time for the limiter, replaced by a fake clock in tests.
+trait Clock {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+}
+
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Wait until `count` more events may be simulated.
+fn throttle_with(limiter: &Mutex<RateLimiter>, clock: &impl Clock, count: u32) {
+    // Reserve under the lock, wait outside it
+    let wait = match limiter.lock() {
+        Ok(mut limiter) => limiter.reserve(count, clock.now()),
+        Err(_) => return,
+    };
+    if !wait.is_zero() {
+        clock.sleep(wait);
+    }
+}
+
+fn throttle(count: u32) {
+    throttle_with(&LIMITER, &SystemClock, count);
+}
+
+/// Replace the global simulation rate limit.
+pub fn set_simulation_config(config: SimulationConfig) {
+    if let Ok(mut limiter) = LIMITER.lock() {
+        limiter.configure(config);
+    }
+}
+
+/// The current simulation rate limit.
+pub fn simulation_config() -> SimulationConfig {
+    LIMITER
+        .lock()
+        .map(|limiter| limiter.config)
+        .unwrap_or_default()
+}
+
+/// Rate limiter diagnostics since the process started.
+pub fn simulation_stats() -> SimulationStats {
+    LIMITER
+        .lock()
+        .map(|limiter| limiter.stats)
+        .unwrap_or_default()
+}
+
+/// Simulate an event.
+pub fn simulate(event: &Event) -> Result<()> {
+    policy::check_simulation()?;
+    throttle(1);
+    platform::simulate(event)
+}
+
+/// Simulate events in order.
+///
+/// Events are throttled in chunks of the configured burst size, so a long
+/// batch runs at the configured rate without a lock round-trip per event.
+pub fn simulate_batch(events: &[Event]) -> Result<()> {
+    policy::check_simulation()?;
+    let chunk = simulation_config().burst.max(1) as usize;
+    for events in events.chunks(chunk) {
+        throttle(events.len() as u32);
+        for event in events {
+            platform::simulate(event)?;
+        }
+    }
+    Ok(())
+}
+
+/// Press a key.
+pub fn key_press(key: Key) -> Result<()> {
+    policy::check_simulation()?;
+    throttle(1);
+    platform::key_press(key)
+}
+
+/// Release a key.
+pub fn key_release(key: Key) -> Result<()> {
+    policy::check_simulation()?;
+    throttle(1);
+    platform::key_release(key)
+}
+
+/// Press and release a key.
+pub fn key_tap(key: Key) -> Result<()> {
+    policy::check_simulation()?;
+    throttle(2);
+    platform::key_tap(key)
+}
+
+/// Press a mouse button.
+pub fn mouse_press(button: Button) -> Result<()> {
+    policy::check_simulation()?;
+    throttle(1);
+    platform::mouse_press(button)
+}
+
+/// Release a mouse button.
+pub fn mouse_release(button: Button) -> Result<()> {
+    policy::check_simulation()?;
+    throttle(1);
+    platform::mouse_release(button)
+}
+
+/// Click a mouse button.
+pub fn mouse_click(button: Button) -> Result<()> {
+    policy::check_simulation()?;
+    throttle(2);
+    platform::mouse_click(button)
+}
+
+/// Move the mouse to an absolute position.
+pub fn mouse_move(x: f64, y: f64) -> Result<()> {
+    policy::check_simulation()?;
+    throttle(1);
+    platform::mouse_move(x, y)
+}
+
+/// Post an event to a specific window instead of the focused one.
+///
+/// Works with minimized and background windows, at lower fidelity than
+/// [`simulate`]; see the limitations on [`WindowTarget`]. Not rate limited,
+/// since posted messages are queued per window rather than dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use monio::simulation::{find_window, send_to_window};
+/// use monio::{Event, Key};
+///
+/// for window in find_window("Notepad") {
+///     send_to_window(&window, &Event::key_pressed(Key::KeyA, 0)).unwrap();
+///     send_to_window(&window, &Event::key_released(Key::KeyA, 0)).unwrap();
+/// }
+/// ```
+#[cfg(all(target_os = "windows", feature = "windows-targeted"))]
+pub fn send_to_window(target: impl Into<WindowTarget>, event: &Event) -> Result<()> {
+    policy::check_simulation()?;
+    platform::send_to_window(target.into(), event)
+}
+
+/// Post an event to a specific app instead of the frontmost one.
+///
+/// Works while the app is in the background; which events it acts on is
+/// described on [`AppTarget`]. Needs Accessibility permissions, like
+/// [`simulate`]. Not rate limited.
+///
+/// # Example
+///
+/// ```no_run
+/// use monio::simulation::{AppTarget, send_to_app};
+/// use monio::{Event, Key};
+///
+/// let editor = AppTarget::BundleId("com.apple.TextEdit".into());
+/// send_to_app(editor.clone(), &Event::key_pressed(Key::KeyA, 0)).unwrap();
+/// send_to_app(editor, &Event::key_released(Key::KeyA, 0)).unwrap();
+/// ```
+#[cfg(all(target_os = "macos", feature = "macos-targeted"))]
+pub fn send_to_app(target: impl Into<AppTarget>, event: &Event) -> Result<()> {
+    policy::check_simulation()?;
+    platform::send_to_app(&target.into(), event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Clock that only advances when slept on.
+    struct FakeClock {
+        now: Cell<Instant>,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.now.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.now.set(self.now.get() + duration);
+        }
+    }
+
+    /// Whole milliseconds, ignoring float rounding in the limiter.
+    fn ms(duration: Duration) -> u64 {
+        (duration.as_secs_f64() * 1000.0).round() as u64
+    }
+
+    fn limiter(config: SimulationConfig) -> Mutex<RateLimiter> {
+        let mut limiter = RateLimiter::new();
+        limiter.configure(config);
+        Mutex::new(limiter)
+    }
+
+    #[test]
+    fn test_limiter_spaces_calls() {
+        let start = Instant::now();
+        let clock = FakeClock {
+            now: Cell::new(start),
+        };
+        let limiter = limiter(
+            SimulationConfig::default()
+                .with_max_events_per_second(100)
+                .with_burst(2),
+        );
+
+        let mut times = Vec::new();
+        for _ in 0..5 {
+            throttle_with(&limiter, &clock, 1);
+            times.push(ms(clock.now().duration_since(start)));
+        }
+
+        // The burst goes straight through, then one event every 10ms
+        assert_eq!(times, vec![0, 0, 10, 20, 30]);
+        let stats = limiter.lock().unwrap().stats;
+        assert_eq!(stats.simulated_events, 5);
+        assert_eq!(stats.delayed_events, 3);
+        assert_eq!(ms(stats.total_delay), 30);
+
+        // Idle time refills the bucket, up to the burst size
+        clock.sleep(Duration::from_secs(1));
+        let resumed = clock.now();
+        for _ in 0..3 {
+            throttle_with(&limiter, &clock, 1);
+        }
+        assert_eq!(ms(clock.now().duration_since(resumed)), 10);
+    }
+
+    #[test]
+    fn test_limiter_chunks_and_opt_out() {
+        let start = Instant::now();
+        let clock = FakeClock {
+            now: Cell::new(start),
+        };
+        let limited = limiter(
+            SimulationConfig::default()
+                .with_max_events_per_second(1000)
+                .with_burst(10),
+        );
+        for _ in 0..3 {
+            throttle_with(&limited, &clock, 10);
+        }
+        assert_eq!(ms(clock.now().duration_since(start)), 20);
+
+        let unlimited = limiter(SimulationConfig::unlimited());
+        let before = clock.now();
+        for _ in 0..100 {
+            throttle_with(&unlimited, &clock, 10);
+        }
+        assert_eq!(clock.now(), before);
+        assert_eq!(unlimited.lock().unwrap().stats.delayed_events, 0);
+    }
+}
@@ -0,0 +1,426 @@
+//! Snapshots of the whole input state.
+//!
+//! [`input_snapshot`] gathers the mouse position, held buttons, modifiers,
+//! lock keys and held keys in one [`InputSnapshot`]. Buttons, modifiers,
+//! keys and the position come from the events running hooks have seen, and
+//! are read together under a sequence lock, so they always describe the same
+//! instant. What isn't tracked is asked from the OS: the lock keys always,
+//! and the mouse position while no hook is running.
+//!
+//! Taking a snapshot doesn't block the hook thread and costs a few atomic
+//! loads plus the OS queries, so it is fine to call every frame.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::{Button, Hook, Key, input_snapshot};
+//!
+//! let hook = Hook::new();
+//! hook.run_async(|_: &monio::Event| {})?;
+//!
+//! let snapshot = input_snapshot();
+//! if snapshot.buttons.is_pressed(Button::Left) && snapshot.modifiers.shift {
+//!     println!("Shift-dragging at {:?}", snapshot.mouse_position);
+//! }
+//! if snapshot.lock_keys.caps_lock {
+//!     println!("Caps Lock is on");
+//! }
+//! println!("Held: {:?}", snapshot.pressed_keys);
+//! # Ok::<(), monio::Error>(())
+//! ```
+
+use crate::event::{Button, Event, EventType};
+use crate::keycode::Key;
+use crate::state::{
+    MASK_BUTTON1, MASK_BUTTON2, MASK_BUTTON3, MASK_BUTTON4, MASK_BUTTON5, Modifiers,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering, fence};
+use std::time::SystemTime;
+
+/// Held keys beyond this many are not tracked.
+const MAX_PRESSED_KEYS: usize = 32;
+
+/// Held mouse buttons.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ButtonStates {
+    /// [`Button::Left`] is held.
+    pub left: bool,
+    /// [`Button::Right`] is held.
+    pub right: bool,
+    /// [`Button::Middle`] is held.
+    pub middle: bool,
+    /// [`Button::Button4`] is held.
+    pub button4: bool,
+    /// [`Button::Button5`] is held.
+    pub button5: bool,
+}
+
+impl ButtonStates {
+    /// Decode the buttons held in a mask (e.g. `Event::mask`).
+    pub fn from_mask(mask: u32) -> Self {
+        Self {
+            left: mask & MASK_BUTTON1 != 0,
+            right: mask & MASK_BUTTON2 != 0,
+            middle: mask & MASK_BUTTON3 != 0,
+            button4: mask & MASK_BUTTON4 != 0,
+            button5: mask & MASK_BUTTON5 != 0,
+        }
+    }
+
+    /// Whether `button` is held.
+    pub fn is_pressed(&self, button: Button) -> bool {
+        match button {
+            Button::Left => self.left,
+            Button::Right => self.right,
+            Button::Middle => self.middle,
+            Button::Button4 => self.button4,
+            Button::Button5 => self.button5,
+            Button::Unknown(_) => false,
+        }
+    }
+
+    /// Whether any button is held.
+    pub fn any(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// Whether the lock keys are on.
+///
+/// Platforms without a key report it as off: macOS has no Num Lock or
+/// Scroll Lock, and X11 and evdev only know about keyboards with the
+/// corresponding LED.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LockStates {
+    /// Caps Lock is on.
+    pub caps_lock: bool,
+    /// Num Lock is on.
+    pub num_lock: bool,
+    /// Scroll Lock is on.
+    pub scroll_lock: bool,
+}
+
+/// The input state at one instant, see [`input_snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InputSnapshot {
+    /// Mouse position, `None` if it couldn't be determined.
+    pub mouse_position: Option<(f64, f64)>,
+    /// Held mouse buttons.
+    pub buttons: ButtonStates,
+    /// Held keyboard modifiers.
+    pub modifiers: Modifiers,
+    /// Lock key states, from the OS.
+    pub lock_keys: LockStates,
+    /// Held keys, in the order they were pressed. Only tracked while a hook
+    /// is running.
+    pub pressed_keys: Vec<Key>,
+    /// When the snapshot was taken.
+    pub timestamp: SystemTime,
+}
+
+impl InputSnapshot {
+    /// Whether `key` is held.
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+}
+
+/// The tracked part of a snapshot.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct TrackedState {
+    pub(crate) mouse_position: Option<(f64, f64)>,
+    pub(crate) mask: u32,
+    pub(crate) pressed_keys: Vec<Key>,
+}
+
+/// Keys seen so far, so held keys can be stored as atomic ids. Only grows,
+/// so an id always means the same key.
+static KEY_IDS: Mutex<Vec<Key>> = Mutex::new(Vec::new());
+
+/// Id of `key` (from 1), registering it if needed.
+fn key_id(key: Key) -> Option<u32> {
+    let mut keys = KEY_IDS.lock().ok()?;
+    let index = match keys.iter().position(|&k| k == key) {
+        Some(index) => index,
+        None => {
+            keys.push(key);
+            keys.len() - 1
+        }
+    };
+    Some(index as u32 + 1)
+}
+
+/// Input state updated from events, readable from any thread.
+///
+/// Updates take a lock among themselves; reads don't, and retry if an update
+/// happened while reading (a sequence lock).
+pub(crate) struct StateTracker {
+    /// Odd while an update is in progress.
+    sequence: AtomicU64,
+    writer: Mutex<()>,
+    has_position: AtomicBool,
+    mouse_x: AtomicU64,
+    mouse_y: AtomicU64,
+    mask: AtomicU32,
+    /// Ids of the held keys (see [`key_id`]), 0 for free slots.
+    keys: [AtomicU32; MAX_PRESSED_KEYS],
+}
+
+impl StateTracker {
+    pub(crate) const fn new() -> Self {
+        Self {
+            sequence: AtomicU64::new(0),
+            writer: Mutex::new(()),
+            has_position: AtomicBool::new(false),
+            mouse_x: AtomicU64::new(0),
+            mouse_y: AtomicU64::new(0),
+            mask: AtomicU32::new(0),
+            keys: [const { AtomicU32::new(0) }; MAX_PRESSED_KEYS],
+        }
+    }
+
+    /// Apply `f` as one update, invisible to readers until complete.
+    fn write(&self, f: impl FnOnce(&Self)) {
+        let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let sequence = self.sequence.load(Ordering::Relaxed);
+        self.sequence
+            .store(sequence.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        f(self);
+        self.sequence
+            .store(sequence.wrapping_add(2), Ordering::Release);
+    }
+
+    /// Update from an input event. Other events are ignored.
+    pub(crate) fn update(&self, event: &Event) {
+        if event.keyboard.is_none() && event.mouse.is_none() && event.wheel.is_none() {
+            return;
+        }
+        let key = event.keyboard.as_ref().and_then(|kb| {
+            let held = match event.event_type {
+                EventType::KeyPressed => true,
+                EventType::KeyReleased => false,
+                _ => return None,
+            };
+            Some((key_id(kb.key)?, held))
+        });
+
+        self.write(|state| {
+            state.mask.store(event.mask, Ordering::Relaxed);
+            if let Some(mouse) = &event.mouse {
+                state.mouse_x.store(mouse.x.to_bits(), Ordering::Relaxed);
+                state.mouse_y.store(mouse.y.to_bits(), Ordering::Relaxed);
+                state.has_position.store(true, Ordering::Relaxed);
+            }
+            match key {
+                Some((id, true)) => {
+                    // Only updates write, so plain loads see the latest ids
+                    let held = |slot: &AtomicU32| slot.load(Ordering::Relaxed);
+                    if !state.keys.iter().any(|slot| held(slot) == id)
+                        && let Some(free) = state.keys.iter().find(|slot| held(slot) == 0)
+                    {
+                        free.store(id, Ordering::Relaxed);
+                    }
+                }
+                Some((id, false)) => {
+                    // Keep the rest in press order
+                    let mut ids: Vec<u32> = state
+                        .keys
+                        .iter()
+                        .map(|slot| slot.load(Ordering::Relaxed))
+                        .filter(|&held| held != 0 && held != id)
+                        .collect();
+                    ids.resize(MAX_PRESSED_KEYS, 0);
+                    for (slot, held) in state.keys.iter().zip(ids) {
+                        slot.store(held, Ordering::Relaxed);
+                    }
+                }
+                None => {}
+            }
+        });
+    }
+
+    /// Forget everything, e.g. once no hook is left to keep the state
+    /// current.
+    pub(crate) fn clear(&self) {
+        self.write(|state| {
+            state.has_position.store(false, Ordering::Relaxed);
+            state.mask.store(0, Ordering::Relaxed);
+            for slot in &state.keys {
+                slot.store(0, Ordering::Relaxed);
+            }
+        });
+    }
+
+    /// The tracked state, as of one instant.
+    pub(crate) fn read(&self) -> TrackedState {
+        loop {
+            let before = self.sequence.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+            let has_position = self.has_position.load(Ordering::Relaxed);
+            let x = self.mouse_x.load(Ordering::Relaxed);
+            let y = self.mouse_y.load(Ordering::Relaxed);
+            let mask = self.mask.load(Ordering::Relaxed);
+            let ids: [u32; MAX_PRESSED_KEYS] =
+                std::array::from_fn(|i| self.keys[i].load(Ordering::Relaxed));
+            fence(Ordering::Acquire);
+            if self.sequence.load(Ordering::Relaxed) != before {
+                continue;
+            }
+
+            let pressed_keys = match KEY_IDS.lock() {
+                Ok(keys) => ids
+                    .iter()
+                    .take_while(|&&id| id != 0)
+                    .filter_map(|&id| keys.get(id as usize - 1).copied())
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            return TrackedState {
+                mouse_position: has_position.then(|| (f64::from_bits(x), f64::from_bits(y))),
+                mask,
+                pressed_keys,
+            };
+        }
+    }
+
+    /// A full snapshot: the tracked state, completed from the OS.
+    pub(crate) fn snapshot(&self) -> InputSnapshot {
+        let tracked = self.read();
+        InputSnapshot {
+            mouse_position: tracked
+                .mouse_position
+                .or_else(|| crate::platform::mouse_position().ok()),
+            buttons: ButtonStates::from_mask(tracked.mask),
+            modifiers: Modifiers::from_mask(tracked.mask),
+            lock_keys: crate::platform::lock_states(),
+            pressed_keys: tracked.pressed_keys,
+            timestamp: SystemTime::now(),
+        }
+    }
+}
+
+/// State fed by every running hook.
+static LIVE: StateTracker = StateTracker::new();
+
+/// Number of hooks currently enabled.
+static ACTIVE_HOOKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Feed an event dispatched by a hook into the live state.
+pub(crate) fn observe(event: &Event) {
+    match event.event_type {
+        EventType::HookEnabled => {
+            ACTIVE_HOOKS.fetch_add(1, Ordering::SeqCst);
+        }
+        EventType::HookDisabled => {
+            let previous = ACTIVE_HOOKS
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .unwrap_or(0);
+            if previous <= 1 {
+                LIVE.clear();
+            }
+        }
+        _ => LIVE.update(event),
+    }
+}
+
+/// The current input state.
+///
+/// Buttons, modifiers, held keys and the mouse position are tracked from
+/// the events of running hooks (of any kind), so they are only complete
+/// while a hook runs: without one, the mouse position is asked from the OS
+/// and no buttons, modifiers or keys are reported. Lock keys always come
+/// from the OS.
+pub fn input_snapshot() -> InputSnapshot {
+    LIVE.snapshot()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MASK_SHIFT;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_tracks_keys_in_press_order() {
+        let tracker = StateTracker::new();
+        for key in [Key::KeyA, Key::KeyB, Key::KeyC] {
+            tracker.update(&Event::key_pressed(key, 0));
+        }
+        // Auto-repeat doesn't add the key twice
+        tracker.update(&Event::key_pressed(Key::KeyA, 0));
+        tracker.update(&Event::key_released(Key::KeyB, 0));
+        tracker.update(&Event::key_pressed(Key::Char('§'), 0));
+
+        assert_eq!(
+            tracker.read().pressed_keys,
+            vec![Key::KeyA, Key::KeyC, Key::Char('§')]
+        );
+
+        tracker.clear();
+        assert_eq!(tracker.read(), TrackedState::default());
+    }
+
+    #[test]
+    fn test_buttons_modifiers_and_position() {
+        let tracker = StateTracker::new();
+        assert_eq!(tracker.read().mouse_position, None);
+
+        let mut press = Event::mouse_pressed(Button::Right, 10.0, 20.0);
+        press.mask = MASK_SHIFT | MASK_BUTTON2;
+        tracker.update(&press);
+        // Lifecycle events don't reset the mask
+        tracker.update(&Event::hook_enabled());
+
+        let state = tracker.read();
+        assert_eq!(state.mouse_position, Some((10.0, 20.0)));
+        let buttons = ButtonStates::from_mask(state.mask);
+        assert!(buttons.is_pressed(Button::Right));
+        assert!(!buttons.is_pressed(Button::Left));
+        assert!(buttons.any());
+        assert!(Modifiers::from_mask(state.mask).shift);
+    }
+
+    #[test]
+    fn test_too_many_keys_are_ignored() {
+        let tracker = StateTracker::new();
+        for code in 0..MAX_PRESSED_KEYS as u32 + 5 {
+            tracker.update(&Event::key_pressed(Key::Unknown(10_000 + code), code));
+        }
+        assert_eq!(tracker.read().pressed_keys.len(), MAX_PRESSED_KEYS);
+    }
+
+    #[test]
+    fn test_reads_are_consistent() {
+        // Every update sets the Shift bit and the x position together
+        let tracker = Arc::new(StateTracker::new());
+        let writer = {
+            let tracker = tracker.clone();
+            std::thread::spawn(move || {
+                for i in 0..20_000 {
+                    let shift = i % 2 == 0;
+                    let mut event = Event::mouse_moved(if shift { 1.0 } else { 0.0 }, 0.0);
+                    event.mask = if shift { MASK_SHIFT } else { 0 };
+                    tracker.update(&event);
+                }
+            })
+        };
+
+        while !writer.is_finished() {
+            let state = tracker.read();
+            if let Some((x, _)) = state.mouse_position {
+                assert_eq!(x == 1.0, state.mask & MASK_SHIFT != 0);
+            }
+        }
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! Where hooks get their input from: the OS, or a demo script while a
+//! [`synthetic_session`](crate::demo::synthetic_session) is active.
+//!
+//! With the `hid` feature, running hooks also take events from
+//! [`hid`](crate::hid) bridges, delivered with [`deliver`].
+
+use crate::demo;
+use crate::error::Result;
+use crate::hook::{EventHandler, GrabHandler};
+use crate::platform;
+use crate::policy::ListenOnly;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+#[cfg(feature = "hid")]
+use crate::event::Event;
+#[cfg(feature = "hid")]
+use crate::hook::Shared;
+#[cfg(feature = "hid")]
+use std::sync::Mutex;
+#[cfg(feature = "hid")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Run a listen hook until `running` is cleared.
+pub(crate) fn run_hook<H: EventHandler + 'static>(
+    running: &Arc<AtomicBool>,
+    handler: H,
+) -> Result<()> {
+    #[cfg(feature = "hid")]
+    let (handler, _attached) = {
+        let handler = Arc::new(handler);
+        let attached = Attachment::new(running, Shared(handler.clone()));
+        (Shared(handler), attached)
+    };
+    match demo::active() {
+        Some(script) => demo::run(running, &script, handler),
+        None => platform::run_hook(running, handler),
+    }
+}
+
+/// Run a grab hook until `running` is cleared. Scripted events never reach
+/// the OS, so verdicts on them are ignored.
+pub(crate) fn run_grab_hook<H: GrabHandler + 'static>(
+    running: &Arc<AtomicBool>,
+    handler: H,
+) -> Result<()> {
+    // Events from other sources never reach the OS either
+    #[cfg(feature = "hid")]
+    let (handler, _attached) = {
+        let handler = Arc::new(handler);
+        let attached = Attachment::new(running, ListenOnly(Shared(handler.clone())));
+        (Shared(handler), attached)
+    };
+    match demo::active() {
+        Some(script) => demo::run(running, &script, ListenOnly(handler)),
+        None => platform::run_grab_hook(running, handler),
+    }
+}
+
+/// A running hook's handler, and whether it's still running.
+#[cfg(feature = "hid")]
+type Attached = (u64, Arc<AtomicBool>, Arc<dyn EventHandler>);
+
+/// Handlers of the running hooks, for events from sources besides the OS.
+#[cfg(feature = "hid")]
+static ATTACHED: Mutex<Vec<Attached>> = Mutex::new(Vec::new());
+
+#[cfg(feature = "hid")]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Send `event` to every running hook, on the calling thread.
+#[cfg(feature = "hid")]
+pub(crate) fn deliver(event: &Event) {
+    let handlers: Vec<_> = ATTACHED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .filter(|(_, running, _)| running.load(Ordering::SeqCst))
+        .map(|(_, _, handler)| handler.clone())
+        .collect();
+    for handler in handlers {
+        handler.handle_event(event);
+    }
+}
+
+/// A handler attached for [`deliver`] until dropped.
+#[cfg(feature = "hid")]
+struct Attachment(u64);
+
+#[cfg(feature = "hid")]
+impl Attachment {
+    fn new(running: &Arc<AtomicBool>, handler: impl EventHandler + 'static) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        ATTACHED.lock().unwrap_or_else(|e| e.into_inner()).push((
+            id,
+            running.clone(),
+            Arc::new(handler),
+        ));
+        Self(id)
+    }
+}
+
+#[cfg(feature = "hid")]
+impl Drop for Attachment {
+    fn drop(&mut self) {
+        ATTACHED
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|(id, _, _)| *id != self.0);
+    }
+}
+
+#[cfg(all(test, feature = "hid"))]
+mod tests {
+    use super::*;
+    use crate::event::EventType;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_deliver_reaches_running_hooks_only() {
+        let seen = Arc::new(AtomicUsize::new(0));
+        let running = Arc::new(AtomicBool::new(true));
+        let attached = {
+            let seen = seen.clone();
+            Attachment::new(&running, move |event: &Event| {
+                if event.event_type == EventType::KeyPressed
+                    && event
+                        .keyboard
+                        .as_ref()
+                        .is_some_and(|kb| kb.raw_code == 0xABCD)
+                {
+                    seen.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+        let event = Event::key_pressed(crate::keycode::Key::Unknown(0xABCD), 0xABCD);
+
+        deliver(&event);
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+
+        running.store(false, Ordering::SeqCst);
+        deliver(&event);
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+
+        running.store(true, Ordering::SeqCst);
+        drop(attached);
+        deliver(&event);
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+    }
+}
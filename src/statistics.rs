@@ -19,15 +19,49 @@
 //! println!("Most pressed key: {:?}", stats.most_frequent_key());
 //! println!("Mouse moved: {:.1} pixels", stats.total_mouse_distance);
 //! ```
+//!
+//! Figures beyond the built-in ones can be computed by implementing
+//! [`Metric`] and adding it with [`StatisticsCollector::add_metric`].
+//!
+//! With the `serde` feature, a collector can keep its totals across
+//! restarts:
+//!
+//! ```no_run
+//! # #[cfg(feature = "serde")]
+//! # {
+//! use monio::statistics::StatisticsCollector;
+//! use std::time::Duration;
+//!
+//! let mut collector = StatisticsCollector::resume_from("stats.json")
+//!     .unwrap()
+//!     .autosave("stats.json", Duration::from_secs(60));
+//! collector.start().unwrap();
+//! # }
+//! ```
+
+pub mod clicks;
+mod effort;
+mod metric;
+#[cfg(feature = "serde")]
+mod state;
+#[cfg(feature = "sqlite")]
+pub mod store;
+
+pub use clicks::{ClickTimingAnalyzer, ClickTimingReport, analyze_clicks_for};
+pub use effort::{EffortWeights, INTENSITY_BUCKET, Intensity};
+pub use metric::{Metric, MetricReport, MetricValue};
 
 use crate::Hook;
 use crate::error::{Error, Result};
 use crate::event::{Event, EventType};
 use crate::keycode::Key;
+use effort::EffortSample;
 use std::collections::HashMap;
+#[cfg(feature = "serde")]
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Statistics collected from input events.
 #[derive(Debug, Clone, Default)]
@@ -51,16 +85,38 @@ pub struct EventStatistics {
     pub mouse_drag_count: u64,
     /// Number of mouse wheel events.
     pub mouse_wheel_count: u64,
+    /// Number of input method composition sessions (see
+    /// [`ime`](crate::ime)); stays 0 where composition isn't detected.
+    pub ime_composition_count: u64,
+    /// Number of events generated by software (see [`Event::injected`]).
+    pub injected_event_count: u64,
+    /// Number of events known to come from a physical device.
+    ///
+    /// Events whose origin the platform can't tell count as neither.
+    pub physical_event_count: u64,
 
     // Key statistics
     /// Count of each key pressed.
     pub key_frequency: HashMap<Key, u64>,
 
+    // Character statistics
+    /// Count of each character typed (from `KeyTyped` events).
+    ///
+    /// Unlike `key_frequency`, this reflects the produced character, so shifted
+    /// symbols and dead-key compositions (e.g. `é`) are counted as typed.
+    pub char_frequency: HashMap<char, u64>,
+    /// Estimated number of words typed (runs of non-whitespace characters).
+    pub words_typed: u64,
+    /// Whether the last typed character was part of a word.
+    in_word: bool,
+
     // Mouse statistics
     /// Total distance the mouse has moved (in pixels).
     pub total_mouse_distance: f64,
     /// Current mouse position (last known).
     pub current_mouse_position: (f64, f64),
+    /// Distance dragged per mouse button (in pixels).
+    pub drag_distance: HashMap<crate::event::Button, f64>,
 
     // Timing statistics
     /// When statistics collection started.
@@ -88,6 +144,12 @@ pub struct EventStatistics {
     /// Number of click intervals measured.
     click_interval_count: u64,
 
+    // Effort
+    /// Input counted per [`INTENSITY_BUCKET`], from `effort_origin`.
+    effort_buckets: Vec<EffortSample>,
+    /// Start of the first effort bucket.
+    effort_origin: Option<Instant>,
+
     // Button statistics
     /// Count of clicks per mouse button.
     pub button_clicks: HashMap<crate::event::Button, u64>,
@@ -112,7 +174,9 @@ impl EventStatistics {
     pub fn new() -> Self {
         Self {
             key_frequency: HashMap::new(),
+            char_frequency: HashMap::new(),
             button_clicks: HashMap::new(),
+            drag_distance: HashMap::new(),
             current_mouse_position: (0.0, 0.0),
             ..Default::default()
         }
@@ -120,7 +184,21 @@ impl EventStatistics {
 
     /// Process an event and update statistics.
     pub fn record_event(&mut self, event: &Event) {
-        self.total_event_count += 1;
+        self.record_weighted(event, 1);
+    }
+
+    /// Record `event` as standing in for `weight` events of its kind.
+    ///
+    /// Used for sampled mouse motion: counts grow by `weight`, while distance
+    /// is measured from the previously recorded position and so already
+    /// covers the skipped moves.
+    fn record_weighted(&mut self, event: &Event, weight: u64) {
+        self.total_event_count += weight;
+        match event.injected {
+            Some(true) => self.injected_event_count += weight,
+            Some(false) => self.physical_event_count += weight,
+            None => {}
+        }
 
         match event.event_type {
             EventType::KeyPressed => {
@@ -140,6 +218,7 @@ impl EventStatistics {
                 }
 
                 self.last_key_time = Some(now);
+                self.effort_bucket(now).key_presses += 1;
 
                 if let Some(ref kb) = event.keyboard {
                     *self.key_frequency.entry(kb.key).or_insert(0) += 1;
@@ -148,6 +227,21 @@ impl EventStatistics {
             EventType::KeyReleased => {
                 self.key_release_count += 1;
             }
+            EventType::KeyTyped => {
+                if let Some(ch) = event.keyboard.as_ref().and_then(|kb| kb.char) {
+                    *self.char_frequency.entry(ch).or_insert(0) += 1;
+
+                    if ch.is_whitespace() {
+                        self.in_word = false;
+                    } else if !self.in_word {
+                        self.in_word = true;
+                        self.words_typed += 1;
+                    }
+                }
+            }
+            EventType::ImeCompositionStarted => {
+                self.ime_composition_count += 1;
+            }
             EventType::MousePressed => {
                 self.mouse_press_count += 1;
 
@@ -160,6 +254,7 @@ impl EventStatistics {
                         Some(self.click_interval_sum / self.click_interval_count as u32);
                 }
                 self.last_click_time = Some(now);
+                self.effort_bucket(now).clicks += 1;
 
                 if let Some(ref mouse) = event.mouse
                     && let Some(button) = mouse.button
@@ -175,9 +270,9 @@ impl EventStatistics {
             }
             EventType::MouseMoved | EventType::MouseDragged => {
                 if event.event_type == EventType::MouseMoved {
-                    self.mouse_move_count += 1;
+                    self.mouse_move_count += weight;
                 } else {
-                    self.mouse_drag_count += 1;
+                    self.mouse_drag_count += weight;
                 }
 
                 let now = Instant::now();
@@ -189,12 +284,21 @@ impl EventStatistics {
                 if let Some(ref mouse) = event.mouse {
                     let dx = mouse.x - self.current_mouse_position.0;
                     let dy = mouse.y - self.current_mouse_position.1;
-                    self.total_mouse_distance += (dx * dx + dy * dy).sqrt();
+                    let distance = (dx * dx + dy * dy).sqrt();
+                    self.total_mouse_distance += distance;
                     self.current_mouse_position = (mouse.x, mouse.y);
+                    self.effort_bucket(now).distance += distance as f32;
+
+                    if event.event_type == EventType::MouseDragged
+                        && let Some(button) = mouse.button
+                    {
+                        *self.drag_distance.entry(button).or_insert(0.0) += distance;
+                    }
                 }
             }
             EventType::MouseWheel => {
                 self.mouse_wheel_count += 1;
+                self.effort_bucket(Instant::now()).scrolls += 1;
                 if let Some(ref wheel) = event.wheel {
                     match wheel.direction {
                         crate::event::ScrollDirection::Up => {
@@ -216,6 +320,20 @@ impl EventStatistics {
         }
     }
 
+    /// The effort bucket covering `now`.
+    fn effort_bucket(&mut self, now: Instant) -> &mut EffortSample {
+        let origin = *self
+            .effort_origin
+            .get_or_insert(self.start_time.unwrap_or(now));
+        let index =
+            (now.saturating_duration_since(origin).as_secs() / INTENSITY_BUCKET.as_secs()) as usize;
+        if index >= self.effort_buckets.len() {
+            self.effort_buckets
+                .resize(index + 1, EffortSample::default());
+        }
+        &mut self.effort_buckets[index]
+    }
+
     /// Get total number of events.
     pub fn total_events(&self) -> u64 {
         self.total_event_count
@@ -229,6 +347,52 @@ impl EventStatistics {
             .map(|(key, count)| (*key, *count))
     }
 
+    /// Get the most frequently typed character.
+    pub fn most_frequent_char(&self) -> Option<(char, u64)> {
+        self.char_frequency
+            .iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(ch, count)| (*ch, *count))
+    }
+
+    /// Get the total number of characters typed.
+    pub fn total_chars_typed(&self) -> u64 {
+        self.char_frequency.values().sum()
+    }
+
+    /// Get the percentage breakdown of typed characters by category.
+    pub fn char_breakdown(&self) -> CharBreakdown {
+        let total = self.total_chars_typed();
+        if total == 0 {
+            return CharBreakdown::default();
+        }
+
+        let mut counts = [0u64; 5];
+        for (ch, count) in &self.char_frequency {
+            let idx = if ch.is_alphabetic() {
+                0
+            } else if ch.is_numeric() {
+                1
+            } else if ch.is_whitespace() {
+                3
+            } else if ch.is_control() {
+                4
+            } else {
+                2
+            };
+            counts[idx] += count;
+        }
+
+        let pct = |n: u64| n as f64 / total as f64 * 100.0;
+        CharBreakdown {
+            letters: pct(counts[0]),
+            digits: pct(counts[1]),
+            punctuation: pct(counts[2]),
+            whitespace: pct(counts[3]),
+            other: pct(counts[4]),
+        }
+    }
+
     /// Get the most frequently used mouse button.
     pub fn most_frequent_button(&self) -> Option<(crate::event::Button, u64)> {
         self.button_clicks
@@ -264,6 +428,52 @@ impl EventStatistics {
         self.key_press_count as f64 / duration.as_secs_f64() * 60.0
     }
 
+    /// Total input effort, see [`EffortWeights`].
+    ///
+    /// A heuristic for comparing periods, not an ergonomic measure.
+    pub fn total_effort(&self, weights: EffortWeights) -> f64 {
+        effort::effort(
+            &weights,
+            self.key_press_count,
+            self.mouse_press_count,
+            self.mouse_wheel_count,
+            self.total_mouse_distance,
+        )
+    }
+
+    /// Input effort per hour of collection, see [`EffortWeights`].
+    ///
+    /// Returns 0.0 when no time has been collected, e.g. for statistics
+    /// loaded from a store; divide [`total_effort`](Self::total_effort) by
+    /// the range instead. Like `total_effort`, this is a heuristic.
+    pub fn effort_score(&self, weights: EffortWeights) -> f64 {
+        let duration = self.collection_duration();
+        if duration.is_zero() {
+            return 0.0;
+        }
+        self.total_effort(weights) / duration.as_secs_f64() * 3600.0
+    }
+
+    /// Effort in each [`INTENSITY_BUCKET`] since collection started.
+    ///
+    /// Only covers events recorded by this instance (or merged from others
+    /// that recorded them); saved state and stores keep just the totals.
+    pub fn effort_timeline(&self, weights: EffortWeights) -> Vec<f64> {
+        self.effort_buckets
+            .iter()
+            .map(|bucket| bucket.effort(&weights))
+            .collect()
+    }
+
+    /// How busy each [`INTENSITY_BUCKET`] since collection started was, see
+    /// [`effort_timeline`](Self::effort_timeline).
+    pub fn intensity_timeline(&self, weights: EffortWeights) -> Vec<Intensity> {
+        self.effort_buckets
+            .iter()
+            .map(|bucket| Intensity::classify(bucket.effort(&weights)))
+            .collect()
+    }
+
     /// Get mouse activity ratio (0.0 to 1.0).
     pub fn mouse_activity_ratio(&self) -> f64 {
         let total_input = self.key_press_count + self.mouse_press_count + self.mouse_move_count;
@@ -326,6 +536,13 @@ impl EventStatistics {
             self.events_per_minute()
         );
 
+        if self.injected_event_count > 0 || self.physical_event_count > 0 {
+            summary.push_str(&format!(
+                "Physical / Injected: {} / {}\n\n",
+                self.physical_event_count, self.injected_event_count
+            ));
+        }
+
         // Keyboard stats
         summary.push_str(&format!(
             "Keyboard:\n\
@@ -341,6 +558,17 @@ impl EventStatistics {
             summary.push_str(&format!("- Most pressed: {:?} ({} times)\n", key, count));
         }
 
+        if let Some((ch, count)) = self.most_frequent_char() {
+            summary.push_str(&format!(
+                "- Chars typed: {} (~{} words)\n\
+                 - Most typed: {:?} ({} times)\n",
+                self.total_chars_typed(),
+                self.words_typed,
+                ch,
+                count
+            ));
+        }
+
         summary.push('\n');
 
         // Mouse stats
@@ -364,6 +592,12 @@ impl EventStatistics {
             summary.push_str(&format!("- Most clicked: {:?} ({} times)\n", btn, count));
         }
 
+        let mut drags: Vec<_> = self.drag_distance.iter().collect();
+        drags.sort_by(|a, b| b.1.total_cmp(a.1));
+        for (btn, distance) in drags {
+            summary.push_str(&format!("- {:?} drag: {:.0} pixels\n", btn, distance));
+        }
+
         summary
     }
 
@@ -378,21 +612,153 @@ impl EventStatistics {
         self.mouse_move_count += other.mouse_move_count;
         self.mouse_drag_count += other.mouse_drag_count;
         self.mouse_wheel_count += other.mouse_wheel_count;
+        self.ime_composition_count += other.ime_composition_count;
+        self.injected_event_count += other.injected_event_count;
+        self.physical_event_count += other.physical_event_count;
 
         // Merge key frequencies
         for (key, count) in &other.key_frequency {
             *self.key_frequency.entry(*key).or_insert(0) += count;
         }
 
+        // Merge char frequencies
+        for (ch, count) in &other.char_frequency {
+            *self.char_frequency.entry(*ch).or_insert(0) += count;
+        }
+        self.words_typed += other.words_typed;
+
         // Merge button clicks
         for (btn, count) in &other.button_clicks {
             *self.button_clicks.entry(*btn).or_insert(0) += count;
         }
+        for (btn, distance) in &other.drag_distance {
+            *self.drag_distance.entry(*btn).or_insert(0.0) += distance;
+        }
 
         self.total_mouse_distance += other.total_mouse_distance;
         self.total_vertical_scroll += other.total_vertical_scroll;
         self.total_horizontal_scroll += other.total_horizontal_scroll;
         self.active_typing_duration += other.active_typing_duration;
+
+        // Effort buckets are added up by position from each one's start
+        if self.effort_buckets.len() < other.effort_buckets.len() {
+            self.effort_buckets
+                .resize(other.effort_buckets.len(), EffortSample::default());
+        }
+        for (bucket, other) in self.effort_buckets.iter_mut().zip(&other.effort_buckets) {
+            bucket.add(other);
+        }
+    }
+}
+
+/// The built-in statistics, reported as the `input` metric.
+impl Metric for EventStatistics {
+    fn name(&self) -> &str {
+        "input"
+    }
+
+    fn record(&mut self, event: &Event) {
+        self.record_event(event);
+    }
+
+    fn record_sampled(&mut self, event: &Event, weight: u64) {
+        self.record_weighted(event, weight);
+    }
+
+    fn report(&self) -> MetricReport {
+        MetricReport::new()
+            .with("events", self.total_event_count)
+            .with("key_presses", self.key_press_count)
+            .with("chars_typed", self.total_chars_typed())
+            .with("words_typed", self.words_typed)
+            .with("mouse_presses", self.mouse_press_count)
+            .with("mouse_moves", self.mouse_move_count)
+            .with("mouse_distance", self.total_mouse_distance)
+            .with("wheel_events", self.mouse_wheel_count)
+            .with("active_typing", self.active_typing_duration)
+            .with(
+                "effort_per_hour",
+                self.effort_score(EffortWeights::default()),
+            )
+    }
+
+    fn merge(&mut self, other: &dyn Metric) {
+        if let Some(other) = other.downcast_ref::<Self>() {
+            EventStatistics::merge(self, other);
+        }
+    }
+}
+
+/// Percentage breakdown of typed characters by category.
+///
+/// Each field is a percentage (0.0 to 100.0) of all typed characters.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CharBreakdown {
+    /// Alphabetic characters (any script).
+    pub letters: f64,
+    /// Numeric characters.
+    pub digits: f64,
+    /// Punctuation and symbols.
+    pub punctuation: f64,
+    /// Whitespace characters (space, tab, newline).
+    pub whitespace: f64,
+    /// Anything else (control characters, etc.).
+    pub other: f64,
+}
+
+/// Largest interval the adaptive motion sampler backs off to.
+const MAX_MOTION_SAMPLE_INTERVAL: u64 = 32;
+
+/// Picks which mouse moves to record, see [`StatisticsCollector::sample_motion`].
+#[derive(Debug, Clone)]
+struct MotionSampler {
+    /// Record one in this many moves.
+    interval: u64,
+    /// Latency above which the adaptive mode samples less often.
+    target_latency: Option<Duration>,
+    skipped: u64,
+}
+
+impl MotionSampler {
+    fn new(interval: u64, target_latency: Option<Duration>) -> Self {
+        Self {
+            interval: interval.max(1),
+            target_latency,
+            skipped: 0,
+        }
+    }
+
+    /// The weight to record `event` with, or `None` to skip it.
+    ///
+    /// Only mouse moves and drags are ever skipped.
+    fn admit(&mut self, event: &Event) -> Option<u64> {
+        if !matches!(
+            event.event_type,
+            EventType::MouseMoved | EventType::MouseDragged
+        ) {
+            return Some(1);
+        }
+        self.skipped += 1;
+        if self.skipped < self.interval {
+            return None;
+        }
+        let weight = std::mem::take(&mut self.skipped);
+
+        if let Some(target) = self.target_latency {
+            let latency = SystemTime::now()
+                .duration_since(event.time)
+                .unwrap_or_default();
+            self.adapt(latency, target);
+        }
+        Some(weight)
+    }
+
+    fn adapt(&mut self, latency: Duration, target: Duration) {
+        if latency > target {
+            self.interval = (self.interval * 2).min(MAX_MOTION_SAMPLE_INTERVAL);
+        } else if latency < target / 2 {
+            self.interval = (self.interval / 2).max(1);
+        }
     }
 }
 
@@ -401,6 +767,12 @@ pub struct StatisticsCollector {
     stats: Arc<Mutex<EventStatistics>>,
     hook: Option<Hook>,
     running: Arc<AtomicBool>,
+    sampler: MotionSampler,
+    metrics: Arc<Mutex<Vec<Box<dyn Metric>>>>,
+    #[cfg(feature = "sqlite")]
+    flusher: Option<store::StoreFlusher>,
+    #[cfg(feature = "serde")]
+    autosaver: Option<state::Autosaver>,
 }
 
 impl StatisticsCollector {
@@ -413,9 +785,142 @@ impl StatisticsCollector {
             stats: Arc::new(Mutex::new(stats)),
             hook: None,
             running: Arc::new(AtomicBool::new(false)),
+            sampler: MotionSampler::new(1, None),
+            metrics: Arc::new(Mutex::new(Vec::new())),
+            #[cfg(feature = "sqlite")]
+            flusher: None,
+            #[cfg(feature = "serde")]
+            autosaver: None,
+        }
+    }
+
+    /// Record only a fraction of mouse moves, e.g. `1.0 / 8.0` for every 8th.
+    ///
+    /// Key, button and wheel events are always recorded. Move and drag
+    /// counts are scaled back up, so they stay within one sampling interval
+    /// of the true count. Distance is measured between sampled positions,
+    /// which cuts corners: on a smooth path sampled every `s` points of
+    /// travel, the relative error is about `s² / (24 r²)` for curve radius
+    /// `r` (well under 1% for ordinary cursor paths), plus at most one
+    /// interval of travel lost at the end of each movement. Sharp zig-zags
+    /// shorter than the interval are undercounted.
+    pub fn sample_motion(mut self, fraction: f64) -> Self {
+        let interval = if fraction > 0.0 {
+            (1.0 / fraction).round().max(1.0) as u64
+        } else {
+            1
+        };
+        self.sampler = MotionSampler::new(interval, self.sampler.target_latency);
+        self
+    }
+
+    /// Sample mouse moves adaptively, based on how late events arrive.
+    ///
+    /// Whenever a recorded move was delivered more than `target_latency`
+    /// after it happened, the sampling interval doubles (up to every 32nd
+    /// move); once latency drops below half the target, it halves again.
+    /// Starts from the interval set by [`sample_motion`](Self::sample_motion).
+    /// The error bounds documented there apply at the current interval.
+    pub fn sample_motion_adaptive(mut self, target_latency: Duration) -> Self {
+        self.sampler.target_latency = Some(target_latency);
+        self
+    }
+
+    /// Feed a custom metric with the collected events.
+    ///
+    /// Metrics see events after motion sampling, through
+    /// [`Metric::record_sampled`]. They can be added while collecting, and
+    /// only see events from then on. Custom metrics aren't saved with
+    /// [`save_state`](Self::save_state) or written to a store.
+    pub fn add_metric(&mut self, metric: Box<dyn Metric>) {
+        if let Ok(mut metrics) = self.metrics.lock() {
+            metrics.push(metric);
         }
     }
 
+    /// Reports from the built-in statistics and each custom metric, by name.
+    pub fn reports(&self) -> Vec<(String, MetricReport)> {
+        let stats = self.snapshot();
+        let mut reports = vec![(stats.name().to_string(), stats.report())];
+        if let Ok(metrics) = self.metrics.lock() {
+            reports.extend(
+                metrics
+                    .iter()
+                    .map(|metric| (metric.name().to_string(), metric.report())),
+            );
+        }
+        reports
+    }
+
+    /// Call `f` with the first custom metric of type `T`.
+    ///
+    /// Returns `None` if no such metric was added.
+    pub fn with_metric<T: Metric, R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        let metrics = self.metrics.lock().ok()?;
+        metrics
+            .iter()
+            .find_map(|metric| metric.downcast_ref::<T>())
+            .map(f)
+    }
+
+    /// Create a collector that persists statistics to a store.
+    ///
+    /// While collecting, deltas are written to `store` every `flush_interval`
+    /// from a background thread, and once more when collection stops.
+    #[cfg(feature = "sqlite")]
+    pub fn with_store(store: store::SqliteStore, flush_interval: Duration) -> Self {
+        let mut collector = Self::new();
+        collector.flusher = Some(store::StoreFlusher::new(store, flush_interval));
+        collector
+    }
+
+    /// Resume from a state file written by [`save_state`](Self::save_state)
+    /// or autosave.
+    ///
+    /// Totals keep accumulating from the saved values, and the motion
+    /// sampling and autosave settings are restored. Timestamps such as
+    /// `last_key_time` start out unset, so active typing time doesn't span
+    /// the downtime. If there is no file at `path`, this is a fresh
+    /// collector.
+    #[cfg(feature = "serde")]
+    pub fn resume_from(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut collector = Self::new();
+        let Some((stats, config)) = state::load(path)? else {
+            return Ok(collector);
+        };
+        collector.stats = Arc::new(Mutex::new(stats));
+        collector.sampler = config.sampler();
+        if let Some(interval) = config.autosave_interval() {
+            collector = collector.autosave(path, interval);
+        }
+        Ok(collector)
+    }
+
+    /// Save the current statistics and settings to `path`.
+    ///
+    /// The file is replaced atomically, so a crash mid-save leaves the
+    /// previous state intact.
+    #[cfg(feature = "serde")]
+    pub fn save_state(&self, path: impl AsRef<Path>) -> Result<()> {
+        let stats = self
+            .stats
+            .lock()
+            .map_err(|_| Error::ThreadError("statistics mutex poisoned".into()))?
+            .clone();
+        let config =
+            state::SavedConfig::new(&self.sampler, self.autosaver.as_ref().map(|a| a.interval()));
+        state::save(path.as_ref(), &stats, config)
+    }
+
+    /// Save state to `path` every `interval` while collecting, and when
+    /// collection stops.
+    #[cfg(feature = "serde")]
+    pub fn autosave(mut self, path: impl AsRef<Path>, interval: Duration) -> Self {
+        self.autosaver = Some(state::Autosaver::new(path.as_ref().to_path_buf(), interval));
+        self
+    }
+
     /// Start collecting statistics in the background.
     pub fn start(&mut self) -> Result<()> {
         if self.running.load(Ordering::SeqCst) {
@@ -424,17 +929,45 @@ impl StatisticsCollector {
 
         let stats = self.stats.clone();
         let running = self.running.clone();
+        let sampler = Mutex::new(self.sampler.clone());
+        let metrics = self.metrics.clone();
+        #[cfg(feature = "sqlite")]
+        let pending = self.flusher.as_ref().map(|f| f.pending());
 
         let hook = Hook::new();
         hook.run_async(move |event: &Event| {
             if !running.load(Ordering::SeqCst) {
                 return;
             }
+            let Some(weight) = sampler.lock().map_or(Some(1), |mut s| s.admit(event)) else {
+                return;
+            };
             if let Ok(mut s) = stats.lock() {
-                s.record_event(event);
+                s.record_weighted(event, weight);
+            }
+            if let Ok(mut metrics) = metrics.lock() {
+                for metric in metrics.iter_mut() {
+                    metric.record_sampled(event, weight);
+                }
+            }
+            #[cfg(feature = "sqlite")]
+            if let Some(pending) = &pending
+                && let Ok(mut p) = pending.lock()
+            {
+                p.record_weighted(event, weight);
             }
         })?;
 
+        #[cfg(feature = "sqlite")]
+        if let Some(flusher) = self.flusher.as_mut() {
+            flusher.start()?;
+        }
+
+        #[cfg(feature = "serde")]
+        if let Some(autosaver) = self.autosaver.as_mut() {
+            autosaver.start(self.stats.clone(), &self.sampler);
+        }
+
         // Only set running flag after hook is successfully started
         self.running.store(true, Ordering::SeqCst);
         self.hook = Some(hook);
@@ -451,12 +984,28 @@ impl StatisticsCollector {
             hook.stop()?;
         }
 
-        let mut stats = self
-            .stats
-            .lock()
-            .map_err(|_| Error::ThreadError("statistics mutex poisoned".into()))?;
-        stats.end_time = Some(Instant::now());
-        Ok(stats.clone())
+        #[cfg(feature = "sqlite")]
+        if let Some(flusher) = self.flusher.as_mut() {
+            flusher.stop()?;
+        }
+
+        let stats = {
+            let mut stats = self
+                .stats
+                .lock()
+                .map_err(|_| Error::ThreadError("statistics mutex poisoned".into()))?;
+            stats.end_time = Some(Instant::now());
+            stats.clone()
+        };
+
+        #[cfg(feature = "serde")]
+        if let Some(autosaver) = self.autosaver.as_mut() {
+            autosaver.stop()?;
+            let path = autosaver.path().to_path_buf();
+            self.save_state(path)?;
+        }
+
+        Ok(stats)
     }
 
     /// Get a snapshot of current statistics without stopping.
@@ -493,6 +1042,7 @@ impl Default for StatisticsCollector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::event::Button;
 
     #[test]
     fn test_statistics_new() {
@@ -525,6 +1075,25 @@ mod tests {
         assert_eq!(count, 2);
     }
 
+    #[test]
+    fn test_char_frequency() {
+        let mut stats = EventStatistics::new();
+        for ch in "héllo, 42!".chars() {
+            stats.record_event(&Event::key_typed(Key::Unknown(0), 0, ch));
+        }
+
+        assert_eq!(stats.total_chars_typed(), 10);
+        assert_eq!(stats.char_frequency.get(&'é'), Some(&1));
+        assert_eq!(stats.most_frequent_char(), Some(('l', 2)));
+        assert_eq!(stats.words_typed, 2);
+
+        let breakdown = stats.char_breakdown();
+        assert!((breakdown.letters - 50.0).abs() < 0.001);
+        assert!((breakdown.digits - 20.0).abs() < 0.001);
+        assert!((breakdown.punctuation - 20.0).abs() < 0.001);
+        assert!((breakdown.whitespace - 10.0).abs() < 0.001);
+    }
+
     #[test]
     fn test_mouse_distance() {
         let mut stats = EventStatistics::new();
@@ -537,6 +1106,39 @@ mod tests {
         assert!((stats.total_mouse_distance - 10.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_drag_distance_per_button() {
+        let mut stats = EventStatistics::new();
+
+        let mut drag = Event::mouse_dragged(3.0, 4.0);
+        drag.mouse.as_mut().unwrap().button = Some(Button::Right);
+        stats.record_event(&drag);
+        stats.record_event(&Event::mouse_moved(3.0, 14.0));
+
+        assert!((stats.total_mouse_distance - 15.0).abs() < 0.001);
+        assert_eq!(stats.drag_distance.len(), 1);
+        assert!((stats.drag_distance[&Button::Right] - 5.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_injected_physical_split() {
+        let mut stats = EventStatistics::new();
+
+        let mut physical = Event::key_pressed(Key::KeyA, 0);
+        physical.injected = Some(false);
+        let mut injected = Event::key_pressed(Key::KeyB, 0);
+        injected.injected = Some(true);
+        stats.record_event(&physical);
+        stats.record_event(&physical);
+        stats.record_event(&injected);
+        stats.record_event(&Event::key_pressed(Key::KeyC, 0));
+
+        assert_eq!(stats.total_event_count, 4);
+        assert_eq!(stats.physical_event_count, 2);
+        assert_eq!(stats.injected_event_count, 1);
+        assert!(stats.summary().contains("Physical / Injected: 2 / 1"));
+    }
+
     #[test]
     fn test_merge() {
         let mut stats1 = EventStatistics::new();
@@ -551,4 +1153,194 @@ mod tests {
         assert_eq!(stats1.key_frequency.get(&Key::KeyA), Some(&1));
         assert_eq!(stats1.key_frequency.get(&Key::KeyB), Some(&1));
     }
+
+    #[test]
+    fn test_counts_ime_compositions() {
+        let mut stats = EventStatistics::new();
+        for _ in 0..2 {
+            stats.record_event(&Event::ime_composition_started());
+            stats.record_event(&Event::key_pressed(Key::KeyK, 37));
+            stats.record_event(&Event::ime_composition_ended());
+        }
+        assert_eq!(stats.ime_composition_count, 2);
+
+        let mut merged = EventStatistics::new();
+        merged.merge(&stats);
+        assert_eq!(merged.ime_composition_count, 2);
+    }
+
+    /// Record `events` through a sampler, like the collector does.
+    fn sampled(events: &[Event], sampler: &mut MotionSampler) -> EventStatistics {
+        let mut stats = EventStatistics::new();
+        for event in events {
+            if let Some(weight) = sampler.admit(event) {
+                stats.record_weighted(event, weight);
+            }
+        }
+        stats
+    }
+
+    #[test]
+    fn test_sampled_distance_close_to_full() {
+        // Two laps around a circle of radius 300, a move every ~2 points
+        let path: Vec<Event> = (0..=2000)
+            .map(|i| {
+                let angle = i as f64 / 2000.0 * 4.0 * std::f64::consts::PI;
+                Event::mouse_moved(400.0 + 300.0 * angle.cos(), 400.0 + 300.0 * angle.sin())
+            })
+            .collect();
+
+        let full = sampled(&path, &mut MotionSampler::new(1, None));
+        for interval in [4, 8, 16] {
+            let stats = sampled(&path, &mut MotionSampler::new(interval, None));
+            let error = (stats.total_mouse_distance - full.total_mouse_distance).abs()
+                / full.total_mouse_distance;
+            assert!(error < 0.02, "interval {interval}: error {error}");
+            assert!(full.mouse_move_count - stats.mouse_move_count < interval);
+        }
+    }
+
+    #[test]
+    fn test_sampling_never_skips_keys_and_clicks() {
+        let mut events = Vec::new();
+        for i in 0..10 {
+            events.push(Event::key_pressed(Key::KeyA, 0));
+            events.push(Event::mouse_pressed(Button::Left, 0.0, 0.0));
+            events.push(Event::mouse_moved(i as f64, 0.0));
+        }
+        let stats = sampled(&events, &mut MotionSampler::new(4, None));
+        assert_eq!(stats.key_press_count, 10);
+        assert_eq!(stats.mouse_press_count, 10);
+        assert_eq!(stats.mouse_move_count, 8);
+    }
+
+    #[test]
+    fn test_adaptive_sampling_follows_latency() {
+        let target = Duration::from_millis(10);
+        let mut sampler = MotionSampler::new(1, Some(target));
+
+        sampler.adapt(Duration::from_millis(50), target);
+        sampler.adapt(Duration::from_millis(50), target);
+        assert_eq!(sampler.interval, 4);
+        for _ in 0..10 {
+            sampler.adapt(Duration::from_secs(1), target);
+        }
+        assert_eq!(sampler.interval, MAX_MOTION_SAMPLE_INTERVAL);
+
+        // Between half the target and the target: hold steady
+        sampler.adapt(Duration::from_millis(7), target);
+        assert_eq!(sampler.interval, MAX_MOTION_SAMPLE_INTERVAL);
+        for _ in 0..10 {
+            sampler.adapt(Duration::from_millis(1), target);
+        }
+        assert_eq!(sampler.interval, 1);
+    }
+
+    #[derive(Default)]
+    struct PressCount(u64);
+
+    impl Metric for PressCount {
+        fn name(&self) -> &str {
+            "presses"
+        }
+
+        fn record(&mut self, event: &Event) {
+            if event.event_type == EventType::KeyPressed {
+                self.0 += 1;
+            }
+        }
+
+        fn report(&self) -> MetricReport {
+            MetricReport::new().with("count", self.0)
+        }
+
+        fn merge(&mut self, other: &dyn Metric) {
+            if let Some(other) = other.downcast_ref::<Self>() {
+                self.0 += other.0;
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_metric() {
+        let mut collector = StatisticsCollector::new();
+        collector.add_metric(Box::new(PressCount(3)));
+
+        assert_eq!(collector.with_metric(|m: &PressCount| m.0), Some(3));
+        assert_eq!(
+            collector.with_metric(|m: &EventStatistics| m.key_press_count),
+            None
+        );
+
+        let reports = collector.reports();
+        assert_eq!(reports[0].0, "input");
+        assert_eq!(reports[1].0, "presses");
+        assert_eq!(reports[1].1.get("count"), Some(&MetricValue::Count(3)));
+    }
+
+    #[test]
+    fn test_metric_merge() {
+        let mut a: Box<dyn Metric> = Box::new(PressCount::default());
+        let b: Box<dyn Metric> = Box::new(PressCount(2));
+        a.record(&Event::key_pressed(Key::KeyA, 30));
+        a.record(&Event::key_released(Key::KeyA, 30));
+        a.merge(b.as_ref());
+        assert_eq!(a.downcast_ref::<PressCount>().unwrap().0, 3);
+
+        // Other kinds of metric are left alone
+        a.merge(&EventStatistics::new());
+        assert_eq!(a.report().get("count"), Some(&MetricValue::Count(3)));
+
+        let mut stats = EventStatistics::new();
+        let mut other = EventStatistics::new();
+        other.record_event(&Event::key_pressed(Key::KeyA, 30));
+        Metric::merge(&mut stats, &other);
+        assert_eq!(stats.key_press_count, 1);
+        assert_eq!(
+            Metric::report(&stats).get("key_presses"),
+            Some(&MetricValue::Count(1))
+        );
+    }
+
+    #[test]
+    fn test_effort() {
+        let mut stats = EventStatistics::new();
+        let start = Instant::now();
+        stats.start_time = Some(start);
+        for _ in 0..20 {
+            stats.record_event(&Event::key_pressed(Key::KeyA, 30));
+        }
+        stats.record_event(&Event::mouse_pressed(Button::Left, 0.0, 0.0));
+        stats.record_event(&Event::mouse_moved(300.0, 400.0));
+        stats.record_event(&Event::mouse_wheel(
+            0.0,
+            0.0,
+            crate::event::ScrollDirection::Down,
+            3.0,
+        ));
+
+        let weights = EffortWeights::default();
+        // 20 keys + 1 click * 2 + 1 wheel * 0.5 + 500 px * 0.01
+        assert_eq!(stats.total_effort(weights), 27.5);
+        stats.end_time = Some(start + Duration::from_secs(30 * 60));
+        assert_eq!(stats.effort_score(weights), 55.0);
+
+        assert_eq!(stats.effort_timeline(weights), vec![27.5]);
+        assert_eq!(stats.intensity_timeline(weights), vec![Intensity::Light]);
+        let keys_only = EffortWeights {
+            click: 0.0,
+            scroll: 0.0,
+            mouse_distance: 0.0,
+            ..weights
+        };
+        assert_eq!(stats.intensity_timeline(keys_only), vec![Intensity::Light]);
+
+        let mut merged = stats.clone();
+        merged.merge(&stats);
+        assert_eq!(merged.effort_timeline(weights), vec![55.0]);
+        assert_eq!(
+            Metric::report(&stats).get("effort_per_hour"),
+            Some(&MetricValue::Amount(55.0))
+        );
+    }
 }
@@ -0,0 +1,388 @@
+//! Double-click timing analysis.
+//!
+//! [`ClickTimingAnalyzer`] measures how fast a user actually double-clicks,
+//! so an app can suggest a system double-click time that fits them. It pairs
+//! consecutive presses of the same button that land close together, and
+//! reports the distribution of the intervals between them.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::statistics::analyze_clicks_for;
+//! use std::time::Duration;
+//!
+//! println!("Double-click something a few times...");
+//! let report = analyze_clicks_for(Duration::from_secs(30)).unwrap();
+//!
+//! println!("{} double-clicks, p95 {:?}", report.samples, report.p95);
+//! println!("Suggested double-click time: {:?}", report.suggested_threshold);
+//! println!("Current setting: {:?}", report.system_threshold);
+//! ```
+
+use crate::Hook;
+use crate::error::{Error, Result};
+use crate::event::{Button, Event, EventType};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// A press that may start a double-click.
+#[derive(Debug, Clone, Copy)]
+struct Press {
+    time: SystemTime,
+    x: f64,
+    y: f64,
+}
+
+/// Collects double-click intervals from mouse presses.
+///
+/// Two presses count as a double-click attempt when they are of the same
+/// button, no further apart than [`with_max_distance`](Self::with_max_distance)
+/// and no slower than [`with_max_interval`](Self::with_max_interval). A
+/// triple-click gives two intervals. Pressing another button in between ends
+/// the sequence, and simulated presses are ignored.
+#[derive(Debug, Clone)]
+pub struct ClickTimingAnalyzer {
+    max_interval: Duration,
+    max_distance: f64,
+    margin: Duration,
+    bucket_width: Duration,
+    last_press: HashMap<Button, Press>,
+    intervals: Vec<Duration>,
+}
+
+impl ClickTimingAnalyzer {
+    /// Create an analyzer with the default limits: presses up to 1 s and
+    /// 8 pixels apart, 50 ms buckets, and a 50 ms margin on the suggestion.
+    pub fn new() -> Self {
+        Self {
+            max_interval: Duration::from_millis(1000),
+            max_distance: 8.0,
+            margin: Duration::from_millis(50),
+            bucket_width: Duration::from_millis(50),
+            last_press: HashMap::new(),
+            intervals: Vec::new(),
+        }
+    }
+
+    /// Slowest pair of presses still counted as a double-click attempt.
+    pub fn with_max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval;
+        self
+    }
+
+    /// Furthest apart (in screen coordinates) two presses may be.
+    pub fn with_max_distance(mut self, max_distance: f64) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Added to the 95th percentile to get the suggested threshold.
+    pub fn with_margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Width of each histogram bucket. Zero is treated as 1 ms.
+    pub fn with_bucket_width(mut self, bucket_width: Duration) -> Self {
+        self.bucket_width = bucket_width.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Feed an event. Anything other than a physical mouse press is ignored.
+    pub fn process_event(&mut self, event: &Event) {
+        if event.event_type != EventType::MousePressed || event.injected == Some(true) {
+            return;
+        }
+        let Some(mouse) = &event.mouse else {
+            return;
+        };
+        let Some(button) = mouse.button else {
+            return;
+        };
+
+        let press = Press {
+            time: event.time,
+            x: mouse.x,
+            y: mouse.y,
+        };
+        self.last_press.retain(|b, _| *b == button);
+        if let Some(previous) = self.last_press.insert(button, press)
+            && let Ok(interval) = press.time.duration_since(previous.time)
+            && interval <= self.max_interval
+            && (press.x - previous.x).hypot(press.y - previous.y) <= self.max_distance
+        {
+            self.intervals.push(interval);
+        }
+    }
+
+    /// The intervals collected so far, in the order they were seen.
+    pub fn intervals(&self) -> &[Duration] {
+        &self.intervals
+    }
+
+    /// Discard everything collected so far.
+    pub fn reset(&mut self) {
+        self.last_press.clear();
+        self.intervals.clear();
+    }
+
+    /// Summarize the intervals, comparing against the system's current
+    /// double-click time (if the platform reports one).
+    pub fn report(&self) -> ClickTimingReport {
+        let system_threshold = crate::display::system_settings()
+            .ok()
+            .and_then(|settings| settings.double_click_time)
+            .map(|ms| Duration::from_millis(ms.into()));
+        self.report_against(system_threshold)
+    }
+
+    /// Summarize the intervals, comparing against `system_threshold`
+    /// instead of querying the system.
+    pub fn report_against(&self, system_threshold: Option<Duration>) -> ClickTimingReport {
+        let mut sorted = self.intervals.clone();
+        sorted.sort();
+
+        let p95 = percentile(&sorted, 95);
+        ClickTimingReport {
+            samples: sorted.len(),
+            histogram: histogram(&sorted, self.bucket_width),
+            median: percentile(&sorted, 50),
+            p95,
+            suggested_threshold: p95.map(|p95| p95 + self.margin),
+            system_threshold,
+            margin: self.margin,
+        }
+    }
+
+    /// Listen for `duration` and report on the clicks seen.
+    ///
+    /// Convenience wrapper like
+    /// [`StatisticsCollector::collect_for`](super::StatisticsCollector::collect_for).
+    pub fn analyze_for(self, duration: Duration) -> Result<ClickTimingReport> {
+        let analyzer = Arc::new(Mutex::new(self));
+        let hook = Hook::new();
+        {
+            let analyzer = analyzer.clone();
+            hook.run_async(move |event: &Event| {
+                if let Ok(mut analyzer) = analyzer.lock() {
+                    analyzer.process_event(event);
+                }
+            })?;
+        }
+        std::thread::sleep(duration);
+        hook.stop()?;
+
+        let analyzer = analyzer
+            .lock()
+            .map_err(|_| Error::ThreadError("click analyzer mutex poisoned".into()))?;
+        Ok(analyzer.report())
+    }
+}
+
+impl Default for ClickTimingAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Listen for `duration` with a default [`ClickTimingAnalyzer`] and report
+/// on the clicks seen.
+pub fn analyze_clicks_for(duration: Duration) -> Result<ClickTimingReport> {
+    ClickTimingAnalyzer::new().analyze_for(duration)
+}
+
+/// One bucket of the interval histogram, covering `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistogramBucket {
+    /// Inclusive lower bound.
+    pub start: Duration,
+    /// Exclusive upper bound.
+    pub end: Duration,
+    /// Number of intervals in the bucket.
+    pub count: usize,
+}
+
+/// How the system double-click time compares to the measured intervals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThresholdAdvice {
+    /// The setting covers the user's double-clicks with little slack.
+    Keep,
+    /// Shorter than the 95th percentile: some double-clicks register as
+    /// two single clicks.
+    Increase,
+    /// Longer than the suggestion by more than the margin: separate clicks
+    /// may merge into double-clicks.
+    Decrease,
+}
+
+/// Summary of measured double-click intervals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClickTimingReport {
+    /// Number of intervals measured.
+    pub samples: usize,
+    /// Interval counts per bucket, from zero up to the slowest interval.
+    pub histogram: Vec<HistogramBucket>,
+    /// Median interval.
+    pub median: Option<Duration>,
+    /// 95th percentile interval.
+    pub p95: Option<Duration>,
+    /// Suggested double-click time: the 95th percentile plus the margin.
+    pub suggested_threshold: Option<Duration>,
+    /// The system's double-click time, if known.
+    pub system_threshold: Option<Duration>,
+    /// The margin the suggestion was computed with.
+    pub margin: Duration,
+}
+
+impl ClickTimingReport {
+    /// Compare the system double-click time to the measurements.
+    ///
+    /// `None` without samples or a known system setting.
+    pub fn advice(&self) -> Option<ThresholdAdvice> {
+        let system = self.system_threshold?;
+        let p95 = self.p95?;
+        let suggested = self.suggested_threshold?;
+        Some(if system < p95 {
+            ThresholdAdvice::Increase
+        } else if system > suggested + self.margin {
+            ThresholdAdvice::Decrease
+        } else {
+            ThresholdAdvice::Keep
+        })
+    }
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[Duration], pct: usize) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
+
+fn histogram(sorted: &[Duration], width: Duration) -> Vec<HistogramBucket> {
+    let Some(max) = sorted.last() else {
+        return Vec::new();
+    };
+    let width_nanos = width.as_nanos();
+    let buckets = (max.as_nanos() / width_nanos) as usize + 1;
+
+    let mut histogram: Vec<HistogramBucket> = (0..buckets)
+        .map(|i| HistogramBucket {
+            start: width * i as u32,
+            end: width * (i as u32 + 1),
+            count: 0,
+        })
+        .collect();
+    for interval in sorted {
+        histogram[(interval.as_nanos() / width_nanos) as usize].count += 1;
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Presses of `button` at `x`, offset from a fixed start by `ms`.
+    fn press_at(button: Button, ms: u64, x: f64) -> Event {
+        let mut event = Event::mouse_pressed(button, x, 100.0);
+        event.time = SystemTime::UNIX_EPOCH + Duration::from_millis(ms);
+        event
+    }
+
+    fn feed(analyzer: &mut ClickTimingAnalyzer, presses: &[(Button, u64, f64)]) {
+        for &(button, ms, x) in presses {
+            analyzer.process_event(&press_at(button, ms, x));
+        }
+    }
+
+    #[test]
+    fn test_pairs_by_button_distance_and_time() {
+        let mut analyzer = ClickTimingAnalyzer::new();
+        feed(
+            &mut analyzer,
+            &[
+                (Button::Left, 0, 10.0),
+                (Button::Left, 180, 12.0),    // double-click: 180 ms
+                (Button::Left, 5000, 12.0),   // too slow
+                (Button::Left, 5200, 200.0),  // too far
+                (Button::Left, 5350, 201.0),  // 150 ms
+                (Button::Right, 5400, 201.0), // other button ends the sequence
+                (Button::Left, 5500, 201.0),
+                (Button::Right, 5600, 201.0),
+                (Button::Right, 5700, 201.0), // 100 ms
+            ],
+        );
+        // Releases, moves and simulated presses don't count
+        analyzer.process_event(&Event::mouse_released(Button::Right, 201.0, 100.0));
+        let mut injected = press_at(Button::Right, 5750, 201.0);
+        injected.injected = Some(true);
+        analyzer.process_event(&injected);
+
+        assert_eq!(
+            analyzer.intervals(),
+            &[
+                Duration::from_millis(180),
+                Duration::from_millis(150),
+                Duration::from_millis(100),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_percentiles_and_histogram() {
+        let mut analyzer = ClickTimingAnalyzer::new().with_bucket_width(Duration::from_millis(100));
+        // Twenty double-clicks: 19 between 120 and 300 ms, one slow outlier
+        let mut t = 0;
+        for i in 0..20u64 {
+            let interval = if i == 19 { 480 } else { 120 + i * 10 };
+            feed(
+                &mut analyzer,
+                &[(Button::Left, t, 50.0), (Button::Left, t + interval, 50.0)],
+            );
+            t += 10_000;
+        }
+
+        let report = analyzer.report_against(Some(Duration::from_millis(500)));
+        assert_eq!(report.samples, 20);
+        assert_eq!(report.median, Some(Duration::from_millis(210)));
+        assert_eq!(report.p95, Some(Duration::from_millis(300)));
+        assert_eq!(report.suggested_threshold, Some(Duration::from_millis(350)));
+        assert_eq!(
+            report.histogram.iter().map(|b| b.count).collect::<Vec<_>>(),
+            vec![0, 8, 10, 1, 1]
+        );
+        assert_eq!(report.histogram[4].start, Duration::from_millis(400));
+        assert_eq!(report.histogram[4].end, Duration::from_millis(500));
+        assert_eq!(report.advice(), Some(ThresholdAdvice::Decrease));
+    }
+
+    #[test]
+    fn test_advice() {
+        let mut analyzer = ClickTimingAnalyzer::new();
+        feed(
+            &mut analyzer,
+            &[(Button::Left, 0, 0.0), (Button::Left, 400, 0.0)],
+        );
+
+        let advice = |ms| {
+            analyzer
+                .report_against(Some(Duration::from_millis(ms)))
+                .advice()
+        };
+        assert_eq!(advice(300), Some(ThresholdAdvice::Increase));
+        assert_eq!(advice(400), Some(ThresholdAdvice::Keep));
+        assert_eq!(advice(500), Some(ThresholdAdvice::Keep));
+        assert_eq!(advice(501), Some(ThresholdAdvice::Decrease));
+        assert_eq!(analyzer.report_against(None).advice(), None);
+
+        analyzer.reset();
+        let report = analyzer.report_against(Some(Duration::from_millis(500)));
+        assert_eq!(report.samples, 0);
+        assert!(report.histogram.is_empty());
+        assert_eq!(report.advice(), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! A single "input effort" figure combining keys, clicks, scrolling and
+//! mouse travel.
+//!
+//! Effort is a heuristic for charting how busy the hands were, e.g. per hour
+//! over a day. It is not an ergonomic measure: the weights are arbitrary
+//! conversion rates, and the same score can come from very different kinds
+//! of work.
+
+/// Width of the buckets [`EventStatistics::intensity_timeline`] classifies.
+///
+/// [`EventStatistics::intensity_timeline`]: super::EventStatistics::intensity_timeline
+pub const INTENSITY_BUCKET: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// How much each kind of input contributes to the effort score.
+///
+/// The defaults count a key press as one unit, a mouse button press as two,
+/// a wheel event as half a unit and 100 pixels of mouse travel as one.
+/// Wheel events are counted rather than their deltas, whose units differ
+/// between platforms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffortWeights {
+    /// Units per key press.
+    pub key_press: f64,
+    /// Units per mouse button press.
+    pub click: f64,
+    /// Units per wheel event.
+    pub scroll: f64,
+    /// Units per pixel of mouse travel, drags included.
+    pub mouse_distance: f64,
+}
+
+impl Default for EffortWeights {
+    fn default() -> Self {
+        Self {
+            key_press: 1.0,
+            click: 2.0,
+            scroll: 0.5,
+            mouse_distance: 0.01,
+        }
+    }
+}
+
+impl EffortWeights {
+    fn effort(&self, key_presses: f64, clicks: f64, scrolls: f64, distance: f64) -> f64 {
+        key_presses * self.key_press
+            + clicks * self.click
+            + scrolls * self.scroll
+            + distance * self.mouse_distance
+    }
+}
+
+/// How busy one [`INTENSITY_BUCKET`] was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Intensity {
+    /// Under 10 units: practically no input.
+    Idle,
+    /// Under 300 units, about one key press a second.
+    Light,
+    /// Under 1200 units.
+    Moderate,
+    /// 1200 units or more, e.g. sustained typing at 4 keys a second.
+    Heavy,
+}
+
+impl Intensity {
+    /// Classify the effort spent in one bucket.
+    pub fn classify(effort: f64) -> Self {
+        if effort < 10.0 {
+            Intensity::Idle
+        } else if effort < 300.0 {
+            Intensity::Light
+        } else if effort < 1200.0 {
+            Intensity::Moderate
+        } else {
+            Intensity::Heavy
+        }
+    }
+}
+
+/// The input counted in one bucket, kept small since there are 288 a day.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct EffortSample {
+    pub(crate) key_presses: u32,
+    pub(crate) clicks: u32,
+    pub(crate) scrolls: u32,
+    pub(crate) distance: f32,
+}
+
+impl EffortSample {
+    pub(crate) fn effort(&self, weights: &EffortWeights) -> f64 {
+        weights.effort(
+            self.key_presses as f64,
+            self.clicks as f64,
+            self.scrolls as f64,
+            self.distance as f64,
+        )
+    }
+
+    pub(crate) fn add(&mut self, other: &EffortSample) {
+        self.key_presses = self.key_presses.saturating_add(other.key_presses);
+        self.clicks = self.clicks.saturating_add(other.clicks);
+        self.scrolls = self.scrolls.saturating_add(other.scrolls);
+        self.distance += other.distance;
+    }
+}
+
+/// Total effort for the given totals.
+pub(crate) fn effort(
+    weights: &EffortWeights,
+    key_presses: u64,
+    clicks: u64,
+    scrolls: u64,
+    distance: f64,
+) -> f64 {
+    weights.effort(key_presses as f64, clicks as f64, scrolls as f64, distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_weights() {
+        let weights = EffortWeights::default();
+        assert_eq!(
+            effort(&weights, 100, 10, 4, 5000.0),
+            100.0 + 20.0 + 2.0 + 50.0
+        );
+        let sample = EffortSample {
+            key_presses: 100,
+            clicks: 10,
+            scrolls: 4,
+            distance: 5000.0,
+        };
+        assert_eq!(sample.effort(&weights), 172.0);
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(Intensity::classify(0.0), Intensity::Idle);
+        assert_eq!(Intensity::classify(9.9), Intensity::Idle);
+        assert_eq!(Intensity::classify(10.0), Intensity::Light);
+        assert_eq!(Intensity::classify(300.0), Intensity::Moderate);
+        assert_eq!(Intensity::classify(1200.0), Intensity::Heavy);
+        assert!(Intensity::Heavy > Intensity::Light);
+    }
+}
@@ -0,0 +1,177 @@
+//! Custom metrics fed alongside the built-in statistics.
+
+use crate::event::Event;
+use std::any::Any;
+use std::fmt;
+use std::time::Duration;
+
+/// A figure computed from the event stream.
+///
+/// Add metrics to a collector with
+/// [`StatisticsCollector::add_metric`](super::StatisticsCollector::add_metric);
+/// they see the same events as the built-in [`EventStatistics`](super::EventStatistics),
+/// which is itself a `Metric`.
+///
+/// # Example
+///
+/// ```
+/// use monio::statistics::{Metric, MetricReport};
+/// use monio::{Event, EventType};
+///
+/// #[derive(Default)]
+/// struct Backspaces(u64);
+///
+/// impl Metric for Backspaces {
+///     fn name(&self) -> &str {
+///         "backspaces"
+///     }
+///
+///     fn record(&mut self, event: &Event) {
+///         if event.event_type == EventType::KeyPressed
+///             && event.keyboard.as_ref().is_some_and(|kb| kb.key == monio::Key::Backspace)
+///         {
+///             self.0 += 1;
+///         }
+///     }
+///
+///     fn report(&self) -> MetricReport {
+///         MetricReport::new().with("count", self.0)
+///     }
+///
+///     fn merge(&mut self, other: &dyn Metric) {
+///         if let Some(other) = other.downcast_ref::<Self>() {
+///             self.0 += other.0;
+///         }
+///     }
+/// }
+/// ```
+pub trait Metric: Any + Send {
+    /// Name the metric is reported under.
+    fn name(&self) -> &str;
+
+    /// Update the metric with an event.
+    fn record(&mut self, event: &Event);
+
+    /// Record `event` as standing in for `weight` events of its kind.
+    ///
+    /// Called instead of [`record`](Self::record) when the collector samples
+    /// mouse motion. The default ignores the weight.
+    fn record_sampled(&mut self, event: &Event, weight: u64) {
+        let _ = weight;
+        self.record(event);
+    }
+
+    /// The metric's current values.
+    fn report(&self) -> MetricReport;
+
+    /// Fold in another instance's figures, e.g. from a previous session.
+    ///
+    /// `other` is usually the same type; use
+    /// [`downcast_ref`](trait.Metric.html#method.downcast_ref) to get at it.
+    /// The default keeps this instance's figures unchanged.
+    fn merge(&mut self, other: &dyn Metric) {
+        let _ = other;
+    }
+}
+
+impl dyn Metric {
+    /// The metric as a concrete type, if it is one.
+    pub fn downcast_ref<T: Metric>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref()
+    }
+
+    /// The metric as a mutable concrete type, if it is one.
+    pub fn downcast_mut<T: Metric>(&mut self) -> Option<&mut T> {
+        (self as &mut dyn Any).downcast_mut()
+    }
+}
+
+/// One value in a [`MetricReport`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MetricValue {
+    /// A number of events or things.
+    Count(u64),
+    /// A measured amount, e.g. pixels or a rate.
+    Amount(f64),
+    /// A span of time.
+    Duration(Duration),
+    /// Anything else.
+    Text(String),
+}
+
+impl fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricValue::Count(n) => write!(f, "{}", n),
+            MetricValue::Amount(x) => write!(f, "{:.2}", x),
+            MetricValue::Duration(d) => write!(f, "{:.1}s", d.as_secs_f64()),
+            MetricValue::Text(s) => f.write_str(s),
+        }
+    }
+}
+
+impl From<u64> for MetricValue {
+    fn from(n: u64) -> Self {
+        MetricValue::Count(n)
+    }
+}
+
+impl From<f64> for MetricValue {
+    fn from(x: f64) -> Self {
+        MetricValue::Amount(x)
+    }
+}
+
+impl From<Duration> for MetricValue {
+    fn from(d: Duration) -> Self {
+        MetricValue::Duration(d)
+    }
+}
+
+impl From<String> for MetricValue {
+    fn from(s: String) -> Self {
+        MetricValue::Text(s)
+    }
+}
+
+impl From<&str> for MetricValue {
+    fn from(s: &str) -> Self {
+        MetricValue::Text(s.to_string())
+    }
+}
+
+/// Named values reported by a [`Metric`], in display order.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetricReport {
+    /// `(name, value)` pairs.
+    pub values: Vec<(String, MetricValue)>,
+}
+
+impl MetricReport {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a value.
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<MetricValue>) -> Self {
+        self.values.push((name.into(), value.into()));
+        self
+    }
+
+    /// The value named `name`.
+    pub fn get(&self, name: &str) -> Option<&MetricValue> {
+        self.values.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+}
+
+impl fmt::Display for MetricReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.values {
+            writeln!(f, "{}: {}", name, value)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,344 @@
+//! Saving and resuming collector state across restarts.
+//!
+//! State files are JSON with a `version` field. Fields missing from older
+//! files take their defaults, and files written by a newer, incompatible
+//! version are rejected rather than silently misread.
+
+use super::{EventStatistics, MotionSampler};
+use crate::error::{Error, Result};
+use crate::event::Button;
+use crate::keycode::Key;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Current state file version.
+const STATE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StateFile {
+    version: u32,
+    #[serde(default)]
+    config: SavedConfig,
+    #[serde(default)]
+    stats: SavedStatistics,
+}
+
+/// Collector settings carried over by a resume.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct SavedConfig {
+    motion_sample_interval: u64,
+    motion_target_latency_ms: Option<u64>,
+    autosave_interval_ms: Option<u64>,
+}
+
+/// The persistent parts of [`EventStatistics`].
+///
+/// Instants, the open word and the last key/click times are left out, so a
+/// resumed collector doesn't bridge the downtime.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SavedStatistics {
+    total_event_count: u64,
+    key_press_count: u64,
+    key_release_count: u64,
+    mouse_press_count: u64,
+    mouse_release_count: u64,
+    mouse_click_count: u64,
+    mouse_move_count: u64,
+    mouse_drag_count: u64,
+    mouse_wheel_count: u64,
+    ime_composition_count: u64,
+    injected_event_count: u64,
+    physical_event_count: u64,
+    key_frequency: Vec<(Key, u64)>,
+    char_frequency: Vec<(char, u64)>,
+    words_typed: u64,
+    total_mouse_distance: f64,
+    current_mouse_position: (f64, f64),
+    drag_distance: Vec<(Button, f64)>,
+    button_clicks: Vec<(Button, u64)>,
+    total_vertical_scroll: f64,
+    total_horizontal_scroll: f64,
+    active_typing_ms: u64,
+    click_interval_sum_ms: u64,
+    click_interval_count: u64,
+    /// Time spent collecting, excluding downtime between runs.
+    collection_ms: u64,
+}
+
+impl From<&EventStatistics> for SavedStatistics {
+    fn from(stats: &EventStatistics) -> Self {
+        Self {
+            total_event_count: stats.total_event_count,
+            key_press_count: stats.key_press_count,
+            key_release_count: stats.key_release_count,
+            mouse_press_count: stats.mouse_press_count,
+            mouse_release_count: stats.mouse_release_count,
+            mouse_click_count: stats.mouse_click_count,
+            mouse_move_count: stats.mouse_move_count,
+            mouse_drag_count: stats.mouse_drag_count,
+            mouse_wheel_count: stats.mouse_wheel_count,
+            ime_composition_count: stats.ime_composition_count,
+            injected_event_count: stats.injected_event_count,
+            physical_event_count: stats.physical_event_count,
+            key_frequency: stats.key_frequency.iter().map(|(k, c)| (*k, *c)).collect(),
+            char_frequency: stats.char_frequency.iter().map(|(k, c)| (*k, *c)).collect(),
+            words_typed: stats.words_typed,
+            total_mouse_distance: stats.total_mouse_distance,
+            current_mouse_position: stats.current_mouse_position,
+            drag_distance: stats.drag_distance.iter().map(|(b, d)| (*b, *d)).collect(),
+            button_clicks: stats.button_clicks.iter().map(|(b, c)| (*b, *c)).collect(),
+            total_vertical_scroll: stats.total_vertical_scroll,
+            total_horizontal_scroll: stats.total_horizontal_scroll,
+            active_typing_ms: stats.active_typing_duration.as_millis() as u64,
+            click_interval_sum_ms: stats.click_interval_sum.as_millis() as u64,
+            click_interval_count: stats.click_interval_count,
+            collection_ms: stats.collection_duration().as_millis() as u64,
+        }
+    }
+}
+
+impl SavedStatistics {
+    fn into_statistics(self) -> EventStatistics {
+        let mut stats = EventStatistics::new();
+        stats.total_event_count = self.total_event_count;
+        stats.key_press_count = self.key_press_count;
+        stats.key_release_count = self.key_release_count;
+        stats.mouse_press_count = self.mouse_press_count;
+        stats.mouse_release_count = self.mouse_release_count;
+        stats.mouse_click_count = self.mouse_click_count;
+        stats.mouse_move_count = self.mouse_move_count;
+        stats.mouse_drag_count = self.mouse_drag_count;
+        stats.mouse_wheel_count = self.mouse_wheel_count;
+        stats.ime_composition_count = self.ime_composition_count;
+        stats.injected_event_count = self.injected_event_count;
+        stats.physical_event_count = self.physical_event_count;
+        stats.key_frequency = self.key_frequency.into_iter().collect();
+        stats.char_frequency = self.char_frequency.into_iter().collect();
+        stats.words_typed = self.words_typed;
+        stats.total_mouse_distance = self.total_mouse_distance;
+        stats.current_mouse_position = self.current_mouse_position;
+        stats.drag_distance = self.drag_distance.into_iter().collect();
+        stats.button_clicks = self.button_clicks.into_iter().collect();
+        stats.total_vertical_scroll = self.total_vertical_scroll;
+        stats.total_horizontal_scroll = self.total_horizontal_scroll;
+        stats.active_typing_duration = Duration::from_millis(self.active_typing_ms);
+        stats.click_interval_sum = Duration::from_millis(self.click_interval_sum_ms);
+        stats.click_interval_count = self.click_interval_count;
+        if self.click_interval_count > 0 {
+            stats.avg_click_interval =
+                Some(stats.click_interval_sum / self.click_interval_count as u32);
+        }
+
+        // Backdate the start so collection_duration() keeps counting from
+        // where it left off.
+        let now = Instant::now();
+        stats.start_time = Some(
+            now.checked_sub(Duration::from_millis(self.collection_ms))
+                .unwrap_or(now),
+        );
+        stats
+    }
+}
+
+impl SavedConfig {
+    pub(super) fn new(sampler: &MotionSampler, autosave_interval: Option<Duration>) -> Self {
+        Self {
+            motion_sample_interval: sampler.interval,
+            motion_target_latency_ms: sampler.target_latency.map(|d| d.as_millis() as u64),
+            autosave_interval_ms: autosave_interval.map(|d| d.as_millis() as u64),
+        }
+    }
+
+    pub(super) fn sampler(&self) -> MotionSampler {
+        MotionSampler::new(
+            self.motion_sample_interval,
+            self.motion_target_latency_ms.map(Duration::from_millis),
+        )
+    }
+
+    pub(super) fn autosave_interval(&self) -> Option<Duration> {
+        self.autosave_interval_ms.map(Duration::from_millis)
+    }
+}
+
+/// Write `stats` and `config` to `path`, replacing it atomically.
+pub(super) fn save(path: &Path, stats: &EventStatistics, config: SavedConfig) -> Result<()> {
+    let file = StateFile {
+        version: STATE_VERSION,
+        config,
+        stats: SavedStatistics::from(stats),
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| Error::Other(format!("Failed to serialize statistics state: {}", e)))?;
+
+    // Write next to the target first so a crash never leaves a torn file
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, json)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|e| Error::Other(format!("Failed to write statistics state file: {}", e)))
+}
+
+/// Read a state file, or `None` if there is none at `path`.
+pub(super) fn load(path: &Path) -> Result<Option<(EventStatistics, SavedConfig)>> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(Error::Other(format!(
+                "Failed to read statistics state file: {}",
+                e
+            )));
+        }
+    };
+    let file: StateFile = serde_json::from_str(&json)
+        .map_err(|e| Error::Other(format!("Failed to deserialize statistics state: {}", e)))?;
+    if file.version > STATE_VERSION {
+        return Err(Error::Other(format!(
+            "Statistics state file version {} is newer than supported version {}",
+            file.version, STATE_VERSION
+        )));
+    }
+    Ok(Some((file.stats.into_statistics(), file.config)))
+}
+
+/// Periodically saves a collector's statistics to a state file.
+pub(super) struct Autosaver {
+    path: PathBuf,
+    interval: Duration,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Autosaver {
+    pub(super) fn new(path: PathBuf, interval: Duration) -> Self {
+        Self {
+            path,
+            interval,
+            stop: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(super) fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Start the background save thread.
+    pub(super) fn start(&mut self, stats: Arc<Mutex<EventStatistics>>, sampler: &MotionSampler) {
+        self.stop.store(false, Ordering::SeqCst);
+        let stop = self.stop.clone();
+        let path = self.path.clone();
+        let interval = self.interval;
+        let sampler = sampler.clone();
+
+        self.thread = Some(std::thread::spawn(move || {
+            let mut last_save = Instant::now();
+            while !stop.load(Ordering::SeqCst) {
+                std::thread::sleep(interval.min(Duration::from_millis(100)));
+                if last_save.elapsed() >= interval {
+                    let snapshot = match stats.lock() {
+                        Ok(s) => s.clone(),
+                        Err(_) => return,
+                    };
+                    let config = SavedConfig::new(&sampler, Some(interval));
+                    if let Err(e) = save(&path, &snapshot, config) {
+                        log::warn!("Failed to autosave statistics: {}", e);
+                    }
+                    last_save = Instant::now();
+                }
+            }
+        }));
+    }
+
+    /// Stop the save thread. The caller writes the final state.
+    pub(super) fn stop(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.thread.take() {
+            handle
+                .join()
+                .map_err(|_| Error::ThreadError("failed to join autosave thread".into()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+
+    fn temp_state(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("monio_test_{}.json", name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let path = temp_state("state_roundtrip");
+        let mut stats = EventStatistics::new();
+        stats.start_time = Some(Instant::now());
+        stats.record_event(&Event::key_pressed(Key::KeyA, 30));
+        stats.record_event(&Event::key_typed(Key::KeyA, 30, 'a'));
+        stats.record_event(&Event::key_pressed(Key::Unknown(300), 300));
+        let mut drag = Event::mouse_dragged(3.0, 4.0);
+        drag.mouse.as_mut().unwrap().button = Some(Button::Left);
+        stats.record_event(&drag);
+
+        let sampler = MotionSampler::new(4, Some(Duration::from_millis(20)));
+        let config = SavedConfig::new(&sampler, Some(Duration::from_secs(30)));
+        save(&path, &stats, config).unwrap();
+
+        let (loaded, config) = load(&path).unwrap().unwrap();
+        assert_eq!(loaded.total_event_count, 4);
+        assert_eq!(loaded.key_frequency.get(&Key::KeyA), Some(&1));
+        assert_eq!(loaded.key_frequency.get(&Key::Unknown(300)), Some(&1));
+        assert_eq!(loaded.char_frequency.get(&'a'), Some(&1));
+        assert_eq!(loaded.drag_distance.get(&Button::Left), Some(&5.0));
+        assert_eq!(loaded.current_mouse_position, (3.0, 4.0));
+        assert!(loaded.start_time.is_some());
+        assert_eq!(config.sampler().interval, 4);
+        assert_eq!(
+            config.sampler().target_latency,
+            Some(Duration::from_millis(20))
+        );
+        assert_eq!(config.autosave_interval(), Some(Duration::from_secs(30)));
+
+        // Transient state doesn't survive, so typing time doesn't bridge the gap
+        assert!(loaded.last_key_time.is_none());
+        assert!(loaded.first_key_time.is_none());
+        assert!(!loaded.in_word);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_missing_and_partial_files() {
+        let path = temp_state("state_partial");
+        assert!(load(&path).unwrap().is_none());
+
+        std::fs::write(&path, r#"{"version": 1, "stats": {"key_press_count": 7}}"#).unwrap();
+        let (loaded, config) = load(&path).unwrap().unwrap();
+        assert_eq!(loaded.key_press_count, 7);
+        assert_eq!(loaded.total_event_count, 0);
+        assert_eq!(config.sampler().interval, 1);
+        assert_eq!(config.autosave_interval(), None);
+
+        std::fs::write(&path, r#"{"version": 99}"#).unwrap();
+        assert!(load(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,615 @@
+//! SQLite persistence for input statistics.
+//!
+//! A [`SqliteStore`] receives periodic deltas from a running
+//! [`StatisticsCollector`](super::StatisticsCollector) and can aggregate them
+//! back into an [`EventStatistics`] for any time range.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::statistics::StatisticsCollector;
+//! use monio::statistics::store::SqliteStore;
+//! use std::time::{Duration, SystemTime};
+//!
+//! let store = SqliteStore::open("stats.db").unwrap();
+//! let mut collector = StatisticsCollector::with_store(store, Duration::from_secs(30));
+//! collector.start().unwrap();
+//!
+//! // ... later, possibly after a restart ...
+//!
+//! let store = SqliteStore::open("stats.db").unwrap();
+//! let day_ago = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+//! let stats = store.load_range(day_ago, SystemTime::now()).unwrap();
+//! println!("Key presses today: {}", stats.key_press_count);
+//! ```
+
+use super::EventStatistics;
+use crate::error::{Error, Result};
+use crate::event::Button;
+use crate::keycode::Key;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Current schema version, stored in `PRAGMA user_version`.
+const SCHEMA_VERSION: i64 = 3;
+
+/// Migrations, indexed by the version they upgrade *from*.
+const MIGRATIONS: &[&str] = &[
+    // 0 -> 1: initial schema
+    "CREATE TABLE sessions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        started_at INTEGER NOT NULL,
+        ended_at INTEGER
+    );
+    CREATE TABLE deltas (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id INTEGER NOT NULL REFERENCES sessions(id),
+        flushed_at INTEGER NOT NULL,
+        total_event_count INTEGER NOT NULL,
+        key_press_count INTEGER NOT NULL,
+        key_release_count INTEGER NOT NULL,
+        mouse_press_count INTEGER NOT NULL,
+        mouse_release_count INTEGER NOT NULL,
+        mouse_click_count INTEGER NOT NULL,
+        mouse_move_count INTEGER NOT NULL,
+        mouse_drag_count INTEGER NOT NULL,
+        mouse_wheel_count INTEGER NOT NULL,
+        words_typed INTEGER NOT NULL,
+        total_mouse_distance REAL NOT NULL,
+        total_vertical_scroll REAL NOT NULL,
+        total_horizontal_scroll REAL NOT NULL,
+        active_typing_ms INTEGER NOT NULL
+    );
+    CREATE INDEX deltas_flushed_at ON deltas(flushed_at);
+    CREATE TABLE key_frequency (
+        delta_id INTEGER NOT NULL REFERENCES deltas(id),
+        key TEXT NOT NULL,
+        count INTEGER NOT NULL
+    );
+    CREATE TABLE char_frequency (
+        delta_id INTEGER NOT NULL REFERENCES deltas(id),
+        ch TEXT NOT NULL,
+        count INTEGER NOT NULL
+    );
+    CREATE TABLE button_clicks (
+        delta_id INTEGER NOT NULL REFERENCES deltas(id),
+        button INTEGER NOT NULL,
+        count INTEGER NOT NULL
+    );",
+    // 1 -> 2: per-button drag distance
+    "CREATE TABLE button_drag_distance (
+        delta_id INTEGER NOT NULL REFERENCES deltas(id),
+        button INTEGER NOT NULL,
+        distance REAL NOT NULL
+    );",
+    // 2 -> 3: injected/physical split
+    "ALTER TABLE deltas ADD COLUMN injected_event_count INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE deltas ADD COLUMN physical_event_count INTEGER NOT NULL DEFAULT 0;",
+];
+
+/// Statistics storage backed by a SQLite database.
+pub struct SqliteStore {
+    conn: Connection,
+    session_id: Option<i64>,
+}
+
+impl SqliteStore {
+    /// Open (or create) a store at the given path, migrating the schema if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| Error::Other(format!("Failed to open statistics database: {}", e)))?;
+        Self::from_connection(conn)
+    }
+
+    /// Open a transient in-memory store.
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory()
+            .map_err(|e| Error::Other(format!("Failed to open statistics database: {}", e)))?;
+        Self::from_connection(conn)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self> {
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn,
+            session_id: None,
+        })
+    }
+
+    /// Get the schema version of the underlying database.
+    pub fn schema_version(&self) -> Result<i64> {
+        self.conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(sql_err)
+    }
+
+    /// Start a new session row. Subsequent deltas are attributed to it.
+    pub fn begin_session(&mut self) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO sessions (started_at) VALUES (?1)",
+                params![to_millis(SystemTime::now())],
+            )
+            .map_err(sql_err)?;
+        self.session_id = Some(self.conn.last_insert_rowid());
+        Ok(())
+    }
+
+    /// Mark the current session as ended.
+    pub fn end_session(&mut self) -> Result<()> {
+        if let Some(id) = self.session_id.take() {
+            self.conn
+                .execute(
+                    "UPDATE sessions SET ended_at = ?1 WHERE id = ?2",
+                    params![to_millis(SystemTime::now()), id],
+                )
+                .map_err(sql_err)?;
+        }
+        Ok(())
+    }
+
+    /// Write a statistics delta for the current session.
+    ///
+    /// Starts a session implicitly if none is active.
+    pub fn write_delta(&mut self, delta: &EventStatistics) -> Result<()> {
+        if self.session_id.is_none() {
+            self.begin_session()?;
+        }
+        let session_id = self.session_id.unwrap_or_default();
+
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        tx.execute(
+            "INSERT INTO deltas (
+                session_id, flushed_at, total_event_count, key_press_count,
+                key_release_count, mouse_press_count, mouse_release_count,
+                mouse_click_count, mouse_move_count, mouse_drag_count,
+                mouse_wheel_count, words_typed, total_mouse_distance,
+                total_vertical_scroll, total_horizontal_scroll, active_typing_ms,
+                injected_event_count, physical_event_count
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                ?17, ?18)",
+            params![
+                session_id,
+                to_millis(SystemTime::now()),
+                delta.total_event_count as i64,
+                delta.key_press_count as i64,
+                delta.key_release_count as i64,
+                delta.mouse_press_count as i64,
+                delta.mouse_release_count as i64,
+                delta.mouse_click_count as i64,
+                delta.mouse_move_count as i64,
+                delta.mouse_drag_count as i64,
+                delta.mouse_wheel_count as i64,
+                delta.words_typed as i64,
+                delta.total_mouse_distance,
+                delta.total_vertical_scroll,
+                delta.total_horizontal_scroll,
+                delta.active_typing_duration.as_millis() as i64,
+                delta.injected_event_count as i64,
+                delta.physical_event_count as i64,
+            ],
+        )
+        .map_err(sql_err)?;
+        let delta_id = tx.last_insert_rowid();
+
+        for (key, count) in &delta.key_frequency {
+            tx.execute(
+                "INSERT INTO key_frequency (delta_id, key, count) VALUES (?1, ?2, ?3)",
+                params![delta_id, key_to_text(key)?, *count as i64],
+            )
+            .map_err(sql_err)?;
+        }
+        for (ch, count) in &delta.char_frequency {
+            tx.execute(
+                "INSERT INTO char_frequency (delta_id, ch, count) VALUES (?1, ?2, ?3)",
+                params![delta_id, ch.to_string(), *count as i64],
+            )
+            .map_err(sql_err)?;
+        }
+        for (button, count) in &delta.button_clicks {
+            tx.execute(
+                "INSERT INTO button_clicks (delta_id, button, count) VALUES (?1, ?2, ?3)",
+                params![delta_id, button.number(), *count as i64],
+            )
+            .map_err(sql_err)?;
+        }
+        for (button, distance) in &delta.drag_distance {
+            tx.execute(
+                "INSERT INTO button_drag_distance (delta_id, button, distance) VALUES (?1, ?2, ?3)",
+                params![delta_id, button.number(), distance],
+            )
+            .map_err(sql_err)?;
+        }
+
+        tx.commit().map_err(sql_err)
+    }
+
+    /// Aggregate all deltas flushed within `[from, to]` into one statistics object.
+    ///
+    /// Timing fields that only make sense for a live session (`start_time`,
+    /// `last_key_time`, ...) are left unset.
+    pub fn load_range(&self, from: SystemTime, to: SystemTime) -> Result<EventStatistics> {
+        let (from, to) = (to_millis(from), to_millis(to));
+        let mut stats = EventStatistics::new();
+
+        let totals = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*), SUM(total_event_count), SUM(key_press_count),
+                    SUM(key_release_count), SUM(mouse_press_count), SUM(mouse_release_count),
+                    SUM(mouse_click_count), SUM(mouse_move_count), SUM(mouse_drag_count),
+                    SUM(mouse_wheel_count), SUM(words_typed), SUM(total_mouse_distance),
+                    SUM(total_vertical_scroll), SUM(total_horizontal_scroll),
+                    SUM(active_typing_ms), SUM(injected_event_count),
+                    SUM(physical_event_count)
+                 FROM deltas WHERE flushed_at BETWEEN ?1 AND ?2",
+                params![from, to],
+                |row| {
+                    let count: i64 = row.get(0)?;
+                    if count == 0 {
+                        return Ok(None);
+                    }
+                    let int = |i| row.get::<_, i64>(i).map(|v| v as u64);
+                    Ok(Some((
+                        [
+                            int(1)?,
+                            int(2)?,
+                            int(3)?,
+                            int(4)?,
+                            int(5)?,
+                            int(6)?,
+                            int(7)?,
+                            int(8)?,
+                            int(9)?,
+                            int(10)?,
+                        ],
+                        [row.get::<_, f64>(11)?, row.get(12)?, row.get(13)?],
+                        int(14)?,
+                        [int(15)?, int(16)?],
+                    )))
+                },
+            )
+            .optional()
+            .map_err(sql_err)?
+            .flatten();
+
+        let Some((counts, floats, typing_ms, origins)) = totals else {
+            return Ok(stats);
+        };
+
+        stats.total_event_count = counts[0];
+        stats.key_press_count = counts[1];
+        stats.key_release_count = counts[2];
+        stats.mouse_press_count = counts[3];
+        stats.mouse_release_count = counts[4];
+        stats.mouse_click_count = counts[5];
+        stats.mouse_move_count = counts[6];
+        stats.mouse_drag_count = counts[7];
+        stats.mouse_wheel_count = counts[8];
+        stats.words_typed = counts[9];
+        stats.total_mouse_distance = floats[0];
+        stats.total_vertical_scroll = floats[1];
+        stats.total_horizontal_scroll = floats[2];
+        stats.active_typing_duration = Duration::from_millis(typing_ms);
+        stats.injected_event_count = origins[0];
+        stats.physical_event_count = origins[1];
+
+        for (key, count) in self.load_frequencies::<String>(
+            "SELECT f.key, SUM(f.count) FROM key_frequency f
+             JOIN deltas d ON d.id = f.delta_id
+             WHERE d.flushed_at BETWEEN ?1 AND ?2 GROUP BY f.key",
+            from,
+            to,
+        )? {
+            stats.key_frequency.insert(text_to_key(&key)?, count);
+        }
+
+        for (ch, count) in self.load_frequencies::<String>(
+            "SELECT f.ch, SUM(f.count) FROM char_frequency f
+             JOIN deltas d ON d.id = f.delta_id
+             WHERE d.flushed_at BETWEEN ?1 AND ?2 GROUP BY f.ch",
+            from,
+            to,
+        )? {
+            if let Some(ch) = ch.chars().next() {
+                stats.char_frequency.insert(ch, count);
+            }
+        }
+
+        for (button, count) in self.load_frequencies::<u8>(
+            "SELECT f.button, SUM(f.count) FROM button_clicks f
+             JOIN deltas d ON d.id = f.delta_id
+             WHERE d.flushed_at BETWEEN ?1 AND ?2 GROUP BY f.button",
+            from,
+            to,
+        )? {
+            stats
+                .button_clicks
+                .insert(Button::from_number(button), count);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT f.button, SUM(f.distance) FROM button_drag_distance f
+                 JOIN deltas d ON d.id = f.delta_id
+                 WHERE d.flushed_at BETWEEN ?1 AND ?2 GROUP BY f.button",
+            )
+            .map_err(sql_err)?;
+        let drags = stmt
+            .query_map(params![from, to], |row| {
+                Ok((row.get::<_, u8>(0)?, row.get::<_, f64>(1)?))
+            })
+            .map_err(sql_err)?;
+        for drag in drags {
+            let (button, distance) = drag.map_err(sql_err)?;
+            stats
+                .drag_distance
+                .insert(Button::from_number(button), distance);
+        }
+
+        Ok(stats)
+    }
+
+    fn load_frequencies<T: rusqlite::types::FromSql>(
+        &self,
+        sql: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<(T, u64)>> {
+        let mut stmt = self.conn.prepare(sql).map_err(sql_err)?;
+        let rows = stmt
+            .query_map(params![from, to], |row| {
+                Ok((row.get::<_, T>(0)?, row.get::<_, i64>(1)? as u64))
+            })
+            .map_err(sql_err)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(sql_err)
+    }
+}
+
+/// Periodically flushes accumulated deltas from a collector into a store.
+///
+/// The hook callback only records into `pending`; all database work happens
+/// on the flush thread so the hook path never blocks on I/O.
+pub(super) struct StoreFlusher {
+    store: Arc<Mutex<SqliteStore>>,
+    pending: Arc<Mutex<EventStatistics>>,
+    interval: Duration,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StoreFlusher {
+    pub(super) fn new(store: SqliteStore, interval: Duration) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(store)),
+            pending: Arc::new(Mutex::new(EventStatistics::new())),
+            interval,
+            stop: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+
+    /// Statistics accumulated since the last flush.
+    pub(super) fn pending(&self) -> Arc<Mutex<EventStatistics>> {
+        self.pending.clone()
+    }
+
+    /// Begin a session and start the background flush thread.
+    pub(super) fn start(&mut self) -> Result<()> {
+        self.store
+            .lock()
+            .map_err(|_| Error::ThreadError("store mutex poisoned".into()))?
+            .begin_session()?;
+
+        self.stop.store(false, Ordering::SeqCst);
+        let store = self.store.clone();
+        let pending = self.pending.clone();
+        let stop = self.stop.clone();
+        let interval = self.interval;
+
+        self.thread = Some(std::thread::spawn(move || {
+            let mut last_flush = Instant::now();
+            while !stop.load(Ordering::SeqCst) {
+                std::thread::sleep(interval.min(Duration::from_millis(100)));
+                if last_flush.elapsed() >= interval {
+                    if let Err(e) = flush(&store, &pending) {
+                        log::warn!("Failed to flush statistics: {}", e);
+                    }
+                    last_flush = Instant::now();
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    /// Stop the flush thread, write any remaining delta, and end the session.
+    pub(super) fn stop(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.thread.take() {
+            handle
+                .join()
+                .map_err(|_| Error::ThreadError("failed to join flush thread".into()))?;
+        }
+
+        flush(&self.store, &self.pending)?;
+        self.store
+            .lock()
+            .map_err(|_| Error::ThreadError("store mutex poisoned".into()))?
+            .end_session()
+    }
+}
+
+/// Take the pending delta and write it to the store.
+fn flush(store: &Mutex<SqliteStore>, pending: &Mutex<EventStatistics>) -> Result<()> {
+    let delta = {
+        let mut pending = pending
+            .lock()
+            .map_err(|_| Error::ThreadError("statistics mutex poisoned".into()))?;
+        if pending.total_event_count == 0 {
+            return Ok(());
+        }
+
+        // Carry over continuity state so the next delta doesn't count a jump
+        // from the origin or split a word in two.
+        let mut next = EventStatistics::new();
+        next.current_mouse_position = pending.current_mouse_position;
+        next.in_word = pending.in_word;
+        next.last_key_time = pending.last_key_time;
+        std::mem::replace(&mut *pending, next)
+    };
+
+    store
+        .lock()
+        .map_err(|_| Error::ThreadError("store mutex poisoned".into()))?
+        .write_delta(&delta)
+}
+
+/// Bring the database schema up to [`SCHEMA_VERSION`].
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(sql_err)?;
+
+    if version > SCHEMA_VERSION {
+        return Err(Error::Other(format!(
+            "Statistics database schema version {} is newer than supported version {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+
+    for (from, sql) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.transaction().map_err(sql_err)?;
+        tx.execute_batch(sql).map_err(sql_err)?;
+        tx.pragma_update(None, "user_version", from as i64 + 1)
+            .map_err(sql_err)?;
+        tx.commit().map_err(sql_err)?;
+    }
+
+    Ok(())
+}
+
+fn sql_err(e: rusqlite::Error) -> Error {
+    Error::Other(format!("Statistics database error: {}", e))
+}
+
+fn to_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn key_to_text(key: &Key) -> Result<String> {
+    serde_json::to_string(key).map_err(|e| Error::Other(format!("Failed to serialize key: {}", e)))
+}
+
+fn text_to_key(text: &str) -> Result<Key> {
+    serde_json::from_str(text)
+        .map_err(|e| Error::Other(format!("Failed to deserialize key: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+
+    fn temp_db(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("monio_test_{}.db", name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_migration_is_idempotent() {
+        let path = temp_db("store_migration");
+        {
+            let store = SqliteStore::open(&path).unwrap();
+            assert_eq!(store.schema_version().unwrap(), SCHEMA_VERSION);
+        }
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.schema_version().unwrap(), SCHEMA_VERSION);
+
+        drop(store);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_and_load_range() {
+        let path = temp_db("store_roundtrip");
+        let start = SystemTime::now() - Duration::from_secs(1);
+
+        {
+            let mut store = SqliteStore::open(&path).unwrap();
+            let mut delta = EventStatistics::new();
+            let mut physical = Event::key_pressed(Key::KeyA, 30);
+            physical.injected = Some(false);
+            delta.record_event(&physical);
+            delta.record_event(&Event::key_pressed(Key::Unknown(300), 300));
+            delta.record_event(&Event::key_typed(Key::KeyA, 30, 'a'));
+            delta.record_event(&Event::mouse_pressed(Button::Left, 0.0, 0.0));
+            let mut drag = Event::mouse_dragged(3.0, 4.0);
+            drag.mouse.as_mut().unwrap().button = Some(Button::Left);
+            delta.record_event(&drag);
+            store.write_delta(&delta).unwrap();
+
+            let mut delta = EventStatistics::new();
+            delta.record_event(&Event::key_pressed(Key::KeyA, 30));
+            store.write_delta(&delta).unwrap();
+            store.end_session().unwrap();
+        }
+
+        // Reload from a fresh connection, as after a restart
+        let store = SqliteStore::open(&path).unwrap();
+        let stats = store
+            .load_range(start, SystemTime::now() + Duration::from_secs(1))
+            .unwrap();
+
+        assert_eq!(stats.total_event_count, 6);
+        assert_eq!(stats.key_press_count, 3);
+        assert_eq!(stats.physical_event_count, 1);
+        assert_eq!(stats.injected_event_count, 0);
+        assert_eq!(stats.key_frequency.get(&Key::KeyA), Some(&2));
+        assert_eq!(stats.key_frequency.get(&Key::Unknown(300)), Some(&1));
+        assert_eq!(stats.char_frequency.get(&'a'), Some(&1));
+        assert_eq!(stats.button_clicks.get(&Button::Left), Some(&1));
+        assert_eq!(stats.drag_distance.get(&Button::Left), Some(&5.0));
+
+        let empty = store
+            .load_range(UNIX_EPOCH, UNIX_EPOCH + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(empty.total_events(), 0);
+
+        drop(store);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_flush_carries_mouse_position() {
+        let store = Mutex::new(SqliteStore::open_in_memory().unwrap());
+        let pending = Mutex::new(EventStatistics::new());
+
+        pending
+            .lock()
+            .unwrap()
+            .record_event(&Event::mouse_moved(3.0, 4.0));
+        flush(&store, &pending).unwrap();
+        assert_eq!(pending.lock().unwrap().total_event_count, 0);
+
+        pending
+            .lock()
+            .unwrap()
+            .record_event(&Event::mouse_moved(6.0, 8.0));
+        flush(&store, &pending).unwrap();
+
+        let stats = store
+            .lock()
+            .unwrap()
+            .load_range(UNIX_EPOCH, SystemTime::now() + Duration::from_secs(1))
+            .unwrap();
+        assert!((stats.total_mouse_distance - 10.0).abs() < 0.001);
+    }
+}
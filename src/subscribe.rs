@@ -0,0 +1,679 @@
+//! Callback subscriptions sharing one hook.
+//!
+//! [`Hook::subscribe`](crate::Hook::subscribe) and the typed helpers built on
+//! it (`on_key_pressed`, `on_any_key`, `on_click`, `on_wheel`) register
+//! callbacks with the hook. Every event the hook dispatches, in any mode, is
+//! offered to all subscriptions before the hook's own handler, so any number
+//! of subscriptions share the single OS hook. Dropping the returned
+//! [`Subscription`] unsubscribes.
+
+use crate::display::{DisplayRegion, DisplayTracker};
+use crate::drag::PointerTracker;
+use crate::event::{Event, EventType};
+use crate::hook::{EventHandler, EventMask, GrabHandler};
+use crate::ime::ImeTracker;
+use crate::latency::LatencyMonitor;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Instant;
+
+type Callback = Arc<dyn Fn(&Event) + Send + Sync>;
+type Entries = RwLock<Vec<(u64, Callback)>>;
+
+/// The callbacks subscribed to a hook.
+#[derive(Clone, Default)]
+pub(crate) struct Subscribers {
+    entries: Arc<Entries>,
+}
+
+impl Subscribers {
+    pub(crate) fn add(&self, callback: Callback) -> Subscription {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut entries) = self.entries.write() {
+            entries.push((id, callback));
+        }
+        Subscription {
+            id,
+            entries: Arc::downgrade(&self.entries),
+        }
+    }
+
+    fn dispatch(&self, event: &Event) {
+        // Snapshot first, so callbacks may subscribe or unsubscribe
+        let callbacks: Vec<Callback> = match self.entries.read() {
+            Ok(entries) if !entries.is_empty() => {
+                entries.iter().map(|(_, cb)| cb.clone()).collect()
+            }
+            _ => return,
+        };
+        for callback in callbacks {
+            callback(event);
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries
+            .read()
+            .map(|entries| entries.len())
+            .unwrap_or(0)
+    }
+}
+
+/// Handle to a subscribed callback; dropping it unsubscribes.
+#[must_use = "dropping a Subscription unsubscribes its callback"]
+pub struct Subscription {
+    id: u64,
+    entries: Weak<Entries>,
+}
+
+impl Subscription {
+    /// Keep the callback subscribed for the lifetime of the hook.
+    pub fn detach(mut self) {
+        self.entries = Weak::new();
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(entries) = self.entries.upgrade()
+            && let Ok(mut entries) = entries.write()
+        {
+            entries.retain(|(id, _)| *id != self.id);
+        }
+    }
+}
+
+impl std::fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/// Drops mouse moves closer than a minimum distance to the last one reported.
+pub(crate) struct MotionFilter {
+    min_distance: f64,
+    last_reported: Option<(f64, f64)>,
+    /// The latest move that was dropped, delivered before the next button
+    /// or wheel event so it isn't reported at a stale position.
+    pending: Option<Event>,
+}
+
+impl MotionFilter {
+    pub(crate) fn new(min_distance: f64) -> Self {
+        Self {
+            min_distance,
+            last_reported: None,
+            pending: None,
+        }
+    }
+
+    /// Whether to deliver `event`, pushing any move to flush first to `out`.
+    fn filter(&mut self, event: &Event, out: &mut Vec<Event>) -> bool {
+        match event.event_type {
+            EventType::MouseMoved | EventType::MouseDragged => {
+                let Some(mouse) = &event.mouse else {
+                    return true;
+                };
+                let far_enough = self
+                    .last_reported
+                    .is_none_or(|(x, y)| (mouse.x - x).hypot(mouse.y - y) >= self.min_distance);
+                if far_enough {
+                    self.last_reported = Some((mouse.x, mouse.y));
+                    self.pending = None;
+                } else {
+                    self.pending = Some(event.clone());
+                }
+                far_enough
+            }
+            EventType::MousePressed
+            | EventType::MouseReleased
+            | EventType::MouseClicked
+            | EventType::MouseWheel => {
+                if let Some(pending) = self.pending.take() {
+                    self.last_reported = pending.mouse.as_ref().map(|m| (m.x, m.y));
+                    out.push(pending);
+                }
+                true
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Handler wrapper that offers each event to the subscribers first.
+pub(crate) struct Dispatcher<H> {
+    pub(crate) subscribers: Subscribers,
+    /// Pass software-generated events on without dispatching them.
+    pub(crate) ignore_injected: bool,
+    /// Only dispatches events in these categories.
+    pub(crate) events: EventMask,
+    /// Reports [`EventType::DisplayChanged`] when set.
+    pub(crate) displays: Option<Mutex<DisplayTracker>>,
+    /// Only dispatches events on one display when set.
+    pub(crate) region: Option<Mutex<DisplayRegion>>,
+    /// Marks key events with the input method state, and reports its
+    /// changes, when set.
+    pub(crate) ime: Option<Mutex<ImeTracker>>,
+    /// Reports drags only past the drag threshold, and clicks, when set.
+    pub(crate) pointer: Option<Mutex<PointerTracker>>,
+    /// Thins out mouse moves when set.
+    pub(crate) motion: Option<Mutex<MotionFilter>>,
+    /// Sets and keeps track of [`Event::queue_latency`].
+    pub(crate) latency: Arc<LatencyMonitor>,
+    pub(crate) handler: H,
+}
+
+impl<H> Dispatcher<H> {
+    fn ignores(&self, event: &Event) -> bool {
+        self.ignore_injected && event.injected == Some(true)
+    }
+
+    /// The display change caused by a mouse move, if any.
+    fn display_change(&self, event: &Event) -> Option<Event> {
+        if !matches!(
+            event.event_type,
+            EventType::MouseMoved | EventType::MouseDragged
+        ) {
+            return None;
+        }
+        let mouse = event.mouse.as_ref()?;
+        let mut tracker = self.displays.as_ref()?.lock().ok()?;
+        let (from, to) = tracker.update(mouse.x, mouse.y, Instant::now())?;
+
+        let mut change = Event::display_changed(from, to, mouse.x, mouse.y);
+        change.injected = event.injected;
+        Some(change)
+    }
+
+    /// What to dispatch for `event`, leaving out events not in
+    /// [`events`](Self::events).
+    fn prepare<'a>(&self, event: &'a Event) -> Prepared<'a> {
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        before.extend(self.display_change(event));
+        let event = match &self.ime {
+            Some(ime) => match ime.lock() {
+                Ok(mut ime) => ime
+                    .observe(event, Instant::now(), &mut before)
+                    .map_or(Cow::Borrowed(event), Cow::Owned),
+                Err(_) => Cow::Borrowed(event),
+            },
+            None => Cow::Borrowed(event),
+        };
+        let event = match &self.pointer {
+            Some(pointer) => match pointer.lock() {
+                Ok(mut pointer) => pointer
+                    .track(&event, &mut before, &mut after)
+                    .map_or(event, Cow::Owned),
+                Err(_) => event,
+            },
+            None => event,
+        };
+        let on_display = match &self.region {
+            Some(region) => match region.lock() {
+                Ok(mut region) => region.contains(&event, Instant::now()),
+                Err(_) => true,
+            },
+            None => true,
+        };
+        let deliver = on_display
+            && match &self.motion {
+                Some(motion) => match motion.lock() {
+                    Ok(mut motion) => motion.filter(&event, &mut before),
+                    Err(_) => true,
+                },
+                None => true,
+            };
+        if !on_display {
+            // Clicks follow their release off the display
+            after.clear();
+        }
+        before.retain(|early| self.events.includes(early.event_type));
+        after.retain(|late| self.events.includes(late.event_type));
+        Prepared {
+            before,
+            deliver: deliver && self.events.includes(event.event_type),
+            event,
+            after,
+        }
+    }
+}
+
+/// What a [`Dispatcher`] dispatches for an event.
+///
+/// The OS has already seen (or will see) the events before and after on
+/// their own, so they are informational in grab mode.
+struct Prepared<'a> {
+    /// Events to dispatch ahead of the event.
+    before: Vec<Event>,
+    /// The event to dispatch in its place.
+    event: Cow<'a, Event>,
+    /// Whether to dispatch it.
+    deliver: bool,
+    /// Events to dispatch after it, if it is dispatched.
+    after: Vec<Event>,
+}
+
+impl<H: EventHandler> EventHandler for Dispatcher<H> {
+    fn handle_event(&self, event: &Event) {
+        if self.ignores(event) {
+            return;
+        }
+        let Prepared {
+            before,
+            event,
+            deliver,
+            after,
+        } = self.prepare(event);
+        let after = if deliver { after } else { Vec::new() };
+        for event in before
+            .iter()
+            .chain(deliver.then_some(&*event))
+            .chain(&after)
+        {
+            let event = self.latency.stamp(event);
+            self.subscribers.dispatch(&event);
+            self.handler.handle_event(&event);
+        }
+    }
+}
+
+impl<H: GrabHandler> GrabHandler for Dispatcher<H> {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        if self.ignores(event) {
+            return Some(event.clone());
+        }
+        let Prepared {
+            before,
+            event,
+            deliver,
+            after,
+        } = self.prepare(event);
+        self.inform(&before);
+        if !deliver {
+            return Some(event.into_owned());
+        }
+        let event = self.latency.stamp(&event);
+        self.subscribers.dispatch(&event);
+        let result = self.handler.handle_event(&event);
+        self.inform(&after);
+        result
+    }
+}
+
+impl<H: GrabHandler> Dispatcher<H> {
+    /// Dispatch events derived from OS events, which have no OS event of
+    /// their own to pass on or consume.
+    fn inform(&self, events: &[Event]) {
+        for event in events {
+            let event = self.latency.stamp(event);
+            self.subscribers.dispatch(&event);
+            let _ = self.handler.handle_event(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{Button, EventType, ScrollDirection};
+    use crate::filter::EventFilter;
+    use crate::hook::Hook;
+    use crate::keycode::Key;
+    use crate::state::{MASK_CTRL, Modifiers};
+
+    #[test]
+    fn test_drop_unsubscribes() {
+        let subscribers = Subscribers::default();
+        let count = Arc::new(AtomicU64::new(0));
+
+        let counter = count.clone();
+        let first = subscribers.add(Arc::new(move |_: &Event| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+        let counter = count.clone();
+        subscribers
+            .add(Arc::new(move |_: &Event| {
+                counter.fetch_add(10, Ordering::Relaxed);
+            }))
+            .detach();
+
+        let dispatcher = Dispatcher {
+            subscribers: subscribers.clone(),
+            ignore_injected: false,
+            events: EventMask::ALL,
+            displays: None,
+            region: None,
+            ime: None,
+            pointer: None,
+            motion: None,
+            latency: Default::default(),
+            handler: |_: &Event| {},
+        };
+        EventHandler::handle_event(&dispatcher, &Event::key_pressed(Key::KeyA, 0));
+        assert_eq!(count.load(Ordering::Relaxed), 11);
+
+        drop(first);
+        assert_eq!(subscribers.len(), 1);
+        EventHandler::handle_event(&dispatcher, &Event::key_pressed(Key::KeyA, 0));
+        assert_eq!(count.load(Ordering::Relaxed), 21);
+    }
+
+    #[test]
+    fn test_ignore_injected() {
+        let subscribers = Subscribers::default();
+        let count = Arc::new(AtomicU64::new(0));
+        let counter = count.clone();
+        subscribers
+            .add(Arc::new(move |_: &Event| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }))
+            .detach();
+
+        let dispatcher = Dispatcher {
+            subscribers,
+            ignore_injected: true,
+            events: EventMask::ALL,
+            displays: None,
+            region: None,
+            ime: None,
+            pointer: None,
+            motion: None,
+            latency: Default::default(),
+            handler: |_: &Event| None,
+        };
+
+        let mut injected = Event::key_pressed(Key::KeyA, 0);
+        injected.injected = Some(true);
+        // Passed on untouched, not consumed by the handler
+        assert!(GrabHandler::handle_event(&dispatcher, &injected).is_some());
+        assert_eq!(count.load(Ordering::Relaxed), 0);
+
+        // Unknown origin is still dispatched
+        let unknown = Event::key_pressed(Key::KeyA, 0);
+        assert!(GrabHandler::handle_event(&dispatcher, &unknown).is_none());
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_typed_helpers() {
+        let hook = Hook::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let log = seen.clone();
+        let _escape = hook.on_key_pressed(Key::Escape, move || {
+            log.lock().unwrap().push("escape".to_string());
+        });
+        let log = seen.clone();
+        let _any = hook.on_any_key(move |key, modifiers| {
+            log.lock()
+                .unwrap()
+                .push(format!("{key:?} ctrl={}", modifiers.ctrl));
+        });
+        let log = seen.clone();
+        let _click = hook.on_click(Button::Right, move |x, y| {
+            log.lock().unwrap().push(format!("right {x},{y}"));
+        });
+        let log = seen.clone();
+        let filter = EventFilter::parse("mouse:pressed && button == left").unwrap();
+        let _left = hook.on_filter(filter, move |event| {
+            log.lock()
+                .unwrap()
+                .push(format!("filtered {:?}", event.event_type));
+        });
+        let log = seen.clone();
+        let _wheel = hook.on_wheel(move |direction, delta| {
+            log.lock().unwrap().push(format!("{direction:?} {delta}"));
+        });
+
+        let mut ctrl_c = Event::key_pressed(Key::KeyC, 0);
+        ctrl_c.mask = MASK_CTRL;
+        assert!(Modifiers::from_mask(ctrl_c.mask).ctrl);
+        for event in [
+            Event::key_pressed(Key::Escape, 0),
+            Event::key_released(Key::Escape, 0),
+            ctrl_c,
+            Event::mouse_pressed(Button::Left, 1.0, 2.0),
+            Event::mouse_pressed(Button::Right, 3.0, 4.0),
+            Event::mouse_wheel(0.0, 0.0, ScrollDirection::Down, 2.0),
+            Event::new(EventType::HookEnabled),
+        ] {
+            hook.subscribers.dispatch(&event);
+        }
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                "escape",
+                "Escape ctrl=false",
+                "KeyC ctrl=true",
+                "filtered MousePressed",
+                "right 3,4",
+                "Down 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_motion_min_distance_flushes_before_click() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let dispatcher = Dispatcher {
+            subscribers: Subscribers::default(),
+            ignore_injected: false,
+            events: EventMask::ALL,
+            displays: None,
+            region: None,
+            ime: None,
+            pointer: None,
+            motion: Some(Mutex::new(MotionFilter::new(10.0))),
+            latency: Default::default(),
+            handler: move |event: &Event| {
+                let mouse = event.mouse.as_ref().unwrap();
+                log.lock()
+                    .unwrap()
+                    .push((event.event_type, mouse.x, mouse.y));
+            },
+        };
+
+        for x in [0.0, 3.0, 6.0, 12.0, 14.0, 15.0] {
+            EventHandler::handle_event(&dispatcher, &Event::mouse_moved(x, 0.0));
+        }
+        EventHandler::handle_event(&dispatcher, &Event::mouse_pressed(Button::Left, 15.0, 0.0));
+        // Diagonal distance counts
+        EventHandler::handle_event(&dispatcher, &Event::mouse_dragged(21.0, 8.0));
+        EventHandler::handle_event(&dispatcher, &Event::mouse_dragged(23.0, 8.0));
+        EventHandler::handle_event(&dispatcher, &Event::mouse_released(Button::Left, 23.0, 8.0));
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (EventType::MouseMoved, 0.0, 0.0),
+                (EventType::MouseMoved, 12.0, 0.0),
+                (EventType::MouseMoved, 15.0, 0.0),
+                (EventType::MousePressed, 15.0, 0.0),
+                (EventType::MouseDragged, 21.0, 8.0),
+                (EventType::MouseDragged, 23.0, 8.0),
+                (EventType::MouseReleased, 23.0, 8.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_motion_filter_passes_dropped_moves_in_grab_mode() {
+        let dispatcher = Dispatcher {
+            subscribers: Subscribers::default(),
+            ignore_injected: false,
+            events: EventMask::ALL,
+            displays: None,
+            region: None,
+            ime: None,
+            pointer: None,
+            motion: Some(Mutex::new(MotionFilter::new(10.0))),
+            latency: Default::default(),
+            handler: |_: &Event| None,
+        };
+        assert!(GrabHandler::handle_event(&dispatcher, &Event::mouse_moved(0.0, 0.0)).is_none());
+        // Too close: not shown to the handler, so not consumed either
+        let nearby = Event::mouse_moved(1.0, 1.0);
+        assert_eq!(
+            GrabHandler::handle_event(&dispatcher, &nearby),
+            Some(nearby)
+        );
+    }
+
+    #[test]
+    fn test_event_mask_keeps_excluded_events_from_handler() {
+        let subscribers = Subscribers::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        subscribers
+            .add(Arc::new(move |event: &Event| {
+                log.lock().unwrap().push(event.event_type);
+            }))
+            .detach();
+        let handled = Arc::new(AtomicU64::new(0));
+        let count = handled.clone();
+        let dispatcher = Dispatcher {
+            subscribers,
+            ignore_injected: false,
+            events: EventMask::KEYBOARD | EventMask::MOUSE_BUTTONS,
+            displays: None,
+            region: None,
+            ime: None,
+            pointer: Some(Mutex::new(PointerTracker::new(3.0))),
+            motion: None,
+            latency: Default::default(),
+            handler: move |_: &Event| {
+                count.fetch_add(1, Ordering::Relaxed);
+                None
+            },
+        };
+
+        let passed = [
+            Event::hook_enabled(),
+            Event::mouse_moved(1.0, 1.0),
+            Event::mouse_pressed(Button::Left, 1.0, 1.0),
+            Event::mouse_dragged(9.0, 9.0),
+            Event::mouse_wheel(9.0, 9.0, ScrollDirection::Down, 1.0),
+            Event::key_pressed(Key::KeyA, 0),
+        ]
+        .iter()
+        .filter_map(|event| GrabHandler::handle_event(&dispatcher, event))
+        .map(|event| event.event_type)
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                EventType::HookEnabled,
+                EventType::MousePressed,
+                EventType::KeyPressed,
+            ]
+        );
+        assert_eq!(handled.load(Ordering::Relaxed), 3);
+        // Excluded events pass through a grab untouched
+        assert_eq!(
+            passed,
+            vec![
+                EventType::MouseMoved,
+                EventType::MouseDragged,
+                EventType::MouseWheel,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drag_threshold_reports_drag_started_to_subscribers() {
+        let subscribers = Subscribers::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        subscribers
+            .add(Arc::new(move |event: &Event| {
+                log.lock().unwrap().push(event.event_type);
+            }))
+            .detach();
+        let dispatcher = Dispatcher {
+            subscribers,
+            ignore_injected: false,
+            events: EventMask::ALL,
+            displays: None,
+            region: None,
+            ime: None,
+            pointer: Some(Mutex::new(PointerTracker::new(3.0))),
+            motion: None,
+            latency: Default::default(),
+            handler: |event: &Event| Some(event.clone()),
+        };
+
+        GrabHandler::handle_event(&dispatcher, &Event::mouse_pressed(Button::Left, 0.0, 0.0));
+        let jitter = GrabHandler::handle_event(&dispatcher, &Event::mouse_dragged(1.0, 1.0));
+        assert_eq!(jitter.unwrap().event_type, EventType::MouseMoved);
+        GrabHandler::handle_event(&dispatcher, &Event::mouse_dragged(5.0, 0.0));
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                EventType::MousePressed,
+                EventType::MouseMoved,
+                EventType::DragStarted,
+                EventType::MouseDragged,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_restrict_to_display_passes_other_displays_on() {
+        fn displays() -> crate::Result<Vec<crate::DisplayInfo>> {
+            let display = |id, x| crate::DisplayInfo {
+                id,
+                bounds: crate::Rect {
+                    x,
+                    y: 0.0,
+                    width: 100.0,
+                    height: 100.0,
+                },
+                scale_factor: 1.0,
+                refresh_rate: None,
+                is_primary: id == 1,
+                source: crate::DisplaySource::WindowSystem,
+            };
+            Ok(vec![display(1, 0.0), display(2, 100.0)])
+        }
+
+        let count = Arc::new(AtomicU64::new(0));
+        let subscribers = Subscribers::default();
+        let counter = count.clone();
+        subscribers
+            .add(Arc::new(move |_: &Event| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }))
+            .detach();
+        let dispatcher = Dispatcher {
+            subscribers,
+            ignore_injected: false,
+            events: EventMask::ALL,
+            displays: None,
+            region: Some(Mutex::new(DisplayRegion::with_source(2, false, displays))),
+            ime: None,
+            pointer: None,
+            motion: None,
+            latency: Default::default(),
+            handler: |_: &Event| None,
+        };
+
+        let elsewhere = Event::mouse_moved(50.0, 50.0);
+        assert_eq!(
+            GrabHandler::handle_event(&dispatcher, &elsewhere),
+            Some(elsewhere)
+        );
+        assert_eq!(count.load(Ordering::Relaxed), 0);
+        assert!(GrabHandler::handle_event(&dispatcher, &Event::mouse_moved(150.0, 50.0)).is_none());
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+}
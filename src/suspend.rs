@@ -0,0 +1,224 @@
+//! A process-wide "do not disturb" switch for every hook.
+//!
+//! [`suspend_all`] makes every running hook stop reacting for a while, e.g.
+//! during a presentation, without touching each consumer: listen hooks skip
+//! their handler and subscriptions, and grab hooks pass every event through
+//! untouched. This covers everything built on hooks, such as statistics
+//! collectors, recorders, hotkeys and channels. [`resume_all`] ends the
+//! suspension early.
+//!
+//! Hooks that must keep working, like an abort chord, opt out with
+//! [`Hook::ignore_global_suspend`](crate::Hook::ignore_global_suspend).
+//!
+//! Every hook reports [`EventType::GlobalSuspendChanged`] when it notices
+//! the switch flip, just before the next event it sees, so UIs can show the
+//! state; check [`is_suspended`] from there. A suspension running out is
+//! reported the same way. Hook lifecycle events are delivered while
+//! suspended.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! monio::suspend_all(Duration::from_secs(30 * 60));
+//! assert!(monio::is_suspended());
+//! monio::resume_all();
+//! ```
+
+use crate::event::{Event, EventType};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Not suspended.
+const NONE: u64 = 0;
+
+/// Suspension state shared by the hooks checking it.
+pub(crate) struct SuspendState {
+    /// Bumped by every `suspend_all` / `resume_all`.
+    generation: AtomicU64,
+    /// When the suspension ends, in nanoseconds since [`epoch`] plus one, or
+    /// [`NONE`].
+    deadline: AtomicU64,
+}
+
+static GLOBAL: SuspendState = SuspendState::new();
+
+/// Reference point for deadlines.
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+fn encode(instant: Instant) -> u64 {
+    let nanos = instant.saturating_duration_since(epoch()).as_nanos();
+    u64::try_from(nanos).unwrap_or(u64::MAX - 1) + 1
+}
+
+impl SuspendState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            generation: AtomicU64::new(0),
+            deadline: AtomicU64::new(NONE),
+        }
+    }
+
+    pub(crate) fn suspend(&self, duration: Duration) {
+        let deadline = match Instant::now().checked_add(duration) {
+            Some(until) => encode(until),
+            None => u64::MAX,
+        };
+        self.deadline.store(deadline, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn resume(&self) {
+        self.deadline.store(NONE, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn until(&self) -> Option<Instant> {
+        match self.deadline.load(Ordering::SeqCst) {
+            NONE => None,
+            deadline => {
+                let until = epoch() + Duration::from_nanos(deadline - 1);
+                (Instant::now() < until).then_some(until)
+            }
+        }
+    }
+
+    /// The generation and whether suspended, packed for comparison.
+    fn key(&self) -> u64 {
+        let generation = self.generation.load(Ordering::SeqCst);
+        (generation << 1) | self.until().is_some() as u64
+    }
+}
+
+/// Stop every hook from reacting to input for `duration`.
+///
+/// Replaces any suspension already running, so this can also shorten or
+/// extend one. `Duration::MAX` suspends until [`resume_all`].
+pub fn suspend_all(duration: Duration) {
+    GLOBAL.suspend(duration);
+}
+
+/// End a suspension started with [`suspend_all`].
+pub fn resume_all() {
+    GLOBAL.resume();
+}
+
+/// Whether hooks are suspended now.
+pub fn is_suspended() -> bool {
+    GLOBAL.until().is_some()
+}
+
+/// When the current suspension ends, if hooks are suspended.
+pub fn suspended_until() -> Option<Instant> {
+    GLOBAL.until()
+}
+
+/// A hook's view of the suspension state.
+pub(crate) struct SuspendGate {
+    state: &'static SuspendState,
+    /// Keep dispatching while suspended.
+    ignore: bool,
+    /// The state last reported to the hook.
+    seen: AtomicU64,
+}
+
+impl SuspendGate {
+    /// A gate following the global switch.
+    pub(crate) fn new(ignore: bool) -> Self {
+        Self::with_state(&GLOBAL, ignore)
+    }
+
+    pub(crate) fn with_state(state: &'static SuspendState, ignore: bool) -> Self {
+        Self {
+            state,
+            ignore,
+            seen: AtomicU64::new(state.key()),
+        }
+    }
+
+    /// A [`GlobalSuspendChanged`](EventType::GlobalSuspendChanged) event to
+    /// dispatch before `event`, and whether to dispatch `event` itself.
+    pub(crate) fn check(&self, event: &Event) -> (Option<Event>, bool) {
+        let key = self.state.key();
+        let changed = self.seen.swap(key, Ordering::SeqCst) != key;
+        let notice = changed.then(|| Event::new(EventType::GlobalSuspendChanged));
+        let suspended = key & 1 == 1;
+        let lifecycle = matches!(
+            event.event_type,
+            EventType::HookEnabled
+                | EventType::HookDisabled
+                | EventType::GrabSuspended
+                | EventType::GrabDegraded
+                | EventType::GlobalSuspendChanged
+        );
+        (notice, self.ignore || !suspended || lifecycle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycode::Key;
+
+    fn state() -> &'static SuspendState {
+        Box::leak(Box::new(SuspendState::new()))
+    }
+
+    #[test]
+    fn test_gate_follows_state() {
+        let state = state();
+        let gate = SuspendGate::with_state(state, false);
+        let key = Event::key_pressed(Key::KeyA, 0);
+
+        assert_eq!(gate.check(&key), (None, true));
+
+        state.suspend(Duration::from_secs(60));
+        let (notice, deliver) = gate.check(&key);
+        assert_eq!(
+            notice.map(|e| e.event_type),
+            Some(EventType::GlobalSuspendChanged)
+        );
+        assert!(!deliver);
+        assert_eq!(gate.check(&key), (None, false));
+        assert!(gate.check(&Event::hook_disabled()).1);
+
+        // Extending a suspension is reported too
+        state.suspend(Duration::from_secs(120));
+        assert!(gate.check(&key).0.is_some());
+
+        state.resume();
+        let (notice, deliver) = gate.check(&key);
+        assert!(notice.is_some() && deliver);
+    }
+
+    #[test]
+    fn test_suspension_expires() {
+        let state = state();
+        let gate = SuspendGate::with_state(state, false);
+        let key = Event::key_pressed(Key::KeyA, 0);
+
+        state.suspend(Duration::from_millis(20));
+        assert!(!gate.check(&key).1);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(state.until().is_none());
+        let (notice, deliver) = gate.check(&key);
+        assert!(notice.is_some() && deliver);
+    }
+
+    #[test]
+    fn test_opt_out_sees_notices() {
+        let state = state();
+        let gate = SuspendGate::with_state(state, true);
+        let key = Event::key_pressed(Key::KeyA, 0);
+
+        state.suspend(Duration::MAX);
+        let (notice, deliver) = gate.check(&key);
+        assert!(notice.is_some() && deliver);
+        assert_eq!(gate.check(&key), (None, true));
+    }
+}
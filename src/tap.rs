@@ -0,0 +1,265 @@
+//! Double-tap detection, like tapping Shift twice to open a launcher.
+//!
+//! [`DoubleTapDetector`] consumes a stream of events and reports when a key
+//! was tapped twice in quick succession. A tap is a press and release with
+//! nothing else pressed in between, so using the key in a chord (Shift+A,
+//! Shift+click) never counts, and autorepeat is ignored. The second tap
+//! fires on its release; a third tap starts over.
+//!
+//! Like [`rollover`](crate::rollover), the detector is decoupled from the
+//! hook and uses event timestamps, so it can be fed from a channel or a
+//! recording. [`Hook::on_double_tap`](crate::Hook::on_double_tap) wires it
+//! to a hook.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::tap::DoubleTapDetector;
+//! use monio::{Event, Hook, Key};
+//! use std::time::Duration;
+//!
+//! let hook = Hook::new();
+//! let shift = DoubleTapDetector::new(Key::ShiftLeft, Duration::from_millis(300)).either_side(true);
+//! let _launcher = hook.on_double_tap(shift, || println!("search everywhere"));
+//! hook.run(|_: &Event| {}).expect("Failed to start hook");
+//! ```
+
+use crate::event::{Event, EventType};
+use crate::keycode::Key;
+use std::time::{Duration, SystemTime};
+
+/// The same modifier on the other side of the keyboard.
+fn other_side(key: Key) -> Option<Key> {
+    Some(match key {
+        Key::ShiftLeft => Key::ShiftRight,
+        Key::ShiftRight => Key::ShiftLeft,
+        Key::ControlLeft => Key::ControlRight,
+        Key::ControlRight => Key::ControlLeft,
+        Key::AltLeft => Key::AltRight,
+        Key::AltRight => Key::AltLeft,
+        Key::MetaLeft => Key::MetaRight,
+        Key::MetaRight => Key::MetaLeft,
+        _ => return None,
+    })
+}
+
+/// Detects two quick taps of a key.
+#[derive(Debug, Clone)]
+pub struct DoubleTapDetector {
+    key: Key,
+    window: Duration,
+    either_side: bool,
+    /// When the key was pressed, while it is held.
+    pressed_at: Option<SystemTime>,
+    /// Whether nothing else was pressed since the key was.
+    clean: bool,
+    /// When the first tap was released, while waiting for the second.
+    first_tap: Option<SystemTime>,
+}
+
+impl DoubleTapDetector {
+    /// Detect two taps of `key`, each held at most `window`, with at most
+    /// `window` between the first release and the second press.
+    pub fn new(key: Key, window: Duration) -> Self {
+        Self {
+            key,
+            window,
+            either_side: false,
+            pressed_at: None,
+            clean: false,
+            first_tap: None,
+        }
+    }
+
+    /// For modifiers, whether a tap on each side (left Shift, then right
+    /// Shift) counts as a double tap. Off by default: both taps must be on
+    /// the same key.
+    pub fn either_side(mut self, either: bool) -> Self {
+        self.either_side = either;
+        self
+    }
+
+    /// Forget any tap in progress.
+    pub fn reset(&mut self) {
+        self.pressed_at = None;
+        self.clean = false;
+        self.first_tap = None;
+    }
+
+    fn is_key(&self, key: Key) -> bool {
+        key == self.key || (self.either_side && other_side(self.key) == Some(key))
+    }
+
+    fn within_window(&self, from: SystemTime, to: SystemTime) -> bool {
+        to.duration_since(from)
+            .is_ok_and(|elapsed| elapsed <= self.window)
+    }
+
+    /// Feed an event. Returns `true` when it completes a double tap.
+    pub fn process_event(&mut self, event: &Event) -> bool {
+        match event.event_type {
+            EventType::KeyPressed => {
+                let Some(kb) = &event.keyboard else {
+                    return false;
+                };
+                if !self.is_key(kb.key) {
+                    self.interrupt();
+                } else if self.pressed_at.is_none() {
+                    if let Some(released) = self.first_tap
+                        && !self.within_window(released, event.time)
+                    {
+                        self.first_tap = None;
+                    }
+                    self.pressed_at = Some(event.time);
+                    self.clean = true;
+                }
+                // Otherwise autorepeat
+                false
+            }
+            EventType::KeyReleased => {
+                let Some(kb) = &event.keyboard else {
+                    return false;
+                };
+                if !self.is_key(kb.key) {
+                    return false;
+                }
+                let Some(pressed) = self.pressed_at.take() else {
+                    return false;
+                };
+                if !self.clean || !self.within_window(pressed, event.time) {
+                    self.first_tap = None;
+                    return false;
+                }
+                if self.first_tap.take().is_some() {
+                    return true;
+                }
+                self.first_tap = Some(event.time);
+                false
+            }
+            EventType::MousePressed | EventType::MouseWheel => {
+                self.interrupt();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Something else was pressed: neither a held key nor a finished tap
+    /// counts anymore.
+    fn interrupt(&mut self) {
+        self.clean = false;
+        self.first_tap = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(300);
+
+    /// Feeds events at given milliseconds, returning when it fired.
+    struct Feed {
+        detector: DoubleTapDetector,
+        fired: Vec<u64>,
+    }
+
+    impl Feed {
+        fn new(detector: DoubleTapDetector) -> Self {
+            Self {
+                detector,
+                fired: Vec::new(),
+            }
+        }
+
+        fn event(&mut self, ms: u64, mut event: Event) -> &mut Self {
+            event.time = SystemTime::UNIX_EPOCH + Duration::from_millis(ms);
+            if self.detector.process_event(&event) {
+                self.fired.push(ms);
+            }
+            self
+        }
+
+        fn tap(&mut self, ms: u64, key: Key) -> &mut Self {
+            self.event(ms, Event::key_pressed(key, 0))
+                .event(ms + 50, Event::key_released(key, 0))
+        }
+    }
+
+    fn shift() -> DoubleTapDetector {
+        DoubleTapDetector::new(Key::ShiftLeft, WINDOW)
+    }
+
+    #[test]
+    fn test_double_tap_fires_on_second_release() {
+        let mut feed = Feed::new(shift());
+        feed.tap(0, Key::ShiftLeft).tap(200, Key::ShiftLeft);
+        assert_eq!(feed.fired, [250]);
+    }
+
+    #[test]
+    fn test_chord_cancels_tap() {
+        let mut feed = Feed::new(shift());
+        // Shift+A, then a tap: only one tap
+        feed.event(0, Event::key_pressed(Key::ShiftLeft, 0))
+            .event(20, Event::key_pressed(Key::KeyA, 0))
+            .event(40, Event::key_released(Key::KeyA, 0))
+            .event(60, Event::key_released(Key::ShiftLeft, 0))
+            .tap(200, Key::ShiftLeft);
+        assert!(feed.fired.is_empty());
+
+        // A tap, then Shift+click
+        let mut feed = Feed::new(shift());
+        feed.tap(0, Key::ShiftLeft)
+            .event(200, Event::key_pressed(Key::ShiftLeft, 0))
+            .event(220, Event::mouse_pressed(crate::Button::Left, 0.0, 0.0))
+            .event(240, Event::key_released(Key::ShiftLeft, 0));
+        assert!(feed.fired.is_empty());
+
+        // A key typed between the taps
+        let mut feed = Feed::new(shift());
+        feed.tap(0, Key::ShiftLeft)
+            .tap(100, Key::KeyA)
+            .tap(200, Key::ShiftLeft);
+        assert!(feed.fired.is_empty());
+    }
+
+    #[test]
+    fn test_slow_taps_and_long_holds_dont_count() {
+        let mut feed = Feed::new(shift());
+        feed.tap(0, Key::ShiftLeft).tap(500, Key::ShiftLeft);
+        assert!(feed.fired.is_empty());
+        // The slow second tap can start a new double tap
+        feed.tap(700, Key::ShiftLeft);
+        assert_eq!(feed.fired, [750]);
+
+        let mut feed = Feed::new(shift());
+        feed.event(0, Event::key_pressed(Key::ShiftLeft, 0))
+            .event(400, Event::key_released(Key::ShiftLeft, 0))
+            .tap(500, Key::ShiftLeft);
+        assert!(feed.fired.is_empty());
+    }
+
+    #[test]
+    fn test_autorepeat_and_triple_taps() {
+        let mut feed = Feed::new(shift());
+        feed.event(0, Event::key_pressed(Key::ShiftLeft, 0))
+            .event(30, Event::key_pressed(Key::ShiftLeft, 0))
+            .event(60, Event::key_released(Key::ShiftLeft, 0))
+            .tap(200, Key::ShiftLeft)
+            .tap(400, Key::ShiftLeft)
+            .tap(600, Key::ShiftLeft);
+        assert_eq!(feed.fired, [250, 650]);
+    }
+
+    #[test]
+    fn test_either_side() {
+        let mut feed = Feed::new(shift());
+        feed.tap(0, Key::ShiftLeft).tap(200, Key::ShiftRight);
+        assert!(feed.fired.is_empty());
+
+        let mut feed = Feed::new(shift().either_side(true));
+        feed.tap(0, Key::ShiftLeft).tap(200, Key::ShiftRight);
+        assert_eq!(feed.fired, [250]);
+    }
+}
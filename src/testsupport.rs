@@ -0,0 +1,19 @@
+//! Utilities for end-to-end tests of input handling (`test-util` feature).
+//!
+//! Hooks normally need a person at the keyboard. On Linux with the evdev
+//! backend, [`VirtualInput`] stands in: it creates a uinput device whose
+//! events go through the same path as real hardware, so tests can assert on
+//! the [`Event`](crate::Event)s a hook reports, in CI and in downstream
+//! crates alike:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! monio = { version = "0.1", default-features = false, features = ["evdev", "test-util"] }
+//! ```
+//!
+//! Creating the device needs write access to `/dev/uinput`, and listening
+//! needs read access to `/dev/input`; tests should skip when
+//! [`VirtualInput::is_available`] says no. Hooks share global state, so run
+//! such tests one at a time.
+
+pub use crate::platform::VirtualInput;
@@ -0,0 +1,319 @@
+//! Bounding how long handlers may take, see
+//! [`Hook::handler_timeout`](crate::Hook::handler_timeout).
+//!
+//! Slow handlers stall input: on macOS, a tap whose callback takes too long
+//! is disabled by the system, and Windows silently removes slow low-level
+//! hooks. With a handler timeout, the hook hands events to a dedicated
+//! worker thread running the handler, in order, and never waits longer than
+//! the timeout:
+//!
+//! - Listen hooks don't wait at all. Events the worker can't start within
+//!   the timeout of their arrival are abandoned: the handler never sees
+//!   them.
+//! - Grab hooks wait up to the timeout for the handler's verdict, then let
+//!   the event through (fail open) and abandon it.
+//!
+//! Lifecycle events such as `HookEnabled` and `HookDisabled` are never
+//! abandoned, though grab hooks don't wait longer for them. Abandoned events
+//! are counted in [`Hook::handler_timeouts`](crate::Hook::handler_timeouts).
+//!
+//! A handler already running can't be interrupted; a handler stuck for good
+//! makes every later event time out. The trade-off is latency: handlers see
+//! events a thread hop later than native callbacks, and subscriptions still
+//! run on the hook thread.
+
+use crate::event::{Event, EventType};
+use crate::hook::{EventHandler, GrabHandler};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How many events a listen hook queues for a busy worker.
+const LISTEN_QUEUE: usize = 4096;
+
+fn is_lifecycle(event: &Event) -> bool {
+    matches!(
+        event.event_type,
+        EventType::HookEnabled
+            | EventType::HookDisabled
+            | EventType::GrabSuspended
+            | EventType::GrabDegraded
+            | EventType::GlobalSuspendChanged
+    )
+}
+
+/// An event for the worker, and when it is abandoned.
+pub(crate) struct Job {
+    event: Event,
+    deadline: Instant,
+    sequence: u64,
+}
+
+/// The hook thread's end of a worker, shared by listen and grab hooks.
+pub(crate) struct Worker<S> {
+    jobs: Mutex<Option<S>>,
+    /// Disconnects when the worker thread exits.
+    done: Mutex<Receiver<()>>,
+    timeout: Duration,
+    timeouts: Arc<AtomicU64>,
+}
+
+impl<S> Worker<S> {
+    fn spawn(
+        jobs: S,
+        timeout: Duration,
+        timeouts: Arc<AtomicU64>,
+        run: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        let (done_tx, done) = mpsc::channel::<()>();
+        thread::spawn(move || {
+            let _done = done_tx;
+            run();
+        });
+        Self {
+            jobs: Mutex::new(Some(jobs)),
+            done: Mutex::new(done),
+            timeout,
+            timeouts,
+        }
+    }
+
+    fn job(&self, event: &Event, sequence: u64) -> Job {
+        Job {
+            event: event.clone(),
+            deadline: Instant::now() + self.timeout,
+            sequence,
+        }
+    }
+
+    fn abandon(&self) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl<S> Drop for Worker<S> {
+    fn drop(&mut self) {
+        // Let the worker finish the queue (`HookDisabled` last), but don't
+        // wait on a stuck handler
+        if let Ok(mut jobs) = self.jobs.lock() {
+            *jobs = None;
+        }
+        if let Ok(done) = self.done.lock() {
+            let _ = done.recv_timeout(self.timeout);
+        }
+    }
+}
+
+/// Listen handler running `H` on a worker, or directly without a timeout.
+pub(crate) enum TimedListen<H> {
+    Direct(H),
+    Worker(Worker<SyncSender<Job>>),
+}
+
+impl<H: EventHandler + 'static> TimedListen<H> {
+    pub(crate) fn new(handler: H, timeout: Option<Duration>, timeouts: Arc<AtomicU64>) -> Self {
+        let Some(timeout) = timeout else {
+            return Self::Direct(handler);
+        };
+        let (jobs, queue) = mpsc::sync_channel::<Job>(LISTEN_QUEUE);
+        let counter = timeouts.clone();
+        Self::Worker(Worker::spawn(jobs, timeout, timeouts, move || {
+            for job in queue {
+                if is_lifecycle(&job.event) || Instant::now() < job.deadline {
+                    handler.handle_event(&job.event);
+                } else {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }))
+    }
+}
+
+impl<H: EventHandler> EventHandler for TimedListen<H> {
+    fn handle_event(&self, event: &Event) {
+        let worker = match self {
+            Self::Direct(handler) => return handler.handle_event(event),
+            Self::Worker(worker) => worker,
+        };
+        let Ok(jobs) = worker.jobs.lock() else {
+            return;
+        };
+        if let Some(jobs) = jobs.as_ref()
+            && jobs.try_send(worker.job(event, 0)).is_err()
+        {
+            worker.abandon();
+        }
+    }
+}
+
+/// The hook thread's end of a grab worker.
+pub(crate) struct GrabWorker {
+    worker: Worker<Sender<Job>>,
+    verdicts: Mutex<Receiver<(u64, Option<Event>)>>,
+    next_sequence: AtomicU64,
+}
+
+/// Grab handler running `H` on a worker, or directly without a timeout.
+pub(crate) enum TimedGrab<H> {
+    Direct(H),
+    Worker(GrabWorker),
+}
+
+impl<H: GrabHandler + 'static> TimedGrab<H> {
+    pub(crate) fn new(handler: H, timeout: Option<Duration>, timeouts: Arc<AtomicU64>) -> Self {
+        let Some(timeout) = timeout else {
+            return Self::Direct(handler);
+        };
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let (verdict_tx, verdicts) = mpsc::channel();
+        let worker = Worker::spawn(jobs, timeout, timeouts, move || {
+            for job in queue {
+                // Already let through; the handler wouldn't be heard
+                if !is_lifecycle(&job.event) && Instant::now() >= job.deadline {
+                    continue;
+                }
+                let verdict = handler.handle_event(&job.event);
+                if verdict_tx.send((job.sequence, verdict)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self::Worker(GrabWorker {
+            worker,
+            verdicts: Mutex::new(verdicts),
+            next_sequence: AtomicU64::new(0),
+        })
+    }
+}
+
+impl<H: GrabHandler> GrabHandler for TimedGrab<H> {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        let grab = match self {
+            Self::Direct(handler) => return handler.handle_event(event),
+            Self::Worker(grab) => grab,
+        };
+        let sequence = grab.next_sequence.fetch_add(1, Ordering::Relaxed);
+        let job = grab.worker.job(event, sequence);
+        let deadline = job.deadline;
+        let sent = match grab.worker.jobs.lock() {
+            Ok(jobs) => jobs.as_ref().is_some_and(|jobs| jobs.send(job).is_ok()),
+            Err(_) => false,
+        };
+        let Ok(verdicts) = grab.verdicts.lock() else {
+            return Some(event.clone());
+        };
+        if !sent {
+            return Some(event.clone());
+        }
+
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match verdicts.recv_timeout(left) {
+                Ok((seq, verdict)) if seq == sequence => return verdict,
+                // The verdict for an event that already timed out
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => {
+                    grab.worker.abandon();
+                    return Some(event.clone());
+                }
+                // The handler panicked
+                Err(RecvTimeoutError::Disconnected) => return Some(event.clone()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycode::Key;
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    /// A handler sleeping for a while on `KeyS`, consuming `KeyC`.
+    fn slow(event: &Event) -> Option<Event> {
+        let key = event.keyboard.as_ref().map(|kb| kb.key);
+        if key == Some(Key::KeyS) {
+            thread::sleep(TIMEOUT * 4);
+        }
+        (key != Some(Key::KeyC)).then(|| event.clone())
+    }
+
+    #[test]
+    fn test_grab_fails_open_after_timeout() {
+        let timeouts = Arc::new(AtomicU64::new(0));
+        let handler = TimedGrab::new(slow, Some(TIMEOUT), timeouts.clone());
+
+        assert!(
+            handler
+                .handle_event(&Event::key_pressed(Key::KeyC, 0))
+                .is_none()
+        );
+        let start = Instant::now();
+        assert!(
+            handler
+                .handle_event(&Event::key_pressed(Key::KeyS, 0))
+                .is_some()
+        );
+        assert!(start.elapsed() < TIMEOUT * 3);
+        assert_eq!(timeouts.load(Ordering::Relaxed), 1);
+
+        // Still stuck: lets through and counts
+        assert!(
+            handler
+                .handle_event(&Event::key_pressed(Key::KeyC, 0))
+                .is_some()
+        );
+        assert_eq!(timeouts.load(Ordering::Relaxed), 2);
+
+        // Once the handler is back, verdicts count again
+        thread::sleep(TIMEOUT * 4);
+        assert!(
+            handler
+                .handle_event(&Event::key_pressed(Key::KeyC, 0))
+                .is_none()
+        );
+        assert_eq!(timeouts.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_listen_keeps_order_and_abandons_late_events() {
+        let timeouts = Arc::new(AtomicU64::new(0));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let seen = seen.clone();
+            TimedListen::new(
+                move |event: &Event| {
+                    let _ = slow(event);
+                    seen.lock().unwrap().push(event.event_type);
+                },
+                Some(TIMEOUT),
+                timeouts.clone(),
+            )
+        };
+
+        let start = Instant::now();
+        handler.handle_event(&Event::hook_enabled());
+        handler.handle_event(&Event::key_pressed(Key::KeyS, 0));
+        handler.handle_event(&Event::key_pressed(Key::KeyA, 0));
+        handler.handle_event(&Event::hook_disabled());
+        assert!(start.elapsed() < TIMEOUT, "the hook thread never waits");
+        drop(handler);
+        while seen.lock().unwrap().len() < 3 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        // KeyA waited behind the slow KeyS; HookDisabled is never abandoned
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                EventType::HookEnabled,
+                EventType::KeyPressed,
+                EventType::HookDisabled
+            ]
+        );
+        assert_eq!(timeouts.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,642 @@
+//! The lifecycle of a mouse button: press, drag, release and click.
+//!
+//! Backends only report what the OS tells them: button presses and
+//! releases, and moves with or without a button held. Everything derived
+//! from those (whether a move is jitter or a drag, where the drag started,
+//! whether a release completes a click and how many clicks in a row) is
+//! decided by one [`PointerStateMachine`], which every hook feeds the same
+//! way whatever the platform.
+//!
+//! The machine is pure: it makes no OS calls and reads no clocks, and every
+//! threshold comes from a [`PointerConfig`]. Inputs carry their own
+//! positions and timestamps, so it can also be fed from a recording:
+//!
+//! ```
+//! use monio::tracking::{PointerConfig, PointerInput, PointerOutput, PointerStateMachine};
+//! use monio::Button;
+//! use std::time::{Duration, SystemTime};
+//!
+//! let mut pointer = PointerStateMachine::new(PointerConfig::default());
+//! let start = SystemTime::now();
+//! let mut out = Vec::new();
+//! for (i, input) in [
+//!     PointerInput::Press(Button::Left),
+//!     PointerInput::Release(Button::Left),
+//!     PointerInput::Press(Button::Left),
+//!     PointerInput::Release(Button::Left),
+//! ]
+//! .into_iter()
+//! .enumerate()
+//! {
+//!     let time = start + Duration::from_millis(80 * i as u64);
+//!     pointer.feed(input, 10.0, 10.0, time, &mut out);
+//! }
+//! assert_eq!(
+//!     out.last(),
+//!     Some(&PointerOutput::Clicked { button: Button::Left, x: 10.0, y: 10.0, count: 2 })
+//! );
+//! ```
+//!
+//! # Transitions
+//!
+//! | State | Input | Outputs | Next |
+//! |---|---|---|---|
+//! | idle | press | `Pressed` | pressed |
+//! | idle | move | `Moved`, or `Dragged` if a button is held | idle |
+//! | pressed | move within the drag threshold | `Moved` | pressed |
+//! | pressed | move past the drag threshold | `DragStarted` (at the press), `Dragged` | dragging |
+//! | pressed | release near the press | `Released`, `Clicked` | idle |
+//! | pressed | release far from the press | `Released` | idle |
+//! | dragging | move | `Dragged` | dragging |
+//! | dragging | release | `DragEnded`, `Released` | idle |
+//!
+//! Only the first button held is tracked; presses and releases of others
+//! while it is held are reported as they come and never click.
+//!
+//! A release the hook never saw is recovered from when the next input
+//! contradicts it: a move with no button held, or another press of the held
+//! button. The drag is ended and a `Released` with `recovered: true` is
+//! reported first, and no click is counted.
+
+use crate::event::Button;
+use std::time::{Duration, SystemTime};
+
+/// Drag threshold in points where none is set.
+pub const DEFAULT_DRAG_THRESHOLD: f64 = 4.0;
+
+/// Longest time between clicks counted together where none is set.
+pub const DEFAULT_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Thresholds and timeouts of a [`PointerStateMachine`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerConfig {
+    /// How far the pointer may move from where a button was pressed before
+    /// it drags, in the units of input positions. At 0 or less, every move
+    /// with a button held drags.
+    pub drag_threshold: f64,
+    /// Longest time from one click's release to the next click's press for
+    /// them to count as a double (or triple...) click.
+    pub click_interval: Duration,
+    /// How far a press may be from the last click for them to count
+    /// together.
+    pub click_distance: f64,
+}
+
+impl Default for PointerConfig {
+    fn default() -> Self {
+        Self {
+            drag_threshold: DEFAULT_DRAG_THRESHOLD,
+            click_interval: DEFAULT_CLICK_INTERVAL,
+            click_distance: DEFAULT_DRAG_THRESHOLD,
+        }
+    }
+}
+
+/// What the OS reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerInput {
+    /// A button went down.
+    Press(Button),
+    /// A button went up.
+    Release(Button),
+    /// The pointer moved, with the button the OS reports held, if any.
+    Move(Option<Button>),
+}
+
+/// What an input means.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointerOutput {
+    /// A button went down.
+    Pressed { button: Button, x: f64, y: f64 },
+    /// The pointer moved, without dragging.
+    Moved { x: f64, y: f64 },
+    /// The pointer left the drag threshold; `x` and `y` are where the
+    /// button was pressed.
+    DragStarted { button: Button, x: f64, y: f64 },
+    /// The pointer moved while dragging.
+    Dragged { button: Button, x: f64, y: f64 },
+    /// The button dragging was released.
+    DragEnded { button: Button, x: f64, y: f64 },
+    /// A button went up. `recovered` releases were never reported by the
+    /// OS, but later input showed the button was no longer held.
+    Released {
+        button: Button,
+        x: f64,
+        y: f64,
+        recovered: bool,
+    },
+    /// A press and release without a drag; `count` is 2 for the second
+    /// click of a double click, and so on.
+    Clicked {
+        button: Button,
+        x: f64,
+        y: f64,
+        count: u8,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    /// A button is held, and the pointer hasn't left the drag threshold.
+    Pressed {
+        button: Button,
+        origin: (f64, f64),
+        time: SystemTime,
+    },
+    /// The pointer left the drag threshold; moves drag until release.
+    Dragging {
+        button: Button,
+    },
+}
+
+/// The last click, which the next may continue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LastClick {
+    button: Button,
+    position: (f64, f64),
+    time: SystemTime,
+    count: u8,
+}
+
+/// Derives drags and clicks from button presses, releases and moves.
+#[derive(Debug, Clone)]
+pub struct PointerStateMachine {
+    config: PointerConfig,
+    state: State,
+    last_click: Option<LastClick>,
+}
+
+impl PointerStateMachine {
+    /// A machine with nothing held.
+    pub fn new(config: PointerConfig) -> Self {
+        Self {
+            config,
+            state: State::Idle,
+            last_click: None,
+        }
+    }
+
+    /// The thresholds in use.
+    pub fn config(&self) -> &PointerConfig {
+        &self.config
+    }
+
+    /// Change the thresholds, from the next input on.
+    pub fn set_config(&mut self, config: PointerConfig) {
+        self.config = config;
+    }
+
+    /// The button being tracked, while one is held.
+    pub fn held(&self) -> Option<Button> {
+        match self.state {
+            State::Idle => None,
+            State::Pressed { button, .. } | State::Dragging { button } => Some(button),
+        }
+    }
+
+    /// Whether the held button is dragging.
+    pub fn is_dragging(&self) -> bool {
+        matches!(self.state, State::Dragging { .. })
+    }
+
+    /// Forget the held button and the last click.
+    pub fn reset(&mut self) {
+        self.state = State::Idle;
+        self.last_click = None;
+    }
+
+    /// Feed an input at `(x, y)`, pushing what it means to `out`, in order.
+    pub fn feed(
+        &mut self,
+        input: PointerInput,
+        x: f64,
+        y: f64,
+        time: SystemTime,
+        out: &mut Vec<PointerOutput>,
+    ) {
+        match input {
+            PointerInput::Press(button) => {
+                if self.held() == Some(button) {
+                    self.recover(x, y, out);
+                }
+                if self.state == State::Idle {
+                    self.state = State::Pressed {
+                        button,
+                        origin: (x, y),
+                        time,
+                    };
+                }
+                out.push(PointerOutput::Pressed { button, x, y });
+            }
+
+            PointerInput::Release(button) => {
+                match self.state {
+                    State::Pressed {
+                        button: held,
+                        origin,
+                        time: pressed,
+                    } if held == button => {
+                        self.state = State::Idle;
+                        out.push(PointerOutput::Released {
+                            button,
+                            x,
+                            y,
+                            recovered: false,
+                        });
+                        if self.within_drag_threshold(origin, (x, y)) {
+                            let count = self.click_count(button, origin, pressed);
+                            self.last_click = Some(LastClick {
+                                button,
+                                position: origin,
+                                time,
+                                count,
+                            });
+                            out.push(PointerOutput::Clicked {
+                                button,
+                                x,
+                                y,
+                                count,
+                            });
+                        } else {
+                            // Released on another display, or moved without
+                            // reported motion: a drag no one saw
+                            self.last_click = None;
+                        }
+                        return;
+                    }
+                    State::Dragging { button: held } if held == button => {
+                        self.state = State::Idle;
+                        self.last_click = None;
+                        out.push(PointerOutput::DragEnded { button, x, y });
+                    }
+                    _ => {}
+                }
+                out.push(PointerOutput::Released {
+                    button,
+                    x,
+                    y,
+                    recovered: false,
+                });
+            }
+
+            PointerInput::Move(None) => {
+                if self.held().is_some() {
+                    self.recover(x, y, out);
+                }
+                out.push(PointerOutput::Moved { x, y });
+            }
+
+            PointerInput::Move(Some(reported)) => match self.state {
+                State::Idle => out.push(PointerOutput::Dragged {
+                    // The press came before tracking started
+                    button: reported,
+                    x,
+                    y,
+                }),
+                State::Pressed { button, origin, .. } => {
+                    if self.within_drag_threshold(origin, (x, y)) {
+                        out.push(PointerOutput::Moved { x, y });
+                    } else {
+                        self.state = State::Dragging { button };
+                        out.push(PointerOutput::DragStarted {
+                            button,
+                            x: origin.0,
+                            y: origin.1,
+                        });
+                        out.push(PointerOutput::Dragged { button, x, y });
+                    }
+                }
+                State::Dragging { button } => out.push(PointerOutput::Dragged { button, x, y }),
+            },
+        }
+    }
+
+    /// Release the held button the OS no longer holds.
+    fn recover(&mut self, x: f64, y: f64, out: &mut Vec<PointerOutput>) {
+        let button = match self.state {
+            State::Idle => return,
+            State::Pressed { button, .. } => button,
+            State::Dragging { button } => {
+                out.push(PointerOutput::DragEnded { button, x, y });
+                button
+            }
+        };
+        self.state = State::Idle;
+        self.last_click = None;
+        out.push(PointerOutput::Released {
+            button,
+            x,
+            y,
+            recovered: true,
+        });
+    }
+
+    /// Whether `to` is within the drag threshold of `from`.
+    fn within_drag_threshold(&self, from: (f64, f64), to: (f64, f64)) -> bool {
+        let threshold = self.config.drag_threshold;
+        let distance = (to.0 - from.0).hypot(to.1 - from.1);
+        if threshold > 0.0 {
+            distance <= threshold
+        } else {
+            distance == 0.0
+        }
+    }
+
+    /// The click count of a press of `button` at `position` at `pressed`.
+    fn click_count(&self, button: Button, position: (f64, f64), pressed: SystemTime) -> u8 {
+        let Some(last) = self.last_click else {
+            return 1;
+        };
+        let soon = pressed
+            .duration_since(last.time)
+            .is_ok_and(|elapsed| elapsed <= self.config.click_interval);
+        let near = (position.0 - last.position.0).hypot(position.1 - last.position.1)
+            <= self.config.click_distance;
+        if last.button == button && soon && near {
+            last.count.saturating_add(1)
+        } else {
+            1
+        }
+    }
+}
+
+impl Default for PointerStateMachine {
+    fn default() -> Self {
+        Self::new(PointerConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `inputs`, each `(input, x, y, ms)`, returning every output.
+    fn run(
+        pointer: &mut PointerStateMachine,
+        inputs: &[(PointerInput, f64, f64, u64)],
+    ) -> Vec<PointerOutput> {
+        let start = SystemTime::UNIX_EPOCH;
+        let mut out = Vec::new();
+        for &(input, x, y, ms) in inputs {
+            pointer.feed(input, x, y, start + Duration::from_millis(ms), &mut out);
+        }
+        out
+    }
+
+    fn clicks(out: &[PointerOutput]) -> Vec<u8> {
+        out.iter()
+            .filter_map(|output| match output {
+                PointerOutput::Clicked { count, .. } => Some(*count),
+                _ => None,
+            })
+            .collect()
+    }
+
+    use PointerInput::{Move, Press, Release};
+    const LEFT: Button = Button::Left;
+
+    #[test]
+    fn test_tiny_move_then_quick_press_is_double_click() {
+        let mut pointer = PointerStateMachine::default();
+        let out = run(
+            &mut pointer,
+            &[
+                (Press(LEFT), 10.0, 10.0, 0),
+                (Move(Some(LEFT)), 11.0, 10.0, 20),
+                (Release(LEFT), 11.0, 10.0, 40),
+                (Press(LEFT), 12.0, 11.0, 200),
+                (Release(LEFT), 12.0, 11.0, 240),
+            ],
+        );
+        assert_eq!(
+            out,
+            vec![
+                PointerOutput::Pressed {
+                    button: LEFT,
+                    x: 10.0,
+                    y: 10.0
+                },
+                PointerOutput::Moved { x: 11.0, y: 10.0 },
+                PointerOutput::Released {
+                    button: LEFT,
+                    x: 11.0,
+                    y: 10.0,
+                    recovered: false
+                },
+                PointerOutput::Clicked {
+                    button: LEFT,
+                    x: 11.0,
+                    y: 10.0,
+                    count: 1
+                },
+                PointerOutput::Pressed {
+                    button: LEFT,
+                    x: 12.0,
+                    y: 11.0
+                },
+                PointerOutput::Released {
+                    button: LEFT,
+                    x: 12.0,
+                    y: 11.0,
+                    recovered: false
+                },
+                PointerOutput::Clicked {
+                    button: LEFT,
+                    x: 12.0,
+                    y: 11.0,
+                    count: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_click_count_resets() {
+        let mut pointer = PointerStateMachine::default();
+        let out = run(
+            &mut pointer,
+            &[
+                (Press(LEFT), 0.0, 0.0, 0),
+                (Release(LEFT), 0.0, 0.0, 10),
+                (Press(LEFT), 0.0, 0.0, 100),
+                (Release(LEFT), 0.0, 0.0, 110),
+                (Press(LEFT), 0.0, 0.0, 200),
+                (Release(LEFT), 0.0, 0.0, 210),
+                // Too late
+                (Press(LEFT), 0.0, 0.0, 800),
+                (Release(LEFT), 0.0, 0.0, 810),
+                // Too far
+                (Press(LEFT), 50.0, 0.0, 900),
+                (Release(LEFT), 50.0, 0.0, 910),
+                // Another button
+                (Press(Button::Right), 50.0, 0.0, 950),
+                (Release(Button::Right), 50.0, 0.0, 960),
+            ],
+        );
+        assert_eq!(clicks(&out), vec![1, 2, 3, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_drag_lifecycle() {
+        let mut pointer = PointerStateMachine::default();
+        let out = run(
+            &mut pointer,
+            &[
+                (Press(LEFT), 0.0, 0.0, 0),
+                // Exactly at the threshold is still a move
+                (Move(Some(LEFT)), 4.0, 0.0, 10),
+                (Move(Some(LEFT)), 5.0, 0.0, 20),
+                // Coming back doesn't end the drag
+                (Move(Some(LEFT)), 0.0, 0.0, 30),
+                (Release(LEFT), 0.0, 0.0, 40),
+                (Press(LEFT), 0.0, 0.0, 100),
+                (Release(LEFT), 0.0, 0.0, 110),
+            ],
+        );
+        assert_eq!(
+            out[1..6],
+            [
+                PointerOutput::Moved { x: 4.0, y: 0.0 },
+                PointerOutput::DragStarted {
+                    button: LEFT,
+                    x: 0.0,
+                    y: 0.0
+                },
+                PointerOutput::Dragged {
+                    button: LEFT,
+                    x: 5.0,
+                    y: 0.0
+                },
+                PointerOutput::Dragged {
+                    button: LEFT,
+                    x: 0.0,
+                    y: 0.0
+                },
+                PointerOutput::DragEnded {
+                    button: LEFT,
+                    x: 0.0,
+                    y: 0.0
+                },
+            ]
+        );
+        // A drag is no click, and doesn't start a double click
+        assert_eq!(clicks(&out), vec![1]);
+        assert!(!pointer.is_dragging());
+    }
+
+    #[test]
+    fn test_release_on_another_display_is_no_click() {
+        let mut pointer = PointerStateMachine::default();
+        let out = run(
+            &mut pointer,
+            &[
+                (Press(LEFT), 100.0, 100.0, 0),
+                // The pointer jumped to another display without motion
+                (Release(LEFT), 2100.0, 100.0, 50),
+            ],
+        );
+        assert_eq!(
+            out.last(),
+            Some(&PointerOutput::Released {
+                button: LEFT,
+                x: 2100.0,
+                y: 100.0,
+                recovered: false
+            })
+        );
+        assert!(clicks(&out).is_empty());
+    }
+
+    #[test]
+    fn test_missed_release_recovery() {
+        let mut pointer = PointerStateMachine::default();
+        let out = run(
+            &mut pointer,
+            &[
+                (Press(LEFT), 0.0, 0.0, 0),
+                (Move(Some(LEFT)), 20.0, 0.0, 10),
+                // The release was lost: moves without a button held
+                (Move(None), 30.0, 0.0, 20),
+            ],
+        );
+        assert_eq!(
+            out[3..],
+            [
+                PointerOutput::DragEnded {
+                    button: LEFT,
+                    x: 30.0,
+                    y: 0.0
+                },
+                PointerOutput::Released {
+                    button: LEFT,
+                    x: 30.0,
+                    y: 0.0,
+                    recovered: true
+                },
+                PointerOutput::Moved { x: 30.0, y: 0.0 },
+            ]
+        );
+
+        // A second press of the held button
+        let out = run(
+            &mut pointer,
+            &[
+                (Press(LEFT), 0.0, 0.0, 100),
+                (Press(LEFT), 1.0, 0.0, 200),
+                (Release(LEFT), 1.0, 0.0, 210),
+            ],
+        );
+        assert_eq!(
+            out[1],
+            PointerOutput::Released {
+                button: LEFT,
+                x: 1.0,
+                y: 0.0,
+                recovered: true
+            }
+        );
+        assert_eq!(clicks(&out), vec![1]);
+    }
+
+    #[test]
+    fn test_other_buttons_while_held() {
+        let mut pointer = PointerStateMachine::default();
+        let out = run(
+            &mut pointer,
+            &[
+                (Press(LEFT), 0.0, 0.0, 0),
+                (Press(Button::Right), 0.0, 0.0, 10),
+                (Release(Button::Right), 0.0, 0.0, 20),
+                (Release(LEFT), 0.0, 0.0, 30),
+            ],
+        );
+        assert_eq!(clicks(&out), vec![1]);
+        assert_eq!(pointer.held(), None);
+
+        // A drag whose press came before tracking
+        let mut pointer = PointerStateMachine::default();
+        let out = run(&mut pointer, &[(Move(Some(LEFT)), 5.0, 5.0, 0)]);
+        assert_eq!(
+            out,
+            vec![PointerOutput::Dragged {
+                button: LEFT,
+                x: 5.0,
+                y: 5.0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_zero_threshold_drags_on_any_move() {
+        let mut pointer = PointerStateMachine::new(PointerConfig {
+            drag_threshold: 0.0,
+            ..PointerConfig::default()
+        });
+        let out = run(
+            &mut pointer,
+            &[(Press(LEFT), 0.0, 0.0, 0), (Move(Some(LEFT)), 0.5, 0.0, 10)],
+        );
+        assert!(matches!(out[1], PointerOutput::DragStarted { .. }));
+    }
+}
@@ -0,0 +1,473 @@
+//! Composable input transformations on top of grab mode.
+//!
+//! A [`Pipeline`] is a stack of [`Transform`] stages. Every grabbed event
+//! enters the first stage; each stage decides to pass it on, consume it, or
+//! hold it back until it knows more (a later event or elapsed time). Held
+//! events can later be replayed in order or dropped, and stages can emit new
+//! events. Replayed and emitted events continue through the stages after the
+//! one that produced them and are then injected through an [`Injector`].
+//!
+//! Ready-made transformations live in [`presets`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use monio::Hook;
+//! use monio::transform::{Pipeline, presets};
+//! use std::time::Duration;
+//!
+//! let pipeline = Pipeline::new()
+//!     .with(presets::long_press_to_middle_click(Duration::from_millis(400)))
+//!     .with(presets::double_tap_shift_to_caps_lock(Duration::from_millis(300)));
+//!
+//! let hook = Hook::new();
+//! hook.grab(pipeline.into_handler()).unwrap();
+//! ```
+//!
+//! # Ordering
+//!
+//! Events returned to the OS from the grab callback are delivered
+//! immediately, while injected events are queued behind them. A stage that
+//! replays held events should therefore also consume and re-emit the event
+//! that triggered the replay, so the replayed events stay in front of it.
+//!
+//! On Linux/X11 grabbing is not supported, so held events still reach other
+//! applications and replays duplicate them.
+
+pub mod presets;
+
+use crate::error::Result;
+use crate::event::{Event, EventType};
+use crate::hook::GrabHandler;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often [`PipelineHandler`] ticks the pipeline.
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long an injected event is expected to come back through the hook.
+const ECHO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What a stage does with the event it was given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Hand the event to the next stage (or the OS after the last stage).
+    Pass,
+    /// Suppress the event.
+    Consume,
+    /// Suppress the event for now and buffer it in this stage until it is
+    /// replayed or dropped through [`Context`].
+    Hold,
+}
+
+/// Side effects a stage requests while handling an event or a tick.
+#[derive(Debug, Default)]
+pub struct Context {
+    emitted: Vec<Event>,
+    release: Option<Release>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Release {
+    Replay,
+    Drop,
+}
+
+impl Context {
+    /// Emit a new event after any replayed ones.
+    pub fn emit(&mut self, event: Event) {
+        self.emitted.push(event);
+    }
+
+    /// Replay the events this stage is holding, in the order they arrived.
+    pub fn replay_held(&mut self) {
+        self.release = Some(Release::Replay);
+    }
+
+    /// Discard the events this stage is holding.
+    pub fn drop_held(&mut self) {
+        self.release = Some(Release::Drop);
+    }
+}
+
+/// A single input transformation stage.
+pub trait Transform: Send {
+    /// Handle an event reaching this stage.
+    fn handle(&mut self, event: &Event, ctx: &mut Context) -> Action;
+
+    /// Called periodically, so time-based stages can act without new input.
+    fn tick(&mut self, _now: SystemTime, _ctx: &mut Context) {}
+}
+
+/// Delivers events produced by the pipeline.
+pub trait Injector: Send + Sync {
+    /// Inject an event into the system input stream.
+    fn inject(&self, event: &Event) -> Result<()>;
+}
+
+/// Injects events with [`simulate`](crate::simulate).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemInjector;
+
+impl Injector for SystemInjector {
+    fn inject(&self, event: &Event) -> Result<()> {
+        crate::simulate(event)
+    }
+}
+
+struct Stage {
+    transform: Box<dyn Transform>,
+    held: Vec<Event>,
+}
+
+/// A stack of transformations applied to grabbed events.
+pub struct Pipeline {
+    stages: Vec<Stage>,
+    injector: Arc<dyn Injector>,
+    /// Injected events not yet seen coming back through the hook.
+    echoes: VecDeque<(Event, Instant)>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pipeline {
+    /// Create an empty pipeline that injects through [`SystemInjector`].
+    pub fn new() -> Self {
+        Self {
+            stages: Vec::new(),
+            injector: Arc::new(SystemInjector),
+            echoes: VecDeque::new(),
+        }
+    }
+
+    /// Replace the injector, e.g. with a recording one in tests.
+    pub fn with_injector(mut self, injector: Arc<dyn Injector>) -> Self {
+        self.injector = injector;
+        self
+    }
+
+    /// Append a stage. Stages see events in the order they were added.
+    pub fn with(mut self, transform: impl Transform + 'static) -> Self {
+        self.stages.push(Stage {
+            transform: Box::new(transform),
+            held: Vec::new(),
+        });
+        self
+    }
+
+    /// Run a grabbed event through the pipeline.
+    ///
+    /// Returns the event to pass on to the OS, or `None` to suppress it.
+    /// Events the pipeline injected itself pass through untouched.
+    pub fn process(&mut self, event: &Event) -> Option<Event> {
+        if self.take_echo(event) {
+            return Some(event.clone());
+        }
+        self.run(0, event.clone())
+    }
+
+    /// Let time-based stages act. Called periodically by [`PipelineHandler`].
+    pub fn tick(&mut self, now: SystemTime) {
+        for index in 0..self.stages.len() {
+            let mut ctx = Context::default();
+            self.stages[index].transform.tick(now, &mut ctx);
+            self.apply(index, ctx);
+        }
+    }
+
+    /// Number of events currently held across all stages.
+    pub fn held_count(&self) -> usize {
+        self.stages.iter().map(|stage| stage.held.len()).sum()
+    }
+
+    /// Wrap the pipeline in a grab handler that also ticks it in the background.
+    pub fn into_handler(self) -> PipelineHandler {
+        PipelineHandler::new(self)
+    }
+
+    fn run(&mut self, start: usize, event: Event) -> Option<Event> {
+        for index in start..self.stages.len() {
+            let mut ctx = Context::default();
+            let action = self.stages[index].transform.handle(&event, &mut ctx);
+            if action == Action::Hold {
+                self.stages[index].held.push(event.clone());
+            }
+            self.apply(index, ctx);
+            if action != Action::Pass {
+                return None;
+            }
+        }
+        Some(event)
+    }
+
+    /// Carry out a stage's requests; outputs continue after that stage.
+    fn apply(&mut self, index: usize, ctx: Context) {
+        let mut outputs = match ctx.release {
+            Some(Release::Replay) => std::mem::take(&mut self.stages[index].held),
+            Some(Release::Drop) => {
+                self.stages[index].held.clear();
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        outputs.extend(ctx.emitted);
+
+        for output in outputs {
+            if let Some(output) = self.run(index + 1, output) {
+                self.inject(output);
+            }
+        }
+    }
+
+    fn inject(&mut self, event: Event) {
+        match self.injector.inject(&event) {
+            Ok(()) => self.echoes.push_back((event, Instant::now())),
+            Err(e) => log::warn!("Failed to inject {:?}: {}", event.event_type, e),
+        }
+    }
+
+    /// Check whether an event is one we injected, forgetting it if so.
+    fn take_echo(&mut self, event: &Event) -> bool {
+        let now = Instant::now();
+        while self
+            .echoes
+            .front()
+            .is_some_and(|(_, at)| now.duration_since(*at) > ECHO_TIMEOUT)
+        {
+            self.echoes.pop_front();
+        }
+
+        let position = self
+            .echoes
+            .iter()
+            .position(|(injected, _)| same_input(injected, event));
+        position.is_some_and(|index| self.echoes.remove(index).is_some())
+    }
+}
+
+/// Whether two events describe the same input (ignoring time and position).
+fn same_input(a: &Event, b: &Event) -> bool {
+    if a.event_type != b.event_type {
+        return false;
+    }
+    match a.event_type {
+        EventType::KeyPressed | EventType::KeyReleased | EventType::KeyTyped => {
+            a.keyboard.as_ref().map(|kb| kb.key) == b.keyboard.as_ref().map(|kb| kb.key)
+        }
+        EventType::MousePressed | EventType::MouseReleased | EventType::MouseClicked => {
+            a.mouse.as_ref().and_then(|m| m.button) == b.mouse.as_ref().and_then(|m| m.button)
+        }
+        EventType::MouseWheel => {
+            a.wheel.as_ref().map(|w| w.direction) == b.wheel.as_ref().map(|w| w.direction)
+        }
+        _ => true,
+    }
+}
+
+/// Grab handler driving a [`Pipeline`], ticking it from a background thread.
+///
+/// The ticker thread stops when the handler is dropped.
+pub struct PipelineHandler {
+    pipeline: Arc<Mutex<Pipeline>>,
+    ticking: Arc<AtomicBool>,
+}
+
+impl PipelineHandler {
+    fn new(pipeline: Pipeline) -> Self {
+        let pipeline = Arc::new(Mutex::new(pipeline));
+        let ticking = Arc::new(AtomicBool::new(true));
+
+        let thread_pipeline = pipeline.clone();
+        let thread_ticking = ticking.clone();
+        std::thread::spawn(move || {
+            while thread_ticking.load(Ordering::SeqCst) {
+                std::thread::sleep(TICK_INTERVAL);
+                if let Ok(mut pipeline) = thread_pipeline.lock() {
+                    pipeline.tick(SystemTime::now());
+                }
+            }
+        });
+
+        Self { pipeline, ticking }
+    }
+}
+
+impl GrabHandler for PipelineHandler {
+    fn handle_event(&self, event: &Event) -> Option<Event> {
+        match self.pipeline.lock() {
+            Ok(mut pipeline) => pipeline.process(event),
+            Err(_) => Some(event.clone()),
+        }
+    }
+}
+
+impl Drop for PipelineHandler {
+    fn drop(&mut self) {
+        self.ticking.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::event::Button;
+    use crate::keycode::Key;
+
+    /// Injector that records events instead of delivering them.
+    #[derive(Default)]
+    pub(crate) struct RecordingInjector {
+        pub(crate) events: Mutex<Vec<Event>>,
+    }
+
+    impl RecordingInjector {
+        pub(crate) fn take(&self) -> Vec<Event> {
+            std::mem::take(&mut *self.events.lock().unwrap())
+        }
+    }
+
+    impl Injector for RecordingInjector {
+        fn inject(&self, event: &Event) -> Result<()> {
+            self.events.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    /// Holds key presses until the next release, then replays or drops them.
+    struct HoldUntilRelease {
+        replay: bool,
+    }
+
+    impl Transform for HoldUntilRelease {
+        fn handle(&mut self, event: &Event, ctx: &mut Context) -> Action {
+            match event.event_type {
+                EventType::KeyPressed => Action::Hold,
+                EventType::KeyReleased if self.replay => {
+                    ctx.replay_held();
+                    ctx.emit(event.clone());
+                    Action::Consume
+                }
+                EventType::KeyReleased => {
+                    ctx.drop_held();
+                    Action::Pass
+                }
+                _ => Action::Pass,
+            }
+        }
+    }
+
+    /// Maps KeyA to KeyB.
+    struct AToB;
+
+    impl Transform for AToB {
+        fn handle(&mut self, event: &Event, ctx: &mut Context) -> Action {
+            match &event.keyboard {
+                Some(kb) if kb.key == Key::KeyA => {
+                    let mut mapped = event.clone();
+                    if let Some(kb) = mapped.keyboard.as_mut() {
+                        kb.key = Key::KeyB;
+                    }
+                    ctx.emit(mapped);
+                    Action::Consume
+                }
+                _ => Action::Pass,
+            }
+        }
+    }
+
+    fn keys(events: &[Event]) -> Vec<(EventType, Key)> {
+        events
+            .iter()
+            .map(|e| (e.event_type, e.keyboard.as_ref().unwrap().key))
+            .collect()
+    }
+
+    #[test]
+    fn test_hold_and_replay_in_order() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(HoldUntilRelease { replay: true })
+            .with_injector(injector.clone());
+
+        assert!(
+            pipeline
+                .process(&Event::key_pressed(Key::KeyX, 0))
+                .is_none()
+        );
+        assert!(
+            pipeline
+                .process(&Event::key_pressed(Key::KeyY, 0))
+                .is_none()
+        );
+        assert_eq!(pipeline.held_count(), 2);
+        assert!(
+            pipeline
+                .process(&Event::key_released(Key::KeyY, 0))
+                .is_none()
+        );
+
+        assert_eq!(
+            keys(&injector.take()),
+            vec![
+                (EventType::KeyPressed, Key::KeyX),
+                (EventType::KeyPressed, Key::KeyY),
+                (EventType::KeyReleased, Key::KeyY),
+            ]
+        );
+        assert_eq!(pipeline.held_count(), 0);
+
+        // The injected events come back through the hook and pass untouched
+        let echo = Event::key_pressed(Key::KeyX, 0);
+        assert_eq!(pipeline.process(&echo), Some(echo));
+    }
+
+    #[test]
+    fn test_hold_and_drop() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(HoldUntilRelease { replay: false })
+            .with_injector(injector.clone());
+
+        assert!(
+            pipeline
+                .process(&Event::key_pressed(Key::KeyX, 0))
+                .is_none()
+        );
+        assert!(
+            pipeline
+                .process(&Event::key_released(Key::KeyX, 0))
+                .is_some()
+        );
+        assert!(injector.take().is_empty());
+        assert_eq!(pipeline.held_count(), 0);
+    }
+
+    #[test]
+    fn test_stacked_stages() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(HoldUntilRelease { replay: true })
+            .with(AToB)
+            .with_injector(injector.clone());
+
+        pipeline.process(&Event::key_pressed(Key::KeyA, 0));
+        pipeline.process(&Event::key_released(Key::KeyA, 0));
+
+        // Replayed events run through the later stage before injection
+        assert_eq!(
+            keys(&injector.take()),
+            vec![
+                (EventType::KeyPressed, Key::KeyB),
+                (EventType::KeyReleased, Key::KeyB),
+            ]
+        );
+
+        let click = Event::mouse_pressed(Button::Left, 0.0, 0.0);
+        assert_eq!(pipeline.process(&click), Some(click));
+    }
+}
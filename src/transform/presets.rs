@@ -0,0 +1,982 @@
+//! Ready-made transformations for common remapping needs.
+//!
+//! Each preset returns a [`Transform`] that can be stacked with others in a
+//! [`Pipeline`](super::Pipeline).
+
+use super::{Action, Context, Transform};
+use crate::event::{Button, Event, EventType, ScrollDirection};
+use crate::keycode::Key;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Turn a right-button long press into a middle click.
+///
+/// The right press is held back. Released before `threshold`, it is replayed
+/// as a normal right click; held past `threshold`, it becomes a middle button
+/// press, released together with the right button.
+pub fn long_press_to_middle_click(threshold: Duration) -> LongPressToMiddleClick {
+    LongPressToMiddleClick {
+        threshold,
+        pressed: None,
+        converted: false,
+    }
+}
+
+/// Scroll horizontally while `key` is held: wheel up scrolls left, down scrolls right.
+pub fn scroll_horizontally_while_held(key: Key) -> ScrollHorizontallyWhileHeld {
+    ScrollHorizontallyWhileHeld { key, held: false }
+}
+
+/// Toggle Caps Lock by tapping Shift twice within `window`.
+///
+/// The Shift taps themselves still pass through.
+pub fn double_tap_shift_to_caps_lock(window: Duration) -> DoubleTapShiftToCapsLock {
+    DoubleTapShiftToCapsLock {
+        window,
+        last_tap: None,
+        tap_pending: false,
+    }
+}
+
+/// Sticky keys: tapping a modifier latches it for the next key.
+///
+/// A modifier press is held back until it's clear what it's for. If another
+/// key or a mouse button is pressed while the modifier is down, the press is
+/// replayed and the chord works as usual. If the modifier is released first,
+/// it latches: the next key press (not mouse click) is delivered with the
+/// latched modifiers pressed around it, and they are released with that key.
+///
+/// Tapping a latched modifier again locks it (see
+/// [`StickyKeysConfig::with_lock_on_double_tap`]) or cancels the latch.
+/// Tapping a locked modifier unlocks it.
+pub fn sticky_keys(config: StickyKeysConfig) -> StickyKeys {
+    StickyKeys {
+        config,
+        down: Vec::new(),
+        pending: Vec::new(),
+        latched: Vec::new(),
+        locked: Vec::new(),
+        applied: None,
+    }
+}
+
+/// Slow keys: ignore key presses shorter than `min_hold`.
+///
+/// Each key press is held back and only delivered once the key has been down
+/// for `min_hold`; keys released earlier are dropped entirely. Autorepeat
+/// while waiting is swallowed.
+pub fn slow_keys(min_hold: Duration) -> SlowKeys {
+    SlowKeys {
+        min_hold,
+        pending: Vec::new(),
+        accepted: Vec::new(),
+    }
+}
+
+/// Debounce chattering keys: drop a key press that comes within `window` of
+/// the same key's release.
+///
+/// Worn switches can turn one physical press into several press/release
+/// pairs a few milliseconds apart. The first press and release pass through
+/// as they arrive, without delay; a press following the release within
+/// `window` is dropped, along with its autorepeat and its release, so the
+/// bounce never registers. A key genuinely pressed again within `window` is
+/// dropped too, so keep the window short (5–30 ms).
+///
+/// Dropped bounces are counted per key in [`Debounce::chatter_counts`], to
+/// find the faulty switch. Outside a pipeline, e.g. in a listener, use
+/// [`Debounce::filter`]:
+///
+/// ```no_run
+/// use monio::transform::presets;
+/// use monio::{Event, Hook};
+/// use std::sync::Mutex;
+/// use std::time::Duration;
+///
+/// let debounce = Mutex::new(presets::debounce(Duration::from_millis(15)));
+/// let counts = debounce.lock().unwrap().chatter_counts();
+///
+/// let hook = Hook::new();
+/// hook.run_async(move |event: &Event| {
+///     if debounce.lock().unwrap().filter(event) {
+///         println!("{:?}", event.event_type);
+///     }
+/// })
+/// .unwrap();
+/// # std::thread::sleep(Duration::from_secs(60));
+/// println!("Chatter: {:?}", counts.snapshot());
+/// ```
+pub fn debounce(window: Duration) -> Debounce {
+    Debounce {
+        window,
+        released: HashMap::new(),
+        bouncing: HashSet::new(),
+        counts: ChatterCounts::default(),
+    }
+}
+
+/// Configuration for [`sticky_keys`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StickyKeysConfig {
+    /// Tapping a latched modifier again locks it until it's tapped once more.
+    pub lock_on_double_tap: bool,
+    /// Latches not used within this time are released. Locks never expire.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for StickyKeysConfig {
+    fn default() -> Self {
+        Self {
+            lock_on_double_tap: true,
+            timeout: Some(Duration::from_secs(5)),
+        }
+    }
+}
+
+impl StickyKeysConfig {
+    /// Set whether a double tap locks the modifier (otherwise it cancels the latch).
+    pub fn with_lock_on_double_tap(mut self, lock: bool) -> Self {
+        self.lock_on_double_tap = lock;
+        self
+    }
+
+    /// Set how long a latch waits for a key, or `None` to wait forever.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// See [`long_press_to_middle_click`].
+#[derive(Debug, Clone)]
+pub struct LongPressToMiddleClick {
+    threshold: Duration,
+    /// Time and position of the held right press.
+    pressed: Option<(SystemTime, f64, f64)>,
+    converted: bool,
+}
+
+impl LongPressToMiddleClick {
+    fn convert_if_due(&mut self, now: SystemTime, ctx: &mut Context) {
+        let Some((at, x, y)) = self.pressed else {
+            return;
+        };
+        if self.converted || now.duration_since(at).unwrap_or_default() < self.threshold {
+            return;
+        }
+
+        self.converted = true;
+        ctx.drop_held();
+        ctx.emit(Event::mouse_pressed(Button::Middle, x, y));
+    }
+}
+
+impl Transform for LongPressToMiddleClick {
+    fn handle(&mut self, event: &Event, ctx: &mut Context) -> Action {
+        self.convert_if_due(event.time, ctx);
+
+        let Some(mouse) = &event.mouse else {
+            return Action::Pass;
+        };
+        if mouse.button != Some(Button::Right) {
+            return Action::Pass;
+        }
+
+        match event.event_type {
+            EventType::MousePressed if self.pressed.is_none() => {
+                self.pressed = Some((event.time, mouse.x, mouse.y));
+                Action::Hold
+            }
+            EventType::MouseReleased if self.pressed.is_some() => {
+                if std::mem::take(&mut self.converted) {
+                    ctx.emit(Event::mouse_released(Button::Middle, mouse.x, mouse.y));
+                } else {
+                    // Keep the replayed press ahead of its release
+                    ctx.replay_held();
+                    ctx.emit(event.clone());
+                }
+                self.pressed = None;
+                Action::Consume
+            }
+            _ => Action::Pass,
+        }
+    }
+
+    fn tick(&mut self, now: SystemTime, ctx: &mut Context) {
+        self.convert_if_due(now, ctx);
+    }
+}
+
+/// See [`scroll_horizontally_while_held`].
+#[derive(Debug, Clone)]
+pub struct ScrollHorizontallyWhileHeld {
+    key: Key,
+    held: bool,
+}
+
+impl Transform for ScrollHorizontallyWhileHeld {
+    fn handle(&mut self, event: &Event, ctx: &mut Context) -> Action {
+        match event.event_type {
+            EventType::KeyPressed | EventType::KeyReleased => {
+                if event.keyboard.as_ref().is_some_and(|kb| kb.key == self.key) {
+                    self.held = event.event_type == EventType::KeyPressed;
+                }
+                Action::Pass
+            }
+            EventType::MouseWheel if self.held => {
+                let Some(wheel) = &event.wheel else {
+                    return Action::Pass;
+                };
+                let direction = match wheel.direction {
+                    ScrollDirection::Up => ScrollDirection::Left,
+                    ScrollDirection::Down => ScrollDirection::Right,
+                    _ => return Action::Pass,
+                };
+                ctx.emit(Event::mouse_wheel(wheel.x, wheel.y, direction, wheel.delta));
+                Action::Consume
+            }
+            _ => Action::Pass,
+        }
+    }
+}
+
+/// See [`double_tap_shift_to_caps_lock`].
+#[derive(Debug, Clone)]
+pub struct DoubleTapShiftToCapsLock {
+    window: Duration,
+    /// When the previous clean Shift tap was released.
+    last_tap: Option<SystemTime>,
+    /// Shift is down with no other key pressed since.
+    tap_pending: bool,
+}
+
+impl Transform for DoubleTapShiftToCapsLock {
+    fn handle(&mut self, event: &Event, ctx: &mut Context) -> Action {
+        let Some(kb) = &event.keyboard else {
+            return Action::Pass;
+        };
+        let is_shift = matches!(kb.key, Key::ShiftLeft | Key::ShiftRight);
+
+        match event.event_type {
+            EventType::KeyPressed if is_shift => {
+                // Autorepeat keeps a pending tap alive
+                self.tap_pending = true;
+            }
+            EventType::KeyPressed => {
+                self.tap_pending = false;
+                self.last_tap = None;
+            }
+            EventType::KeyReleased if is_shift && self.tap_pending => {
+                self.tap_pending = false;
+                let double = self.last_tap.is_some_and(|last| {
+                    event.time.duration_since(last).unwrap_or_default() <= self.window
+                });
+                if double {
+                    self.last_tap = None;
+                    ctx.emit(Event::key_pressed(Key::CapsLock, 0));
+                    ctx.emit(Event::key_released(Key::CapsLock, 0));
+                } else {
+                    self.last_tap = Some(event.time);
+                }
+            }
+            _ => {}
+        }
+        Action::Pass
+    }
+}
+
+/// See [`sticky_keys`].
+#[derive(Debug, Clone)]
+pub struct StickyKeys {
+    config: StickyKeysConfig,
+    /// Modifiers physically down.
+    down: Vec<Key>,
+    /// Presses of modifiers in `down` that haven't been delivered yet.
+    pending: Vec<Event>,
+    /// Tapped modifiers, and when they were tapped.
+    latched: Vec<(Key, SystemTime)>,
+    locked: Vec<Key>,
+    /// The key the latched modifiers were applied to, and those modifiers.
+    applied: Option<(Key, Vec<Key>)>,
+}
+
+impl StickyKeys {
+    /// Deliver held modifier presses: they're part of a chord.
+    fn flush_pending(&mut self, ctx: &mut Context) -> bool {
+        let flushed = !self.pending.is_empty();
+        for press in self.pending.drain(..) {
+            ctx.emit(press);
+        }
+        flushed
+    }
+
+    fn tap(&mut self, key: Key, time: SystemTime) {
+        if let Some(index) = self.locked.iter().position(|k| *k == key) {
+            self.locked.remove(index);
+        } else if let Some(index) = self.latched.iter().position(|(k, _)| *k == key) {
+            self.latched.remove(index);
+            if self.config.lock_on_double_tap {
+                self.locked.push(key);
+            }
+        } else {
+            self.latched.push((key, time));
+        }
+    }
+
+    fn handle_modifier(&mut self, event: &Event, key: Key) -> Action {
+        match event.event_type {
+            EventType::KeyPressed if self.down.contains(&key) => {
+                // Autorepeat: only pass it on once the press was delivered
+                if self.pending.iter().any(|press| is_key(press, key)) {
+                    Action::Consume
+                } else {
+                    Action::Pass
+                }
+            }
+            EventType::KeyPressed => {
+                self.down.push(key);
+                // Part of a chord already under way
+                if self.pending.is_empty() && self.down.len() > 1 {
+                    return Action::Pass;
+                }
+                self.pending.push(event.clone());
+                Action::Consume
+            }
+            EventType::KeyReleased => {
+                self.down.retain(|k| *k != key);
+                let tapped = self.pending.iter().position(|press| is_key(press, key));
+                match tapped {
+                    Some(index) => {
+                        self.pending.remove(index);
+                        self.tap(key, event.time);
+                        Action::Consume
+                    }
+                    None => Action::Pass,
+                }
+            }
+            _ => Action::Pass,
+        }
+    }
+}
+
+impl Transform for StickyKeys {
+    fn handle(&mut self, event: &Event, ctx: &mut Context) -> Action {
+        self.tick(event.time, ctx);
+
+        if let Some(kb) = &event.keyboard
+            && kb.key.is_modifier()
+        {
+            return self.handle_modifier(event, kb.key);
+        }
+
+        match event.event_type {
+            EventType::KeyPressed => {
+                let Some(key) = event.keyboard.as_ref().map(|kb| kb.key) else {
+                    return Action::Pass;
+                };
+                let flushed = self.flush_pending(ctx);
+
+                if self.applied.is_none() {
+                    let sticky: Vec<Key> = self
+                        .locked
+                        .iter()
+                        .copied()
+                        .chain(self.latched.drain(..).map(|(k, _)| k))
+                        .filter(|k| !self.down.contains(k))
+                        .collect();
+                    if !sticky.is_empty() {
+                        for modifier in &sticky {
+                            ctx.emit(Event::key_pressed(*modifier, 0));
+                        }
+                        ctx.emit(event.clone());
+                        self.applied = Some((key, sticky));
+                        return Action::Consume;
+                    }
+                }
+
+                if flushed {
+                    // Keep the key behind the modifiers just replayed
+                    ctx.emit(event.clone());
+                    return Action::Consume;
+                }
+                Action::Pass
+            }
+            EventType::KeyReleased => {
+                let Some(key) = event.keyboard.as_ref().map(|kb| kb.key) else {
+                    return Action::Pass;
+                };
+                match self.applied.take() {
+                    Some((applied, modifiers)) if applied == key => {
+                        ctx.emit(event.clone());
+                        for modifier in modifiers.into_iter().rev() {
+                            ctx.emit(Event::key_released(modifier, 0));
+                        }
+                        Action::Consume
+                    }
+                    other => {
+                        self.applied = other;
+                        Action::Pass
+                    }
+                }
+            }
+            EventType::MousePressed if self.flush_pending(ctx) => {
+                ctx.emit(event.clone());
+                Action::Consume
+            }
+            _ => Action::Pass,
+        }
+    }
+
+    fn tick(&mut self, now: SystemTime, _ctx: &mut Context) {
+        if let Some(timeout) = self.config.timeout {
+            self.latched
+                .retain(|(_, at)| now.duration_since(*at).unwrap_or_default() < timeout);
+        }
+    }
+}
+
+/// See [`slow_keys`].
+#[derive(Debug, Clone)]
+pub struct SlowKeys {
+    min_hold: Duration,
+    /// Presses waiting for `min_hold` to pass.
+    pending: Vec<Event>,
+    /// Keys whose press was delivered.
+    accepted: Vec<Key>,
+}
+
+impl SlowKeys {
+    /// Deliver presses held long enough, returning whether there were any.
+    fn accept_due(&mut self, now: SystemTime, ctx: &mut Context) -> bool {
+        let min_hold = self.min_hold;
+        let (due, waiting): (Vec<Event>, Vec<Event>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|press| now.duration_since(press.time).unwrap_or_default() >= min_hold);
+        self.pending = waiting;
+
+        for press in &due {
+            if let Some(kb) = &press.keyboard {
+                self.accepted.push(kb.key);
+            }
+            ctx.emit(press.clone());
+        }
+        !due.is_empty()
+    }
+}
+
+impl Transform for SlowKeys {
+    fn handle(&mut self, event: &Event, ctx: &mut Context) -> Action {
+        let flushed = self.accept_due(event.time, ctx);
+
+        let action = match (&event.keyboard, event.event_type) {
+            (Some(kb), EventType::KeyPressed) => {
+                if self.accepted.contains(&kb.key) {
+                    Action::Pass
+                } else {
+                    if !self.pending.iter().any(|press| is_key(press, kb.key)) {
+                        self.pending.push(event.clone());
+                    }
+                    Action::Consume
+                }
+            }
+            (Some(kb), EventType::KeyReleased) => {
+                let before = self.pending.len();
+                self.pending.retain(|press| !is_key(press, kb.key));
+                if self.pending.len() < before {
+                    // Released too soon
+                    Action::Consume
+                } else {
+                    self.accepted.retain(|key| *key != kb.key);
+                    Action::Pass
+                }
+            }
+            _ => Action::Pass,
+        };
+
+        if flushed && action == Action::Pass {
+            // Keep it behind the presses just delivered
+            ctx.emit(event.clone());
+            return Action::Consume;
+        }
+        action
+    }
+
+    fn tick(&mut self, now: SystemTime, ctx: &mut Context) {
+        self.accept_due(now, ctx);
+    }
+}
+
+/// See [`debounce`].
+#[derive(Debug, Clone)]
+pub struct Debounce {
+    window: Duration,
+    /// When each key was last released, including dropped releases.
+    released: HashMap<Key, SystemTime>,
+    /// Keys whose bounce press was dropped, until their release.
+    bouncing: HashSet<Key>,
+    counts: ChatterCounts,
+}
+
+impl Debounce {
+    /// Whether `event` should be kept, i.e. is not chatter.
+    pub fn filter(&mut self, event: &Event) -> bool {
+        let Some(kb) = &event.keyboard else {
+            return true;
+        };
+        match event.event_type {
+            EventType::KeyPressed if self.bouncing.contains(&kb.key) => false,
+            EventType::KeyPressed => {
+                let bounced = self.released.get(&kb.key).is_some_and(|released| {
+                    event.time.duration_since(*released).unwrap_or_default() < self.window
+                });
+                if bounced {
+                    self.bouncing.insert(kb.key);
+                    self.counts.add(kb.key);
+                }
+                !bounced
+            }
+            EventType::KeyReleased => {
+                // Releases of dropped presses can bounce too
+                self.released.insert(kb.key, event.time);
+                !self.bouncing.remove(&kb.key)
+            }
+            _ => true,
+        }
+    }
+
+    /// A handle to the number of bounces dropped per key.
+    ///
+    /// The handle stays live after the stage is moved into a
+    /// [`Pipeline`](super::Pipeline).
+    pub fn chatter_counts(&self) -> ChatterCounts {
+        self.counts.clone()
+    }
+}
+
+impl Transform for Debounce {
+    fn handle(&mut self, event: &Event, _ctx: &mut Context) -> Action {
+        if self.filter(event) {
+            Action::Pass
+        } else {
+            Action::Consume
+        }
+    }
+}
+
+/// Bounces dropped by a [`Debounce`], per key. Clones share the counts.
+#[derive(Debug, Clone, Default)]
+pub struct ChatterCounts(Arc<Mutex<HashMap<Key, u64>>>);
+
+impl ChatterCounts {
+    fn add(&self, key: Key) {
+        if let Ok(mut counts) = self.0.lock() {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+
+    /// Bounces dropped for `key`.
+    pub fn get(&self, key: Key) -> u64 {
+        self.0
+            .lock()
+            .map(|counts| counts.get(&key).copied().unwrap_or(0))
+            .unwrap_or(0)
+    }
+
+    /// Bounces dropped across all keys.
+    pub fn total(&self) -> u64 {
+        self.0
+            .lock()
+            .map(|counts| counts.values().sum())
+            .unwrap_or(0)
+    }
+
+    /// The counts of every key that chattered.
+    pub fn snapshot(&self) -> HashMap<Key, u64> {
+        self.0
+            .lock()
+            .map(|counts| counts.clone())
+            .unwrap_or_default()
+    }
+
+    /// Forget all counts.
+    pub fn reset(&self) {
+        if let Ok(mut counts) = self.0.lock() {
+            counts.clear();
+        }
+    }
+}
+
+fn is_key(event: &Event, key: Key) -> bool {
+    event.keyboard.as_ref().is_some_and(|kb| kb.key == key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::Pipeline;
+    use crate::transform::tests::RecordingInjector;
+    use std::sync::Arc;
+
+    fn at(event: Event, time: SystemTime) -> Event {
+        Event { time, ..event }
+    }
+
+    fn buttons(events: &[Event]) -> Vec<(EventType, Button)> {
+        events
+            .iter()
+            .map(|e| (e.event_type, e.mouse.as_ref().unwrap().button.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_long_press_short_click_is_replayed() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(long_press_to_middle_click(Duration::from_millis(300)))
+            .with_injector(injector.clone());
+        let t0 = SystemTime::now();
+
+        let press = at(Event::mouse_pressed(Button::Right, 1.0, 2.0), t0);
+        let release = at(
+            Event::mouse_released(Button::Right, 1.0, 2.0),
+            t0 + Duration::from_millis(100),
+        );
+        assert!(pipeline.process(&press).is_none());
+        assert!(pipeline.process(&release).is_none());
+
+        assert_eq!(
+            buttons(&injector.take()),
+            vec![
+                (EventType::MousePressed, Button::Right),
+                (EventType::MouseReleased, Button::Right),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_press_becomes_middle_click() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(long_press_to_middle_click(Duration::from_millis(300)))
+            .with_injector(injector.clone());
+        let t0 = SystemTime::now();
+
+        pipeline.process(&at(Event::mouse_pressed(Button::Right, 1.0, 2.0), t0));
+        pipeline.tick(t0 + Duration::from_millis(100));
+        assert!(injector.take().is_empty());
+
+        pipeline.tick(t0 + Duration::from_millis(400));
+        assert_eq!(
+            buttons(&injector.take()),
+            vec![(EventType::MousePressed, Button::Middle)]
+        );
+        assert_eq!(pipeline.held_count(), 0);
+
+        let release = at(
+            Event::mouse_released(Button::Right, 1.0, 2.0),
+            t0 + Duration::from_millis(500),
+        );
+        assert!(pipeline.process(&release).is_none());
+        assert_eq!(
+            buttons(&injector.take()),
+            vec![(EventType::MouseReleased, Button::Middle)]
+        );
+    }
+
+    #[test]
+    fn test_scroll_horizontally_while_held() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(scroll_horizontally_while_held(Key::ShiftLeft))
+            .with_injector(injector.clone());
+
+        let wheel = Event::mouse_wheel(0.0, 0.0, ScrollDirection::Down, 1.0);
+        assert!(pipeline.process(&wheel).is_some());
+
+        pipeline.process(&Event::key_pressed(Key::ShiftLeft, 0));
+        assert!(pipeline.process(&wheel).is_none());
+        let injected = injector.take();
+        assert_eq!(injected.len(), 1);
+        assert_eq!(
+            injected[0].wheel.as_ref().unwrap().direction,
+            ScrollDirection::Right
+        );
+
+        pipeline.process(&Event::key_released(Key::ShiftLeft, 0));
+        // A fresh wheel event (not an echo of the injected one) passes through
+        assert!(pipeline.process(&wheel).is_some());
+    }
+
+    #[test]
+    fn test_double_tap_shift() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(double_tap_shift_to_caps_lock(Duration::from_millis(300)))
+            .with_injector(injector.clone());
+        let t0 = SystemTime::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+
+        let tap = |pipeline: &mut Pipeline, down, up| {
+            pipeline.process(&at(Event::key_pressed(Key::ShiftLeft, 0), ms(down)));
+            pipeline.process(&at(Event::key_released(Key::ShiftLeft, 0), ms(up)));
+        };
+
+        // Too slow: no toggle
+        tap(&mut pipeline, 0, 50);
+        tap(&mut pipeline, 500, 550);
+        assert!(injector.take().is_empty());
+
+        // Interrupted by another key: no toggle
+        pipeline.process(&at(Event::key_pressed(Key::KeyA, 0), ms(600)));
+        tap(&mut pipeline, 650, 700);
+        assert!(injector.take().is_empty());
+
+        // Quick double tap toggles
+        tap(&mut pipeline, 800, 850);
+        let injected = injector.take();
+        assert_eq!(injected.len(), 2);
+        assert_eq!(injected[0].keyboard.as_ref().unwrap().key, Key::CapsLock);
+    }
+
+    fn keys(events: &[Event]) -> Vec<(EventType, Key)> {
+        events
+            .iter()
+            .map(|e| (e.event_type, e.keyboard.as_ref().unwrap().key))
+            .collect()
+    }
+
+    #[test]
+    fn test_sticky_keys_tap_latches() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(sticky_keys(StickyKeysConfig::default()))
+            .with_injector(injector.clone());
+
+        assert!(
+            pipeline
+                .process(&Event::key_pressed(Key::ShiftLeft, 0))
+                .is_none()
+        );
+        assert!(
+            pipeline
+                .process(&Event::key_released(Key::ShiftLeft, 0))
+                .is_none()
+        );
+        assert!(injector.take().is_empty());
+
+        assert!(
+            pipeline
+                .process(&Event::key_pressed(Key::KeyA, 0))
+                .is_none()
+        );
+        assert!(
+            pipeline
+                .process(&Event::key_released(Key::KeyA, 0))
+                .is_none()
+        );
+        assert_eq!(
+            keys(&injector.take()),
+            vec![
+                (EventType::KeyPressed, Key::ShiftLeft),
+                (EventType::KeyPressed, Key::KeyA),
+                (EventType::KeyReleased, Key::KeyA),
+                (EventType::KeyReleased, Key::ShiftLeft),
+            ]
+        );
+
+        // The latch is spent
+        let b = Event::key_pressed(Key::KeyB, 0);
+        assert_eq!(pipeline.process(&b), Some(b));
+    }
+
+    #[test]
+    fn test_sticky_keys_hold_is_a_normal_chord() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(sticky_keys(StickyKeysConfig::default()))
+            .with_injector(injector.clone());
+
+        assert!(
+            pipeline
+                .process(&Event::key_pressed(Key::ControlLeft, 0))
+                .is_none()
+        );
+        // Autorepeat while undecided is swallowed
+        assert!(
+            pipeline
+                .process(&Event::key_pressed(Key::ControlLeft, 0))
+                .is_none()
+        );
+        assert!(
+            pipeline
+                .process(&Event::key_pressed(Key::KeyC, 0))
+                .is_none()
+        );
+        assert_eq!(
+            keys(&injector.take()),
+            vec![
+                (EventType::KeyPressed, Key::ControlLeft),
+                (EventType::KeyPressed, Key::KeyC),
+            ]
+        );
+
+        let release_c = Event::key_released(Key::KeyC, 0);
+        assert_eq!(pipeline.process(&release_c), Some(release_c));
+        let release_ctrl = Event::key_released(Key::ControlLeft, 0);
+        assert_eq!(pipeline.process(&release_ctrl), Some(release_ctrl));
+
+        // Nothing latched
+        let a = Event::key_pressed(Key::KeyA, 0);
+        assert_eq!(pipeline.process(&a), Some(a));
+    }
+
+    #[test]
+    fn test_sticky_keys_cancel_lock_and_timeout() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(sticky_keys(
+                StickyKeysConfig::default().with_timeout(Some(Duration::from_secs(1))),
+            ))
+            .with_injector(injector.clone());
+        let t0 = SystemTime::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let tap = |pipeline: &mut Pipeline, key, time| {
+            pipeline.process(&at(Event::key_pressed(key, 0), time));
+            pipeline.process(&at(Event::key_released(key, 0), time));
+        };
+
+        // Double tap locks Alt for every following key
+        tap(&mut pipeline, Key::AltLeft, ms(0));
+        tap(&mut pipeline, Key::AltLeft, ms(100));
+        for time in [200, 5000] {
+            tap(&mut pipeline, Key::KeyX, ms(time));
+            let injected = injector.take();
+            assert_eq!(injected.len(), 4);
+            // The injected events come back through the hook
+            for echo in &injected {
+                assert_eq!(pipeline.process(echo).as_ref(), Some(echo));
+            }
+        }
+
+        // A third tap unlocks
+        tap(&mut pipeline, Key::AltLeft, ms(6000));
+        let w = at(Event::key_pressed(Key::KeyW, 0), ms(6100));
+        assert_eq!(pipeline.process(&w), Some(w));
+        pipeline.process(&at(Event::key_released(Key::KeyW, 0), ms(6100)));
+
+        // An unused latch expires
+        tap(&mut pipeline, Key::ShiftLeft, ms(7000));
+        pipeline.tick(ms(8500));
+        let v = at(Event::key_pressed(Key::KeyV, 0), ms(8600));
+        assert_eq!(pipeline.process(&v), Some(v));
+        assert!(injector.take().is_empty());
+
+        // Without locking, a double tap cancels the latch
+        let mut pipeline = Pipeline::new()
+            .with(sticky_keys(
+                StickyKeysConfig::default().with_lock_on_double_tap(false),
+            ))
+            .with_injector(injector.clone());
+        tap(&mut pipeline, Key::ShiftLeft, ms(0));
+        tap(&mut pipeline, Key::ShiftLeft, ms(100));
+        let z = at(Event::key_pressed(Key::KeyZ, 0), ms(200));
+        assert_eq!(pipeline.process(&z), Some(z));
+        assert!(injector.take().is_empty());
+    }
+
+    #[test]
+    fn test_slow_keys() {
+        let injector = Arc::new(RecordingInjector::default());
+        let mut pipeline = Pipeline::new()
+            .with(slow_keys(Duration::from_millis(300)))
+            .with_injector(injector.clone());
+        let t0 = SystemTime::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+
+        // A tap shorter than the threshold is dropped
+        assert!(
+            pipeline
+                .process(&at(Event::key_pressed(Key::KeyA, 0), ms(0)))
+                .is_none()
+        );
+        assert!(
+            pipeline
+                .process(&at(Event::key_released(Key::KeyA, 0), ms(100)))
+                .is_none()
+        );
+        pipeline.tick(ms(1000));
+        assert!(injector.take().is_empty());
+
+        // A long enough hold is delivered; autorepeat while waiting is not
+        pipeline.process(&at(Event::key_pressed(Key::KeyB, 0), ms(2000)));
+        pipeline.process(&at(Event::key_pressed(Key::KeyB, 0), ms(2200)));
+        pipeline.tick(ms(2250));
+        assert!(injector.take().is_empty());
+        pipeline.tick(ms(2310));
+        assert_eq!(
+            keys(&injector.take()),
+            vec![(EventType::KeyPressed, Key::KeyB)]
+        );
+        let repeat = at(Event::key_pressed(Key::KeyB, 0), ms(2400));
+        assert_eq!(pipeline.process(&repeat), Some(repeat));
+        let release = at(Event::key_released(Key::KeyB, 0), ms(2500));
+        assert_eq!(pipeline.process(&release), Some(release));
+
+        // A press that came due before the next event stays in front of it
+        pipeline.process(&at(Event::key_pressed(Key::KeyC, 0), ms(3000)));
+        let click = at(Event::mouse_pressed(Button::Left, 0.0, 0.0), ms(3400));
+        assert!(pipeline.process(&click).is_none());
+        let injected = injector.take();
+        assert_eq!(injected.len(), 2);
+        assert_eq!(injected[0].keyboard.as_ref().unwrap().key, Key::KeyC);
+        assert_eq!(injected[1].event_type, EventType::MousePressed);
+    }
+
+    #[test]
+    fn test_debounce_drops_chatter() {
+        let injector = Arc::new(RecordingInjector::default());
+        let stage = debounce(Duration::from_millis(10));
+        let counts = stage.chatter_counts();
+        let mut pipeline = Pipeline::new().with(stage).with_injector(injector.clone());
+        let t0 = SystemTime::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut passed = |event: Event| pipeline.process(&event).is_some();
+
+        // One physical press of A bouncing twice, with autorepeat in between
+        assert!(passed(at(Event::key_pressed(Key::KeyA, 0), ms(0))));
+        assert!(passed(at(Event::key_released(Key::KeyA, 0), ms(2))));
+        assert!(!passed(at(Event::key_pressed(Key::KeyA, 0), ms(5))));
+        assert!(!passed(at(Event::key_pressed(Key::KeyA, 0), ms(8))));
+        // Other keys are unaffected
+        assert!(passed(at(Event::key_pressed(Key::KeyB, 0), ms(9))));
+        assert!(!passed(at(Event::key_released(Key::KeyA, 0), ms(20))));
+        assert!(!passed(at(Event::key_pressed(Key::KeyA, 0), ms(22))));
+        assert!(!passed(at(Event::key_released(Key::KeyA, 0), ms(24))));
+        assert!(passed(at(Event::key_released(Key::KeyB, 0), ms(25))));
+
+        // The window runs from the last release, dropped or not
+        assert!(passed(at(Event::key_pressed(Key::KeyA, 0), ms(40))));
+        assert!(passed(at(Event::key_released(Key::KeyA, 0), ms(60))));
+
+        assert!(injector.take().is_empty());
+        assert_eq!(counts.get(Key::KeyA), 2);
+        assert_eq!(counts.get(Key::KeyB), 0);
+        assert_eq!(counts.total(), 2);
+        counts.reset();
+        assert!(counts.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_debounce_filter_keeps_other_events() {
+        let mut stage = debounce(Duration::from_millis(10));
+        let t0 = SystemTime::now();
+        assert!(stage.filter(&at(Event::key_released(Key::KeyA, 0), t0)));
+        assert!(stage.filter(&at(Event::mouse_pressed(Button::Left, 0.0, 0.0), t0)));
+        assert!(stage.filter(&at(Event::key_typed(Key::KeyA, 0, 'a'), t0)));
+        assert!(!stage.filter(&at(
+            Event::key_pressed(Key::KeyA, 0),
+            t0 + Duration::from_millis(1)
+        )));
+    }
+}
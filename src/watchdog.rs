@@ -0,0 +1,268 @@
+//! Recovering from mouse button releases the hook never saw.
+//!
+//! Hooks track held buttons from the press and release events they see, and
+//! report moves as [`MouseDragged`](crate::EventType::MouseDragged) while
+//! any is held. If a release gets lost (the lid closed mid-drag, a user
+//! switch, a grab interrupted), the button would stay "held" and every
+//! later move would be a drag.
+//!
+//! So once moves have been reported as drags for longer than a threshold
+//! (60 seconds by default) without any button changing, the hook asks the
+//! OS which buttons are really held. Buttons that aren't are released:
+//! a [`MouseReleased`](crate::EventType::MouseReleased) event is delivered
+//! for each, so state machines downstream can recover, and the move is
+//! reported as what it really is. Corrections are logged and counted in
+//! [`stuck_button_corrections`].
+//!
+//! Backends that can't query the OS (no Linux backend enabled) never
+//! correct anything.
+
+use crate::event::{Event, EventType};
+use crate::platform;
+use crate::state::{self, MASK_ALL_BUTTONS};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long buttons may look held during motion before they are checked.
+pub const DEFAULT_STUCK_BUTTON_THRESHOLD: Duration = Duration::from_secs(60);
+
+/// The threshold in milliseconds, or [`DISABLED`].
+static THRESHOLD_MS: AtomicU64 = AtomicU64::new(DEFAULT_STUCK_BUTTON_THRESHOLD.as_millis() as u64);
+const DISABLED: u64 = u64::MAX;
+
+static CORRECTIONS: AtomicU64 = AtomicU64::new(0);
+
+static WATCHDOG: Mutex<StuckButtons> = Mutex::new(StuckButtons::new());
+
+/// Check held buttons after `threshold` of motion without button changes,
+/// or never with `None`.
+pub fn set_stuck_button_threshold(threshold: Option<Duration>) {
+    let ms = threshold.map_or(DISABLED, |t| {
+        u64::try_from(t.as_millis()).unwrap_or(DISABLED - 1)
+    });
+    THRESHOLD_MS.store(ms, Ordering::Relaxed);
+}
+
+/// The threshold set with [`set_stuck_button_threshold`].
+pub fn stuck_button_threshold() -> Option<Duration> {
+    match THRESHOLD_MS.load(Ordering::Relaxed) {
+        DISABLED => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// How many buttons have been released because the OS no longer held them.
+pub fn stuck_button_corrections() -> u64 {
+    CORRECTIONS.load(Ordering::Relaxed)
+}
+
+/// Decides when to check held buttons against the OS.
+#[derive(Debug)]
+struct StuckButtons {
+    /// Buttons held at the last drag.
+    buttons: u32,
+    /// Since when `buttons` have been held unchanged.
+    since: Option<Instant>,
+}
+
+impl StuckButtons {
+    const fn new() -> Self {
+        Self {
+            buttons: 0,
+            since: None,
+        }
+    }
+
+    /// Buttons to release, for motion at `now` with `held` buttons tracked.
+    ///
+    /// `query` reads the buttons the OS holds; it is only called once the
+    /// same buttons have been held for `threshold`, and then at most once
+    /// per `threshold`.
+    fn on_motion(
+        &mut self,
+        now: Instant,
+        held: u32,
+        threshold: Duration,
+        query: impl FnOnce() -> Option<u32>,
+    ) -> u32 {
+        let held = held & MASK_ALL_BUTTONS;
+        if held == 0 || held != self.buttons {
+            self.buttons = held;
+            self.since = (held != 0).then_some(now);
+            return 0;
+        }
+        let since = *self.since.get_or_insert(now);
+        if now.saturating_duration_since(since) < threshold {
+            return 0;
+        }
+        self.since = Some(now);
+        let Some(real) = query() else {
+            return 0;
+        };
+        let stale = held & !real;
+        self.buttons = held & real;
+        stale
+    }
+}
+
+/// Releases to deliver before `event`, and what `event` really is, if the
+/// hook lost track of a button.
+pub(crate) fn check(event: &Event) -> Option<(Vec<Event>, Event)> {
+    // Plain moves only restart the clock
+    if !matches!(
+        event.event_type,
+        EventType::MouseMoved | EventType::MouseDragged
+    ) {
+        return None;
+    }
+    let threshold = stuck_button_threshold()?;
+    let stale = WATCHDOG.lock().ok()?.on_motion(
+        Instant::now(),
+        state::get_mask(),
+        threshold,
+        platform::held_buttons,
+    );
+    if stale == 0 {
+        return None;
+    }
+    state::unset_mask(stale);
+    for button in state::buttons_in_mask(stale) {
+        CORRECTIONS.fetch_add(1, Ordering::Relaxed);
+        log::warn!(
+            "{:?} mouse button was released without the hook noticing; releasing it",
+            button
+        );
+    }
+    Some(correct(event, stale))
+}
+
+/// Release `stale` buttons before `event`.
+pub(crate) fn correct(event: &Event, stale: u32) -> (Vec<Event>, Event) {
+    let (x, y) = event.mouse.as_ref().map_or((0.0, 0.0), |m| (m.x, m.y));
+    let mut mask = event.mask;
+    let mut releases = Vec::new();
+    for button in state::buttons_in_mask(stale) {
+        mask &= !state::button_to_mask(button.number());
+        let mut release = Event::mouse_released(button, x, y);
+        release.time = event.time;
+        release.mask = mask;
+        release.injected = event.injected;
+        releases.push(release);
+    }
+
+    let mut corrected = event.clone();
+    corrected.mask = mask;
+    let held = state::buttons_in_mask(mask);
+    if held.is_empty() {
+        corrected.event_type = EventType::MouseMoved;
+    }
+    if let Some(mouse) = &mut corrected.mouse {
+        mouse.button = held.first().copied();
+    }
+    (releases, corrected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Button;
+    use crate::state::{MASK_BUTTON1, MASK_BUTTON2, MASK_SHIFT};
+
+    const THRESHOLD: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_checks_only_after_threshold() {
+        let mut watchdog = StuckButtons::new();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let never = || -> Option<u32> { panic!("queried too early") };
+
+        assert_eq!(watchdog.on_motion(at(0), MASK_BUTTON1, THRESHOLD, never), 0);
+        assert_eq!(
+            watchdog.on_motion(at(59), MASK_BUTTON1, THRESHOLD, never),
+            0
+        );
+        // Still held: checked, and not again for another threshold
+        assert_eq!(
+            watchdog.on_motion(at(60), MASK_BUTTON1, THRESHOLD, || Some(MASK_BUTTON1)),
+            0
+        );
+        assert_eq!(
+            watchdog.on_motion(at(119), MASK_BUTTON1, THRESHOLD, never),
+            0
+        );
+        // The release was lost
+        assert_eq!(
+            watchdog.on_motion(at(120), MASK_BUTTON1 | MASK_SHIFT, THRESHOLD, || Some(0)),
+            MASK_BUTTON1
+        );
+    }
+
+    #[test]
+    fn test_button_changes_restart_the_clock() {
+        let mut watchdog = StuckButtons::new();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let never = || -> Option<u32> { panic!("queried too early") };
+
+        watchdog.on_motion(at(0), MASK_BUTTON1, THRESHOLD, never);
+        // A second button went down, then up again
+        watchdog.on_motion(at(50), MASK_BUTTON1 | MASK_BUTTON2, THRESHOLD, never);
+        watchdog.on_motion(at(70), MASK_BUTTON1, THRESHOLD, never);
+        assert_eq!(
+            watchdog.on_motion(at(129), MASK_BUTTON1, THRESHOLD, never),
+            0
+        );
+        // Moves without buttons reset too
+        watchdog.on_motion(at(129), 0, THRESHOLD, never);
+        assert_eq!(
+            watchdog.on_motion(at(130), MASK_BUTTON1, THRESHOLD, never),
+            0
+        );
+
+        // Only the stale button is released; unknown state corrects nothing
+        assert_eq!(
+            watchdog.on_motion(at(190), MASK_BUTTON1 | MASK_BUTTON2, THRESHOLD, never),
+            0
+        );
+        assert_eq!(
+            watchdog.on_motion(at(250), MASK_BUTTON1 | MASK_BUTTON2, THRESHOLD, || None),
+            0
+        );
+        assert_eq!(
+            watchdog.on_motion(at(310), MASK_BUTTON1 | MASK_BUTTON2, THRESHOLD, || {
+                Some(MASK_BUTTON2)
+            }),
+            MASK_BUTTON1
+        );
+    }
+
+    #[test]
+    fn test_correct_turns_drag_into_move() {
+        let mut drag = Event::mouse_dragged(5.0, 6.0);
+        drag.mask = MASK_BUTTON1 | MASK_SHIFT;
+
+        let (releases, corrected) = correct(&drag, MASK_BUTTON1);
+        assert_eq!(releases.len(), 1);
+        assert_eq!(releases[0].event_type, EventType::MouseReleased);
+        assert_eq!(
+            releases[0].mouse.as_ref().unwrap().button,
+            Some(Button::Left)
+        );
+        assert_eq!(releases[0].mask, MASK_SHIFT);
+        assert_eq!(corrected.event_type, EventType::MouseMoved);
+        assert_eq!(corrected.mask, MASK_SHIFT);
+        assert_eq!(corrected.mouse.as_ref().unwrap().button, None);
+
+        // Another button still held: still a drag
+        drag.mask = MASK_BUTTON1 | MASK_BUTTON2;
+        let (_, corrected) = correct(&drag, MASK_BUTTON1);
+        assert_eq!(corrected.event_type, EventType::MouseDragged);
+        assert_eq!(corrected.mask, MASK_BUTTON2);
+        assert_eq!(
+            corrected.mouse.as_ref().unwrap().button,
+            Some(Button::Right)
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! Cross-checks of what `monio::capabilities()` claims against what the
+//! backend does.
+//!
+//! Only claims that can be checked without a session or injecting input are
+//! exercised: a capability reported missing must fail, and display
+//! information must come from where it says.
+
+use monio::{Event, Hook, Key, capabilities};
+
+#[test]
+fn test_grab_claims_match_hook() {
+    let caps = capabilities();
+    let grab = Hook::new().grab_capabilities();
+    assert_eq!(caps.backend, grab.backend);
+    assert_eq!(caps.can_grab, grab.can_consume);
+    assert!(caps.can_grab || !caps.can_consume_selectively);
+}
+
+#[test]
+fn test_missing_absolute_position_fails() {
+    if !capabilities().absolute_mouse_position {
+        assert!(monio::mouse_position().is_err());
+    }
+}
+
+#[test]
+fn test_missing_unicode_simulation_fails() {
+    if !capabilities().can_simulate_unicode {
+        // No layout has a key for a snowman
+        assert!(monio::simulate(&Event::key_pressed(Key::Char('☃'), 0)).is_err());
+    }
+}
+
+#[test]
+fn test_display_source_is_honest() {
+    match capabilities().display_info_quality {
+        None => assert!(monio::displays().is_err()),
+        Some(source) => {
+            // Headless machines may have no displays to check
+            for display in monio::displays().unwrap_or_default() {
+                assert_eq!(display.source, source);
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Compatibility of the serialized formats.
+//!
+//! `fixtures/format-v*` hold files as each format version wrote them; they
+//! must keep loading. `golden/events.json` holds a canonical event of every
+//! type as serialized now, so representation changes show up as failures
+//! here. After a deliberate change, bump `FORMAT_VERSION`, add fixtures for
+//! the new version and regenerate the golden file:
+//!
+//! ```text
+//! MONIO_UPDATE_GOLDEN=1 cargo test --test compat --features recorder,serde
+//! ```
+
+#![cfg(all(feature = "recorder", feature = "serde"))]
+
+use monio::recorder::Recording;
+use monio::session::LogHeader;
+use monio::{Button, Event, EventType, FORMAT_VERSION, Key, ScrollDirection, ScrollPhase};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+fn path(relative: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(relative)
+}
+
+/// The canonical event of a type, with every field it can carry set.
+fn canonical(event_type: EventType) -> Event {
+    let mut event = match event_type {
+        EventType::HookEnabled => Event::hook_enabled(),
+        EventType::HookDisabled => Event::hook_disabled(),
+        EventType::GrabSuspended => Event::grab_suspended(),
+        EventType::GrabDegraded => Event::grab_degraded(),
+        EventType::GlobalSuspendChanged => Event::new(EventType::GlobalSuspendChanged),
+        EventType::KeyPressed => {
+            let mut event = Event::key_pressed(Key::ControlRight, 97);
+            event.keyboard.as_mut().unwrap().extended = true;
+            event
+        }
+        EventType::KeyReleased => Event::key_released(Key::KeyA, 30),
+        EventType::KeyTyped => {
+            let mut event = Event::key_typed(Key::Char('é'), 18, 'é');
+            event.keyboard.as_mut().unwrap().ime_active = Some(true);
+            event
+        }
+        EventType::ImeCompositionStarted => Event::ime_composition_started(),
+        EventType::ImeCompositionEnded => Event::ime_composition_ended(),
+        EventType::MousePressed => Event::mouse_pressed(Button::Left, 10.5, 20.0),
+        EventType::MouseReleased => Event::mouse_released(Button::Unknown(8), 10.5, 20.0),
+        EventType::MouseClicked => Event::mouse_clicked(Button::Right, 10.5, 20.0, 2),
+        EventType::MouseMoved => Event::mouse_moved(-1.0, 1080.25),
+        EventType::MouseDragged => Event::mouse_dragged(30.0, 40.0),
+        EventType::DragStarted => Event::drag_started(Button::Middle, 25.0, 40.0),
+        EventType::MouseWheel => {
+            let mut event = Event::mouse_wheel(5.0, 6.0, ScrollDirection::Up, 0.25);
+            let wheel = event.wheel.as_mut().unwrap();
+            wheel.pixel_delta = Some(12.0);
+            wheel.phase = Some(ScrollPhase::Changed);
+            event
+        }
+        EventType::TouchBegan | EventType::TouchMoved | EventType::TouchEnded => {
+            Event::touch(event_type, 3, 100.0, 200.0, Some(0.5))
+        }
+        EventType::DisplayChanged => Event::display_changed(1, 2, 1920.0, 300.0),
+    };
+    event.time = SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 123_000_000);
+    event.mask = 0x101;
+    event.injected = Some(false);
+    event.target_pid = Some(4242);
+    event
+}
+
+/// Every event type. `canonical` fails to compile when a type is added;
+/// add it here too.
+const ALL_TYPES: [EventType; 21] = [
+    EventType::HookEnabled,
+    EventType::HookDisabled,
+    EventType::GrabSuspended,
+    EventType::GrabDegraded,
+    EventType::GlobalSuspendChanged,
+    EventType::KeyPressed,
+    EventType::KeyReleased,
+    EventType::KeyTyped,
+    EventType::ImeCompositionStarted,
+    EventType::ImeCompositionEnded,
+    EventType::MousePressed,
+    EventType::MouseReleased,
+    EventType::MouseClicked,
+    EventType::MouseMoved,
+    EventType::MouseDragged,
+    EventType::DragStarted,
+    EventType::MouseWheel,
+    EventType::TouchBegan,
+    EventType::TouchMoved,
+    EventType::TouchEnded,
+    EventType::DisplayChanged,
+];
+
+#[test]
+fn events_match_golden_file() {
+    let events: Vec<Event> = ALL_TYPES.into_iter().map(canonical).collect();
+    let json = serde_json::to_string_pretty(&events).unwrap() + "\n";
+    let golden = path("golden/events.json");
+
+    if std::env::var_os("MONIO_UPDATE_GOLDEN").is_some() {
+        std::fs::write(&golden, &json).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&golden).unwrap();
+    assert!(
+        json == expected,
+        "serialized events differ from {}; if the change is deliberate, bump \
+         FORMAT_VERSION and regenerate it. Serialized now:\n{json}",
+        golden.display()
+    );
+
+    // And they read back the same
+    let loaded: Vec<Event> = serde_json::from_str(&expected).unwrap();
+    assert_eq!(loaded, events);
+}
+
+/// Recordings from before formats were versioned.
+#[test]
+fn format_v0_recording_loads() {
+    let recording = Recording::load(path("fixtures/format-v0/recording.json")).unwrap();
+    assert_eq!(recording.metadata.format_version, FORMAT_VERSION);
+    assert_eq!(recording.description.as_deref(), Some("Type hi"));
+    assert_eq!(recording.event_count(), 5);
+    assert_eq!(recording.duration(), Duration::from_millis(250));
+    assert_eq!(recording.events[0].wall_time, None);
+
+    let key = recording.events[0].event.keyboard.as_ref().unwrap();
+    assert_eq!(
+        (key.key, key.raw_code, key.extended),
+        (Key::KeyH, 35, false)
+    );
+    let wheel = recording.events[4].event.wheel.as_ref().unwrap();
+    assert_eq!(wheel.direction, ScrollDirection::Down);
+    assert_eq!((wheel.pixel_delta, wheel.phase), (None, None));
+}
+
+#[test]
+fn format_v1_recording_loads() {
+    let recording = Recording::load(path("fixtures/format-v1/recording.json")).unwrap();
+    assert_eq!(recording.metadata.format_version, 1);
+    assert_eq!(recording.metadata.backend, "evdev");
+    assert_eq!(recording.event_count(), 4);
+    assert!(recording.events.iter().all(|e| e.wall_time.is_some()));
+
+    let types: Vec<_> = recording
+        .events
+        .iter()
+        .map(|e| e.event.event_type)
+        .collect();
+    assert_eq!(
+        types,
+        [
+            EventType::MouseMoved,
+            EventType::MousePressed,
+            EventType::MouseReleased,
+            EventType::MouseWheel
+        ]
+    );
+    let wheel = recording.events[3].event.wheel.as_ref().unwrap();
+    assert_eq!(wheel.phase, Some(ScrollPhase::Began));
+}
+
+#[test]
+fn format_v1_event_log_loads() {
+    let log = std::fs::read_to_string(path("fixtures/format-v1/events.jsonl")).unwrap();
+    let mut lines = log.lines();
+    let header: LogHeader = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert_eq!(header.format_version, 1);
+
+    let events: Vec<Event> = lines.map(|l| serde_json::from_str(l).unwrap()).collect();
+    let types: Vec<_> = events.iter().map(|e| e.event_type).collect();
+    assert_eq!(
+        types,
+        [
+            EventType::HookEnabled,
+            EventType::KeyPressed,
+            EventType::KeyReleased,
+            EventType::HookDisabled
+        ]
+    );
+}
@@ -0,0 +1,97 @@
+//! Hooks started and stopped next to a running one leave its handler be, in
+//! a process of its own since it drives the global demo session.
+//!
+//! This only covers the demo source, which stands in for the platform
+//! backend while a session plays; `ActiveHook`, which the backends claim, is
+//! tested in `platform::active`. The demo source lets a grab run next to a
+//! listener, which the macOS, Windows and X11 backends refuse with
+//! `Error::AlreadyRunning`.
+
+use monio::channel::listen_channel;
+use monio::demo::{self, DemoScript};
+use monio::{Error, Event, EventType, Hook, Key};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Wait until `count` passes `from`.
+fn fires_past(count: &AtomicU64, from: u64) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if count.load(Ordering::SeqCst) > from {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    false
+}
+
+fn key_counter(count: &Arc<AtomicU64>) -> impl Fn(&Event) + Send + Sync + 'static {
+    let count = count.clone();
+    move |event: &Event| {
+        if event.event_type == EventType::KeyPressed {
+            count.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[test]
+fn original_handler_keeps_firing_as_other_hooks_come_and_go() {
+    // Taps keep coming, without waiting out the pause between rounds
+    let script = (0..500)
+        .fold(DemoScript::new(), |script, _| {
+            script.tap(Key::KeyA).pause(Duration::from_millis(5))
+        })
+        .looping(true);
+    let _session = demo::synthetic_session(script);
+
+    let original = Arc::new(AtomicU64::new(0));
+    let hook = Hook::new();
+    hook.run_async(key_counter(&original)).unwrap();
+    assert!(fires_past(&original, 0));
+
+    for round in 0..3 {
+        // Another listener, started and stopped
+        let other = Arc::new(AtomicU64::new(0));
+        let listener = Hook::new();
+        listener.run_async(key_counter(&other)).unwrap();
+        assert!(fires_past(&other, 0), "round {round}: listener never fired");
+        listener.stop().unwrap();
+        assert!(fires_past(&original, original.load(Ordering::SeqCst)));
+
+        // A grab, started and stopped; the demo source allows it next to
+        // the listener
+        let grabbed = Arc::new(AtomicU64::new(0));
+        let grab = Hook::new();
+        {
+            let count = key_counter(&grabbed);
+            grab.grab_async(move |event: &Event| {
+                count(event);
+                Some(event.clone())
+            })
+            .unwrap();
+        }
+        assert!(fires_past(&grabbed, 0), "round {round}: grab never fired");
+        grab.stop().unwrap();
+        assert!(matches!(grab.stop(), Err(Error::NotRunning)));
+        assert!(fires_past(&original, original.load(Ordering::SeqCst)));
+
+        // A channel, started and stopped
+        let (handle, rx) = listen_channel(64).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let received = std::iter::from_fn(|| {
+            let left = deadline.saturating_duration_since(Instant::now());
+            rx.recv_timeout(left).ok()
+        })
+        .any(|event| event.event_type == EventType::KeyPressed);
+        assert!(received, "round {round}: channel never received");
+        handle.stop().unwrap();
+        assert!(fires_past(&original, original.load(Ordering::SeqCst)));
+    }
+
+    assert!(hook.is_running());
+    hook.stop().unwrap();
+    let stopped = original.load(Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(original.load(Ordering::SeqCst), stopped);
+}
@@ -0,0 +1,105 @@
+//! Grab-and-reinject round trip.
+//!
+//! Consumes simulated keys with a grab hook, re-posts them with
+//! `Event::reinject` and checks that a listener sees the same keys. Only
+//! macOS and Windows can consume events and listen in one process; on Linux
+//! the evdev grab doesn't read simulated input and X11 can't consume.
+//!
+//! They need a real session with input access (Accessibility permission on
+//! macOS), so they are ignored by default:
+//!
+//! ```text
+//! cargo test --test reinject -- --ignored
+//! ```
+
+#![cfg(any(target_os = "macos", target_os = "windows"))]
+
+use monio::{Event, EventType, Hook, Key, key_press, key_release, key_tap};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Keys taken out by the grab hook: a plain key and an extended one.
+const TARGETS: [Key; 2] = [Key::F13, Key::ArrowRight];
+
+const SETTLE: Duration = Duration::from_millis(200);
+
+fn is_target(event: &Event) -> bool {
+    event.is_keyboard()
+        && event
+            .keyboard
+            .as_ref()
+            .is_some_and(|kb| TARGETS.contains(&kb.key))
+}
+
+/// What a listener should see again: type, key, raw code and extended flag,
+/// plus the modifiers on macOS, where they travel with the event.
+fn summary(event: &Event) -> (EventType, Key, u32, bool, u32) {
+    let kb = event.keyboard.as_ref().unwrap();
+    let modifiers = if cfg!(target_os = "macos") {
+        event.mask & monio::state::MASK_ALL_MODIFIERS
+    } else {
+        0
+    };
+    (
+        event.event_type,
+        kb.key,
+        kb.raw_code,
+        kb.extended,
+        modifiers,
+    )
+}
+
+#[test]
+#[ignore = "needs input access"]
+fn reinjected_keys_reach_listener() {
+    let consumed = Arc::new(Mutex::new(Vec::new()));
+    let grab = Hook::new();
+    {
+        let consumed = Arc::clone(&consumed);
+        grab.grab_async(move |event: &Event| {
+            if is_target(event) {
+                consumed.lock().unwrap().push(event.clone());
+                return None;
+            }
+            Some(event.clone())
+        })
+        .unwrap();
+    }
+    thread::sleep(SETTLE);
+
+    key_press(Key::ShiftLeft).unwrap();
+    for key in TARGETS {
+        key_tap(key).unwrap();
+    }
+    key_release(Key::ShiftLeft).unwrap();
+    thread::sleep(SETTLE);
+    grab.stop().unwrap();
+
+    let consumed = consumed.lock().unwrap().clone();
+    assert_eq!(consumed.len(), 2 * TARGETS.len(), "grab missed keys");
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let listen = Hook::new();
+    {
+        let seen = Arc::clone(&seen);
+        listen
+            .run_async(move |event: &Event| {
+                if is_target(event) {
+                    seen.lock().unwrap().push(event.clone());
+                }
+            })
+            .unwrap();
+    }
+    thread::sleep(SETTLE);
+
+    for event in &consumed {
+        event.reinject().unwrap();
+    }
+    thread::sleep(SETTLE);
+    listen.stop().unwrap();
+
+    let expected: Vec<_> = consumed.iter().map(summary).collect();
+    let seen: Vec<_> = seen.lock().unwrap().iter().map(summary).collect();
+    assert_eq!(seen, expected);
+}
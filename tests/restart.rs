@@ -0,0 +1,73 @@
+//! `Hook::restart` after a failed start, in a process of its own since it
+//! drives the global grab policy and demo session.
+
+use monio::demo::{self, DemoScript};
+use monio::policy::{self, Policy};
+use monio::{Error, Event, EventType, GrabOptions, Hook, Key};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+fn wait_for(seen: &Mutex<Vec<EventType>>, event_type: EventType) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if seen.lock().unwrap().contains(&event_type) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    false
+}
+
+#[test]
+fn restart_keeps_handler_and_subscriptions_after_failed_starts() {
+    let hook = Hook::new().grab_options(GrabOptions::default().require_consumption(true));
+    if !hook.grab_capabilities().can_consume {
+        // Nothing to switch between on backends that never consume
+        return;
+    }
+    assert!(matches!(hook.restart(), Err(Error::HookStartFailed(_))));
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let subscribed = Arc::new(Mutex::new(Vec::new()));
+    {
+        let subscribed = subscribed.clone();
+        hook.subscribe(move |event: &Event| subscribed.lock().unwrap().push(event.event_type))
+            .detach();
+    }
+    let handler = {
+        let seen = seen.clone();
+        move |event: &Event| {
+            seen.lock().unwrap().push(event.event_type);
+            Some(event.clone())
+        }
+    };
+
+    // Fails to start, twice
+    policy::set_global_grab_policy(Policy::Deny);
+    assert!(matches!(
+        hook.grab_async(handler),
+        Err(Error::NotSupported(_))
+    ));
+    assert!(matches!(hook.restart(), Err(Error::NotSupported(_))));
+    assert!(!hook.is_running());
+
+    // Then succeeds with the same handler
+    policy::set_global_grab_policy(Policy::Allow);
+    let _session = demo::synthetic_session(DemoScript::new().tap(Key::KeyA));
+    hook.restart().unwrap();
+    assert!(hook.is_running());
+    assert!(wait_for(&seen, EventType::KeyReleased));
+    assert!(wait_for(&subscribed, EventType::KeyReleased));
+
+    // And again while running
+    hook.restart().unwrap();
+    hook.stop().unwrap();
+    let enabled = seen
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|&&t| t == EventType::HookEnabled)
+        .count();
+    assert_eq!(enabled, 2);
+    policy::clear_global_grab_policy();
+}
@@ -0,0 +1,157 @@
+//! Start/stop soak tests.
+//!
+//! Runs many start/stop cycles of each hook API and checks that the process's
+//! open handles (fds on Unix) and resident memory stay bounded, so platform
+//! resources (event taps, hooks, X connections, device fds) aren't leaked.
+//!
+//! They need a real session with input access (Accessibility permission on
+//! macOS, the `input` group for evdev), so they are ignored by default:
+//!
+//! ```text
+//! cargo test --test soak -- --ignored
+//! cargo test --test soak --features recorder -- --ignored
+//! ```
+
+use monio::channel::listen_channel;
+use monio::{Event, Hook};
+use std::sync::Mutex;
+
+const CYCLES: usize = 1000;
+
+/// Cycles run before taking the baseline, so lazily created state (the
+/// virtual uinput device, thread-locals, allocator pools) isn't counted.
+const WARMUP: usize = 20;
+
+/// Handles that may come and go independently of the hooks (e.g. a logger).
+const HANDLE_SLACK: usize = 4;
+
+const MAX_RSS_GROWTH_KB: u64 = 8 * 1024;
+
+/// Hooks share global platform state, so cycles must not overlap.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy)]
+struct Usage {
+    handles: usize,
+    rss_kb: Option<u64>,
+}
+
+impl Usage {
+    fn now() -> Self {
+        Self {
+            handles: open_handles(),
+            rss_kb: rss_kb(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_handles() -> usize {
+    std::fs::read_dir("/proc/self/fd").unwrap().count()
+}
+
+#[cfg(target_os = "macos")]
+fn open_handles() -> usize {
+    std::fs::read_dir("/dev/fd").unwrap().count()
+}
+
+#[cfg(target_os = "windows")]
+fn open_handles() -> usize {
+    use windows::Win32::System::Threading::{GetCurrentProcess, GetProcessHandleCount};
+
+    let mut count = 0u32;
+    unsafe { GetProcessHandleCount(GetCurrentProcess(), &mut count) }.unwrap();
+    count as usize
+}
+
+#[cfg(target_os = "linux")]
+fn rss_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(target_os = "macos")]
+fn rss_kb() -> Option<u64> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=", "-p", &std::process::id().to_string()])
+        .output()
+        .ok()?;
+    String::from_utf8(output.stdout).ok()?.trim().parse().ok()
+}
+
+/// Not measured on Windows; the handle count covers the hooks.
+#[cfg(target_os = "windows")]
+fn rss_kb() -> Option<u64> {
+    None
+}
+
+fn soak(name: &str, mut cycle: impl FnMut()) {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+
+    for _ in 0..WARMUP {
+        cycle();
+    }
+    let before = Usage::now();
+    for _ in 0..CYCLES {
+        cycle();
+    }
+    let after = Usage::now();
+
+    assert!(
+        after.handles <= before.handles + HANDLE_SLACK,
+        "{name}: open handles grew from {} to {} over {CYCLES} cycles",
+        before.handles,
+        after.handles,
+    );
+    if let (Some(before_rss), Some(after_rss)) = (before.rss_kb, after.rss_kb) {
+        assert!(
+            after_rss <= before_rss + MAX_RSS_GROWTH_KB,
+            "{name}: RSS grew from {before_rss} KiB to {after_rss} KiB over {CYCLES} cycles",
+        );
+    }
+}
+
+#[test]
+#[ignore = "soak test: needs input access"]
+fn soak_listen() {
+    soak("listen", || {
+        let hook = Hook::new();
+        hook.run_async(|_: &Event| {}).unwrap();
+        hook.stop().unwrap();
+    });
+}
+
+#[test]
+#[ignore = "soak test: needs input access"]
+fn soak_grab() {
+    soak("grab", || {
+        let hook = Hook::new();
+        hook.grab_async(|event: &Event| Some(event.clone()))
+            .unwrap();
+        hook.stop().unwrap();
+    });
+}
+
+#[test]
+#[ignore = "soak test: needs input access"]
+fn soak_channel() {
+    soak("channel", || {
+        let (handle, rx) = listen_channel(64).unwrap();
+        handle.stop().unwrap();
+        drop(rx);
+    });
+}
+
+#[cfg(feature = "recorder")]
+#[test]
+#[ignore = "soak test: needs input access"]
+fn soak_recorder() {
+    use monio::recorder::EventRecorder;
+
+    soak("recorder", || {
+        let mut recorder = EventRecorder::new();
+        recorder.start_recording().unwrap();
+        recorder.stop_recording().unwrap();
+    });
+}
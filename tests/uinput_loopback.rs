@@ -0,0 +1,241 @@
+//! End-to-end tests of the evdev backend through a virtual uinput device.
+//!
+//! Each test creates a [`VirtualInput`], starts a listener, drives the device
+//! and checks the events reported. They need write access to `/dev/uinput`
+//! and read access to `/dev/input`, and skip themselves without it:
+//!
+//! ```text
+//! cargo test --test uinput_loopback --no-default-features --features evdev,test-util
+//! ```
+//!
+//! Input from real devices can end up in the stream too, so only the keys
+//! and buttons the tests press are looked at.
+
+#![cfg(all(
+    target_os = "linux",
+    feature = "evdev",
+    feature = "test-util",
+    not(feature = "x11")
+))]
+
+use monio::channel::{ChannelHookHandle, listen_channel};
+use monio::testsupport::VirtualInput;
+use monio::{Button, Event, EventType, Key, ScrollDirection};
+use std::sync::Mutex;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Hooks share global platform state, so tests must not overlap.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// A virtual device and a listener that has enumerated it.
+struct Loopback {
+    input: VirtualInput,
+    rx: Receiver<Event>,
+    _handle: ChannelHookHandle,
+}
+
+impl Loopback {
+    /// `None` when uinput isn't available here.
+    fn new() -> Option<Self> {
+        if !VirtualInput::is_available() {
+            eprintln!("skipping: /dev/uinput is not writable");
+            return None;
+        }
+        let input = VirtualInput::new().expect("Failed to create virtual device");
+        let (handle, rx) = listen_channel(1024).expect("Failed to start hook");
+        let enabled = rx.recv_timeout(TIMEOUT).expect("Hook didn't start in time");
+        assert_eq!(enabled.event_type, EventType::HookEnabled);
+        Some(Self {
+            input,
+            rx,
+            _handle: handle,
+        })
+    }
+
+    /// Collect events matching `keep` until there are `count` of them.
+    fn collect(&self, count: usize, keep: impl Fn(&Event) -> bool) -> Vec<Event> {
+        let deadline = Instant::now() + TIMEOUT;
+        let mut events = Vec::new();
+        while events.len() < count {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.rx.recv_timeout(left) {
+                Ok(event) if keep(&event) => events.push(event),
+                Ok(_) => {}
+                Err(_) => panic!("got {} of {count} events: {events:?}", events.len()),
+            }
+        }
+        events
+    }
+}
+
+fn key_summary(event: &Event) -> (EventType, Key) {
+    (event.event_type, event.keyboard.as_ref().unwrap().key)
+}
+
+#[test]
+fn keys_map_to_monio_keys() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut loopback) = Loopback::new() else {
+        return;
+    };
+
+    let keys = [
+        Key::KeyQ,
+        Key::Num7,
+        Key::F5,
+        Key::ArrowLeft,
+        Key::ShiftRight,
+    ];
+    for key in keys {
+        loopback.input.key_tap(key).unwrap();
+    }
+
+    let events = loopback.collect(keys.len() * 2, |e| {
+        matches!(e.event_type, EventType::KeyPressed | EventType::KeyReleased)
+            && e.keyboard.as_ref().is_some_and(|kb| keys.contains(&kb.key))
+    });
+    let expected: Vec<_> = keys
+        .iter()
+        .flat_map(|&key| [(EventType::KeyPressed, key), (EventType::KeyReleased, key)])
+        .collect();
+    assert_eq!(events.iter().map(key_summary).collect::<Vec<_>>(), expected);
+    assert!(events.iter().all(|e| e.injected == Some(false)));
+}
+
+#[test]
+fn motion_with_left_button_held_is_a_drag() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut loopback) = Loopback::new() else {
+        return;
+    };
+
+    loopback.input.move_by(5, 0).unwrap();
+    loopback.input.button_press(Button::Left).unwrap();
+    loopback.input.move_by(10, 0).unwrap();
+    loopback.input.move_by(0, 10).unwrap();
+    loopback.input.button_release(Button::Left).unwrap();
+    loopback.input.move_by(-5, 0).unwrap();
+
+    let events = loopback.collect(6, |e| e.mouse.is_some());
+    let types: Vec<_> = events.iter().map(|e| e.event_type).collect();
+    assert_eq!(
+        types,
+        [
+            EventType::MouseMoved,
+            EventType::MousePressed,
+            EventType::MouseDragged,
+            EventType::MouseDragged,
+            EventType::MouseReleased,
+            EventType::MouseMoved,
+        ]
+    );
+    assert_eq!(events[1].mouse.as_ref().unwrap().button, Some(Button::Left));
+
+    // Motion is relative: the drag moved 10 points each way from the press
+    let at = |i: usize| {
+        let mouse = events[i].mouse.as_ref().unwrap();
+        (mouse.x, mouse.y)
+    };
+    let (x, y) = at(1);
+    assert_eq!(at(2), (x + 10.0, y));
+    assert_eq!(at(3), (x + 10.0, y + 10.0));
+}
+
+#[test]
+fn wheel_direction_follows_the_axis_sign() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut loopback) = Loopback::new() else {
+        return;
+    };
+
+    let turns = [
+        (ScrollDirection::Up, 1),
+        (ScrollDirection::Down, 3),
+        (ScrollDirection::Left, 1),
+        (ScrollDirection::Right, 2),
+    ];
+    for (direction, clicks) in turns {
+        loopback.input.scroll(direction, clicks).unwrap();
+    }
+
+    let events = loopback.collect(turns.len(), |e| e.event_type == EventType::MouseWheel);
+    let wheels: Vec<_> = events
+        .iter()
+        .map(|e| {
+            let wheel = e.wheel.as_ref().unwrap();
+            (wheel.direction, wheel.delta as u32)
+        })
+        .collect();
+    assert_eq!(wheels, turns);
+}
+
+/// The failsafe ungrabs the devices while the grab handler is stuck.
+#[cfg(feature = "failsafe")]
+#[test]
+fn failsafe_releases_a_stuck_grab() {
+    use evdev::raw_stream::RawDevice;
+    use monio::{Hook, failsafe};
+    use std::sync::mpsc;
+
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    if !VirtualInput::is_available() {
+        eprintln!("skipping: /dev/uinput is not writable");
+        return;
+    }
+    let mut input = VirtualInput::new().expect("Failed to create virtual device");
+    let chord = [Key::ControlRight, Key::ShiftRight, Key::F12];
+    let failsafe = failsafe::arm(&chord).expect("Failed to arm the failsafe");
+    let triggers = failsafe::trigger_count();
+
+    // The handler gets stuck on F13 until told to go on
+    let (stuck_tx, stuck) = mpsc::channel();
+    let (go_on, wait) = mpsc::channel::<()>();
+    let wait = Mutex::new(wait);
+    let hook = Hook::new();
+    hook.grab_async(move |event: &Event| {
+        if event.event_type == EventType::KeyPressed
+            && event.keyboard.as_ref().is_some_and(|kb| kb.key == Key::F13)
+        {
+            let _ = stuck_tx.send(());
+            let _ = wait.lock().unwrap().recv();
+        }
+        Some(event.clone())
+    })
+    .expect("Failed to start grab hook");
+    std::thread::sleep(Duration::from_millis(200));
+
+    let grab_free = |input: &VirtualInput| {
+        let mut device = RawDevice::open(input.path()).unwrap();
+        let free = device.grab().is_ok();
+        if free {
+            device.ungrab().unwrap();
+        }
+        free
+    };
+    input.key_tap(Key::F13).unwrap();
+    stuck.recv_timeout(TIMEOUT).expect("Handler didn't see F13");
+    assert!(!grab_free(&input), "device should be grabbed by the hook");
+
+    for key in chord {
+        input.key_press(key).unwrap();
+    }
+    let deadline = Instant::now() + TIMEOUT;
+    while failsafe::trigger_count() == triggers {
+        assert!(Instant::now() < deadline, "failsafe didn't fire");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    for key in chord.into_iter().rev() {
+        input.key_release(key).unwrap();
+    }
+    assert!(
+        grab_free(&input),
+        "failsafe should have ungrabbed the device"
+    );
+
+    go_on.send(()).unwrap();
+    hook.stop().unwrap();
+    failsafe.disarm();
+}